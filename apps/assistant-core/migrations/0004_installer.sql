@@ -0,0 +1,22 @@
+-- 0004: Persisted installer plans (previously held in-process only)
+
+CREATE TABLE IF NOT EXISTS InstallPlan (
+  id TEXT PRIMARY KEY NOT NULL,
+  manager TEXT NOT NULL,
+  pkg TEXT NOT NULL,
+  commands_json TEXT NOT NULL,
+  rollback_json TEXT NULL,
+  status TEXT NOT NULL DEFAULT 'planned',
+  dry_run_json TEXT NULL,
+  result_json TEXT NULL,
+  created_at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')),
+  updated_at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_installplan_status ON InstallPlan(status, updated_at DESC);
+
+CREATE TRIGGER IF NOT EXISTS trg_installplan_updated_at
+AFTER UPDATE ON InstallPlan FOR EACH ROW
+BEGIN
+  UPDATE InstallPlan SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ','now') WHERE id = OLD.id;
+END;
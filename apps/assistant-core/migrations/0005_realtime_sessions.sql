@@ -0,0 +1,18 @@
+-- 0005: Realtime call sessions. Turns, tool calls and errors are Event rows on the session's Task.
+
+CREATE TABLE IF NOT EXISTS RealtimeSession (
+  id TEXT PRIMARY KEY NOT NULL,
+  task_id INTEGER NOT NULL,
+  model TEXT NULL,
+  endpoint TEXT NULL,
+  transport TEXT NULL,
+  status TEXT NOT NULL DEFAULT 'active',
+  error TEXT NULL,
+  recording TEXT NULL,
+  started_at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')),
+  ended_at DATETIME NULL,
+  FOREIGN KEY(task_id) REFERENCES Task(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_realtimesession_started ON RealtimeSession(started_at DESC);
+CREATE INDEX IF NOT EXISTS idx_event_task ON Event(task_id, id);
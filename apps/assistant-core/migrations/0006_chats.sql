@@ -0,0 +1,41 @@
+-- 0006: Chat sessions. Messages form a tree through parent_id; editing or
+-- regenerating adds a sibling instead of overwriting. head_id is the leaf of
+-- the branch the session shows.
+
+CREATE TABLE IF NOT EXISTS ChatSession (
+  id TEXT PRIMARY KEY NOT NULL,
+  title TEXT NULL,
+  head_id TEXT NULL,
+  forked_from TEXT NULL,
+  created_at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')),
+  updated_at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now'))
+);
+
+CREATE TABLE IF NOT EXISTS ChatMessage (
+  seq INTEGER PRIMARY KEY AUTOINCREMENT,
+  id TEXT NOT NULL UNIQUE,
+  session_id TEXT NOT NULL,
+  parent_id TEXT NULL,
+  role TEXT NOT NULL,
+  content TEXT NOT NULL DEFAULT '',
+  at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')),
+  FOREIGN KEY(session_id) REFERENCES ChatSession(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_chatsession_updated ON ChatSession(updated_at DESC);
+CREATE INDEX IF NOT EXISTS idx_chatmessage_session ON ChatMessage(session_id, seq);
+
+-- FTS5 external-content index over message text
+CREATE VIRTUAL TABLE IF NOT EXISTS chat_fts
+USING fts5(content, content='ChatMessage', content_rowid='seq', tokenize='porter');
+
+CREATE TRIGGER IF NOT EXISTS chatmessage_ai AFTER INSERT ON ChatMessage BEGIN
+  INSERT INTO chat_fts(rowid, content) VALUES (new.seq, new.content);
+END;
+CREATE TRIGGER IF NOT EXISTS chatmessage_ad AFTER DELETE ON ChatMessage BEGIN
+  INSERT INTO chat_fts(chat_fts, rowid, content) VALUES('delete', old.seq, old.content);
+END;
+CREATE TRIGGER IF NOT EXISTS chatmessage_au AFTER UPDATE OF content ON ChatMessage BEGIN
+  INSERT INTO chat_fts(chat_fts, rowid, content) VALUES('delete', old.seq, old.content);
+  INSERT INTO chat_fts(rowid, content) VALUES (new.seq, new.content);
+END;
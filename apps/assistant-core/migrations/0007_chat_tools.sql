@@ -0,0 +1,10 @@
+-- 0007: Tool-call turns in chat sessions. An assistant message that called
+-- tools carries the calls as JSON ([{id, name, arguments}]); each result is a
+-- 'tool' message answering one call. Large results are capped in content and
+-- saved whole as an artifact.
+
+ALTER TABLE ChatMessage ADD COLUMN tool_calls TEXT NULL;
+ALTER TABLE ChatMessage ADD COLUMN tool_call_id TEXT NULL;
+ALTER TABLE ChatMessage ADD COLUMN tool_name TEXT NULL;
+ALTER TABLE ChatMessage ADD COLUMN approval_id TEXT NULL;
+ALTER TABLE ChatMessage ADD COLUMN artifact_id INTEGER NULL;
@@ -0,0 +1,6 @@
+-- 0008: Rolling summary of the turns that no longer fit a chat's context
+-- window. summary_upto is the last message folded in; when it is not on the
+-- active branch the summary is rebuilt.
+
+ALTER TABLE ChatSession ADD COLUMN summary TEXT NULL;
+ALTER TABLE ChatSession ADD COLUMN summary_upto TEXT NULL;
@@ -23,22 +23,6 @@ use serde::Deserialize;
 use sqlx::Row;
 use uuid::Uuid;
 use crate::agents::AgentStatus;
-use once_cell::sync::Lazy;
-use std::collections::HashMap;
-use std::sync::Arc;
-
-// Serialize chat session appends to avoid read-modify-write races
-static CHAT_APPEND_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
-// Per-session append locks to avoid cross-session serialization
-static CHAT_SESSION_LOCKS: Lazy<tokio::sync::Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>> = Lazy::new(|| tokio::sync::Mutex::new(HashMap::new()));
-
-async fn session_lock(id: &str) -> Arc<tokio::sync::Mutex<()>> {
-    let mut map = CHAT_SESSION_LOCKS.lock().await;
-    if let Some(l) = map.get(id) { return l.clone(); }
-    let l = Arc::new(tokio::sync::Mutex::new(()));
-    map.insert(id.to_string(), l.clone());
-    l
-}
 
 #[derive(Serialize)]
 struct Health {
@@ -65,30 +49,44 @@ pub fn build_router(state: SharedState) -> Router {
         .route("/metrics", get(metrics))
         .route("/control", get(ws_upgrade))
         .route("/api/voice/test", get(voice_test))
+        .route("/api/stt/stream", get(stt_stream))
+        .route("/api/tts/speak", axum::routing::post(tts_speak))
+        .route("/api/tts/stop", axum::routing::post(tts_stop))
+        .route("/api/tts/status", get(tts_status))
+        .route("/api/notes", axum::routing::post(notes_create))
+        .route("/api/notes/dictate", axum::routing::post(notes_dictate))
+        .route("/api/notes/recall", get(notes_recall))
         // audio diagnostics
         .route("/api/audio/devices", get(audio_devices))
-        .route("/api/audio/diagnose", axum::routing::post(|Json(req): Json<AudioDiagReq>| async move {
+        .route("/api/audio/diagnose", axum::routing::post(|State(state): State<SharedState>, Json(req): Json<AudioDiagReq>| async move {
             #[cfg(feature = "realtime-audio")]
             {
-                let v = crate::realtime_audio::vad_capture_diagnostic(req.seconds, req.in_sr, req.chunk_ms, req.sensitivity, req.min_speech_ms, req.transcribe.unwrap_or(false)).await;
+                let stt = req.transcribe.unwrap_or(false).then(|| stt_options(&state));
+                let v = crate::realtime_audio::vad_capture_diagnostic(req.seconds, req.in_sr, req.chunk_ms, req.sensitivity, req.min_speech_ms, stt.as_ref()).await;
                 return Json(v).into_response();
             }
             #[cfg(not(feature = "realtime-audio"))]
             {
+                let _ = &state;
                 return (StatusCode::NOT_IMPLEMENTED, Json(serde_json::json!({"error":"audio disabled"}))).into_response();
             }
         }))
         .route("/api/audio/beep", axum::routing::post(audio_beep))
         .route("/api/chat/complete", axum::routing::post(chat_complete))
+        .route("/api/prompt", get(prompt_preview))
+        .route("/api/prompt/reload", axum::routing::post(prompt_reload))
         .route("/api/chat/stream", axum::routing::post(chat_stream))
         // realtime voice bridge
         .route("/api/realtime/start", axum::routing::post(realtime_start))
         .route("/api/realtime/stop", axum::routing::post(|state: axum::extract::State<SharedState>| async move { realtime_stop(state).await }))
         .route("/api/realtime/status", get(realtime_status))
+        .route("/api/realtime/sessions", get(realtime_sessions))
+        .route("/api/realtime/sessions/:id", get(realtime_session_get))
         // wake sentinel
         .route("/api/wake/status", get(wake_status))
         .route("/api/wake/enable", axum::routing::post(wake_enable))
         .route("/api/wake/disable", axum::routing::post(wake_disable))
+        .route("/api/wake/enroll", axum::routing::post(wake_enroll))
         // codex adapter endpoints
         .route("/api/codex/new", axum::routing::post(codex_new))
         .route("/api/codex/continue", axum::routing::post(codex_continue))
@@ -101,10 +99,12 @@ pub fn build_router(state: SharedState) -> Router {
         .route("/api/system_map", get(system_map))
         .route("/api/system_map/digest", get(system_map_digest))
         .route("/api/system_map/refresh", axum::routing::post(system_map_refresh))
+        .route("/api/system_map/diff", get(system_map_diff))
         .route("/api/context/pack", axum::routing::post(context_pack))
         .route("/api/context/expand", axum::routing::post(context_expand))
         // memory APIs
         .route("/api/memory/search", get(memory_search))
+        .route("/api/search", get(web_search))
         .route("/api/memory/atoms/:id", get(get_atom))
         .route("/api/memory/atoms/:id/pin", axum::routing::post(pin_atom))
         .route("/api/memory/atoms/:id/unpin", axum::routing::post(unpin_atom))
@@ -130,6 +130,11 @@ pub fn build_router(state: SharedState) -> Router {
         .route("/api/chat/sessions/latest", get(chat_sessions_latest))
         .route("/api/chat/sessions/:id", get(chat_session_get).delete(chat_session_delete))
         .route("/api/chat/sessions/:id/append", axum::routing::post(chat_session_append))
+        .route("/api/chat/sessions/:id/messages/:mid/edit", axum::routing::post(chat_message_edit))
+        .route("/api/chat/sessions/:id/regenerate", axum::routing::post(chat_session_regenerate))
+        .route("/api/chat/sessions/:id/fork", axum::routing::post(chat_session_fork))
+        .route("/api/chat/sessions/:id/head", axum::routing::post(chat_session_head))
+        .route("/api/chat/search", get(chat_search))
         // tools proxy
         .route("/api/tools/:server/:tool", axum::routing::post(call_tool))
         // policy and approvals
@@ -163,6 +168,196 @@ async fn ws_upgrade(ws: WebSocketUpgrade, State(_state): State<SharedState>) ->
     ws.on_upgrade(handle_ws)
 }
 
+fn stt_options(state: &SharedState) -> crate::stt::SttOptions {
+    crate::stt::SttOptions::from_config(state.config.read().voice.as_ref().and_then(|v| v.stt.as_ref()))
+}
+
+#[derive(Deserialize)]
+struct SttStreamQuery { #[serde(default)] sample_rate: Option<u32> }
+
+/// Streaming STT for clients without their own recognizer (TUI push-to-talk):
+/// binary PCM16LE frames in, `{"type":"end"}` to finish; partial/final events out.
+async fn stt_stream(ws: WebSocketUpgrade, State(state): State<SharedState>, axum::extract::Query(q): axum::extract::Query<SttStreamQuery>) -> Response {
+    crate::metrics::inc_api_request("/api/stt/stream");
+    let opts = stt_options(&state);
+    ws.on_upgrade(move |socket| stt_stream_session(socket, opts, q.sample_rate.unwrap_or(16_000)))
+}
+
+async fn stt_stream_session(mut socket: WebSocket, opts: crate::stt::SttOptions, sample_rate: u32) {
+    use crate::stt::SttEvent;
+    let (mut audio, mut events) = match crate::stt::open_stream(&opts, sample_rate).await {
+        Ok(s) => s.split(),
+        Err(e) => {
+            let ev = SttEvent::Error { message: e.to_string() };
+            let _ = socket.send(Message::Text(serde_json::to_string(&ev).unwrap_or_default())).await;
+            return;
+        }
+    };
+    loop {
+        tokio::select! {
+            msg = socket.recv() => match msg {
+                Some(Ok(Message::Binary(b))) => {
+                    let pcm: Vec<i16> = b.chunks_exact(2).map(|c| i16::from_le_bytes([c[0], c[1]])).collect();
+                    if let Some(tx) = audio.as_ref() { let _ = tx.send(pcm); }
+                }
+                Some(Ok(Message::Text(t))) => {
+                    if serde_json::from_str::<serde_json::Value>(&t).ok().and_then(|v| v.get("type").and_then(|x| x.as_str()).map(|s| s == "end")).unwrap_or(false) {
+                        audio = None;
+                    }
+                }
+                Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                Some(Ok(_)) => {}
+            },
+            ev = events.recv() => match ev {
+                Some(ev) => {
+                    let done = !matches!(ev, SttEvent::Partial { .. });
+                    if socket.send(Message::Text(serde_json::to_string(&ev).unwrap_or_default())).await.is_err() || done { break; }
+                }
+                None => break,
+            },
+        }
+    }
+    let _ = socket.close().await;
+}
+
+#[derive(Deserialize)]
+struct TtsSpeakReq {
+    text: String,
+    #[serde(default)]
+    priority: crate::tts::Priority,
+    /// false returns the audio as WAV instead of playing it.
+    #[serde(default)]
+    play: Option<bool>,
+}
+
+async fn tts_speak(State(state): State<SharedState>, Json(req): Json<TtsSpeakReq>) -> Response {
+    crate::metrics::inc_api_request("/api/tts/speak");
+    let tts = &state.handles.tts;
+    let Some(engine) = tts.engine.clone() else {
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(ApiError { message: "tts not configured ([voice] tts.engine)".into() })).into_response();
+    };
+    if req.play.unwrap_or(true) {
+        if let Some(sentences) = tts.speak(&req.text, req.priority) {
+            return Json(serde_json::json!({"engine": engine.name(), "sentences": sentences})).into_response();
+        }
+        if req.play == Some(true) {
+            return (StatusCode::SERVICE_UNAVAILABLE, Json(ApiError { message: "no audio output (build with realtime-audio)".into() })).into_response();
+        }
+    }
+    match engine.synthesize(&req.text).await {
+        Ok(speech) => (
+            [(CONTENT_TYPE, HeaderValue::from_static("audio/wav"))],
+            crate::stt::encode_wav_pcm16(&speech.pcm, speech.sample_rate),
+        ).into_response(),
+        Err(e) => (StatusCode::BAD_GATEWAY, Json(ApiError { message: format!("tts: {}", e) })).into_response(),
+    }
+}
+
+async fn tts_stop(State(state): State<SharedState>) -> StatusCode {
+    if let Some(q) = state.handles.tts.queue.as_ref() { q.clear(); }
+    StatusCode::OK
+}
+
+async fn tts_status(State(state): State<SharedState>) -> impl IntoResponse {
+    let tts = &state.handles.tts;
+    Json(serde_json::json!({
+        "engine": tts.engine.as_ref().map(|e| e.name()),
+        "playback": tts.queue.as_ref().map(|q| q.is_open()).unwrap_or(false),
+        "speak_chat": tts.speak_chat,
+        "queue": tts.queue.as_ref().map(|q| q.status()),
+    }))
+}
+
+#[derive(Deserialize)]
+struct NoteReq {
+    #[serde(default)]
+    text: Option<String>,
+    /// PCM16 mono; transcribed when `text` is absent and saved as the note's artifact.
+    #[serde(default)]
+    samples: Option<Vec<i16>>,
+    #[serde(default)]
+    sample_rate: Option<u32>,
+    #[serde(default)]
+    task_id: Option<i64>,
+    /// Task title; created if missing. Defaults to `[voice] notes.task`.
+    #[serde(default)]
+    task: Option<String>,
+}
+
+async fn notes_create(State(state): State<SharedState>, Json(req): Json<NoteReq>) -> Response {
+    crate::metrics::inc_api_request("/api/notes");
+    if state.handles.memory.is_none() {
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(ApiError { message: "memory not initialized".into() })).into_response();
+    }
+    let pcm = req.samples.unwrap_or_default();
+    if pcm.is_empty() && req.text.as_deref().map(str::trim).unwrap_or("").is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(ApiError { message: "provide text or samples".into() })).into_response();
+    }
+    let note = crate::notes::NewNote { text: req.text, pcm, sample_rate: req.sample_rate.unwrap_or(16_000), task_id: req.task_id, task: req.task };
+    match state.handles.notes.file(note).await {
+        Ok(rec) => Json(rec).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiError { message: format!("note: {}", e) })).into_response(),
+    }
+}
+
+/// Record from core's microphone until silence, then file the note.
+async fn notes_dictate(State(state): State<SharedState>, Json(req): Json<NoteReq>) -> Response {
+    crate::metrics::inc_api_request("/api/notes/dictate");
+    if state.handles.memory.is_none() {
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(ApiError { message: "memory not initialized".into() })).into_response();
+    }
+    #[cfg(feature = "realtime-audio")]
+    {
+        let notes = &state.handles.notes;
+        // The sentinel holds the mic while listening
+        let (listening, _) = state.handles.wake.status();
+        if listening { state.handles.wake.stop_task().await; }
+        let recorded = crate::notes::record(notes.dictation_gate(16_000)).await;
+        if listening { state.handles.wake.start_task(state.handles.realtime.clone()).await; }
+        let (pcm, end) = match recorded {
+            Ok(r) => r,
+            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiError { message: format!("recording failed: {}", e) })).into_response(),
+        };
+        if pcm.is_empty() {
+            return (StatusCode::UNPROCESSABLE_ENTITY, Json(ApiError { message: "no speech heard".into() })).into_response();
+        }
+        let note = crate::notes::NewNote { text: req.text, pcm, sample_rate: 16_000, task_id: req.task_id, task: req.task };
+        match notes.file(note).await {
+            Ok(rec) => {
+                notes.say(&format!("Noted under {}.", rec.task));
+                Json(serde_json::json!({"note": rec, "ended_by": end})).into_response()
+            }
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiError { message: format!("note: {}", e) })).into_response(),
+        }
+    }
+    #[cfg(not(feature = "realtime-audio"))]
+    {
+        let _ = req;
+        (StatusCode::NOT_IMPLEMENTED, Json(ApiError { message: "dictation needs a realtime-audio build; POST /api/notes with samples instead".into() })).into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct RecallQ { q: String, task_id: Option<i64>, k: Option<usize>, speak: Option<bool> }
+
+/// "What did I note about X": search notes and read the hits back.
+async fn notes_recall(State(state): State<SharedState>, axum::extract::Query(RecallQ { q, task_id, k, speak }): axum::extract::Query<RecallQ>) -> Response {
+    crate::metrics::inc_api_request("/api/notes/recall");
+    if state.handles.memory.is_none() {
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(ApiError { message: "memory not initialized".into() })).into_response();
+    }
+    let notes = &state.handles.notes;
+    match notes.recall(&q, task_id, k.unwrap_or(5).clamp(1, 20)).await {
+        Ok(recall) => {
+            let spoken = speak.unwrap_or(true) && notes.say(&recall.spoken);
+            let mut v = serde_json::to_value(&recall).unwrap_or_default();
+            v["spoken_aloud"] = serde_json::json!(spoken);
+            Json(v).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiError { message: e.to_string() })).into_response(),
+    }
+}
+
 async fn handle_ws(mut socket: WebSocket) {
     // Echo server
     while let Some(Ok(msg)) = socket.recv().await {
@@ -518,18 +713,58 @@ async fn system_map(State(state): State<SharedState>) -> impl IntoResponse {
 
 async fn system_map_digest(State(state): State<SharedState>) -> impl IntoResponse {
     #[derive(Serialize)]
-    struct Digest { digest: String }
-    let d = state.handles.system_map.get_digest();
-    Json(Digest { digest: d })
+    struct Digest { digest: String, scanned_at: Option<chrono::DateTime<chrono::Utc>>, stale: bool }
+    let sm = &state.handles.system_map;
+    Json(Digest { digest: sm.get_digest(), scanned_at: sm.get_map().map(|m| m.scanned_at), stale: sm.is_stale() })
+}
+
+#[derive(serde::Deserialize)]
+struct RefreshQ { full: Option<bool> }
+
+#[derive(serde::Deserialize)]
+struct PromptQ { mode: Option<String> }
+
+/// The system prompt as rendered now, with per-section token estimates and skip reasons.
+async fn prompt_preview(State(state): State<SharedState>, axum::extract::Query(q): axum::extract::Query<PromptQ>) -> impl IntoResponse {
+    crate::metrics::inc_api_request("/api/prompt");
+    let mode = match q.mode.as_deref().map(crate::prompt::Mode::parse) {
+        None => crate::prompt::Mode::Text,
+        Some(Some(m)) => m,
+        Some(None) => return (StatusCode::BAD_REQUEST, Json(ApiError { message: "mode must be text or voice".into() })).into_response(),
+    };
+    Json(state.handles.prompts.render(mode)).into_response()
 }
 
-async fn system_map_refresh(State(state): State<SharedState>) -> impl IntoResponse {
-    match state.handles.system_map.refresh().await {
+/// Drop cached prompt sections and the ROM catalog so the next render reads them again.
+async fn prompt_reload(State(state): State<SharedState>) -> impl IntoResponse {
+    state.handles.prompts.reload();
+    StatusCode::OK
+}
+
+async fn system_map_refresh(State(state): State<SharedState>, axum::extract::Query(q): axum::extract::Query<RefreshQ>) -> impl IntoResponse {
+    let sm = &state.handles.system_map;
+    let res = if q.full.unwrap_or(false) { sm.refresh_full().await } else { sm.refresh().await };
+    match res {
         Ok(()) => StatusCode::OK.into_response(),
         Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiError { message: e.to_string() })).into_response(),
     }
 }
 
+#[derive(serde::Deserialize)]
+struct MapDiffQ { since: Option<String> }
+
+async fn system_map_diff(State(state): State<SharedState>, axum::extract::Query(q): axum::extract::Query<MapDiffQ>) -> impl IntoResponse {
+    crate::metrics::inc_api_request("/api/system_map/diff");
+    let now = chrono::Utc::now();
+    let raw = q.since.unwrap_or_else(|| "7d".into());
+    let Some(since) = crate::system_map::diff::parse_since(&raw, now) else {
+        return (StatusCode::BAD_REQUEST, Json(ApiError { message: "since must be RFC 3339 or a span like 30m, 24h, 7d".into() })).into_response();
+    };
+    let diffs = state.handles.system_map.diff_since(since).await;
+    let count: usize = diffs.iter().map(|d| d.changes.len()).sum();
+    Json(serde_json::json!({ "since": since, "count": count, "diffs": diffs })).into_response()
+}
+
 #[derive(serde::Deserialize)]
 struct PackReq { task_id: Option<i64>, token_budget: Option<usize>, k_cards: Option<i64>, expansions: Option<Vec<String>> }
 
@@ -539,7 +774,7 @@ async fn context_pack(State(state): State<SharedState>, Json(req): Json<PackReq>
     let k_cards = req.k_cards.unwrap_or(12);
 
     #[derive(serde::Serialize)]
-    struct EmptyPack { system_digest: String, task_digest: Option<String>, cards: Vec<serde_json::Value>, expansions: Vec<String>, dropped: serde_json::Value }
+    struct EmptyPack { system_digest: String, task_digest: Option<String>, recent_calls: Option<String>, cards: Vec<serde_json::Value>, expansions: Vec<String>, dropped: serde_json::Value }
 
     if let Some(mem) = state.handles.memory.as_ref() {
         let cards_raw = mem
@@ -564,10 +799,11 @@ async fn context_pack(State(state): State<SharedState>, Json(req): Json<PackReq>
             .collect();
         // Prefer pinned/important first (already sorted by SQL), but keep a stable order
         let expansions = req.expansions.unwrap_or_default();
-        let pack = crate::memory::context_pack::build_pack(&d, None, cards, budget, expansions);
+        let calls = crate::realtime_sessions::recent_context(&mem.store, crate::realtime::RECENT_CALLS).await;
+        let pack = crate::memory::context_pack::build_pack(&d, None, calls.as_deref(), cards, budget, expansions);
         return Json(pack).into_response();
     }
-    Json(EmptyPack { system_digest: d, task_digest: None, cards: vec![], expansions: vec![], dropped: serde_json::json!({"cards":0,"expansions":0}) }).into_response()
+    Json(EmptyPack { system_digest: d, task_digest: None, recent_calls: None, cards: vec![], expansions: vec![], dropped: serde_json::json!({"cards":0,"expansions":0}) }).into_response()
 }
 
 fn estimate_tokens_len(text: &str) -> usize { (text.len() / 4).max(1) }
@@ -578,7 +814,7 @@ struct ToolParams { params: serde_json::Value }
 async fn call_tool(
     State(state): State<SharedState>,
     AxPath((server, tool)): AxPath<(String, String)>,
-    Json(ToolParams { params }): Json<ToolParams>,
+    Json(ToolParams { mut params }): Json<ToolParams>,
 ) -> impl IntoResponse {
     // Policy/approval gating for risky tools
     if (server.as_str(), tool.as_str()) == ("patch", "apply") {
@@ -600,23 +836,54 @@ async fn call_tool(
             if !ok { return (StatusCode::FORBIDDEN, Json(ApiError { message: "invalid approval token".into() })).into_response(); }
         }
     }
-    if (server.as_str(), tool.as_str()) == ("git", "commit") {
-        let path = params.get("path").and_then(|v| v.as_str()).unwrap_or(".");
-        let msg = params.get("message").and_then(|v| v.as_str()).unwrap_or("");
-        let action = ProposedAction { command: "git commit".into(), writes: true, paths: vec![path.to_string()], intent: Some(msg.into()) };
+    // Reject malformed calls before asking anyone to approve them (the
+    // approval fields are ours, not the tool's)
+    let mut call_params = params.clone();
+    if let Some(obj) = call_params.as_object_mut() {
+        obj.remove("approval_id");
+        obj.remove("approve_token");
+    }
+    if let Err(e) = state.handles.tools.validate(&server, &tool, &call_params) {
+        return (StatusCode::BAD_REQUEST, Json(ApiError { message: e.to_string() })).into_response();
+    }
+    // Every other call is judged by the policy view the tools manager builds
+    // from the tool's risk flags: writes and require_approval keywords are
+    // held. The installer gates its own apply (planning only records a plan);
+    // dry runs change nothing, but only tools that honour the flag skip the
+    // gate with it
+    let gated_elsewhere = (server.as_str(), tool.as_str()) == ("patch", "apply") || server == "installer";
+    if !gated_elsewhere && !state.handles.tools.is_dry_run(&server, &tool, &call_params) {
+        let action = state.handles.tools.proposed_action(&server, &tool, &call_params);
         let decision = state.handles.policy.evaluate(&action);
         if !matches!(decision.kind, crate::gatekeeper::PolicyDecisionKind::Allow) {
             let approval_id = params.get("approval_id").and_then(|v| v.as_str());
             let approve_token = params.get("approve_token").and_then(|v| v.as_str());
             if approval_id.is_none() || approve_token.is_none() {
-                let title = format!("Git commit requires approval ({})", path);
-                let prompt = crate::app::EphemeralApproval { id: uuid::Uuid::new_v4().to_string(), title, action, details: serde_json::json!({"path": path, "message": msg, "reasons": decision.reasons}) };
+                let (title, details) = match (server.as_str(), tool.as_str()) {
+                    ("fs", _) => (format!("{} requires approval", action.command), serde_json::json!({"paths": action.paths, "reasons": decision.reasons})),
+                    ("git", _) => {
+                        let path = action.paths.first().cloned().unwrap_or_default();
+                        (format!("{} requires approval ({})", action.command, path), serde_json::json!({"path": path, "message": action.intent, "reasons": decision.reasons}))
+                    }
+                    ("proc", "kill") => {
+                        let pid = params.get("pid").cloned().unwrap_or_default();
+                        let signal = params.get("signal").and_then(|v| v.as_str()).unwrap_or("TERM");
+                        let process = mcp_proc::list(&serde_json::json!({"pids": [pid], "sample_ms": 0})).await.ok().and_then(|v| v["processes"].get(0).cloned());
+                        (format!("Signal {} to process {} requires approval", signal, pid), serde_json::json!({"pid": pid, "signal": signal, "process": process, "reasons": decision.reasons}))
+                    }
+                    _ => (format!("{} requires approval", action.command), serde_json::json!({"server": server, "tool": tool, "arguments": call_params, "paths": action.paths, "reasons": decision.reasons})),
+                };
+                let prompt = crate::app::EphemeralApproval { id: uuid::Uuid::new_v4().to_string(), title, action, details };
                 *state.handles.approval_prompt.write() = Some(prompt);
                 return (StatusCode::CONFLICT, Json(ApiError { message: "approval required".into() })).into_response();
             }
             let ok = state.handles.approvals.validate_token(approval_id.unwrap(), approve_token.unwrap());
             if !ok { return (StatusCode::FORBIDDEN, Json(ApiError { message: "invalid approval token".into() })).into_response(); }
         }
+        if let Some(obj) = params.as_object_mut() {
+            obj.remove("approval_id");
+            obj.remove("approve_token");
+        }
     }
     // Installer apply gating: if missing approval, interrupt with ephemeral prompt
     if server == "installer" && tool == "apply_install" {
@@ -625,7 +892,10 @@ async fn call_tool(
         let approve_token = params.get("approve_token").and_then(|v| v.as_str());
         if plan_id.is_none() || approval_id.is_none() || approve_token.is_none() {
             // Create a one-off prompt for TUI
-            let cmd_list = plan_id.and_then(|pid| crate::tools::installer_plan_commands(pid)).unwrap_or_default();
+            let cmd_list = match plan_id {
+                Some(pid) => state.handles.tools.installer().plan_commands(pid).await.unwrap_or_default(),
+                None => vec![],
+            };
             let title = format!("Install plan requires approval: {}", plan_id.unwrap_or("(unknown)"));
             let action = ProposedAction { command: "installer.apply".into(), writes: true, paths: vec![], intent: Some("package install".into()) };
             let prompt = crate::app::EphemeralApproval { id: uuid::Uuid::new_v4().to_string(), title, action, details: serde_json::json!({"commands": cmd_list}) };
@@ -706,9 +976,9 @@ async fn list_tool_status(State(state): State<SharedState>) -> impl IntoResponse
     Json(v)
 }
 
-async fn list_games() -> impl IntoResponse {
-    let v = scan_roms_root("/home/kil/games/roms");
-    Json(v)
+async fn list_games(State(state): State<SharedState>) -> impl IntoResponse {
+    let root = state.config.read().games.clone().unwrap_or_default().roms_root();
+    Json(mcp_games::catalog::by_console(&mcp_games::catalog::scan_roms(&root)))
 }
 
 async fn run_schedule_job(State(state): State<SharedState>, AxPath((job,)): AxPath<(String,)>) -> Response {
@@ -788,6 +1058,8 @@ async fn audio_beep(Json(_): Json<BeepReq>) -> impl IntoResponse { (StatusCode::
         endpoint: Option<String>,
         #[serde(default)]
         transport: Option<String>,
+        #[serde(default)]
+        record: Option<bool>,
     }
 
 async fn realtime_start(State(state): State<SharedState>, Json(req): Json<RtStartReq>) -> impl IntoResponse {
@@ -797,13 +1069,22 @@ async fn realtime_start(State(state): State<SharedState>, Json(req): Json<RtStar
     let (def_model, def_voice, def_endpoint) = cfg.voice.as_ref().map(|v| (
         v.realtime_model.clone(), v.realtime_voice.clone(), v.realtime_endpoint.clone()
     )).unwrap_or((None, None, None));
+    // Duplex switches fall back to [voice] when the request leaves them unset
+    let mut audio = req.audio.unwrap_or_default();
+    let mut record = req.record;
+    if let Some(v) = cfg.voice.as_ref() {
+        audio.full_duplex = audio.full_duplex.or(v.full_duplex);
+        audio.aec = audio.aec.or(v.aec);
+        record = record.or(v.record_sessions);
+    }
     let opts = crate::realtime::RealtimeOptions {
         model: req.model.or(def_model),
         voice: req.voice.or(def_voice),
-        audio: req.audio,
+        audio: Some(audio),
         instructions: req.instructions,
         endpoint: req.endpoint.or(def_endpoint),
         transport: req.transport,
+        record,
     };
     match state.handles.realtime.start(opts).await {
         Ok(()) => StatusCode::OK.into_response(),
@@ -821,6 +1102,32 @@ async fn realtime_status(State(state): State<SharedState>) -> impl IntoResponse
     Json(state.handles.realtime.status())
 }
 
+#[derive(serde::Deserialize)]
+struct SessionsQ { limit: Option<i64> }
+
+async fn realtime_sessions(State(state): State<SharedState>, axum::extract::Query(q): axum::extract::Query<SessionsQ>) -> Response {
+    crate::metrics::inc_api_request("/api/realtime/sessions");
+    let Some(mem) = state.handles.memory.as_ref() else {
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(ApiError { message: "memory not initialized".into() })).into_response();
+    };
+    match crate::realtime_sessions::list(&mem.store, q.limit.unwrap_or(20).clamp(1, 200)).await {
+        Ok(sessions) => Json(serde_json::json!({ "sessions": sessions })).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiError { message: e.to_string() })).into_response(),
+    }
+}
+
+async fn realtime_session_get(State(state): State<SharedState>, Path(id): Path<String>) -> Response {
+    crate::metrics::inc_api_request("/api/realtime/sessions/:id");
+    let Some(mem) = state.handles.memory.as_ref() else {
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(ApiError { message: "memory not initialized".into() })).into_response();
+    };
+    match crate::realtime_sessions::get(&mem.store, &id).await {
+        Ok(Some(session)) => Json(session).into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, Json(ApiError { message: format!("no realtime session {}", id) })).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiError { message: e.to_string() })).into_response(),
+    }
+}
+
 async fn wake_status(State(state): State<SharedState>) -> impl IntoResponse {
     let (active, opts) = state.handles.wake.status();
     Json(serde_json::json!({
@@ -830,9 +1137,65 @@ async fn wake_status(State(state): State<SharedState>) -> impl IntoResponse {
         "vad_sensitivity": opts.vad_sensitivity,
         "min_speech_ms": opts.min_speech_ms,
         "refractory_ms": opts.refractory_ms,
+        "engine": opts.resolved_engine().as_str(),
+        "enrolled": opts.kws_model.exists(),
     }))
 }
 
+#[derive(serde::Deserialize)]
+struct WakeEnrollReq {
+    #[serde(default)]
+    phrase: Option<String>,
+    /// Recorded takes of the phrase as PCM16 mono.
+    #[serde(default)]
+    samples: Option<Vec<Vec<i16>>>,
+    #[serde(default)]
+    sample_rate: Option<u32>,
+    /// Record this many takes from the microphone instead (realtime-audio builds).
+    #[serde(default)]
+    record: Option<usize>,
+    #[serde(default)]
+    seconds: Option<f32>,
+}
+
+async fn wake_enroll(State(state): State<SharedState>, Json(req): Json<WakeEnrollReq>) -> impl IntoResponse {
+    crate::metrics::inc_api_request("/api/wake/enroll");
+    let (_, opts) = state.handles.wake.status();
+    let bad = |code: StatusCode, message: String| (code, Json(ApiError { message })).into_response();
+    let takes: Vec<Vec<i16>> = match (req.samples, req.record) {
+        (Some(samples), _) => {
+            let sr = req.sample_rate.unwrap_or(crate::wake_kws::SAMPLE_RATE);
+            samples.iter().map(|s| crate::realtime_audio::resample_linear_i16(s, sr, crate::wake_kws::SAMPLE_RATE)).collect()
+        }
+        #[cfg(feature = "realtime-audio")]
+        (None, Some(count)) => {
+            // The sentinel holds the mic while listening
+            state.handles.wake.stop_task().await;
+            let recorded = crate::wake::record_takes(count.clamp(2, 8), req.seconds.unwrap_or(2.0)).await;
+            match recorded { Ok(t) => t, Err(e) => return bad(StatusCode::INTERNAL_SERVER_ERROR, format!("recording failed: {}", e)) }
+        }
+        _ => return bad(StatusCode::BAD_REQUEST, "provide samples (or record in a realtime-audio build)".into()),
+    };
+    let phrase = req.phrase.unwrap_or_else(|| opts.phrase.clone());
+    let model = match crate::wake_kws::KeywordModel::enroll(&phrase, &takes) {
+        Ok(m) => m,
+        Err(e) => return bad(StatusCode::UNPROCESSABLE_ENTITY, e.to_string()),
+    };
+    if let Err(e) = model.save(&opts.kws_model) {
+        return bad(StatusCode::INTERNAL_SERVER_ERROR, format!("save model: {}", e));
+    }
+    #[cfg(feature = "realtime-audio")]
+    if opts.enabled {
+        state.handles.wake.restart_task(state.handles.realtime.clone()).await;
+    }
+    Json(serde_json::json!({
+        "phrase": model.phrase,
+        "templates": model.templates.len(),
+        "calibration": model.calibration,
+        "path": opts.kws_model,
+    })).into_response()
+}
+
 async fn wake_enable(State(state): State<SharedState>) -> impl IntoResponse {
     state.handles.wake.set_enabled(true);
     #[cfg(feature = "realtime-audio")]
@@ -859,18 +1222,26 @@ async fn get_approval_prompt(State(state): State<SharedState>) -> impl IntoRespo
 }
 
 #[derive(serde::Deserialize)]
-struct ApprovalAnswer { id: String, answer: String }
+struct ApprovalAnswer { id: String, answer: String, #[serde(default)] reason: Option<String> }
 
 async fn answer_approval(State(state): State<SharedState>, Json(ans): Json<ApprovalAnswer>) -> impl IntoResponse {
-    let mut g = state.handles.approval_prompt.write();
-    if let Some(p) = g.clone() {
-        if p.id == ans.id {
+    let Some(decision) = crate::gatekeeper::PromptDecision::parse(&ans.answer, ans.reason.clone()) else {
+        return (StatusCode::BAD_REQUEST, Json(ApiError { message: "answer must be approve/yes or deny/no".into() })).into_response();
+    };
+    {
+        let mut g = state.handles.approval_prompt.write();
+        match g.as_ref() {
             // Clear prompt; agent/UI can retry the action as needed
-            *g = None;
-            return StatusCode::OK.into_response();
+            Some(p) if p.id == ans.id => *g = None,
+            _ => return StatusCode::NOT_FOUND.into_response(),
         }
     }
-    StatusCode::NOT_FOUND.into_response()
+    // Wake a realtime tool call blocked on this prompt, if any
+    let delivered = state.handles.approval_decisions.resolve(&ans.id, decision.clone());
+    if let Some(mem) = state.handles.memory.as_ref() {
+        let _ = mem.store.append_event(None, "approval:answered", Some(&serde_json::json!({"id": ans.id, "answer": decision, "delivered": delivered}))).await;
+    }
+    StatusCode::OK.into_response()
 }
 
 async fn explain_ephemeral(State(state): State<SharedState>, AxPath((id,)): AxPath<(String,)>) -> impl IntoResponse {
@@ -1021,240 +1392,148 @@ async fn codex_session_detail(State(state): State<SharedState>, AxPath((sid,)):
 
 // ---- Chat completion with tools ----
 #[derive(serde::Deserialize)]
-struct ChatMsg { role: String, content: String }
+struct ChatReq {
+    messages: Vec<crate::chats::Turn>, model: Option<String>, max_steps: Option<usize>, session_id: Option<String>,
+    /// Answers to the approvals a held reply asked for.
+    #[serde(default)]
+    approvals: Vec<ChatApprovalGrant>,
+}
 
+/// An approval from `/api/approvals` for one held tool call; the token comes
+/// with approving it.
 #[derive(serde::Deserialize)]
-struct ChatReq { messages: Vec<ChatMsg>, model: Option<String>, max_steps: Option<usize> }
+struct ChatApprovalGrant { tool_call_id: String, approval_id: String, approve_token: Option<String> }
 
 #[derive(serde::Serialize)]
 struct ChatResp { reply: String }
 
+/// A reply held on tool calls that need approval. Nothing in the held step has
+/// run; answer the approvals and post `messages` back with the grants.
+#[derive(serde::Serialize)]
+struct ChatPending { pending_approval: Vec<PendingToolCall>, messages: Vec<crate::chats::Turn> }
+
+#[derive(serde::Serialize)]
+struct PendingToolCall { tool_call_id: String, name: String, arguments: serde_json::Value, approval_id: String, reasons: Vec<String> }
+
+/// What a chat tool call gets in a held-reply round trip.
+enum ChatCallGate { Run, Answer(serde_json::Value), Pending(PendingToolCall) }
+
+/// Gate one call of `/api/chat/complete`. A held call runs once its grant
+/// names an approval for this exact action with a valid token; without a
+/// grant it gets a pending approval instead of waiting on a prompt.
+fn gate_chat_call(state: &SharedState, grants: &[ChatApprovalGrant], call: &crate::chats::ToolCallRecord) -> ChatCallGate {
+    use crate::gatekeeper::{tool_calls, ApprovalStatus, PromptDecision};
+    let h = &state.handles;
+    let (server, tool) = tool_calls::split_tool_name(&call.name);
+    let (action, decision) = match tool_calls::gate(&h.tools, &h.policy, &server, &tool, &call.arguments) {
+        tool_calls::ToolGate::Run => return ChatCallGate::Run,
+        tool_calls::ToolGate::Reject(err) => return ChatCallGate::Answer(err),
+        tool_calls::ToolGate::Hold { action, decision } => (action, decision),
+    };
+    let granted = grants.iter()
+        .find(|g| g.tool_call_id == call.id)
+        .and_then(|g| h.approvals.get(&g.approval_id).filter(|a| a.action == action).map(|a| (g, a)));
+    let approval = match granted {
+        Some((g, a)) if a.status == ApprovalStatus::Approved => {
+            return match g.approve_token.as_deref() {
+                Some(token) if h.approvals.validate_token(&a.id, token) => ChatCallGate::Run,
+                _ => ChatCallGate::Answer(serde_json::json!({"error": "invalid approval token"})),
+            };
+        }
+        Some((_, a)) if a.status == ApprovalStatus::Denied => {
+            return ChatCallGate::Answer(tool_calls::declined(&server, &tool, &PromptDecision::Deny { reason: None }).unwrap_or_default());
+        }
+        Some((_, a)) => a,
+        None => h.approvals.create(action),
+    };
+    ChatCallGate::Pending(PendingToolCall { tool_call_id: call.id.clone(), name: call.name.clone(), arguments: call.arguments.clone(), approval_id: approval.id, reasons: decision.reasons })
+}
+
+/// Single reply with tools. A tool call that policy holds does not block the
+/// request: the reply comes back `202` with the pending approvals, and posting
+/// the returned `messages` with `approvals` resumes it from the held step.
 async fn chat_complete(State(state): State<SharedState>, Json(req): Json<ChatReq>) -> impl IntoResponse {
     let model = req.model.unwrap_or_else(|| std::env::var("OPENAI_MODEL").unwrap_or_else(|_| "gpt-5".into()));
     let key = match std::env::var("OPENAI_API_KEY") { Ok(k) => k, Err(_) => return (StatusCode::BAD_REQUEST, Json(ApiError { message: "OPENAI_API_KEY not set".into() })).into_response() };
     let client = HttpClient::new();
-    // Inject system prompt with local knowledge and tool safety rules
-    let mut messages: Vec<serde_json::Value> = vec![serde_json::json!({"role": "system", "content": system_prompt()})];
-    messages.extend(req.messages.into_iter().map(|m| serde_json::json!({"role": m.role, "content": m.content})));
-    let tools = build_tool_defs(&state.handles.tools);
+    // A resumed reply ends in the held step: the turns after the last user turn,
+    // closing on an assistant turn with calls. They follow the assembled context.
+    let mut turns = req.messages;
+    let mut held = None;
+    let mut resumed = vec![];
+    if turns.last().map(|t| t.role == "assistant" && !t.tool_calls.is_empty()).unwrap_or(false) {
+        let from = turns.iter().rposition(|t| t.role == "user").map(|i| i + 1).unwrap_or(0);
+        resumed = turns.split_off(from);
+        held = resumed.pop();
+    }
+    // System prompt, memory pack, summary of older turns and the recent window
+    let summarizer = crate::chat_context::Summarizer { client: client.clone(), key: key.clone(), model: model.clone() };
+    let mut messages = chat_context(&state, req.session_id.as_deref(), &turns, Some(&summarizer)).await.messages;
+    messages.extend(crate::chats::provider_messages(&resumed));
+    turns.extend(resumed);
+    let tools = crate::tools::chat_tool_defs(&state.handles.tools);
     let max_steps = req.max_steps.unwrap_or(6);
     for _ in 0..max_steps {
-        match openai_chat_once(&client, &key, &model, &messages, &tools).await {
-            Ok(OnceResult::Final(reply)) => return Json(ChatResp { reply }).into_response(),
-            Ok(OnceResult::ToolCalls(calls, assistant_msg)) => {
-                messages.push(assistant_msg);
-                for c in calls {
-                    let (server, tool) = match c.name.split_once('_') { Some((s, t)) => (s.to_string(), t.to_string()), None => (c.name.clone(), String::new()) };
-                    let params = c.arguments.unwrap_or_else(|| serde_json::json!({}));
-                    let result = match state.handles.tools.invoke(&server, &tool, params).await { Ok(v) => v, Err(e) => serde_json::json!({"error": e.to_string()}) };
-                    messages.push(serde_json::json!({"role": "tool", "tool_call_id": c.id, "content": serde_json::to_string(&result).unwrap_or_else(|_| "{}".into()) }));
+        let step = match held.take() {
+            Some(step) => step,
+            None => match openai_chat_once(&client, &key, &model, &messages, &tools).await {
+                Ok(OnceResult::Final(reply)) => return Json(ChatResp { reply }).into_response(),
+                Ok(OnceResult::ToolCalls(calls, assistant_msg)) => crate::chats::Turn {
+                    role: "assistant".into(),
+                    content: assistant_msg.get("content").and_then(|c| c.as_str()).unwrap_or("").to_string(),
+                    tool_calls: calls.into_iter().map(|c| crate::chats::ToolCallRecord { id: c.id, name: c.name, arguments: c.arguments.unwrap_or_else(|| serde_json::json!({})) }).collect(),
+                    tool_call_id: None,
+                },
+                Err(e) => {
+                    tracing::warn!(error=%e, "chat_complete: OpenAI/tool orchestration failed");
+                    return (StatusCode::BAD_GATEWAY, Json(ApiError { message: e.to_string() })).into_response();
                 }
-                continue;
-            }
-            Err(e) => {
-                tracing::warn!(error=%e, "chat_complete: OpenAI/tool orchestration failed");
-                return (StatusCode::BAD_GATEWAY, Json(ApiError { message: e.to_string() })).into_response();
-            }
-        }
-    }
-    (StatusCode::BAD_GATEWAY, Json(ApiError { message: "tool loop exceeded".into() })).into_response()
-}
-
-pub(crate) fn load_steamgames_user_list() -> Vec<(String, String)> {
-    let path = std::path::Path::new("config/steamgames.toml");
-    if !path.exists() { return vec![]; }
-    let Ok(text) = std::fs::read_to_string(path) else { return vec![] };
-    let Ok(val) = toml::from_str::<toml::Value>(&text) else { return vec![] };
-    let mut out: Vec<(String,String)> = vec![];
-    // Expect a [games] table with key -> appid (string or integer)
-    if let Some(tbl) = val.get("games").and_then(|v| v.as_table()) {
-        for (name, v) in tbl.iter() {
-            let appid = if let Some(s) = v.as_str() { s.to_string() } else if let Some(n) = v.as_integer() { n.to_string() } else { continue };
-            out.push((name.to_string(), appid));
+            },
+        };
+        // Gate the whole step first so nothing in it runs while any call is held
+        let gates: Vec<ChatCallGate> = step.tool_calls.iter().map(|c| gate_chat_call(&state, &req.approvals, c)).collect();
+        if gates.iter().any(|g| matches!(g, ChatCallGate::Pending(_))) {
+            let pending = gates.into_iter().filter_map(|g| match g { ChatCallGate::Pending(p) => Some(p), _ => None }).collect();
+            turns.push(step);
+            return (StatusCode::ACCEPTED, Json(ChatPending { pending_approval: pending, messages: turns })).into_response();
         }
-    }
-    out.sort_by(|a,b| a.0.to_lowercase().cmp(&b.0.to_lowercase()));
-    out
-}
-
-fn system_prompt() -> String { crate::prompt::base_system_prompt() }
-
-fn scan_roms_root(root: &str) -> Vec<(String, Vec<String>)> {
-    use std::fs;
-    use std::path::Path;
-    let mut out: Vec<(String, Vec<String>)> = vec![];
-    let root_path = Path::new(root);
-    if !root_path.exists() { return out; }
-    let Ok(entries) = fs::read_dir(root_path) else { return out; };
-    for entry in entries.flatten() {
-        let path = entry.path();
-        let Ok(ft) = entry.file_type() else { continue; };
-        if ft.is_dir() {
-            let console = entry.file_name().to_string_lossy().to_string();
-            let mut titles: Vec<String> = vec![];
-            if let Ok(mut games) = fs::read_dir(&path) {
-                while let Some(Ok(g)) = games.next() {
-                    let gp = g.path();
-                    if gp.is_file() {
-                        if let Some(name) = gp.file_stem().and_then(|s| s.to_str()) {
-                            let title = name.replace('_', " ");
-                            titles.push(title);
-                        }
-                    }
+        let mut results = vec![];
+        for (c, g) in step.tool_calls.iter().zip(gates) {
+            let result = match g {
+                ChatCallGate::Answer(v) => v,
+                _ => {
+                    let (server, tool) = crate::gatekeeper::tool_calls::split_tool_name(&c.name);
+                    state.handles.tools.invoke(&server, &tool, c.arguments.clone()).await.unwrap_or_else(|e| serde_json::json!({"error": e.to_string()}))
                 }
-            }
-            if !titles.is_empty() {
-                titles.sort();
-                out.push((console, titles));
-            }
-        }
-    }
-    out.sort_by(|a, b| a.0.cmp(&b.0));
-    out
-}
-
-fn build_tool_defs(tm: &crate::tools::ToolsManager) -> Vec<serde_json::Value> {
-    let mut out = vec![];
-    for (server, tools) in tm.list().into_iter() {
-        for t in tools {
-            let name = format!("{}_{}", server, t).replace('-', "_");
-            let (desc, params): (String, serde_json::Value) = if server == "shell" && t == "exec" {
-                (
-                    "Execute a desktop command with strict policy. Usage: {\"cmd\":\"mgba-qt\",\"args\":[\"/home/kil/games/roms/<console>/<file>\"]} (GB/GBA). For Nintendo DS: {\"cmd\":\"/home/kil/games/emulators/melonDS-x86_64.AppImage\",\"args\":[]}. Optional: {\"wait\": false} to spawn and return a PID.".to_string(),
-                    serde_json::json!({
-                        "type": "object",
-                        "properties": {
-                            "cmd": {"type": "string", "description": "Program to run. 'mgba-qt' or the absolute DS emulator path."},
-                            "args": {"type": "array", "items": {"type": "string"}, "description": "Arguments list. For mgba-qt, exactly one ROM path under /home/kil/games/roms. For DS emulator, empty."},
-                            "wait": {"type": "boolean", "description": "If false, spawn and return pid; defaults to true (wait for exit)."}
-                        },
-                        "required": ["cmd"],
-                        "additionalProperties": false
-                    })
-                )
-            } else if server == "shell" && t == "which" {
-                (
-                    "Resolve a command name to a full path (PATH lookup).".to_string(),
-                    serde_json::json!({
-                        "type": "object",
-                        "properties": {"cmd": {"type": "string", "description": "Command name, e.g., 'mgba-qt'"}},
-                        "required": ["cmd"],
-                        "additionalProperties": false
-                    })
-                )
-            } else if server == "shell" && (t == "list_dir" || t == "read_file") {
-                let d = if t == "list_dir" { "List entries in a directory." } else { "Read a UTF-8 text file." };
-                (
-                    d.to_string(),
-                    serde_json::json!({
-                        "type": "object",
-                        "properties": {"path": {"type": "string", "description": "Filesystem path"}},
-                        "required": ["path"],
-                        "additionalProperties": false
-                    })
-                )
-            } else if server == "steam" && t == "installed" {
-                (
-                    "List installed Steam games by reading local Steam app manifests (~/.local/share/Steam/steamapps).".to_string(),
-                    serde_json::json!({
-                        "type": "object",
-                        "properties": {
-                            "root": {"type": "string", "description": "Optional override for steamapps directory"}
-                        },
-                        "additionalProperties": false
-                    })
-                )
-            } else if server == "steam" && t == "launch" {
-                (
-                    "Launch a Steam game (steam -applaunch APPID).".to_string(),
-                    serde_json::json!({
-                        "type": "object",
-                        "properties": {"appid": {"type": "string", "description": "Steam AppID (digits)"}},
-                        "required": ["appid"],
-                        "additionalProperties": false
-                    })
-                )
-            } else if server == "arxiv" && t == "search" {
-                (
-                    "Search arXiv. Provide a query and optional filters. Example: {\"query\":\"mixture-of-experts\",\"categories\":[\"cs.LG\"],\"from\":\"2025-09-01T00:00:00Z\",\"max_results\":25}".to_string(),
-                    serde_json::json!({
-                        "type": "object",
-                        "properties": {
-                            "query": {"type": "string", "description": "Search query (keywords)."},
-                            "categories": {"type": "array", "items": {"type": "string"}, "description": "Optional category codes, e.g., cs.LG, cs.AI."},
-                            "from": {"type": "string", "description": "Optional ISO-8601 UTC lower bound for updated date (e.g., 2025-09-01T00:00:00Z)."},
-                            "max_results": {"type": "integer", "minimum": 1, "maximum": 50, "description": "Limit results (1-50, default 25)."}
-                        },
-                        "required": ["query"],
-                        "additionalProperties": false
-                    })
-                )
-            } else if server == "arxiv" && t == "top" {
-                (
-                    "Top recent papers for a month (first N by latest update). Example: {\"month\":\"2025-09\",\"n\":5}".to_string(),
-                    serde_json::json!({
-                        "type": "object",
-                        "properties": {
-                            "month": {"type": "string", "description": "YYYY-MM month (defaults to current)."},
-                            "n": {"type": "integer", "minimum": 1, "maximum": 50, "description": "Number of items to return (default 5)."}
-                        },
-                        "additionalProperties": false
-                    })
-                )
-            } else if server == "arxiv" && t == "fetch_pdf" {
-                (
-                    "Download a paper PDF by arXiv ID. Example: {\"id\":\"2509.01234\"}".to_string(),
-                    serde_json::json!({
-                        "type": "object",
-                        "properties": {
-                            "id": {"type": "string", "description": "arXiv id (YYMM.NNNNN or arXiv:YYMM.NNNNN)."}
-                        },
-                        "required": ["id"],
-                        "additionalProperties": false
-                    })
-                )
-            } else if server == "arxiv" && t == "summarize" {
-                (
-                    "Summarize a paper by arXiv ID (not implemented; will return an error for now). Example: {\"id\":\"2509.01234\"}".to_string(),
-                    serde_json::json!({
-                        "type": "object",
-                        "properties": {
-                            "id": {"type": "string", "description": "arXiv id (YYMM.NNNNN)."}
-                        },
-                        "required": ["id"],
-                        "additionalProperties": false
-                    })
-                )
-            } else {
-                (
-                    format!("Call {}.{} via Foreman MCP", server, t),
-                    serde_json::json!({"type": "object", "properties": {}, "additionalProperties": true})
-                )
             };
-            out.push(serde_json::json!({
-                "type": "function",
-                "function": {
-                    "name": name,
-                    "description": desc,
-                    "parameters": params
-                }
-            }));
+            results.push(crate::chats::Turn { role: "tool".into(), content: serde_json::to_string(&result).unwrap_or_else(|_| "{}".into()), tool_calls: vec![], tool_call_id: Some(c.id.clone()) });
         }
+        turns.push(step);
+        turns.extend(results);
+        // The model sees its own calls, then their results
+        let at = turns.iter().rposition(|t| t.role == "assistant").unwrap_or(0);
+        messages.extend(crate::chats::provider_messages(&turns[at..]));
     }
-    out
+    (StatusCode::BAD_GATEWAY, Json(ApiError { message: "tool loop exceeded".into() })).into_response()
 }
 
 enum OnceResult { Final(String), ToolCalls(Vec<ToolCall>, serde_json::Value) }
 
 struct ToolCall { id: String, name: String, arguments: Option<serde_json::Value> }
 
+/// Chat completions endpoint; `OPENAI_BASE_URL` points it at a compatible server.
+pub(crate) fn openai_chat_url() -> String {
+    let base = std::env::var("OPENAI_BASE_URL").unwrap_or_else(|_| "https://api.openai.com/v1".into());
+    format!("{}/chat/completions", base.trim_end_matches('/'))
+}
+
 async fn openai_chat_once(client: &HttpClient, key: &str, model: &str, messages: &Vec<serde_json::Value>, tools: &Vec<serde_json::Value>) -> anyhow::Result<OnceResult> {
     #[derive(serde::Deserialize)]
     struct Choice { message: serde_json::Value }
     #[derive(serde::Deserialize)]
     struct Resp { choices: Vec<Choice> }
     let body = serde_json::json!({"model": model, "messages": messages, "tools": tools, "tool_choice": "auto"});
-    let resp = client.post("https://api.openai.com/v1/chat/completions").bearer_auth(key).header("content-type","application/json").json(&body).send().await?;
+    let resp = client.post(openai_chat_url()).bearer_auth(key).header("content-type","application/json").json(&body).send().await?;
     if !resp.status().is_success() {
         let status = resp.status();
         let txt = resp.text().await.unwrap_or_default();
@@ -1278,74 +1557,133 @@ async fn openai_chat_once(client: &HttpClient, key: &str, model: &str, messages:
 }
 
 #[derive(serde::Deserialize)]
-struct ChatStreamReq { messages: Vec<ChatMsg>, model: Option<String>, max_steps: Option<usize>, session_id: Option<String> }
+struct ChatStreamReq {
+    messages: Vec<crate::chats::Turn>, model: Option<String>, max_steps: Option<usize>, session_id: Option<String>,
+    /// Speak the reply as it streams (default `[voice] tts.speak_chat`).
+    #[serde(default)]
+    speak: Option<bool>,
+}
+
+/// Model settings for one streamed reply.
+struct ReplyOpts { model: String, mock: bool, key: String, max_steps: usize, speak: bool }
+
+impl ReplyOpts {
+    /// Mock mode (model "mock" or `CHAT_MOCK=1`) needs no key; otherwise a
+    /// missing `OPENAI_API_KEY` is a 400.
+    fn new(state: &SharedState, model: Option<String>, max_steps: Option<usize>, speak: Option<bool>) -> Result<Self, (StatusCode, &'static str)> {
+        let mock = model.as_deref() == Some("mock") || std::env::var("CHAT_MOCK").ok().as_deref() == Some("1");
+        let model = model.unwrap_or_else(|| std::env::var("OPENAI_MODEL").unwrap_or_else(|_| "gpt-5".into()));
+        let key = if mock { String::new() } else {
+            match std::env::var("OPENAI_API_KEY") { Ok(k) => k, Err(_) => return Err((StatusCode::BAD_REQUEST, "OPENAI_API_KEY not set")) }
+        };
+        Ok(Self { model, mock, key, max_steps: max_steps.unwrap_or(6), speak: speak.unwrap_or(state.handles.tts.speak_chat) })
+    }
+
+    /// The reply model also writes summaries of older turns; mock mode has none.
+    fn summarizer(&self) -> Option<crate::chat_context::Summarizer> {
+        if self.mock { return None; }
+        Some(crate::chat_context::Summarizer { client: HttpClient::new(), key: self.key.clone(), model: self.model.clone() })
+    }
+}
 
 async fn chat_stream(State(state): State<SharedState>, Json(req): Json<ChatStreamReq>) -> impl IntoResponse {
-    let model = req.model.clone().unwrap_or_else(|| std::env::var("OPENAI_MODEL").unwrap_or_else(|_| "gpt-5".into()));
-    let mock_ok = req.model.as_deref() == Some("mock") || std::env::var("CHAT_MOCK").ok().as_deref() == Some("1");
-    let key = if mock_ok { String::new() } else { match std::env::var("OPENAI_API_KEY") { Ok(k) => k, Err(_) => return (StatusCode::BAD_REQUEST, "OPENAI_API_KEY not set").into_response() } };
-    let client = if mock_ok { HttpClient::new() } else { HttpClient::new() };
-    let tools = build_tool_defs(&state.handles.tools);
-    let max_steps = req.max_steps.unwrap_or(6);
+    let opts = match ReplyOpts::new(&state, req.model, req.max_steps, req.speak) { Ok(o) => o, Err(r) => return r.into_response() };
+    let ctx = chat_context(&state, req.session_id.as_deref(), &req.messages, opts.summarizer().as_ref()).await;
+    let persist = match (req.session_id, chat_store(&state)) {
+        (Some(sid), Ok(chats)) => Some((chats, sid)),
+        (Some(sid), Err(_)) => { tracing::warn!(session=%sid, "chat_stream: memory not initialized; reply not saved"); None }
+        (None, _) => None,
+    };
+    stream_reply(state, opts, ctx, persist)
+}
 
-    let (tx, rx) = tokio::sync::mpsc::channel::<String>(16);
-    let mut messages: Vec<serde_json::Value> = vec![serde_json::json!({"role": "system", "content": system_prompt()})];
-    messages.extend(req.messages.into_iter().map(|m| serde_json::json!({"role": m.role, "content": m.content})));
-    let session_id = req.session_id.clone();
-    tokio::spawn(async move {
-        // Optional assistant message id for this stream
-        let assistant_id = session_id.as_ref().map(|_| uuid::Uuid::new_v4().to_string());
-        // If persisting, create placeholder message
-        if let (Some(sid), Some(aid)) = (session_id.as_ref(), assistant_id.as_ref()) {
-            let path = chat_dir(&state).join(format!("{}.json", sid));
-            let lock = session_lock(sid).await;
-            let _g = lock.lock().await;
-            let mut s = match read_session(&path).await { Ok(s) => s, Err(_) => ChatSession { id: sid.clone(), messages: vec![] } };
-            let at = chrono::Utc::now().to_rfc3339();
-            s.messages.push(ChatMessage { id: aid.clone(), role: "assistant".to_string(), content: String::new(), at: Some(at) });
-            let _ = write_session(&path, &s).await;
-            let _ = tx.send("event: assistant_started\n".to_string()).await;
-            let _ = tx.send(format!("data: {}\n\n", serde_json::json!({"id": aid}))).await;
+/// Model input for a reply. A stored session is read back from the database
+/// (the client's copy is ignored); otherwise the client's history is used.
+async fn chat_context(state: &SharedState, session_id: Option<&str>, history: &[crate::chats::Turn], summarizer: Option<&crate::chat_context::Summarizer>) -> crate::chat_context::Assembled {
+    if let (Some(sid), Ok(chats)) = (session_id, chat_store(state)) {
+        match chats.get(sid).await {
+            Ok(Some(session)) if !session.messages.is_empty() => return crate::chat_context::for_session(state, &chats, &session, summarizer).await,
+            Ok(_) => {}
+            Err(e) => tracing::warn!(session=%sid, error=%e, "chat context: session not readable; using client history"),
         }
+    }
+    crate::chat_context::for_history(state, history).await
+}
+
+/// Run the tool loop and stream the reply as SSE. With a session, the reply is
+/// saved under the session head: each step that calls tools becomes an
+/// assistant message with its calls followed by one `tool` message per result,
+/// and the final text a last assistant message.
+fn stream_reply(state: SharedState, opts: ReplyOpts, ctx: crate::chat_context::Assembled, persist: Option<(crate::chats::ChatStore, String)>) -> Response {
+    let crate::chat_context::Assembled { mut messages, report } = ctx;
+    let ReplyOpts { model, mock: mock_ok, key, max_steps, speak } = opts;
+    let client = HttpClient::new();
+    let tools = crate::tools::chat_tool_defs(&state.handles.tools);
 
+    let (tx, rx) = tokio::sync::mpsc::channel::<String>(16);
+    let mut speaker = if speak { state.handles.tts.stream(crate::tts::Priority::Normal) } else { None };
+    tokio::spawn(async move {
+        let _ = tx.send("event: context\n".to_string()).await;
+        let _ = tx.send(format!("data: {}\n\n", serde_json::to_string(&report).unwrap_or_default())).await;
+        // If persisting, create the placeholder message for this stream
+        let mut assistant_id = start_assistant(&persist, &tx).await;
         let mut assistant_acc = String::new();
         for _ in 0..max_steps {
             if mock_ok {
                 // In mock mode, stream a simple assistant reply and break
                 // Create a small tokenized reply deterministically
-                if let (Some(_sid), Some(aid)) = (session_id.as_ref(), assistant_id.as_ref()) {
+                if let Some(aid) = assistant_id.as_ref() {
                     let _ = tx.send("event: token\n".to_string()).await;
                     let _ = tx.send(format!("data: {}\n\n", serde_json::json!({"id": aid, "text": "Hello"}))).await;
                     assistant_acc.push_str("Hello");
                     let _ = tx.send("event: token\n".to_string()).await;
                     let _ = tx.send(format!("data: {}\n\n", serde_json::json!({"id": aid, "text": " world"}))).await;
                     assistant_acc.push_str(" world");
+                    if let Some(sp) = speaker.as_mut() { sp.push(&assistant_acc); }
                 }
                 break;
             }
             match openai_chat_once(&client, &key, &model, &messages, &tools).await {
                 Ok(OnceResult::ToolCalls(calls, assistant_msg)) => {
-                    let _ = tx.send(format!("event: tool_calls\n")).await;
-                    for c in calls.iter() {
-                        let name = &c.name;
-                        let args = c.arguments.clone().unwrap_or_else(|| serde_json::json!({}));
-                        let _ = tx.send(format!("event: tool_call\n")).await;
-                        let _ = tx.send(format!("data: {}\n\n", serde_json::json!({"name": name, "arguments": args}))).await;
-                        let (server, tool) = match name.split_once('_') { Some((s,t)) => (s.to_string(), t.to_string()), None => (name.clone(), String::new()) };
-                        let result = match state.handles.tools.invoke(&server, &tool, args).await { Ok(v) => v, Err(e) => serde_json::json!({"error": e.to_string()}) };
+                    let records: Vec<crate::chats::ToolCallRecord> = calls.iter()
+                        .map(|c| crate::chats::ToolCallRecord { id: c.id.clone(), name: c.name.clone(), arguments: c.arguments.clone().unwrap_or_else(|| serde_json::json!({})) })
+                        .collect();
+                    if let (Some((chats, _)), Some(aid)) = (persist.as_ref(), assistant_id.as_ref()) {
+                        let text = assistant_msg.get("content").and_then(|c| c.as_str()).unwrap_or("");
+                        if let Err(e) = save_tool_calls(chats, aid, text, &records).await { tracing::warn!(error=%e, "chat_stream: could not save tool calls"); }
+                    }
+                    // The model must see its own calls before their results
+                    messages.push(assistant_msg);
+                    let _ = tx.send("event: tool_calls\n".to_string()).await;
+                    let _ = tx.send(format!("data: {}\n\n", serde_json::json!({"id": assistant_id, "calls": records}))).await;
+                    for c in records.iter() {
+                        let _ = tx.send("event: tool_call\n".to_string()).await;
+                        let _ = tx.send(format!("data: {}\n\n", serde_json::json!({"id": c.id, "name": c.name, "arguments": c.arguments}))).await;
+                        let h = &state.handles;
+                        let (result, approval) = crate::gatekeeper::tool_calls::run_tool_call(&h.tools, &h.policy, &h.approval_prompt, &h.approval_decisions, None, "Chat", &c.name, c.arguments.clone()).await;
                         let snip = serde_json::to_string(&result).unwrap_or_else(|_| "{}".into());
+                        let approval_id = approval.map(|a| a.id);
+                        let mut saved = None;
+                        if let Some((chats, sid)) = persist.as_ref() {
+                            match chats.append_tool_result(sid, c, &result, approval_id.as_deref()).await {
+                                Ok(m) => saved = Some(m),
+                                Err(e) => tracing::warn!(error=%e, "chat_stream: could not save tool result"),
+                            }
+                        }
                         let _ = tx.send("event: tool_result\n".to_string()).await;
-                        let _ = tx.send(format!("data: {}\n\n", serde_json::json!({"name": name, "result": snip}))).await;
-                        // Append tool message
-                        messages.push(serde_json::json!({"role":"tool","tool_call_id": c.id, "content": snip}));
+                        let _ = tx.send(format!("data: {}\n\n", serde_json::json!({
+                            "id": c.id, "name": c.name, "result": snip, "approval_id": approval_id,
+                            "message_id": saved.as_ref().map(|m| &m.id), "artifact_id": saved.as_ref().and_then(|m| m.artifact_id),
+                        }))).await;
+                        messages.push(serde_json::json!({"role": "tool", "tool_call_id": c.id, "content": snip}));
                     }
-                    // Add assistant tool_calls message so model sees its own tool calls
-                    messages.push(assistant_msg);
+                    // The next step answers under the results
+                    if persist.is_some() { assistant_id = start_assistant(&persist, &tx).await; }
                     continue;
                 }
                 Ok(OnceResult::Final(_)) => {
                     // Stream final tokens with OpenAI stream=true with no tools (to avoid further calls)
-                    if let Err(e) = openai_stream_tokens(&client, &key, &model, &messages, tx.clone(), assistant_id.as_deref(), &mut assistant_acc).await {
+                    if let Err(e) = openai_stream_tokens(&client, &key, &model, &messages, tx.clone(), assistant_id.as_deref(), &mut assistant_acc, speaker.as_mut()).await {
                         let _ = tx.send(format!("event: error\n")).await;
                         let _ = tx.send(format!("data: {}\n\n", serde_json::json!({"message": e.to_string()}))).await;
                     }
@@ -1358,17 +1696,10 @@ async fn chat_stream(State(state): State<SharedState>, Json(req): Json<ChatStrea
                 }
             }
         }
-        // Persist assistant content if we have a session and placeholder id
-        if let (Some(sid), Some(aid)) = (session_id.as_ref(), assistant_id.as_ref()) {
-            let path = chat_dir(&state).join(format!("{}.json", sid));
-            let lock = session_lock(sid).await;
-            let _g = lock.lock().await;
-            if let Ok(mut s) = read_session(&path).await {
-                if let Some(m) = s.messages.iter_mut().find(|m| m.id == *aid) {
-                    m.content = assistant_acc.clone();
-                }
-                let _ = write_session(&path, &s).await;
-            }
+        if let Some(sp) = speaker.take() { sp.finish(); }
+        // Persist assistant content into the placeholder
+        if let (Some((chats, _)), Some(aid)) = (persist.as_ref(), assistant_id.as_ref()) {
+            if let Err(e) = chats.set_content(aid, &assistant_acc).await { tracing::warn!(error=%e, "chat_stream: could not save reply"); }
         }
         let _ = tx.send("event: done\n\n".to_string()).await;
     });
@@ -1384,9 +1715,29 @@ async fn chat_stream(State(state): State<SharedState>, Json(req): Json<ChatStrea
         .unwrap()
 }
 
-async fn openai_stream_tokens(client: &HttpClient, key: &str, model: &str, messages: &Vec<serde_json::Value>, tx: tokio::sync::mpsc::Sender<String>, assistant_id: Option<&str>, acc: &mut String) -> anyhow::Result<()> {
+/// Save an empty assistant message under the head and announce it; tokens
+/// stream into it.
+async fn start_assistant(persist: &Option<(crate::chats::ChatStore, String)>, tx: &tokio::sync::mpsc::Sender<String>) -> Option<String> {
+    let (chats, sid) = persist.as_ref()?;
+    match chats.append(sid, "assistant", "").await {
+        Ok(m) => {
+            let _ = tx.send("event: assistant_started\n".to_string()).await;
+            let _ = tx.send(format!("data: {}\n\n", serde_json::json!({"id": m.id}))).await;
+            Some(m.id)
+        }
+        Err(e) => { tracing::warn!(error=%e, session=%sid, "chat_stream: could not save reply"); None }
+    }
+}
+
+async fn save_tool_calls(chats: &crate::chats::ChatStore, message_id: &str, text: &str, calls: &[crate::chats::ToolCallRecord]) -> anyhow::Result<()> {
+    chats.set_content(message_id, text).await?;
+    chats.set_tool_calls(message_id, calls).await
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn openai_stream_tokens(client: &HttpClient, key: &str, model: &str, messages: &Vec<serde_json::Value>, tx: tokio::sync::mpsc::Sender<String>, assistant_id: Option<&str>, acc: &mut String, mut speaker: Option<&mut crate::tts::SpeechStream>) -> anyhow::Result<()> {
     let body = serde_json::json!({"model": model, "messages": messages, "stream": true});
-    let mut resp = client.post("https://api.openai.com/v1/chat/completions").bearer_auth(key).header("content-type","application/json").json(&body).send().await?;
+    let mut resp = client.post(openai_chat_url()).bearer_auth(key).header("content-type","application/json").json(&body).send().await?;
     if !resp.status().is_success() { anyhow::bail!(format!("openai http {}", resp.status())); }
     let mut buf: Vec<u8> = vec![];
     let mut s = resp.bytes_stream();
@@ -1406,6 +1757,7 @@ async fn openai_stream_tokens(client: &HttpClient, key: &str, model: &str, messa
                                 if let Some(delta) = choice.get("delta") {
                                     if let Some(piece) = delta.get("content").and_then(|c| c.as_str()) {
                                         acc.push_str(piece);
+                                        if let Some(sp) = speaker.as_deref_mut() { sp.push(piece); }
                                         let _ = tx.send("event: token\n".to_string()).await;
                                         let payload = if let Some(id) = assistant_id { serde_json::json!({"id": id, "text": piece}) } else { serde_json::json!({"text": piece}) };
                                         let _ = tx.send(format!("data: {}\n\n", payload)).await;
@@ -1421,138 +1773,166 @@ async fn openai_stream_tokens(client: &HttpClient, key: &str, model: &str, messa
     Ok(())
 }
 
-// ---- Chat session persistence ----
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
-struct ChatMessage {
-    #[serde(default)]
-    id: String,
-    role: String,
-    content: String,
-    at: Option<String>,
+// ---- Chat sessions ----
+fn chat_store(state: &SharedState) -> Result<crate::chats::ChatStore, (StatusCode, Json<ApiError>)> {
+    state.handles.chats.clone().ok_or_else(|| (StatusCode::SERVICE_UNAVAILABLE, Json(ApiError { message: "memory not initialized".into() })))
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
-struct ChatSession { id: String, messages: Vec<ChatMessage> }
+fn chat_error(e: anyhow::Error) -> Response {
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiError { message: e.to_string() })).into_response()
+}
 
-#[derive(serde::Serialize, Clone, Debug)]
-struct SessionInfo { id: String, updated_at: String, title: Option<String> }
+/// A [`crate::chats::ChatError`] is the caller's fault; anything else is ours.
+fn chat_request_error(e: anyhow::Error) -> Response {
+    let status = match e.downcast_ref::<crate::chats::ChatError>() {
+        Some(crate::chats::ChatError::NotFound(_)) => StatusCode::NOT_FOUND,
+        Some(crate::chats::ChatError::BadRequest(_)) => StatusCode::BAD_REQUEST,
+        None => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, Json(ApiError { message: e.to_string() })).into_response()
+}
 
-fn chat_dir(state: &SharedState) -> std::path::PathBuf {
-    let base = state.handles.system_map.map_path().parent().unwrap_or(std::path::Path::new("."));
-    base.join("chats")
+#[derive(serde::Deserialize)]
+struct ListQ { limit: Option<i64> }
+
+async fn chat_sessions_list(State(state): State<SharedState>, axum::extract::Query(q): axum::extract::Query<ListQ>) -> impl IntoResponse {
+    crate::metrics::inc_api_request("/api/chat/sessions");
+    let chats = match chat_store(&state) { Ok(c) => c, Err(r) => return r.into_response() };
+    match chats.list(q.limit.unwrap_or(200)).await {
+        Ok(list) => Json(list).into_response(),
+        Err(e) => chat_error(e),
+    }
 }
 
-async fn read_session(path: &std::path::Path) -> anyhow::Result<ChatSession> {
-    let bytes = tokio::fs::read(path).await?;
-    let mut s: ChatSession = serde_json::from_slice(&bytes)?;
-    // Backfill missing message IDs for legacy sessions
-    let mut changed = false;
-    for m in s.messages.iter_mut() {
-        if m.id.is_empty() {
-            m.id = uuid::Uuid::new_v4().to_string();
-            changed = true;
-        }
+async fn chat_sessions_latest(State(state): State<SharedState>) -> impl IntoResponse {
+    crate::metrics::inc_api_request("/api/chat/sessions/latest");
+    let chats = match chat_store(&state) { Ok(c) => c, Err(r) => return r.into_response() };
+    match chats.latest().await {
+        Ok(Some(s)) => Json(s).into_response(),
+        Ok(None) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => chat_error(e),
     }
-    if changed {
-        let _ = write_session(path, &s).await;
+}
+
+async fn chat_sessions_create(State(state): State<SharedState>) -> impl IntoResponse {
+    crate::metrics::inc_api_request("/api/chat/sessions");
+    let chats = match chat_store(&state) { Ok(c) => c, Err(r) => return r.into_response() };
+    match chats.create().await {
+        Ok(s) => Json(s).into_response(),
+        Err(e) => chat_error(e),
     }
-    Ok(s)
 }
 
-async fn write_session(path: &std::path::Path, s: &ChatSession) -> anyhow::Result<()> {
-    if let Some(p) = path.parent() { tokio::fs::create_dir_all(p).await.ok(); }
-    let bytes = serde_json::to_vec_pretty(s)?;
-    tokio::fs::write(path, bytes).await?;
-    Ok(())
+async fn chat_session_get(State(state): State<SharedState>, Path(id): Path<String>) -> impl IntoResponse {
+    crate::metrics::inc_api_request("/api/chat/sessions/:id");
+    let chats = match chat_store(&state) { Ok(c) => c, Err(r) => return r.into_response() };
+    match chats.get(&id).await {
+        Ok(Some(s)) => Json(s).into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => chat_error(e),
+    }
 }
 
-async fn chat_sessions_list(State(state): State<SharedState>) -> impl IntoResponse {
-    let dir = chat_dir(&state);
-    let mut entries: Vec<SessionInfo> = vec![];
-    if let Ok(mut rd) = tokio::fs::read_dir(&dir).await {
-        while let Ok(Some(e)) = rd.next_entry().await {
-            if e.file_type().await.map(|t| t.is_file()).unwrap_or(false) {
-                let path = e.path();
-                let id = path.file_stem().and_then(|s| s.to_str()).unwrap_or("").to_string();
-                let meta = e.metadata().await.ok();
-                let mtime = meta.and_then(|m| m.modified().ok()).unwrap_or(std::time::SystemTime::UNIX_EPOCH);
-                let updated_at = chrono::DateTime::<chrono::Utc>::from(mtime).to_rfc3339();
-                let title = read_session(&path).await.ok().and_then(|s| s.messages.iter().find(|m| m.role=="user").map(|m| m.content.clone()));
-                entries.push(SessionInfo { id, updated_at, title });
-            }
-        }
+#[derive(serde::Deserialize)]
+struct AppendReq { role: String, content: String }
+async fn chat_session_append(State(state): State<SharedState>, Path(id): Path<String>, Json(req): Json<AppendReq>) -> impl IntoResponse {
+    crate::metrics::inc_api_request("/api/chat/sessions/:id/append");
+    let chats = match chat_store(&state) { Ok(c) => c, Err(r) => return r.into_response() };
+    match chats.append(&id, &req.role, &req.content).await {
+        Ok(m) => Json(m).into_response(),
+        Err(e) => chat_error(e),
     }
-    entries.sort_by(|a,b| b.updated_at.cmp(&a.updated_at));
-    Json(entries)
 }
 
-async fn chat_sessions_latest(State(state): State<SharedState>) -> impl IntoResponse {
-    let dir = chat_dir(&state);
-    if let Ok(mut rd) = tokio::fs::read_dir(&dir).await {
-        let mut best: Option<(std::time::SystemTime, std::path::PathBuf)> = None;
-        while let Ok(Some(e)) = rd.next_entry().await {
-            if e.file_type().await.map(|t| t.is_file()).unwrap_or(false) {
-                if let Ok(meta) = e.metadata().await {
-                    if let Ok(mtime) = meta.modified() {
-                        if best.as_ref().map(|(t,_)| mtime > *t).unwrap_or(true) {
-                            best = Some((mtime, e.path()));
-                        }
-                    }
-                }
-            }
-        }
-        if let Some((_, path)) = best {
-            match read_session(&path).await {
-                Ok(s) => return Json(s).into_response(),
-                Err(_) => {}
-            }
-        }
+async fn chat_session_delete(State(state): State<SharedState>, Path(id): Path<String>) -> impl IntoResponse {
+    crate::metrics::inc_api_request("/api/chat/sessions/:id");
+    let chats = match chat_store(&state) { Ok(c) => c, Err(r) => return r.into_response() };
+    match chats.delete(&id).await {
+        Ok(true) => StatusCode::OK.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => chat_error(e),
     }
-    StatusCode::NO_CONTENT.into_response()
 }
 
-async fn chat_sessions_create(State(state): State<SharedState>) -> impl IntoResponse {
-    let id = uuid::Uuid::new_v4().to_string();
-    let s = ChatSession { id: id.clone(), messages: vec![] };
-    let path = chat_dir(&state).join(format!("{}.json", id));
-    let _g = CHAT_APPEND_LOCK.lock().await;
-    if let Err(e) = write_session(&path, &s).await {
-        return (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiError { message: e.to_string() })).into_response();
+#[derive(serde::Deserialize)]
+struct EditReq {
+    content: String,
+    /// Stream a new reply to the edited message (default true).
+    regenerate: Option<bool>,
+    model: Option<String>, max_steps: Option<usize>, speak: Option<bool>,
+}
+
+/// Edit a user message: the new text becomes a sibling branch and, unless
+/// `regenerate` is false, a fresh reply is streamed from it.
+async fn chat_message_edit(State(state): State<SharedState>, AxPath((id, mid)): AxPath<(String, String)>, Json(req): Json<EditReq>) -> impl IntoResponse {
+    crate::metrics::inc_api_request("/api/chat/sessions/:id/messages/:mid/edit");
+    let chats = match chat_store(&state) { Ok(c) => c, Err(r) => return r.into_response() };
+    let regenerate = req.regenerate.unwrap_or(true);
+    let opts = if regenerate { match ReplyOpts::new(&state, req.model, req.max_steps, req.speak) { Ok(o) => Some(o), Err(r) => return r.into_response() } } else { None };
+    let edited = match chats.edit(&id, &mid, &req.content).await { Ok(m) => m, Err(e) => return chat_request_error(e) };
+    let Some(opts) = opts else { return Json(edited).into_response() };
+    let session = match chats.get(&id).await { Ok(Some(s)) => s, Ok(None) => return StatusCode::NOT_FOUND.into_response(), Err(e) => return chat_error(e) };
+    let ctx = crate::chat_context::for_session(&state, &chats, &session, opts.summarizer().as_ref()).await;
+    stream_reply(state, opts, ctx, Some((chats, id)))
+}
+
+#[derive(serde::Deserialize, Default)]
+struct RegenerateReq {
+    /// Assistant message to replace (default the head).
+    message_id: Option<String>,
+    model: Option<String>, max_steps: Option<usize>, speak: Option<bool>,
+}
+
+/// Stream a new reply alongside an existing one; the old reply stays as a sibling.
+async fn chat_session_regenerate(State(state): State<SharedState>, Path(id): Path<String>, body: Option<Json<RegenerateReq>>) -> impl IntoResponse {
+    crate::metrics::inc_api_request("/api/chat/sessions/:id/regenerate");
+    let chats = match chat_store(&state) { Ok(c) => c, Err(r) => return r.into_response() };
+    let req = body.map(|Json(b)| b).unwrap_or_default();
+    let opts = match ReplyOpts::new(&state, req.model, req.max_steps, req.speak) { Ok(o) => o, Err(r) => return r.into_response() };
+    if let Err(e) = chats.rewind(&id, req.message_id.as_deref()).await { return chat_request_error(e); }
+    let session = match chats.get(&id).await { Ok(Some(s)) => s, Ok(None) => return StatusCode::NOT_FOUND.into_response(), Err(e) => return chat_error(e) };
+    if session.messages.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(ApiError { message: "nothing to reply to".into() })).into_response();
     }
-    Json(s).into_response()
+    let ctx = crate::chat_context::for_session(&state, &chats, &session, opts.summarizer().as_ref()).await;
+    stream_reply(state, opts, ctx, Some((chats, id)))
 }
 
-async fn chat_session_get(State(state): State<SharedState>, Path(id): Path<String>) -> impl IntoResponse {
-    let path = chat_dir(&state).join(format!("{}.json", id));
-    match read_session(&path).await {
+#[derive(serde::Deserialize, Default)]
+struct ForkReq { message_id: Option<String> }
+
+async fn chat_session_fork(State(state): State<SharedState>, Path(id): Path<String>, body: Option<Json<ForkReq>>) -> impl IntoResponse {
+    crate::metrics::inc_api_request("/api/chat/sessions/:id/fork");
+    let chats = match chat_store(&state) { Ok(c) => c, Err(r) => return r.into_response() };
+    let req = body.map(|Json(b)| b).unwrap_or_default();
+    match chats.fork(&id, req.message_id.as_deref()).await {
         Ok(s) => Json(s).into_response(),
-        Err(_) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => chat_request_error(e),
     }
 }
 
 #[derive(serde::Deserialize)]
-struct AppendReq { role: String, content: String }
-async fn chat_session_append(State(state): State<SharedState>, Path(id): Path<String>, Json(req): Json<AppendReq>) -> impl IntoResponse {
-    let path = chat_dir(&state).join(format!("{}.json", id));
-    // Serialize concurrent appends per-session to avoid lost updates
-    let l = session_lock(&id).await;
-    let _g = l.lock().await;
-    let mut s = match read_session(&path).await { Ok(s) => s, Err(_) => ChatSession { id: id.clone(), messages: vec![] } };
-    let at = chrono::Utc::now().to_rfc3339();
-    let msg = ChatMessage { id: uuid::Uuid::new_v4().to_string(), role: req.role, content: req.content, at: Some(at) };
-    s.messages.push(msg.clone());
-    match write_session(&path, &s).await {
-        Ok(()) => Json(msg).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiError { message: e.to_string() })).into_response(),
+struct HeadReq { message_id: String }
+
+/// Switch the session to the branch through `message_id`.
+async fn chat_session_head(State(state): State<SharedState>, Path(id): Path<String>, Json(req): Json<HeadReq>) -> impl IntoResponse {
+    crate::metrics::inc_api_request("/api/chat/sessions/:id/head");
+    let chats = match chat_store(&state) { Ok(c) => c, Err(r) => return r.into_response() };
+    match chats.checkout(&id, &req.message_id).await {
+        Ok(s) => Json(s).into_response(),
+        Err(e) => chat_request_error(e),
     }
 }
 
-async fn chat_session_delete(State(state): State<SharedState>, Path(id): Path<String>) -> impl IntoResponse {
-    let path = chat_dir(&state).join(format!("{}.json", id));
-    match tokio::fs::remove_file(&path).await {
-        Ok(()) => StatusCode::OK.into_response(),
-        Err(e) if e.kind() == std::io::ErrorKind::NotFound => StatusCode::NOT_FOUND.into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiError { message: e.to_string() })).into_response(),
+#[derive(serde::Deserialize)]
+struct ChatSearchQ { q: String, limit: Option<i64> }
+
+async fn chat_search(State(state): State<SharedState>, axum::extract::Query(q): axum::extract::Query<ChatSearchQ>) -> impl IntoResponse {
+    crate::metrics::inc_api_request("/api/chat/search");
+    let chats = match chat_store(&state) { Ok(c) => c, Err(r) => return r.into_response() };
+    match chats.search(&q.q, q.limit.unwrap_or(20)).await {
+        Ok(hits) => Json(hits).into_response(),
+        Err(e) => chat_error(e),
     }
 }
 
@@ -1570,6 +1950,35 @@ async fn memory_search(State(state): State<SharedState>, axum::extract::Query(Se
     (StatusCode::SERVICE_UNAVAILABLE, Json(ApiError { message: "memory not initialized".into() })).into_response()
 }
 
+#[derive(serde::Deserialize)]
+struct WebSearchQ { q: Option<String>, limit: Option<u64>, engine: Option<String> }
+
+/// Proxy to the websearch MCP server's `search` tool.
+async fn web_search(State(state): State<SharedState>, axum::extract::Query(WebSearchQ { q, limit, engine }): axum::extract::Query<WebSearchQ>) -> impl IntoResponse {
+    crate::metrics::inc_api_request("/api/search");
+    let q = q.unwrap_or_default();
+    if q.trim().is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(ApiError { message: "q required".into() })).into_response();
+    }
+    let mut params = serde_json::json!({"query": q, "limit": limit.unwrap_or(10)});
+    if let Some(e) = engine { params["engine"] = serde_json::json!(e); }
+    match state.handles.tools.invoke("websearch", "search", params).await {
+        Ok(v) => {
+            if let Some(mem) = state.handles.memory.as_ref() {
+                let n = v.get("results").and_then(|r| r.as_array()).map(|a| a.len()).unwrap_or(0);
+                let _ = mem.store.append_event(None, "websearch:search", Some(&serde_json::json!({"query": q, "engine": v.get("engine"), "results": n}))).await;
+            }
+            let results: Vec<serde_json::Value> = v
+                .get("results")
+                .and_then(|r| r.as_array())
+                .map(|a| a.iter().map(|h| serde_json::json!({"title": h.get("title"), "url": h.get("url"), "snippet": h.get("snippet")})).collect())
+                .unwrap_or_default();
+            Json(serde_json::json!({"query": q, "engine": v.get("engine"), "results": results})).into_response()
+        }
+        Err(e) => (StatusCode::BAD_GATEWAY, Json(ApiError { message: e.to_string() })).into_response(),
+    }
+}
+
 async fn get_atom(State(state): State<SharedState>, Path(id): Path<i64>) -> impl IntoResponse {
     if let Some(mem) = state.handles.memory.as_ref() {
         match mem.store.get_atom_full(id).await {
@@ -1610,6 +2019,16 @@ struct ExpandResp { handle: String, chunk: String, tokens_est: usize, done: bool
 
 async fn context_expand(State(state): State<SharedState>, Json(req): Json<ExpandReq>) -> impl IntoResponse {
     let handle = req.handle.clone();
+    if handle.starts_with("map://") {
+        return match state.handles.system_map.resolve_uri(&handle) {
+            Some(v) => {
+                let chunk = serde_json::to_string_pretty(&v).unwrap_or_default();
+                let tokens_est = estimate_tokens_len(&chunk);
+                Json(ExpandResp { handle, chunk, tokens_est, done: true }).into_response()
+            }
+            None => StatusCode::NOT_FOUND.into_response(),
+        };
+    }
     if let Some(mem) = state.handles.memory.as_ref() {
         if let Some(rest) = handle.strip_prefix("expand://task/") {
             if let Ok(task_id) = rest.parse::<i64>() {
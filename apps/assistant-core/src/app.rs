@@ -19,13 +19,21 @@ pub struct AppHandles {
     pub provenance: ProvenanceEngine,
     // Future wiring placeholders
     pub memory: Option<Memory>,
+    /// Chat sessions in memory under `<home>/chats`; `None` without memory.
+    pub chats: Option<crate::chats::ChatStore>,
     pub system_map: SystemMapManager,
     pub tools: ToolsManager,
     pub mcp_client: (),
     pub scheduler: Scheduler,
     pub approval_prompt: Arc<RwLock<Option<EphemeralApproval>>>,
+    /// Waiters blocked on an ephemeral prompt (realtime and chat tool calls).
+    pub approval_decisions: crate::gatekeeper::PromptDecisions,
     pub realtime: RealtimeManager,
+    /// System prompt sections from `config/prompts`.
+    pub prompts: crate::prompt::Prompts,
     pub wake: WakeSentinel,
+    pub tts: crate::tts::TtsService,
+    pub notes: crate::notes::Notes,
     pub agents: AgentsSupervisor,
 }
 
@@ -64,13 +72,21 @@ impl AppState {
             }
         };
         // Initialize System Map using the same home dir
-        let system_map = SystemMapManager::new(&home_abs, memory.clone());
+        let scan_opts = crate::system_map::scanners::ScanOptions::from_config(config.system_map.as_ref());
+        let refresh_policy = crate::system_map::RefreshPolicy::from_config(config.system_map.as_ref());
+        let system_map = SystemMapManager::with_options(&home_abs, memory.clone(), scan_opts, refresh_policy);
         // Load or scan map; do not fail hard on errors
         let _ = system_map.load_or_scan().await;
+        system_map.spawn_refresher();
 
         // Load tool manifests
         let tools_dir = PathBuf::from("config/tools.d");
-        let tools = ToolsManager::load_from_dir(&tools_dir);
+        let installer = crate::installer::Installer::new(memory.clone(), Some(system_map.clone()));
+        installer.recover_interrupted().await;
+        let tools = ToolsManager::load_from_dir(&tools_dir)
+            .with_installer(installer)
+            .with_system_map(system_map.clone())
+            .with_config(&config);
         // Autostart MCP servers (best-effort)
         let tools_autostart = tools.clone();
         tokio::spawn(async move { tools_autostart.autostart().await; });
@@ -81,9 +97,18 @@ impl AppState {
         let scheduler = Scheduler::new(sched_cfg, home_abs.clone(), memory.clone(), tools_for_sched);
         scheduler.clone().start();
 
-        // Realtime manager (pass chats dir for context seeding)
-        let chats_dir = system_map.map_path().parent().unwrap_or(std::path::Path::new(".")).join("chats");
-        let realtime = RealtimeManager::new(tools.clone(), policy.clone(), approval_prompt.clone(), Some(chats_dir));
+        // Chats live in memory; JSON sessions from older builds are imported once.
+        // Realtime still gets the dir for recordings and memory-less logging.
+        let chats_dir = home_abs.join("chats");
+        let chats = memory.as_ref().map(|m| crate::chats::ChatStore::new(m.store.clone()).with_results_dir(chats_dir.join("tool_results")));
+        if let Some(chats) = chats.as_ref() {
+            if let Err(e) = crate::chats::import_json_dir(chats, &chats_dir).await {
+                tracing::warn!(error=%e, dir=%chats_dir.display(), "chat import failed");
+            }
+        }
+        let approval_decisions = crate::gatekeeper::PromptDecisions::default();
+        let prompts = crate::prompt::Prompts::from_config(&config).with_tools(tools.clone()).with_system_map(system_map.clone());
+        let realtime = RealtimeManager::new(tools.clone(), policy.clone(), approval_prompt.clone(), approval_decisions.clone(), Some(chats_dir)).with_memory(memory.clone()).with_prompts(prompts.clone());
         // Wake sentinel
         let vc = config.voice.clone();
         let wake_opts = WakeOptions {
@@ -92,8 +117,36 @@ impl AppState {
             vad_sensitivity: vc.as_ref().and_then(|v| v.vad_sensitivity).unwrap_or(0.5),
             min_speech_ms: vc.as_ref().and_then(|v| v.min_speech_ms).unwrap_or(400),
             refractory_ms: vc.as_ref().and_then(|v| v.refractory_ms).unwrap_or(3000) as u64,
+            engine: vc.as_ref().and_then(|v| v.wake_engine.as_deref()).and_then(crate::wake::WakeEngine::parse),
+            kws_model: vc.as_ref().and_then(|v| v.kws.as_ref()).and_then(|k| k.model_path.clone()).map(PathBuf::from)
+                .unwrap_or_else(|| home_abs.join("wake").join("kws.json")),
+            kws_sensitivity: vc.as_ref().and_then(|v| v.kws.as_ref()).and_then(|k| k.sensitivity).unwrap_or(0.5),
+            porcupine: vc.as_ref().and_then(|v| v.porcupine.clone()),
+            stt: crate::stt::SttOptions::from_config(vc.as_ref().and_then(|v| v.stt.as_ref())),
+            realtime_model: vc.as_ref().and_then(|v| v.realtime_model.clone()),
+            realtime_voice: vc.as_ref().and_then(|v| v.realtime_voice.clone()),
+            realtime_endpoint: vc.as_ref().and_then(|v| v.realtime_endpoint.clone()),
+            full_duplex: vc.as_ref().and_then(|v| v.full_duplex).unwrap_or(false),
+            aec: vc.as_ref().and_then(|v| v.aec),
+            record_sessions: vc.as_ref().and_then(|v| v.record_sessions).unwrap_or(false),
         };
         let wake = WakeSentinel::new(wake_opts);
+        // Local TTS; playback only in audio builds
+        let tts_opts = crate::tts::TtsOptions::from_config(vc.as_ref().and_then(|v| v.tts.as_ref()));
+        let tts_engine = tts_opts.build_engine();
+        #[cfg(feature = "realtime-audio")]
+        let tts_queue = tts_engine.as_ref().map(|_| crate::realtime_audio::speech_queue(24_000));
+        #[cfg(not(feature = "realtime-audio"))]
+        let tts_queue = None;
+        let tts = crate::tts::TtsService::new(tts_engine, tts_queue, tts_opts.speak_chat);
+        let note_opts = crate::notes::NoteOptions::from_config(
+            vc.as_ref().and_then(|v| v.notes.as_ref()),
+            home_abs.join("notes"),
+            crate::stt::SttOptions::from_config(vc.as_ref().and_then(|v| v.stt.as_ref())),
+            vc.as_ref().and_then(|v| v.vad_sensitivity).unwrap_or(0.5),
+        );
+        let notes = crate::notes::Notes::new(note_opts, memory.clone(), tts.clone());
+        wake.set_notes(notes.clone());
         #[cfg(feature = "realtime-audio")]
         {
             let w = wake.clone();
@@ -104,7 +157,7 @@ impl AppState {
         Arc::new(AppState {
             version: env!("CARGO_PKG_VERSION"),
             config: Arc::new(RwLock::new(config)),
-            handles: AppHandles { policy: policy.clone(), approvals, provenance, memory, system_map, tools, mcp_client: (), scheduler, approval_prompt, realtime, wake, agents: AgentsSupervisor::new() },
+            handles: AppHandles { policy: policy.clone(), approvals, provenance, memory, chats, system_map, tools, mcp_client: (), scheduler, approval_prompt, approval_decisions, realtime, prompts, wake, tts, notes, agents: AgentsSupervisor::new() },
         })
     }
 }
@@ -0,0 +1,19 @@
+use anyhow::{Context, Result};
+use assistant_core::config::Config;
+use assistant_core::realtime_gateway::{router, GatewayConfig};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let (config, _) = Config::load().context("loading config/foreman.toml")?;
+    let addr = std::env::args().nth(1)
+        .or_else(|| config.voice.as_ref().and_then(|v| v.realtime_gateway.as_ref()).and_then(|g| g.addr.clone()))
+        .unwrap_or_else(|| "127.0.0.1:7072".into());
+    let cfg = GatewayConfig::from_config(&config);
+    eprintln!(
+        "realtime-gateway listening on ws://{}/v1/realtime (llm={} model={} tts={})",
+        addr, cfg.llm_url, cfg.llm_model, cfg.tts_cmd.as_deref().unwrap_or("(none)")
+    );
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    axum::serve(listener, router(cfg)).await?;
+    Ok(())
+}
@@ -0,0 +1,279 @@
+//! Context for a chat reply, assembled on the server. The newest turns go to
+//! the model as they are, within a token budget; the turns before them are
+//! folded into a rolling summary cached on the session; and a memory pack for
+//! the latest user message (pinned and matching atoms, the task digest, the
+//! system map digest) goes in front.
+
+use crate::app::SharedState;
+use crate::chats::{self, ChatSession, ChatStore, Turn};
+use crate::memory::context_pack::{build_pack, newest_lines, tokens_est, Card};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// Atoms searched for the latest user message.
+const RELEVANT_ATOMS: i64 = 8;
+/// Pinned and important atoms considered for every reply.
+const PINNED_ATOMS: i64 = 12;
+/// Longest stretch of one turn that goes into a summary request.
+const SUMMARY_TURN_CHARS: usize = 1_000;
+
+/// `[chat]` budgets, in estimated tokens.
+#[derive(Debug, Clone, Copy)]
+pub struct ContextOpts {
+    pub history_tokens: usize,
+    pub summary_tokens: usize,
+    pub pack_tokens: usize,
+    /// A slow summary falls back to the digest instead of holding up the reply.
+    pub summary_timeout: std::time::Duration,
+}
+
+impl ContextOpts {
+    pub fn from_config(cfg: Option<&crate::config::ChatConfig>) -> Self {
+        Self {
+            history_tokens: cfg.and_then(|c| c.history_tokens).unwrap_or(6000),
+            summary_tokens: cfg.and_then(|c| c.summary_tokens).unwrap_or(400),
+            pack_tokens: cfg.and_then(|c| c.pack_tokens).unwrap_or(1024),
+            summary_timeout: std::time::Duration::from_millis(cfg.and_then(|c| c.summary_timeout_ms).unwrap_or(5000)),
+        }
+    }
+}
+
+/// Model that writes summaries. Without one (mock mode, or when the call
+/// fails) the summary is the newest lines of a transcript digest.
+pub struct Summarizer {
+    pub client: reqwest::Client,
+    pub key: String,
+    pub model: String,
+}
+
+/// What went into the context, sent to clients as the `context` event.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ContextReport {
+    /// Turns sent as they are.
+    pub kept: usize,
+    /// Turns covered by the summary.
+    pub summarized: usize,
+    /// The cached summary was still current.
+    pub summary_cached: bool,
+    pub history_tokens: usize,
+    pub summary_tokens: usize,
+    pub pack_cards: usize,
+    pub pack_tokens: usize,
+}
+
+/// Model input for one reply.
+pub struct Assembled {
+    pub messages: Vec<Value>,
+    pub report: ContextReport,
+}
+
+/// Context from a stored session's active branch. The summary is reused while
+/// it still ends where the window starts, extended when the window has moved
+/// on, and rebuilt when it belongs to another branch.
+pub async fn for_session(state: &SharedState, chats: &ChatStore, session: &ChatSession, summarizer: Option<&Summarizer>) -> Assembled {
+    let opts = ContextOpts::from_config(state.config.read().chat.as_ref());
+    let turns: Vec<Turn> = session.messages.iter().map(Into::into).collect();
+    let start = window_start(&turns, opts.history_tokens);
+    let mut report = ContextReport { kept: turns.len() - start, summarized: start, ..Default::default() };
+    let summary = match session.messages[..start].last() {
+        None => None,
+        Some(last) => {
+            let cached = session.summary.as_deref().zip(session.summary_upto.as_deref());
+            let folded = cached.and_then(|(s, upto)| session.messages[..start].iter().position(|m| m.id == upto).map(|p| (s, p + 1)));
+            match folded {
+                Some((s, from)) if from == start => {
+                    report.summary_cached = true;
+                    Some(s.to_string())
+                }
+                _ => {
+                    let (prev, from) = folded.map(|(s, from)| (Some(s), from)).unwrap_or((None, 0));
+                    let s = summarize(prev, &turns[from..start], opts.summary_tokens, opts.summary_timeout, summarizer).await;
+                    if let Err(e) = chats.set_summary(&session.id, &s, &last.id).await { tracing::warn!(error=%e, "chat context: could not cache summary"); }
+                    Some(s)
+                }
+            }
+        }
+    };
+    assemble(state, &turns[start..], summary, opts, report).await
+}
+
+/// Context from a client-supplied history. Nothing is cached, so older turns
+/// get the transcript digest rather than a model summary.
+pub async fn for_history(state: &SharedState, turns: &[Turn]) -> Assembled {
+    let opts = ContextOpts::from_config(state.config.read().chat.as_ref());
+    let start = window_start(turns, opts.history_tokens);
+    let report = ContextReport { kept: turns.len() - start, summarized: start, ..Default::default() };
+    let summary = if start == 0 { None } else { Some(digest(None, &turns[..start], opts.summary_tokens)) };
+    assemble(state, &turns[start..], summary, opts, report).await
+}
+
+async fn assemble(state: &SharedState, kept: &[Turn], summary: Option<String>, opts: ContextOpts, mut report: ContextReport) -> Assembled {
+    let prompt = state.handles.prompts.render(crate::prompt::Mode::Text);
+    let mut messages = vec![json!({"role": "system", "content": prompt.text})];
+    let latest_user = kept.iter().rev().find(|t| t.role == "user").map(|t| t.content.as_str());
+    // The system digest goes in once: in the pack unless a prompt section has it
+    let with_system = !prompt.uses("system_digest");
+    if let Some((pack, cards)) = memory_pack(state, latest_user, opts.pack_tokens, with_system).await {
+        report.pack_cards = cards;
+        report.pack_tokens = tokens_est(&pack);
+        messages.push(json!({"role": "system", "content": pack}));
+    }
+    if let Some(s) = summary.filter(|s| !s.trim().is_empty()) {
+        report.summary_tokens = tokens_est(&s);
+        messages.push(json!({"role": "system", "content": format!("Summary of the earlier conversation:\n{}", s)}));
+    }
+    let history = chats::provider_messages(kept);
+    report.history_tokens = history.iter().map(|m| tokens_est(&m.to_string())).sum();
+    messages.extend(history);
+    Assembled { messages, report }
+}
+
+fn turn_tokens(t: &Turn) -> usize {
+    let calls: usize = t.tool_calls.iter().map(|c| tokens_est(&c.name) + tokens_est(&c.arguments.to_string())).sum();
+    tokens_est(&t.content) + calls + 4
+}
+
+/// Index of the first turn sent as is: the earliest user turn from which the
+/// rest fits `budget`. Windows start at a user turn so tool calls stay with
+/// their results; the latest user turn and what follows are always kept.
+pub fn window_start(turns: &[Turn], budget: usize) -> usize {
+    let mut used = 0;
+    let mut start = None;
+    for (i, t) in turns.iter().enumerate().rev() {
+        used += turn_tokens(t);
+        if t.role != "user" { continue; }
+        if used <= budget || start.is_none() { start = Some(i); }
+        if used > budget { break; }
+    }
+    start.unwrap_or(0)
+}
+
+/// Fold `turns` into `prev` with the model, falling back to the digest when
+/// the call fails or takes longer than `timeout`.
+async fn summarize(prev: Option<&str>, turns: &[Turn], max_tokens: usize, timeout: std::time::Duration, summarizer: Option<&Summarizer>) -> String {
+    let Some(s) = summarizer else { return digest(prev, turns, max_tokens) };
+    let transcript: Vec<String> = turns.iter().filter_map(|t| line(t, SUMMARY_TURN_CHARS)).collect();
+    let words = max_tokens * 3 / 4;
+    let body = json!({"model": s.model, "messages": [
+        {"role": "system", "content": format!("You keep a running summary of a conversation between a user and their assistant. Merge the new turns into the summary. Keep facts about the user and their machine, decisions, names, paths, commands, tool results that matter, and open questions; drop pleasantries. Reply with the summary only, at most {} words.", words)},
+        {"role": "user", "content": format!("Summary so far:\n{}\n\nNew turns:\n{}", prev.unwrap_or("(none)"), transcript.join("\n"))},
+    ]});
+    let reply = async {
+        let resp = s.client.post(crate::api::openai_chat_url()).bearer_auth(&s.key).json(&body).send().await?;
+        if !resp.status().is_success() { anyhow::bail!("openai http {}", resp.status()); }
+        let v: Value = resp.json().await?;
+        Ok::<_, anyhow::Error>(v["choices"][0]["message"]["content"].as_str().unwrap_or("").trim().to_string())
+    };
+    match tokio::time::timeout(timeout, reply).await {
+        Ok(Ok(text)) if !text.is_empty() => text,
+        Ok(Ok(_)) => digest(prev, turns, max_tokens),
+        Ok(Err(e)) => {
+            tracing::warn!(error=%e, "chat context: summary failed; using digest");
+            digest(prev, turns, max_tokens)
+        }
+        Err(_) => {
+            tracing::warn!(?timeout, "chat context: summary timed out; using digest");
+            digest(prev, turns, max_tokens)
+        }
+    }
+}
+
+/// One line per turn after `prev`, newest kept within `max_tokens`.
+fn digest(prev: Option<&str>, turns: &[Turn], max_tokens: usize) -> String {
+    let mut lines: Vec<String> = prev.map(|p| p.lines().map(String::from).collect()).unwrap_or_default();
+    lines.extend(turns.iter().filter_map(|t| line(t, 200)));
+    newest_lines(&lines.join("\n"), max_tokens).unwrap_or_default()
+}
+
+fn line(t: &Turn, max_chars: usize) -> Option<String> {
+    let text: String = t.content.split_whitespace().collect::<Vec<_>>().join(" ");
+    let text = if text.chars().count() > max_chars { format!("{}…", text.chars().take(max_chars).collect::<String>()) } else { text };
+    if !t.tool_calls.is_empty() {
+        let names: Vec<&str> = t.tool_calls.iter().map(|c| c.name.as_str()).collect();
+        return Some(format!("assistant called {}{}", names.join(", "), if text.is_empty() { String::new() } else { format!(": {}", text) }));
+    }
+    if text.is_empty() { return None; }
+    Some(format!("{}: {}", t.role, text))
+}
+
+/// The memory pack as a system message, and how many atoms it carries.
+async fn memory_pack(state: &SharedState, query: Option<&str>, budget: usize, with_system: bool) -> Option<(String, usize)> {
+    if budget == 0 { return None; }
+    let system = if with_system { truncate(&state.handles.system_map.get_digest(), budget * 2) } else { String::new() };
+    let (mut cards, mut task_id): (Vec<Card>, Option<i64>) = (vec![], None);
+    if let Some(mem) = state.handles.memory.as_ref() {
+        // Atoms matching the latest user message first, then pinned and important ones
+        let terms = query.map(chats::fts_terms).unwrap_or_default();
+        if !terms.is_empty() {
+            let hits = mem.store.search_atoms(&terms.join(" OR "), None, RELEVANT_ATOMS).await.unwrap_or_default();
+            for h in hits {
+                if let Ok(Some(a)) = mem.store.get_atom_full(h.atom_id).await {
+                    task_id.get_or_insert(a.task_id);
+                    cards.push(card(a.id, a.text, a.tokens_est, a.importance, a.pinned));
+                }
+            }
+        }
+        for a in mem.store.list_cards(None, PINNED_ATOMS).await.unwrap_or_default() {
+            if cards.iter().any(|c| c.atom_id == a.id) { continue; }
+            task_id.get_or_insert(a.task_id);
+            cards.push(card(a.id, a.text, a.tokens_est, a.importance, a.pinned));
+        }
+    }
+    let task = match (state.handles.memory.as_ref(), task_id) {
+        (Some(mem), Some(id)) => mem.store.get_task_digest(id).await.ok().flatten(),
+        _ => None,
+    };
+    let spent = tokens_est(&system) + task.as_deref().map(tokens_est).unwrap_or(0);
+    let pack = build_pack(&system, task.as_deref(), None, cards, budget.saturating_sub(spent), vec![]);
+    if pack.system_digest.trim().is_empty() && pack.task_digest.is_none() && pack.cards.is_empty() { return None; }
+    let mut text = String::from("Memory context (from Foreman's notes; may be incomplete or out of date):");
+    if !pack.system_digest.trim().is_empty() { text.push_str(&format!("\nSystem: {}", pack.system_digest.trim())); }
+    if let Some(t) = pack.task_digest.as_deref() { text.push_str(&format!("\nTask: {}", t.trim())); }
+    if !pack.cards.is_empty() {
+        text.push_str("\nNotes:");
+        for c in &pack.cards { text.push_str(&format!("\n- {}", c.text.trim())); }
+    }
+    Some((text, pack.cards.len()))
+}
+
+fn card(atom_id: i64, text: String, tokens: i64, importance: i64, pinned: bool) -> Card {
+    let tokens_est = if tokens > 0 { tokens as usize } else { tokens_est(&text) };
+    Card { atom_id, text, tokens_est, importance: importance as i32, pinned }
+}
+
+/// At most about `max_tokens` of `text`, cut at a char boundary.
+fn truncate(text: &str, max_tokens: usize) -> String {
+    let max = max_tokens * 4;
+    if text.len() <= max { return text.to_string(); }
+    let mut end = max;
+    while !text.is_char_boundary(end) { end -= 1; }
+    format!("{}…", &text[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(role: &str, words: usize) -> Turn {
+        Turn { role: role.into(), content: "word ".repeat(words), ..Default::default() }
+    }
+
+    #[test]
+    fn window_starts_at_a_user_turn_within_budget() {
+        // ~25 tokens per 20-word turn plus overhead
+        let turns = vec![turn("user", 20), turn("assistant", 20), turn("user", 20), turn("assistant", 20), turn("user", 20)];
+        assert_eq!(window_start(&turns, 1000), 0);
+        assert_eq!(window_start(&turns, 90), 2);
+        // The latest question is kept even when it alone is over budget
+        assert_eq!(window_start(&turns, 5), 4);
+        assert_eq!(window_start(&[turn("assistant", 5)], 100), 0);
+    }
+
+    #[test]
+    fn digest_keeps_the_newest_lines() {
+        let turns = vec![turn("user", 3), Turn { tool_calls: vec![chats::ToolCallRecord { id: "a".into(), name: "fs_list".into(), arguments: json!({}) }], ..turn("assistant", 0) }];
+        let d = digest(Some("user: earlier"), &turns, 100);
+        assert_eq!(d, "user: earlier\nuser: word word word\nassistant called fs_list");
+        assert_eq!(digest(Some("user: earlier"), &turns, 6), "assistant called fs_list");
+    }
+}
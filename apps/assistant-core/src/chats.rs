@@ -0,0 +1,560 @@
+//! Chat sessions in SQLite. Messages form a tree (`parent_id`); the session's
+//! head is the leaf of the branch it shows. Editing a user message or
+//! regenerating a reply adds a sibling rather than overwriting history.
+//! Tool use is kept as turns of its own: an assistant message carries the calls
+//! it made and each result follows as a `tool` message, so a reloaded session
+//! replays them to the model.
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Utc};
+use foreman_memory as fm;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+const TITLE_CHARS: usize = 60;
+/// Tool results longer than this are cut in the session; the full result is
+/// saved as an artifact.
+pub const TOOL_RESULT_CHARS: usize = 8_000;
+
+/// A request naming something the session doesn't have, or asking for what
+/// it can't do. Other errors from the store are the server's.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChatError {
+    NotFound(&'static str),
+    BadRequest(&'static str),
+}
+
+impl std::fmt::Display for ChatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self { ChatError::NotFound(m) | ChatError::BadRequest(m) => f.write_str(m) }
+    }
+}
+
+impl std::error::Error for ChatError {}
+/// Task that owns tool-result artifacts.
+const TOOL_RESULTS_TASK: &str = "Chat tool results";
+
+/// One tool call made by an assistant turn.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCallRecord {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub arguments: Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatMessage {
+    pub id: String,
+    pub role: String,
+    pub content: String,
+    pub at: DateTime<Utc>,
+    pub parent_id: Option<String>,
+    /// Ids of every alternative at this point (this one included), oldest first;
+    /// empty when there is only one.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub siblings: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCallRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approval_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artifact_id: Option<i64>,
+}
+
+impl From<fm::ChatMessageRow> for ChatMessage {
+    fn from(m: fm::ChatMessageRow) -> Self {
+        let tool_calls = m.tool_calls.as_deref().and_then(|s| serde_json::from_str(s).ok()).unwrap_or_default();
+        Self {
+            id: m.id, role: m.role, content: m.content, at: m.at, parent_id: m.parent_id, siblings: vec![],
+            tool_calls, tool_call_id: m.tool_call_id, tool_name: m.tool_name, approval_id: m.approval_id, artifact_id: m.artifact_id,
+        }
+    }
+}
+
+/// A message as sent to the model: what clients post to `/api/chat/stream`
+/// and `/api/chat/complete`, and what a stored branch reduces to.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Turn {
+    pub role: String,
+    #[serde(default)]
+    pub content: String,
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCallRecord>,
+    #[serde(default)]
+    pub tool_call_id: Option<String>,
+}
+
+impl From<&ChatMessage> for Turn {
+    fn from(m: &ChatMessage) -> Self {
+        Self { role: m.role.clone(), content: m.content.clone(), tool_calls: m.tool_calls.clone(), tool_call_id: m.tool_call_id.clone() }
+    }
+}
+
+/// A session with its active branch, root first.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatSession {
+    pub id: String,
+    pub title: Option<String>,
+    pub head_id: Option<String>,
+    pub forked_from: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Rolling summary of the turns that no longer fit the model context, and
+    /// the last message it covers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary_upto: Option<String>,
+    pub messages: Vec<ChatMessage>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionInfo {
+    pub id: String,
+    pub updated_at: DateTime<Utc>,
+    pub title: Option<String>,
+    pub forked_from: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct ChatStore {
+    store: fm::MemoryStore,
+    results_dir: Option<PathBuf>,
+}
+
+impl ChatStore {
+    pub fn new(store: fm::MemoryStore) -> Self { Self { store, results_dir: None } }
+
+    /// Where full tool results go when they are too long for the session.
+    /// Without it, long results are only cut.
+    pub fn with_results_dir(mut self, dir: PathBuf) -> Self { self.results_dir = Some(dir); self }
+
+    pub async fn create(&self) -> Result<ChatSession> {
+        let id = Uuid::new_v4().to_string();
+        self.store.create_chat_session(&id, None, None).await?;
+        self.get(&id).await?.ok_or_else(|| anyhow!("session vanished"))
+    }
+
+    pub async fn get(&self, id: &str) -> Result<Option<ChatSession>> {
+        let Some(s) = self.store.get_chat_session(id).await? else { return Ok(None) };
+        let all = self.store.get_chat_messages(id).await?;
+        let messages = branch(&all, s.head_id.as_deref());
+        Ok(Some(ChatSession {
+            id: s.id, title: s.title, head_id: s.head_id, forked_from: s.forked_from, created_at: s.created_at, updated_at: s.updated_at,
+            summary: s.summary, summary_upto: s.summary_upto, messages,
+        }))
+    }
+
+    /// Most recently updated first.
+    pub async fn list(&self, limit: i64) -> Result<Vec<SessionInfo>> {
+        Ok(self.store.list_chat_sessions(limit).await?.into_iter()
+            .map(|s| SessionInfo { id: s.id, updated_at: s.updated_at, title: s.title, forked_from: s.forked_from })
+            .collect())
+    }
+
+    pub async fn latest(&self) -> Result<Option<ChatSession>> {
+        match self.store.list_chat_sessions(1).await?.into_iter().next() {
+            Some(s) => self.get(&s.id).await,
+            None => Ok(None),
+        }
+    }
+
+    pub async fn delete(&self, id: &str) -> Result<bool> { self.store.delete_chat_session(id).await }
+
+    /// Add a message under the head, creating the session if needed. The first
+    /// user message titles an untitled session.
+    pub async fn append(&self, id: &str, role: &str, content: &str) -> Result<ChatMessage> {
+        let session = match self.store.get_chat_session(id).await? {
+            Some(s) => s,
+            None => {
+                self.store.create_chat_session(id, None, None).await?;
+                self.store.get_chat_session(id).await?.ok_or_else(|| anyhow!("session vanished"))?
+            }
+        };
+        let mid = Uuid::new_v4().to_string();
+        let row = self.store.append_chat_message_at_head(id, &mid, role, content).await?;
+        if session.title.is_none() && role == "user" {
+            if let Some(title) = title_from(content) { self.store.set_chat_title(id, &title).await?; }
+        }
+        Ok(row.into())
+    }
+
+    pub async fn set_content(&self, message_id: &str, content: &str) -> Result<()> {
+        self.store.set_chat_message_content(message_id, content).await
+    }
+
+    pub async fn set_summary(&self, id: &str, summary: &str, upto: &str) -> Result<()> {
+        self.store.set_chat_summary(id, summary, upto).await
+    }
+
+    /// Record the tool calls an assistant message made.
+    pub async fn set_tool_calls(&self, message_id: &str, calls: &[ToolCallRecord]) -> Result<()> {
+        let json = serde_json::to_string(calls)?;
+        self.store.set_chat_message_tools(message_id, Some(&json), None, None, None, None).await
+    }
+
+    /// Add the result of `call` under the head as a `tool` message. Results
+    /// over [`TOOL_RESULT_CHARS`] are cut, with the whole result saved as an
+    /// artifact when a results dir is set.
+    pub async fn append_tool_result(&self, id: &str, call: &ToolCallRecord, result: &Value, approval_id: Option<&str>) -> Result<ChatMessage> {
+        let full = serde_json::to_string(result)?;
+        let mid = Uuid::new_v4().to_string();
+        let (content, artifact_id) = if full.chars().count() <= TOOL_RESULT_CHARS { (full, None) } else {
+            let artifact_id = match self.save_tool_result(&mid, &full).await {
+                Ok(a) => a,
+                Err(e) => { tracing::warn!(error=%e, "chat: could not save tool result"); None }
+            };
+            let note = match artifact_id {
+                Some(a) => format!("… [cut at {} of {} chars; full result in artifact {}]", TOOL_RESULT_CHARS, full.chars().count(), a),
+                None => format!("… [cut at {} of {} chars]", TOOL_RESULT_CHARS, full.chars().count()),
+            };
+            (format!("{}{}", truncate_chars(&full, TOOL_RESULT_CHARS), note), artifact_id)
+        };
+        let row = self.store.append_chat_message_at_head(id, &mid, "tool", &content).await?;
+        self.store.set_chat_message_tools(&mid, None, Some(&call.id), Some(&call.name), approval_id, artifact_id).await?;
+        Ok(ChatMessage { tool_call_id: Some(call.id.clone()), tool_name: Some(call.name.clone()), approval_id: approval_id.map(String::from), artifact_id, ..row.into() })
+    }
+
+    async fn save_tool_result(&self, message_id: &str, full: &str) -> Result<Option<i64>> {
+        let Some(dir) = self.results_dir.as_ref() else { return Ok(None) };
+        tokio::fs::create_dir_all(dir).await?;
+        let path = dir.join(format!("{}.json", message_id));
+        tokio::fs::write(&path, full).await?;
+        let task = match self.store.list_tasks().await?.into_iter().find(|t| t.title == TOOL_RESULTS_TASK) {
+            Some(t) => t,
+            None => self.store.create_task(TOOL_RESULTS_TASK, "open", Some("chat")).await?,
+        };
+        Ok(Some(self.store.create_artifact(task.id, &path, Some("application/json"), None).await?))
+    }
+
+    /// Replace a user message with a new sibling carrying `content`; the head
+    /// moves to it, leaving the old branch reachable.
+    pub async fn edit(&self, id: &str, message_id: &str, content: &str) -> Result<ChatMessage> {
+        let old = self.message(id, message_id).await?;
+        if old.role != "user" { return Err(ChatError::BadRequest("only user messages can be edited").into()); }
+        let mid = Uuid::new_v4().to_string();
+        let row = self.store.append_chat_message(id, &mid, old.parent_id.as_deref(), "user", content, None).await?;
+        Ok(row.into())
+    }
+
+    /// Move the head back to where an assistant reply was requested, so the next
+    /// reply becomes its sibling. `message_id` defaults to the head; a user
+    /// message at the head is left alone. A reply that used tools is rewound
+    /// whole, tool turns included. Returns the new head.
+    pub async fn rewind(&self, id: &str, message_id: Option<&str>) -> Result<Option<String>> {
+        let session = self.store.get_chat_session(id).await?.ok_or(ChatError::NotFound("no such session"))?;
+        let Some(target) = message_id.map(String::from).or(session.head_id) else { return Ok(None) };
+        let all = self.store.get_chat_messages(id).await?;
+        let mut m = all.iter().find(|m| m.id == target).ok_or(ChatError::NotFound("message not in session"))?;
+        while m.role == "assistant" || m.role == "tool" {
+            match m.parent_id.as_deref().and_then(|p| all.iter().find(|o| o.id == p)) {
+                Some(parent) => m = parent,
+                None => {
+                    self.store.set_chat_head(id, None).await?;
+                    return Ok(None);
+                }
+            }
+        }
+        let head = Some(m.id.clone());
+        self.store.set_chat_head(id, head.as_deref()).await?;
+        Ok(head)
+    }
+
+    /// Switch to the branch through `message_id`, following its newest replies.
+    pub async fn checkout(&self, id: &str, message_id: &str) -> Result<ChatSession> {
+        let all = self.store.get_chat_messages(id).await?;
+        if !all.iter().any(|m| m.id == message_id) { return Err(ChatError::NotFound("message not in session").into()); }
+        let mut leaf = message_id.to_string();
+        while let Some(child) = all.iter().rev().find(|m| m.parent_id.as_deref() == Some(leaf.as_str())) {
+            leaf = child.id.clone();
+        }
+        self.store.set_chat_head(id, Some(&leaf)).await?;
+        Ok(self.get(id).await?.ok_or(ChatError::NotFound("no such session"))?)
+    }
+
+    /// Copy the active branch, up to `message_id` (default the head), into a new
+    /// session.
+    pub async fn fork(&self, id: &str, message_id: Option<&str>) -> Result<ChatSession> {
+        let src = self.get(id).await?.ok_or(ChatError::NotFound("no such session"))?;
+        let upto = match message_id {
+            Some(mid) => src.messages.iter().position(|m| m.id == mid).ok_or(ChatError::NotFound("message not on the active branch"))? + 1,
+            None => src.messages.len(),
+        };
+        let new_id = Uuid::new_v4().to_string();
+        self.store.create_chat_session(&new_id, src.title.as_deref(), Some(id)).await?;
+        let mut parent: Option<String> = None;
+        for m in &src.messages[..upto] {
+            let mid = Uuid::new_v4().to_string();
+            self.store.append_chat_message(&new_id, &mid, parent.as_deref(), &m.role, &m.content, Some(m.at)).await?;
+            if !m.tool_calls.is_empty() || m.tool_call_id.is_some() {
+                let calls = if m.tool_calls.is_empty() { None } else { Some(serde_json::to_string(&m.tool_calls)?) };
+                self.store.set_chat_message_tools(&mid, calls.as_deref(), m.tool_call_id.as_deref(), m.tool_name.as_deref(), m.approval_id.as_deref(), m.artifact_id).await?;
+            }
+            parent = Some(mid);
+        }
+        self.store.set_chat_head(&new_id, parent.as_deref()).await?;
+        self.get(&new_id).await?.ok_or_else(|| anyhow!("session vanished"))
+    }
+
+    /// Search every message of every session. Words are matched as terms (porter
+    /// stemmed), so user input never reaches FTS syntax.
+    pub async fn search(&self, q: &str, limit: i64) -> Result<Vec<fm::ChatSearchHit>> {
+        let terms = fts_terms(q);
+        if terms.is_empty() { return Ok(vec![]); }
+        self.store.search_chat_messages(&terms.join(" "), limit).await
+    }
+
+    async fn message(&self, id: &str, message_id: &str) -> Result<fm::ChatMessageRow> {
+        self.store.get_chat_messages(id).await?.into_iter().find(|m| m.id == message_id)
+            .ok_or_else(|| ChatError::NotFound("message not in session").into())
+    }
+}
+
+// Realtime transcript logging
+#[cfg_attr(not(feature = "realtime"), allow(dead_code))]
+impl ChatStore {
+    /// Add to the most recent session (creating one if there is none). With
+    /// `coalesce`, streamed pieces extend the head when it has the same role.
+    pub async fn append_to_latest(&self, role: &str, text: &str, coalesce: bool) -> Result<()> {
+        let id = match self.store.list_chat_sessions(1).await?.into_iter().next() {
+            Some(s) => s.id,
+            None => self.create().await?.id,
+        };
+        if !coalesce || !self.store.extend_chat_head(&id, role, text).await? {
+            self.append(&id, role, text).await?;
+        }
+        Ok(())
+    }
+
+    /// Compact digest of the latest session's last turns, for seeding a call.
+    pub async fn recent_context(&self) -> Option<String> {
+        let s = self.latest().await.ok()??;
+        let take = s.messages.len().saturating_sub(8);
+        let lines: Vec<String> = s.messages.into_iter().skip(take)
+            .filter(|m| !m.content.is_empty() && m.role != "tool")
+            .map(|m| {
+                let mut content = m.content;
+                if content.len() > 200 { content = format!("{}…", truncate_chars(&content, 200)); }
+                format!("{}: {}", m.role, content)
+            })
+            .collect();
+        if lines.is_empty() { None } else { Some(lines.join("\n")) }
+    }
+
+    /// Last user message of the latest session.
+    pub async fn last_user(&self) -> Option<String> {
+        let s = self.latest().await.ok()??;
+        s.messages.into_iter().rev().find(|m| m.role == "user").map(|m| m.content)
+    }
+}
+
+/// Walk from `head` to the root and return the branch root first, with sibling
+/// ids filled in where a message has alternatives.
+fn branch(all: &[fm::ChatMessageRow], head: Option<&str>) -> Vec<ChatMessage> {
+    let by_id: HashMap<&str, &fm::ChatMessageRow> = all.iter().map(|m| (m.id.as_str(), m)).collect();
+    let mut out = vec![];
+    let mut cur = head;
+    while let Some(m) = cur.and_then(|id| by_id.get(id)) {
+        let mut msg = ChatMessage::from((*m).clone());
+        let siblings: Vec<String> = all.iter().filter(|o| o.parent_id == m.parent_id).map(|o| o.id.clone()).collect();
+        if siblings.len() > 1 { msg.siblings = siblings; }
+        out.push(msg);
+        cur = m.parent_id.as_deref();
+        if out.len() > all.len() { break; }
+    }
+    out.reverse();
+    out
+}
+
+/// The words of `q` as quoted FTS5 terms, so user input never reaches query
+/// syntax.
+pub fn fts_terms(q: &str) -> Vec<String> {
+    q.split_whitespace()
+        .map(|w| w.chars().filter(|c| c.is_alphanumeric() || *c == '_' || *c == '-').collect::<String>())
+        .filter(|w| !w.is_empty())
+        .map(|w| format!("\"{}\"", w))
+        .collect()
+}
+
+/// Chat-completions messages for `turns`. Assistant turns keep their tool calls
+/// and tool turns their call id, so the model sees what it already did. Calls
+/// left without a result (an interrupted reply) and results without their call
+/// are dropped, as are empty placeholders.
+pub fn provider_messages(turns: &[Turn]) -> Vec<Value> {
+    let mut out = vec![];
+    let mut i = 0;
+    while i < turns.len() {
+        let t = &turns[i];
+        if t.role == "tool" { i += 1; continue; }
+        if t.tool_calls.is_empty() {
+            if !t.content.is_empty() { out.push(serde_json::json!({"role": t.role, "content": t.content})); }
+            i += 1;
+            continue;
+        }
+        // The results that follow this call turn
+        let mut j = i + 1;
+        while j < turns.len() && turns[j].role == "tool" { j += 1; }
+        let results = &turns[i + 1..j];
+        let answered: HashSet<&str> = results.iter().filter_map(|r| r.tool_call_id.as_deref()).collect();
+        let calls: Vec<Value> = t.tool_calls.iter().filter(|c| answered.contains(c.id.as_str()))
+            .map(|c| serde_json::json!({"id": c.id, "type": "function", "function": {"name": c.name, "arguments": c.arguments.to_string()}}))
+            .collect();
+        if calls.is_empty() {
+            if !t.content.is_empty() { out.push(serde_json::json!({"role": "assistant", "content": t.content})); }
+        } else {
+            let content = if t.content.is_empty() { Value::Null } else { Value::String(t.content.clone()) };
+            out.push(serde_json::json!({"role": "assistant", "content": content, "tool_calls": calls}));
+            let made: HashSet<&str> = t.tool_calls.iter().map(|c| c.id.as_str()).collect();
+            for r in results {
+                if let Some(cid) = r.tool_call_id.as_deref().filter(|c| made.contains(c)) {
+                    out.push(serde_json::json!({"role": "tool", "tool_call_id": cid, "content": r.content}));
+                }
+            }
+        }
+        i = j;
+    }
+    out
+}
+
+/// Session title from the opening user message: first line, whitespace
+/// collapsed, cut at a word boundary.
+pub fn title_from(text: &str) -> Option<String> {
+    let line = text.lines().map(str::trim).find(|l| !l.is_empty())?;
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let mut title = String::new();
+    for w in words {
+        if title.chars().count() + w.chars().count() + 1 > TITLE_CHARS {
+            if title.is_empty() { title = truncate_chars(w, TITLE_CHARS); }
+            title.push('…');
+            break;
+        }
+        if !title.is_empty() { title.push(' '); }
+        title.push_str(w);
+    }
+    Some(title)
+}
+
+fn truncate_chars(s: &str, n: usize) -> String { s.chars().take(n).collect() }
+
+/// Legacy `storage/chats/<id>.json` file.
+#[derive(Deserialize)]
+struct LegacySession {
+    #[serde(default)]
+    id: String,
+    #[serde(default)]
+    messages: Vec<LegacyMessage>,
+}
+
+#[derive(Deserialize)]
+struct LegacyMessage {
+    #[serde(default)]
+    id: String,
+    role: String,
+    #[serde(default)]
+    content: String,
+    at: Option<String>,
+}
+
+/// One-time import of the JSON chat files in `dir`. Each file becomes a linear
+/// session and is then moved to `dir/imported/`; files that fail to parse are
+/// left in place. Returns how many sessions were imported.
+pub async fn import_json_dir(chats: &ChatStore, dir: &Path) -> Result<usize> {
+    let mut rd = match tokio::fs::read_dir(dir).await {
+        Ok(rd) => rd,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+    let done = dir.join("imported");
+    let mut files = vec![];
+    while let Some(e) = rd.next_entry().await? {
+        let path = e.path();
+        if e.file_type().await?.is_file() && path.extension().and_then(|s| s.to_str()) == Some("json") {
+            let mtime = e.metadata().await?.modified().map(DateTime::<Utc>::from).unwrap_or_else(|_| Utc::now());
+            files.push((mtime, path));
+        }
+    }
+    // Oldest first so the most recent file ends up as the latest session
+    files.sort();
+    let mut imported = 0;
+    for (mtime, path) in files {
+        match import_file(chats, &path, mtime).await {
+            Ok(true) => imported += 1,
+            Ok(false) => {}
+            Err(e) => { tracing::warn!(error=%e, path=%path.display(), "chat import failed"); continue; }
+        }
+        tokio::fs::create_dir_all(&done).await?;
+        if let Some(name) = path.file_name() { tokio::fs::rename(&path, done.join(name)).await?; }
+    }
+    if imported > 0 { tracing::info!(imported, dir=%dir.display(), "imported chat sessions"); }
+    Ok(imported)
+}
+
+/// Returns false when the session is already in the database.
+async fn import_file(chats: &ChatStore, path: &Path, mtime: DateTime<Utc>) -> Result<bool> {
+    let bytes = tokio::fs::read(path).await?;
+    let legacy: LegacySession = serde_json::from_slice(&bytes)?;
+    let id = if legacy.id.is_empty() { path.file_stem().and_then(|s| s.to_str()).unwrap_or_default().to_string() } else { legacy.id };
+    if id.is_empty() { bail!("session has no id"); }
+    if chats.store.get_chat_session(&id).await?.is_some() { return Ok(false); }
+    let title = legacy.messages.iter().find(|m| m.role == "user").and_then(|m| title_from(&m.content));
+    chats.store.create_chat_session(&id, title.as_deref(), None).await?;
+    let mut parent: Option<String> = None;
+    for m in legacy.messages {
+        let mid = if m.id.is_empty() { Uuid::new_v4().to_string() } else { m.id };
+        let at = m.at.as_deref().and_then(|s| DateTime::parse_from_rfc3339(s).ok()).map(|t| t.with_timezone(&Utc)).unwrap_or(mtime);
+        if let Err(e) = chats.store.append_chat_message(&id, &mid, parent.as_deref(), &m.role, &m.content, Some(at)).await {
+            // Leave no half-imported session behind; the file stays for another try
+            let _ = chats.store.delete_chat_session(&id).await;
+            return Err(e);
+        }
+        parent = Some(mid);
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn titles_are_short_single_lines() {
+        assert_eq!(title_from("\n  How do I   install ripgrep?\nThanks").as_deref(), Some("How do I install ripgrep?"));
+        let long = "word ".repeat(30);
+        let t = title_from(&long).unwrap();
+        assert!(t.ends_with('…') && t.chars().count() <= TITLE_CHARS + 1, "{}", t);
+        assert_eq!(title_from(&"x".repeat(100)).unwrap().chars().count(), TITLE_CHARS + 1);
+        assert_eq!(title_from("  \n "), None);
+    }
+
+    #[test]
+    fn tool_turns_replay_only_when_answered() {
+        let call = |id: &str| ToolCallRecord { id: id.into(), name: "fs_list".into(), arguments: serde_json::json!({"path": "/tmp"}) };
+        let turn = |role: &str, content: &str| Turn { role: role.into(), content: content.into(), ..Default::default() };
+        let result = |id: &str| Turn { tool_call_id: Some(id.into()), ..turn("tool", "{\"entries\":[]}") };
+        let turns = vec![
+            turn("user", "What's in /tmp?"),
+            Turn { tool_calls: vec![call("a"), call("b")], ..turn("assistant", "") },
+            result("a"),
+            result("stray"),
+            turn("assistant", "Nothing."),
+            Turn { tool_calls: vec![call("c")], ..turn("assistant", "Looking again") },
+        ];
+        let out = provider_messages(&turns);
+        assert_eq!(out.len(), 5, "{:#?}", out);
+        assert_eq!(out[1]["content"], Value::Null);
+        assert_eq!(out[1]["tool_calls"].as_array().unwrap().len(), 1);
+        assert_eq!(out[1]["tool_calls"][0]["function"]["arguments"], "{\"path\":\"/tmp\"}");
+        assert_eq!(out[2], serde_json::json!({"role": "tool", "tool_call_id": "a", "content": "{\"entries\":[]}"}));
+        assert_eq!(out[3]["content"], "Nothing.");
+        // An unanswered call turn keeps its text only
+        assert_eq!(out[4], serde_json::json!({"role": "assistant", "content": "Looking again"}));
+    }
+}
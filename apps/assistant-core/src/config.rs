@@ -1,6 +1,12 @@
 use serde::{Deserialize, Serialize};
 use std::{env, fs, path::PathBuf};
 
+/// `[games]`: ROM folder, Steam paths and the emulator registry, shared with the games server.
+pub use mcp_games::GamesConfig;
+
+/// `[fs]`: sandbox roots and read cap, shared with the fs server.
+pub use mcp_fs::FsConfig;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VoiceConfig {
     pub wake_phrase: Option<String>,
@@ -13,9 +19,14 @@ pub struct VoiceConfig {
     #[serde(default)]
     pub refractory_ms: Option<u32>,
     #[serde(default)]
-    pub stt: serde_json::Value,
+    pub stt: Option<SttConfig>,
+    #[serde(default)]
+    pub tts: Option<TtsConfig>,
+    /// Wake engine: "kws" (built-in keyword spotter), "porcupine" or "stt". Unset picks kws once enrolled.
     #[serde(default)]
-    pub tts: serde_json::Value,
+    pub wake_engine: Option<String>,
+    #[serde(default)]
+    pub kws: Option<KwsConfig>,
     #[serde(default)]
     pub porcupine: Option<PorcupineConfig>,
     #[serde(default)]
@@ -24,6 +35,59 @@ pub struct VoiceConfig {
     pub realtime_model: Option<String>,
     #[serde(default)]
     pub realtime_voice: Option<String>,
+    /// The local `realtime-gateway` binary.
+    #[serde(default)]
+    pub realtime_gateway: Option<RealtimeGatewayConfig>,
+    /// Keep the mic open while the assistant speaks (echo-cancelled) so the user can barge in.
+    #[serde(default)]
+    pub full_duplex: Option<bool>,
+    /// Echo cancellation in full-duplex mode (default on; disable for headsets).
+    #[serde(default)]
+    pub aec: Option<bool>,
+    /// Record realtime sessions to `storage/realtime_sessions/` for replay.
+    #[serde(default)]
+    pub record_sessions: Option<bool>,
+    /// Voice notes.
+    #[serde(default)]
+    pub notes: Option<NotesConfig>,
+}
+
+/// `[voice] notes = { ... }`: dictated voice notes.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct NotesConfig {
+    /// Task title for notes that don't name one (default "Voice notes").
+    pub task: Option<String>,
+    /// Recording directory (default `<home>/notes`).
+    pub dir: Option<String>,
+    /// Trailing quiet that ends a dictation (default 1500).
+    pub silence_ms: Option<u32>,
+    /// Longest dictation (default 120).
+    pub max_seconds: Option<u32>,
+    /// Give up when nothing is said for this long (default 4000).
+    pub lead_in_ms: Option<u32>,
+    /// Listen for note commands after the wake word (default false).
+    pub wake_commands: Option<bool>,
+}
+
+/// `[voice.realtime_gateway]`: model and turn-taking for the local gateway; its
+/// STT and TTS come from `[voice] stt` and `tts`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct RealtimeGatewayConfig {
+    /// Listen address (default 127.0.0.1:7072).
+    pub addr: Option<String>,
+    /// OpenAI-compatible chat completions URL (default the core chat provider
+    /// when `OPENAI_BASE_URL` is set, else Ollama on localhost).
+    pub llm_url: Option<String>,
+    /// Default `OPENAI_MODEL` when following the core provider, else llama3.1.
+    pub llm_model: Option<String>,
+    /// Env var name holding the API key, for endpoints that need one.
+    pub llm_api_key_env: Option<String>,
+    /// Sample rate of incoming PCM16 (default 16000).
+    pub in_sr: Option<u32>,
+    /// Silence that ends a user turn (default 600).
+    pub silence_ms: Option<u32>,
+    /// Server VAD, 0.0..1.0 (default 0.5).
+    pub vad_sensitivity: Option<f32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,6 +104,42 @@ pub struct PorcupineConfig {
     pub access_key_env: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct KwsConfig {
+    /// Enrolled model file (default `<home>/wake/kws.json`).
+    pub model_path: Option<String>,
+    /// 0.0..1.0; higher accepts looser matches (default 0.5).
+    pub sensitivity: Option<f32>,
+}
+
+/// `[voice] stt = { ... }`: where the local speech recognizer listens.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SttConfig {
+    /// Streaming WS endpoint (default `LOCAL_STT_STREAM_ENDPOINT`, then the voice daemon on 7071).
+    pub stream_endpoint: Option<String>,
+    /// Whole-utterance HTTP endpoint (default `LOCAL_STT_ENDPOINT`).
+    pub endpoint: Option<String>,
+    /// `false` skips the streaming client and always transcribes whole utterances.
+    pub streaming: Option<bool>,
+}
+
+/// `[voice] tts = { ... }`: local speech synthesis.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct TtsConfig {
+    /// "piper", "command", "wav" or "none".
+    pub engine: Option<String>,
+    /// Piper model (`--model`).
+    pub voice: Option<String>,
+    /// Command line for piper/command engines; reads text on stdin, writes raw PCM16.
+    pub command: Option<String>,
+    /// Output rate of the command (default 22050).
+    pub sample_rate: Option<u32>,
+    /// Clip directory for the wav engine.
+    pub dir: Option<String>,
+    /// Speak `/api/chat/stream` replies by default (default false).
+    pub speak_chat: Option<bool>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SchedulesConfig {
     pub arxiv_brief: Option<String>,
@@ -58,12 +158,65 @@ pub struct ForemanConfig {
     pub profile: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SystemMapConfig {
+    /// Roots searched for git repositories (`~/` expanded). Default: existing ~/src, ~/code, ~/projects, ~/dev, ~/git.
+    pub repo_roots: Option<Vec<String>>,
+    /// Directory depth below each root (default 3).
+    pub repo_depth: Option<usize>,
+    /// Scanner names to skip (e.g. "pip", "docker").
+    #[serde(default)]
+    pub disabled_scanners: Vec<String>,
+    /// Per-scanner timeout overrides in milliseconds, keyed by scanner name.
+    #[serde(default)]
+    pub scanner_timeouts_ms: std::collections::HashMap<String, u64>,
+    /// Background incremental rescan interval in seconds (default 3600; 0 disables).
+    pub refresh_interval_secs: Option<u64>,
+    /// Hours after which the digest is flagged stale (default 24).
+    pub max_age_hours: Option<u64>,
+}
+
+/// Context budgets for chat replies, in estimated tokens.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ChatConfig {
+    /// Recent turns sent as they are (default 6000).
+    pub history_tokens: Option<usize>,
+    /// Rolling summary of the turns before them (default 400).
+    pub summary_tokens: Option<usize>,
+    /// Memory pack for the latest user message (default 1024; 0 disables).
+    pub pack_tokens: Option<usize>,
+    /// Longest wait for a model summary before the reply goes ahead with the digest (default 5000).
+    #[serde(default)]
+    pub summary_timeout_ms: Option<u64>,
+}
+
+/// System prompt sections: `*.md` templates with front matter and `{{var}}` placeholders.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PromptsConfig {
+    /// Section directory (default `config/prompts`).
+    pub dir: Option<String>,
+    /// Cap on the whole prompt in estimated tokens; sections past it are dropped.
+    pub max_tokens: Option<usize>,
+    /// Extra template variables, e.g. emulator commands.
+    #[serde(default)]
+    pub vars: std::collections::BTreeMap<String, String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Config {
     pub foreman: Option<ForemanConfig>,
     pub voice: Option<VoiceConfig>,
     pub schedules: Option<SchedulesConfig>,
     pub mcp: Option<McpConfig>,
+    pub system_map: Option<SystemMapConfig>,
+    #[serde(default)]
+    pub chat: Option<ChatConfig>,
+    #[serde(default)]
+    pub prompts: Option<PromptsConfig>,
+    #[serde(default)]
+    pub games: Option<GamesConfig>,
+    #[serde(default)]
+    pub fs: Option<FsConfig>,
 }
 
 impl Config {
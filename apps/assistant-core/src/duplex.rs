@@ -0,0 +1,223 @@
+//! Full-duplex voice: acoustic echo cancellation against the playback
+//! reference, a noise-floor VAD over the cleaned mic signal, and barge-in
+//! detection while the assistant is speaking. Pure Rust and device-free, so the
+//! realtime audio loop and the fixture tests drive the same code.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplexConfig {
+    /// Capture sample rate the pipeline runs at.
+    pub sample_rate: u32,
+    /// Echo canceller filter length in milliseconds (covers the echo tail).
+    pub aec_tail_ms: u32,
+    /// NLMS step size (0..1); larger adapts faster but is noisier.
+    pub aec_step: f32,
+    /// Disable to pass the mic through unchanged (VAD still runs).
+    pub aec_enabled: bool,
+    /// 0..1, higher triggers on quieter speech (same scale as `voice.vad_sensitivity`).
+    pub vad_sensitivity: f32,
+    /// Continuous speech needed before a barge-in fires.
+    pub min_speech_ms: u32,
+}
+
+impl Default for DuplexConfig {
+    fn default() -> Self {
+        Self { sample_rate: 16_000, aec_tail_ms: 64, aec_step: 0.5, aec_enabled: true, vad_sensitivity: 0.5, min_speech_ms: 200 }
+    }
+}
+
+/// Normalised LMS echo canceller with a Geigel double-talk detector that
+/// freezes adaptation while the near end is talking.
+pub struct EchoCanceller {
+    weights: Vec<f32>,
+    /// Reference history stored twice so `history[pos..pos + taps]` is newest-first.
+    history: Vec<f32>,
+    pos: usize,
+    energy: f32,
+    step: f32,
+    /// Samples left in the double-talk hold.
+    hold: usize,
+    hold_len: usize,
+}
+
+impl EchoCanceller {
+    pub fn new(taps: usize, step: f32, sample_rate: u32) -> Self {
+        let taps = taps.max(1);
+        Self { weights: vec![0.0; taps], history: vec![0.0; taps * 2], pos: 0, energy: 0.0, step, hold: 0, hold_len: (sample_rate / 33) as usize }
+    }
+
+    /// Cancel echo of `reference` (played samples, same rate and length) from `mic`.
+    pub fn process(&mut self, mic: &[i16], reference: &[i16]) -> Vec<i16> {
+        let n = self.weights.len();
+        let mut out = Vec::with_capacity(mic.len());
+        for (i, &m) in mic.iter().enumerate() {
+            let x = reference.get(i).copied().unwrap_or(0) as f32 / 32768.0;
+            let d = m as f32 / 32768.0;
+            // The slot being overwritten holds the sample leaving the window
+            self.pos = (self.pos + n - 1) % n;
+            let old = self.history[self.pos];
+            self.history[self.pos] = x;
+            self.history[self.pos + n] = x;
+            self.energy = (self.energy + x * x - old * old).max(0.0);
+
+            let window = &self.history[self.pos..self.pos + n];
+            let mut y = 0.0f32;
+            let mut peak = 0.0f32;
+            for (w, h) in self.weights.iter().zip(window) {
+                y += w * h;
+                peak = peak.max(h.abs());
+            }
+            let e = d - y;
+            // Geigel: near end louder than half the recent far-end peak means double talk
+            if peak > 0.0 && d.abs() > 0.5 * peak {
+                self.hold = self.hold_len;
+            }
+            if self.hold > 0 {
+                self.hold -= 1;
+            } else if self.energy > 1e-6 {
+                let g = self.step * e / (self.energy + 1e-6);
+                for (w, h) in self.weights.iter_mut().zip(window) {
+                    *w += g * h;
+                }
+            }
+            out.push((e * 32768.0).clamp(i16::MIN as f32, i16::MAX as f32) as i16);
+        }
+        out
+    }
+}
+
+pub fn rms(pcm: &[i16]) -> f32 {
+    if pcm.is_empty() { return 0.0; }
+    let sum: f64 = pcm.iter().map(|s| (*s as f64) * (*s as f64)).sum();
+    (sum / pcm.len() as f64).sqrt() as f32
+}
+
+/// Energy VAD with an adaptive noise floor (fast down, slow up).
+pub struct Vad {
+    floor: f32,
+    ratio: f32,
+    min_rms: f32,
+    speech_ms: f32,
+    min_speech_ms: f32,
+}
+
+impl Vad {
+    pub fn new(sensitivity: f32, min_speech_ms: u32) -> Self {
+        let s = sensitivity.clamp(0.0, 1.0);
+        // sensitivity 0 -> 8x floor / 900 rms, 1 -> 2x floor / 150 rms
+        Self { floor: 100.0, ratio: 8.0 - 6.0 * s, min_rms: 900.0 - 750.0 * s, speech_ms: 0.0, min_speech_ms: min_speech_ms as f32 }
+    }
+
+    /// Feed one frame; `gate` raises the threshold (e.g. residual echo level).
+    /// Returns true once speech has lasted `min_speech_ms`.
+    pub fn push(&mut self, frame: &[i16], frame_ms: f32, gate: f32) -> bool {
+        let level = rms(frame);
+        let threshold = (self.floor * self.ratio).max(self.min_rms).max(gate);
+        if level > threshold {
+            self.speech_ms += frame_ms;
+        } else {
+            self.speech_ms = 0.0;
+            // Track the floor only on non-speech frames
+            self.floor = if level < self.floor { 0.5 * self.floor + 0.5 * level } else { 0.98 * self.floor + 0.02 * level }.max(1.0);
+        }
+        self.speech_ms >= self.min_speech_ms
+    }
+
+    pub fn reset(&mut self) { self.speech_ms = 0.0; }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DuplexOutput {
+    /// Mic audio with the assistant's echo removed; this is what goes upstream.
+    pub cleaned: Vec<i16>,
+    /// The user started talking over the assistant (fires once per response).
+    pub barge_in: bool,
+    pub speech: bool,
+}
+
+pub struct DuplexPipeline {
+    cfg: DuplexConfig,
+    aec: Option<EchoCanceller>,
+    vad: Vad,
+    fired: bool,
+    /// Residual echo relative to the reference, learned on frames without user speech.
+    /// Starts pessimistic so an unconverged filter cannot trigger a barge-in.
+    leak: f32,
+}
+
+impl DuplexPipeline {
+    pub fn new(cfg: DuplexConfig) -> Self {
+        let taps = (cfg.sample_rate as usize) * (cfg.aec_tail_ms as usize) / 1000;
+        let aec = cfg.aec_enabled.then(|| EchoCanceller::new(taps, cfg.aec_step, cfg.sample_rate));
+        let vad = Vad::new(cfg.vad_sensitivity, cfg.min_speech_ms);
+        Self { cfg, aec, vad, fired: false, leak: 1.0 }
+    }
+
+    /// Process one mic frame with the reference that was played over the same span.
+    pub fn process(&mut self, mic: &[i16], reference: &[i16], assistant_speaking: bool) -> DuplexOutput {
+        let cleaned = match self.aec.as_mut() {
+            Some(aec) => aec.process(mic, reference),
+            None => mic.to_vec(),
+        };
+        let frame_ms = mic.len() as f32 * 1000.0 / self.cfg.sample_rate as f32;
+        let ref_level = rms(reference);
+        // Residual echo guard: speech must beat the expected leak by 6 dB
+        let gate = 2.0 * self.leak * ref_level;
+        let speech = self.vad.push(&cleaned, frame_ms, gate);
+        if !speech && ref_level > 100.0 {
+            let ratio = (rms(&cleaned) / ref_level).min(1.0);
+            self.leak = 0.9 * self.leak + 0.1 * ratio;
+        }
+        if !assistant_speaking {
+            self.fired = false;
+        }
+        let barge_in = speech && assistant_speaking && !self.fired;
+        if barge_in {
+            self.fired = true;
+        }
+        DuplexOutput { cleaned, barge_in, speech }
+    }
+}
+
+/// Client events that stop the current answer: cancel the response and, when the
+/// assistant item is known, truncate it to the audio the user actually heard.
+pub fn barge_in_events(item_id: Option<&str>, played_ms: u64) -> Vec<serde_json::Value> {
+    let mut v = vec![serde_json::json!({"type": "response.cancel"})];
+    if let Some(id) = item_id {
+        v.push(serde_json::json!({"type": "conversation.item.truncate", "item_id": id, "content_index": 0, "audio_end_ms": played_ms}));
+    }
+    v
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(n: usize, sr: f32, hz: f32, amp: f32) -> Vec<i16> {
+        (0..n).map(|i| ((i as f32 * hz * std::f32::consts::TAU / sr).sin() * amp) as i16).collect()
+    }
+
+    #[test]
+    fn vad_needs_sustained_energy_above_floor() {
+        let mut vad = Vad::new(0.5, 100);
+        let quiet = vec![20i16; 320];
+        for _ in 0..20 { assert!(!vad.push(&quiet, 20.0, 0.0)); }
+        let loud = tone(320, 16_000.0, 220.0, 6000.0);
+        assert!(!vad.push(&loud, 20.0, 0.0));
+        for _ in 0..3 { vad.push(&loud, 20.0, 0.0); }
+        assert!(vad.push(&loud, 20.0, 0.0));
+        // A gate above the level suppresses it
+        vad.reset();
+        for _ in 0..10 { assert!(!vad.push(&loud, 20.0, 1e6)); }
+    }
+
+    #[test]
+    fn barge_in_events_truncate_known_item() {
+        let ev = barge_in_events(Some("item_1"), 1234);
+        assert_eq!(ev[0]["type"], "response.cancel");
+        assert_eq!(ev[1]["type"], "conversation.item.truncate");
+        assert_eq!(ev[1]["audio_end_ms"], 1234);
+        assert_eq!(barge_in_events(None, 10).len(), 1);
+    }
+}
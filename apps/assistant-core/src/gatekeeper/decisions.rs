@@ -0,0 +1,100 @@
+//! Decision channels for ephemeral approval prompts. A waiter registers the
+//! prompt id and blocks on the receiver; `/api/approval/answer` (or a spoken
+//! yes/no during a voice call) resolves it.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::oneshot;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "decision", rename_all = "lowercase")]
+pub enum PromptDecision {
+    Approve,
+    Deny { reason: Option<String> },
+    Timeout,
+}
+
+impl PromptDecision {
+    /// Parse an answer as sent by the TUI ("yes"/"no") or the web UI ("approve"/"deny").
+    pub fn parse(answer: &str, reason: Option<String>) -> Option<Self> {
+        match answer.trim().to_ascii_lowercase().as_str() {
+            "approve" | "approved" | "allow" | "yes" | "y" => Some(Self::Approve),
+            "deny" | "denied" | "reject" | "no" | "n" => Some(Self::Deny { reason }),
+            _ => None,
+        }
+    }
+}
+
+/// Spoken confirmation: "yes, go ahead" -> Some(true), "no thanks" -> Some(false).
+/// Ambiguous or unrelated utterances (including both yes and no) return None.
+pub fn parse_spoken_answer(text: &str) -> Option<bool> {
+    const YES: &[&str] = &["yes", "yeah", "yep", "sure", "approve", "approved", "confirm", "ok", "okay", "affirmative", "proceed"];
+    const NO: &[&str] = &["no", "nope", "nah", "deny", "denied", "cancel", "stop", "don't", "dont", "negative", "reject"];
+    let words: Vec<String> = text
+        .split(|c: char| !(c.is_alphanumeric() || c == '\''))
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect();
+    let text = words.join(" ");
+    let yes = words.iter().any(|w| YES.contains(&w.as_str())) || text.contains("go ahead") || text.contains("do it");
+    let no = words.iter().any(|w| NO.contains(&w.as_str()));
+    match (yes, no) {
+        (true, false) => Some(true),
+        (false, true) => Some(false),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct PromptDecisions(Arc<Mutex<HashMap<String, oneshot::Sender<PromptDecision>>>>);
+
+impl PromptDecisions {
+    /// Register a waiter for prompt `id`; a second registration replaces the first.
+    pub fn register(&self, id: &str) -> oneshot::Receiver<PromptDecision> {
+        let (tx, rx) = oneshot::channel();
+        self.0.lock().insert(id.to_string(), tx);
+        rx
+    }
+
+    /// Deliver a decision; false when nobody is waiting on `id`.
+    pub fn resolve(&self, id: &str, decision: PromptDecision) -> bool {
+        match self.0.lock().remove(id) {
+            Some(tx) => tx.send(decision).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Drop a waiter (e.g. after it timed out).
+    pub fn cancel(&self, id: &str) {
+        self.0.lock().remove(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_answers_and_speech() {
+        assert_eq!(PromptDecision::parse("yes", None), Some(PromptDecision::Approve));
+        assert_eq!(PromptDecision::parse("Deny", Some("risky".into())), Some(PromptDecision::Deny { reason: Some("risky".into()) }));
+        assert_eq!(PromptDecision::parse("explain", None), None);
+        assert_eq!(parse_spoken_answer("Yeah, go ahead."), Some(true));
+        assert_eq!(parse_spoken_answer("No, don't do that"), Some(false));
+        assert_eq!(parse_spoken_answer("what is this about"), None);
+        assert_eq!(parse_spoken_answer("yes no"), None);
+        assert_eq!(parse_spoken_answer("nobody knows"), None);
+    }
+
+    #[tokio::test]
+    async fn resolve_reaches_the_registered_waiter() {
+        let d = PromptDecisions::default();
+        let rx = d.register("p1");
+        assert!(!d.resolve("other", PromptDecision::Approve));
+        assert!(d.resolve("p1", PromptDecision::Deny { reason: None }));
+        assert_eq!(rx.await.unwrap(), PromptDecision::Deny { reason: None });
+        assert!(!d.resolve("p1", PromptDecision::Approve), "resolved only once");
+    }
+}
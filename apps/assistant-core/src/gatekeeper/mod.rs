@@ -1,8 +1,11 @@
 pub mod policy;
 pub mod approvals;
 pub mod provenance;
+pub mod decisions;
+pub mod tool_calls;
 
 pub use policy::{PolicyEngine, PolicyDecision, PolicyDecisionKind, ProposedAction};
 pub use approvals::{Approval, ApprovalId, ApprovalStatus, ApprovalsStore};
 pub use provenance::{ExplainCard, ProvenanceEngine};
+pub use decisions::{parse_spoken_answer, PromptDecision, PromptDecisions};
 
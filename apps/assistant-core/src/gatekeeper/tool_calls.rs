@@ -0,0 +1,161 @@
+//! Gate for tool calls made by a model, in chat or a realtime call: validate
+//! the arguments, evaluate policy from the tool's risk flags, and ask for
+//! approval of anything policy does not allow.
+
+use super::{PolicyDecision, PolicyDecisionKind, PolicyEngine, PromptDecision, PromptDecisions, ProposedAction};
+use crate::app::EphemeralApproval;
+use crate::realtime::SpokenConfirm;
+use crate::tools::ToolsManager;
+use parking_lot::RwLock;
+use serde_json::{json, Value as JsonValue};
+use std::sync::Arc;
+
+/// How long a call held on the live prompt waits for an answer.
+const APPROVAL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
+
+/// Split a model tool name, `server.tool` or `server_tool`.
+pub fn split_tool_name(name: &str) -> (String, String) {
+    match name.split_once('.').or_else(|| name.split_once('_')) {
+        Some((s, t)) => (s.to_string(), t.to_string()),
+        None => (name.to_string(), String::new()),
+    }
+}
+
+/// What the gate makes of a call before anything runs.
+pub enum ToolGate {
+    /// Policy allows it.
+    Run,
+    /// Malformed; the error goes back to the model instead of a result.
+    Reject(JsonValue),
+    /// Runs only once approved.
+    Hold { action: ProposedAction, decision: PolicyDecision },
+}
+
+/// Validate, then evaluate policy. Malformed calls are rejected before anyone
+/// is asked to approve them.
+pub fn gate(tools: &ToolsManager, policy: &PolicyEngine, server: &str, tool: &str, args: &JsonValue) -> ToolGate {
+    if let Err(e) = tools.validate(server, tool, args) {
+        return ToolGate::Reject(json!({"error": e.to_string()}));
+    }
+    let action = tools.proposed_action(server, tool, args);
+    let decision = policy.evaluate(&action);
+    if decision.kind == PolicyDecisionKind::Allow { ToolGate::Run } else { ToolGate::Hold { action, decision } }
+}
+
+/// The result a model gets for a call that was denied or timed out; `None`
+/// when it was approved.
+pub fn declined(server: &str, tool: &str, answer: &PromptDecision) -> Option<JsonValue> {
+    match answer {
+        PromptDecision::Approve => None,
+        PromptDecision::Deny { reason } => Some(json!({
+            "error": "denied by user",
+            "approved": false,
+            "decision": "deny",
+            "tool": format!("{}.{}", server, tool),
+            "reason": reason,
+            "instruction": "The user declined this action. Do not retry it; tell the user it was not done and ask how they want to proceed.",
+        })),
+        PromptDecision::Timeout => Some(json!({
+            "error": "approval timeout",
+            "approved": false,
+            "decision": "timeout",
+            "tool": format!("{}.{}", server, tool),
+            "instruction": "Nobody answered the approval prompt. The action was not performed; ask the user to confirm before trying again.",
+        })),
+    }
+}
+
+/// Approval prompt shown before a tool call ran, and how it was answered.
+#[derive(Clone, Debug)]
+pub(crate) struct ToolApproval {
+    pub(crate) id: String,
+    #[cfg(feature = "realtime")]
+    pub(crate) decision: &'static str,
+}
+
+/// Run a model's tool call through the gate, waiting on the live approval
+/// prompt (and `mic`, for a spoken yes/no) when policy holds it. `origin`
+/// names the caller in the prompt title. Returns the function output and the
+/// approval asked for it, if any.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn run_tool_call(tools: &ToolsManager, policy: &PolicyEngine, approval_prompt: &Arc<RwLock<Option<EphemeralApproval>>>, decisions: &PromptDecisions, mic: Option<&SpokenConfirm>, origin: &str, name: &str, args: JsonValue) -> (JsonValue, Option<ToolApproval>) {
+    let (server, tool) = split_tool_name(name);
+    let mut approval = None;
+    match gate(tools, policy, &server, &tool, &args) {
+        ToolGate::Run => {}
+        ToolGate::Reject(err) => return (err, None),
+        ToolGate::Hold { action, decision } => {
+            let risk = tools.spec(&server, &tool).map(|t| t.risk);
+            let prompt = EphemeralApproval {
+                id: uuid::Uuid::new_v4().to_string(),
+                title: format!("{} tool requires approval: {}.{}", origin, server, tool),
+                action,
+                details: json!({"server": server, "tool": tool, "arguments": args.clone(), "reasons": decision.reasons, "risk": risk, "voice_confirm": mic.is_some()}),
+            };
+            let id = prompt.id.clone();
+            let answer = await_tool_approval(approval_prompt, decisions, prompt, mic).await;
+            tracing::info!(server=%server, tool=%tool, answer=?answer, "tool approval answered");
+            #[cfg(feature = "realtime")]
+            let label = match answer { PromptDecision::Approve => "approve", PromptDecision::Deny { .. } => "deny", PromptDecision::Timeout => "timeout" };
+            let asked = ToolApproval { id, #[cfg(feature = "realtime")] decision: label };
+            if let Some(result) = declined(&server, &tool, &answer) { return (result, Some(asked)); }
+            approval = Some(asked);
+        }
+    }
+    let result = match tools.invoke(&server, &tool, args).await {
+        Ok(v) => v,
+        Err(e) => json!({"error": e.to_string()}),
+    };
+    (result, approval)
+}
+
+/// Show `prompt` (queued behind any prompt already on screen) and wait for an
+/// answer from `/api/approval/answer`, a spoken yes/no, or the timeout.
+async fn await_tool_approval(approval_prompt: &Arc<RwLock<Option<EphemeralApproval>>>, decisions: &PromptDecisions, prompt: EphemeralApproval, mic: Option<&SpokenConfirm>) -> PromptDecision {
+    let deadline = tokio::time::Instant::now() + APPROVAL_TIMEOUT;
+    let id = prompt.id.clone();
+    // Register before the prompt becomes visible so an immediate answer is not lost
+    let rx = decisions.register(&id);
+    // Clears the prompt on every exit, including a session ending mid-wait
+    let _shown = ShownPrompt { approval_prompt, decisions, id: &id, mic };
+    loop {
+        {
+            let mut w = approval_prompt.write();
+            if w.is_none() {
+                *w = Some(prompt);
+                break;
+            }
+        }
+        if tokio::time::Instant::now() >= deadline { return PromptDecision::Timeout; }
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    }
+    let spoken = async {
+        match mic {
+            Some(m) => m.answer().await,
+            None => std::future::pending().await,
+        }
+    };
+    tokio::select! {
+        d = rx => d.unwrap_or(PromptDecision::Deny { reason: Some("approval channel closed".into()) }),
+        yes = spoken => if yes { PromptDecision::Approve } else { PromptDecision::Deny { reason: Some("declined by voice".into()) } },
+        _ = tokio::time::sleep_until(deadline) => PromptDecision::Timeout,
+    }
+}
+
+/// An approval prompt on screen; dropping it takes the prompt down.
+struct ShownPrompt<'a> {
+    approval_prompt: &'a Arc<RwLock<Option<EphemeralApproval>>>,
+    decisions: &'a PromptDecisions,
+    id: &'a str,
+    mic: Option<&'a SpokenConfirm>,
+}
+
+impl Drop for ShownPrompt<'_> {
+    fn drop(&mut self) {
+        self.decisions.cancel(self.id);
+        if let Some(m) = self.mic { m.release(); }
+        // The answer endpoint clears the slot itself; voice, timeout and shutdown paths do it here
+        let mut w = self.approval_prompt.write();
+        if w.as_ref().map(|p| p.id == self.id).unwrap_or(false) { *w = None; }
+    }
+}
@@ -0,0 +1,813 @@
+//! Installer backend: validated, SQLite-persisted install plans.
+//! Commands are argv vectors (never shell strings); apply streams output as events
+//! and records installed versions into the System Map.
+
+use crate::memory::Memory;
+use crate::system_map::{model::InstalledPackage, SystemMapManager};
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::mpsc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Manager {
+    Apt,
+    Snap,
+    Flatpak,
+    Pip,
+    Cargo,
+}
+
+impl Manager {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "apt" | "apt-get" => Ok(Manager::Apt),
+            "snap" => Ok(Manager::Snap),
+            "flatpak" => Ok(Manager::Flatpak),
+            "pip" | "pip3" => Ok(Manager::Pip),
+            "cargo" => Ok(Manager::Cargo),
+            other => bail!("unsupported package manager: {}", other),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Manager::Apt => "apt",
+            Manager::Snap => "snap",
+            Manager::Flatpak => "flatpak",
+            Manager::Pip => "pip",
+            Manager::Cargo => "cargo",
+        }
+    }
+
+    /// Use the requested manager, else guess from the package name and platform.
+    pub fn detect(pkg: &str, provided: Option<&str>) -> Result<Self> {
+        if let Some(m) = provided { return Self::parse(m); }
+        // Reverse-DNS application ids are flatpak refs
+        if pkg.matches('.').count() >= 2 && pkg.chars().any(|c| c.is_ascii_uppercase()) { return Ok(Manager::Flatpak); }
+        Ok(if cfg!(target_os = "linux") { Manager::Apt } else { Manager::Cargo })
+    }
+}
+
+/// Validate a package name against the manager's naming rules. Names only: no
+/// version specifiers, paths, URLs or option-like values.
+pub fn validate_package(manager: Manager, pkg: &str) -> Result<()> {
+    if pkg.is_empty() { bail!("pkg required"); }
+    if pkg.len() > 128 { bail!("package name too long"); }
+    if pkg.starts_with('-') { bail!("invalid package name '{}': must not start with '-'", pkg); }
+    let ok = match manager {
+        // Debian policy: lowercase alnum and + - . , at least two chars, starting alnum; optional :arch
+        Manager::Apt => {
+            let (name, arch) = match pkg.split_once(':') { Some((n, a)) => (n, Some(a)), None => (pkg, None) };
+            name.len() >= 2
+                && name.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+                && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '+' | '-' | '.'))
+                && arch.map(|a| !a.is_empty() && a.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())).unwrap_or(true)
+        }
+        // Snap store names: lowercase alnum and hyphens, no leading/trailing/double hyphen
+        Manager::Snap => {
+            pkg.len() <= 40
+                && pkg.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+                && !pkg.ends_with('-')
+                && !pkg.contains("--")
+                && pkg.chars().any(|c| c.is_ascii_lowercase())
+        }
+        // Flatpak application ids: reverse DNS with at least three segments
+        Manager::Flatpak => {
+            let segs: Vec<&str> = pkg.split('.').collect();
+            segs.len() >= 3
+                && segs.iter().all(|s| {
+                    !s.is_empty()
+                        && !s.starts_with(|c: char| c.is_ascii_digit())
+                        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+                })
+        }
+        // PEP 508 distribution names
+        Manager::Pip => {
+            pkg.starts_with(|c: char| c.is_ascii_alphanumeric())
+                && pkg.ends_with(|c: char| c.is_ascii_alphanumeric())
+                && pkg.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+        }
+        // crates.io names
+        Manager::Cargo => {
+            pkg.len() <= 64
+                && pkg.starts_with(|c: char| c.is_ascii_alphabetic())
+                && pkg.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        }
+    };
+    if !ok { bail!("invalid {} package name: '{}'", manager.as_str(), pkg); }
+    Ok(())
+}
+
+/// Flatpak remote used when a plan does not name one.
+pub const DEFAULT_FLATPAK_REMOTE: &str = "flathub";
+
+/// Validate a flatpak remote name: alnum plus `-_.`, never option-like.
+pub fn validate_remote(remote: &str) -> Result<()> {
+    let ok = !remote.is_empty()
+        && remote.len() <= 64
+        && remote.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && remote.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !ok { bail!("invalid flatpak remote: '{}'", remote); }
+    Ok(())
+}
+
+/// Prefix for commands that need root; empty when already running as root.
+fn privileged(args: &[&str]) -> Vec<String> {
+    let mut v: Vec<String> = if running_as_root() { vec![] } else { vec!["sudo".into(), "-n".into()] };
+    v.extend(args.iter().map(|s| s.to_string()));
+    v
+}
+
+fn running_as_root() -> bool {
+    std::fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|s| s.lines().find(|l| l.starts_with("Uid:")).and_then(|l| l.split_whitespace().nth(1)).map(|u| u == "0"))
+        .unwrap_or(false)
+}
+
+fn argv(args: &[&str]) -> Vec<String> { args.iter().map(|s| s.to_string()).collect() }
+
+/// `remote` only applies to flatpak, which installs from that remote.
+pub fn install_commands(manager: Manager, pkg: &str, remote: &str) -> Vec<Vec<String>> {
+    match manager {
+        Manager::Apt => vec![privileged(&["apt-get", "update"]), privileged(&["apt-get", "install", "-y", pkg])],
+        Manager::Snap => vec![privileged(&["snap", "install", pkg])],
+        Manager::Flatpak => vec![argv(&["flatpak", "install", "-y", "--noninteractive", remote, pkg])],
+        Manager::Pip => vec![argv(&["python3", "-m", "pip", "install", pkg])],
+        Manager::Cargo => vec![argv(&["cargo", "install", pkg])],
+    }
+}
+
+pub fn rollback_commands(manager: Manager, pkg: &str) -> Vec<Vec<String>> {
+    match manager {
+        Manager::Apt => vec![privileged(&["apt-get", "remove", "-y", pkg])],
+        Manager::Snap => vec![privileged(&["snap", "remove", pkg])],
+        Manager::Flatpak => vec![argv(&["flatpak", "uninstall", "-y", "--noninteractive", pkg])],
+        Manager::Pip => vec![argv(&["python3", "-m", "pip", "uninstall", "-y", pkg])],
+        Manager::Cargo => vec![argv(&["cargo", "uninstall", pkg])],
+    }
+}
+
+/// Simulation command; cargo has none, so its dry run diffs `cargo install --list`.
+fn dry_run_command(manager: Manager, pkg: &str, remote: &str) -> Vec<String> {
+    match manager {
+        Manager::Apt => argv(&["apt-get", "-s", "install", pkg]),
+        Manager::Snap => argv(&["snap", "info", pkg]),
+        Manager::Flatpak => argv(&["flatpak", "remote-info", remote, pkg]),
+        Manager::Pip => argv(&["python3", "-m", "pip", "install", "--dry-run", pkg]),
+        Manager::Cargo => argv(&["cargo", "install", "--list"]),
+    }
+}
+
+fn version_command(manager: Manager, pkg: &str) -> Vec<String> {
+    match manager {
+        Manager::Apt => argv(&["dpkg-query", "-W", "-f=${Version}", pkg]),
+        Manager::Snap => argv(&["snap", "list", pkg]),
+        Manager::Flatpak => argv(&["flatpak", "info", pkg]),
+        Manager::Pip => argv(&["python3", "-m", "pip", "show", pkg]),
+        Manager::Cargo => argv(&["cargo", "install", "--list"]),
+    }
+}
+
+/// Quote argv for display in approval prompts and explanations.
+pub fn display_argv(argv: &[String]) -> String {
+    argv.iter()
+        .map(|a| {
+            if !a.is_empty() && a.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=+@,${}".contains(c)) { a.clone() } else { format!("'{}'", a.replace('\'', "'\\''")) }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PackageChange {
+    pub name: String,
+    pub version: Option<String>,
+}
+
+/// `apt-get -s install` prints `Inst <name> [<old>] (<new> <repo> [<arch>])` per package.
+pub fn parse_apt_simulation(out: &str) -> Vec<PackageChange> {
+    out.lines()
+        .filter_map(|l| l.strip_prefix("Inst "))
+        .filter_map(|rest| {
+            let name = rest.split_whitespace().next()?.to_string();
+            let version = rest.split_once('(').and_then(|(_, v)| v.split_whitespace().next()).map(|s| s.to_string());
+            Some(PackageChange { name, version })
+        })
+        .collect()
+}
+
+/// `pip install --dry-run` prints `Would install a-1.0 b_c-2.0`.
+pub fn parse_pip_dry_run(out: &str) -> Vec<PackageChange> {
+    out.lines()
+        .filter_map(|l| l.trim().strip_prefix("Would install "))
+        .flat_map(|rest| rest.split_whitespace())
+        .map(|tok| match tok.rsplit_once('-') {
+            Some((n, v)) => PackageChange { name: n.to_string(), version: Some(v.to_string()) },
+            None => PackageChange { name: tok.to_string(), version: None },
+        })
+        .collect()
+}
+
+/// `cargo install --list` prints `name vX.Y.Z[ (source)]:` followed by indented binaries.
+pub fn parse_cargo_list(out: &str) -> Vec<PackageChange> {
+    out.lines()
+        .filter(|l| !l.starts_with(char::is_whitespace) && l.trim_end().ends_with(':'))
+        .filter_map(|l| {
+            let mut it = l.trim_end().trim_end_matches(':').split_whitespace();
+            let name = it.next()?.to_string();
+            let version = it.next().map(|v| v.trim_start_matches('v').to_string());
+            Some(PackageChange { name, version })
+        })
+        .collect()
+}
+
+fn parse_installed_version(manager: Manager, pkg: &str, out: &str) -> Option<String> {
+    let field = |key: &str| out.lines().find_map(|l| l.trim().strip_prefix(key).map(|v| v.trim().to_string())).filter(|v| !v.is_empty());
+    match manager {
+        Manager::Apt => Some(out.trim().to_string()).filter(|v| !v.is_empty()),
+        // `snap list <pkg>`: header line, then `name version rev ...`
+        Manager::Snap => out.lines().nth(1).and_then(|l| l.split_whitespace().nth(1)).map(|s| s.to_string()),
+        Manager::Flatpak | Manager::Pip => field("Version:"),
+        Manager::Cargo => parse_cargo_list(out).into_iter().find(|c| c.name == pkg).and_then(|c| c.version),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    pub fn as_str(&self) -> &'static str {
+        match self { Stream::Stdout => "stdout", Stream::Stderr => "stderr" }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RunOutput {
+    pub code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl RunOutput {
+    pub fn success(&self) -> bool { self.code == Some(0) }
+}
+
+/// Executes argv commands; swapped out in tests.
+#[async_trait]
+pub trait CommandRunner: Send + Sync {
+    /// Run `argv` to completion, forwarding each output line to `lines` when given.
+    async fn run(&self, argv: &[String], lines: Option<mpsc::UnboundedSender<(Stream, String)>>) -> Result<RunOutput>;
+}
+
+pub struct ProcessRunner {
+    pub timeout: std::time::Duration,
+}
+
+impl Default for ProcessRunner {
+    fn default() -> Self { Self { timeout: std::time::Duration::from_secs(30 * 60) } }
+}
+
+#[async_trait]
+impl CommandRunner for ProcessRunner {
+    async fn run(&self, argv: &[String], lines: Option<mpsc::UnboundedSender<(Stream, String)>>) -> Result<RunOutput> {
+        let (prog, args) = argv.split_first().ok_or_else(|| anyhow!("empty command"))?;
+        let mut child = tokio::process::Command::new(prog)
+            .args(args)
+            .env("DEBIAN_FRONTEND", "noninteractive")
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| anyhow!("spawn {}: {}", prog, e))?;
+        let pump = |r: Box<dyn tokio::io::AsyncRead + Unpin + Send>, stream: Stream, tx: Option<mpsc::UnboundedSender<(Stream, String)>>| {
+            tokio::spawn(async move {
+                let mut buf = String::new();
+                let mut rd = BufReader::new(r).lines();
+                while let Ok(Some(line)) = rd.next_line().await {
+                    if let Some(tx) = tx.as_ref() { let _ = tx.send((stream, line.clone())); }
+                    buf.push_str(&line);
+                    buf.push('\n');
+                }
+                buf
+            })
+        };
+        let out_task = pump(Box::new(child.stdout.take().expect("stdout piped")), Stream::Stdout, lines.clone());
+        let err_task = pump(Box::new(child.stderr.take().expect("stderr piped")), Stream::Stderr, lines);
+        let status = match tokio::time::timeout(self.timeout, child.wait()).await {
+            Ok(s) => s?,
+            Err(_) => {
+                let _ = child.kill().await;
+                bail!("{} timed out after {}s", prog, self.timeout.as_secs());
+            }
+        };
+        Ok(RunOutput { code: status.code(), stdout: out_task.await.unwrap_or_default(), stderr: err_task.await.unwrap_or_default() })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InstallPlan {
+    pub id: String,
+    pub manager: String,
+    pub pkg: String,
+    pub commands: Vec<Vec<String>>,
+    pub rollback: Vec<Vec<String>>,
+    pub status: String,
+    pub dry_run: Option<JsonValue>,
+    pub result: Option<JsonValue>,
+}
+
+impl InstallPlan {
+    fn manager(&self) -> Result<Manager> { Manager::parse(&self.manager) }
+
+    pub fn command_lines(&self) -> Vec<String> { self.commands.iter().map(|c| display_argv(c)).collect() }
+
+    pub fn rollback_lines(&self) -> Vec<String> { self.rollback.iter().map(|c| display_argv(c)).collect() }
+
+    /// The remote named in a flatpak install command; plans made before remotes
+    /// were recorded install from the default.
+    fn flatpak_remote(&self) -> &str {
+        self.commands
+            .iter()
+            .find(|c| c.len() > 2 && c[0] == "flatpak" && c[1] == "install")
+            .and_then(|c| c.iter().rev().nth(1))
+            .map(|s| s.as_str())
+            .filter(|s| !s.starts_with('-'))
+            .unwrap_or(DEFAULT_FLATPAK_REMOTE)
+    }
+}
+
+#[derive(Clone)]
+pub struct Installer {
+    memory: Option<Memory>,
+    system_map: Option<SystemMapManager>,
+    runner: Arc<RwLock<Arc<dyn CommandRunner>>>,
+}
+
+impl Default for Installer {
+    fn default() -> Self { Self::new(None, None) }
+}
+
+/// Keep the tail of long command output in tool results.
+fn tail(s: &str, max_lines: usize) -> String {
+    let lines: Vec<&str> = s.lines().collect();
+    lines[lines.len().saturating_sub(max_lines)..].join("\n")
+}
+
+/// Marks a claimed plan failed unless disarmed, so an error or a dropped
+/// `apply` future never leaves it stuck in `running`.
+struct RunningPlan {
+    store: foreman_memory::MemoryStore,
+    id: String,
+    armed: bool,
+}
+
+impl Drop for RunningPlan {
+    fn drop(&mut self) {
+        if !self.armed { return; }
+        let Ok(rt) = tokio::runtime::Handle::try_current() else { return };
+        let (store, id) = (self.store.clone(), self.id.clone());
+        rt.spawn(async move {
+            let result = json!({"plan_id": id, "applied": false, "note": "apply was interrupted; review rollback commands before retrying"});
+            if let Err(e) = store.finish_install_plan(&id, "failed", &result.to_string()).await {
+                tracing::warn!(error=%e, plan=%id, "installer: cannot mark interrupted plan failed");
+            }
+        });
+    }
+}
+
+impl Installer {
+    pub fn new(memory: Option<Memory>, system_map: Option<SystemMapManager>) -> Self {
+        Self { memory, system_map, runner: Arc::new(RwLock::new(Arc::new(ProcessRunner::default()))) }
+    }
+
+    /// Fail plans left `running` by a process that exited mid-apply.
+    pub async fn recover_interrupted(&self) {
+        let Some(mem) = self.memory.as_ref() else { return };
+        let result = json!({"applied": false, "note": "core restarted while this plan was running; review rollback commands before retrying"});
+        match mem.store.fail_running_install_plans(&result.to_string()).await {
+            Ok(0) => {}
+            Ok(n) => tracing::warn!(plans = n, "installer: marked interrupted plans failed"),
+            Err(e) => tracing::warn!(error=%e, "installer: cannot recover interrupted plans"),
+        }
+    }
+
+    /// Replace the command runner (tests, alternate executors).
+    pub fn set_runner(&self, runner: Arc<dyn CommandRunner>) { *self.runner.write() = runner; }
+
+    fn runner(&self) -> Arc<dyn CommandRunner> { self.runner.read().clone() }
+
+    fn memory(&self) -> Result<&Memory> { self.memory.as_ref().ok_or_else(|| anyhow!("memory not initialized")) }
+
+    async fn event(&self, kind: &str, payload: JsonValue) {
+        if let Some(mem) = self.memory.as_ref() {
+            let _ = mem.store.append_event(None, kind, Some(&payload)).await;
+        }
+    }
+
+    /// `remote` names the flatpak remote to install from (default `flathub`).
+    pub async fn plan(&self, pkg: &str, manager: Option<&str>, remote: Option<&str>) -> Result<InstallPlan> {
+        let pkg = pkg.trim();
+        if pkg.is_empty() { bail!("pkg required"); }
+        let mgr = Manager::detect(pkg, manager)?;
+        validate_package(mgr, pkg)?;
+        let remote = match remote.map(str::trim) {
+            Some(r) if mgr != Manager::Flatpak => bail!("remote '{}' only applies to flatpak", r),
+            Some(r) => { validate_remote(r)?; r }
+            None => DEFAULT_FLATPAK_REMOTE,
+        };
+        let plan = InstallPlan {
+            id: uuid::Uuid::new_v4().to_string(),
+            manager: mgr.as_str().to_string(),
+            pkg: pkg.to_string(),
+            commands: install_commands(mgr, pkg, remote),
+            rollback: rollback_commands(mgr, pkg),
+            status: "planned".into(),
+            dry_run: None,
+            result: None,
+        };
+        self.memory()?
+            .store
+            .create_install_plan(&plan.id, &plan.manager, &plan.pkg, &serde_json::to_string(&plan.commands)?, Some(&serde_json::to_string(&plan.rollback)?))
+            .await?;
+        Ok(plan)
+    }
+
+    pub async fn get(&self, plan_id: &str) -> Result<Option<InstallPlan>> {
+        let Some(row) = self.memory()?.store.get_install_plan(plan_id).await? else { return Ok(None) };
+        let parse = |s: Option<&str>| s.and_then(|s| serde_json::from_str::<JsonValue>(s).ok());
+        Ok(Some(InstallPlan {
+            id: row.id,
+            manager: row.manager,
+            pkg: row.pkg,
+            commands: serde_json::from_str(&row.commands_json)?,
+            rollback: row.rollback_json.as_deref().and_then(|s| serde_json::from_str(s).ok()).unwrap_or_default(),
+            status: row.status,
+            dry_run: parse(row.dry_run_json.as_deref()),
+            result: parse(row.result_json.as_deref()),
+        }))
+    }
+
+    async fn require(&self, plan_id: &str) -> Result<InstallPlan> {
+        self.get(plan_id).await?.ok_or_else(|| anyhow!("unknown plan_id"))
+    }
+
+    /// Display strings for the approval prompt.
+    pub async fn plan_commands(&self, plan_id: &str) -> Option<Vec<String>> {
+        self.get(plan_id).await.ok().flatten().map(|p| p.command_lines())
+    }
+
+    /// Run the manager's simulation and report what would change.
+    pub async fn dry_run(&self, plan_id: &str) -> Result<JsonValue> {
+        let plan = self.require(plan_id).await?;
+        let mgr = plan.manager()?;
+        let cmd = dry_run_command(mgr, &plan.pkg, plan.flatpak_remote());
+        let out = self.runner().run(&cmd, None).await?;
+        let (changes, already_installed) = match mgr {
+            Manager::Apt => (parse_apt_simulation(&out.stdout), None),
+            Manager::Pip => (parse_pip_dry_run(&out.stdout), None),
+            Manager::Cargo => {
+                let current = parse_cargo_list(&out.stdout).into_iter().find(|c| c.name == plan.pkg);
+                let changes = if current.is_some() { vec![] } else { vec![PackageChange { name: plan.pkg.clone(), version: None }] };
+                (changes, Some(current))
+            }
+            Manager::Snap | Manager::Flatpak => (
+                if out.success() { vec![PackageChange { name: plan.pkg.clone(), version: None }] } else { vec![] },
+                None,
+            ),
+        };
+        let mut v = json!({
+            "plan_id": plan.id,
+            "dry_run": true,
+            "ok": out.success(),
+            "exit_code": out.code,
+            "simulation": display_argv(&cmd),
+            "commands": plan.command_lines(),
+            "changes": changes,
+            "output": tail(&out.stdout, 40),
+        });
+        if let Some(current) = already_installed {
+            v["already_installed"] = json!(current.is_some());
+            v["installed_version"] = json!(current.and_then(|c| c.version));
+        }
+        if !out.success() { v["stderr"] = json!(tail(&out.stderr, 20)); }
+        self.memory()?.store.set_install_plan_dry_run(&plan.id, &v.to_string()).await?;
+        Ok(v)
+    }
+
+    /// Execute an approved plan. Approval is enforced by the API layer before this is called.
+    /// Only a plan that has not been applied yet runs, and only once.
+    pub async fn apply(&self, plan_id: &str) -> Result<JsonValue> {
+        let plan = self.require(plan_id).await?;
+        let mgr = plan.manager()?;
+        let store = &self.memory()?.store;
+        // Check and claim in one conditional UPDATE so concurrent applies can't both pass
+        if !store.transition_install_plan(&plan.id, "planned", "running").await? {
+            let status = self.get(plan_id).await.ok().flatten().map(|p| p.status).unwrap_or(plan.status);
+            bail!("plan {} is {}; only a planned install can be applied", plan.id, status);
+        }
+        let mut guard = RunningPlan { store: store.clone(), id: plan.id.clone(), armed: true };
+
+        // Forward output lines as events while commands run
+        let (tx, mut rx) = mpsc::unbounded_channel::<(Stream, String)>();
+        let forward = {
+            let this = self.clone();
+            let pid = plan.id.clone();
+            tokio::spawn(async move {
+                while let Some((stream, line)) = rx.recv().await {
+                    this.event("installer:output", json!({"plan_id": pid, "stream": stream.as_str(), "line": line})).await;
+                }
+            })
+        };
+
+        let runner = self.runner();
+        let mut failure: Option<JsonValue> = None;
+        for (i, cmd) in plan.commands.iter().enumerate() {
+            self.event("installer:step", json!({"plan_id": plan.id, "step": i, "command": display_argv(cmd)})).await;
+            match runner.run(cmd, Some(tx.clone())).await {
+                Ok(out) if out.success() => {}
+                Ok(out) => {
+                    failure = Some(json!({"step": i, "command": display_argv(cmd), "exit_code": out.code, "stderr": tail(&out.stderr, 20)}));
+                    break;
+                }
+                Err(e) => {
+                    failure = Some(json!({"step": i, "command": display_argv(cmd), "error": e.to_string()}));
+                    break;
+                }
+            }
+        }
+        drop(tx);
+        let _ = forward.await;
+
+        let result = if let Some(f) = failure {
+            json!({
+                "plan_id": plan.id,
+                "applied": false,
+                "failed": f,
+                "rollback": plan.rollback_lines(),
+                "note": "earlier steps may have partially completed; review rollback commands before retrying",
+            })
+        } else {
+            let version = match runner.run(&version_command(mgr, &plan.pkg), None).await {
+                Ok(out) if out.success() => parse_installed_version(mgr, &plan.pkg, &out.stdout),
+                _ => None,
+            };
+            if let Some(map) = self.system_map.as_ref() {
+                let rec = InstalledPackage {
+                    manager: plan.manager.clone(),
+                    name: plan.pkg.clone(),
+                    version: version.clone(),
+                    plan_id: Some(plan.id.clone()),
+                    installed_at: chrono::Utc::now(),
+                };
+                if let Err(e) = map.record_installed(vec![rec]).await {
+                    tracing::warn!(error=%e, "failed to record install in system map");
+                }
+                map.request_refresh("installer.apply_install");
+            }
+            json!({
+                "plan_id": plan.id,
+                "applied": true,
+                "installed": [{"manager": plan.manager, "name": plan.pkg, "version": version}],
+                "rollback": plan.rollback_lines(),
+            })
+        };
+        let applied = result["applied"].as_bool().unwrap_or(false);
+        let status = if applied { "applied" } else { "failed" };
+        store.finish_install_plan(&plan.id, status, &result.to_string()).await?;
+        guard.armed = false;
+        self.event("installer:finished", json!({"plan_id": plan.id, "status": status})).await;
+        Ok(result)
+    }
+
+    /// Tool dispatch for the `installer` server.
+    pub async fn invoke(&self, tool: &str, params: JsonValue) -> Result<JsonValue> {
+        let plan_id = || params.get("plan_id").and_then(|v| v.as_str()).unwrap_or("").to_string();
+        match tool {
+            "plan_install" => {
+                let pkg = params.get("pkg").and_then(|v| v.as_str()).unwrap_or("");
+                let manager = params.get("manager").and_then(|v| v.as_str());
+                let remote = params.get("remote").and_then(|v| v.as_str());
+                let plan = self.plan(pkg, manager, remote).await?;
+                Ok(json!({
+                    "plan_id": plan.id,
+                    "manager": plan.manager,
+                    "pkg": plan.pkg,
+                    "commands": plan.command_lines(),
+                    "argv": plan.commands,
+                    "rollback": plan.rollback_lines(),
+                }))
+            }
+            "explain_install" => {
+                let p = self.require(&plan_id()).await?;
+                Ok(json!({
+                    "plan_id": p.id,
+                    "explain": format!(
+                        "Install {} via {}. Commands run directly (no shell) after approval; run dry_run first to preview changes. Rollback: {}.",
+                        p.pkg, p.manager, p.rollback_lines().join("; ")
+                    ),
+                    "commands": p.command_lines(),
+                    "rollback": p.rollback_lines(),
+                    "status": p.status,
+                    "dry_run": p.dry_run,
+                }))
+            }
+            "dry_run" => self.dry_run(&plan_id()).await,
+            "apply_install" => self.apply(&plan_id()).await,
+            _ => Err(anyhow!("unknown tool")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+
+    #[test]
+    fn validates_names_per_manager() {
+        assert!(validate_package(Manager::Apt, "ripgrep").is_ok());
+        assert!(validate_package(Manager::Apt, "libstdc++6:amd64").is_ok());
+        assert!(validate_package(Manager::Apt, "Ripgrep").is_err());
+        assert!(validate_package(Manager::Apt, "rg; rm -rf /").is_err());
+        assert!(validate_package(Manager::Apt, "-oDebug::pkgDPkgPm=1").is_err());
+        assert!(validate_package(Manager::Pip, "requests").is_ok());
+        assert!(validate_package(Manager::Pip, "zope.interface").is_ok());
+        assert!(validate_package(Manager::Pip, "../evil.whl").is_err());
+        assert!(validate_package(Manager::Pip, "git+https://x/y").is_err());
+        assert!(validate_package(Manager::Cargo, "cargo-edit").is_ok());
+        assert!(validate_package(Manager::Cargo, "1bad").is_err());
+        assert!(validate_package(Manager::Snap, "code").is_ok());
+        assert!(validate_package(Manager::Snap, "bad--name").is_err());
+        assert!(validate_package(Manager::Flatpak, "org.mozilla.firefox").is_ok());
+        assert!(validate_package(Manager::Flatpak, "firefox").is_err());
+    }
+
+    #[test]
+    fn parses_simulation_output() {
+        let apt = "NOTE: This is only a simulation!\nInst libpcre2-8-0 [10.39-3] (10.42-1 Debian:12/stable [amd64])\nInst ripgrep (13.0.0-4 Debian:12/stable [amd64])\nConf ripgrep (13.0.0-4 Debian:12/stable [amd64])\n";
+        assert_eq!(
+            parse_apt_simulation(apt),
+            vec![
+                PackageChange { name: "libpcre2-8-0".into(), version: Some("10.42-1".into()) },
+                PackageChange { name: "ripgrep".into(), version: Some("13.0.0-4".into()) },
+            ]
+        );
+        let pip = "Collecting requests\nWould install certifi-2024.2.2 charset_normalizer-3.3.2 requests-2.31.0\n";
+        let changes = parse_pip_dry_run(pip);
+        assert_eq!(changes.len(), 3);
+        assert_eq!(changes[2], PackageChange { name: "requests".into(), version: Some("2.31.0".into()) });
+        let cargo = "cargo-edit v0.12.2:\n    cargo-add\n    cargo-rm\nripgrep v14.1.0 (/src/ripgrep):\n    rg\n";
+        let list = parse_cargo_list(cargo);
+        assert_eq!(list[1], PackageChange { name: "ripgrep".into(), version: Some("14.1.0".into()) });
+        assert_eq!(parse_installed_version(Manager::Cargo, "cargo-edit", cargo).as_deref(), Some("0.12.2"));
+        assert_eq!(parse_installed_version(Manager::Pip, "requests", "Name: requests\nVersion: 2.31.0\n").as_deref(), Some("2.31.0"));
+    }
+
+    /// Replies by program name and records every argv it was given.
+    #[derive(Default)]
+    struct FakeRunner {
+        calls: Mutex<Vec<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl CommandRunner for FakeRunner {
+        async fn run(&self, argv: &[String], lines: Option<mpsc::UnboundedSender<(Stream, String)>>) -> Result<RunOutput> {
+            self.calls.lock().push(argv.to_vec());
+            let joined = argv.join(" ");
+            let stdout = if joined.contains("install --list") {
+                let n = self.calls.lock().iter().filter(|c| c.join(" ").contains("install --list")).count();
+                if n > 1 { "tokei v12.1.2:\n    tokei\n" } else { "" }
+            } else if joined.contains("cargo install tokei") {
+                "Installed package `tokei v12.1.2`"
+            } else {
+                ""
+            };
+            if let Some(tx) = lines {
+                for l in stdout.lines() { let _ = tx.send((Stream::Stdout, l.to_string())); }
+            }
+            Ok(RunOutput { code: Some(0), stdout: stdout.to_string(), stderr: String::new() })
+        }
+    }
+
+    async fn installer(dir: &std::path::Path) -> (Installer, Memory, SystemMapManager) {
+        let migrations = std::path::PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/migrations"));
+        let mem = Memory::init_in_memory(migrations).await.unwrap();
+        let map = SystemMapManager::new(dir, Some(mem.clone()));
+        (Installer::new(Some(mem.clone()), Some(map.clone())), mem, map)
+    }
+
+    #[tokio::test]
+    async fn plan_persists_and_apply_records_version() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (inst, mem, map) = installer(tmp.path()).await;
+        let runner = Arc::new(FakeRunner::default());
+        inst.set_runner(runner.clone());
+
+        assert!(inst.plan("tokei; reboot", Some("cargo"), None).await.is_err());
+        let plan = inst.plan("tokei", Some("cargo"), None).await.unwrap();
+        assert_eq!(plan.commands, vec![vec!["cargo".to_string(), "install".into(), "tokei".into()]]);
+
+        let dry = inst.dry_run(&plan.id).await.unwrap();
+        assert_eq!(dry["already_installed"], false);
+        assert_eq!(dry["changes"][0]["name"], "tokei");
+
+        let res = inst.apply(&plan.id).await.unwrap();
+        assert_eq!(res["applied"], true);
+        assert_eq!(res["installed"][0]["version"], "12.1.2");
+
+        let stored = inst.get(&plan.id).await.unwrap().unwrap();
+        assert_eq!(stored.status, "applied");
+        assert!(stored.dry_run.is_some());
+
+        let installed = map.get_map().unwrap().installed;
+        assert_eq!(installed.len(), 1);
+        assert_eq!(installed[0].version.as_deref(), Some("12.1.2"));
+
+        let kinds: Vec<String> = mem.store.get_recent_events(20).await.unwrap().into_iter().map(|e| e.kind).collect();
+        assert!(kinds.iter().any(|k| k == "installer:output"), "{:?}", kinds);
+        assert!(kinds.iter().any(|k| k == "installer:finished"));
+    }
+
+    #[tokio::test]
+    async fn a_plan_is_applied_once() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (inst, _mem, _map) = installer(tmp.path()).await;
+        let runner = Arc::new(FakeRunner::default());
+        inst.set_runner(runner.clone());
+
+        let plan = inst.plan("tokei", Some("cargo"), None).await.unwrap();
+        let (a, b) = tokio::join!(inst.apply(&plan.id), inst.apply(&plan.id));
+        assert!(a.is_ok() != b.is_ok(), "{:?} / {:?}", a, b);
+        let installs = runner.calls.lock().iter().filter(|c| c.join(" ") == "cargo install tokei").count();
+        assert_eq!(installs, 1);
+
+        let again = inst.apply(&plan.id).await.unwrap_err().to_string();
+        assert!(again.contains("is applied"), "{}", again);
+    }
+
+    #[tokio::test]
+    async fn flatpak_plans_use_the_requested_remote() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (inst, _mem, _map) = installer(tmp.path()).await;
+        let runner = Arc::new(FakeRunner::default());
+        inst.set_runner(runner.clone());
+
+        assert!(inst.plan("org.gnome.Maps", Some("flatpak"), Some("--user")).await.is_err());
+        assert!(inst.plan("tokei", Some("cargo"), Some("flathub")).await.is_err());
+        let plan = inst.plan("org.gnome.Maps", Some("flatpak"), Some("gnome-nightly")).await.unwrap();
+        assert_eq!(plan.command_lines(), vec!["flatpak install -y --noninteractive gnome-nightly org.gnome.Maps"]);
+        let dry = inst.dry_run(&plan.id).await.unwrap();
+        assert_eq!(dry["simulation"], "flatpak remote-info gnome-nightly org.gnome.Maps");
+
+        let plan = inst.plan("org.gnome.Maps", Some("flatpak"), None).await.unwrap();
+        let dry = inst.dry_run(&plan.id).await.unwrap();
+        assert_eq!(dry["simulation"], "flatpak remote-info flathub org.gnome.Maps");
+    }
+
+    /// Never finishes, like a package manager stuck on a lock.
+    struct HangingRunner;
+
+    #[async_trait]
+    impl CommandRunner for HangingRunner {
+        async fn run(&self, _argv: &[String], _lines: Option<mpsc::UnboundedSender<(Stream, String)>>) -> Result<RunOutput> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn interrupted_applies_do_not_stay_running() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (inst, mem, _map) = installer(tmp.path()).await;
+        inst.set_runner(Arc::new(HangingRunner));
+
+        // Dropping the apply future mid-command fails the plan
+        let plan = inst.plan("tokei", Some("cargo"), None).await.unwrap();
+        let timed_out = tokio::time::timeout(std::time::Duration::from_millis(50), inst.apply(&plan.id)).await;
+        assert!(timed_out.is_err());
+        let mut status = String::new();
+        for _ in 0..50 {
+            status = inst.get(&plan.id).await.unwrap().unwrap().status;
+            if status != "running" { break; }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(status, "failed");
+
+        // A plan left running by a crash is failed on the next start
+        let stale = inst.plan("ripgrep", Some("cargo"), None).await.unwrap();
+        assert!(mem.store.transition_install_plan(&stale.id, "planned", "running").await.unwrap());
+        inst.recover_interrupted().await;
+        let stale = inst.get(&stale.id).await.unwrap().unwrap();
+        assert_eq!(stale.status, "failed");
+        assert_eq!(stale.result.unwrap()["applied"], false);
+    }
+}
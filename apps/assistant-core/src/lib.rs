@@ -9,13 +9,24 @@ pub mod scheduler;
 pub mod telemetry;
 pub mod system_map;
 pub mod tools;
+pub mod installer;
 pub mod metrics;
 pub mod realtime;
 pub mod realtime_audio;
+pub mod duplex;
+pub mod realtime_record;
+pub mod realtime_sessions;
+#[cfg(feature = "realtime-gateway")]
+pub mod realtime_gateway;
 pub mod wake;
+pub mod wake_kws;
 pub mod prompt;
 pub mod research;
 pub mod agents;
 #[cfg(feature = "wake-porcupine")]
 pub mod wake_porcupine;
 pub mod stt;
+pub mod tts;
+pub mod notes;
+pub mod chats;
+pub mod chat_context;
@@ -8,11 +8,20 @@ mod scheduler;
 mod telemetry;
 mod system_map;
 mod tools;
+mod installer;
 mod metrics;
 mod realtime;
 mod realtime_audio;
+mod duplex;
+mod realtime_record;
+mod realtime_sessions;
 mod wake;
+mod wake_kws;
 mod stt;
+mod tts;
+mod notes;
+mod chats;
+mod chat_context;
 mod prompt;
 mod research;
 mod agents;
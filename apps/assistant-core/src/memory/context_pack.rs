@@ -16,14 +16,18 @@ pub struct Dropped { pub cards: usize, pub expansions: usize }
 pub struct ContextPack {
     pub system_digest: String,
     pub task_digest: Option<String>,
+    /// Transcript digest of the last realtime calls; spent from the budget before cards.
+    pub recent_calls: Option<String>,
     pub cards: Vec<Card>,
     pub expansions: Vec<String>,
     pub dropped: Dropped,
 }
 
-pub fn build_pack(system_digest: &str, task_digest: Option<&str>, mut cards: Vec<Card>, budget_tokens: usize, expansions: Vec<String>) -> ContextPack {
+pub fn build_pack(system_digest: &str, task_digest: Option<&str>, recent_calls: Option<&str>, mut cards: Vec<Card>, budget_tokens: usize, expansions: Vec<String>) -> ContextPack {
+    // Call history gets at most half the budget (newest lines kept) so cards still fit
+    let recent_calls = recent_calls.and_then(|c| newest_lines(c, budget_tokens / 2));
     // naive budget: sum card tokens; drop from end if exceeds
-    let mut used = 0usize;
+    let mut used = recent_calls.as_deref().map(tokens_est).unwrap_or(0);
     let mut keep: Vec<Card> = vec![];
     let mut total = 0usize;
     for c in cards.drain(..) {
@@ -34,8 +38,25 @@ pub fn build_pack(system_digest: &str, task_digest: Option<&str>, mut cards: Vec
     ContextPack {
         system_digest: system_digest.to_string(),
         task_digest: task_digest.map(|s| s.to_string()),
+        recent_calls,
         cards: keep,
         expansions,
         dropped,
     }
 }
+
+pub fn tokens_est(text: &str) -> usize { (text.len() / 4).max(1) }
+
+/// The trailing lines of `text` that fit in `max_tokens`.
+pub fn newest_lines(text: &str, max_tokens: usize) -> Option<String> {
+    let mut keep: Vec<&str> = vec![];
+    let mut len = 0usize;
+    for line in text.lines().rev() {
+        if (len + line.len() + 1) / 4 > max_tokens { break; }
+        len += line.len() + 1;
+        keep.push(line);
+    }
+    if keep.iter().all(|l| l.trim().is_empty()) { return None; }
+    keep.reverse();
+    Some(keep.join("\n"))
+}
@@ -0,0 +1,459 @@
+//! Voice notes: dictation captured until silence, transcribed with the local
+//! recognizer and filed in memory as `note` atoms with the recording as an artifact.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+
+/// Resolved `[voice] notes` settings ([`crate::config::NotesConfig`]).
+#[derive(Debug, Clone)]
+pub struct NoteOptions {
+    /// Task title for notes that don't name one (created on first use).
+    pub task: String,
+    /// Where recordings are written.
+    pub dir: PathBuf,
+    /// Trailing quiet that ends a dictation.
+    pub silence_ms: u32,
+    pub max_seconds: u32,
+    /// Give up when nothing is said for this long.
+    pub lead_in_ms: u32,
+    pub vad_sensitivity: f32,
+    /// After the wake word, listen for "take a note" / "what did I note about …"
+    /// before falling through to a realtime session.
+    pub wake_commands: bool,
+    pub stt: crate::stt::SttOptions,
+}
+
+impl Default for NoteOptions {
+    fn default() -> Self {
+        Self {
+            task: "Voice notes".into(), dir: PathBuf::from("storage/notes"), silence_ms: 1_500, max_seconds: 120,
+            lead_in_ms: 4_000, vad_sensitivity: 0.5, wake_commands: false, stt: Default::default(),
+        }
+    }
+}
+
+impl NoteOptions {
+    pub fn from_config(cfg: Option<&crate::config::NotesConfig>, dir: PathBuf, stt: crate::stt::SttOptions, vad_sensitivity: f32) -> Self {
+        let d = Self::default();
+        let c = cfg.cloned().unwrap_or_default();
+        Self {
+            task: c.task.unwrap_or(d.task),
+            dir: c.dir.map(PathBuf::from).unwrap_or(dir),
+            silence_ms: c.silence_ms.unwrap_or(d.silence_ms),
+            max_seconds: c.max_seconds.unwrap_or(d.max_seconds),
+            lead_in_ms: c.lead_in_ms.unwrap_or(d.lead_in_ms),
+            vad_sensitivity,
+            wake_commands: c.wake_commands.unwrap_or(false),
+            stt,
+        }
+    }
+}
+
+// ---- Capture ----
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GateEnd {
+    Silence,
+    MaxLength,
+    /// Nothing was said before the lead-in ran out.
+    NoSpeech,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GateState {
+    Waiting,
+    Speaking,
+    Done(GateEnd),
+}
+
+/// Speech that counts as an onset: one 30 ms capture frame.
+const ONSET_MS: u32 = 30;
+
+/// Collects one utterance on the duplex VAD: waits for speech, then ends after enough
+/// trailing quiet or at the length cap. Keeps a short pre-roll so onsets aren't clipped.
+pub struct UtteranceGate {
+    vad: crate::duplex::Vad,
+    sample_rate: u32,
+    silence: usize,
+    max: usize,
+    lead_in: usize,
+    pre_roll: usize,
+    seen: usize,
+    quiet: usize,
+    started: bool,
+    end: Option<GateEnd>,
+    pcm: Vec<i16>,
+}
+
+impl UtteranceGate {
+    pub fn new(sample_rate: u32, silence_ms: u32, max_ms: u32, lead_in_ms: u32, vad_sensitivity: f32) -> Self {
+        let samples = |ms: u32| ms as usize * sample_rate as usize / 1000;
+        Self {
+            vad: crate::duplex::Vad::new(vad_sensitivity, ONSET_MS), sample_rate,
+            silence: samples(silence_ms), max: samples(max_ms), lead_in: samples(lead_in_ms), pre_roll: samples(300),
+            seen: 0, quiet: 0, started: false, end: None, pcm: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, frame: &[i16]) -> GateState {
+        if let Some(end) = self.end { return GateState::Done(end); }
+        let frame_ms = frame.len() as f32 * 1000.0 / self.sample_rate as f32;
+        let speech = self.vad.push(frame, frame_ms, 0.0);
+        self.seen += frame.len();
+        self.pcm.extend_from_slice(frame);
+        if speech {
+            self.started = true;
+            self.quiet = 0;
+        } else {
+            self.quiet += frame.len();
+        }
+        if !self.started {
+            if self.pcm.len() > self.pre_roll { self.pcm.drain(..self.pcm.len() - self.pre_roll); }
+            if self.seen >= self.lead_in { self.end = Some(GateEnd::NoSpeech); }
+        } else if self.quiet >= self.silence {
+            self.end = Some(GateEnd::Silence);
+        } else if self.pcm.len() >= self.max {
+            self.end = Some(GateEnd::MaxLength);
+        }
+        match self.end {
+            Some(end) => GateState::Done(end),
+            None if self.started => GateState::Speaking,
+            None => GateState::Waiting,
+        }
+    }
+
+    pub fn heard_speech(&self) -> bool { self.started }
+
+    /// The utterance with trailing quiet cut back to the pre-roll length.
+    pub fn into_pcm(mut self) -> Vec<i16> {
+        if !self.started { return Vec::new(); }
+        let cut = self.quiet.saturating_sub(self.pre_roll).min(self.pcm.len());
+        self.pcm.truncate(self.pcm.len() - cut);
+        self.pcm
+    }
+}
+
+/// Feed frames through the gate until it closes (or the source does).
+pub async fn capture(frames: &mut mpsc::Receiver<Vec<i16>>, gate: &mut UtteranceGate) -> GateEnd {
+    while let Some(f) = frames.recv().await {
+        if let GateState::Done(end) = gate.push(&f) { return end; }
+    }
+    if gate.heard_speech() { GateEnd::Silence } else { GateEnd::NoSpeech }
+}
+
+/// Record from the default microphone until the gate closes.
+#[cfg(feature = "realtime-audio")]
+pub async fn record(mut gate: UtteranceGate) -> Result<(Vec<i16>, GateEnd)> {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    let (tx, mut rx) = mpsc::channel::<Vec<i16>>(64);
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel::<Result<()>>();
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_for_thread = stop.clone();
+    // cpal streams are !Send; keep the capture on its own thread
+    std::thread::spawn(move || match crate::realtime_audio::start_capture(16000, 30, tx) {
+        Ok(_cap) => {
+            let _ = ready_tx.send(Ok(()));
+            while !stop_for_thread.load(Ordering::SeqCst) { std::thread::sleep(std::time::Duration::from_millis(20)); }
+        }
+        Err(e) => { let _ = ready_tx.send(Err(e)); }
+    });
+    ready_rx.await.map_err(|_| anyhow::anyhow!("capture thread exited"))??;
+    let end = capture(&mut rx, &mut gate).await;
+    stop.store(true, Ordering::SeqCst);
+    Ok((gate.into_pcm(), end))
+}
+
+// ---- Spoken commands ----
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VoiceCommand {
+    /// "take a note [for task X][: text]"; without text the dictation follows.
+    TakeNote { task: Option<String>, text: Option<String> },
+    /// "what did I note about X"
+    Recall { query: String },
+}
+
+const TAKE_NOTE: &[&str] = &["take a note", "take note", "make a note", "note to self", "note that"];
+const RECALL: &[&str] = &[
+    "what did i note about", "what did i note on", "what did i say about", "what notes do i have about",
+    "what notes do i have on", "find my notes about", "find my notes on", "read my notes about", "read my notes on",
+];
+/// Words allowed before a command ("hey vim", "can you", "please").
+const MAX_PREAMBLE: usize = 4;
+
+/// Recognise a note command in a transcript; anything else is None.
+pub fn parse_command(text: &str) -> Option<VoiceCommand> {
+    // Original tokens alongside their normalized form so note text keeps its punctuation
+    let tokens: Vec<(&str, String)> = text.split_whitespace()
+        .map(|t| (t, t.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_lowercase()))
+        .filter(|(_, w)| !w.is_empty())
+        .collect();
+    let words: Vec<&str> = tokens.iter().map(|(_, w)| w.as_str()).collect();
+    let find = |patterns: &[&str]| -> Option<usize> {
+        (0..=MAX_PREAMBLE.min(words.len())).find_map(|start| {
+            patterns.iter().find_map(|p| {
+                let p: Vec<&str> = p.split(' ').collect();
+                (words.len() >= start + p.len() && words[start..start + p.len()] == p[..]).then_some(start + p.len())
+            })
+        })
+    };
+    let rest = |from: usize| -> String {
+        let s = tokens[from..].iter().map(|(t, _)| *t).collect::<Vec<_>>().join(" ");
+        s.trim_matches(|c: char| !c.is_alphanumeric() && c != '\'' && c != '"').to_string()
+    };
+    if let Some(at) = find(RECALL) {
+        let query = rest(at);
+        return (!query.is_empty()).then_some(VoiceCommand::Recall { query });
+    }
+    let mut at = find(TAKE_NOTE)?;
+    // "for task groceries: …" / "on the project roadmap, …"
+    let mut task = None;
+    if matches!(words.get(at), Some(&("for" | "on" | "in" | "under" | "to"))) {
+        let mut i = at + 1;
+        if words.get(i) == Some(&"the") { i += 1; }
+        if matches!(words.get(i), Some(&("task" | "project"))) {
+            let name_start = i + 1;
+            let mut end = name_start;
+            while end < tokens.len() && words[end] != "that" {
+                end += 1;
+                if tokens[end - 1].0.ends_with([':', ',', '.']) { break; }
+            }
+            let name = tokens[name_start..end].iter().map(|(t, _)| *t).collect::<Vec<_>>().join(" ");
+            let name = name.trim_matches(|c: char| !c.is_alphanumeric()).to_string();
+            if !name.is_empty() { task = Some(name); at = end; }
+        }
+    }
+    if words.get(at) == Some(&"that") { at += 1; }
+    let text = rest(at);
+    Some(VoiceCommand::TakeNote { task, text: (!text.is_empty()).then_some(text) })
+}
+
+// ---- Filing and recall ----
+
+/// A note to file: text, or audio to transcribe, or both.
+#[derive(Debug, Clone, Default)]
+pub struct NewNote {
+    pub text: Option<String>,
+    pub pcm: Vec<i16>,
+    pub sample_rate: u32,
+    pub task_id: Option<i64>,
+    pub task: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NoteRecord {
+    pub atom_id: i64,
+    pub task_id: i64,
+    pub task: String,
+    pub text: String,
+    pub artifact_id: Option<i64>,
+    pub audio: Option<PathBuf>,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NoteHit {
+    pub atom_id: i64,
+    pub task_id: i64,
+    pub text: String,
+    pub snippet: String,
+    pub created_at: DateTime<Utc>,
+    /// Recording path, for notes that were dictated.
+    pub audio: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Recall {
+    pub query: String,
+    pub hits: Vec<NoteHit>,
+    /// What gets read back.
+    pub spoken: String,
+}
+
+#[derive(Clone)]
+pub struct Notes {
+    pub opts: NoteOptions,
+    memory: Option<crate::memory::Memory>,
+    tts: crate::tts::TtsService,
+}
+
+impl Notes {
+    pub fn new(opts: NoteOptions, memory: Option<crate::memory::Memory>, tts: crate::tts::TtsService) -> Self {
+        Self { opts, memory, tts }
+    }
+
+    fn store(&self) -> Result<&foreman_memory::MemoryStore> {
+        self.memory.as_ref().map(|m| &m.store).ok_or_else(|| anyhow::anyhow!("memory not initialized"))
+    }
+
+    /// Gate for a dictation.
+    pub fn dictation_gate(&self, sample_rate: u32) -> UtteranceGate {
+        let o = &self.opts;
+        UtteranceGate::new(sample_rate, o.silence_ms, o.max_seconds.saturating_mul(1000), o.lead_in_ms, o.vad_sensitivity)
+    }
+
+    /// Gate for the short command that follows the wake word.
+    pub fn command_gate(&self, sample_rate: u32) -> UtteranceGate {
+        UtteranceGate::new(sample_rate, 700, 6_000, 2_500, self.opts.vad_sensitivity)
+    }
+
+    pub async fn transcribe(&self, pcm: &[i16], sample_rate: u32) -> Result<String> {
+        let stream = crate::stt::open_stream(&self.opts.stt, sample_rate).await?;
+        // 100 ms chunks, as a live source would deliver them
+        for chunk in pcm.chunks((sample_rate as usize / 10).max(1)) { stream.push(chunk); }
+        Ok(stream.finish().await?.trim().to_string())
+    }
+
+    /// Task by id, else by title (case-insensitive), else the default notes task;
+    /// a named task that doesn't exist yet is created.
+    pub async fn resolve_task(&self, task_id: Option<i64>, title: Option<&str>) -> Result<foreman_memory::Task> {
+        let store = self.store()?;
+        let tasks = store.list_tasks().await?;
+        if let Some(id) = task_id {
+            return tasks.into_iter().find(|t| t.id == id).ok_or_else(|| anyhow::anyhow!("task {} not found", id));
+        }
+        let title = title.map(str::trim).filter(|t| !t.is_empty()).unwrap_or(&self.opts.task);
+        if let Some(t) = tasks.into_iter().find(|t| t.title.eq_ignore_ascii_case(title)) { return Ok(t); }
+        store.create_task(title, "open", Some("notes")).await
+    }
+
+    /// Transcribe (unless text is given), save the recording and file the note.
+    pub async fn file(&self, note: NewNote) -> Result<NoteRecord> {
+        let store = self.store()?;
+        let sr = if note.sample_rate == 0 { 16_000 } else { note.sample_rate };
+        let text = match note.text.map(|t| t.trim().to_string()).filter(|t| !t.is_empty()) {
+            Some(t) => t,
+            None if note.pcm.is_empty() => anyhow::bail!("a note needs text or audio"),
+            None => self.transcribe(&note.pcm, sr).await?,
+        };
+        if text.is_empty() { anyhow::bail!("nothing was transcribed"); }
+        let task = self.resolve_task(note.task_id, note.task.as_deref()).await?;
+        let (mut artifact_id, mut audio) = (None, None);
+        if !note.pcm.is_empty() {
+            tokio::fs::create_dir_all(&self.opts.dir).await?;
+            let path = self.opts.dir.join(format!("{}-{}.wav", chrono::Local::now().format("%Y%m%d-%H%M%S"), &uuid::Uuid::new_v4().to_string()[..8]));
+            tokio::fs::write(&path, crate::stt::encode_wav_pcm16(&note.pcm, sr)).await?;
+            artifact_id = Some(store.create_artifact(task.id, &path, Some("audio/wav"), None).await?);
+            audio = Some(path);
+        }
+        let atom_id = match artifact_id {
+            Some(a) => store.put_atom_sourced(task.id, "note", &text, Some("voice"), "voice", Some(&format!("artifact:{}", a))).await?,
+            None => store.put_atom(task.id, "note", &text, None).await?,
+        };
+        let _ = store.append_event(Some(task.id), "note:filed", Some(&serde_json::json!({"atom_id": atom_id, "artifact_id": artifact_id}))).await;
+        Ok(NoteRecord {
+            atom_id, task_id: task.id, task: task.title, text, artifact_id, audio,
+            duration_ms: note.pcm.len() as u64 * 1000 / sr as u64,
+        })
+    }
+
+    /// Search notes for `query` and compose a read-back.
+    pub async fn recall(&self, query: &str, task_id: Option<i64>, k: usize) -> Result<Recall> {
+        let store = self.store()?;
+        let mut hits = Vec::new();
+        if let Some(fts) = fts_query(query) {
+            // Other atom kinds share the index; over-fetch and keep notes
+            for h in store.search_atoms(&fts, task_id, (k * 4).max(20) as i64).await? {
+                let Some(atom) = store.get_atom_full(h.atom_id).await? else { continue };
+                if atom.kind != "note" { continue; }
+                let audio = match atom.source_ref.as_deref().and_then(|r| r.strip_prefix("artifact:")).and_then(|id| id.parse().ok()) {
+                    Some(id) => store.get_artifact(id).await?.map(|a| a.path),
+                    None => None,
+                };
+                hits.push(NoteHit { atom_id: atom.id, task_id: atom.task_id, text: atom.text, snippet: h.snippet, created_at: atom.created_at, audio });
+                if hits.len() >= k { break; }
+            }
+        }
+        let spoken = spoken_summary(query, &hits);
+        Ok(Recall { query: query.to_string(), hits, spoken })
+    }
+
+    /// Read text aloud; false without TTS playback.
+    pub fn say(&self, text: &str) -> bool {
+        self.tts.speak(text, crate::tts::Priority::Normal).is_some()
+    }
+}
+
+const STOPWORDS: &[&str] = &["a", "an", "and", "the", "of", "to", "for", "on", "in", "my", "me", "i", "about", "what", "is", "it"];
+
+/// FTS5 query from free speech: bare words ORed together (bm25 ranks the best match first).
+fn fts_query(q: &str) -> Option<String> {
+    let words: Vec<String> = q.split(|c: char| !c.is_alphanumeric())
+        .map(|w| w.to_lowercase())
+        .filter(|w| w.len() > 1 && !STOPWORDS.contains(&w.as_str()))
+        .map(|w| format!("\"{}\"", w))
+        .collect();
+    (!words.is_empty()).then(|| words.join(" OR "))
+}
+
+fn spoken_summary(query: &str, hits: &[NoteHit]) -> String {
+    match hits {
+        [] => format!("I couldn't find any notes about {}.", query),
+        [one] => format!("You noted one thing about {}: {}", query, end_sentence(&one.text)),
+        many => {
+            let mut s = format!("You noted {} things about {}.", many.len(), query);
+            for h in many.iter().take(5) {
+                s.push(' ');
+                s.push_str(&end_sentence(&h.text));
+            }
+            s
+        }
+    }
+}
+
+fn end_sentence(text: &str) -> String {
+    let t = text.trim();
+    if t.ends_with(['.', '!', '?']) { t.to_string() } else { format!("{}.", t) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_note_and_recall_commands() {
+        assert_eq!(parse_command("Hey Vim, take a note."), Some(VoiceCommand::TakeNote { task: None, text: None }));
+        assert_eq!(
+            parse_command("hey vim take a note for task Groceries: buy oat milk, eggs"),
+            Some(VoiceCommand::TakeNote { task: Some("Groceries".into()), text: Some("buy oat milk, eggs".into()) })
+        );
+        assert_eq!(
+            parse_command("Note that the dentist moved to Friday."),
+            Some(VoiceCommand::TakeNote { task: None, text: Some("the dentist moved to Friday".into()) })
+        );
+        assert_eq!(parse_command("hey vim, what did I note about the dentist?"), Some(VoiceCommand::Recall { query: "the dentist".into() }));
+        assert_eq!(parse_command("what did I note about"), None);
+        assert_eq!(parse_command("hey vim what's the weather"), None);
+        assert_eq!(parse_command("so I was thinking we should take a note of that"), None, "command must lead");
+        assert_eq!(fts_query("the dentist's address?").as_deref(), Some("\"dentist\" OR \"address\""));
+        assert_eq!(fts_query("about it"), None);
+    }
+
+    #[test]
+    fn gate_ends_on_silence_and_trims() {
+        let loud = vec![3_000i16; 480];
+        let quiet = vec![0i16; 480];
+        let mut gate = UtteranceGate::new(16_000, 600, 10_000, 2_000, 0.5);
+        for _ in 0..20 { assert_eq!(gate.push(&quiet), GateState::Waiting); }
+        for _ in 0..30 { assert_eq!(gate.push(&loud), GateState::Speaking); }
+        let mut end = None;
+        for _ in 0..40 { if let GateState::Done(e) = gate.push(&quiet) { end = Some(e); break; } }
+        assert_eq!(end, Some(GateEnd::Silence));
+        // 300 ms pre-roll + speech + 300 ms of the trailing quiet
+        assert_eq!(gate.into_pcm().len(), 4_800 + 30 * 480 + 4_800);
+
+        let mut gate = UtteranceGate::new(16_000, 600, 10_000, 1_000, 0.5);
+        let states: Vec<GateState> = (0..40).map(|_| gate.push(&quiet)).collect();
+        assert!(states.contains(&GateState::Done(GateEnd::NoSpeech)));
+        assert!(gate.into_pcm().is_empty());
+
+        let mut gate = UtteranceGate::new(16_000, 600, 1_000, 1_000, 0.5);
+        let states: Vec<GateState> = (0..40).map(|_| gate.push(&loud)).collect();
+        assert_eq!(states.last(), Some(&GateState::Done(GateEnd::MaxLength)));
+    }
+}
@@ -1,101 +1,448 @@
-pub fn base_system_prompt() -> String {
-    // Local context and tool usage policy for emulator + Steam flows
-    let roms_root = "/home/kil/games/roms";
-    let ds_emulator = "/home/kil/games/emulators/melonDS-x86_64.AppImage";
-    let gb_cmd = "mgba-qt";
-    let mut s = String::new();
-    s.push_str("Local Context:\n");
-    s.push_str(&format!("- Game ROMs folder: {}\n", roms_root));
-    s.push_str("- Emulators:\n");
-    s.push_str(&format!("  • Nintendo DS: {} (launch without arguments)\n", ds_emulator));
-    s.push_str(&format!("  • Game Boy / Game Boy Advance: {} (usage: mgba-qt /path/to/game)\n", gb_cmd));
-
-    // List available games by console (best effort)
-    if let Some(by_console) = scan_roms_root(roms_root) {
-        if !by_console.is_empty() {
-            s.push_str("\nAvailable games by console:\n");
-            for (console, titles) in by_console {
-                if titles.is_empty() { continue; }
-                s.push_str(&format!("- {}: ", console));
-                let joined = titles.join(", ");
-                s.push_str(&joined);
-                s.push('\n');
+//! System prompt assembled from template sections in `config/prompts/*.md`.
+//! Each file is one section: optional YAML front matter saying when it applies
+//! (loaded tool servers, voice or text mode, profile, variables that must not
+//! be empty) and a Markdown body with `{{var}}` placeholders. Sections render
+//! in file-name order and each reports its estimated tokens. Parsed sections
+//! and the ROM catalog are kept until their files change or `reload` is called.
+
+use crate::config::{Config, GamesConfig};
+use crate::memory::context_pack::tokens_est;
+use crate::system_map::SystemMapManager;
+use crate::tools::ToolsManager;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// Used when the section directory is missing or has no sections.
+const FALLBACK: &str = "Safety & Policy:\n- Risky actions require approval; propose the action and wait.\n- Use available tools via the provided schemas; avoid guessing paths.";
+
+/// Steam games listed in `{{steam_games}}`.
+const STEAM_GAMES_LISTED: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode { Text, Voice }
+
+impl Mode {
+    pub fn as_str(self) -> &'static str {
+        match self { Mode::Text => "text", Mode::Voice => "voice" }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() { "text" => Some(Mode::Text), "voice" => Some(Mode::Voice), _ => None }
+    }
+}
+
+/// Front matter of a section; every condition given must hold.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Front {
+    /// Tool servers that must be loaded.
+    tools: Vec<String>,
+    mode: Option<Mode>,
+    /// Profiles the section is for (any when empty).
+    profiles: Vec<String>,
+    /// Variables that must render non-empty.
+    requires: Vec<String>,
+    /// Longer bodies are cut to about this many tokens.
+    max_tokens: Option<usize>,
+}
+
+/// How one section fared in a render.
+#[derive(Debug, Clone, Serialize)]
+pub struct SectionReport {
+    pub name: String,
+    pub tokens: usize,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    /// Why the section was left out.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<String>,
+    /// Variables the body refers to.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub vars: Vec<String>,
+}
+
+impl SectionReport {
+    fn skipped(name: &str, vars: Vec<String>, reason: String) -> Self {
+        Self { name: name.into(), tokens: 0, truncated: false, skipped: Some(reason), vars }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Rendered {
+    pub text: String,
+    pub tokens: usize,
+    pub sections: Vec<SectionReport>,
+}
+
+impl Rendered {
+    /// Whether an included section used `var`.
+    pub fn uses(&self, var: &str) -> bool {
+        self.sections.iter().any(|s| s.skipped.is_none() && s.vars.iter().any(|v| v == var))
+    }
+}
+
+/// A section file as parsed, or why it could not be.
+type Parsed = Arc<Result<(Front, String), String>>;
+
+/// Modification times a cached value was built from.
+type Stamp = Vec<(PathBuf, Option<SystemTime>)>;
+
+#[derive(Default)]
+struct Cache {
+    sections: HashMap<PathBuf, (SystemTime, Parsed)>,
+    games_by_console: Option<(Stamp, String)>,
+}
+
+/// Renders the system prompt. Sections are re-read when their file changes,
+/// so edits apply from the next reply on.
+#[derive(Clone)]
+pub struct Prompts {
+    dir: PathBuf,
+    max_tokens: Option<usize>,
+    vars: BTreeMap<String, String>,
+    profile: String,
+    games: GamesConfig,
+    tools: Option<ToolsManager>,
+    system_map: Option<SystemMapManager>,
+    cache: Arc<Mutex<Cache>>,
+}
+
+impl Prompts {
+    pub fn from_config(cfg: &Config) -> Self {
+        let p = cfg.prompts.clone().unwrap_or_default();
+        Self {
+            dir: PathBuf::from(p.dir.unwrap_or_else(|| "config/prompts".into())),
+            max_tokens: p.max_tokens,
+            vars: p.vars,
+            profile: cfg.foreman.as_ref().and_then(|f| f.profile.clone()).unwrap_or_else(|| "default".into()),
+            games: cfg.games.clone().unwrap_or_default(),
+            tools: None,
+            system_map: None,
+            cache: Arc::default(),
+        }
+    }
+
+    /// Loaded tool servers gate sections and fill `{{tool_servers}}`.
+    pub fn with_tools(mut self, tools: ToolsManager) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+
+    /// Source of `{{system_digest}}`.
+    pub fn with_system_map(mut self, system_map: SystemMapManager) -> Self {
+        self.system_map = Some(system_map);
+        self
+    }
+
+    /// Forget parsed sections and the ROM catalog, for changes a modification
+    /// time does not show.
+    pub fn reload(&self) {
+        *self.cache.lock() = Cache::default();
+    }
+
+    pub fn render(&self, mode: Mode) -> Rendered {
+        self.render_sections(mode, false)
+    }
+
+    /// Only the sections written for `mode`, to follow caller-supplied instructions.
+    #[cfg_attr(not(feature = "realtime"), allow(dead_code))]
+    pub fn render_mode_sections(&self, mode: Mode) -> Rendered {
+        self.render_sections(mode, true)
+    }
+
+    fn render_sections(&self, mode: Mode, mode_only: bool) -> Rendered {
+        let files = section_files(&self.dir);
+        if files.is_empty() {
+            if mode_only { return Rendered { text: String::new(), tokens: 0, sections: vec![] }; }
+            let tokens = tokens_est(FALLBACK);
+            let report = SectionReport { name: "fallback".into(), tokens, truncated: false, skipped: None, vars: vec![] };
+            return Rendered { text: FALLBACK.into(), tokens, sections: vec![report] };
+        }
+        let servers = self.tools.as_ref().map(|t| t.servers()).unwrap_or_default();
+        let mut vars = Vars { prompts: self, mode, servers: &servers, cache: HashMap::new() };
+        let (mut bodies, mut sections, mut total) = (vec![], vec![], 0);
+        self.cache.lock().sections.retain(|path, _| files.iter().any(|(_, p)| p == path));
+        for (name, path) in files {
+            let parsed = self.section(&name, &path);
+            let (front, body) = match parsed.as_ref() {
+                Ok((front, body)) => (front, body.as_str()),
+                Err(reason) => { sections.push(SectionReport::skipped(&name, vec![], reason.clone())); continue; }
+            };
+            if mode_only && front.mode != Some(mode) { continue; }
+            let used = placeholders(body);
+            let skipped = if let Some(t) = front.tools.iter().find(|t| !servers.contains(t)) {
+                Some(format!("tool server {} not loaded", t))
+            } else if let Some(m) = front.mode.filter(|m| *m != mode) {
+                Some(format!("{} mode only", m.as_str()))
+            } else if !front.profiles.is_empty() && !front.profiles.contains(&self.profile) {
+                Some(format!("not for profile {}", self.profile))
+            } else {
+                front.requires.iter().find(|v| vars.get(v).trim().is_empty()).map(|v| format!("{} is empty", v))
+            };
+            if let Some(reason) = skipped { sections.push(SectionReport::skipped(&name, used, reason)); continue; }
+            let rendered = substitute(body, |v| vars.get(v));
+            let (body, truncated) = match front.max_tokens {
+                Some(max) => cap(rendered.trim(), max),
+                None => (rendered.trim().to_string(), false),
+            };
+            if body.is_empty() { sections.push(SectionReport::skipped(&name, used, "empty".into())); continue; }
+            let tokens = tokens_est(&body);
+            if self.max_tokens.is_some_and(|max| total + tokens > max) {
+                sections.push(SectionReport::skipped(&name, used, "over prompt budget".into()));
+                continue;
             }
+            total += tokens;
+            bodies.push(body);
+            sections.push(SectionReport { name, tokens, truncated, skipped: None, vars: used });
         }
+        Rendered { text: bodies.join("\n\n"), tokens: total, sections }
     }
 
-    // Steam quick-launch mapping from config/steamgames.toml
-    let steam_games = crate::api::load_steamgames_user_list();
-    if !steam_games.is_empty() {
-        s.push_str("\nSteam Games: appid\n");
-        for (name, appid) in steam_games.iter().take(20) {
-            s.push_str(&format!("- {}: {}\n", name, appid));
+    /// The section file parsed, from the cache while its modification time holds.
+    fn section(&self, name: &str, path: &Path) -> Parsed {
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        if let Some((at, parsed)) = self.cache.lock().sections.get(path) {
+            if Some(*at) == modified { return parsed.clone(); }
         }
-        if steam_games.len() > 20 { s.push_str(&format!("- … {} more\n", steam_games.len() - 20)); }
-        s.push_str("\nLaunch policy:\n");
-        s.push_str("- Prefer the steam.launch tool with the AppID.\n");
-        s.push_str("- Or use shell.exec: steam -applaunch <APPID> (allowed).\n");
-    }
-
-    s.push_str("\nTool usage policy:\n");
-    s.push_str("- When launching emulators:\n");
-    s.push_str(&format!("  • DS: run the emulator by itself: {} (no ROM path).\n", ds_emulator));
-    s.push_str(&format!("  • Game Boy: launch with the ROM file: {} /path/to/game.\n", gb_cmd));
-    s.push_str(&format!("  • For Game Boy, list files in {} first to find the exact ROM path requested.\n", roms_root));
-    s.push_str("  • If a game title appears under exactly one console above, you can infer the console. If ambiguous, ask.\n");
-    s.push_str("  • Do not attempt other shell commands. Only the above patterns are allowed.\n");
-
-    s.push_str("\nSafety & Policy:\n");
-    s.push_str("- Risky actions require approval; propose the action and wait.\n");
-    s.push_str("- Use available tools via the provided schemas; avoid guessing paths.\n");
-
-    // Research tools (arXiv)
-    s.push_str("\nResearch (arXiv) tools:\n");
-    s.push_str("- Use arxiv.search with JSON params: {query, categories?, from?, max_results?}.\n");
-    s.push_str("  Example: {\"query\":\"mixture-of-experts\",\"categories\":[\"cs.LG\"],\"from\":\"2025-09-01T00:00:00Z\",\"max_results\":25}.\n");
-    s.push_str("- Use arxiv.top for month snapshots: {month:\"YYYY-MM\", n}. Example: {\"month\":\"2025-09\",\"n\":5}.\n");
-    s.push_str("- Use arxiv.fetch_pdf to download: {id:\"YYMM.NNNNN\"}. Example: {\"id\":\"2509.01234\"}.\n");
-    s.push_str("- arxiv.summarize is not implemented yet; avoid calling it.\n");
-    s
-}
-
-fn scan_roms_root(root: &str) -> Option<Vec<(String, Vec<String>)>> {
-    use std::fs;
-    use std::path::Path;
-    let mut out: Vec<(String, Vec<String>)> = vec![];
-    let root_path = Path::new(root);
-    if !root_path.exists() { return None; }
-    let entries = fs::read_dir(root_path).ok()?;
-    for entry in entries.flatten() {
-        let path = entry.path();
-        let Ok(ft) = entry.file_type() else { continue };
-        if ft.is_dir() {
-            let console = entry.file_name().to_string_lossy().to_string();
-            let mut titles: Vec<String> = vec![];
-            if let Ok(mut games) = fs::read_dir(&path) {
-                while let Some(Ok(g)) = games.next() {
-                    let gp = g.path();
-                    if gp.is_file() {
-                        if let Some(name) = gp.file_stem().and_then(|s| s.to_str()) {
-                            let title = name.replace('_', " ");
-                            titles.push(title);
-                        }
-                    }
-                }
+        let parsed: Parsed = Arc::new(match std::fs::read_to_string(path) {
+            Err(e) => Err(format!("unreadable: {}", e)),
+            Ok(text) => split_front(&text).map(|(front, body)| (front, body.to_string())).map_err(|e| {
+                tracing::warn!(section=%name, error=%e, "prompt section: bad front matter");
+                format!("bad front matter: {}", e)
+            }),
+        });
+        if let Some(at) = modified { self.cache.lock().sections.insert(path.to_path_buf(), (at, parsed.clone())); }
+        parsed
+    }
+
+    /// `{{games_by_console}}`, scanned again once the ROM root or a console
+    /// folder in it changes.
+    fn games_by_console(&self) -> String {
+        let root = self.games.roms_root();
+        let stamp = roms_stamp(&root);
+        if let Some((at, text)) = self.cache.lock().games_by_console.as_ref() {
+            if *at == stamp { return text.clone(); }
+        }
+        let text = mcp_games::catalog::by_console(&mcp_games::catalog::scan_roms(&root)).into_iter()
+            .map(|(console, titles)| format!("- {}: {}", console, titles.join(", ")))
+            .collect::<Vec<_>>().join("\n");
+        self.cache.lock().games_by_console = Some((stamp, text.clone()));
+        text
+    }
+}
+
+/// Modification times of the ROM root and the console folders in it; adding
+/// or removing a ROM changes its folder's.
+fn roms_stamp(root: &Path) -> Stamp {
+    let modified = |p: &Path| std::fs::metadata(p).and_then(|m| m.modified()).ok();
+    let mut stamp: Stamp = std::fs::read_dir(root).into_iter().flatten().flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .map(|p| { let at = modified(&p); (p, at) })
+        .collect();
+    stamp.sort();
+    stamp.push((root.to_path_buf(), modified(root)));
+    stamp
+}
+
+/// Template variables, computed on first use.
+struct Vars<'a> {
+    prompts: &'a Prompts,
+    mode: Mode,
+    servers: &'a [String],
+    cache: HashMap<String, String>,
+}
+
+impl Vars<'_> {
+    fn get(&mut self, name: &str) -> String {
+        if let Some(v) = self.cache.get(name) { return v.clone(); }
+        let p = self.prompts;
+        let value = match name {
+            "mode" => self.mode.as_str().to_string(),
+            "profile" => p.profile.clone(),
+            "tool_servers" => self.servers.join(", "),
+            "system_digest" => p.system_map.as_ref().map(|m| m.get_digest()).unwrap_or_default(),
+            "roms_root" => p.games.roms_root().display().to_string(),
+            "games_by_console" => p.games_by_console(),
+            "emulators" => p.games.emulators.iter()
+                .map(|(console, emu)| {
+                    let name = emu.name.as_deref().map(|n| format!("{} ({})", n, console)).unwrap_or_else(|| console.clone());
+                    let bin = foreman_mcp::expand_home(&emu.bin).display().to_string();
+                    if emu.args.is_empty() { format!("- {}: {} (no arguments)", name, bin) } else { format!("- {}: {} {}", name, bin, emu.args.join(" ")) }
+                })
+                .collect::<Vec<_>>().join("\n"),
+            "steam_games" => {
+                let games = mcp_games::steam::user_list(&p.games.steam_list());
+                let mut lines: Vec<String> = games.iter().take(STEAM_GAMES_LISTED).map(|(name, appid)| format!("- {}: {}", name, appid)).collect();
+                if games.len() > STEAM_GAMES_LISTED { lines.push(format!("- … {} more", games.len() - STEAM_GAMES_LISTED)); }
+                lines.join("\n")
             }
-            titles.sort();
-            out.push((console, titles));
+            _ => match p.vars.get(name) {
+                Some(v) if v.starts_with("~/") => foreman_mcp::expand_home(v).display().to_string(),
+                Some(v) => v.clone(),
+                None => {
+                    tracing::debug!(var=%name, "prompt section: unknown variable");
+                    String::new()
+                }
+            },
+        };
+        self.cache.insert(name.to_string(), value.clone());
+        value
+    }
+}
+
+/// `*.md` files in `dir` by name; the name without extension names the section.
+fn section_files(dir: &Path) -> Vec<(String, PathBuf)> {
+    let Ok(rd) = std::fs::read_dir(dir) else { return vec![] };
+    let mut out: Vec<(String, PathBuf)> = rd.flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().and_then(|x| x.to_str()) == Some("md"))
+        .filter_map(|p| Some((p.file_stem()?.to_str()?.to_string(), p.clone())))
+        .collect();
+    out.sort();
+    out
+}
+
+/// Front matter between leading `---` lines, and the body after it.
+fn split_front(text: &str) -> anyhow::Result<(Front, &str)> {
+    let Some(rest) = text.strip_prefix("---\n").or_else(|| text.strip_prefix("---\r\n")) else { return Ok((Front::default(), text)) };
+    let (yaml, body) = match rest.find("\n---") {
+        Some(i) => (&rest[..i], rest[i + 4..].split_once('\n').map(|(_, b)| b).unwrap_or("")),
+        None => anyhow::bail!("front matter not closed"),
+    };
+    let front = if yaml.trim().is_empty() { Front::default() } else { serde_yaml::from_str(yaml)? };
+    Ok((front, body))
+}
+
+/// Names of the `{{var}}` placeholders in `body`, once each.
+fn placeholders(body: &str) -> Vec<String> {
+    let mut out: Vec<String> = vec![];
+    substitute(body, |v| {
+        if !out.iter().any(|o| o == v) { out.push(v.to_string()); }
+        String::new()
+    });
+    out
+}
+
+fn substitute(body: &str, mut value: impl FnMut(&str) -> String) -> String {
+    let mut out = String::with_capacity(body.len());
+    let mut rest = body;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else { break };
+        let name = rest[start + 2..start + 2 + len].trim();
+        out.push_str(&rest[..start]);
+        if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            out.push_str(&value(name));
+        } else {
+            out.push_str(&rest[start..start + 4 + len]);
         }
+        rest = &rest[start + 4 + len..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Whole lines of `body` within about `max_tokens`, marked with `…` when cut.
+fn cap(body: &str, max_tokens: usize) -> (String, bool) {
+    if tokens_est(body) <= max_tokens { return (body.to_string(), false); }
+    let mut kept = String::new();
+    for line in body.lines() {
+        if tokens_est(&format!("{}{}\n…", kept, line)) > max_tokens { break; }
+        kept.push_str(line);
+        kept.push('\n');
+    }
+    if kept.is_empty() {
+        let mut end = (max_tokens * 4).min(body.len());
+        while !body.is_char_boundary(end) { end -= 1; }
+        kept.push_str(&body[..end]);
+        kept.push('\n');
     }
-    out.sort_by(|a, b| a.0.cmp(&b.0));
-    Some(out)
+    kept.push('…');
+    (kept, true)
 }
 
-pub fn voice_mode_suffix() -> String {
-    // Additional guidance when running in realtime voice mode
-    let mut s = String::new();
-    s.push_str("\nVoice Mode:\n");
-    s.push_str("- Keep responses concise and conversational.\n");
-    s.push_str("- Prefer stepwise guidance; avoid long lists unless asked.\n");
-    s
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prompts(dir: &Path, vars: &[(&str, &str)]) -> Prompts {
+        let cfg = Config {
+            prompts: Some(crate::config::PromptsConfig {
+                dir: Some(dir.to_string_lossy().to_string()),
+                max_tokens: Some(40),
+                vars: vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            }),
+            games: Some(GamesConfig { roms_root: Some(dir.join("roms").to_string_lossy().to_string()), ..Default::default() }),
+            ..Default::default()
+        };
+        Prompts::from_config(&cfg)
+    }
+
+    #[test]
+    fn sections_follow_their_conditions_and_budgets() {
+        let dir = std::env::temp_dir().join(format!("prompts_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("roms").join("gba")).unwrap();
+        std::fs::write(dir.join("roms").join("gba").join("Golden_Sun.gba"), b"").unwrap();
+        let files = [
+            ("10-intro.md", "Emulator: {{gb_emulator}} ({{mode}}, {{unknown}})"),
+            ("20-games.md", "---\nrequires: [games_by_console]\n---\nGames:\n{{games_by_console}}"),
+            ("30-shell.md", "---\ntools: [shell]\n---\nShell rules"),
+            ("40-voice.md", "---\nmode: voice\n---\nKeep it short."),
+            ("50-long.md", "---\nmax_tokens: 6\n---\nfirst line here\nsecond line that is cut\nthird"),
+            ("60-late.md", "This one no longer fits in the forty token prompt budget at all, being far too long for what is left of it by now."),
+            ("70-bad.md", "---\nwhen: always\n---\nx"),
+        ];
+        for (name, body) in files { std::fs::write(dir.join(name), body).unwrap(); }
+        let p = prompts(&dir, &[("gb_emulator", "mgba-qt")]);
+
+        let text = p.render(Mode::Text);
+        let skipped: HashMap<&str, &str> = text.sections.iter().filter_map(|s| Some((s.name.as_str(), s.skipped.as_deref()?))).collect();
+        assert!(text.text.starts_with("Emulator: mgba-qt (text, )\n\nGames:\n- gba: Golden Sun\n\nfirst line here\n…"), "{}", text.text);
+        assert_eq!(skipped["30-shell"], "tool server shell not loaded");
+        assert_eq!(skipped["40-voice"], "voice mode only");
+        assert_eq!(skipped["60-late"], "over prompt budget");
+        assert!(skipped["70-bad"].starts_with("bad front matter"));
+        assert!(text.sections.iter().any(|s| s.name == "50-long" && s.truncated));
+        assert_eq!(text.tokens, text.sections.iter().map(|s| s.tokens).sum::<usize>());
+        assert!(text.uses("games_by_console") && !text.uses("system_digest"));
+
+        let voice = p.render_mode_sections(Mode::Voice);
+        assert_eq!(voice.text, "Keep it short.");
+        std::fs::remove_dir_all(dir.join("roms")).unwrap();
+        assert_eq!(prompts(&dir, &[]).render(Mode::Text).sections.iter().find(|s| s.name == "20-games").unwrap().skipped.as_deref(), Some("games_by_console is empty"));
+
+        let empty = prompts(&dir.join("missing"), &[]).render(Mode::Voice);
+        assert_eq!(empty.text, FALLBACK);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn sections_and_roms_are_cached_until_they_change() {
+        let dir = std::env::temp_dir().join(format!("prompts_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("roms").join("gba")).unwrap();
+        let section = dir.join("10-games.md");
+        std::fs::write(&section, "Games:\n{{games_by_console}}").unwrap();
+        let p = prompts(&dir, &[]);
+        assert_eq!(p.render(Mode::Text).text, "Games:");
+
+        // Same modification time: the parsed section is reused
+        let at = std::fs::metadata(&section).unwrap().modified().unwrap();
+        std::fs::write(&section, "Changed:\n{{games_by_console}}").unwrap();
+        std::fs::File::options().write(true).open(&section).unwrap().set_modified(at).unwrap();
+        assert_eq!(p.render(Mode::Text).text, "Games:");
+        p.reload();
+        assert_eq!(p.render(Mode::Text).text, "Changed:");
+
+        // A newer file or a new ROM shows up without a reload
+        std::fs::write(&section, "Now:\n{{games_by_console}}").unwrap();
+        std::fs::File::options().write(true).open(&section).unwrap().set_modified(at + std::time::Duration::from_secs(5)).unwrap();
+        std::fs::write(dir.join("roms").join("gba").join("Golden_Sun.gba"), b"").unwrap();
+        assert_eq!(p.render(Mode::Text).text, "Now:\n- gba: Golden Sun");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
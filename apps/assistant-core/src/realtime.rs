@@ -7,10 +7,10 @@ use std::collections::{VecDeque, HashSet};
 use futures_util::{SinkExt, StreamExt};
 use std::path::PathBuf;
 use uuid::Uuid;
+use crate::realtime_record::SessionRecorder;
+use crate::realtime_sessions::SessionJournal;
 #[cfg(feature = "realtime")]
-use tokio::net::TcpStream;
-#[cfg(feature = "realtime")]
-use tokio_tungstenite::MaybeTlsStream;
+use crate::realtime_record::Direction;
 #[cfg(feature = "realtime-audio")]
 use tokio::sync::mpsc;
 #[cfg(feature = "realtime-audio")]
@@ -28,20 +28,28 @@ fn rt_log(line: impl AsRef<str>) {
     }
 }
 
+/// Handle one inbound frame. `ws` is any sink of client frames: the live socket
+/// (wrapped in `RecordingSink`) or a capturing sink during replay.
 #[cfg(feature = "realtime")]
-async fn handle_ws_message(
+#[allow(clippy::too_many_arguments)]
+async fn handle_ws_message<W>(
     maybe_msg: Option<Result<tokio_tungstenite::tungstenite::Message, tokio_tungstenite::tungstenite::Error>>,
-    ws: &mut tokio_tungstenite::WebSocketStream<MaybeTlsStream<TcpStream>>,
+    ws: &mut W,
     inner: &Arc<RwLock<InnerState>>,
-    tools: &crate::tools::ToolsManager,
-    policy: &Arc<crate::gatekeeper::PolicyEngine>,
-    approval_prompt: &Arc<RwLock<Option<crate::app::EphemeralApproval>>>,
-    chat_dir: &Option<PathBuf>,
+    tool_calls: &mut Vec<ToolBatch>,
+    chat_log: &Option<ChatLog>,
     in_sr: u32,
     out_fmt: &String,
     #[allow(unused_variables)] playback: Option<&crate::realtime_audio::AudioPlayback>,
-) {
+) where W: futures_util::Sink<tokio_tungstenite::tungstenite::Message> + Unpin {
     use tokio_tungstenite::tungstenite::Message;
+    if let Some(rec) = inner.read().recorder.clone() {
+        match &maybe_msg {
+            Some(Ok(m)) => record_frame(&rec, Direction::In, m),
+            None => rec.close(Direction::In),
+            _ => {}
+        }
+    }
     match maybe_msg {
         Some(Ok(Message::Text(txt))) => {
             rt_log(format!("<- TEXT {} bytes", txt.len()));
@@ -52,6 +60,11 @@ async fn handle_ws_message(
                 if typ.ends_with("audio.delta") || typ.ends_with("output_audio.delta") {
                     // Accept either {audio:"..."} or {delta:"..."}
                     let audio_field = v.get("audio").and_then(|s| s.as_str()).or_else(|| v.get("delta").and_then(|s| s.as_str()));
+                    {
+                        let mut g = inner.write();
+                        if g.barged_in { return; }
+                        if let Some(id) = v.get("item_id").and_then(|s| s.as_str()) { g.assistant_item = Some(id.to_string()); }
+                    }
                     if let Some(b64) = audio_field {
                         rt_log(format!("<- audio.delta len(b64)={}", b64.len()));
                         if let Ok(bytes) = B64.decode(b64) {
@@ -73,6 +86,7 @@ async fn handle_ws_message(
                             let gain: f32 = std::env::var("REALTIME_PLAYBACK_GAIN").ok().and_then(|s| s.parse::<f32>().ok()).unwrap_or(0.25).clamp(0.0, 1.0);
                             if gain != 1.0 { for s in pcm.iter_mut() { let v = (*s as f32) * gain; *s = v.clamp(i16::MIN as f32, i16::MAX as f32) as i16; } }
                             if let Some(pb) = playback { pb.push_pcm(&pcm, in_sr); }
+                            if let Some(rec) = inner.read().recorder.clone() { rec.playback(&pcm); }
                             // Accumulate assistant audio for possible STT
                             {
                                 let mut g = inner.write();
@@ -106,11 +120,14 @@ async fn handle_ws_message(
                         let txt = if should { Some(std::mem::take(&mut g.assistant_text_buf)) } else { None };
                         (should, txt)
                     };
-                    if should_append {
-                        if let (Some(dir), Some(line)) = (chat_dir.as_ref(), text_opt) {
-                            let _ = append_latest_chat_message(dir, "assistant", &line).await;
-                            let mut g = inner.write();
-                            g.assistant_flushed = true;
+                    if let (true, Some(line)) = (should_append, text_opt) {
+                        let journal = inner.read().journal.clone();
+                        if let Some(j) = journal.as_ref() { j.turn("assistant", &line, Utc::now()).await; }
+                        if let Some(log) = chat_log.as_ref() {
+                            let _ = log.append("assistant", &line).await;
+                        }
+                        if chat_log.is_some() || journal.is_some() {
+                            inner.write().assistant_flushed = true;
                         }
                     }
                     // Continue to lifecycle handling below
@@ -127,14 +144,16 @@ async fn handle_ws_message(
                         // Mark flushed now to avoid duplicate on multiple done events
                         g.assistant_flushed = true;
                         drop(g);
-                        if !pcm.is_empty() {
-                            if let Some(dir) = chat_dir.as_ref() {
-                                let dir = dir.clone();
-                                tokio::spawn(async move {
-                                    let t = transcribe_user_local(&pcm, in_sr).await; // reuse same STT
-                                    if !t.is_empty() { let _ = append_latest_chat_message(&dir, "assistant", &t).await; }
-                                });
-                            }
+                        let journal = inner.read().journal.clone();
+                        if !pcm.is_empty() && (chat_log.is_some() || journal.is_some()) {
+                            let log = chat_log.clone();
+                            let at = Utc::now();
+                            tokio::spawn(async move {
+                                let t = transcribe_user_local(&pcm, in_sr).await; // reuse same STT
+                                if t.is_empty() { return; }
+                                if let Some(j) = journal { j.turn("assistant", &t, at).await; }
+                                if let Some(log) = log { let _ = log.append("assistant", &t).await; }
+                            });
                         }
                     } else {
                         drop(g);
@@ -155,8 +174,21 @@ async fn handle_ws_message(
                     g.assistant_text_buf.clear();
                     g.assistant_flushed = false;
                     g.response_active = true;
+                    g.barged_in = false;
+                    g.assistant_item = None;
+                    drop(g);
+                    #[cfg(feature = "realtime-audio")]
+                    if let Some(pb) = playback { pb.reset_played(); }
                     // fall through to normal processing/logging
                 }
+                if typ == "response.output_item.added" {
+                    let item = v.get("item");
+                    if item.and_then(|i| i.get("type")).and_then(|t| t.as_str()) == Some("message") {
+                        if let Some(id) = item.and_then(|i| i.get("id")).and_then(|s| s.as_str()) {
+                            inner.write().assistant_item = Some(id.to_string());
+                        }
+                    }
+                }
                 if typ == "input_audio_buffer.speech_started" || typ.ends_with("speech_started") {
                     rt_log("<- speech_started");
                     // Mark that a user utterance started (no-op for now; ring always accumulates)
@@ -166,109 +198,39 @@ async fn handle_ws_message(
                     if let Some(delta) = v.get("delta").and_then(|s| s.as_str()).or_else(|| v.get("text").and_then(|s| s.as_str())).or_else(|| v.get("transcript").and_then(|s| s.as_str())) {
                         let mut g = inner.write();
                         g.user_text_buf.push_str(delta);
+                        g.server_transcripts = true;
                     }
                     return;
                 }
                 if (typ.contains("input_audio_buffer") && typ.contains("transcription") && (typ.ends_with("completed") || typ.ends_with("done"))) || typ == "input_audio_buffer.transcription.completed" {
-                    let text = {
+                    let (text, journal) = {
                         let mut g = inner.write();
-                        if g.user_text_buf.is_empty() { None } else { Some(std::mem::take(&mut g.user_text_buf)) }
+                        g.server_transcripts = true;
+                        let text = if g.user_text_buf.is_empty() { None } else { Some(std::mem::take(&mut g.user_text_buf)) };
+                        (text, g.journal.clone())
                     };
-                    if let (Some(dir), Some(line)) = (chat_dir.as_ref(), text) {
-                        let _ = append_latest_chat_message(dir, "user", &line).await;
+                    if let Some(line) = text {
+                        if let Some(j) = journal { j.turn("user", &line, Utc::now()).await; }
+                        if let Some(log) = chat_log.as_ref() { let _ = log.append("user", &line).await; }
                     }
                     return;
                 }
-                // Function calling: process function_call items on response.done
+                // Function calling: collect function_call items on response.done; they run off the loop
                 if typ == "response.done" {
-                    let mut handled = false;
-                    let mut call_events: Vec<(String, bool, Option<String>)> = vec![]; // (name, ok, err)
-                    if let Some(resp) = v.get("response") {
-                        if let Some(items) = resp.get("output").and_then(|o| o.as_array()) {
-                            for it in items {
-                                if it.get("type").and_then(|s| s.as_str()) == Some("function_call") {
-                                    let name = it.get("name").and_then(|s| s.as_str()).unwrap_or("").to_string();
-                                    let call_id = it.get("call_id").and_then(|s| s.as_str()).unwrap_or("").to_string();
-                                    let args_str = it.get("arguments").and_then(|s| s.as_str()).unwrap_or("{}");
-                                    // Skip if already processed via args.done
-                                    let mut skip = false;
-                                    {
-                                        let mut g = inner.write();
-                                        if g.processed_calls.contains(&call_id) { skip = true; } else { g.processed_calls.insert(call_id.clone()); }
-                                    }
-                                    let args_json: serde_json::Value = serde_json::from_str(args_str).unwrap_or_else(|_| serde_json::json!({}));
-                                    rt_log(format!("function_call(done): name={} call_id={} args={}", name, call_id, truncate(args_str, 200)));
-                                    // Invoke via ToolsManager with policy/approvals
-                                    let result = handle_tool_call(tools, policy, approval_prompt, &name, args_json).await;
-                                    // Log to session
-                                    {
-                                        let ok = result.get("error").is_none();
-                                        let err = result.get("error").and_then(|e| e.as_str()).map(|s| truncate(s, 120));
-                                        let mut g = inner.write();
-                                        if let Some(log) = g.session_log.as_mut() { log.tool_calls.push(SessionToolEvent { name: name.clone(), ok, error: err.clone() }); }
-                                        call_events.push((name.clone(), ok, err));
-                                    }
-                                    // Send function_call_output conversation item (with event_id)
-                                    let output_str = serde_json::to_string(&result).unwrap_or_else(|_| "{}".into());
-                                    rt_log(format!("function_result: name={} ok={} len={} body={}", name, result.get("error").is_none(), output_str.len(), truncate(&output_str, 300)));
-                                    let eid = Uuid::new_v4().to_string();
-                                    {
-                                        let mut g = inner.write();
-                                        g.event_tags.insert(eid.clone(), format!("function_call_output {}", call_id));
-                                    }
-                                    let item = serde_json::json!({
-                                        "event_id": eid,
-                                        "type": "conversation.item.create",
-                                        "item": {"type": "function_call_output", "call_id": call_id, "output": output_str}
-                                    });
-                                    let _ = ws.send(Message::Text(item.to_string())).await;
-                                    // If end_call, close session now (no further response)
-                                    if name == "end_call" || name == "end.call" {
-                                        rt_log("end_call: closing websocket and ending session (response.done)");
-                                        {
-                                            let mut g = inner.write();
-                                            g.status.active = false;
-                                            g.response_active = false;
-                                        }
-                                        let _ = ws.close(None).await;
-                                        return;
-                                    }
-                                    handled = true;
-                                }
-                            }
-                        }
+                    let items = v.get("response").and_then(|r| r.get("output")).and_then(|o| o.as_array()).cloned().unwrap_or_default();
+                    let mut calls = vec![];
+                    for it in items.iter().filter(|it| it.get("type").and_then(|s| s.as_str()) == Some("function_call")) {
+                        let name = it.get("name").and_then(|s| s.as_str()).unwrap_or("").to_string();
+                        let call_id = it.get("call_id").and_then(|s| s.as_str()).unwrap_or("").to_string();
+                        let args_str = it.get("arguments").and_then(|s| s.as_str()).unwrap_or("{}");
+                        // Skip calls already run for this session
+                        if !inner.write().processed_calls.insert(call_id.clone()) { continue; }
+                        rt_log(format!("function_call(done): name={} call_id={} args={}", name, call_id, truncate(args_str, 200)));
+                        let args = serde_json::from_str(args_str).unwrap_or_else(|_| serde_json::json!({}));
+                        calls.push(ToolRun::new(Some(call_id), name, args));
                     }
-                    if handled {
-                        // Optionally append a compact tool summary into chat
-                        let append_summary = match std::env::var("REALTIME_APPEND_TOOL_SUMMARY") { Ok(v) => matches!(v.as_str(), "1"|"true"|"True"|"TRUE"), Err(_) => false };
-                        if append_summary && !call_events.is_empty() {
-                            let mut parts: Vec<String> = vec![];
-                            for (name, ok, err) in call_events.iter().take(4) {
-                                if *ok { parts.push(format!("{} ok", name)); }
-                                else { parts.push(format!("{} err: {}", name, err.clone().unwrap_or_else(|| "(error)".into()))); }
-                            }
-                            if call_events.len() > 4 { parts.push(format!("… {} more", call_events.len() - 4)); }
-                            let line = if call_events.len() == 1 { format!("Tool: {}", parts.join(", ")) } else { format!("Tools: {}", parts.join(", ")) };
-                            if let Some(dir) = chat_dir.as_ref() { let _ = append_latest_chat_message(dir, "assistant", &line).await; }
-                        }
-                        // Ask model to continue with the provided function outputs (guard against concurrent)
-                        let mut allow = false;
-                        {
-                            let mut g = inner.write();
-                            if !g.response_active { g.response_active = true; allow = true; }
-                        }
-                        if allow {
-                            let eid = Uuid::new_v4().to_string();
-                            {
-                                let mut g = inner.write();
-                                g.event_tags.insert(eid.clone(), "response.create post function_call_output".into());
-                            }
-                            rt_log("-> response.create (post function_call_output)");
-                            let create = serde_json::json!({"event_id": eid, "type":"response.create","response": {"modalities":["audio","text"]}});
-                            let _ = ws.send(Message::Text(create.to_string())).await;
-                        } else {
-                            rt_log("skip response.create (response_active)");
-                        }
+                    if !calls.is_empty() {
+                        tool_calls.push(ToolBatch { kind: ToolBatchKind::Function, calls });
                         return;
                     }
                     // fall through when no function_call outputs were present
@@ -288,6 +250,8 @@ async fn handle_ws_message(
                         if let Some(t) = tag { rt_log(format!("<- error raw (event_id={} tag={}): {}", eid, t, txt)); }
                         else { rt_log(format!("<- error raw (event_id={}): {}", eid, txt)); }
                     }
+                    let journal = inner.read().journal.clone();
+                    if let Some(j) = journal { j.error(if !msg.is_empty() { msg } else { txt.as_str() }).await; }
                     let mut g = inner.write();
                     g.status.last_error = Some(if !msg.is_empty() { format!("realtime error: {}", msg) } else { "realtime error".into() });
                     return;
@@ -310,7 +274,7 @@ async fn handle_ws_message(
                         rt_log("<- speech_stopped; response already active (skip create)");
                     }
                     // Also produce a local transcript for chat logging (best-effort)
-                    if let Some(dir) = chat_dir.as_ref() {
+                    if chat_log.is_some() || inner.read().journal.is_some() {
                         let samples: Vec<i16> = {
                             let g = inner.read();
                             let take = (in_sr as usize) * 4; // last 4s
@@ -319,10 +283,16 @@ async fn handle_ws_message(
                             g.ring.iter().skip(start).cloned().collect()
                         };
                         if !samples.is_empty() {
-                            let dir = dir.clone();
+                            let log = chat_log.clone();
+                            let inner = inner.clone();
+                            let at = Utc::now();
                             tokio::spawn(async move {
                                 let t = transcribe_user_local(&samples, in_sr).await;
-                                if !t.is_empty() { let _ = append_latest_chat_message(&dir, "user", &t).await; }
+                                if t.is_empty() { return; }
+                                // Server transcripts are the record when present; don't journal the turn twice
+                                let journal = { let g = inner.read(); if g.server_transcripts { None } else { g.journal.clone() } };
+                                if let Some(j) = journal { j.turn("user", &t, at).await; }
+                                if let Some(log) = log { let _ = log.append("user", &t).await; }
                             });
                         }
                     }
@@ -337,30 +307,21 @@ async fn handle_ws_message(
                     // Special case: end_call terminates the session
                     if name == "end_call" || name == "end.call" {
                         let mut out = serde_json::json!({"type": "tool.output", "name": name, "output": {"ok": true}});
-                        if let Some(i) = id { out.as_object_mut().unwrap().insert("id".into(), serde_json::json!(i)); }
+                        if let Some(i) = id.clone() { out.as_object_mut().unwrap().insert("id".into(), serde_json::json!(i)); }
                         let _ = ws.send(Message::Text(out.to_string())).await;
+                        let journal = inner.read().journal.clone();
+                        if let Some(j) = journal { j.tool(&tool_record(id.clone(), &name, args, &serde_json::json!({"ok": true}), None)).await; }
                         // Append a brief end-of-call summary to latest chat (best-effort)
-                        if let Some(dir) = chat_dir.as_ref() {
-                            if let Some(sum) = build_session_summary(inner, chat_dir.as_ref()) { let _ = append_voice_summary(dir, &sum).await; }
+                        if let Some(log) = chat_log.as_ref() {
+                            let last_user = log.last_user().await;
+                            if let Some(sum) = build_session_summary(inner, last_user) { let _ = log.append_summary(&sum).await; }
                         }
                         { let mut g = inner.write(); g.session_log = None; g.status.active = false; }
-                        let _ = ws.close(None).await;
+                        let _ = ws.send(Message::Close(None)).await;
                         return;
                     }
 
-                    let result = handle_tool_call(tools, policy, approval_prompt, &name, args.clone()).await;
-                    // Update session log
-                    {
-                        let ok = !result.get("error").is_some();
-                        let err = result.get("error").and_then(|e| e.as_str()).map(|s| truncate(s, 120));
-                        let mut g = inner.write();
-                        if let Some(log) = g.session_log.as_mut() {
-                            log.tool_calls.push(SessionToolEvent { name: name.clone(), ok, error: err });
-                        }
-                    }
-                    let mut out = serde_json::json!({"type": "tool.output", "name": name, "output": result});
-                    if let Some(i) = id { out.as_object_mut().unwrap().insert("id".into(), serde_json::json!(i)); }
-                    let _ = ws.send(Message::Text(out.to_string())).await;
+                    tool_calls.push(ToolBatch { kind: ToolBatchKind::Legacy, calls: vec![ToolRun::new(id, name, args)] });
                 }
             }
         }
@@ -383,6 +344,7 @@ async fn handle_ws_message(
                     g.playing_audio = true;
                 }
                 pb.push_pcm(&pcm, in_sr);
+                if let Some(rec) = inner.read().recorder.clone() { rec.playback(&pcm); }
                 // Accumulate assistant audio for possible STT
                 {
                     let mut g = inner.write();
@@ -390,18 +352,127 @@ async fn handle_ws_message(
                 }
             }
         }
-        Some(Ok(Message::Close(c))) => { rt_log(format!("<- CLOSE: {:?}", c)); let _ = ws.close(None).await; let mut g = inner.write(); g.status.active = false; }
-        None => { rt_log("<- CLOSE: None"); let _ = ws.close(None).await; let mut g = inner.write(); g.status.active = false; }
+        Some(Ok(Message::Close(c))) => { rt_log(format!("<- CLOSE: {:?}", c)); let _ = ws.send(Message::Close(None)).await; let mut g = inner.write(); g.status.active = false; }
+        None => { rt_log("<- CLOSE: None"); let _ = ws.send(Message::Close(None)).await; let mut g = inner.write(); g.status.active = false; }
         _ => {}
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Where session bundles go: next to the chats dir (`<home>/realtime_sessions`).
+#[cfg(feature = "realtime")]
+fn recordings_dir(chat_dir: Option<&PathBuf>) -> PathBuf {
+    chat_dir.and_then(|d| d.parent()).map(|p| p.join("realtime_sessions")).unwrap_or_else(|| PathBuf::from("storage/realtime_sessions"))
+}
+
+#[cfg(feature = "realtime")]
+fn record_frame(rec: &SessionRecorder, dir: Direction, m: &tokio_tungstenite::tungstenite::Message) {
+    use tokio_tungstenite::tungstenite::Message;
+    match m {
+        Message::Text(t) => {
+            // Mic and assistant audio are kept in mic.pcm / playback.pcm; don't
+            // duplicate them as base64 in the event log
+            let audio = match dir {
+                Direction::Out => t.contains("\"input_audio_buffer.append\""),
+                Direction::In => t.contains("audio.delta\""),
+            };
+            if audio {
+                if let Ok(mut v) = serde_json::from_str::<serde_json::Value>(t) {
+                    let typ = v.get("type").and_then(|s| s.as_str()).unwrap_or("");
+                    let is_delta = typ.ends_with("audio.delta");
+                    if let Some(obj) = v.as_object_mut().filter(|_| dir == Direction::Out || is_delta) {
+                        let b64 = obj.remove("audio").or_else(|| if is_delta { obj.remove("delta") } else { None });
+                        let len = b64.and_then(|a| a.as_str().map(|s| s.len() / 4 * 3 - s.bytes().rev().take_while(|&b| b == b'=').count())).unwrap_or(0);
+                        obj.insert("audio_bytes".into(), serde_json::json!(len));
+                    }
+                    rec.text(dir, &v.to_string());
+                    return;
+                }
+            }
+            rec.text(dir, t);
+        }
+        Message::Binary(b) => rec.binary(dir, b),
+        Message::Close(_) => rec.close(dir),
+        _ => {}
+    }
+}
+
+/// Recorded audio deltas keep only `audio_bytes`; replay them as that much
+/// silence so the response state machine sees audio as it did live.
+#[cfg(feature = "realtime-audio")]
+fn restore_elided_audio(event: &serde_json::Value) -> serde_json::Value {
+    let typ = event.get("type").and_then(|t| t.as_str()).unwrap_or("");
+    let mut v = event.clone();
+    if typ.ends_with("audio.delta") && v.get("delta").is_none() && v.get("audio").is_none() {
+        if let Some(len) = v.get("audio_bytes").and_then(|n| n.as_u64()) {
+            v["delta"] = serde_json::json!(B64.encode(vec![0u8; len as usize]));
+        }
+    }
+    v
+}
+
+/// Client-side socket half that records every outbound frame.
+#[cfg(feature = "realtime")]
+struct RecordingSink<W> {
+    inner: W,
+    recorder: Option<SessionRecorder>,
+}
+
+#[cfg(feature = "realtime")]
+impl<W> futures_util::Sink<tokio_tungstenite::tungstenite::Message> for RecordingSink<W>
+where W: futures_util::Sink<tokio_tungstenite::tungstenite::Message> + Unpin {
+    type Error = W::Error;
+
+    fn poll_ready(mut self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), Self::Error>> {
+        std::pin::Pin::new(&mut self.inner).poll_ready(cx)
+    }
+
+    fn start_send(mut self: std::pin::Pin<&mut Self>, item: tokio_tungstenite::tungstenite::Message) -> Result<(), Self::Error> {
+        if let Some(rec) = self.recorder.as_ref() { record_frame(rec, Direction::Out, &item); }
+        std::pin::Pin::new(&mut self.inner).start_send(item)
+    }
+
+    fn poll_flush(mut self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), Self::Error>> {
+        std::pin::Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), Self::Error>> {
+        std::pin::Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
+
+/// Result of replaying a recorded session: what the client would have sent and
+/// the state machine flags after the last event.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReplayOutcome {
+    /// Client JSON events sent in response to the recorded inbound frames.
+    pub outbound: Vec<serde_json::Value>,
+    /// The handler sent a Close frame.
+    pub closed: bool,
+    pub active: bool,
+    pub playing_audio: bool,
+    pub response_active: bool,
+    pub assistant_flushed: bool,
+    /// call_ids executed during the replay, sorted.
+    pub processed_calls: Vec<String>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct RealtimeAudioOpts {
     #[serde(default)]
     pub in_sr: Option<u32>,
     #[serde(default)]
     pub out_format: Option<String>,
+    /// Stream the mic during playback through AEC + barge-in detection (default half-duplex).
+    #[serde(default)]
+    pub full_duplex: Option<bool>,
+    #[serde(default)]
+    pub aec: Option<bool>,
+    /// Barge-in VAD tuning (see `duplex::DuplexConfig`).
+    #[serde(default)]
+    pub vad_sensitivity: Option<f32>,
+    #[serde(default)]
+    pub min_speech_ms: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -418,6 +489,9 @@ pub struct RealtimeOptions {
     pub endpoint: Option<String>,
     #[serde(default)]
     pub transport: Option<String>,
+    /// Record the session to `<home>/realtime_sessions/<id>/` (also `REALTIME_RECORD=1`).
+    #[serde(default)]
+    pub record: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -429,6 +503,9 @@ pub struct RealtimeStatus {
     pub since: Option<DateTime<Utc>>,
     #[serde(default)]
     pub last_error: Option<String>,
+    /// Id of the session bundle being (or last) recorded.
+    #[serde(default)]
+    pub recording: Option<String>,
 }
 
 #[derive(Default)]
@@ -454,6 +531,16 @@ struct InnerState {
     event_tags: std::collections::HashMap<String, String>,
     // Recent mic audio ring buffer for local STT
     ring: VecDeque<i16>,
+    // Assistant conversation item currently being spoken (truncated on barge-in)
+    assistant_item: Option<String>,
+    // User barged in: drop late audio deltas until the next response starts
+    barged_in: bool,
+    // Session recorder when recording is enabled
+    recorder: Option<SessionRecorder>,
+    // Structured call history when memory is available
+    journal: Option<SessionJournal>,
+    // Server sent transcription events; local STT of the mic ring is then chat-only
+    server_transcripts: bool,
 }
 
 #[derive(Clone)]
@@ -462,29 +549,61 @@ pub struct RealtimeManager {
     tools: crate::tools::ToolsManager,
     policy: Arc<crate::gatekeeper::PolicyEngine>,
     approval_prompt: Arc<RwLock<Option<crate::app::EphemeralApproval>>>,
+    decisions: crate::gatekeeper::PromptDecisions,
     chat_dir: Option<PathBuf>,
+    memory: Option<crate::memory::Memory>,
+    prompts: Option<crate::prompt::Prompts>,
 }
 
 impl Default for RealtimeManager {
     fn default() -> Self {
         Self {
-            inner: Arc::new(RwLock::new(InnerState { status: RealtimeStatus::default(), handle: None, stop_tx: None, session_log: None, playing_audio: false, user_text_buf: String::new(), assistant_text_buf: String::new(), assistant_pcm: Vec::new(), assistant_flushed: false, response_active: false, processed_calls: HashSet::new(), event_tags: std::collections::HashMap::new(), ring: VecDeque::with_capacity(16000*8) })),
+            inner: Arc::new(RwLock::new(InnerState { status: RealtimeStatus::default(), handle: None, stop_tx: None, session_log: None, playing_audio: false, user_text_buf: String::new(), assistant_text_buf: String::new(), assistant_pcm: Vec::new(), assistant_flushed: false, response_active: false, processed_calls: HashSet::new(), event_tags: std::collections::HashMap::new(), ring: VecDeque::with_capacity(16000*8), assistant_item: None, barged_in: false, recorder: None, journal: None, server_transcripts: false })),
             tools: crate::tools::ToolsManager::default(),
             policy: Arc::new(crate::gatekeeper::PolicyEngine::default()),
             approval_prompt: Arc::new(RwLock::new(None)),
+            decisions: crate::gatekeeper::PromptDecisions::default(),
             chat_dir: None,
+            memory: None,
+            prompts: None,
         }
     }
 }
 
 impl RealtimeManager {
-    pub fn new(tools: crate::tools::ToolsManager, policy: Arc<crate::gatekeeper::PolicyEngine>, approval_prompt: Arc<RwLock<Option<crate::app::EphemeralApproval>>>, chat_dir: Option<PathBuf>) -> Self {
+    pub fn new(tools: crate::tools::ToolsManager, policy: Arc<crate::gatekeeper::PolicyEngine>, approval_prompt: Arc<RwLock<Option<crate::app::EphemeralApproval>>>, decisions: crate::gatekeeper::PromptDecisions, chat_dir: Option<PathBuf>) -> Self {
         Self {
-            inner: Arc::new(RwLock::new(InnerState { status: RealtimeStatus::default(), handle: None, stop_tx: None, session_log: None, playing_audio: false, user_text_buf: String::new(), assistant_text_buf: String::new(), assistant_pcm: Vec::new(), assistant_flushed: false, response_active: false, processed_calls: HashSet::new(), event_tags: std::collections::HashMap::new(), ring: VecDeque::with_capacity(16000*8) })),
+            inner: Arc::new(RwLock::new(InnerState { status: RealtimeStatus::default(), handle: None, stop_tx: None, session_log: None, playing_audio: false, user_text_buf: String::new(), assistant_text_buf: String::new(), assistant_pcm: Vec::new(), assistant_flushed: false, response_active: false, processed_calls: HashSet::new(), event_tags: std::collections::HashMap::new(), ring: VecDeque::with_capacity(16000*8), assistant_item: None, barged_in: false, recorder: None, journal: None, server_transcripts: false })),
             tools,
             policy,
             approval_prompt,
+            decisions,
             chat_dir,
+            memory: None,
+            prompts: None,
+        }
+    }
+
+    /// Voice-mode system prompt for calls started without instructions.
+    pub fn with_prompts(mut self, prompts: crate::prompt::Prompts) -> Self {
+        self.prompts = Some(prompts);
+        self
+    }
+
+    /// Record each call as a `RealtimeSession` with its turns and tool calls, and
+    /// seed new calls from that history instead of the latest chat file.
+    pub fn with_memory(mut self, memory: Option<crate::memory::Memory>) -> Self {
+        self.memory = memory;
+        self
+    }
+
+    /// Transcripts go to the chat store when memory is up, else the chats dir.
+    #[cfg(feature = "realtime")]
+    fn chat_log(&self) -> Option<ChatLog> {
+        match (self.memory.as_ref(), self.chat_dir.as_ref()) {
+            (Some(m), _) => Some(ChatLog::Store(crate::chats::ChatStore::new(m.store.clone()))),
+            (None, Some(dir)) => Some(ChatLog::Dir(dir.clone())),
+            (None, None) => None,
         }
     }
 
@@ -512,6 +631,12 @@ impl RealtimeManager {
             g.processed_calls.clear();
             g.event_tags.clear();
             g.ring.clear();
+            g.assistant_item = None;
+            g.barged_in = false;
+            g.recorder = None;
+            g.status.recording = None;
+            g.journal = None;
+            g.server_transcripts = false;
             drop(g);
 
             // Extract options to move into the async task
@@ -530,8 +655,30 @@ impl RealtimeManager {
                 "g711_ulaw" => 8_000,
                 _ => 24_000,
             };
-            let base_instructions = opts.instructions.clone();
+            // Full-duplex: mic stays open during playback, echo-cancelled, with barge-in
+            #[cfg_attr(not(feature = "realtime-audio"), allow(unused_variables))]
+            let duplex_cfg = opts.audio.as_ref().filter(|a| a.full_duplex.unwrap_or(false)).map(|a| {
+                let d = crate::duplex::DuplexConfig::default();
+                crate::duplex::DuplexConfig {
+                    sample_rate: cap_sr,
+                    aec_enabled: a.aec.unwrap_or(true),
+                    vad_sensitivity: a.vad_sensitivity.unwrap_or(d.vad_sensitivity),
+                    min_speech_ms: a.min_speech_ms.unwrap_or(d.min_speech_ms),
+                    ..d
+                }
+            });
+            let record = opts.record.unwrap_or(false) || matches!(std::env::var("REALTIME_RECORD").as_deref(), Ok("1" | "true" | "TRUE" | "True"));
+            let sessions_root = recordings_dir(self.chat_dir.as_ref());
             let transport = opts.transport.clone().unwrap_or_else(|| std::env::var("OPENAI_REALTIME_TRANSPORT").unwrap_or_else(|_| "websocket".into()));
+            // Caller instructions keep only the voice-mode sections after them
+            let prompts = self.prompts.clone().unwrap_or_else(|| crate::prompt::Prompts::from_config(&Default::default()).with_tools(self.tools.clone()));
+            let base_instructions = match opts.instructions.clone() {
+                Some(custom) => {
+                    let voice = prompts.render_mode_sections(crate::prompt::Mode::Voice).text;
+                    if voice.is_empty() { custom } else { format!("{}\n\n{}", custom, voice) }
+                }
+                None => prompts.render(crate::prompt::Mode::Voice).text,
+            };
 
             // spawn connection task
             let (tx, mut rx) = oneshot::channel::<()>();
@@ -539,27 +686,43 @@ impl RealtimeManager {
             let tools = self.tools.clone();
             let policy = self.policy.clone();
             let approval_prompt = self.approval_prompt.clone();
-            let chat_dir = self.chat_dir.clone();
+            let decisions = self.decisions.clone();
+            let chat_log = self.chat_log();
+            let memory = self.memory.clone();
             let handle = std::thread::spawn(move || {
                 let rt = tokio::runtime::Builder::new_current_thread().enable_all().build();
                 if rt.is_err() { return; }
                 let rt = rt.unwrap();
                 rt.block_on(async move {
-                let mut instructions = base_instructions.unwrap_or_else(|| crate::prompt::base_system_prompt());
-                instructions.push_str(&crate::prompt::voice_mode_suffix());
-                // Seed with recent chat turns (compact digest)
-                if let Some(dir) = chat_dir.as_ref() {
-                    if let Some(ctx) = build_recent_context(dir).await { instructions.push_str("\n\nRecent context:\n"); instructions.push_str(&ctx); }
+                let mut instructions = base_instructions;
+                // Seed with the last calls from memory, else recent chat turns (compact digest)
+                let calls = match memory.as_ref() {
+                    Some(m) => crate::realtime_sessions::recent_context(&m.store, RECENT_CALLS).await,
+                    None => None,
+                };
+                if let Some(ctx) = calls {
+                    instructions.push_str("\n\nRecent calls:\n"); instructions.push_str(&ctx);
+                } else if let Some(log) = chat_log.as_ref() {
+                    if let Some(ctx) = log.recent_context().await { instructions.push_str("\n\nRecent context:\n"); instructions.push_str(&ctx); }
                 }
 
-                // Transport selection (default websocket; optional webrtc stub)
+                // WebSocket is the only transport; WebRTC is not implemented (no peer stack or Opus codec)
                 if transport.eq_ignore_ascii_case("webrtc") {
                     let mut g = inner.write();
-                    g.status.last_error = Some("realtime transport=webrtc not implemented in this build".into());
+                    g.status.last_error = Some("realtime transport=webrtc is not supported; use websocket".into());
                     g.status.active = false;
                     return;
                 }
 
+                let journal = match memory.as_ref() {
+                    Some(m) => match SessionJournal::open(&m.store, Some(&model), Some(&endpoint), Some(&transport)).await {
+                        Ok(j) => { rt_log(format!("journal: session {} (task {})", j.id(), j.task_id())); Some(j) }
+                        Err(e) => { rt_log(format!("cannot journal session: {}", e)); None }
+                    },
+                    None => None,
+                };
+                inner.write().journal = journal.clone();
+
                 // WebSocket connect (with optional auth headers)
                 rt_log(format!("connecting to {}", endpoint));
                 use tokio_tungstenite::tungstenite::client::IntoClientRequest;
@@ -567,8 +730,10 @@ impl RealtimeManager {
                     Ok(r) => r,
                     Err(e) => {
                         rt_log(format!("bad endpoint: {}", e));
+                        let err = format!("bad endpoint {}: {}", endpoint, e);
+                        if let Some(j) = journal.as_ref() { j.finish(Some(&err)).await; }
                         let mut g = inner.write();
-                        g.status.last_error = Some(format!("bad endpoint {}: {}", endpoint, e));
+                        g.status.last_error = Some(err);
                         g.status.active = false; return;
                     }
                 };
@@ -582,7 +747,7 @@ impl RealtimeManager {
                         headers.insert("Sec-WebSocket-Protocol", HeaderValue::from_static("openai-realtime-v1"));
                     }
                 }
-                let (mut ws, _resp) = match tokio_tungstenite::connect_async(req).await {
+                let (ws, _resp) = match tokio_tungstenite::connect_async(req).await {
                     Ok(ok) => {
                         rt_log("connected");
                         let mut g = inner.write();
@@ -593,12 +758,29 @@ impl RealtimeManager {
                     }
                     Err(e) => {
                         rt_log(format!("connect error: {}", e));
+                        let err = format!("connect {}: {}", endpoint, e);
+                        if let Some(j) = journal.as_ref() { j.finish(Some(&err)).await; }
                         let mut g = inner.write();
-                        g.status.last_error = Some(format!("connect {}: {}", endpoint, e));
+                        g.status.last_error = Some(err);
                         g.status.active = false;
                         return;
                     }
                 };
+                let recorder = if record {
+                    match SessionRecorder::create(&sessions_root, crate::realtime_record::RecordingMeta::new(Some(model.clone()), Some(endpoint.clone()), cap_sr, srv_out_sr, &out_fmt)) {
+                        Ok(rec) => {
+                            rt_log(format!("[rec] recording session to {}", rec.dir().display()));
+                            if let Some(j) = journal.as_ref() { j.set_recording(&rec.id()).await; }
+                            let mut g = inner.write();
+                            g.status.recording = Some(rec.id());
+                            g.recorder = Some(rec.clone());
+                            Some(rec)
+                        }
+                        Err(e) => { rt_log(format!("[rec] cannot record session: {}", e)); None }
+                    }
+                } else { None };
+                let (ws_sink, mut ws_stream) = ws.split();
+                let mut ws = RecordingSink { inner: ws_sink, recorder: recorder.clone() };
 
                 #[cfg(feature = "realtime-audio")]
                 let playback = match crate::realtime_audio::AudioPlayback::new(srv_out_sr) { Ok(pb) => { rt_log(format!("[cfg] playback_device_sr={}Hz", pb.device_sr())); Some(pb) }, Err(e) => { eprintln!("[realtime] audio playback init error: {}", e); None } };
@@ -606,6 +788,8 @@ impl RealtimeManager {
                 let (tx_frames, mut rx_frames) = mpsc::channel::<Vec<i16>>(8);
                 #[cfg(feature = "realtime-audio")]
                 let _cap_keepalive = match crate::realtime_audio::start_capture(cap_sr, 40, tx_frames) { Ok(c) => Some(c), Err(e) => { eprintln!("[realtime] audio capture init error: {}", e); None } };
+                #[cfg(feature = "realtime-audio")]
+                let mut duplex = duplex_cfg.map(|c| { rt_log(format!("[cfg] full_duplex=true aec={} vad_sensitivity={:.2}", c.aec_enabled, c.vad_sensitivity)); crate::duplex::DuplexPipeline::new(c) });
 
                 // Build session.update with fields supported by OpenAI Realtime WS
                 let payload = serde_json::json!({
@@ -646,6 +830,7 @@ impl RealtimeManager {
                 // Log state reset marker for diagnostics
                 rt_log("[state] session reset: response_active=false, buffers cleared");
                 if ws.send(Message::Text(upd_txt)).await.is_err() {
+                    if let Some(j) = journal.as_ref() { j.finish(Some("send session.update failed")).await; }
                     let mut g = inner.write();
                     g.status.last_error = Some("send session.update failed".into());
                     g.status.active = false;
@@ -653,6 +838,15 @@ impl RealtimeManager {
                     return;
                 }
 
+                // Tool calls run as jobs so approvals never stall the socket or the mic
+                #[cfg(feature = "realtime-audio")]
+                let spoken = SpokenConfirm::new(cap_sr);
+                #[cfg(feature = "realtime-audio")]
+                let runner = ToolRunner { tools, policy, approval_prompt, decisions, mic: Some(spoken.clone()) };
+                #[cfg(not(feature = "realtime-audio"))]
+                let runner = ToolRunner { tools, policy, approval_prompt, decisions, mic: None };
+                let mut tool_jobs = tokio::task::JoinSet::new();
+
                 // Event loop: stop on signal or socket close. We stream audio frames continuously; server VAD will commit turns.
                 #[cfg(feature = "realtime-audio")]
                 {
@@ -660,7 +854,27 @@ impl RealtimeManager {
                         tokio::select! {
                             _ = tokio::time::sleep(std::time::Duration::from_millis(10)) => {},
                             Some(frame) = rx_frames.recv() => {
-                                let pcm = frame;
+                                let speaking = inner.read().playing_audio;
+                                let mut pcm = frame;
+                                if let Some(rec) = recorder.as_ref() { rec.mic(&pcm); }
+                                if let Some(pipeline) = duplex.as_mut() {
+                                    let reference = playback.as_ref().map(|pb| pb.take_reference(pcm.len(), cap_sr)).unwrap_or_else(|| vec![0; pcm.len()]);
+                                    let out = pipeline.process(&pcm, &reference, speaking);
+                                    if out.barge_in {
+                                        let (item, played_ms) = (inner.read().assistant_item.clone(), playback.as_ref().map(|pb| pb.played_ms()).unwrap_or(0));
+                                        rt_log(format!("[state] barge-in: cancel response, truncate item={:?} at {} ms", item, played_ms));
+                                        for ev in crate::duplex::barge_in_events(item.as_deref(), played_ms) {
+                                            let _ = ws.send(tokio_tungstenite::tungstenite::Message::Text(ev.to_string())).await;
+                                        }
+                                        if let Some(pb) = playback.as_ref() { pb.flush(); }
+                                        let mut g = inner.write();
+                                        g.playing_audio = false;
+                                        g.response_active = false;
+                                        g.barged_in = true;
+                                        g.assistant_pcm.clear();
+                                    }
+                                    pcm = out.cleaned;
+                                }
                                 // Push into recent ring for local STT (keep ~8s)
                                 {
                                     let mut g = inner.write();
@@ -668,8 +882,11 @@ impl RealtimeManager {
                                     let max_samples = (cap_sr as usize) * 8;
                                     while g.ring.len() > max_samples { g.ring.pop_front(); }
                                 }
-                                // Half-duplex: if assistant is speaking, drop mic frames to avoid barge-in
-                                if inner.read().playing_audio {
+                                // A spoken approval answer is for the prompt, not the model
+                                if spoken.offer(&pcm) {
+                                    rt_log("(approval) mic frame to spoken confirmation");
+                                } else if speaking && duplex.is_none() {
+                                    // Half-duplex: if assistant is speaking, drop mic frames to avoid barge-in
                                     rt_log("(drop) mic frame during playback");
                                 } else {
                                     let frame_samples = pcm.len();
@@ -685,12 +902,18 @@ impl RealtimeManager {
                                     let _ = ws.send(tokio_tungstenite::tungstenite::Message::Text(event.to_string())).await;
                                 }
                             },
-                            maybe_msg = ws.next() => {
-                                handle_ws_message(maybe_msg, &mut ws, &inner, &tools, &policy, &approval_prompt, &chat_dir, srv_out_sr, &out_fmt, playback.as_ref()).await;
+                            maybe_msg = ws_stream.next() => {
+                                let mut batches = vec![];
+                                handle_ws_message(maybe_msg, &mut ws, &inner, &mut batches, &chat_log, srv_out_sr, &out_fmt, playback.as_ref()).await;
+                                for b in batches { tool_jobs.spawn(runner.clone().run(b)); }
+                                if !inner.read().status.active { break; }
+                            }
+                            Some(Ok(done)) = tool_jobs.join_next() => {
+                                finish_tool_batch(done, &mut ws, &inner, &chat_log).await;
                                 if !inner.read().status.active { break; }
                             }
                             _ = &mut rx => {
-                                let _ = ws.close(None).await; break;
+                                let _ = ws.send(Message::Close(None)).await; break;
                             }
                         }
                     }
@@ -700,17 +923,29 @@ impl RealtimeManager {
                     loop {
                         tokio::select! {
                             _ = tokio::time::sleep(std::time::Duration::from_millis(10)) => {},
-                            maybe_msg = ws.next() => {
-                                handle_ws_message(maybe_msg, &mut ws, &inner, &tools, &policy, &approval_prompt, &chat_dir, 24_000, &"pcm16".to_string(), None).await;
+                            maybe_msg = ws_stream.next() => {
+                                let mut batches = vec![];
+                                handle_ws_message(maybe_msg, &mut ws, &inner, &mut batches, &chat_log, 24_000, &"pcm16".to_string(), None).await;
+                                for b in batches { tool_jobs.spawn(runner.clone().run(b)); }
+                                if !inner.read().status.active { break; }
+                            }
+                            Some(Ok(done)) = tool_jobs.join_next() => {
+                                finish_tool_batch(done, &mut ws, &inner, &chat_log).await;
                                 if !inner.read().status.active { break; }
                             }
-                            _ = &mut rx => { let _ = ws.close(None).await; break; }
+                            _ = &mut rx => { let _ = ws.send(Message::Close(None)).await; break; }
                         }
                     }
                 }
 
+                // Calls still waiting on approval end with the session (their prompts come down)
+                tool_jobs.shutdown().await;
+                if let Some(rec) = recorder { rec.finish(); }
+                if let Some(j) = journal.as_ref() { j.finish(None).await; }
                 let mut g = inner.write();
                 g.status.active = false;
+                g.recorder = None;
+                g.journal = None;
                 });
             });
 
@@ -738,64 +973,284 @@ impl RealtimeManager {
     pub fn status(&self) -> RealtimeStatus {
         self.inner.read().status.clone()
     }
-}
 
-#[allow(unused)]
-async fn handle_tool_call(tools: &crate::tools::ToolsManager, policy: &crate::gatekeeper::PolicyEngine, approval_prompt: &Arc<RwLock<Option<crate::app::EphemeralApproval>>>, name: &str, args: serde_json::Value) -> serde_json::Value {
-    // Split server.tool or server_tool
-    let (server, tool) = if let Some((s, t)) = name.split_once('.') { (s.to_string(), t.to_string()) }
-                        else if let Some((s,t)) = name.split_once('_') { (s.to_string(), t.to_string()) }
-                        else { (name.to_string(), String::new()) };
-    // Special synthetic tool: end_call
-    if server == "end" && tool == "call" || name == "end_call" {
-        // Caller (model) is asking to end; return a simple ack. The bridge will interpret this and stop shortly after.
-        return serde_json::json!({"ok": true});
-    }
-    // Basic policy evaluation, with ephemeral approval prompt on Hold/Warn
-    let action = crate::gatekeeper::ProposedAction { command: format!("{}.{}", server, tool), writes: might_write(&server, &tool), paths: vec![], intent: None };
-    let mut decision = policy.evaluate(&action);
-    if decision.kind != crate::gatekeeper::PolicyDecisionKind::Allow {
-        // Prepare or wait for an ephemeral prompt
-        // If another prompt exists, wait for it to clear; else set ours and wait.
-        let my_id = uuid::Uuid::new_v4().to_string();
-        let title = format!("Realtime tool requires approval: {}.{}", server, tool);
-        let details = serde_json::json!({"server": server, "tool": tool, "arguments": args.clone()});
+    /// Feed the inbound frames of a recorded session through the same handler as a
+    /// live call, against a fake socket. Tools, policy and chat dir are this
+    /// manager's; session state is fresh and does not touch a running session.
+    #[cfg(feature = "realtime")]
+    pub async fn replay(&self, bundle: &crate::realtime_record::SessionBundle) -> ReplayOutcome {
+        use crate::realtime_record::RecordedFrame;
+        use tokio_tungstenite::tungstenite::Message;
+        let inner = Arc::new(RwLock::new(InnerState { ring: VecDeque::with_capacity(16000*8), ..Default::default() }));
         {
-            let mut w = approval_prompt.write();
-            if w.is_none() {
-                *w = Some(crate::app::EphemeralApproval { id: my_id.clone(), title, action: action.clone(), details });
-            }
+            let mut g = inner.write();
+            g.status.active = true;
+            g.status.model = bundle.meta.model.clone();
+            g.session_log = Some(SessionLog { started_at: Utc::now(), tool_calls: vec![] });
         }
-        // Wait until prompt clears (treated as approved). Timeout after 120s.
-        let start = std::time::Instant::now();
-        loop {
-            if start.elapsed().as_secs() > 120 {
-                return serde_json::json!({"error": "approval timeout"});
-            }
-            let cur = approval_prompt.read().clone();
-            match cur {
-                None => break,
-                Some(p) => {
-                    if p.id != my_id { /* another prompt is active; keep waiting */ }
+        let chat_log = self.chat_log();
+        let runner = ToolRunner { tools: self.tools.clone(), policy: self.policy.clone(), approval_prompt: self.approval_prompt.clone(), decisions: self.decisions.clone(), mic: None };
+        // Vec<Message> is an infallible sink, so sends behave as on a healthy socket
+        let mut sent: Vec<Message> = vec![];
+        for ev in bundle.inbound() {
+            let msg = match &ev.frame {
+                RecordedFrame::Json { event } => {
+                    #[cfg(feature = "realtime-audio")]
+                    let event = &restore_elided_audio(event);
+                    Some(Ok(Message::Text(event.to_string())))
                 }
+                RecordedFrame::Text { text } => Some(Ok(Message::Text(text.clone()))),
+                RecordedFrame::Binary { offset, len } => Some(Ok(Message::Binary(bundle.binary(*offset, *len).to_vec()))),
+                RecordedFrame::Close => None,
+            };
+            let mut batches = vec![];
+            handle_ws_message(msg, &mut sent, &inner, &mut batches, &chat_log, bundle.meta.out_sr, &bundle.meta.out_format, None).await;
+            // Tool calls finish before the next frame so the replay is deterministic
+            for b in batches {
+                let done = runner.clone().run(b).await;
+                finish_tool_batch(done, &mut sent, &inner, &chat_log).await;
+            }
+            if !inner.read().status.active { break; }
+        }
+        let g = inner.read();
+        let mut processed_calls: Vec<String> = g.processed_calls.iter().cloned().collect();
+        processed_calls.sort();
+        ReplayOutcome {
+            outbound: sent.iter().filter_map(|m| match m { Message::Text(t) => serde_json::from_str(t).ok(), _ => None }).collect(),
+            closed: sent.iter().any(|m| matches!(m, Message::Close(_))),
+            active: g.status.active,
+            playing_audio: g.playing_audio,
+            response_active: g.response_active,
+            assistant_flushed: g.assistant_flushed,
+            processed_calls,
+            last_error: g.status.last_error.clone(),
+        }
+    }
+}
+
+/// Mic tap for a spoken yes/no: while an approval prompt listens, the event
+/// loop hands it mic frames instead of streaming them to the model.
+#[derive(Clone)]
+pub(crate) struct SpokenConfirm {
+    tap: Arc<parking_lot::Mutex<Option<tokio::sync::mpsc::Sender<Vec<i16>>>>>,
+    sample_rate: u32,
+}
+
+impl SpokenConfirm {
+    #[cfg_attr(not(feature = "realtime-audio"), allow(dead_code))]
+    pub(crate) fn new(sample_rate: u32) -> Self { Self { tap: Default::default(), sample_rate } }
+
+    fn listen(&self) -> tokio::sync::mpsc::Receiver<Vec<i16>> {
+        let (tx, rx) = tokio::sync::mpsc::channel(64);
+        *self.tap.lock() = Some(tx);
+        rx
+    }
+
+    pub(crate) fn release(&self) { *self.tap.lock() = None; }
+
+    /// Listen until a clear spoken yes (true) or no (false) is heard.
+    pub(crate) async fn answer(&self) -> bool { listen_for_spoken_answer(&mut self.listen(), self.sample_rate).await }
+
+    /// Give a frame to a listening prompt; false when nobody is listening.
+    #[cfg_attr(not(feature = "realtime-audio"), allow(dead_code))]
+    pub(crate) fn offer(&self, pcm: &[i16]) -> bool {
+        let mut tap = self.tap.lock();
+        let Some(tx) = tap.as_ref() else { return false };
+        if tx.is_closed() { *tap = None; return false; }
+        let _ = tx.try_send(pcm.to_vec());
+        true
+    }
+}
+
+#[cfg(feature = "realtime")]
+/// How a batch of tool results goes back to the model.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum ToolBatchKind {
+    /// `response.done` function calls: `function_call_output` items, then `response.create`.
+    Function,
+    /// `tool.call`: one `tool.output`.
+    Legacy,
+}
+
+#[cfg(feature = "realtime")]
+/// Tool calls from one inbound event. They run off the event loop (approval can
+/// take minutes) and come back with results for `finish_tool_batch`.
+pub(crate) struct ToolBatch { kind: ToolBatchKind, calls: Vec<ToolRun> }
+
+#[cfg(feature = "realtime")]
+pub(crate) struct ToolRun {
+    id: Option<String>,
+    name: String,
+    args: serde_json::Value,
+    result: serde_json::Value,
+    approval: Option<crate::gatekeeper::tool_calls::ToolApproval>,
+}
+
+#[cfg(feature = "realtime")]
+impl ToolRun {
+    fn new(id: Option<String>, name: String, args: serde_json::Value) -> Self {
+        Self { id, name, args, result: serde_json::Value::Null, approval: None }
+    }
+}
+
+#[cfg(feature = "realtime")]
+/// What a tool batch needs to run outside the event loop.
+#[derive(Clone)]
+struct ToolRunner {
+    tools: crate::tools::ToolsManager,
+    policy: Arc<crate::gatekeeper::PolicyEngine>,
+    approval_prompt: Arc<RwLock<Option<crate::app::EphemeralApproval>>>,
+    decisions: crate::gatekeeper::PromptDecisions,
+    mic: Option<SpokenConfirm>,
+}
+
+#[cfg(feature = "realtime")]
+impl ToolRunner {
+    async fn run(self, mut batch: ToolBatch) -> ToolBatch {
+        for call in batch.calls.iter_mut() {
+            let (result, approval) = handle_tool_call(&self.tools, &self.policy, &self.approval_prompt, &self.decisions, self.mic.as_ref(), &call.name, call.args.clone()).await;
+            call.result = result;
+            call.approval = approval;
+        }
+        batch
+    }
+}
+
+#[cfg(feature = "realtime")]
+/// Report a finished batch: journal and session log, the outputs, and for
+/// function calls a follow-up `response.create` (or the close for `end_call`).
+async fn finish_tool_batch<W>(batch: ToolBatch, ws: &mut W, inner: &Arc<RwLock<InnerState>>, chat_log: &Option<ChatLog>)
+where W: futures_util::Sink<tokio_tungstenite::tungstenite::Message> + Unpin {
+    use tokio_tungstenite::tungstenite::Message;
+    let mut call_events: Vec<(String, bool, Option<String>)> = vec![]; // (name, ok, err)
+    for ToolRun { id, name, args, result, approval } in batch.calls {
+        let journal = inner.read().journal.clone();
+        if let Some(j) = journal { j.tool(&tool_record(id.clone(), &name, args, &result, approval)).await; }
+        // Log to session
+        {
+            let ok = result.get("error").is_none();
+            let err = result.get("error").and_then(|e| e.as_str()).map(|s| truncate(s, 120));
+            let mut g = inner.write();
+            if let Some(log) = g.session_log.as_mut() { log.tool_calls.push(SessionToolEvent { name: name.clone(), ok, error: err.clone() }); }
+            call_events.push((name.clone(), ok, err));
+        }
+        if let ToolBatchKind::Legacy = batch.kind {
+            let mut out = serde_json::json!({"type": "tool.output", "name": name, "output": result});
+            if let Some(i) = id { out.as_object_mut().unwrap().insert("id".into(), serde_json::json!(i)); }
+            let _ = ws.send(Message::Text(out.to_string())).await;
+            continue;
+        }
+        // Send function_call_output conversation item (with event_id)
+        let call_id = id.unwrap_or_default();
+        let output_str = serde_json::to_string(&result).unwrap_or_else(|_| "{}".into());
+        rt_log(format!("function_result: name={} ok={} len={} body={}", name, result.get("error").is_none(), output_str.len(), truncate(&output_str, 300)));
+        let eid = Uuid::new_v4().to_string();
+        inner.write().event_tags.insert(eid.clone(), format!("function_call_output {}", call_id));
+        let item = serde_json::json!({
+            "event_id": eid,
+            "type": "conversation.item.create",
+            "item": {"type": "function_call_output", "call_id": call_id, "output": output_str}
+        });
+        let _ = ws.send(Message::Text(item.to_string())).await;
+        // If end_call, close session now (no further response)
+        if name == "end_call" || name == "end.call" {
+            rt_log("end_call: closing websocket and ending session (response.done)");
+            {
+                let mut g = inner.write();
+                g.status.active = false;
+                g.response_active = false;
             }
-            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            let _ = ws.send(Message::Close(None)).await;
+            return;
+        }
+    }
+    if let ToolBatchKind::Legacy = batch.kind { return; }
+    // Optionally append a compact tool summary into chat
+    let append_summary = match std::env::var("REALTIME_APPEND_TOOL_SUMMARY") { Ok(v) => matches!(v.as_str(), "1"|"true"|"True"|"TRUE"), Err(_) => false };
+    if append_summary && !call_events.is_empty() {
+        let mut parts: Vec<String> = vec![];
+        for (name, ok, err) in call_events.iter().take(4) {
+            if *ok { parts.push(format!("{} ok", name)); }
+            else { parts.push(format!("{} err: {}", name, err.clone().unwrap_or_else(|| "(error)".into()))); }
         }
-        // Re-evaluate (policy may still be non-allow but we proceed as approved for now)
-        decision = policy.evaluate(&action);
-        // Continue regardless; approvals path accepted
+        if call_events.len() > 4 { parts.push(format!("… {} more", call_events.len() - 4)); }
+        let line = if call_events.len() == 1 { format!("Tool: {}", parts.join(", ")) } else { format!("Tools: {}", parts.join(", ")) };
+        if let Some(log) = chat_log.as_ref() { let _ = log.append("assistant", &line).await; }
     }
-    // Invoke tool
-    match tools.invoke(&server, &tool, args).await {
-        Ok(v) => v,
-        Err(e) => serde_json::json!({"error": e.to_string()})
+    // Ask model to continue with the provided function outputs (guard against concurrent)
+    let mut allow = false;
+    {
+        let mut g = inner.write();
+        if !g.response_active { g.response_active = true; allow = true; }
+    }
+    if allow {
+        let eid = Uuid::new_v4().to_string();
+        inner.write().event_tags.insert(eid.clone(), "response.create post function_call_output".into());
+        rt_log("-> response.create (post function_call_output)");
+        let create = serde_json::json!({"event_id": eid, "type":"response.create","response": {"modalities":["audio","text"]}});
+        let _ = ws.send(Message::Text(create.to_string())).await;
+    } else {
+        rt_log("skip response.create (response_active)");
     }
 }
 
-fn might_write(server: &str, tool: &str) -> bool {
-    if server == "installer" { return true; }
-    if tool.contains("write") || tool.contains("apply") || tool.contains("install") { return true; }
-    false
+/// Past calls folded into the instructions of a new call.
+pub const RECENT_CALLS: i64 = 3;
+
+/// Run a tool call for the model. `end_call` is answered here; everything
+/// else goes through the gatekeeper.
+#[cfg(feature = "realtime")]
+async fn handle_tool_call(tools: &crate::tools::ToolsManager, policy: &crate::gatekeeper::PolicyEngine, approval_prompt: &Arc<RwLock<Option<crate::app::EphemeralApproval>>>, decisions: &crate::gatekeeper::PromptDecisions, mic: Option<&SpokenConfirm>, name: &str, args: serde_json::Value) -> (serde_json::Value, Option<crate::gatekeeper::tool_calls::ToolApproval>) {
+    if name == "end_call" || name == "end.call" {
+        // Caller (model) is asking to end; return a simple ack. The bridge will interpret this and stop shortly after.
+        return (serde_json::json!({"ok": true}), None);
+    }
+    crate::gatekeeper::tool_calls::run_tool_call(tools, policy, approval_prompt, decisions, mic, "Realtime", name, args).await
+}
+
+/// Journal entry for a finished tool call.
+#[cfg(feature = "realtime")]
+fn tool_record(call_id: Option<String>, name: &str, arguments: serde_json::Value, result: &serde_json::Value, approval: Option<crate::gatekeeper::tool_calls::ToolApproval>) -> crate::realtime_sessions::ToolCall {
+    crate::realtime_sessions::ToolCall {
+        call_id,
+        name: name.to_string(),
+        arguments,
+        ok: result.get("error").is_none(),
+        result: result.clone(),
+        approval_id: approval.as_ref().map(|a| a.id.clone()),
+        approval: approval.map(|a| a.decision.to_string()),
+        at: Utc::now(),
+    }
+}
+
+/// RMS level above which a mic frame counts as speech for spoken confirmation.
+const SPOKEN_RMS: f32 = 500.0;
+
+/// Segment mic audio into utterances (energy gate, 600 ms hangover, 4 s cap),
+/// transcribe each locally and return the first clear yes (true) or no (false).
+async fn listen_for_spoken_answer(frames: &mut tokio::sync::mpsc::Receiver<Vec<i16>>, sr: u32) -> bool {
+    let mut utterance: Vec<i16> = vec![];
+    let mut silence_ms = 0.0f32;
+    while let Some(frame) = frames.recv().await {
+        let ms = frame.len() as f32 * 1000.0 / sr as f32;
+        if crate::duplex::rms(&frame) >= SPOKEN_RMS {
+            utterance.extend_from_slice(&frame);
+            silence_ms = 0.0;
+        } else if !utterance.is_empty() {
+            utterance.extend_from_slice(&frame);
+            silence_ms += ms;
+        }
+        let spoken_ms = utterance.len() as f32 * 1000.0 / sr as f32;
+        if !utterance.is_empty() && (silence_ms >= 600.0 || spoken_ms >= 4000.0) {
+            let text = transcribe_user_local(&utterance, sr).await;
+            rt_log(format!("approval: heard {:?}", text));
+            utterance.clear();
+            silence_ms = 0.0;
+            if let Some(ans) = crate::gatekeeper::parse_spoken_answer(&text) { return ans; }
+        }
+    }
+    // Capture ended; leave the decision to the UI or the timeout
+    std::future::pending().await
 }
 
 #[derive(Clone, Debug)]
@@ -804,7 +1259,7 @@ struct SessionToolEvent { name: String, ok: bool, error: Option<String> }
 #[derive(Clone, Debug)]
 struct SessionLog { started_at: DateTime<Utc>, tool_calls: Vec<SessionToolEvent> }
 
-fn build_session_summary(inner: &Arc<RwLock<InnerState>>, chat_dir: Option<&PathBuf>) -> Option<String> {
+fn build_session_summary(inner: &Arc<RwLock<InnerState>>, last_user: Option<String>) -> Option<String> {
     let (started, calls) = {
         let g = inner.read();
         let log = g.session_log.as_ref()?;
@@ -814,9 +1269,7 @@ fn build_session_summary(inner: &Arc<RwLock<InnerState>>, chat_dir: Option<&Path
     let mut lines = vec![format!("(voice) Session ended — duration {}s, tool calls {}", dur.max(0), calls.len())];
 
     // Optional: last user utterance from latest chat
-    if let Some(dir) = chat_dir {
-        if let Some(last_user) = last_user_utterance(dir) { lines.push(format!("Last user: {}", truncate(&last_user, 160))); }
-    }
+    if let Some(last_user) = last_user { lines.push(format!("Last user: {}", truncate(&last_user, 160))); }
 
     for ev in calls.iter().take(10) {
         if ev.ok { lines.push(format!("- {}: ok", ev.name)); }
@@ -842,6 +1295,47 @@ fn build_session_summary(inner: &Arc<RwLock<InnerState>>, chat_dir: Option<&Path
 
 fn truncate(s: &str, n: usize) -> String { if s.len() > n { format!("{}…", &s[..n]) } else { s.to_string() } }
 
+/// Where a call's transcript is logged: the latest chat session in memory, or
+/// the latest JSON file in the chats dir when running without memory.
+#[cfg(feature = "realtime")]
+#[derive(Clone)]
+enum ChatLog {
+    Store(crate::chats::ChatStore),
+    Dir(PathBuf),
+}
+
+#[cfg(feature = "realtime")]
+impl ChatLog {
+    /// Streamed pieces coalesce into the last message when the role matches.
+    async fn append(&self, role: &str, text: &str) -> anyhow::Result<()> {
+        match self {
+            ChatLog::Store(chats) => chats.append_to_latest(role, text, true).await,
+            ChatLog::Dir(dir) => append_latest_chat_message(dir, role, text).await,
+        }
+    }
+
+    async fn append_summary(&self, line: &str) -> anyhow::Result<()> {
+        match self {
+            ChatLog::Store(chats) => chats.append_to_latest("assistant", line, false).await,
+            ChatLog::Dir(dir) => append_voice_summary(dir, line).await,
+        }
+    }
+
+    async fn recent_context(&self) -> Option<String> {
+        match self {
+            ChatLog::Store(chats) => chats.recent_context().await,
+            ChatLog::Dir(dir) => build_recent_context(dir).await,
+        }
+    }
+
+    async fn last_user(&self) -> Option<String> {
+        match self {
+            ChatLog::Store(chats) => chats.last_user().await,
+            ChatLog::Dir(dir) => last_user_utterance(dir).await,
+        }
+    }
+}
+
 async fn build_recent_context(dir: &PathBuf) -> Option<String> {
     // Find latest *.json file
     let mut best: Option<(std::time::SystemTime, PathBuf)> = None;
@@ -957,30 +1451,26 @@ async fn transcribe_user_local(pcm: &[i16], sr: u32) -> String {
     String::new()
 }
 
-fn last_user_utterance(dir: &PathBuf) -> Option<String> {
+async fn last_user_utterance(dir: &PathBuf) -> Option<String> {
     // Read latest chat and return the last message by role == "user"
-    let mut rt = tokio::runtime::Runtime::new().ok()?;
-    let res: Option<String> = rt.block_on(async {
-        let mut best: Option<(std::time::SystemTime, PathBuf)> = None;
-        if let Ok(mut rd) = tokio::fs::read_dir(dir).await {
-            while let Ok(Some(e)) = rd.next_entry().await {
-                if e.file_type().await.ok().map(|t| t.is_file()).unwrap_or(false) {
-                    if e.path().extension().and_then(|s| s.to_str()) == Some("json") {
-                        if let Ok(meta) = e.metadata().await { if let Ok(mtime) = meta.modified() { if best.as_ref().map(|(t,_)| mtime > *t).unwrap_or(true) { best = Some((mtime, e.path())); } } }
-                    }
+    let mut best: Option<(std::time::SystemTime, PathBuf)> = None;
+    if let Ok(mut rd) = tokio::fs::read_dir(dir).await {
+        while let Ok(Some(e)) = rd.next_entry().await {
+            if e.file_type().await.ok().map(|t| t.is_file()).unwrap_or(false) {
+                if e.path().extension().and_then(|s| s.to_str()) == Some("json") {
+                    if let Ok(meta) = e.metadata().await { if let Ok(mtime) = meta.modified() { if best.as_ref().map(|(t,_)| mtime > *t).unwrap_or(true) { best = Some((mtime, e.path())); } } }
                 }
             }
         }
-        let path = match best { Some((_, p)) => p, None => return None };
-        let bytes = tokio::fs::read(&path).await.ok()?;
-        let v: serde_json::Value = serde_json::from_slice(&bytes).ok()?;
-        let msgs = v.get("messages").and_then(|x| x.as_array())?.clone();
-        for m in msgs.into_iter().rev() {
-            if m.get("role").and_then(|s| s.as_str()) == Some("user") {
-                if let Some(txt) = m.get("content").and_then(|s| s.as_str()) { return Some(txt.to_string()); }
-            }
+    }
+    let path = match best { Some((_, p)) => p, None => return None };
+    let bytes = tokio::fs::read(&path).await.ok()?;
+    let v: serde_json::Value = serde_json::from_slice(&bytes).ok()?;
+    let msgs = v.get("messages").and_then(|x| x.as_array())?.clone();
+    for m in msgs.into_iter().rev() {
+        if m.get("role").and_then(|s| s.as_str()) == Some("user") {
+            if let Some(txt) = m.get("content").and_then(|s| s.as_str()) { return Some(txt.to_string()); }
         }
-        None
-    });
-    res
+    }
+    None
 }
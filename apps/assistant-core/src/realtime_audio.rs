@@ -5,9 +5,14 @@ use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use ringbuf::{Producer, RingBuffer};
 
 #[cfg(feature = "realtime-audio")]
-use std::sync::{Arc, Mutex};
+use std::collections::VecDeque;
+
+#[cfg(feature = "realtime-audio")]
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 
 #[cfg(feature = "realtime-audio")]
+use std::sync::{Arc, Mutex};
+
 pub fn decode_ulaw_to_pcm(ulaw: &[u8]) -> Vec<i16> {
     // ITU-T G.711 µ-law decode
     fn ulaw_to_linear(sample: u8) -> i16 {
@@ -24,7 +29,21 @@ pub fn decode_ulaw_to_pcm(ulaw: &[u8]) -> Vec<i16> {
     ulaw.iter().map(|&b| ulaw_to_linear(b)).collect()
 }
 
-#[cfg(feature = "realtime-audio")]
+pub fn encode_pcm_to_ulaw(pcm: &[i16]) -> Vec<u8> {
+    // ITU-T G.711 µ-law encode (inverse of `decode_ulaw_to_pcm`)
+    fn linear_to_ulaw(sample: i16) -> u8 {
+        const BIAS: i32 = 0x84;
+        const CLIP: i32 = 32635;
+        let mut s = sample as i32;
+        let sign = if s < 0 { s = -s; 0x80 } else { 0 };
+        let s = s.min(CLIP) + BIAS;
+        let exponent = (24 - (s as u32).leading_zeros() as i32).clamp(0, 7);
+        let mantissa = (s >> (exponent + 3)) & 0x0F;
+        !(sign as u8 | ((exponent as u8) << 4) | mantissa as u8)
+    }
+    pcm.iter().map(|&s| linear_to_ulaw(s)).collect()
+}
+
 pub fn resample_linear_i16(input: &[i16], in_sr: u32, out_sr: u32) -> Vec<i16> {
     if in_sr == out_sr || input.is_empty() { return input.to_vec(); }
     let ratio = out_sr as f32 / in_sr as f32;
@@ -49,6 +68,12 @@ pub struct AudioPlayback {
     // Shared ring buffer for PCM i16 samples
     ring: Arc<Mutex<Producer<i16>>>,
     device_sr: u32,
+    // Samples actually sent to the device, kept for echo cancellation (~2s)
+    reference: Arc<Mutex<VecDeque<i16>>>,
+    // Set to drop everything still queued (barge-in)
+    flush: Arc<AtomicBool>,
+    // Non-silent samples played since the last `reset_played`
+    played: Arc<AtomicU64>,
 }
 
 // Provide a stub type when audio feature is disabled so other modules
@@ -82,12 +107,27 @@ impl AudioPlayback {
         let (prod, mut cons) = rb.split();
         let ring = Arc::new(Mutex::new(prod));
         let ring_cb = ring.clone();
+        let reference = Arc::new(Mutex::new(VecDeque::with_capacity(device_sr as usize * 2)));
+        let flush = Arc::new(AtomicBool::new(false));
+        let played = Arc::new(AtomicU64::new(0));
+        let (reference_cb, flush_cb, played_cb) = (reference.clone(), flush.clone(), played.clone());
+        let ref_cap = device_sr as usize * 2;
 
         let stream = device.build_output_stream(
             &cfg,
             move |data: &mut [i16], _: &cpal::OutputCallbackInfo| {
+                if flush_cb.swap(false, Ordering::SeqCst) {
+                    while cons.pop().is_some() {}
+                }
+                let mut n_played = 0u64;
                 for s in data.iter_mut() {
-                    if let Some(sample) = cons.pop() { *s = sample; } else { *s = 0; }
+                    if let Some(sample) = cons.pop() { *s = sample; n_played += 1; } else { *s = 0; }
+                }
+                played_cb.fetch_add(n_played, Ordering::Relaxed);
+                // Never block the audio thread; a missed tap only costs one block of reference
+                if let Ok(mut r) = reference_cb.try_lock() {
+                    r.extend(data.iter().copied());
+                    while r.len() > ref_cap { r.pop_front(); }
                 }
             },
             move |err| {
@@ -97,7 +137,7 @@ impl AudioPlayback {
             None,
         )?;
         stream.play()?;
-        Ok(Self { _stream: stream, ring, device_sr })
+        Ok(Self { _stream: stream, ring, device_sr, reference, flush, played })
     }
 
     pub fn push_pcm(&self, pcm: &[i16], src_sr: u32) {
@@ -108,6 +148,40 @@ impl AudioPlayback {
     }
 
     pub fn device_sr(&self) -> u32 { self.device_sr }
+
+    /// Pop the played reference matching `n` capture samples at `target_sr`,
+    /// zero-padded when playback has been idle.
+    pub fn take_reference(&self, n: usize, target_sr: u32) -> Vec<i16> {
+        let want = ((n as u64) * (self.device_sr as u64) / (target_sr.max(1) as u64)) as usize;
+        let raw: Vec<i16> = match self.reference.lock() {
+            Ok(mut r) => { let k = want.min(r.len()); r.drain(..k).collect() }
+            Err(_) => Vec::new(),
+        };
+        let mut out = resample_linear_i16(&raw, self.device_sr, target_sr);
+        out.resize(n, 0);
+        out
+    }
+
+    /// Drop queued audio so the assistant stops mid-sentence.
+    pub fn flush(&self) { self.flush.store(true, Ordering::SeqCst); }
+
+    /// Milliseconds of assistant audio played since the last `reset_played`.
+    pub fn played_ms(&self) -> u64 { self.played.load(Ordering::Relaxed) * 1000 / (self.device_sr.max(1) as u64) }
+
+    pub fn reset_played(&self) { self.played.store(0, Ordering::Relaxed); }
+}
+
+#[cfg(feature = "realtime-audio")]
+impl crate::tts::PcmSink for AudioPlayback {
+    fn push_pcm(&self, pcm: &[i16], sample_rate: u32) { AudioPlayback::push_pcm(self, pcm, sample_rate) }
+
+    fn flush(&self) { AudioPlayback::flush(self) }
+}
+
+/// Spoken-output queue on the default output device (opened on the player thread).
+#[cfg(feature = "realtime-audio")]
+pub fn speech_queue(sample_rate: u32) -> crate::tts::SpeechQueue {
+    crate::tts::SpeechQueue::spawn(move || AudioPlayback::new(sample_rate))
 }
 
 #[cfg(feature = "realtime-audio")]
@@ -200,7 +274,7 @@ pub fn devices_info_json() -> serde_json::Value {
 }
 
 #[cfg(feature = "realtime-audio")]
-pub async fn vad_capture_diagnostic(seconds: u32, desired_sr: u32, chunk_ms: u32, sensitivity: f32, min_speech_ms: u32, transcribe: bool) -> serde_json::Value {
+pub async fn vad_capture_diagnostic(seconds: u32, desired_sr: u32, chunk_ms: u32, sensitivity: f32, min_speech_ms: u32, transcribe: Option<&crate::stt::SttOptions>) -> serde_json::Value {
     use tokio::sync::mpsc;
     // Gather energies and simple VAD stats
     let (tx, mut rx) = mpsc::channel::<Vec<i16>>(32);
@@ -241,8 +315,12 @@ pub async fn vad_capture_diagnostic(seconds: u32, desired_sr: u32, chunk_ms: u32
     let mut cur_ms: u32 = 0;
     let mut speech_ms_total: u32 = 0;
     let mut speech_segments: u32 = 0;
-    let mut seg_buf: Vec<i16> = vec![];
-    let mut last_segment: Option<Vec<i16>> = None;
+    // Streaming STT per segment: partials as they arrive, finals resolved at the end
+    let mut stt: Option<crate::stt::SttStream> = None;
+    let mut partials: Vec<serde_json::Value> = vec![];
+    let mut finals: Vec<tokio::task::JoinHandle<anyhow::Result<String>>> = vec![];
+    let mut stt_error: Option<String> = None;
+    let t0 = std::time::Instant::now();
 
     let deadline = std::time::Instant::now() + std::time::Duration::from_secs(seconds as u64);
     while std::time::Instant::now() < deadline {
@@ -256,18 +334,37 @@ pub async fn vad_capture_diagnostic(seconds: u32, desired_sr: u32, chunk_ms: u32
             let thr: f32 = 0.010;
             last_thr = thr;
             if e > thr {
-                in_speech = true; cur_ms += chunk_ms; speech_ms_total += chunk_ms; seg_buf.extend_from_slice(&frame);
+                if !in_speech {
+                    if let Some(opts) = transcribe {
+                        match crate::stt::open_stream(opts, desired_sr).await { Ok(st) => stt = Some(st), Err(e) => stt_error = Some(e.to_string()) }
+                    }
+                }
+                in_speech = true; cur_ms += chunk_ms; speech_ms_total += chunk_ms;
+                if let Some(st) = stt.as_ref() { st.push(&frame); }
             } else {
                 if in_speech {
-                    if cur_ms >= min_speech_ms { speech_segments += 1; last_segment = Some(std::mem::take(&mut seg_buf)); }
-                    in_speech = false; cur_ms = 0; seg_buf.clear();
+                    if cur_ms >= min_speech_ms {
+                        speech_segments += 1;
+                        if let Some(st) = stt.take() { finals.push(tokio::spawn(st.finish())); }
+                    }
+                    in_speech = false; cur_ms = 0; stt = None;
+                }
+            }
+            if let Some(st) = stt.as_mut() {
+                while let Some(ev) = st.try_event() {
+                    if let crate::stt::SttEvent::Partial { text } = ev {
+                        partials.push(serde_json::json!({"t_ms": t0.elapsed().as_millis() as u64, "segment": speech_segments + 1, "text": text}));
+                    }
                 }
             }
         } else {
             // timeout - allow loop to continue until deadline
         }
     }
-    if in_speech && cur_ms >= min_speech_ms { speech_segments += 1; last_segment = Some(seg_buf); }
+    if in_speech && cur_ms >= min_speech_ms {
+        speech_segments += 1;
+        if let Some(st) = stt.take() { finals.push(tokio::spawn(st.finish())); }
+    }
     if frames > 0 { avg_energy /= frames as f32; }
     // noise floor approx: average of first 1s worth of frames
     let frames_per_sec = (1000 / chunk_ms.max(1)) as usize;
@@ -289,20 +386,16 @@ pub async fn vad_capture_diagnostic(seconds: u32, desired_sr: u32, chunk_ms: u32
         "speech_ms_total": speech_ms_total,
     });
 
-    if transcribe {
-        if let Some(pcm) = last_segment {
-            if std::env::var("OPENAI_API_KEY").is_ok() {
-                if let Ok(text) = crate::stt::transcribe_openai_pcm16(&pcm, desired_sr).await {
-                    result.as_object_mut().unwrap().insert("transcript".into(), serde_json::json!(text));
-                } else {
-                    result.as_object_mut().unwrap().insert("transcript".into(), serde_json::json!("(stt error)"));
-                }
-            } else {
-                result.as_object_mut().unwrap().insert("transcript".into(), serde_json::json!("(OPENAI_API_KEY not set)"));
-            }
-        } else {
-            result.as_object_mut().unwrap().insert("transcript".into(), serde_json::json!("(no segment)"));
+    if transcribe.is_some() {
+        let mut transcripts = vec![];
+        for h in finals {
+            match h.await { Ok(Ok(t)) => transcripts.push(t), Ok(Err(e)) => stt_error = Some(e.to_string()), Err(e) => stt_error = Some(e.to_string()) }
         }
+        let obj = result.as_object_mut().unwrap();
+        obj.insert("transcript".into(), serde_json::json!(transcripts.last().cloned().unwrap_or_else(|| "(no segment)".into())));
+        obj.insert("transcripts".into(), serde_json::json!(transcripts));
+        obj.insert("partials".into(), serde_json::json!(partials));
+        if let Some(e) = stt_error { obj.insert("stt_error".into(), serde_json::json!(e)); }
     }
 
     // Signal stop and allow the capture thread to exit
@@ -0,0 +1,552 @@
+//! Local realtime gateway: a WebSocket server speaking the subset of the OpenAI
+//! Realtime protocol that `realtime::handle_ws_message` consumes, backed by the
+//! local STT endpoint, an OpenAI-compatible chat completions endpoint and a TTS
+//! command. Point `/api/realtime/start` (or `[voice] realtime_endpoint`) at
+//! `ws://127.0.0.1:7072/v1/realtime` to run voice sessions fully offline.
+//!
+//! Client events: session.update, input_audio_buffer.{append,commit,clear},
+//! conversation.item.{create,truncate}, response.{create,cancel}.
+//! Server events: session.{created,updated}, input_audio_buffer.{speech_started,
+//! speech_stopped,committed,cleared}, conversation.item.*, response.created,
+//! response.output_item.{added,done}, response.{audio,audio_transcript,text}.{delta,done},
+//! response.function_call_arguments.done, response.done, error.
+
+use crate::duplex::Vad;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use base64::{engine::general_purpose::STANDARD as B64, Engine as _};
+use futures_util::{SinkExt, StreamExt};
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+#[derive(Debug, Clone)]
+pub struct GatewayConfig {
+    /// Local STT endpoint (`stt::transcribe_local_pcm16`); None uses LOCAL_STT_ENDPOINT or its default.
+    pub stt_endpoint: Option<String>,
+    /// OpenAI-compatible chat completions URL (Ollama, llama.cpp server, vLLM, OpenAI).
+    pub llm_url: String,
+    pub llm_model: String,
+    pub llm_api_key: Option<String>,
+    /// Shell command reading text on stdin and writing raw s16le mono PCM on stdout
+    /// (e.g. `piper --model voice.onnx --output-raw`). None: transcript only, no audio.
+    pub tts_cmd: Option<String>,
+    pub tts_sr: u32,
+    /// Sample rate of incoming `input_audio_buffer.append` PCM16.
+    pub in_sr: u32,
+    /// Server VAD: silence that ends a user turn.
+    pub silence_ms: u32,
+    pub vad_sensitivity: f32,
+}
+
+impl Default for GatewayConfig {
+    fn default() -> Self {
+        Self {
+            stt_endpoint: None,
+            llm_url: "http://127.0.0.1:11434/v1/chat/completions".into(),
+            llm_model: "llama3.1".into(),
+            llm_api_key: None,
+            tts_cmd: None,
+            tts_sr: 22_050,
+            in_sr: 16_000,
+            silence_ms: 600,
+            vad_sensitivity: 0.5,
+        }
+    }
+}
+
+impl GatewayConfig {
+    /// `[voice.realtime_gateway]` for the model and turn-taking, `[voice] stt`
+    /// for the transcription endpoint and `[voice] tts` (piper or command) for speech.
+    /// Without an `llm_url`, the LLM follows the core chat provider when
+    /// `OPENAI_BASE_URL` points it somewhere (with `OPENAI_MODEL` and
+    /// `OPENAI_API_KEY`), else local Ollama.
+    pub fn from_config(cfg: &crate::config::Config) -> Self {
+        let d = Self::default();
+        let voice = cfg.voice.as_ref();
+        let g = voice.and_then(|v| v.realtime_gateway.clone()).unwrap_or_default();
+        let tts = crate::tts::TtsOptions::from_config(voice.and_then(|v| v.tts.as_ref()));
+        let env = |k: &str| std::env::var(k).ok().filter(|v| !v.trim().is_empty());
+        let core = g.llm_url.is_none() && env("OPENAI_BASE_URL").is_some();
+        let (llm_url, llm_model, llm_key) = if core {
+            (crate::api::openai_chat_url(), env("OPENAI_MODEL").unwrap_or_else(|| "gpt-5".into()), env("OPENAI_API_KEY"))
+        } else {
+            (g.llm_url.unwrap_or(d.llm_url), d.llm_model, None)
+        };
+        Self {
+            stt_endpoint: crate::stt::SttOptions::from_config(voice.and_then(|v| v.stt.as_ref())).endpoint,
+            llm_url,
+            llm_model: g.llm_model.unwrap_or(llm_model),
+            llm_api_key: g.llm_api_key_env.and_then(|k| env(&k)).or(llm_key),
+            tts_cmd: tts.command_line(),
+            tts_sr: tts.command_sample_rate(),
+            in_sr: g.in_sr.unwrap_or(d.in_sr),
+            silence_ms: g.silence_ms.unwrap_or(d.silence_ms),
+            vad_sensitivity: g.vad_sensitivity.unwrap_or(d.vad_sensitivity),
+        }
+    }
+}
+
+/// Routes: `GET /v1/realtime` and `/realtime` (WebSocket), `GET /health`.
+pub fn router(cfg: GatewayConfig) -> Router {
+    Router::new()
+        .route("/v1/realtime", get(ws_upgrade))
+        .route("/realtime", get(ws_upgrade))
+        .route("/health", get(|| async { "ok" }))
+        .with_state(Arc::new(cfg))
+}
+
+async fn ws_upgrade(State(cfg): State<Arc<GatewayConfig>>, ws: WebSocketUpgrade) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| run_session(socket, cfg))
+}
+
+fn new_id(prefix: &str) -> String {
+    format!("{}_{}", prefix, Uuid::new_v4().simple())
+}
+
+/// Queue a server event, stamping an event_id.
+fn emit(tx: &mpsc::UnboundedSender<Value>, mut ev: Value) {
+    if let Some(o) = ev.as_object_mut() {
+        o.entry("event_id").or_insert_with(|| json!(new_id("event")));
+    }
+    let _ = tx.send(ev);
+}
+
+fn emit_error(tx: &mpsc::UnboundedSender<Value>, code: &str, message: &str, event_id: Option<&str>) {
+    emit(tx, json!({"type": "error", "error": {"type": "invalid_request_error", "code": code, "message": message, "event_id": event_id}}));
+}
+
+struct Session {
+    cfg: Arc<GatewayConfig>,
+    id: String,
+    instructions: String,
+    /// Chat-completions tool definitions converted from the Realtime schema.
+    tools: Vec<Value>,
+    out_format: String,
+    auto_response: bool,
+    history: Arc<Mutex<Vec<Value>>>,
+    audio: Vec<i16>,
+    /// Committed turns for the transcriber, as (item id, PCM).
+    commits: mpsc::UnboundedSender<(String, Vec<i16>)>,
+    vad: Vad,
+    in_speech: bool,
+    silence_ms: f32,
+    audio_ms: f32,
+    response: Option<tokio::task::JoinHandle<()>>,
+    response_id: Option<String>,
+}
+
+impl Session {
+    fn new(cfg: Arc<GatewayConfig>, commits: mpsc::UnboundedSender<(String, Vec<i16>)>) -> Self {
+        let vad = Vad::new(cfg.vad_sensitivity, 100);
+        Self {
+            cfg,
+            id: new_id("sess"),
+            instructions: String::new(),
+            tools: vec![],
+            out_format: "pcm16".into(),
+            auto_response: false,
+            history: Arc::new(Mutex::new(vec![])),
+            audio: vec![],
+            commits,
+            vad,
+            in_speech: false,
+            silence_ms: 0.0,
+            audio_ms: 0.0,
+            response: None,
+            response_id: None,
+        }
+    }
+
+    fn response_active(&self) -> bool {
+        self.response.as_ref().map(|h| !h.is_finished()).unwrap_or(false)
+    }
+
+    fn describe(&self) -> Value {
+        json!({"id": self.id, "object": "realtime.session", "model": self.cfg.llm_model, "instructions": self.instructions, "output_audio_format": self.out_format, "input_audio_format": "pcm16", "tools": self.tools.len()})
+    }
+}
+
+async fn run_session(socket: WebSocket, cfg: Arc<GatewayConfig>) {
+    let (mut sink, mut stream) = socket.split();
+    let (tx, mut rx) = mpsc::unbounded_channel::<Value>();
+    let writer = tokio::spawn(async move {
+        while let Some(ev) = rx.recv().await {
+            if sink.send(Message::Text(ev.to_string())).await.is_err() { break; }
+        }
+    });
+    // Transcribe committed turns in order, off the read loop
+    let (commit_tx, mut commit_rx) = mpsc::unbounded_channel::<(String, Vec<i16>)>();
+    let (done_tx, mut done_rx) = mpsc::unbounded_channel::<(String, anyhow::Result<String>)>();
+    let stt = cfg.clone();
+    let transcriber = tokio::spawn(async move {
+        while let Some((item_id, pcm)) = commit_rx.recv().await {
+            let text = crate::stt::transcribe_local_pcm16(&pcm, stt.in_sr, stt.stt_endpoint.as_deref()).await;
+            if done_tx.send((item_id, text)).is_err() { break; }
+        }
+    });
+    let mut s = Session::new(cfg, commit_tx);
+    emit(&tx, json!({"type": "session.created", "session": s.describe()}));
+    loop {
+        tokio::select! {
+            msg = stream.next() => {
+                let txt = match msg {
+                    Some(Ok(Message::Text(t))) => t,
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                };
+                let Ok(v) = serde_json::from_str::<Value>(&txt) else {
+                    emit_error(&tx, "invalid_json", "event is not valid JSON", None);
+                    continue;
+                };
+                handle_client_event(&mut s, &tx, v);
+            }
+            Some((item_id, text)) = done_rx.recv() => transcribed(&mut s, &tx, item_id, text),
+        }
+    }
+    transcriber.abort();
+    if let Some(h) = s.response.take() { h.abort(); }
+    drop(tx);
+    let _ = writer.await;
+}
+
+fn handle_client_event(s: &mut Session, tx: &mpsc::UnboundedSender<Value>, v: Value) {
+    let typ = v.get("type").and_then(|t| t.as_str()).unwrap_or("");
+    let eid = v.get("event_id").and_then(|t| t.as_str());
+    match typ {
+        "session.update" => {
+            let sess = v.get("session").cloned().unwrap_or_else(|| json!({}));
+            if let Some(i) = sess.get("instructions").and_then(|x| x.as_str()) { s.instructions = i.to_string(); }
+            if let Some(f) = sess.get("output_audio_format").and_then(|x| x.as_str()) { s.out_format = f.to_string(); }
+            if let Some(tools) = sess.get("tools").and_then(|x| x.as_array()) { s.tools = tools.iter().filter_map(chat_tool).collect(); }
+            if let Some(td) = sess.get("turn_detection") {
+                s.auto_response = td.get("create_response").and_then(|x| x.as_bool()).unwrap_or(false);
+            }
+            emit(tx, json!({"type": "session.updated", "session": s.describe()}));
+        }
+        "input_audio_buffer.append" => {
+            let Some(b64) = v.get("audio").and_then(|x| x.as_str()) else { return emit_error(tx, "missing_audio", "input_audio_buffer.append requires audio", eid) };
+            let Ok(bytes) = B64.decode(b64) else { return emit_error(tx, "invalid_audio", "audio is not valid base64", eid) };
+            let pcm: Vec<i16> = bytes.chunks_exact(2).map(|c| i16::from_le_bytes([c[0], c[1]])).collect();
+            append_audio(s, tx, &pcm);
+        }
+        "input_audio_buffer.commit" => {
+            if s.audio.is_empty() { return emit_error(tx, "input_audio_buffer_commit_empty", "buffer is empty", eid); }
+            commit_audio(s, tx);
+        }
+        "input_audio_buffer.clear" => {
+            s.audio.clear();
+            s.in_speech = false;
+            s.vad.reset();
+            emit(tx, json!({"type": "input_audio_buffer.cleared"}));
+        }
+        "conversation.item.create" => {
+            let item = v.get("item").cloned().unwrap_or_else(|| json!({}));
+            match chat_message(&item) {
+                Some(msg) => {
+                    s.history.lock().push(msg);
+                    let mut item = item;
+                    if item.get("id").is_none() { item["id"] = json!(new_id("item")); }
+                    emit(tx, json!({"type": "conversation.item.created", "item": item}));
+                }
+                None => emit_error(tx, "unsupported_item", "only message and function_call_output items are supported", eid),
+            }
+        }
+        "conversation.item.truncate" => {
+            // Audio is not replayed server-side; acknowledge so clients can proceed
+            emit(tx, json!({"type": "conversation.item.truncated", "item_id": v.get("item_id"), "content_index": v.get("content_index"), "audio_end_ms": v.get("audio_end_ms")}));
+        }
+        "response.create" => {
+            if s.response_active() {
+                return emit_error(tx, "conversation_already_has_active_response", "a response is already in progress", eid);
+            }
+            let opts = v.get("response").cloned().unwrap_or_else(|| json!({}));
+            start_response(s, tx, &opts);
+        }
+        "response.cancel" => {
+            match s.response.take() {
+                Some(h) if !h.is_finished() => {
+                    h.abort();
+                    let id = s.response_id.take();
+                    emit(tx, json!({"type": "response.done", "response": {"id": id, "object": "realtime.response", "status": "cancelled", "output": []}}));
+                }
+                _ => emit_error(tx, "response_cancel_not_active", "no active response", eid),
+            }
+        }
+        _ => emit_error(tx, "unknown_event", &format!("unsupported event type '{}'", typ), eid),
+    }
+}
+
+/// Server VAD over appended audio: emits speech_started/stopped and commits the
+/// turn after `silence_ms` of trailing silence.
+fn append_audio(s: &mut Session, tx: &mpsc::UnboundedSender<Value>, pcm: &[i16]) {
+    let frame = (s.cfg.in_sr as usize / 50).max(1); // 20 ms
+    for chunk in pcm.chunks(frame) {
+        let ms = chunk.len() as f32 * 1000.0 / s.cfg.in_sr as f32;
+        s.audio.extend_from_slice(chunk);
+        s.audio_ms += ms;
+        let speech = s.vad.push(chunk, ms, 0.0);
+        if speech && !s.in_speech {
+            s.in_speech = true;
+            emit(tx, json!({"type": "input_audio_buffer.speech_started", "audio_start_ms": s.audio_ms as u64}));
+        }
+        if !s.in_speech {
+            // Keep a short pre-roll only; silence before speech is not part of the turn
+            let keep = s.cfg.in_sr as usize / 2;
+            if s.audio.len() > keep { let cut = s.audio.len() - keep; s.audio.drain(..cut); }
+            continue;
+        }
+        s.silence_ms = if speech { 0.0 } else { s.silence_ms + ms };
+        if s.silence_ms >= s.cfg.silence_ms as f32 {
+            emit(tx, json!({"type": "input_audio_buffer.speech_stopped", "audio_end_ms": s.audio_ms as u64}));
+            commit_audio(s, tx);
+        }
+    }
+}
+
+/// Commit the buffered turn and hand it to the transcriber.
+fn commit_audio(s: &mut Session, tx: &mpsc::UnboundedSender<Value>) {
+    let pcm = std::mem::take(&mut s.audio);
+    s.in_speech = false;
+    s.silence_ms = 0.0;
+    s.vad.reset();
+    let item_id = new_id("item");
+    emit(tx, json!({"type": "input_audio_buffer.committed", "item_id": item_id}));
+    let _ = s.commits.send((item_id, pcm));
+}
+
+/// A committed turn came back from the transcriber: add it as a user message.
+fn transcribed(s: &mut Session, tx: &mpsc::UnboundedSender<Value>, item_id: String, text: anyhow::Result<String>) {
+    match text {
+        Ok(text) if !text.trim().is_empty() => {
+            let text = text.trim().to_string();
+            s.history.lock().push(json!({"role": "user", "content": text}));
+            emit(tx, json!({"type": "conversation.item.created", "item": {"id": item_id, "type": "message", "role": "user", "content": [{"type": "input_audio", "transcript": text}]}}));
+            emit(tx, json!({"type": "conversation.item.input_audio_transcription.completed", "item_id": item_id, "content_index": 0, "transcript": text}));
+            if s.auto_response && !s.response_active() { start_response(s, tx, &json!({})); }
+        }
+        Ok(_) => {}
+        Err(e) => emit(tx, json!({"type": "conversation.item.input_audio_transcription.failed", "item_id": item_id, "content_index": 0, "error": {"type": "stt_error", "message": e.to_string()}})),
+    }
+}
+
+fn start_response(s: &mut Session, tx: &mpsc::UnboundedSender<Value>, opts: &Value) {
+    let id = new_id("resp");
+    let modalities: Vec<String> = opts.get("modalities").and_then(|m| m.as_array())
+        .map(|a| a.iter().filter_map(|x| x.as_str().map(String::from)).collect())
+        .unwrap_or_else(|| vec!["audio".into(), "text".into()]);
+    let job = ResponseJob {
+        cfg: s.cfg.clone(),
+        id: id.clone(),
+        instructions: opts.get("instructions").and_then(|x| x.as_str()).map(String::from).unwrap_or_else(|| s.instructions.clone()),
+        tools: s.tools.clone(),
+        audio: modalities.iter().any(|m| m == "audio"),
+        out_format: s.out_format.clone(),
+        history: s.history.clone(),
+        tx: tx.clone(),
+    };
+    s.response_id = Some(id);
+    s.response = Some(tokio::spawn(job.run()));
+}
+
+struct ResponseJob {
+    cfg: Arc<GatewayConfig>,
+    id: String,
+    instructions: String,
+    tools: Vec<Value>,
+    audio: bool,
+    out_format: String,
+    history: Arc<Mutex<Vec<Value>>>,
+    tx: mpsc::UnboundedSender<Value>,
+}
+
+impl ResponseJob {
+    async fn run(self) {
+        let tx = &self.tx;
+        emit(tx, json!({"type": "response.created", "response": {"id": self.id, "object": "realtime.response", "status": "in_progress", "output": []}}));
+        let mut messages = vec![];
+        if !self.instructions.is_empty() { messages.push(json!({"role": "system", "content": self.instructions})); }
+        messages.extend(self.history.lock().iter().cloned());
+        let reply = match chat_once(&self.cfg, &messages, &self.tools).await {
+            Ok(r) => r,
+            Err(e) => {
+                emit(tx, json!({"type": "error", "error": {"type": "server_error", "code": "llm_error", "message": e.to_string()}}));
+                emit(tx, json!({"type": "response.done", "response": {"id": self.id, "object": "realtime.response", "status": "failed", "output": [], "status_details": {"error": e.to_string()}}}));
+                return;
+            }
+        };
+        let output = match reply {
+            Reply::ToolCalls(calls, assistant_msg) => {
+                self.history.lock().push(assistant_msg);
+                let mut items = vec![];
+                for (i, (call_id, name, arguments)) in calls.into_iter().enumerate() {
+                    let item = json!({"id": new_id("item"), "object": "realtime.item", "type": "function_call", "status": "completed", "call_id": call_id, "name": name, "arguments": arguments});
+                    emit(tx, json!({"type": "response.output_item.added", "response_id": self.id, "output_index": i, "item": item}));
+                    emit(tx, json!({"type": "response.function_call_arguments.done", "response_id": self.id, "item_id": item["id"], "output_index": i, "call_id": call_id, "name": name, "arguments": arguments}));
+                    emit(tx, json!({"type": "response.output_item.done", "response_id": self.id, "output_index": i, "item": item}));
+                    items.push(item);
+                }
+                items
+            }
+            Reply::Text(text) => {
+                self.history.lock().push(json!({"role": "assistant", "content": text}));
+                vec![self.speak(&text).await]
+            }
+        };
+        emit(tx, json!({"type": "response.done", "response": {"id": self.id, "object": "realtime.response", "status": "completed", "output": output}}));
+    }
+
+    /// Stream the assistant message: transcript (or text) deltas, then TTS audio.
+    async fn speak(&self, text: &str) -> Value {
+        let tx = &self.tx;
+        let item_id = new_id("item");
+        let base = json!({"response_id": self.id, "item_id": item_id, "output_index": 0, "content_index": 0});
+        let ev = |typ: &str, extra: Value| {
+            let mut e = base.clone();
+            e["type"] = json!(typ);
+            if let (Some(o), Some(x)) = (e.as_object_mut(), extra.as_object()) { o.extend(x.clone()); }
+            e
+        };
+        emit(tx, json!({"type": "response.output_item.added", "response_id": self.id, "output_index": 0, "item": {"id": item_id, "object": "realtime.item", "type": "message", "role": "assistant", "status": "in_progress", "content": []}}));
+        let content = if self.audio {
+            emit(tx, ev("response.audio_transcript.delta", json!({"delta": text})));
+            if let Some(cmd) = self.cfg.tts_cmd.as_deref() {
+                use crate::tts::TtsEngine as _;
+                let tts = crate::tts::CommandTts { command: cmd.to_string(), sample_rate: self.cfg.tts_sr };
+                match tts.synthesize(text).await {
+                    Ok(speech) => {
+                        let bytes = encode_output(&speech.pcm, speech.sample_rate, &self.out_format);
+                        // ~100 ms per delta at the output rate
+                        let chunk = if self.out_format == "g711_ulaw" { 800 } else { 4800 };
+                        for part in bytes.chunks(chunk) {
+                            emit(tx, ev("response.audio.delta", json!({"delta": B64.encode(part)})));
+                        }
+                    }
+                    Err(e) => emit(tx, json!({"type": "error", "error": {"type": "server_error", "code": "tts_error", "message": e.to_string()}})),
+                }
+            }
+            emit(tx, ev("response.audio.done", json!({})));
+            emit(tx, ev("response.audio_transcript.done", json!({"transcript": text})));
+            json!([{"type": "audio", "transcript": text}])
+        } else {
+            emit(tx, ev("response.text.delta", json!({"delta": text})));
+            emit(tx, ev("response.text.done", json!({"text": text})));
+            json!([{"type": "text", "text": text}])
+        };
+        let item = json!({"id": item_id, "object": "realtime.item", "type": "message", "role": "assistant", "status": "completed", "content": content});
+        emit(tx, json!({"type": "response.output_item.done", "response_id": self.id, "output_index": 0, "item": item}));
+        item
+    }
+}
+
+/// Realtime tool schema (`{type, name, description, parameters}`) -> chat completions tool.
+fn chat_tool(t: &Value) -> Option<Value> {
+    let name = t.get("name").and_then(|n| n.as_str())?;
+    Some(json!({"type": "function", "function": {"name": name, "description": t.get("description").cloned().unwrap_or(json!("")), "parameters": t.get("parameters").cloned().unwrap_or_else(|| json!({"type": "object", "properties": {}}))}}))
+}
+
+/// Conversation item -> chat message (user/assistant/system text, function_call_output).
+fn chat_message(item: &Value) -> Option<Value> {
+    match item.get("type").and_then(|t| t.as_str()).unwrap_or("message") {
+        "function_call_output" => {
+            let call_id = item.get("call_id").and_then(|c| c.as_str())?;
+            let output = item.get("output").map(|o| o.as_str().map(String::from).unwrap_or_else(|| o.to_string())).unwrap_or_default();
+            Some(json!({"role": "tool", "tool_call_id": call_id, "content": output}))
+        }
+        "message" => {
+            let role = item.get("role").and_then(|r| r.as_str()).unwrap_or("user");
+            let text: Vec<&str> = item.get("content").and_then(|c| c.as_array()).map(|parts| parts.iter()
+                .filter_map(|p| p.get("text").or_else(|| p.get("transcript")).and_then(|t| t.as_str()))
+                .collect()).unwrap_or_default();
+            if text.is_empty() { return None; }
+            Some(json!({"role": role, "content": text.join("\n")}))
+        }
+        _ => None,
+    }
+}
+
+enum Reply {
+    Text(String),
+    /// (call_id, name, arguments JSON string) plus the assistant message for history.
+    ToolCalls(Vec<(String, String, String)>, Value),
+}
+
+async fn chat_once(cfg: &GatewayConfig, messages: &[Value], tools: &[Value]) -> anyhow::Result<Reply> {
+    let mut body = json!({"model": cfg.llm_model, "messages": messages, "stream": false});
+    if !tools.is_empty() {
+        body["tools"] = json!(tools);
+        body["tool_choice"] = json!("auto");
+    }
+    let mut req = reqwest::Client::new().post(&cfg.llm_url).json(&body);
+    if let Some(key) = cfg.llm_api_key.as_deref() { req = req.bearer_auth(key); }
+    let resp = req.send().await?;
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let txt = resp.text().await.unwrap_or_default();
+        anyhow::bail!("llm http {}: {}", status, txt.chars().take(400).collect::<String>());
+    }
+    let v: Value = resp.json().await?;
+    let msg = v.get("choices").and_then(|c| c.get(0)).and_then(|c| c.get("message")).cloned()
+        .ok_or_else(|| anyhow::anyhow!("llm response has no choices"))?;
+    if let Some(tc) = msg.get("tool_calls").and_then(|x| x.as_array()).filter(|a| !a.is_empty()) {
+        let calls = tc.iter().map(|c| {
+            let id = c.get("id").and_then(|s| s.as_str()).map(String::from).unwrap_or_else(|| new_id("call"));
+            let f = c.get("function");
+            let name = f.and_then(|f| f.get("name")).and_then(|s| s.as_str()).unwrap_or("").to_string();
+            // Some servers return arguments as an object rather than a JSON string
+            let args = f.and_then(|f| f.get("arguments")).map(|a| a.as_str().map(String::from).unwrap_or_else(|| a.to_string())).unwrap_or_else(|| "{}".into());
+            (id, name, args)
+        }).collect::<Vec<_>>();
+        // Echo the ids we hand out so tool outputs line up in the next request
+        let assistant = json!({"role": "assistant", "content": msg.get("content").cloned().unwrap_or(Value::Null), "tool_calls": calls.iter().map(|(id, name, args)| json!({"id": id, "type": "function", "function": {"name": name, "arguments": args}})).collect::<Vec<_>>()});
+        return Ok(Reply::ToolCalls(calls, assistant));
+    }
+    Ok(Reply::Text(msg.get("content").and_then(|s| s.as_str()).unwrap_or("").trim().to_string()))
+}
+
+/// Resample TTS output to the negotiated format: pcm16 @ 24 kHz or g711_ulaw @ 8 kHz.
+fn encode_output(pcm: &[i16], src_sr: u32, out_format: &str) -> Vec<u8> {
+    if out_format == "g711_ulaw" {
+        let pcm = crate::realtime_audio::resample_linear_i16(pcm, src_sr, 8_000);
+        crate::realtime_audio::encode_pcm_to_ulaw(&pcm)
+    } else {
+        crate::realtime_audio::resample_linear_i16(pcm, src_sr, 24_000).iter().flat_map(|s| s.to_le_bytes()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_items_and_tools() {
+        let t = chat_tool(&json!({"type": "function", "name": "shell_which", "description": "d", "parameters": {"type": "object"}})).unwrap();
+        assert_eq!(t["function"]["name"], "shell_which");
+        let m = chat_message(&json!({"type": "function_call_output", "call_id": "c1", "output": "{\"ok\":true}"})).unwrap();
+        assert_eq!(m, json!({"role": "tool", "tool_call_id": "c1", "content": "{\"ok\":true}"}));
+        let m = chat_message(&json!({"type": "message", "role": "user", "content": [{"type": "input_text", "text": "hi"}]})).unwrap();
+        assert_eq!(m["content"], "hi");
+        assert!(chat_message(&json!({"type": "message", "content": []})).is_none());
+    }
+
+    #[test]
+    fn ulaw_output_round_trips() {
+        let pcm: Vec<i16> = (0..800).map(|i| ((i as f32 * 0.05).sin() * 12_000.0) as i16).collect();
+        let bytes = encode_output(&pcm, 8_000, "g711_ulaw");
+        assert_eq!(bytes.len(), pcm.len());
+        // Reference G.711 expansion
+        let back: Vec<i16> = bytes.iter().map(|&b| {
+            let u = !b;
+            let t = ((((u & 0x0F) as i32) << 3) + 0x84) << ((u >> 4) & 0x07);
+            (if u & 0x80 != 0 { 0x84 - t } else { t - 0x84 }) as i16
+        }).collect();
+        let max_err = pcm.iter().zip(&back).map(|(a, b)| (*a as i32 - *b as i32).abs()).max().unwrap();
+        assert!(max_err < 600, "µ-law error {}", max_err);
+        assert_eq!(encode_output(&pcm, 24_000, "pcm16").len(), pcm.len() * 2);
+    }
+}
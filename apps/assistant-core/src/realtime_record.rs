@@ -0,0 +1,336 @@
+//! Opt-in recorder for realtime sessions. Each session becomes a bundle under
+//! `<home>/realtime_sessions/<id>/`:
+//!
+//! - `meta.json`      session parameters, start/end time and counters
+//! - `events.jsonl`   every inbound/outbound WebSocket frame with a relative timestamp
+//!   (outbound `input_audio_buffer.append` audio is elided, see `mic.pcm`;
+//!   inbound audio deltas too, see `playback.pcm`)
+//! - `binary.bin`     payloads of binary frames, referenced by offset/len
+//! - `mic.pcm`        raw mic capture, s16le mono at `meta.in_sr`
+//! - `playback.pcm`   decoded assistant audio, s16le mono at `meta.out_sr`
+//!
+//! Files are written by a writer thread and flushed when it goes idle and on
+//! `finish`. `SessionBundle::load` reads a bundle back for `RealtimeManager::replay`.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingMeta {
+    pub id: String,
+    pub started_at: DateTime<Utc>,
+    #[serde(default)]
+    pub ended_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    pub in_sr: u32,
+    pub out_sr: u32,
+    pub out_format: String,
+    #[serde(default)]
+    pub events: u64,
+    #[serde(default)]
+    pub mic_samples: u64,
+    #[serde(default)]
+    pub playback_samples: u64,
+}
+
+impl RecordingMeta {
+    pub fn new(model: Option<String>, endpoint: Option<String>, in_sr: u32, out_sr: u32, out_format: &str) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            started_at: Utc::now(),
+            ended_at: None,
+            model,
+            endpoint,
+            in_sr,
+            out_sr,
+            out_format: out_format.to_string(),
+            events: 0,
+            mic_samples: 0,
+            playback_samples: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    In,
+    Out,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum RecordedFrame {
+    /// Text frame that parsed as JSON (stored structured for readability).
+    Json { event: serde_json::Value },
+    /// Text frame that was not JSON.
+    Text { text: String },
+    Binary { offset: u64, len: u64 },
+    Close,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    pub t_ms: u64,
+    pub dir: Direction,
+    #[serde(flatten)]
+    pub frame: RecordedFrame,
+}
+
+/// Work for the writer thread. Frames are sent owned and timestamped by the
+/// caller; parsing and file I/O happen on the thread.
+enum Op {
+    Text { t_ms: u64, dir: Direction, text: String },
+    Binary { t_ms: u64, dir: Direction, bytes: Vec<u8> },
+    Close { t_ms: u64, dir: Direction },
+    Pcm { playback: bool, pcm: Vec<i16> },
+    Finish,
+}
+
+/// Owns the bundle files; runs on the recorder's thread.
+struct Writer {
+    dir: PathBuf,
+    meta: RecordingMeta,
+    events: BufWriter<File>,
+    binary: Option<BufWriter<File>>,
+    binary_len: u64,
+    mic: Option<BufWriter<File>>,
+    playback: Option<BufWriter<File>>,
+}
+
+impl Writer {
+    fn run(mut self, rx: mpsc::Receiver<Op>) {
+        loop {
+            match rx.recv_timeout(IDLE_FLUSH) {
+                Ok(Op::Text { t_ms, dir, text }) => {
+                    let frame = match serde_json::from_str::<serde_json::Value>(&text) {
+                        Ok(event) => RecordedFrame::Json { event },
+                        Err(_) => RecordedFrame::Text { text },
+                    };
+                    self.write_event(t_ms, dir, frame);
+                }
+                Ok(Op::Binary { t_ms, dir, bytes }) => self.binary(t_ms, dir, &bytes),
+                Ok(Op::Close { t_ms, dir }) => self.write_event(t_ms, dir, RecordedFrame::Close),
+                Ok(Op::Pcm { playback, pcm }) => self.pcm(playback, &pcm),
+                // A quiet moment: let a live reader see everything so far
+                Err(mpsc::RecvTimeoutError::Timeout) => self.flush(),
+                Ok(Op::Finish) | Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        self.meta.ended_at = Some(Utc::now());
+        self.flush();
+        if let Ok(bytes) = serde_json::to_vec_pretty(&self.meta) {
+            let _ = std::fs::write(self.dir.join("meta.json"), bytes);
+        }
+    }
+
+    fn write_event(&mut self, t_ms: u64, dir: Direction, frame: RecordedFrame) {
+        let ev = RecordedEvent { t_ms, dir, frame };
+        if let Ok(line) = serde_json::to_string(&ev) {
+            let _ = writeln!(self.events, "{}", line);
+            self.meta.events += 1;
+        }
+    }
+
+    fn binary(&mut self, t_ms: u64, dir: Direction, bytes: &[u8]) {
+        if self.binary.is_none() {
+            self.binary = File::create(self.dir.join("binary.bin")).ok().map(BufWriter::new);
+        }
+        let offset = self.binary_len;
+        if let Some(w) = self.binary.as_mut() {
+            if w.write_all(bytes).is_err() { return; }
+        }
+        self.binary_len += bytes.len() as u64;
+        self.write_event(t_ms, dir, RecordedFrame::Binary { offset, len: bytes.len() as u64 });
+    }
+
+    fn pcm(&mut self, playback: bool, pcm: &[i16]) {
+        let (slot, name) = if playback { (&mut self.playback, "playback.pcm") } else { (&mut self.mic, "mic.pcm") };
+        if slot.is_none() {
+            *slot = File::create(self.dir.join(name)).ok().map(BufWriter::new);
+        }
+        if let Some(w) = slot.as_mut() {
+            let bytes: Vec<u8> = pcm.iter().flat_map(|s| s.to_le_bytes()).collect();
+            let _ = w.write_all(&bytes);
+        }
+        if playback { self.meta.playback_samples += pcm.len() as u64; } else { self.meta.mic_samples += pcm.len() as u64; }
+    }
+
+    fn flush(&mut self) {
+        let _ = self.events.flush();
+        for w in [self.binary.as_mut(), self.mic.as_mut(), self.playback.as_mut()].into_iter().flatten() {
+            let _ = w.flush();
+        }
+    }
+}
+
+/// How long the writer waits for a frame before flushing what it has.
+const IDLE_FLUSH: Duration = Duration::from_millis(500);
+
+struct Shared {
+    id: String,
+    dir: PathBuf,
+    started: Instant,
+    tx: Mutex<Option<mpsc::Sender<Op>>>,
+    writer: Mutex<Option<std::thread::JoinHandle<()>>>,
+}
+
+/// Records a session from the realtime event loop without blocking it: every
+/// call queues the frame for a writer thread that owns the files.
+#[derive(Clone)]
+pub struct SessionRecorder {
+    shared: Arc<Shared>,
+}
+
+impl SessionRecorder {
+    /// Create `<root>/<meta.id>/` and start recording into it.
+    pub fn create(root: &Path, meta: RecordingMeta) -> Result<Self> {
+        let dir = root.join(&meta.id);
+        std::fs::create_dir_all(&dir).with_context(|| format!("create {}", dir.display()))?;
+        std::fs::write(dir.join("meta.json"), serde_json::to_vec_pretty(&meta)?)?;
+        let events = BufWriter::new(File::create(dir.join("events.jsonl"))?);
+        let id = meta.id.clone();
+        let writer = Writer { dir: dir.clone(), meta, events, binary: None, binary_len: 0, mic: None, playback: None };
+        let (tx, rx) = mpsc::channel();
+        let handle = std::thread::Builder::new().name("rt-recorder".into()).spawn(move || writer.run(rx))?;
+        let shared = Shared { id, dir, started: Instant::now(), tx: Mutex::new(Some(tx)), writer: Mutex::new(Some(handle)) };
+        Ok(Self { shared: Arc::new(shared) })
+    }
+
+    pub fn id(&self) -> String { self.shared.id.clone() }
+
+    pub fn dir(&self) -> PathBuf { self.shared.dir.clone() }
+
+    fn t_ms(&self) -> u64 { self.shared.started.elapsed().as_millis() as u64 }
+
+    fn send(&self, op: Op) {
+        if let Some(tx) = self.shared.tx.lock().as_ref() { let _ = tx.send(op); }
+    }
+
+    pub fn text(&self, dir: Direction, text: &str) {
+        self.send(Op::Text { t_ms: self.t_ms(), dir, text: text.to_string() });
+    }
+
+    pub fn binary(&self, dir: Direction, bytes: &[u8]) {
+        self.send(Op::Binary { t_ms: self.t_ms(), dir, bytes: bytes.to_vec() });
+    }
+
+    pub fn close(&self, dir: Direction) {
+        self.send(Op::Close { t_ms: self.t_ms(), dir });
+    }
+
+    pub fn mic(&self, pcm: &[i16]) {
+        if !pcm.is_empty() { self.send(Op::Pcm { playback: false, pcm: pcm.to_vec() }); }
+    }
+
+    pub fn playback(&self, pcm: &[i16]) {
+        if !pcm.is_empty() { self.send(Op::Pcm { playback: true, pcm: pcm.to_vec() }); }
+    }
+
+    /// Write out what is queued, flush all files and stamp `ended_at`; later
+    /// writes are ignored. Returns once the bundle is complete on disk.
+    pub fn finish(&self) {
+        let Some(tx) = self.shared.tx.lock().take() else { return };
+        let _ = tx.send(Op::Finish);
+        drop(tx);
+        if let Some(handle) = self.shared.writer.lock().take() { let _ = handle.join(); }
+    }
+}
+
+/// A recorded session loaded from disk.
+#[derive(Debug, Clone)]
+pub struct SessionBundle {
+    pub dir: PathBuf,
+    pub meta: RecordingMeta,
+    pub events: Vec<RecordedEvent>,
+    binary: Vec<u8>,
+}
+
+impl SessionBundle {
+    pub fn load(dir: &Path) -> Result<Self> {
+        let meta: RecordingMeta = serde_json::from_slice(&std::fs::read(dir.join("meta.json")).with_context(|| format!("read {}/meta.json", dir.display()))?)?;
+        let text = std::fs::read_to_string(dir.join("events.jsonl")).unwrap_or_default();
+        let mut events = vec![];
+        for (i, line) in text.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+            events.push(serde_json::from_str(line).with_context(|| format!("events.jsonl line {}", i + 1))?);
+        }
+        let binary = std::fs::read(dir.join("binary.bin")).unwrap_or_default();
+        Ok(Self { dir: dir.to_path_buf(), meta, events, binary })
+    }
+
+    /// Payload of a `Binary` frame.
+    pub fn binary(&self, offset: u64, len: u64) -> &[u8] {
+        let start = (offset as usize).min(self.binary.len());
+        let end = (start + len as usize).min(self.binary.len());
+        &self.binary[start..end]
+    }
+
+    pub fn inbound(&self) -> impl Iterator<Item = &RecordedEvent> {
+        self.events.iter().filter(|e| e.dir == Direction::In)
+    }
+
+    /// JSON events sent by the client, in order.
+    pub fn outbound_json(&self) -> Vec<&serde_json::Value> {
+        self.events.iter().filter(|e| e.dir == Direction::Out).filter_map(|e| match &e.frame { RecordedFrame::Json { event } => Some(event), _ => None }).collect()
+    }
+
+    pub fn mic_pcm(&self) -> Vec<i16> { read_pcm(&self.dir.join("mic.pcm")) }
+
+    pub fn playback_pcm(&self) -> Vec<i16> { read_pcm(&self.dir.join("playback.pcm")) }
+}
+
+fn read_pcm(path: &Path) -> Vec<i16> {
+    std::fs::read(path).map(|b| b.chunks_exact(2).map(|c| i16::from_le_bytes([c[0], c[1]])).collect()).unwrap_or_default()
+}
+
+/// Recorded sessions under `root`, newest first.
+pub fn list_sessions(root: &Path) -> Vec<RecordingMeta> {
+    let mut out: Vec<RecordingMeta> = std::fs::read_dir(root).into_iter().flatten().flatten()
+        .filter_map(|e| std::fs::read(e.path().join("meta.json")).ok())
+        .filter_map(|b| serde_json::from_slice(&b).ok())
+        .collect();
+    out.sort_by_key(|m| std::cmp::Reverse(m.started_at));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundle_round_trips() {
+        let root = std::env::temp_dir().join(format!("rt_rec_{}", uuid::Uuid::new_v4()));
+        let rec = SessionRecorder::create(&root, RecordingMeta::new(Some("m".into()), None, 16_000, 24_000, "pcm16")).unwrap();
+        rec.text(Direction::Out, r#"{"type":"session.update"}"#);
+        rec.text(Direction::In, "not json");
+        rec.binary(Direction::In, &[1, 2, 3]);
+        rec.binary(Direction::In, &[4, 5]);
+        rec.mic(&[1, -1, 2]);
+        rec.playback(&[7; 4]);
+        rec.close(Direction::In);
+        rec.finish();
+        rec.text(Direction::In, "{}"); // ignored after finish
+
+        let b = SessionBundle::load(&rec.dir()).unwrap();
+        assert_eq!(b.events.len(), 5);
+        assert_eq!(b.meta.events, 5);
+        assert!(b.meta.ended_at.is_some());
+        assert_eq!(b.outbound_json()[0]["type"], "session.update");
+        assert_eq!(b.events[1].frame, RecordedFrame::Text { text: "not json".into() });
+        match b.events[3].frame { RecordedFrame::Binary { offset, len } => assert_eq!(b.binary(offset, len), &[4, 5]), ref f => panic!("{:?}", f) }
+        assert_eq!(b.mic_pcm(), vec![1, -1, 2]);
+        assert_eq!(b.playback_pcm().len(), 4);
+        assert_eq!(list_sessions(&root)[0].id, b.meta.id);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,254 @@
+//! Structured history of realtime calls.
+//!
+//! Each call is a Task (tagged `realtime`) with a `RealtimeSession` row for the
+//! call metadata. Turns, tool calls and errors are Events on that task, so the
+//! same records feed `/api/realtime/sessions`, the memory pack and the seed
+//! context of the next call.
+
+use chrono::{DateTime, Utc};
+use foreman_memory::{MemoryStore, RealtimeSessionRow};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const TURN_EVENT: &str = "realtime.turn";
+pub const TOOL_EVENT: &str = "realtime.tool";
+pub const ERROR_EVENT: &str = "realtime.error";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Turn {
+    /// `user` or `assistant`.
+    pub role: String,
+    pub text: String,
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCall {
+    #[serde(default)]
+    pub call_id: Option<String>,
+    pub name: String,
+    pub arguments: Value,
+    pub result: Value,
+    pub ok: bool,
+    /// Id of the approval prompt shown before the call ran, if policy asked for one.
+    #[serde(default)]
+    pub approval_id: Option<String>,
+    /// `approve`, `deny` or `timeout`.
+    #[serde(default)]
+    pub approval: Option<String>,
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallError {
+    pub message: String,
+    pub at: DateTime<Utc>,
+}
+
+/// Session row with event counts, for listings.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionSummary {
+    #[serde(flatten)]
+    pub session: RealtimeSessionRow,
+    pub turns: usize,
+    pub tool_calls: usize,
+    pub errors: usize,
+}
+
+/// Session row with every turn, tool call and error in order.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionDetail {
+    #[serde(flatten)]
+    pub session: RealtimeSessionRow,
+    pub turns: Vec<Turn>,
+    pub tool_calls: Vec<ToolCall>,
+    pub errors: Vec<CallError>,
+}
+
+/// Writer for one live call. Failed writes are logged and dropped: losing a
+/// history line must not break the call.
+#[derive(Clone)]
+pub struct SessionJournal {
+    store: MemoryStore,
+    id: String,
+    task_id: i64,
+}
+
+impl SessionJournal {
+    pub async fn open(store: &MemoryStore, model: Option<&str>, endpoint: Option<&str>, transport: Option<&str>) -> anyhow::Result<Self> {
+        let title = format!("Voice call {}", Utc::now().format("%Y-%m-%d %H:%M UTC"));
+        let task = store.create_task(&title, "active", Some("realtime")).await?;
+        let id = uuid::Uuid::new_v4().to_string();
+        store.create_realtime_session(&id, task.id, model, endpoint, transport).await?;
+        Ok(Self { store: store.clone(), id, task_id: task.id })
+    }
+
+    pub fn id(&self) -> &str { &self.id }
+
+    pub fn task_id(&self) -> i64 { self.task_id }
+
+    pub async fn turn(&self, role: &str, text: &str, at: DateTime<Utc>) {
+        let text = text.trim();
+        if text.is_empty() { return; }
+        self.event(TURN_EVENT, &Turn { role: role.into(), text: text.into(), at }).await;
+    }
+
+    pub async fn tool(&self, call: &ToolCall) {
+        self.event(TOOL_EVENT, call).await;
+    }
+
+    pub async fn error(&self, message: &str) {
+        self.event(ERROR_EVENT, &CallError { message: message.into(), at: Utc::now() }).await;
+    }
+
+    pub async fn set_recording(&self, recording: &str) {
+        if let Err(e) = self.store.set_realtime_session_recording(&self.id, recording).await {
+            tracing::warn!(error=%e, session=%self.id, "realtime session: cannot link recording");
+        }
+    }
+
+    /// Close the call: `ended`, or `failed` with the error that ended it.
+    pub async fn finish(&self, error: Option<&str>) {
+        let status = if error.is_some() { "failed" } else { "ended" };
+        if let Some(msg) = error { self.error(msg).await; }
+        if let Err(e) = self.store.finish_realtime_session(&self.id, status, error).await {
+            tracing::warn!(error=%e, session=%self.id, "realtime session: cannot finish");
+        }
+        let _ = self.store.update_task_status(self.task_id, "done").await;
+    }
+
+    async fn event<T: Serialize>(&self, kind: &str, payload: &T) {
+        let payload = serde_json::to_value(payload).unwrap_or(Value::Null);
+        if let Err(e) = self.store.append_event(Some(self.task_id), kind, Some(&payload)).await {
+            tracing::warn!(error=%e, session=%self.id, kind, "realtime session: cannot record event");
+        }
+    }
+}
+
+pub async fn list(store: &MemoryStore, limit: i64) -> anyhow::Result<Vec<SessionSummary>> {
+    let mut out = vec![];
+    for session in store.list_realtime_sessions(limit).await? {
+        let d = detail(store, session).await?;
+        out.push(SessionSummary { turns: d.turns.len(), tool_calls: d.tool_calls.len(), errors: d.errors.len(), session: d.session });
+    }
+    Ok(out)
+}
+
+pub async fn get(store: &MemoryStore, id: &str) -> anyhow::Result<Option<SessionDetail>> {
+    match store.get_realtime_session(id).await? {
+        Some(session) => Ok(Some(detail(store, session).await?)),
+        None => Ok(None),
+    }
+}
+
+async fn detail(store: &MemoryStore, session: RealtimeSessionRow) -> anyhow::Result<SessionDetail> {
+    let mut d = SessionDetail { session, turns: vec![], tool_calls: vec![], errors: vec![] };
+    for ev in store.get_events_by_task(d.session.task_id).await? {
+        let Some(payload) = ev.payload_json.as_deref() else { continue };
+        match ev.kind.as_str() {
+            TURN_EVENT => d.turns.extend(serde_json::from_str(payload).ok()),
+            TOOL_EVENT => d.tool_calls.extend(serde_json::from_str(payload).ok()),
+            ERROR_EVENT => d.errors.extend(serde_json::from_str(payload).ok()),
+            _ => {}
+        }
+    }
+    // Turns transcribed in the background can land after later events
+    d.turns.sort_by_key(|t| t.at);
+    Ok(d)
+}
+
+/// Turns kept per call in the seed context.
+const TURNS_PER_CALL: usize = 8;
+
+/// Compact transcript of the last `calls` realtime calls, oldest first: one
+/// header per call, then its last turns and tool calls in time order.
+pub async fn recent_context(store: &MemoryStore, calls: i64) -> Option<String> {
+    let sessions = store.list_realtime_sessions(calls).await.ok()?;
+    let mut blocks: Vec<String> = vec![];
+    for session in sessions.into_iter().rev() {
+        let Ok(d) = detail(store, session).await else { continue };
+        if d.turns.is_empty() && d.tool_calls.is_empty() { continue; }
+        blocks.push(call_block(&d));
+    }
+    if blocks.is_empty() { None } else { Some(blocks.join("\n\n")) }
+}
+
+fn call_block(d: &SessionDetail) -> String {
+    let s = &d.session;
+    let mut header = format!("Call {}", s.started_at.format("%Y-%m-%d %H:%M UTC"));
+    if let Some(end) = s.ended_at {
+        header.push_str(&format!(", {} min", (end - s.started_at).num_minutes().max(1)));
+    }
+    if s.status == "failed" { header.push_str(", ended with an error"); }
+    header.push(':');
+
+    let mut lines: Vec<(DateTime<Utc>, String)> = vec![];
+    let skip = d.turns.len().saturating_sub(TURNS_PER_CALL);
+    let since = d.turns.get(skip).map(|t| t.at);
+    for t in d.turns.iter().skip(skip) {
+        lines.push((t.at, format!("{}: {}", t.role, clip(&t.text, 200))));
+    }
+    for c in d.tool_calls.iter().filter(|c| since.map(|s| c.at >= s).unwrap_or(true)) {
+        let outcome = match c.approval.as_deref() {
+            Some("deny") => "denied by user".to_string(),
+            Some("timeout") => "approval timed out".to_string(),
+            _ if c.ok => "ok".to_string(),
+            _ => format!("error: {}", clip(c.result.get("error").and_then(|e| e.as_str()).unwrap_or("(error)"), 120)),
+        };
+        lines.push((c.at, format!("tool {} {} ({})", c.name, clip(&c.arguments.to_string(), 120), outcome)));
+    }
+    lines.sort_by_key(|(at, _)| *at);
+    std::iter::once(header).chain(lines.into_iter().map(|(_, l)| l)).collect::<Vec<_>>().join("\n")
+}
+
+fn clip(s: &str, n: usize) -> String {
+    if s.chars().count() <= n { return s.to_string(); }
+    let mut out: String = s.chars().take(n).collect();
+    out.push('…');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detail_with(turns: Vec<(&str, &str, i64)>, tools: Vec<(&str, bool, Option<&str>, i64)>) -> SessionDetail {
+        let t0 = DateTime::parse_from_rfc3339("2026-03-01T09:00:00Z").unwrap().with_timezone(&Utc);
+        SessionDetail {
+            session: RealtimeSessionRow {
+                id: "s1".into(), task_id: 1, model: Some("gpt-realtime".into()), endpoint: None, transport: None,
+                status: "ended".into(), error: None, recording: None, started_at: t0, ended_at: Some(t0 + chrono::Duration::seconds(150)),
+            },
+            turns: turns.into_iter().map(|(role, text, s)| Turn { role: role.into(), text: text.into(), at: t0 + chrono::Duration::seconds(s) }).collect(),
+            tool_calls: tools.into_iter().map(|(name, ok, approval, s)| ToolCall {
+                call_id: None, name: name.into(), arguments: serde_json::json!({"path": "notes.md"}),
+                result: if ok { serde_json::json!({"ok": true}) } else { serde_json::json!({"error": "denied by user"}) },
+                ok, approval_id: approval.map(|_| "a1".into()), approval: approval.map(str::to_string), at: t0 + chrono::Duration::seconds(s),
+            }).collect(),
+            errors: vec![],
+        }
+    }
+
+    #[test]
+    fn call_block_interleaves_turns_and_tools_in_time_order() {
+        let d = detail_with(
+            vec![("user", "read my notes", 5), ("assistant", "You have two notes.", 20), ("user", "delete them", 30)],
+            vec![("fs.read", true, None, 10), ("fs.delete", false, Some("deny"), 35)],
+        );
+        assert_eq!(call_block(&d), "Call 2026-03-01 09:00 UTC, 2 min:\n\
+            user: read my notes\n\
+            tool fs.read {\"path\":\"notes.md\"} (ok)\n\
+            assistant: You have two notes.\n\
+            user: delete them\n\
+            tool fs.delete {\"path\":\"notes.md\"} (denied by user)");
+    }
+
+    #[test]
+    fn call_block_keeps_only_the_last_turns() {
+        let turns: Vec<(&str, &str, i64)> = (0..12).map(|i| ("user", if i < 4 { "old" } else { "new" }, i)).collect();
+        let d = detail_with(turns, vec![("fs.read", true, None, 1)]);
+        let block = call_block(&d);
+        assert_eq!(block.lines().count(), 1 + TURNS_PER_CALL);
+        assert!(!block.contains("old") && !block.contains("fs.read"));
+    }
+}
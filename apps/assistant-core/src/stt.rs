@@ -1,27 +1,9 @@
 use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
 
-fn encode_wav_pcm16(pcm: &[i16], sr: u32) -> Vec<u8> {
-    let mut out = Vec::with_capacity(44 + pcm.len() * 2);
-    let byte_rate = sr * 2; // mono, 16-bit
-    let block_align = 2u16;
-    let subchunk2_size = (pcm.len() * 2) as u32;
-    let chunk_size = 36 + subchunk2_size;
-    out.extend_from_slice(b"RIFF");
-    out.extend_from_slice(&chunk_size.to_le_bytes());
-    out.extend_from_slice(b"WAVE");
-    out.extend_from_slice(b"fmt ");
-    out.extend_from_slice(&16u32.to_le_bytes()); // PCM subchunk size
-    out.extend_from_slice(&1u16.to_le_bytes()); // PCM format
-    out.extend_from_slice(&1u16.to_le_bytes()); // channels
-    out.extend_from_slice(&sr.to_le_bytes());
-    out.extend_from_slice(&byte_rate.to_le_bytes());
-    out.extend_from_slice(&block_align.to_le_bytes());
-    out.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
-    out.extend_from_slice(b"data");
-    out.extend_from_slice(&subchunk2_size.to_le_bytes());
-    for s in pcm { out.extend_from_slice(&s.to_le_bytes()); }
-    out
-}
+pub use foreman_types::audio::encode_wav_pcm16;
 
 pub async fn transcribe_openai_pcm16(pcm: &[i16], sr: u32) -> Result<String> {
     let key = std::env::var("OPENAI_API_KEY").map_err(|_| anyhow::anyhow!("OPENAI_API_KEY not set"))?;
@@ -66,3 +48,197 @@ pub async fn transcribe_local_pcm16(pcm: &[i16], sr: u32, endpoint: Option<&str>
     let text = v.get("text").and_then(|x| x.as_str()).unwrap_or("").to_string();
     Ok(text)
 }
+
+// ---- Streaming ----
+
+/// A hypothesis from a streaming recognizer. Also the wire format of the local
+/// daemon's `/v1/stt/stream` and of core's `/api/stt/stream`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SttEvent {
+    /// Best guess so far for the whole utterance; replaces the previous partial.
+    Partial { text: String },
+    Final { text: String },
+    Error { message: String },
+}
+
+/// One utterance in flight: push PCM16 mono frames, read hypotheses as they arrive.
+pub struct SttStream {
+    audio: Option<mpsc::UnboundedSender<Vec<i16>>>,
+    events: mpsc::UnboundedReceiver<SttEvent>,
+}
+
+impl SttStream {
+    /// Channel pair for engine implementations: the engine reads audio (closed at
+    /// end of utterance) and sends events.
+    pub fn channel() -> (Self, mpsc::UnboundedReceiver<Vec<i16>>, mpsc::UnboundedSender<SttEvent>) {
+        let (audio_tx, audio_rx) = mpsc::unbounded_channel();
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        (Self { audio: Some(audio_tx), events: event_rx }, audio_rx, event_tx)
+    }
+
+    /// False once the stream has ended or the engine went away.
+    pub fn push(&self, pcm: &[i16]) -> bool {
+        self.audio.as_ref().map(|tx| tx.send(pcm.to_vec()).is_ok()).unwrap_or(false)
+    }
+
+    /// Signal end of audio; the engine answers with a final hypothesis.
+    pub fn end(&mut self) { self.audio = None; }
+
+    pub fn try_event(&mut self) -> Option<SttEvent> { self.events.try_recv().ok() }
+
+    pub async fn next_event(&mut self) -> Option<SttEvent> { self.events.recv().await }
+
+    /// End the utterance and wait for the final transcript, skipping partials.
+    pub async fn finish(mut self) -> Result<String> {
+        self.end();
+        while let Some(ev) = self.events.recv().await {
+            match ev {
+                SttEvent::Final { text } => return Ok(text),
+                SttEvent::Error { message } => anyhow::bail!(message),
+                SttEvent::Partial { .. } => {}
+            }
+        }
+        anyhow::bail!("stt stream closed without a final transcript")
+    }
+
+    /// Separate halves for callers that feed audio and read events from different tasks.
+    pub fn split(self) -> (Option<mpsc::UnboundedSender<Vec<i16>>>, mpsc::UnboundedReceiver<SttEvent>) {
+        (self.audio, self.events)
+    }
+}
+
+#[async_trait]
+pub trait SttEngine: Send + Sync {
+    fn name(&self) -> &'static str;
+    /// Start an utterance. Fails fast when the backend is unreachable so callers can fall back.
+    async fn open(&self, sample_rate: u32) -> Result<SttStream>;
+}
+
+/// Where to find recognizers, from `[voice] stt` ([`crate::config::SttConfig`]) with env fallbacks.
+#[derive(Debug, Clone, Default)]
+pub struct SttOptions {
+    /// Local daemon streaming WS (`stream_endpoint`, `LOCAL_STT_STREAM_ENDPOINT`).
+    pub stream_endpoint: Option<String>,
+    /// Local daemon whole-utterance HTTP (`endpoint`, `LOCAL_STT_ENDPOINT`).
+    pub endpoint: Option<String>,
+    /// Disable the streaming client (`streaming = false`).
+    pub batch_only: bool,
+}
+
+impl SttOptions {
+    pub fn from_config(cfg: Option<&crate::config::SttConfig>) -> Self {
+        let Some(c) = cfg else { return Self::default() };
+        Self { stream_endpoint: c.stream_endpoint.clone(), endpoint: c.endpoint.clone(), batch_only: c.streaming == Some(false) }
+    }
+
+    pub fn stream_url(&self) -> String {
+        self.stream_endpoint.clone()
+            .unwrap_or_else(|| std::env::var("LOCAL_STT_STREAM_ENDPOINT").unwrap_or_else(|_| "ws://127.0.0.1:7071/v1/stt/stream".into()))
+    }
+}
+
+/// Open a stream on the best available engine: the local streaming daemon, else
+/// whole-utterance transcription (local HTTP, then OpenAI).
+pub async fn open_stream(opts: &SttOptions, sample_rate: u32) -> Result<SttStream> {
+    #[cfg(feature = "realtime")]
+    if !opts.batch_only {
+        match LocalStreamingStt::new(opts.stream_url()).open(sample_rate).await {
+            Ok(s) => return Ok(s),
+            Err(e) => tracing::debug!("stt: streaming unavailable ({}), using batch", e),
+        }
+    }
+    BatchStt { endpoint: opts.endpoint.clone() }.open(sample_rate).await
+}
+
+/// Buffers the utterance and transcribes it on end: no partials.
+pub struct BatchStt {
+    pub endpoint: Option<String>,
+}
+
+#[async_trait]
+impl SttEngine for BatchStt {
+    fn name(&self) -> &'static str { "batch" }
+
+    async fn open(&self, sample_rate: u32) -> Result<SttStream> {
+        let (stream, mut audio, events) = SttStream::channel();
+        let endpoint = self.endpoint.clone();
+        tokio::spawn(async move {
+            let mut pcm = Vec::new();
+            while let Some(f) = audio.recv().await { pcm.extend_from_slice(&f); }
+            let res = match transcribe_local_pcm16(&pcm, sample_rate, endpoint.as_deref()).await {
+                Ok(t) => Ok(t),
+                Err(e) if std::env::var("OPENAI_API_KEY").is_ok() => {
+                    tracing::debug!("stt: local failed ({}), trying openai", e);
+                    transcribe_openai_pcm16(&pcm, sample_rate).await
+                }
+                Err(e) => Err(e),
+            };
+            let _ = events.send(match res { Ok(text) => SttEvent::Final { text }, Err(e) => SttEvent::Error { message: e.to_string() } });
+        });
+        Ok(stream)
+    }
+}
+
+/// WebSocket client for the local daemon's `/v1/stt/stream`: binary PCM16LE frames
+/// up, then `{"type":"end"}`; `SttEvent` JSON back.
+#[cfg(feature = "realtime")]
+pub struct LocalStreamingStt {
+    pub url: String,
+}
+
+#[cfg(feature = "realtime")]
+impl LocalStreamingStt {
+    pub fn new(url: impl Into<String>) -> Self { Self { url: url.into() } }
+}
+
+#[cfg(feature = "realtime")]
+#[async_trait]
+impl SttEngine for LocalStreamingStt {
+    fn name(&self) -> &'static str { "local-stream" }
+
+    async fn open(&self, sample_rate: u32) -> Result<SttStream> {
+        use futures_util::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message;
+        let sep = if self.url.contains('?') { '&' } else { '?' };
+        let url = format!("{}{}sample_rate={}", self.url, sep, sample_rate);
+        let (ws, _) = tokio::time::timeout(std::time::Duration::from_secs(2), tokio_tungstenite::connect_async(url.as_str()))
+            .await
+            .map_err(|_| anyhow::anyhow!("connect {}: timeout", url))??;
+        let (mut sink, mut source) = ws.split();
+        let (stream, mut audio, events) = SttStream::channel();
+        tokio::spawn(async move {
+            let mut sending = true;
+            loop {
+                tokio::select! {
+                    frame = audio.recv(), if sending => match frame {
+                        Some(pcm) => {
+                            let mut bytes = Vec::with_capacity(pcm.len() * 2);
+                            for s in &pcm { bytes.extend_from_slice(&s.to_le_bytes()); }
+                            if sink.send(Message::Binary(bytes)).await.is_err() { break; }
+                        }
+                        None => {
+                            sending = false;
+                            let _ = sink.send(Message::Text(serde_json::json!({"type": "end"}).to_string())).await;
+                        }
+                    },
+                    msg = source.next() => match msg {
+                        Some(Ok(Message::Text(t))) => {
+                            let Ok(ev) = serde_json::from_str::<SttEvent>(&t) else { continue };
+                            let done = !matches!(ev, SttEvent::Partial { .. });
+                            let _ = events.send(ev);
+                            if done { break; }
+                        }
+                        Some(Ok(Message::Close(_))) | None | Some(Err(_)) => {
+                            let _ = events.send(SttEvent::Error { message: "stt stream closed".into() });
+                            break;
+                        }
+                        Some(Ok(_)) => {}
+                    },
+                }
+            }
+            let _ = sink.close().await;
+        });
+        Ok(stream)
+    }
+}
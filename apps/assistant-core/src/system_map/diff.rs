@@ -0,0 +1,321 @@
+//! Field-level System Map diffs and their on-disk history (`<home>/map_history/`).
+
+use super::model::*;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Change {
+    /// Dotted path, e.g. `runtimes.rustc`, `apps.steam`, `packages.dpkg.ripgrep`.
+    pub path: String,
+    pub kind: ChangeKind,
+    pub old: Option<JsonValue>,
+    pub new: Option<JsonValue>,
+    /// Short human phrase, e.g. "rustc upgraded to 1.82.0".
+    pub summary: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MapDiff {
+    pub at: DateTime<Utc>,
+    pub from_scan: DateTime<Utc>,
+    pub to_scan: DateTime<Utc>,
+    pub changes: Vec<Change>,
+}
+
+/// First token that looks like a version ("Python 3.12.1" -> "3.12.1", "v20.1.0" -> "v20.1.0").
+fn version_token(s: &str) -> &str {
+    s.split_whitespace()
+        .find(|t| {
+            let t = t.trim_start_matches('v');
+            t.starts_with(|c: char| c.is_ascii_digit()) && t.contains(|c: char| c == '.' || c.is_ascii_digit())
+        })
+        .unwrap_or(s)
+}
+
+fn version_parts(v: &str) -> Vec<u64> {
+    v.trim_start_matches('v')
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|p| !p.is_empty())
+        .filter_map(|p| p.parse().ok())
+        .collect()
+}
+
+/// "upgraded to X" / "downgraded to X" / "changed to X".
+fn version_phrase(old: &str, new: &str) -> String {
+    let (o, n) = (version_token(old), version_token(new));
+    let (po, pn) = (version_parts(o), version_parts(n));
+    let verb = if po.is_empty() || pn.is_empty() || po == pn { "changed" } else if pn > po { "upgraded" } else { "downgraded" };
+    format!("{} to {}", verb, n)
+}
+
+fn scalar(out: &mut Vec<Change>, path: &str, label: &str, old: &Option<String>, new: &Option<String>, versioned: bool) {
+    if old == new { return; }
+    let (kind, summary) = match (old, new) {
+        (None, Some(n)) => (ChangeKind::Added, format!("{} {} detected", label, if versioned { version_token(n) } else { n })),
+        (Some(_), None) => (ChangeKind::Removed, format!("{} no longer detected", label)),
+        (Some(o), Some(n)) if versioned => (ChangeKind::Changed, format!("{} {}", label, version_phrase(o, n))),
+        (Some(_), Some(n)) => (ChangeKind::Changed, format!("{} changed to {}", label, n)),
+        (None, None) => return,
+    };
+    out.push(Change { path: path.into(), kind, old: old.as_ref().map(|v| json!(v)), new: new.as_ref().map(|v| json!(v)), summary });
+}
+
+fn set(out: &mut Vec<Change>, path: &str, label: &str, old: &[String], new: &[String]) {
+    for n in new.iter().filter(|n| !old.contains(n)) {
+        out.push(Change { path: format!("{}.{}", path, n), kind: ChangeKind::Added, old: None, new: Some(json!(n)), summary: format!("{} {} added", label, n) });
+    }
+    for o in old.iter().filter(|o| !new.contains(o)) {
+        out.push(Change { path: format!("{}.{}", path, o), kind: ChangeKind::Removed, old: Some(json!(o)), new: None, summary: format!("{} {} removed", label, o) });
+    }
+}
+
+/// Keyed collections: `key -> (display name, comparable value)`.
+fn keyed(out: &mut Vec<Change>, path: &str, label: &str, old: BTreeMap<String, (String, JsonValue)>, new: BTreeMap<String, (String, JsonValue)>, versioned: bool) {
+    for (k, (name, nv)) in new.iter() {
+        match old.get(k) {
+            None => {
+                let ver = nv.as_str().map(|v| format!(" ({})", v)).unwrap_or_default();
+                out.push(Change { path: format!("{}.{}", path, k), kind: ChangeKind::Added, old: None, new: Some(nv.clone()), summary: format!("{} {} added{}", label, name, ver) });
+            }
+            Some((_, ov)) if ov != nv => {
+                let summary = match (versioned, ov.as_str(), nv.as_str()) {
+                    (true, Some(o), Some(n)) => format!("{} {} {}", label, name, version_phrase(o, n)),
+                    _ => format!("{} {} changed", label, name),
+                };
+                out.push(Change { path: format!("{}.{}", path, k), kind: ChangeKind::Changed, old: Some(ov.clone()), new: Some(nv.clone()), summary });
+            }
+            _ => {}
+        }
+    }
+    for (k, (name, ov)) in old.iter().filter(|(k, _)| !new.contains_key(*k)) {
+        out.push(Change { path: format!("{}.{}", path, k), kind: ChangeKind::Removed, old: Some(ov.clone()), new: None, summary: format!("{} {} removed", label, name) });
+    }
+}
+
+/// Compare only sections whose scanner succeeded in both scans, so a timeout
+/// never shows up as "everything removed".
+fn scanner_ok(map: &SystemMap, name: &str) -> bool {
+    map.scanners.iter().any(|s| s.name == name && s.state == "ok")
+}
+
+pub fn diff_maps(old: &SystemMap, new: &SystemMap) -> Vec<Change> {
+    let mut out = vec![];
+    let both = |name: &str| scanner_ok(old, name) && scanner_ok(new, name);
+
+    scalar(&mut out, "os.name", "OS", &Some(old.os.name.clone()), &Some(new.os.name.clone()), false);
+    scalar(&mut out, "os.version", "OS version", &old.os.version, &new.os.version, false);
+    scalar(&mut out, "os.kernel", "kernel", &old.os.kernel, &new.os.kernel, true);
+    scalar(&mut out, "os.arch", "arch", &old.os.arch, &new.os.arch, false);
+    scalar(&mut out, "hardware.cpu_model", "CPU", &old.hardware.cpu_model, &new.hardware.cpu_model, false);
+    scalar(&mut out, "hardware.gpu_model", "GPU", &old.hardware.gpu_model, &new.hardware.gpu_model, false);
+    let ram = |m: &SystemMap| m.hardware.ram_gb.map(|g| format!("{:.1} GB", g));
+    scalar(&mut out, "hardware.ram_gb", "RAM", &ram(old), &ram(new), false);
+    let (ro, rn) = (&old.runtimes, &new.runtimes);
+    for (field, o, n) in [
+        ("python", &ro.python, &rn.python),
+        ("node", &ro.node, &rn.node),
+        ("rustc", &ro.rustc, &rn.rustc),
+        ("cargo", &ro.cargo, &rn.cargo),
+        ("java", &ro.java, &rn.java),
+        ("cuda", &ro.cuda, &rn.cuda),
+    ] {
+        scalar(&mut out, &format!("runtimes.{}", field), field, o, n, true);
+    }
+    scalar(&mut out, "network.hostname", "hostname", &old.network.hostname, &new.network.hostname, false);
+    set(&mut out, "package_managers", "package manager", &old.package_managers, &new.package_managers);
+    set(&mut out, "apps", "app", &old.apps, &new.apps);
+    set(&mut out, "dev_env.editors", "editor", &old.dev_env.editors, &new.dev_env.editors);
+
+    let installed = |m: &SystemMap| m.installed.iter().map(|p| (format!("{}.{}", p.manager, p.name), (p.name.clone(), json!(p.version)))).collect();
+    keyed(&mut out, "installed", "installed", installed(old), installed(new), true);
+
+    let managers: std::collections::BTreeSet<&str> = old.packages.iter().chain(new.packages.iter()).map(|p| p.manager.as_str()).collect();
+    for m in managers.into_iter().filter(|m| both(m)) {
+        let pk = |map: &SystemMap| map.packages.iter().filter(|p| p.manager == m).map(|p| (p.name.clone(), (p.name.clone(), json!(p.version)))).collect();
+        keyed(&mut out, &format!("packages.{}", m), &format!("{} package", m), pk(old), pk(new), true);
+    }
+    if both("systemd-user") {
+        let sv = |map: &SystemMap| map.services.iter().map(|s| (s.name.clone(), (s.name.clone(), json!(format!("{}/{}", s.active, s.sub))))).collect();
+        keyed(&mut out, "services", "service", sv(old), sv(new), false);
+    }
+    for rt in ["docker", "podman"].into_iter().filter(|r| both(r)) {
+        let ct = |map: &SystemMap| {
+            map.containers.iter().filter(|c| c.runtime == rt).map(|c| (c.name.clone(), (c.name.clone(), json!({"image": c.image, "state": c.state})))).collect()
+        };
+        keyed(&mut out, &format!("containers.{}", rt), &format!("{} container", rt), ct(old), ct(new), false);
+    }
+    if both("net") {
+        set(&mut out, "network.interfaces", "interface", &old.network.interfaces, &new.network.interfaces);
+    }
+    if both("gpu") {
+        let gp = |map: &SystemMap| {
+            map.hardware.gpus.iter().map(|g| {
+                let key = g.pci_slot.clone().unwrap_or_else(|| g.model.clone().unwrap_or_default());
+                (key, (g.model.clone().unwrap_or_else(|| "GPU".into()), json!({"model": g.model, "driver": g.driver})))
+            }).collect()
+        };
+        keyed(&mut out, "hardware.gpus", "GPU", gp(old), gp(new), false);
+    }
+    if both("git-repos") {
+        let rp = |map: &SystemMap| map.repos.iter().map(|r| (r.path.clone(), (r.path.clone(), json!(r.branch)))).collect();
+        keyed(&mut out, "repos", "repo", rp(old), rp(new), false);
+    }
+    out
+}
+
+/// Rank changes for the digest: toolchain/OS first, bulk package churn last.
+fn salience(c: &Change) -> u8 {
+    let p = c.path.as_str();
+    if p.starts_with("runtimes.") || p.starts_with("os.") || p.starts_with("hardware.") { 0 }
+    else if p.starts_with("installed.") || p.starts_with("apps.") || p.starts_with("package_managers.") { 1 }
+    else if p.starts_with("packages.") { 3 }
+    else { 2 }
+}
+
+fn relative_day(at: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let days = (now.date_naive() - at.date_naive()).num_days();
+    match days {
+        i64::MIN..=0 => "today".into(),
+        1 => "yesterday".into(),
+        n => format!("{} days ago", n),
+    }
+}
+
+/// "Recent changes: rustc upgraded to 1.82.0 yesterday; ..." or None when nothing is recent.
+pub fn recent_summary(diffs: &[MapDiff], now: DateTime<Utc>, window: Duration, max: usize) -> Option<String> {
+    let cutoff = now - window;
+    let mut items: Vec<(&Change, DateTime<Utc>)> = diffs
+        .iter()
+        .filter(|d| d.at >= cutoff)
+        .flat_map(|d| d.changes.iter().map(move |c| (c, d.at)))
+        .collect();
+    if items.is_empty() { return None; }
+    // Most salient first, newest first within a class; keep only the latest entry per path
+    items.sort_by(|a, b| salience(a.0).cmp(&salience(b.0)).then(b.1.cmp(&a.1)));
+    let mut seen = std::collections::HashSet::new();
+    items.retain(|(c, _)| seen.insert(c.path.clone()));
+    let total = items.len();
+    let mut parts: Vec<String> = items.iter().take(max).map(|(c, at)| format!("{} {}", c.summary, relative_day(*at, now))).collect();
+    if total > max { parts.push(format!("+{} more (GET /api/system_map/diff)", total - max)); }
+    Some(format!("Recent changes: {}.", parts.join("; ")))
+}
+
+fn history_file_name(at: DateTime<Utc>) -> String {
+    format!("{}.json", at.format("%Y%m%dT%H%M%S%.3fZ"))
+}
+
+pub async fn write_history(dir: &Path, diff: &MapDiff) -> anyhow::Result<()> {
+    tokio::fs::create_dir_all(dir).await?;
+    tokio::fs::write(dir.join(history_file_name(diff.at)), serde_json::to_vec_pretty(diff)?).await?;
+    Ok(())
+}
+
+/// Diffs recorded at or after `since`, oldest first.
+pub async fn read_history(dir: &Path, since: DateTime<Utc>) -> Vec<MapDiff> {
+    let mut out = vec![];
+    let Ok(mut rd) = tokio::fs::read_dir(dir).await else { return out };
+    while let Ok(Some(ent)) = rd.next_entry().await {
+        if ent.path().extension().and_then(|e| e.to_str()) != Some("json") { continue; }
+        if let Ok(text) = tokio::fs::read_to_string(ent.path()).await {
+            if let Ok(d) = serde_json::from_str::<MapDiff>(&text) {
+                if d.at >= since { out.push(d); }
+            }
+        }
+    }
+    out.sort_by_key(|d| d.at);
+    out
+}
+
+/// Parse `since` as RFC 3339 or a relative span (`30m`, `12h`, `7d`).
+pub fn parse_since(s: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let s = s.trim();
+    if let Ok(t) = DateTime::parse_from_rfc3339(s) { return Some(t.with_timezone(&Utc)); }
+    let (num, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit())?);
+    let n: i64 = num.parse().ok()?;
+    let span = match unit {
+        "m" => Duration::minutes(n),
+        "h" => Duration::hours(n),
+        "d" => Duration::days(n),
+        "w" => Duration::weeks(n),
+        _ => return None,
+    };
+    Some(now - span)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ok(names: &[&str]) -> Vec<ScannerStatus> {
+        names.iter().map(|n| ScannerStatus { name: n.to_string(), state: "ok".into(), ..Default::default() }).collect()
+    }
+
+    fn base() -> SystemMap {
+        SystemMap {
+            os: OsInfo { name: "Debian".into(), version: Some("12".into()), kernel: Some("6.1.0-18-amd64".into()), arch: Some("x86_64".into()) },
+            runtimes: RuntimesInfo { rustc: Some("rustc 1.81.0 (eeb90cda1 2024-09-04)".into()), python: Some("Python 3.11.2".into()), ..Default::default() },
+            apps: vec!["git".into(), "steam".into()],
+            packages: vec![
+                PackageEntry { manager: "dpkg".into(), name: "bash".into(), version: Some("5.2.15-2".into()) },
+                PackageEntry { manager: "dpkg".into(), name: "vim".into(), version: Some("9.0.1378-2".into()) },
+                PackageEntry { manager: "pip".into(), name: "requests".into(), version: Some("2.31.0".into()) },
+            ],
+            scanners: ok(&["dpkg", "pip"]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn detects_field_level_changes() {
+        let old = base();
+        let mut new = base();
+        new.runtimes.rustc = Some("rustc 1.82.0 (f6e511eec 2024-10-15)".into());
+        new.apps.retain(|a| a != "steam");
+        new.packages[1].version = Some("9.1.0-1".into());
+        new.packages.push(PackageEntry { manager: "dpkg".into(), name: "ripgrep".into(), version: Some("13.0.0-4".into()) });
+        let changes = diff_maps(&old, &new);
+        let summaries: Vec<&str> = changes.iter().map(|c| c.summary.as_str()).collect();
+        assert!(summaries.contains(&"rustc upgraded to 1.82.0"), "{:?}", summaries);
+        assert!(summaries.contains(&"app steam removed"));
+        assert!(summaries.contains(&"dpkg package vim upgraded to 9.1.0-1"));
+        assert!(summaries.contains(&"dpkg package ripgrep added (13.0.0-4)"));
+        assert_eq!(changes.len(), 4);
+        assert!(diff_maps(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn failed_scanner_is_not_a_mass_removal() {
+        let old = base();
+        let mut new = base();
+        new.packages.retain(|p| p.manager != "pip");
+        new.scanners = vec![ScannerStatus { name: "dpkg".into(), state: "ok".into(), ..Default::default() }, ScannerStatus { name: "pip".into(), state: "timeout".into(), ..Default::default() }];
+        assert!(diff_maps(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn summary_prefers_runtimes_and_relative_days() {
+        let now = DateTime::parse_from_rfc3339("2025-03-10T12:00:00Z").unwrap().with_timezone(&Utc);
+        let ch = |path: &str, summary: &str| Change { path: path.into(), kind: ChangeKind::Changed, old: None, new: None, summary: summary.into() };
+        let diffs = vec![
+            MapDiff { at: now - Duration::days(1), from_scan: now, to_scan: now, changes: vec![ch("packages.dpkg.vim", "dpkg package vim upgraded to 9.1"), ch("runtimes.rustc", "rustc upgraded to 1.82.0")] },
+            MapDiff { at: now - Duration::days(30), from_scan: now, to_scan: now, changes: vec![ch("apps.steam", "app steam added")] },
+        ];
+        let s = recent_summary(&diffs, now, Duration::days(7), 1).unwrap();
+        assert_eq!(s, "Recent changes: rustc upgraded to 1.82.0 yesterday; +1 more (GET /api/system_map/diff).");
+        assert!(recent_summary(&diffs[1..], now, Duration::days(7), 5).is_none());
+        assert_eq!(parse_since("2d", now), Some(now - Duration::days(2)));
+        assert!(parse_since("soon", now).is_none());
+    }
+}
@@ -1,6 +1,11 @@
 use super::model::SystemMap;
 
 pub fn compute_digest(map: &SystemMap) -> String {
+    compute_digest_with(map, None)
+}
+
+/// Digest plus an optional "Recent changes: …" line from the map history.
+pub fn compute_digest_with(map: &SystemMap, recent: Option<&str>) -> String {
     // Build a compact, deterministic summary; sort lists to keep stable
     let mut pms = map.package_managers.clone(); pms.sort();
     let mut apps = map.apps.clone(); apps.sort();
@@ -40,7 +45,36 @@ pub fn compute_digest(map: &SystemMap) -> String {
         let list = if apps.len() > 8 { let mut a = apps[..8].to_vec(); a.push("…".to_string()); a } else { apps };
         parts.push(format!("Apps: {}.", list.join(", ")));
     }
-    parts.push("Rules: prefer local CLI; apt/installs require approval; no external network scans; expand details via map://packages, map://emulators, map://worktrees.".to_string());
+    if !map.installed.is_empty() {
+        let mut inst: Vec<String> = map.installed.iter().map(|p| match &p.version { Some(v) => format!("{}({})", p.name, v), None => p.name.clone() }).collect();
+        inst.sort();
+        parts.push(format!("Installed via Foreman: {}.", inst.join(", ")));
+    }
+    if let Some(g) = hw.gpus.iter().find_map(|g| g.model.clone().or_else(|| g.vendor.clone())) {
+        let more = if hw.gpus.len() > 1 { format!(" (+{} more)", hw.gpus.len() - 1) } else { String::new() };
+        parts.push(format!("GPU: {}{}.", g, more));
+    }
+    if !map.packages.is_empty() {
+        let mut counts = std::collections::BTreeMap::<&str, usize>::new();
+        for p in map.packages.iter() { *counts.entry(p.manager.as_str()).or_default() += 1; }
+        let list: Vec<String> = counts.iter().map(|(m, n)| format!("{} {}", m, n)).collect();
+        parts.push(format!("Packages: {}.", list.join(", ")));
+    }
+    if !map.services.is_empty() {
+        let running = map.services.iter().filter(|s| s.sub == "running").count();
+        parts.push(format!("User services: {} ({} running).", map.services.len(), running));
+    }
+    if !map.containers.is_empty() {
+        let running = map.containers.iter().filter(|c| c.state == "running").count();
+        parts.push(format!("Containers: {} ({} running).", map.containers.len(), running));
+    }
+    if !map.repos.is_empty() {
+        parts.push(format!("Git repos: {}.", map.repos.len()));
+    }
+    if let Some(r) = recent {
+        parts.push(r.to_string());
+    }
+    parts.push("Rules: prefer local CLI; apt/installs require approval; no external network scans; expand details via map://packages, map://emulators, map://worktrees, map://services, map://containers, map://network, map://gpus; changes via /api/system_map/diff.".to_string());
 
     let digest = parts.join(" ");
 
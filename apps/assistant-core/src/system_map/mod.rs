@@ -1,9 +1,12 @@
 pub mod model;
 pub mod scan;
+pub mod scanners;
 pub mod digest;
+pub mod diff;
 
 use crate::memory::Memory;
-use crate::system_map::digest::compute_digest;
+use crate::system_map::digest::compute_digest_with;
+use chrono::{Duration, Utc};
 use parking_lot::RwLock;
 use serde_json::json;
 use std::path::{Path, PathBuf};
@@ -19,10 +22,55 @@ struct Inner {
     map: RwLock<Option<model::SystemMap>>,
     digest: RwLock<String>,
     memory: Option<Memory>,
+    options: scanners::ScanOptions,
+    history_dir: PathBuf,
+    /// Diffs from the last `RECENT_DAYS`, oldest first.
+    recent: RwLock<Vec<diff::MapDiff>>,
+    policy: RefreshPolicy,
+    /// Serializes scans and map writes.
+    scan_lock: tokio::sync::Mutex<()>,
+    trigger: tokio::sync::Notify,
+    pending: parking_lot::Mutex<Vec<String>>,
+}
+
+/// How far back the digest looks for "Recent changes".
+const RECENT_DAYS: i64 = 7;
+
+/// When the background refresher rescans and when the map counts as stale.
+#[derive(Debug, Clone)]
+pub struct RefreshPolicy {
+    /// Periodic incremental rescan; `None` only refreshes on triggers.
+    pub interval: Option<std::time::Duration>,
+    /// Age after which the digest is flagged stale.
+    pub max_age: Duration,
+    /// Quiet period after a trigger so bursts (several installs) scan once.
+    pub debounce: std::time::Duration,
+}
+
+impl Default for RefreshPolicy {
+    fn default() -> Self {
+        Self { interval: Some(std::time::Duration::from_secs(3600)), max_age: Duration::hours(24), debounce: std::time::Duration::from_secs(2) }
+    }
+}
+
+impl RefreshPolicy {
+    pub fn from_config(cfg: Option<&crate::config::SystemMapConfig>) -> Self {
+        let mut p = Self::default();
+        let Some(cfg) = cfg else { return p };
+        if let Some(secs) = cfg.refresh_interval_secs {
+            p.interval = (secs > 0).then(|| std::time::Duration::from_secs(secs));
+        }
+        if let Some(h) = cfg.max_age_hours { p.max_age = Duration::hours(h as i64); }
+        p
+    }
 }
 
 impl SystemMapManager {
     pub fn new(base_dir: &Path, memory: Option<Memory>) -> Self {
+        Self::with_options(base_dir, memory, scanners::ScanOptions::default(), RefreshPolicy::default())
+    }
+
+    pub fn with_options(base_dir: &Path, memory: Option<Memory>, options: scanners::ScanOptions, policy: RefreshPolicy) -> Self {
         let map_path = base_dir.join("map.json");
         Self {
             inner: Arc::new(Inner {
@@ -30,6 +78,13 @@ impl SystemMapManager {
                 map: RwLock::new(None),
                 digest: RwLock::new(String::new()),
                 memory,
+                options,
+                history_dir: base_dir.join("map_history"),
+                recent: RwLock::new(vec![]),
+                policy,
+                scan_lock: tokio::sync::Mutex::new(()),
+                trigger: tokio::sync::Notify::new(),
+                pending: parking_lot::Mutex::new(vec![]),
             }),
         }
     }
@@ -40,50 +95,161 @@ impl SystemMapManager {
         self.inner.map.read().clone()
     }
 
+    /// Digest, prefixed with a staleness note once the map is older than the policy allows.
     pub fn get_digest(&self) -> String {
-        self.inner.digest.read().clone()
+        let digest = self.inner.digest.read().clone();
+        match self.age().filter(|_| self.is_stale()) {
+            Some(age) => format!("Note: System Map is stale (last scanned {} ago); details may be outdated. {}", human_age(age), digest),
+            None => digest,
+        }
+    }
+
+    /// Time since the current map was scanned.
+    pub fn age(&self) -> Option<Duration> {
+        self.inner.map.read().as_ref().map(|m| Utc::now() - m.scanned_at)
+    }
+
+    pub fn is_stale(&self) -> bool {
+        self.age().map(|a| a > self.inner.policy.max_age).unwrap_or(true)
+    }
+
+    pub fn history_dir(&self) -> &Path { &self.inner.history_dir }
+
+    /// Recorded map diffs at or after `since`, oldest first.
+    pub async fn diff_since(&self, since: chrono::DateTime<Utc>) -> Vec<diff::MapDiff> {
+        diff::read_history(&self.inner.history_dir, since).await
+    }
+
+    fn digest_for(&self, map: &model::SystemMap) -> String {
+        let recent = diff::recent_summary(&self.inner.recent.read(), Utc::now(), Duration::days(RECENT_DAYS), 5);
+        compute_digest_with(map, recent.as_deref())
     }
 
     pub async fn load_or_scan(&self) -> anyhow::Result<()> {
+        let recent = diff::read_history(&self.inner.history_dir, Utc::now() - Duration::days(RECENT_DAYS)).await;
+        *self.inner.recent.write() = recent;
         // Try load from disk; fall back to scan and persist
         if let Ok(text) = tokio::fs::read_to_string(&self.inner.map_path).await {
             if let Ok(map) = serde_json::from_str::<model::SystemMap>(&text) {
-                let digest = compute_digest(&map);
+                let digest = self.digest_for(&map);
                 *self.inner.map.write() = Some(map);
                 *self.inner.digest.write() = digest;
                 return Ok(());
             }
         }
-        let map = scan::scan_system().await;
+        let map = scan::scan_system(&self.inner.options, None).await;
         self.update_with(map).await
     }
 
+    /// Incremental rescan: unchanged inputs reuse the previous sections.
     pub async fn refresh(&self) -> anyhow::Result<()> {
-        let map = scan::scan_system().await;
+        self.rescan(true).await
+    }
+
+    /// Rescan every source regardless of fingerprints.
+    pub async fn refresh_full(&self) -> anyhow::Result<()> {
+        self.rescan(false).await
+    }
+
+    async fn rescan(&self, incremental: bool) -> anyhow::Result<()> {
+        let _guard = self.inner.scan_lock.lock().await;
+        let previous = if incremental { self.get_map() } else { None };
+        let mut map = scan::scan_system(&self.inner.options, previous.as_ref()).await;
+        // Installer records are not rediscovered by the scan; carry them over
+        if let Some(old) = self.inner.map.read().as_ref() {
+            map.installed = old.installed.clone();
+        }
         self.update_with(map).await
     }
 
-    pub async fn update_with(&self, new_map: model::SystemMap) -> anyhow::Result<()> {
-        // Compare with existing
-        let mut changed = true;
-            if let Some(old) = self.inner.map.read().as_ref() {
-            let old_json = serde_json::to_string(old)?;
-            let new_json = serde_json::to_string(&new_map)?;
-            changed = old_json != new_json;
+    /// Ask the background refresher for a rescan (debounced); `reason` is logged.
+    pub fn request_refresh(&self, reason: &str) {
+        self.inner.pending.lock().push(reason.to_string());
+        self.inner.trigger.notify_one();
+    }
+
+    /// Start the refresh loop: rescans once if the loaded map is stale, then on
+    /// every interval tick and after `request_refresh` triggers.
+    pub fn spawn_refresher(&self) -> tokio::task::JoinHandle<()> {
+        let this = self.clone();
+        tokio::spawn(async move {
+            if this.is_stale() {
+                this.refresh_logged("stale").await;
+            }
+            loop {
+                let tick = async {
+                    match this.inner.policy.interval {
+                        Some(d) => tokio::time::sleep(d).await,
+                        None => std::future::pending::<()>().await,
+                    }
+                };
+                let reason = tokio::select! {
+                    _ = tick => "interval".to_string(),
+                    _ = this.inner.trigger.notified() => {
+                        tokio::time::sleep(this.inner.policy.debounce).await;
+                        let mut reasons = std::mem::take(&mut *this.inner.pending.lock());
+                        reasons.dedup();
+                        reasons.join(",")
+                    }
+                };
+                this.refresh_logged(&reason).await;
+            }
+        })
+    }
+
+    async fn refresh_logged(&self, reason: &str) {
+        let started = std::time::Instant::now();
+        match self.refresh().await {
+            Ok(()) => tracing::debug!(reason, elapsed_ms = started.elapsed().as_millis() as u64, "system map refreshed"),
+            Err(e) => tracing::warn!(reason, error = %e, "system map refresh failed"),
+        }
+    }
+
+    /// Record (or update) packages installed by the installer and persist the map.
+    pub async fn record_installed(&self, pkgs: Vec<model::InstalledPackage>) -> anyhow::Result<()> {
+        if pkgs.is_empty() { return Ok(()); }
+        let _guard = self.inner.scan_lock.lock().await;
+        let mut map = self.get_map().unwrap_or_default();
+        for p in pkgs {
+            map.installed.retain(|e| !(e.manager == p.manager && e.name == p.name));
+            map.installed.push(p);
         }
+        map.installed.sort_by(|a, b| (a.manager.as_str(), a.name.as_str()).cmp(&(b.manager.as_str(), b.name.as_str())));
+        self.update_with(map).await
+    }
+
+    pub async fn update_with(&self, new_map: model::SystemMap) -> anyhow::Result<()> {
+        // Field-level diff against the current map; the first map has nothing to compare
+        let old = self.inner.map.read().clone();
+        let changes = old.as_ref().map(|o| diff::diff_maps(o, &new_map));
 
         // Persist
         if let Some(parent) = self.inner.map_path.parent() { tokio::fs::create_dir_all(parent).await.ok(); }
         let data = serde_json::to_vec_pretty(&new_map)?;
         tokio::fs::write(&self.inner.map_path, data).await?;
 
+        let recorded = match (old.as_ref(), changes) {
+            (Some(o), Some(changes)) if !changes.is_empty() => {
+                let d = diff::MapDiff { at: Utc::now(), from_scan: o.scanned_at, to_scan: new_map.scanned_at, changes };
+                if let Err(e) = diff::write_history(&self.inner.history_dir, &d).await {
+                    tracing::warn!(error = %e, "failed to write system map history");
+                }
+                let cutoff = Utc::now() - Duration::days(RECENT_DAYS);
+                let mut recent = self.inner.recent.write();
+                recent.retain(|r| r.at >= cutoff);
+                recent.push(d.clone());
+                Some(d)
+            }
+            _ => None,
+        };
+
         // Update in-memory state
-        let digest = compute_digest(&new_map);
+        let digest = self.digest_for(&new_map);
         *self.inner.map.write() = Some(new_map.clone());
         *self.inner.digest.write() = digest;
 
-        // Emit event if changed
-        if changed {
+        // Emit events for the first map and whenever something changed
+        if old.is_none() || recorded.is_some() {
             if let Some(mem) = self.inner.memory.as_ref() {
                 let _ = mem
                     .store
@@ -94,31 +260,99 @@ impl SystemMapManager {
                             "scanned_at": new_map.scanned_at,
                             "os": new_map.os.name,
                             "runtimes": new_map.runtimes,
+                            "changes": recorded.as_ref().map(|d| d.changes.len()).unwrap_or(0),
                         })),
                     )
                     .await;
+                if let Some(d) = recorded.as_ref() {
+                    // Keep event payloads small; the full diff lives in map_history/
+                    let summaries: Vec<&str> = d.changes.iter().take(50).map(|c| c.summary.as_str()).collect();
+                    let _ = mem
+                        .store
+                        .append_event(
+                            None,
+                            "system_map:changed",
+                            Some(&json!({
+                                "at": d.at,
+                                "count": d.changes.len(),
+                                "summaries": summaries,
+                                "truncated": d.changes.len() > summaries.len(),
+                            })),
+                        )
+                        .await;
+                }
             }
         }
         Ok(())
     }
 
+    /// Resolve a `map://` detail URI against the current map.
     pub fn resolve_uri(&self, uri: &str) -> Option<serde_json::Value> {
         let map = self.inner.map.read();
         let map = map.as_ref()?;
-        match uri {
-            "map://packages" => Some(json!({
-                "package_managers": map.package_managers,
-                "apps": map.apps,
-            })),
-            "map://emulators" => Some(json!({
-                "emulators": map.apps.iter().filter(|a| a.contains("emu")).collect::<Vec<_>>()
-            })),
-            "map://worktrees" => Some(json!({
+        let path = uri.strip_prefix("map://")?;
+        if let Some(manager) = path.strip_prefix("packages/") {
+            let pkgs: Vec<_> = map.packages.iter().filter(|p| p.manager == manager).collect();
+            return Some(json!({ "manager": manager, "count": pkgs.len(), "packages": pkgs }));
+        }
+        match path {
+            "packages" => {
+                let mut counts = std::collections::BTreeMap::<&str, usize>::new();
+                for p in map.packages.iter() { *counts.entry(p.manager.as_str()).or_default() += 1; }
+                Some(json!({
+                    "package_managers": map.package_managers,
+                    "apps": map.apps,
+                    "installed": map.installed,
+                    "counts": counts,
+                    "details": counts.keys().map(|m| format!("map://packages/{}", m)).collect::<Vec<_>>(),
+                }))
+            }
+            "emulators" => {
+                // Package names only match known emulators; "emu" substrings are too noisy there
+                let mut found: Vec<String> = map
+                    .apps
+                    .iter()
+                    .filter(|a| a.contains("emu") || is_emulator(a))
+                    .chain(map.packages.iter().map(|p| &p.name).filter(|n| is_emulator(n)))
+                    .cloned()
+                    .collect();
+                found.sort();
+                found.dedup();
+                Some(json!({ "emulators": found }))
+            }
+            "worktrees" | "repos" => Some(json!({
                 "vcs": map.dev_env.vcs,
+                "repos": map.repos,
             })),
+            "services" => Some(json!({ "services": map.services })),
+            "containers" => Some(json!({ "containers": map.containers })),
+            "network" => Some(json!({
+                "hostname": map.network.hostname,
+                "interfaces": map.network.links,
+            })),
+            "gpus" => Some(json!({ "gpus": map.hardware.gpus })),
+            "scanners" => Some(json!({ "scanners": map.scanners })),
             _ => None,
         }
     }
 }
 
+fn human_age(age: Duration) -> String {
+    match (age.num_days(), age.num_hours()) {
+        (d, _) if d >= 2 => format!("{} days", d),
+        (_, h) if h >= 2 => format!("{} hours", h),
+        _ => format!("{} minutes", age.num_minutes().max(1)),
+    }
+}
+
+const EMULATORS: &[&str] = &[
+    "retroarch", "dolphin-emu", "pcsx2", "rpcs3", "ppsspp", "duckstation", "mgba", "mupen64plus", "snes9x", "cemu",
+    "ryujinx", "yuzu", "citra", "melonds", "desmume", "flycast", "xemu", "mame", "dosbox", "scummvm", "visualboyadvance-m",
+];
+
+fn is_emulator(name: &str) -> bool {
+    let n = name.to_ascii_lowercase();
+    EMULATORS.iter().any(|e| n == *e || n.starts_with(&format!("{}-", e)) || n.ends_with(&format!(".{}", e)))
+}
+
 pub use model::SystemMap;
@@ -6,6 +6,17 @@ pub struct HardwareInfo {
     pub cpu_model: Option<String>,
     pub gpu_model: Option<String>,
     pub ram_gb: Option<f64>,
+    #[serde(default)]
+    pub gpus: Vec<GpuInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct GpuInfo {
+    pub pci_slot: Option<String>,
+    pub vendor: Option<String>,
+    pub model: Option<String>,
+    pub driver: Option<String>,
+    pub vram_mb: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -36,6 +47,81 @@ pub struct DevEnvInfo {
 pub struct NetworkInfo {
     pub hostname: Option<String>,
     pub interfaces: Vec<String>,
+    #[serde(default)]
+    pub links: Vec<NetInterface>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct NetInterface {
+    pub name: String,
+    pub mac: Option<String>,
+    pub state: Option<String>,
+    pub mtu: Option<u32>,
+    /// False for loopback/virtual devices (no backing `device` in sysfs).
+    pub physical: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct PackageEntry {
+    /// Source list: dpkg, rpm, pacman, pip, cargo.
+    pub manager: String,
+    pub name: String,
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ServiceEntry {
+    pub name: String,
+    pub active: String,
+    pub sub: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ContainerEntry {
+    /// docker or podman.
+    pub runtime: String,
+    pub id: String,
+    pub name: String,
+    pub image: String,
+    pub state: String,
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct RepoEntry {
+    pub path: String,
+    pub branch: Option<String>,
+    pub remote: Option<String>,
+    /// Linked worktrees (`git worktree add`) registered in this repo.
+    #[serde(default)]
+    pub worktrees: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ScannerStatus {
+    pub name: String,
+    /// ok, error, timeout, unavailable or disabled.
+    pub state: String,
+    pub items: usize,
+    pub elapsed_ms: u64,
+    pub error: Option<String>,
+    /// Fingerprint of the scanner's inputs (file metadata) when it declares any.
+    #[serde(default)]
+    pub fingerprint: Option<String>,
+    /// True when the previous result was reused because the fingerprint matched.
+    #[serde(default)]
+    pub cached: bool,
+}
+
+/// Package installed through an approved installer plan.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct InstalledPackage {
+    pub manager: String,
+    pub name: String,
+    pub version: Option<String>,
+    pub plan_id: Option<String>,
+    pub installed_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -48,5 +134,20 @@ pub struct SystemMap {
     pub apps: Vec<String>,
     pub dev_env: DevEnvInfo,
     pub network: NetworkInfo,
+    #[serde(default)]
+    pub installed: Vec<InstalledPackage>,
+    #[serde(default)]
+    pub packages: Vec<PackageEntry>,
+    #[serde(default)]
+    pub services: Vec<ServiceEntry>,
+    #[serde(default)]
+    pub containers: Vec<ContainerEntry>,
+    #[serde(default)]
+    pub repos: Vec<RepoEntry>,
+    #[serde(default)]
+    pub scanners: Vec<ScannerStatus>,
+    /// Fingerprint of `$PATH` used to skip the `--version` probes on incremental scans.
+    #[serde(default)]
+    pub probes_fingerprint: Option<String>,
 }
 
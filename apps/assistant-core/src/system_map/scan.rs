@@ -1,4 +1,5 @@
 use super::model::*;
+use super::scanners::{default_scanners, fingerprint, path_dirs, run_scanners, ScanOptions};
 use chrono::Utc;
 use std::time::Duration;
 use tokio::process::Command;
@@ -23,7 +24,11 @@ async fn run_and_capture(cmd: &str, args: &[&str]) -> Option<String> {
     }
 }
 
-pub async fn scan_system() -> SystemMap {
+/// Full scan, or an incremental one when `previous` is given: package manager
+/// and app presence probes are skipped while `$PATH` is unchanged, and deep
+/// scanners reuse sections whose inputs are unchanged. Runtime versions are
+/// always probed (`rustup update` changes them without touching `$PATH`).
+pub async fn scan_system(opts: &ScanOptions, previous: Option<&SystemMap>) -> SystemMap {
     let mut map = SystemMap::default();
     map.scanned_at = Utc::now();
 
@@ -60,9 +65,34 @@ pub async fn scan_system() -> SystemMap {
             .and_then(|kb| kb.parse::<u64>().ok())
             .map(|kb| (kb as f64) / (1024.0 * 1024.0))
         );
-    map.hardware = HardwareInfo { cpu_model, gpu_model: None, ram_gb };
+    map.hardware = HardwareInfo { cpu_model, gpu_model: None, ram_gb, gpus: vec![] };
 
-    // Runtimes versions
+    let probes_fp = fingerprint(&path_dirs());
+    map.probes_fingerprint = Some(probes_fp.clone());
+    probe_runtimes(&mut map).await;
+    match previous.filter(|p| p.probes_fingerprint.as_deref() == Some(probes_fp.as_str())) {
+        Some(prev) => {
+            map.package_managers = prev.package_managers.clone();
+            map.apps = prev.apps.clone();
+            map.dev_env = prev.dev_env.clone();
+        }
+        None => probe_path(&mut map).await,
+    }
+
+    // Network (light): hostname
+    let hostname = run_and_capture("hostname", &[]).await.map(|s| parse_first_line(&s));
+    map.network = NetworkInfo { hostname, interfaces: vec![], links: vec![] };
+
+    // Deep scanners (packages, services, containers, sysfs, repos)
+    let (findings, statuses) = run_scanners(&default_scanners(), opts, previous).await;
+    for f in findings { f.apply(&mut map); }
+    map.scanners = statuses;
+
+    map
+}
+
+/// Runtime versions (`--version`); toolchain managers swap these in place.
+async fn probe_runtimes(map: &mut SystemMap) {
     let python = match run_and_capture("python3", &["--version"]).await {
         Some(s) => Some(parse_first_line(&s)),
         None => run_and_capture("python", &["--version"]).await.map(|s| parse_first_line(&s)),
@@ -73,7 +103,10 @@ pub async fn scan_system() -> SystemMap {
     let java = run_and_capture("java", &["-version"]).await.map(|s| parse_first_line(&s));
     let cuda = run_and_capture("nvcc", &["--version"]).await.map(|s| parse_first_line(&s));
     map.runtimes = RuntimesInfo { python, node, rustc, cargo, java, cuda };
+}
 
+/// Package manager and app presence (`--version` on each candidate in `$PATH`).
+async fn probe_path(map: &mut SystemMap) {
     // Package managers presence
     let mut pms = vec![];
     for (cmd, label) in [
@@ -119,10 +152,4 @@ pub async fn scan_system() -> SystemMap {
     }
     let vcs = if map.apps.iter().any(|a| a == "git") { vec!["git".to_string()] } else { vec![] };
     map.dev_env = DevEnvInfo { editors, vcs };
-
-    // Network (light): hostname
-    let hostname = run_and_capture("hostname", &[]).await.map(|s| parse_first_line(&s));
-    map.network = NetworkInfo { hostname, interfaces: vec![] };
-
-    map
 }
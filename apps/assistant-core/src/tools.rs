@@ -1,34 +1,66 @@
-use serde::{Deserialize};
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value as JsonValue};
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
-use uuid::Uuid;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
 use tokio::process::Command;
 use tokio::time::{timeout, Duration};
 use once_cell::sync::OnceCell;
 use std::sync::Arc;
 use tokio::sync::Mutex as AsyncMutex;
+use parking_lot::RwLock;
+
+pub use foreman_mcp::ToolSpec;
+
+/// Tools whose servers check a `dry_run: true` call and return its action
+/// without changing anything.
+const DRY_RUN_TOOLS: &[(&str, &str)] = &[("fs", "write"), ("fs", "append"), ("fs", "move"), ("fs", "delete"), ("games", "launch")];
 
 #[derive(Clone, Debug)]
 pub struct ToolManifest {
     pub server: String,
-    pub tools: Vec<String>,
+    /// Entries are either a bare tool name or a full spec object.
+    pub tools: Vec<ToolSpec>,
     pub transport: Option<String>,
     pub bin: Option<String>,
     pub autostart: bool,
+    /// Ask the server for its tool specs (`describe`) at startup.
+    pub describe: bool,
 }
 
 #[derive(Clone, Default)]
 pub struct ToolsManager {
     manifests: HashMap<String, ToolManifest>,
+    /// Specs returned by the `describe` handshake, per server.
+    described: Arc<RwLock<HashMap<String, Vec<ToolSpec>>>>,
     clients: Arc<AsyncMutex<HashMap<String, Arc<AsyncMutex<StdioClient>>>>>,
+    installer: crate::installer::Installer,
+    /// Refreshed after tools that change the machine (project.init).
+    system_map: Option<crate::system_map::SystemMapManager>,
+    /// Processes started by tools; proc.kill may signal these without approval.
+    spawned: Arc<RwLock<Vec<Spawned>>>,
+    /// In-core fs and games servers, built from `[fs]` / `[games]` (`with_config`).
+    fs: Arc<mcp_fs::Fs>,
+    games: Arc<mcp_games::Games>,
+}
+
+/// A process started by `shell.exec` (wait=false) or a game launch.
+#[derive(Clone, Debug, Serialize)]
+pub struct Spawned {
+    pub pid: u32,
+    /// Start time (unix seconds) when tracked; a later process reusing the pid has another.
+    pub start_time: i64,
+    /// `server.tool` that started it.
+    pub origin: String,
+    pub cmd: String,
+    pub args: Vec<String>,
+    pub started_at: chrono::DateTime<chrono::Utc>,
 }
 
 impl ToolsManager {
     pub fn load_from_dir(dir: &Path) -> Self {
-        let mut m = ToolsManager { manifests: HashMap::new(), clients: Arc::new(AsyncMutex::new(HashMap::new())) };
+        let mut m = ToolsManager::default();
         if let Ok(rd) = fs::read_dir(dir) {
             for ent in rd.flatten() {
                 if let Some(name) = ent.file_name().to_str() {
@@ -36,12 +68,13 @@ impl ToolsManager {
                         if let Ok(text) = fs::read_to_string(ent.path()) {
                             if let Ok(v) = serde_json::from_str::<serde_json::Value>(&text) {
                                 let server = v.get("server").and_then(|s| s.as_str()).unwrap_or("").to_string();
-                                let tools = v.get("tools").and_then(|t| t.as_array()).map(|a| a.iter().filter_map(|x| x.as_str().map(|s| s.to_string())).collect()).unwrap_or_default();
+                                let tools = v.get("tools").and_then(|t| t.as_array()).map(|a| a.iter().filter_map(|x| parse_tool_entry(&server, x)).collect()).unwrap_or_default();
                                 let transport = v.get("transport").and_then(|s| s.as_str()).map(|s| s.to_string());
                                 let bin = v.get("bin").and_then(|s| s.as_str()).map(|s| s.to_string());
                                 let autostart = v.get("autostart").and_then(|b| b.as_bool()).unwrap_or(false);
+                                let describe = v.get("describe").and_then(|b| b.as_bool()).unwrap_or(false);
                                 if !server.is_empty() {
-                                    m.manifests.insert(server.clone(), ToolManifest { server, tools, transport, bin, autostart });
+                                    m.manifests.insert(server.clone(), ToolManifest { server, tools, transport, bin, autostart, describe });
                                 }
                             }
                         }
@@ -52,19 +85,204 @@ impl ToolsManager {
         m
     }
 
+    /// Attach the in-core installer backend (needs memory and the System Map).
+    pub fn with_installer(mut self, installer: crate::installer::Installer) -> Self {
+        self.installer = installer;
+        self
+    }
+
+    pub fn installer(&self) -> &crate::installer::Installer { &self.installer }
+
+    pub fn with_system_map(mut self, system_map: crate::system_map::SystemMapManager) -> Self {
+        self.system_map = Some(system_map);
+        self
+    }
+
+    /// Build the in-core fs and games servers from `[fs]` and `[games]`.
+    pub fn with_config(mut self, config: &crate::config::Config) -> Self {
+        self.fs = Arc::new(mcp_fs::Fs::new(config.fs.clone().unwrap_or_default()));
+        self.games = Arc::new(mcp_games::Games::new(config.games.clone().unwrap_or_default()));
+        self
+    }
+
+    /// Remember the process a tool call started, if its result names a live pid.
+    pub fn track(&self, origin: &str, result: &JsonValue) {
+        let Some(pid) = result.get("pid").and_then(|p| p.as_u64()).filter(|p| *p > 0) else { return };
+        let Some((pid, start_time)) = mcp_proc::identity(pid as u32) else { return };
+        let cmd = result.get("cmd").and_then(|c| c.as_str()).unwrap_or_default().to_string();
+        let args = result.get("args").and_then(|a| a.as_array()).map(|a| a.iter().filter_map(|x| x.as_str().map(str::to_string)).collect()).unwrap_or_default();
+        let mut spawned = self.spawned.write();
+        spawned.retain(|s| s.pid != pid);
+        spawned.push(Spawned { pid, start_time, origin: origin.to_string(), cmd, args, started_at: chrono::Utc::now() });
+    }
+
+    /// Tracked processes still running; exited ones are dropped.
+    pub fn spawned(&self) -> Vec<Spawned> {
+        let mut spawned = self.spawned.write();
+        spawned.retain(|s| mcp_proc::identity(s.pid) == Some((s.pid, s.start_time)));
+        spawned.clone()
+    }
+
+    /// The tracked process behind `params.pid`, if still running.
+    fn owned(&self, params: &JsonValue) -> Option<Spawned> {
+        let pid = params.get("pid").and_then(|p| p.as_u64())?;
+        self.spawned().into_iter().find(|s| s.pid as u64 == pid)
+    }
+
     pub fn servers(&self) -> Vec<String> { self.manifests.keys().cloned().collect() }
 
     pub fn list(&self) -> Vec<(String, Vec<String>)> {
-        let mut v: Vec<(String, Vec<String>)> = self
+        self.specs().into_iter().map(|(server, specs)| (server, specs.into_iter().map(|t| t.name).collect())).collect()
+    }
+
+    /// Tool specs per server, sorted by server. Manifest entries come first and
+    /// are completed by what the server described; a manifest without tool
+    /// names exposes every described tool.
+    pub fn specs(&self) -> Vec<(String, Vec<ToolSpec>)> {
+        let described = self.described.read();
+        let mut v: Vec<(String, Vec<ToolSpec>)> = self
             .manifests
             .iter()
-            .map(|(k, m)| (k.clone(), m.tools.clone()))
+            .map(|(k, m)| {
+                let from_server = described.get(k).map(|d| d.as_slice()).unwrap_or(&[]);
+                let specs = if m.tools.is_empty() {
+                    from_server.to_vec()
+                } else {
+                    m.tools.iter().map(|t| match from_server.iter().find(|d| d.name == t.name) {
+                        Some(d) => t.clone().or(d),
+                        None => t.clone(),
+                    }).collect()
+                };
+                (k.clone(), specs)
+            })
             .collect();
         v.sort_by(|a, b| a.0.cmp(&b.0));
         v
     }
 
-    pub async fn invoke(&self, server: &str, tool: &str, params: JsonValue) -> anyhow::Result<JsonValue> {
+    pub fn spec(&self, server: &str, tool: &str) -> Option<ToolSpec> {
+        self.specs().into_iter().find(|(s, _)| s == server)?.1.into_iter().find(|t| t.name == tool)
+    }
+
+    /// Check `params` against the tool's input schema, if it declares one. A
+    /// server that describes its tools owes a schema for each; until it has
+    /// one, calls are refused rather than passed through unchecked.
+    pub fn validate(&self, server: &str, tool: &str, params: &JsonValue) -> anyhow::Result<()> {
+        let Some(schema) = self.spec(server, tool).and_then(|t| t.input_schema) else {
+            if self.manifests.get(server).map(|m| m.describe).unwrap_or(false) {
+                anyhow::bail!("no schema for {}.{} yet ({} has not described its tools)", server, tool, server);
+            }
+            return Ok(());
+        };
+        foreman_mcp::schema::validate(&schema, params)
+            .map_err(|errors| anyhow::anyhow!("invalid params for {}.{}: {}", server, tool, errors.join("; ")))
+    }
+
+    /// Whether `params` asks for a dry run the tool honours: its server is
+    /// known to implement one and its spec declares a boolean `dry_run`
+    /// input. Anything else is gated like a real call.
+    pub fn is_dry_run(&self, server: &str, tool: &str, params: &JsonValue) -> bool {
+        if params.get("dry_run").and_then(|v| v.as_bool()) != Some(true) || !DRY_RUN_TOOLS.contains(&(server, tool)) {
+            return false;
+        }
+        self.spec(server, tool)
+            .and_then(|t| t.input_schema)
+            .is_some_and(|s| s.pointer("/properties/dry_run/type").and_then(|t| t.as_str()) == Some("boolean"))
+    }
+
+    /// Policy view of a call, built from the tool's risk flags. Exec tools put
+    /// the program and its arguments in the command so `require_approval`
+    /// keywords match them. Tools without a spec are judged by name.
+    pub fn proposed_action(&self, server: &str, tool: &str, params: &JsonValue) -> crate::gatekeeper::ProposedAction {
+        let mut command = format!("{}.{}", server, tool);
+        if server == "fs" {
+            // The fs server knows what a mutating call resolves to (both ends of a move, rm -rf)
+            if let Some(a) = self.fs.proposed_action(tool, params) {
+                return crate::gatekeeper::ProposedAction { command: a.command, writes: a.writes, paths: a.paths, intent: a.intent };
+            }
+        }
+        if (server, tool) == ("games", "launch") {
+            // A launch names a title; the games server knows the emulator line and ROM behind it
+            if let Some(a) = self.games.proposed_action(tool, params) {
+                return crate::gatekeeper::ProposedAction { command: a.command, writes: a.writes, paths: a.paths, intent: a.intent };
+            }
+        }
+        if server == "git" {
+            // Branch and stash calls only write for some actions; the git server tells which
+            return match mcp_git::proposed_action(tool, params) {
+                Some(a) => crate::gatekeeper::ProposedAction { command: a.command, writes: a.writes, paths: a.paths, intent: a.intent },
+                None => crate::gatekeeper::ProposedAction { command, writes: false, paths: vec![], intent: None },
+            };
+        }
+        if (server, tool) == ("proc", "kill") {
+            // Signalling what our own tools started is routine; any other process is held
+            let writes = self.owned(params).is_none();
+            return crate::gatekeeper::ProposedAction { command, writes, paths: vec![], intent: None };
+        }
+        let Some(spec) = self.spec(server, tool) else {
+            let writes = server == "installer" || tool.split('_').any(|w| matches!(w, "write" | "apply" | "install"));
+            return crate::gatekeeper::ProposedAction { command, writes, paths: vec![], intent: None };
+        };
+        if spec.risk.exec {
+            let argv = params.get("cmd").into_iter().chain(params.get("args").and_then(|a| a.as_array()).into_iter().flatten());
+            for arg in argv.filter_map(|a| a.as_str()) {
+                command.push(' ');
+                command.push_str(arg);
+            }
+        }
+        let paths = match spec.risk.paths_param.as_deref().and_then(|p| params.get(p)) {
+            Some(JsonValue::String(p)) => vec![p.clone()],
+            Some(JsonValue::Array(a)) => a.iter().filter_map(|p| p.as_str().map(str::to_string)).collect(),
+            _ => vec![],
+        };
+        crate::gatekeeper::ProposedAction { command, writes: spec.risk.writes, paths, intent: None }
+    }
+
+    /// Run the `describe` handshake with a stdio server and keep its specs.
+    /// Returns how many tools it described.
+    pub async fn describe(&self, server: &str) -> anyhow::Result<usize> {
+        let bin = self.manifests.get(server)
+            .filter(|m| m.transport.as_deref() == Some("stdio"))
+            .and_then(|m| m.bin.clone())
+            .ok_or_else(|| anyhow::anyhow!("{} has no stdio server to describe", server))?;
+        let result = timeout(Duration::from_secs(10), invoke_mcp_stdio(&bin, foreman_mcp::DESCRIBE_TOOL, json!({}))).await
+            .map_err(|_| anyhow::anyhow!("describe timed out"))??;
+        let d: foreman_mcp::Describe = serde_json::from_value(result).map_err(|e| anyhow::anyhow!("bad describe answer: {}", e))?;
+        let n = d.tools.len();
+        self.described.write().insert(server.to_string(), d.tools);
+        Ok(n)
+    }
+
+    /// Run a tool, keeping track of the processes it starts. `proc.list` takes
+    /// `spawned: true` to list only those, and `proc.kill`/`proc.watch` on a
+    /// tracked pid are pinned to the tracked process. Callers gate the call
+    /// first, so `proc.kill` always goes out with `force`.
+    pub async fn invoke(&self, server: &str, tool: &str, mut params: JsonValue) -> anyhow::Result<JsonValue> {
+        if server == "proc" {
+            if let Some(obj) = params.as_object_mut() {
+                if tool == "list" && obj.remove("spawned").and_then(|v| v.as_bool()).unwrap_or(false) {
+                    obj.insert("pids".into(), json!(self.spawned().iter().map(|s| s.pid).collect::<Vec<_>>()));
+                }
+            }
+            if tool == "kill" || tool == "watch" {
+                if let (Some(owned), Some(obj)) = (self.owned(&params), params.as_object_mut()) {
+                    obj.entry("expect_start").or_insert(json!(owned.start_time));
+                }
+            }
+            if tool == "kill" {
+                if let Some(obj) = params.as_object_mut() { obj.insert("force".into(), json!(true)); }
+            }
+        }
+        let res = self.dispatch(server, tool, params).await;
+        if let Ok(v) = &res {
+            if matches!((server, tool), ("shell", "exec" | "shell_exec") | ("games" | "steam", "launch")) {
+                self.track(&format!("{}.{}", server, tool), v);
+            }
+        }
+        res
+    }
+
+    async fn dispatch(&self, server: &str, tool: &str, params: JsonValue) -> anyhow::Result<JsonValue> {
         // Back-compat: allow server-prefixed tool names like "shell_exec" via aliasing
         let tool_aliased: String = if server == "shell" {
             match tool {
@@ -76,6 +294,13 @@ impl ToolsManager {
             }
         } else { tool.to_string() };
         let tool = tool_aliased.as_str();
+        // A call can beat the startup handshake; describe now rather than refuse it
+        if self.manifests.get(server).map(|m| m.describe).unwrap_or(false) && !self.described.read().contains_key(server) {
+            if let Err(e) = self.describe(server).await {
+                tracing::warn!(server=%server, error=%e, "mcp describe failed");
+            }
+        }
+        self.validate(server, tool, &params)?;
         // Special-case shell.exec: enforce a strict whitelist before invoking MCP
         if server == "shell" && tool == "exec" {
             validate_shell_exec(&params)?;
@@ -129,15 +354,21 @@ impl ToolsManager {
         }
         match server {
             "shell" => invoke_shell(tool, params).await,
-            "fs" => invoke_fs(tool, params).await,
+            "fs" => invoke_fs(self.fs.clone(), tool, params).await,
             "proc" => invoke_proc(tool, params).await,
             "git" => invoke_git(tool, params).await,
             "patch" => invoke_patch(tool, params).await,
             "arxiv" => Err(anyhow::anyhow!("arxiv server not available (no stdio or spawn failed)")),
             "news" => invoke_news(tool, params).await,
-            "installer" => invoke_installer(tool, params).await,
+            "installer" => self.installer.invoke(tool, params).await,
             "steam" => invoke_steam(tool, params, self).await,
-            "project" => invoke_project(tool, params).await,
+            "project" => {
+                let res = invoke_project(tool, params).await;
+                if let (Ok(_), "init", Some(map)) = (&res, tool, self.system_map.as_ref()) {
+                    map.request_refresh("project.init");
+                }
+                res
+            }
             _ => {
                 if let Some(e) = stdio_err { Err(e) } else { Err(anyhow::anyhow!("unknown server")) }
             }
@@ -181,6 +412,16 @@ async fn invoke_mcp_stdio(bin: &str, tool: &str, params: JsonValue) -> anyhow::R
 impl ToolsManager {
     pub async fn autostart(&self) {
         for (name, man) in self.manifests.iter() {
+            if man.describe {
+                let name = name.clone();
+                let this = self.clone();
+                tokio::spawn(async move {
+                    match this.describe(&name).await {
+                        Ok(n) => tracing::info!(server=%name, tools=n, "mcp describe"),
+                        Err(e) => tracing::warn!(server=%name, error=%e, "mcp describe failed; using manifest"),
+                    }
+                });
+            }
             if man.autostart && man.transport.as_deref() == Some("stdio") {
                 if let Some(bin) = &man.bin {
                     let name = name.clone();
@@ -216,134 +457,27 @@ impl ToolsManager {
     }
 }
 
-// Build tool schemas for Realtime session.update (flat function schema form)
-pub fn realtime_tool_schemas(tm: &ToolsManager) -> Vec<serde_json::Value> {
+/// Model-facing view of every tool: `(function name, description, parameters)`.
+/// Tools without a declared input schema accept any object.
+fn function_defs(tm: &ToolsManager) -> Vec<(String, String, JsonValue)> {
     let mut out = vec![];
-    for (server, tools) in tm.list().into_iter() {
-        for t in tools {
+    for (server, specs) in tm.specs() {
+        for t in specs {
             // Tool names must match ^[a-zA-Z0-9_-]+$ in Realtime; use underscores
-            let name = format!("{}_{}", server, t).replace('-', "_");
-            let (desc, params): (String, serde_json::Value) = if server == "shell" && t == "exec" {
-                (
-                    "Execute a desktop command with strict policy. Usage: {\"cmd\":\"mgba-qt\",\"args\":[\"/home/kil/games/roms/<console>/<file>\"]} (GB/GBA). For Nintendo DS: {\"cmd\":\"/home/kil/games/emulators/melonDS-x86_64.AppImage\",\"args\":[]}. Optional: {\"wait\": false} to spawn and return a PID.".to_string(),
-                    serde_json::json!({
-                        "type": "object",
-                        "properties": {
-                            "cmd": {"type": "string", "description": "Program to run. 'mgba-qt' or absolute DS emulator path."},
-                            "args": {"type": "array", "items": {"type": "string"}, "description": "Arguments; for mgba-qt, one ROM path under /home/kil/games/roms"},
-                            "wait": {"type": "boolean", "description": "If false, spawn and return pid; defaults to true."}
-                        },
-                        "required": ["cmd"],
-                        "additionalProperties": false
-                    })
-                )
-            } else if server == "shell" && t == "which" {
-                (
-                    "Resolve a command name to a full path (PATH lookup).".to_string(),
-                    serde_json::json!({
-                        "type": "object",
-                        "properties": {"cmd": {"type": "string", "description": "Command name, e.g., 'mgba-qt'"}},
-                        "required": ["cmd"],
-                        "additionalProperties": false
-                    })
-                )
-            } else if server == "shell" && (t == "list_dir" || t == "read_file") {
-                let d = if t == "list_dir" { "List entries in a directory." } else { "Read a UTF-8 text file." };
-                (
-                    d.to_string(),
-                    serde_json::json!({
-                        "type": "object",
-                        "properties": {"path": {"type": "string", "description": "Filesystem path"}},
-                        "required": ["path"],
-                        "additionalProperties": false
-                    })
-                )
-            } else if server == "steam" && t == "installed" {
-                (
-                    "List installed Steam games by reading local Steam app manifests (~/.local/share/Steam/steamapps).".to_string(),
-                    serde_json::json!({
-                        "type": "object",
-                        "properties": {
-                            "root": {"type": "string", "description": "Optional override for steamapps directory"}
-                        },
-                        "additionalProperties": false
-                    })
-                )
-            } else if server == "steam" && t == "launch" {
-                (
-                    "Launch a Steam game (steam -applaunch APPID).".to_string(),
-                    serde_json::json!({
-                        "type": "object",
-                        "properties": {"appid": {"type": "string", "description": "Steam AppID (digits)"}},
-                        "required": ["appid"],
-                        "additionalProperties": false
-                    })
-                )
-            } else if server == "arxiv" && t == "search" {
-                (
-                    "Search arXiv. Provide a query and optional filters. Example: {\"query\":\"mixture-of-experts\",\"categories\":[\"cs.LG\"],\"from\":\"2025-09-01T00:00:00Z\",\"max_results\":25}".to_string(),
-                    serde_json::json!({
-                        "type": "object",
-                        "properties": {
-                            "query": {"type": "string", "description": "Search query (keywords)."},
-                            "categories": {"type": "array", "items": {"type": "string"}, "description": "Optional category codes, e.g., cs.LG, cs.AI."},
-                            "from": {"type": "string", "description": "Optional ISO-8601 UTC lower bound for updated date (e.g., 2025-09-01T00:00:00Z)."},
-                            "max_results": {"type": "integer", "minimum": 1, "maximum": 50, "description": "Limit results (1-50, default 25)."}
-                        },
-                        "required": ["query"],
-                        "additionalProperties": false
-                    })
-                )
-            } else if server == "arxiv" && t == "top" {
-                (
-                    "Top recent papers for a month (first N by latest update). Example: {\"month\":\"2025-09\",\"n\":5}".to_string(),
-                    serde_json::json!({
-                        "type": "object",
-                        "properties": {
-                            "month": {"type": "string", "description": "YYYY-MM month (defaults to current)."},
-                            "n": {"type": "integer", "minimum": 1, "maximum": 50, "description": "Number of items to return (default 5)."}
-                        },
-                        "additionalProperties": false
-                    })
-                )
-            } else if server == "arxiv" && t == "fetch_pdf" {
-                (
-                    "Download a paper PDF by arXiv ID. Example: {\"id\":\"2509.01234\"}".to_string(),
-                    serde_json::json!({
-                        "type": "object",
-                        "properties": {
-                            "id": {"type": "string", "description": "arXiv id (YYMM.NNNNN or arXiv:YYMM.NNNNN)."}
-                        },
-                        "required": ["id"],
-                        "additionalProperties": false
-                    })
-                )
-            } else if server == "arxiv" && t == "summarize" {
-                (
-                    "Summarize a paper by arXiv ID (not implemented; will return an error for now). Example: {\"id\":\"2509.01234\"}".to_string(),
-                    serde_json::json!({
-                        "type": "object",
-                        "properties": {
-                            "id": {"type": "string", "description": "arXiv id (YYMM.NNNNN)."}
-                        },
-                        "required": ["id"],
-                        "additionalProperties": false
-                    })
-                )
-            } else {
-                (
-                    format!("Call {}.{} via Foreman MCP", server, t),
-                    serde_json::json!({"type": "object", "properties": {}, "additionalProperties": true})
-                )
-            };
-            out.push(serde_json::json!({
-                "type": "function",
-                "name": name,
-                "description": desc,
-                "parameters": params
-            }));
+            let name = format!("{}_{}", server, t.name).replace('-', "_");
+            let desc = t.description.unwrap_or_else(|| format!("Call {}.{} via Foreman MCP", server, t.name));
+            let params = t.input_schema.unwrap_or_else(|| json!({"type": "object", "properties": {}, "additionalProperties": true}));
+            out.push((name, desc, params));
         }
     }
+    out
+}
+
+// Build tool schemas for Realtime session.update (flat function schema form)
+pub fn realtime_tool_schemas(tm: &ToolsManager) -> Vec<serde_json::Value> {
+    let mut out: Vec<JsonValue> = function_defs(tm).into_iter()
+        .map(|(name, desc, params)| json!({"type": "function", "name": name, "description": desc, "parameters": params}))
+        .collect();
     // Add synthetic end_call tool
     out.push(serde_json::json!({
         "type": "function",
@@ -354,6 +488,13 @@ pub fn realtime_tool_schemas(tm: &ToolsManager) -> Vec<serde_json::Value> {
     out
 }
 
+/// Tool definitions for Chat Completions (`tools` with nested `function`).
+pub fn chat_tool_defs(tm: &ToolsManager) -> Vec<serde_json::Value> {
+    function_defs(tm).into_iter()
+        .map(|(name, desc, params)| json!({"type": "function", "function": {"name": name, "description": desc, "parameters": params}}))
+        .collect()
+}
+
 async fn ping_server(bin: String, name: String) {
     let _ = ping_once(&bin).await.map_err(|e| tracing::warn!(server=%name, error=%e, "mcp autostart ping failed"));
 }
@@ -435,6 +576,18 @@ impl ToolsManager {
 
 fn truncate_err(s: &str) -> String { if s.len() > 60 { format!("{}…", &s[..60]) } else { s.to_string() } }
 
+/// A manifest `tools` entry: a bare name or a spec object.
+fn parse_tool_entry(server: &str, v: &JsonValue) -> Option<ToolSpec> {
+    match v {
+        JsonValue::String(name) => Some(ToolSpec::named(name.as_str())),
+        JsonValue::Object(_) => match serde_json::from_value::<ToolSpec>(v.clone()) {
+            Ok(spec) => Some(spec),
+            Err(e) => { tracing::warn!(server, error=%e, "tools.d: bad tool entry skipped"); None }
+        },
+        _ => None,
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 struct ShellArgsRule {
     #[serde(default)]
@@ -481,11 +634,14 @@ fn load_shell_rules() -> Vec<ShellRule> {
             }
         }
     }
-    // Built-in defaults if none provided
-    if rules.is_empty() {
-        rules.push(ShellRule { r#match: "mgba-qt".into(), args: Some(ShellArgsRule { count: Some(1), min_count: None, max_count: None, path_prefixes: Some(vec!["/home/kil/games/roms".into()]), starts_with_tokens: None, digits_at: None }) });
-        rules.push(ShellRule { r#match: "/home/kil/games/emulators/melonDS-x86_64.AppImage".into(), args: Some(ShellArgsRule { count: Some(0), min_count: None, max_count: None, path_prefixes: None, starts_with_tokens: None, digits_at: None }) });
-        rules.push(ShellRule { r#match: "melonDS-x86_64.AppImage".into(), args: Some(ShellArgsRule { count: Some(0), min_count: None, max_count: None, path_prefixes: None, starts_with_tokens: None, digits_at: None }) });
+    // Emulators and Steam: whatever the games registry launches
+    rules.extend(mcp_games::GamesConfig::load().shell_allowlist().into_iter().filter_map(|r| serde_json::from_value(r).ok()));
+    // `~/` in commands and path prefixes is the user's home
+    for r in rules.iter_mut() {
+        r.r#match = foreman_mcp::expand_home(&r.r#match).to_string_lossy().to_string();
+        if let Some(prefixes) = r.args.as_mut().and_then(|a| a.path_prefixes.as_mut()) {
+            for p in prefixes.iter_mut() { *p = foreman_mcp::expand_home(p).to_string_lossy().to_string(); }
+        }
     }
     let _ = SHELL_RULES.set(rules.clone());
     rules
@@ -543,6 +699,22 @@ fn validate_shell_exec(params: &JsonValue) -> anyhow::Result<()> {
 
 async fn invoke_shell(tool: &str, params: JsonValue) -> anyhow::Result<JsonValue> {
     match tool {
+        // Callers check the allowlist (validate_shell_exec) first
+        "exec" => {
+            let cmd = params.get("cmd").and_then(|v| v.as_str()).unwrap_or("");
+            let args: Vec<String> = params.get("args").and_then(|v| v.as_array()).map(|a| a.iter().filter_map(|x| x.as_str().map(str::to_string)).collect()).unwrap_or_default();
+            if params.get("wait").and_then(|v| v.as_bool()).unwrap_or(true) {
+                let out = Command::new(cmd).args(&args).output().await?;
+                return Ok(json!({ "ok": out.status.success(), "code": out.status.code(), "stdout": String::from_utf8_lossy(&out.stdout), "stderr": String::from_utf8_lossy(&out.stderr) }));
+            }
+            let mut child = Command::new(cmd).args(&args)
+                .stdin(std::process::Stdio::null()).stdout(std::process::Stdio::null()).stderr(std::process::Stdio::null())
+                .spawn()?;
+            let pid = child.id().unwrap_or(0);
+            // Reap in the background so it does not linger as a zombie
+            tokio::spawn(async move { let _ = child.wait().await; });
+            Ok(json!({ "ok": true, "pid": pid, "cmd": cmd, "args": args }))
+        }
         "list_dir" => {
             let path = params.get("path").and_then(|v| v.as_str()).unwrap_or(".");
             let mut entries: Vec<String> = vec![];
@@ -570,114 +742,18 @@ async fn invoke_shell(tool: &str, params: JsonValue) -> anyhow::Result<JsonValue
     }
 }
 
-async fn invoke_fs(tool: &str, params: JsonValue) -> anyhow::Result<JsonValue> {
-    match tool {
-        "stat" => {
-            let path = params.get("path").and_then(|v| v.as_str()).unwrap_or(".");
-            let meta = tokio::fs::metadata(path).await?;
-            let is_dir = meta.is_dir();
-            let is_file = meta.is_file();
-            let size = if is_file { Some(meta.len()) } else { None };
-            Ok(json!({ "is_dir": is_dir, "is_file": is_file, "size": size }))
-        }
-        "list" => {
-            let path = params.get("path").and_then(|v| v.as_str()).unwrap_or(".");
-            let mut entries: Vec<String> = vec![];
-            let mut dir = tokio::fs::read_dir(path).await?;
-            while let Ok(Some(ent)) = dir.next_entry().await {
-                if let Ok(name) = ent.file_name().into_string() { entries.push(name); }
-            }
-            entries.sort();
-            Ok(json!({ "entries": entries }))
-        }
-        "write_text" => {
-            let path = params.get("path").and_then(|v| v.as_str()).unwrap_or("");
-            let content = params.get("content").and_then(|v| v.as_str()).unwrap_or("");
-            let create_dirs = params.get("create_dirs").and_then(|v| v.as_bool()).unwrap_or(true);
-            if path.is_empty() { anyhow::bail!("path required"); }
-            if path.contains("..") { anyhow::bail!("invalid path"); }
-            let p = std::path::Path::new(path);
-            if create_dirs {
-                if let Some(parent) = p.parent() { tokio::fs::create_dir_all(parent).await.ok(); }
-            }
-            tokio::fs::write(p, content.as_bytes()).await?;
-            Ok(json!({"ok": true}))
-        }
-        "read" => {
-            let path = params.get("path").and_then(|v| v.as_str()).unwrap_or("");
-            let content = tokio::fs::read_to_string(path).await?;
-            Ok(json!({ "content": content }))
-        }
-        _ => Err(anyhow::anyhow!("unknown tool")),
-    }
+async fn invoke_fs(fs: Arc<mcp_fs::Fs>, tool: &str, params: JsonValue) -> anyhow::Result<JsonValue> {
+    // The fs server does plain blocking I/O (walks, searches, copies)
+    let tool = tool.to_string();
+    tokio::task::spawn_blocking(move || fs.call(&tool, &params)).await?
 }
 
-async fn invoke_proc(tool: &str, _params: JsonValue) -> anyhow::Result<JsonValue> {
-    match tool {
-        "list" => {
-            let mut pids: Vec<i32> = vec![];
-            let mut dir = tokio::fs::read_dir("/proc").await?;
-            while let Ok(Some(ent)) = dir.next_entry().await {
-                if let Ok(name) = ent.file_name().into_string() {
-                    if let Ok(pid) = name.parse::<i32>() { pids.push(pid); }
-                }
-            }
-            pids.sort();
-            Ok(json!({ "pids": pids }))
-        }
-        _ => Err(anyhow::anyhow!("unknown tool")),
-    }
+async fn invoke_proc(tool: &str, params: JsonValue) -> anyhow::Result<JsonValue> {
+    mcp_proc::call(tool, &params).await
 }
 
 async fn invoke_git(tool: &str, params: JsonValue) -> anyhow::Result<JsonValue> {
-    match tool {
-        "status" => {
-            let path = params.get("path").and_then(|v| v.as_str()).unwrap_or(".");
-            let git_dir = format!("{}/.git", path.trim_end_matches('/'));
-            let is_repo = tokio::fs::metadata(&git_dir).await.is_ok();
-            if !is_repo { return Ok(json!({ "repo": false })); }
-            let out = tokio::process::Command::new("git").arg("-C").arg(path).arg("status").arg("--porcelain").output().await?;
-            let ok = out.status.success();
-            let changed = String::from_utf8_lossy(&out.stdout).lines().count();
-            Ok(json!({ "repo": true, "ok": ok, "changed": changed }))
-        }
-        "branch" => {
-            let path = params.get("path").and_then(|v| v.as_str()).unwrap_or(".");
-            let name = params.get("name").and_then(|v| v.as_str()).unwrap_or("");
-            if name.is_empty() { anyhow::bail!("branch name required"); }
-            let out = tokio::process::Command::new("git")
-                .arg("-C").arg(path)
-                .arg("checkout").arg("-B").arg(name)
-                .output().await?;
-            let ok = out.status.success();
-            Ok(json!({"ok": ok}))
-        }
-        "add" => {
-            let path = params.get("path").and_then(|v| v.as_str()).unwrap_or(".");
-            let patterns = params.get("patterns").and_then(|v| v.as_array()).cloned().unwrap_or_else(|| vec![]);
-            let mut cmd = tokio::process::Command::new("git");
-            cmd.arg("-C").arg(path).arg("add");
-            if patterns.is_empty() { cmd.arg("-A"); } else {
-                for p in patterns { if let Some(s) = p.as_str() { cmd.arg(s); } }
-            }
-            let out = cmd.output().await?;
-            Ok(json!({"ok": out.status.success()}))
-        }
-        "commit" => {
-            let path = params.get("path").and_then(|v| v.as_str()).unwrap_or(".");
-            let msg = params.get("message").and_then(|v| v.as_str()).unwrap_or("");
-            if msg.is_empty() { anyhow::bail!("message required"); }
-            let out = tokio::process::Command::new("git")
-                .arg("-C").arg(path)
-                .arg("-c").arg("user.email=codex@example.com")
-                .arg("-c").arg("user.name=Codex")
-                .arg("commit").arg("-m").arg(msg)
-                .output().await?;
-            let ok = out.status.success();
-            Ok(json!({"ok": ok, "code": out.status.code()}))
-        }
-        _ => Err(anyhow::anyhow!("unknown tool")),
-    }
+    mcp_git::call(tool, &params).await
 }
 
 async fn invoke_patch(tool: &str, params: JsonValue) -> anyhow::Result<JsonValue> {
@@ -709,36 +785,17 @@ async fn invoke_patch(tool: &str, params: JsonValue) -> anyhow::Result<JsonValue
 async fn invoke_steam(tool: &str, params: JsonValue, tm: &ToolsManager) -> anyhow::Result<JsonValue> {
     match tool {
         "installed" => {
-            let mut out: Vec<(String, String)> = vec![]; // (appid, name)
-            let home = std::env::var("HOME").unwrap_or_else(|_| String::from(""));
-            let candidates = vec![
-                format!("{}/.local/share/Steam/steamapps", home),
-                format!("{}/.steam/steam/steamapps", home),
-                format!("{}/.var/app/com.valvesoftware.Steam/data/Steam/steamapps", home),
-            ];
-            for dir in candidates {
-                if let Ok(mut rd) = tokio::fs::read_dir(&dir).await {
-                    while let Ok(Some(ent)) = rd.next_entry().await {
-                        if let Ok(name) = ent.file_name().into_string() {
-                            if name.starts_with("appmanifest_") && name.ends_with(".acf") {
-                                if let Ok(content) = tokio::fs::read_to_string(ent.path()).await {
-                                    if let Some((id, title)) = parse_appmanifest(&content) {
-                                        out.push((id, title));
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-            out.sort_by(|a,b| a.1.cmp(&b.1));
-            Ok(json!({"games": out.into_iter().map(|(id,name)| json!({"appid": id, "name": name})).collect::<Vec<_>>()}))
+            let libraries = match params.get("root").and_then(|v| v.as_str()) {
+                Some(root) => mcp_games::steam::libraries(&[foreman_mcp::expand_home(root)]),
+                None => tm.games.config().steam_libraries(),
+            };
+            Ok(json!({"libraries": libraries, "games": mcp_games::steam::installed(&libraries)}))
         }
         "launch" => {
             // Proxy to shell.exec with validated args
             let appid_str: Option<String> = if let Some(s) = params.get("appid").and_then(|v| v.as_str()) { Some(s.to_string()) } else if let Some(n) = params.get("appid").and_then(|v| v.as_u64()) { Some(n.to_string()) } else { None };
             let appid = appid_str.ok_or_else(|| anyhow::anyhow!("appid required"))?;
-            let shell_params = json!({"cmd": "steam", "args": ["-applaunch", appid]});
+            let shell_params = json!({"cmd": tm.games.config().steam_bin(), "args": ["-applaunch", appid]});
             // Preflight against allowlist, then invoke shell directly to avoid async recursion issues
             validate_shell_exec(&shell_params)?;
             invoke_shell("exec", shell_params).await
@@ -747,38 +804,6 @@ async fn invoke_steam(tool: &str, params: JsonValue, tm: &ToolsManager) -> anyho
     }
 }
 
-fn parse_appmanifest(text: &str) -> Option<(String, String)> {
-    // Extremely lenient parse: find first appid and name tokens
-    let mut appid: Option<String> = None;
-    let mut name: Option<String> = None;
-    for line in text.lines() {
-        let l = line.trim();
-        if appid.is_none() {
-            if let Some(i) = l.find("\"appid\"") {
-                // expect: "appid"  "12345"
-                if let Some(q1) = l[i..].find('"') {
-                    if let Some(q2) = l[i+q1+1..].find('"') {
-                        let rest = &l[i+q1+1+q2+1..];
-                        if let Some(s) = rest.split('"').nth(1) { appid = Some(s.to_string()); }
-                    }
-                }
-            }
-        }
-        if name.is_none() {
-            if let Some(i) = l.find("\"name\"") {
-                if let Some(q1) = l[i..].find('"') {
-                    if let Some(q2) = l[i+q1+1..].find('"') {
-                        let rest = &l[i+q1+1+q2+1..];
-                        if let Some(s) = rest.split('"').nth(1) { name = Some(s.to_string()); }
-                    }
-                }
-            }
-        }
-        if appid.is_some() && name.is_some() { break; }
-    }
-    match (appid, name) { (Some(i), Some(n)) => Some((i,n)), _ => None }
-}
-
 async fn invoke_arxiv(tool: &str, params: JsonValue) -> anyhow::Result<JsonValue> {
     match tool {
         "search" => {
@@ -872,88 +897,3 @@ async fn invoke_project(tool: &str, params: JsonValue) -> anyhow::Result<JsonVal
         _ => Err(anyhow::anyhow!("unknown tool")),
     }
 }
-
-// --- Installer plan store and tools (in-process stub) ---
-#[derive(Clone, Debug)]
-struct InstallerPlan {
-    id: String,
-    manager: String,
-    pkg: String,
-    cmds: Vec<String>,
-}
-
-static mut INSTALLER_PLANS: Option<std::sync::Mutex<HashMap<String, InstallerPlan>>> = None;
-
-fn plans() -> &'static std::sync::Mutex<HashMap<String, InstallerPlan>> {
-    // Simple lazy init; safe here for single-process use
-    unsafe {
-        if INSTALLER_PLANS.is_none() {
-            INSTALLER_PLANS = Some(std::sync::Mutex::new(HashMap::new()));
-        }
-        INSTALLER_PLANS.as_ref().unwrap()
-    }
-}
-
-fn detect_manager(pkg: &str, provided: Option<&str>) -> String {
-    if let Some(m) = provided { return m.to_string(); }
-    if pkg.ends_with(".whl") || pkg.contains("/") { return "pip".into(); }
-    // default to apt on Linux; this is a stub heuristic
-    if cfg!(target_os = "linux") { "apt".into() } else { "cargo".into() }
-}
-
-fn build_commands(manager: &str, pkg: &str) -> Vec<String> {
-    match manager {
-        "apt" => vec![format!("sudo apt-get update"), format!("sudo apt-get install -y {}", pkg)],
-        "snap" => vec![format!("sudo snap install {}", pkg)],
-        "flatpak" => vec![format!("flatpak install -y {}", pkg)],
-        "pip" => vec![format!("pip install {}", pkg)],
-        "cargo" => vec![format!("cargo install {}", pkg)],
-        _ => vec![format!("echo install {} via {} (unknown manager)", pkg, manager)],
-    }
-}
-
-async fn invoke_installer(tool: &str, params: JsonValue) -> anyhow::Result<JsonValue> {
-    match tool {
-        "plan_install" => {
-            let pkg = params.get("pkg").and_then(|v| v.as_str()).unwrap_or("");
-            if pkg.is_empty() { anyhow::bail!("pkg required"); }
-            let manager = detect_manager(pkg, params.get("manager").and_then(|v| v.as_str()));
-            let cmds = build_commands(&manager, pkg);
-            let id = Uuid::new_v4().to_string();
-            let plan = InstallerPlan { id: id.clone(), manager: manager.clone(), pkg: pkg.to_string(), cmds: cmds.clone() };
-            plans().lock().unwrap().insert(id.clone(), plan);
-            Ok(json!({"plan_id": id, "manager": manager, "pkg": pkg, "commands": cmds }))
-        }
-        "explain_install" => {
-            let pid = params.get("plan_id").and_then(|v| v.as_str()).unwrap_or("");
-            let g = plans().lock().unwrap();
-            let p = g.get(pid).ok_or_else(|| anyhow::anyhow!("unknown plan_id"))?;
-            Ok(json!({
-                "plan_id": p.id,
-                "explain": format!("Install {} via {}. Source: distro/vendor repos where applicable; safety: dry-run first.", p.pkg, p.manager),
-                "commands": p.cmds,
-            }))
-        }
-        "dry_run" => {
-            let pid = params.get("plan_id").and_then(|v| v.as_str()).unwrap_or("");
-            let g = plans().lock().unwrap();
-            let p = g.get(pid).ok_or_else(|| anyhow::anyhow!("unknown plan_id"))?;
-            Ok(json!({"plan_id": p.id, "dry_run": true, "commands": p.cmds}))
-        }
-        "apply_install" => {
-            // Token validation is enforced at API layer; here we just report success without executing.
-            let pid = params.get("plan_id").and_then(|v| v.as_str()).unwrap_or("");
-            let g = plans().lock().unwrap();
-            let p = g.get(pid).ok_or_else(|| anyhow::anyhow!("unknown plan_id"))?;
-            Ok(json!({"plan_id": p.id, "applied": false, "note": "execution stubbed in-core; external process integration later"}))
-        }
-        _ => Err(anyhow::anyhow!("unknown tool")),
-    }
-}
-
-// Helper for approval prompt to fetch installer plan commands
-pub fn installer_plan_commands(plan_id: &str) -> Option<Vec<String>> {
-    let g = plans().lock().ok()?;
-    let p = g.get(plan_id)?;
-    Some(p.cmds.clone())
-}
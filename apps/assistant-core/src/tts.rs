@@ -0,0 +1,413 @@
+//! Local text-to-speech: engines, sentence chunking for streamed text, and a
+//! prioritised playback queue in front of the audio device.
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use parking_lot::{Condvar, Mutex};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Synthesized audio: PCM16 mono.
+#[derive(Debug, Clone, Default)]
+pub struct Speech {
+    pub pcm: Vec<i16>,
+    pub sample_rate: u32,
+}
+
+impl Speech {
+    pub fn duration_ms(&self) -> u64 { self.pcm.len() as u64 * 1000 / self.sample_rate.max(1) as u64 }
+}
+
+#[async_trait]
+pub trait TtsEngine: Send + Sync {
+    fn name(&self) -> &'static str;
+    async fn synthesize(&self, text: &str) -> Result<Speech>;
+}
+
+/// Shell command reading text on stdin and writing raw PCM16LE mono to stdout,
+/// e.g. `piper --model en_US-libritts-high --output-raw`.
+pub struct CommandTts {
+    pub command: String,
+    pub sample_rate: u32,
+}
+
+#[async_trait]
+impl TtsEngine for CommandTts {
+    fn name(&self) -> &'static str { "command" }
+
+    async fn synthesize(&self, text: &str) -> Result<Speech> {
+        use tokio::io::AsyncWriteExt;
+        let mut child = tokio::process::Command::new("sh").arg("-c").arg(&self.command)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes()).await?;
+            stdin.write_all(b"\n").await?;
+        }
+        let out = child.wait_with_output().await?;
+        if !out.status.success() { anyhow::bail!("tts command exited with {}", out.status); }
+        Ok(Speech { pcm: out.stdout.chunks_exact(2).map(|c| i16::from_le_bytes([c[0], c[1]])).collect(), sample_rate: self.sample_rate })
+    }
+}
+
+/// Canned clips for tests and demos: `<dir>/<slug>.wav` for the text (lowercase
+/// alphanumerics joined by `_`), else `<dir>/default.wav`.
+pub struct WavFileTts {
+    pub dir: PathBuf,
+}
+
+#[async_trait]
+impl TtsEngine for WavFileTts {
+    fn name(&self) -> &'static str { "wav" }
+
+    async fn synthesize(&self, text: &str) -> Result<Speech> {
+        let exact = self.dir.join(format!("{}.wav", slug(text)));
+        let path = if exact.exists() { exact } else { self.dir.join("default.wav") };
+        read_wav(&path)
+    }
+}
+
+pub fn slug(text: &str) -> String {
+    text.to_lowercase().split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).collect::<Vec<_>>().join("_")
+}
+
+/// Read a PCM16 WAV; multi-channel files are downmixed to mono.
+pub fn read_wav(path: &Path) -> Result<Speech> {
+    let bytes = std::fs::read(path).with_context(|| format!("read {}", path.display()))?;
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" { anyhow::bail!("{}: not a WAV file", path.display()); }
+    let (mut channels, mut sample_rate, mut bits) = (0u16, 0u32, 0u16);
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let id = &bytes[pos..pos + 4];
+        let len = u32::from_le_bytes([bytes[pos + 4], bytes[pos + 5], bytes[pos + 6], bytes[pos + 7]]) as usize;
+        let body = &bytes[pos + 8..(pos + 8 + len).min(bytes.len())];
+        match id {
+            b"fmt " if body.len() >= 16 => {
+                channels = u16::from_le_bytes([body[2], body[3]]);
+                sample_rate = u32::from_le_bytes([body[4], body[5], body[6], body[7]]);
+                bits = u16::from_le_bytes([body[14], body[15]]);
+            }
+            b"data" => {
+                if bits != 16 || channels == 0 { anyhow::bail!("{}: only PCM16 WAV is supported", path.display()); }
+                let samples: Vec<i16> = body.chunks_exact(2).map(|c| i16::from_le_bytes([c[0], c[1]])).collect();
+                let pcm = samples.chunks(channels as usize).map(|f| (f.iter().map(|&s| s as i32).sum::<i32>() / f.len() as i32) as i16).collect();
+                return Ok(Speech { pcm, sample_rate });
+            }
+            _ => {}
+        }
+        pos += 8 + len + (len & 1);
+    }
+    anyhow::bail!("{}: no data chunk", path.display())
+}
+
+/// Resolved `[voice] tts` settings ([`crate::config::TtsConfig`]).
+#[derive(Debug, Clone, Default)]
+pub struct TtsOptions {
+    /// "piper", "command", "wav" or "none".
+    pub engine: Option<String>,
+    pub voice: Option<String>,
+    /// Command line for piper/command engines (default for piper: `piper --model <voice> --output-raw`).
+    pub command: Option<String>,
+    pub sample_rate: Option<u32>,
+    /// Clip directory for the wav engine.
+    pub dir: Option<String>,
+    /// Speak `/api/chat/stream` replies unless the request says otherwise.
+    pub speak_chat: bool,
+}
+
+impl TtsOptions {
+    pub fn from_config(cfg: Option<&crate::config::TtsConfig>) -> Self {
+        let Some(c) = cfg else { return Self::default() };
+        Self {
+            engine: c.engine.clone(),
+            voice: c.voice.clone(),
+            command: c.command.clone(),
+            sample_rate: c.sample_rate,
+            dir: c.dir.clone(),
+            speak_chat: c.speak_chat.unwrap_or(false),
+        }
+    }
+
+    /// Shell command of the piper/command engines.
+    pub fn command_line(&self) -> Option<String> {
+        match self.engine.as_deref()? {
+            "piper" => self.command.clone().or_else(|| self.voice.as_ref().map(|v| format!("piper --model {} --output-raw", v))),
+            "command" => self.command.clone(),
+            _ => None,
+        }
+    }
+
+    pub fn command_sample_rate(&self) -> u32 { self.sample_rate.unwrap_or(22_050) }
+
+    /// None when TTS is not configured.
+    pub fn build_engine(&self) -> Option<Arc<dyn TtsEngine>> {
+        match self.engine.as_deref()? {
+            "piper" | "command" => Some(Arc::new(CommandTts { command: self.command_line()?, sample_rate: self.command_sample_rate() })),
+            "wav" => Some(Arc::new(WavFileTts { dir: PathBuf::from(self.dir.clone()?) })),
+            _ => None,
+        }
+    }
+}
+
+/// Cuts streamed text into sentences so speech can start before the reply is complete.
+#[derive(Default)]
+pub struct SentenceChunker {
+    buf: String,
+}
+
+impl SentenceChunker {
+    /// Complete sentences in the text so far.
+    pub fn push(&mut self, piece: &str) -> Vec<String> {
+        self.buf.push_str(piece);
+        let mut out = vec![];
+        while let Some(end) = sentence_end(&self.buf) {
+            let rest = self.buf.split_off(end);
+            let sentence = std::mem::replace(&mut self.buf, rest.trim_start().to_string());
+            let sentence = sentence.trim();
+            if !sentence.is_empty() { out.push(sentence.to_string()); }
+        }
+        out
+    }
+
+    /// Whatever is left once the stream ends.
+    pub fn finish(&mut self) -> Option<String> {
+        let rest = std::mem::take(&mut self.buf);
+        let rest = rest.trim();
+        (!rest.is_empty()).then(|| rest.to_string())
+    }
+}
+
+/// Byte offset just past the first sentence terminator that is followed by whitespace.
+fn sentence_end(s: &str) -> Option<usize> {
+    const ABBREVIATIONS: [&str; 8] = ["mr", "mrs", "ms", "dr", "e.g", "i.e", "etc", "vs"];
+    let mut chars = s.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == '\n' { return Some(i + 1); }
+        if !matches!(c, '.' | '!' | '?' | '…' | ';' | ':') { continue; }
+        // Terminator runs ("?!", "...") and closing quotes stay with the sentence
+        let mut end = i + c.len_utf8();
+        while let Some(&(j, n)) = chars.peek() {
+            if matches!(n, '.' | '!' | '?' | '"' | '\'' | ')' | '”') { end = j + n.len_utf8(); chars.next(); } else { break; }
+        }
+        // At the end of the buffer: wait for more text ("3." may become "3.14")
+        let &(_, next) = chars.peek()?;
+        if !next.is_whitespace() { continue; }
+        if c == '.' {
+            let word = s[..i].rsplit(|ch: char| ch.is_whitespace()).next().unwrap_or("").to_lowercase();
+            if ABBREVIATIONS.contains(&word.as_str()) || (word.len() == 1 && word.chars().all(char::is_alphabetic)) { continue; }
+        }
+        return Some(end);
+    }
+    None
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    /// Cuts off whatever is playing and goes first.
+    Alert,
+}
+
+/// Where the queue plays audio. Lives on the player thread, so it need not be Send
+/// (cpal streams are not).
+pub trait PcmSink {
+    fn push_pcm(&self, pcm: &[i16], sample_rate: u32);
+    /// Drop audio already handed to the device.
+    fn flush(&self);
+}
+
+struct Queued {
+    id: u64,
+    priority: Priority,
+    speech: Speech,
+}
+
+#[derive(Default)]
+struct QueueState {
+    items: Vec<Queued>,
+    next_id: u64,
+    playing: Option<(u64, Priority)>,
+    interrupt: bool,
+    closed: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueStatus {
+    pub queued: usize,
+    pub playing: Option<u64>,
+}
+
+/// Playback queue: highest priority first, FIFO within a priority. Each clip is
+/// paced at its real duration so later, higher-priority clips can overtake queued ones.
+#[derive(Clone)]
+pub struct SpeechQueue {
+    inner: Arc<(Mutex<QueueState>, Condvar)>,
+}
+
+impl SpeechQueue {
+    /// Start the player thread; `open_sink` runs on it.
+    pub fn spawn<S, F>(open_sink: F) -> Self
+    where
+        S: PcmSink + 'static,
+        F: FnOnce() -> Result<S> + Send + 'static,
+    {
+        let q = Self { inner: Arc::new((Mutex::new(QueueState::default()), Condvar::new())) };
+        let inner = q.inner.clone();
+        std::thread::spawn(move || {
+            let sink = match open_sink() {
+                Ok(s) => s,
+                Err(e) => { tracing::warn!("tts: playback unavailable: {}", e); inner.0.lock().closed = true; return; }
+            };
+            Self::run(&inner, &sink);
+        });
+        q
+    }
+
+    fn run(inner: &(Mutex<QueueState>, Condvar), sink: &dyn PcmSink) {
+        let (lock, cv) = inner;
+        loop {
+            let item = {
+                let mut st = lock.lock();
+                while st.items.is_empty() && !st.closed { cv.wait(&mut st); }
+                if st.closed { return; }
+                // Highest priority, then oldest
+                let idx = (0..st.items.len()).max_by_key(|&i| (st.items[i].priority, std::cmp::Reverse(st.items[i].id))).unwrap_or(0);
+                let item = st.items.remove(idx);
+                st.playing = Some((item.id, item.priority));
+                st.interrupt = false;
+                item
+            };
+            sink.push_pcm(&item.speech.pcm, item.speech.sample_rate);
+            let deadline = std::time::Instant::now() + std::time::Duration::from_millis(item.speech.duration_ms());
+            let mut st = lock.lock();
+            while !st.interrupt && !st.closed {
+                if cv.wait_until(&mut st, deadline).timed_out() { break; }
+            }
+            if st.interrupt { sink.flush(); }
+            st.playing = None;
+            if st.closed { return; }
+        }
+    }
+
+    pub fn enqueue(&self, speech: Speech, priority: Priority) -> u64 {
+        let (lock, cv) = &*self.inner;
+        let mut st = lock.lock();
+        st.next_id += 1;
+        let id = st.next_id;
+        if priority == Priority::Alert && st.playing.map(|(_, p)| p < Priority::Alert).unwrap_or(false) { st.interrupt = true; }
+        st.items.push(Queued { id, priority, speech });
+        cv.notify_all();
+        id
+    }
+
+    /// Drop everything queued and stop the current clip.
+    pub fn clear(&self) {
+        let (lock, cv) = &*self.inner;
+        let mut st = lock.lock();
+        st.items.clear();
+        if st.playing.is_some() { st.interrupt = true; }
+        cv.notify_all();
+    }
+
+    pub fn status(&self) -> QueueStatus {
+        let st = self.inner.0.lock();
+        QueueStatus { queued: st.items.len(), playing: st.playing.map(|(id, _)| id) }
+    }
+
+    /// False once the player thread has stopped (sink failed to open or `close`).
+    pub fn is_open(&self) -> bool { !self.inner.0.lock().closed }
+
+    pub fn close(&self) {
+        let (lock, cv) = &*self.inner;
+        lock.lock().closed = true;
+        cv.notify_all();
+    }
+}
+
+/// Engine plus (optional) playback, shared by the API and chat streaming.
+#[derive(Clone, Default)]
+pub struct TtsService {
+    pub engine: Option<Arc<dyn TtsEngine>>,
+    pub queue: Option<SpeechQueue>,
+    pub speak_chat: bool,
+}
+
+impl TtsService {
+    pub fn new(engine: Option<Arc<dyn TtsEngine>>, queue: Option<SpeechQueue>, speak_chat: bool) -> Self {
+        Self { engine, queue, speak_chat }
+    }
+
+    fn parts(&self) -> Option<(Arc<dyn TtsEngine>, SpeechQueue)> {
+        Some((self.engine.clone()?, self.queue.clone().filter(|q| q.is_open())?))
+    }
+
+    /// Synthesize sentence by sentence and queue each as soon as it is ready.
+    /// Returns the sentences, or None without an engine or playback.
+    pub fn speak(&self, text: &str, priority: Priority) -> Option<Vec<String>> {
+        let (engine, queue) = self.parts()?;
+        let mut chunker = SentenceChunker::default();
+        let mut sentences = chunker.push(text);
+        sentences.extend(chunker.finish());
+        let todo = sentences.clone();
+        tokio::spawn(async move {
+            for s in todo { play_sentence(engine.as_ref(), &queue, &s, priority).await; }
+        });
+        Some(sentences)
+    }
+
+    /// Feed streamed tokens; complete sentences are spoken in order while the rest streams.
+    pub fn stream(&self, priority: Priority) -> Option<SpeechStream> {
+        let (engine, queue) = self.parts()?;
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+        tokio::spawn(async move {
+            while let Some(s) = rx.recv().await { play_sentence(engine.as_ref(), &queue, &s, priority).await; }
+        });
+        Some(SpeechStream { chunker: SentenceChunker::default(), tx })
+    }
+}
+
+async fn play_sentence(engine: &dyn TtsEngine, queue: &SpeechQueue, sentence: &str, priority: Priority) {
+    match engine.synthesize(sentence).await {
+        Ok(speech) if !speech.pcm.is_empty() => { queue.enqueue(speech, priority); }
+        Ok(_) => {}
+        Err(e) => tracing::warn!("tts({}): {}", engine.name(), e),
+    }
+}
+
+pub struct SpeechStream {
+    chunker: SentenceChunker,
+    tx: tokio::sync::mpsc::UnboundedSender<String>,
+}
+
+impl SpeechStream {
+    pub fn push(&mut self, piece: &str) {
+        for s in self.chunker.push(piece) { let _ = self.tx.send(s); }
+    }
+
+    pub fn finish(mut self) {
+        if let Some(s) = self.chunker.finish() { let _ = self.tx.send(s); }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SentenceChunker;
+
+    #[test]
+    fn chunks_streamed_tokens_into_sentences() {
+        let mut c = SentenceChunker::default();
+        let mut out = vec![];
+        for piece in ["Hello", " there.", " Dr. Smith is", " in, e.g. today", "! Pi is 3.", "14 roughly", "...", " Done?", "\"", " tail"] {
+            out.extend(c.push(piece));
+        }
+        out.extend(c.finish());
+        assert_eq!(out, vec!["Hello there.", "Dr. Smith is in, e.g. today!", "Pi is 3.14 roughly...", "Done?\"", "tail"]);
+    }
+}
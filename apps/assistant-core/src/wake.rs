@@ -1,15 +1,12 @@
 use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use std::sync::Arc;
 
-#[cfg(feature = "realtime-audio")]
-use std::collections::VecDeque;
-
 #[cfg(feature = "realtime-audio")]
 use tokio::sync::mpsc;
 #[cfg(feature = "realtime-audio")]
 use std::sync::atomic::{AtomicBool, Ordering};
-#[cfg(feature = "realtime-audio")]
-use std::sync::{Arc as StdArc, Mutex as StdMutex};
 
 fn wake_log(line: impl AsRef<str>) {
     let line = line.as_ref();
@@ -28,6 +25,237 @@ pub struct WakeOptions {
     pub vad_sensitivity: f32, // 0.0..1.0 higher is more sensitive
     pub min_speech_ms: u32,
     pub refractory_ms: u64,
+    /// Detector; `None` uses the keyword spotter when a phrase is enrolled, else STT matching.
+    pub engine: Option<WakeEngine>,
+    /// Enrolled keyword-spotter model (`/api/wake/enroll` writes it).
+    pub kws_model: PathBuf,
+    pub kws_sensitivity: f32,
+    pub porcupine: Option<crate::config::PorcupineConfig>,
+    /// Recognizer for the `stt` engine.
+    pub stt: crate::stt::SttOptions,
+    /// Session settings for calls started by the wake word (unset fields use the realtime defaults).
+    pub realtime_model: Option<String>,
+    pub realtime_voice: Option<String>,
+    pub realtime_endpoint: Option<String>,
+    /// Start realtime sessions in full-duplex (barge-in) mode.
+    pub full_duplex: bool,
+    pub aec: Option<bool>,
+    pub record_sessions: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WakeEngine {
+    /// Built-in MFCC + DTW keyword spotter (offline, enrolled from recordings).
+    Kws,
+    /// Picovoice Porcupine (`wake-porcupine` feature, needs an access key).
+    Porcupine,
+    /// Energy VAD, then STT of each utterance and a fuzzy phrase match.
+    Stt,
+}
+
+impl WakeEngine {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "kws" | "dtw" | "builtin" => Some(Self::Kws),
+            "porcupine" => Some(Self::Porcupine),
+            "stt" | "stt-match" | "stt_match" => Some(Self::Stt),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self { Self::Kws => "kws", Self::Porcupine => "porcupine", Self::Stt => "stt" }
+    }
+}
+
+/// A wake-word engine fed with 16 kHz mono frames of any length.
+pub trait WakeDetector: Send {
+    fn name(&self) -> &'static str;
+    /// True when the wake phrase was heard; detectors keep their own buffering.
+    fn process(&mut self, frame: &[i16]) -> anyhow::Result<bool>;
+    /// Transcript behind the last hit, for engines that recognise words.
+    fn heard(&mut self) -> Option<String> { None }
+}
+
+impl WakeDetector for crate::wake_kws::KwsDetector {
+    fn name(&self) -> &'static str { "kws" }
+
+    fn process(&mut self, frame: &[i16]) -> anyhow::Result<bool> { Ok(self.push(frame)) }
+}
+
+/// Energy VAD segments utterances; each one is streamed to STT in the background and
+/// partial and final hypotheses are fuzzy-matched against the phrase, so a hit can
+/// land before the speaker stops. Results surface on a later frame.
+pub struct SttMatchDetector {
+    phrase: String,
+    threshold: f32,
+    min_speech_ms: u32,
+    stt: crate::stt::SttOptions,
+    in_speech: bool,
+    speech_ms: u32,
+    silence_ms: u32,
+    /// Frames of the utterance in progress, consumed by its recognizer task.
+    utterance: Option<tokio::sync::mpsc::UnboundedSender<Vec<i16>>>,
+    hit: Arc<std::sync::atomic::AtomicBool>,
+    heard: Arc<parking_lot::Mutex<Option<String>>>,
+}
+
+impl SttMatchDetector {
+    pub fn new(opts: &WakeOptions) -> Self {
+        // Dynamic energy threshold based on sensitivity (0..1)
+        let threshold = 0.005 + (0.025 * opts.vad_sensitivity.clamp(0.0, 1.0));
+        wake_log(format!("wake: VAD threshold set to {:.3}", threshold));
+        Self {
+            phrase: opts.phrase.clone(), threshold, min_speech_ms: opts.min_speech_ms, stt: opts.stt.clone(),
+            in_speech: false, speech_ms: 0, silence_ms: 0, utterance: None, hit: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            heard: Arc::new(parking_lot::Mutex::new(None)),
+        }
+    }
+
+    fn begin_utterance(&mut self) -> tokio::sync::mpsc::UnboundedSender<Vec<i16>> {
+        let (tx, mut frames) = tokio::sync::mpsc::unbounded_channel::<Vec<i16>>();
+        let (phrase, opts, hit, heard) = (self.phrase.clone(), self.stt.clone(), self.hit.clone(), self.heard.clone());
+        let min_samples = self.min_speech_ms as usize * 16;
+        tokio::spawn(async move {
+            let t0 = std::time::Instant::now();
+            let (mut audio, mut events) = match crate::stt::open_stream(&opts, 16000).await {
+                Ok(st) => st.split(),
+                Err(e) => { wake_log(format!("wake: stt unavailable: {}", e)); return; }
+            };
+            let mut samples = 0usize;
+            let mut matched = false;
+            loop {
+                tokio::select! {
+                    f = frames.recv(), if audio.is_some() => match f {
+                        Some(f) => { samples += f.len(); if let Some(a) = audio.as_ref() { let _ = a.send(f); } }
+                        None => audio = None, // end of utterance
+                    },
+                    ev = events.recv() => match ev {
+                        Some(crate::stt::SttEvent::Partial { text }) => {
+                            if !matched && matches_wake(&text, &phrase) {
+                                matched = true;
+                                *heard.lock() = Some(text.clone());
+                                hit.store(true, std::sync::atomic::Ordering::SeqCst);
+                                wake_log(format!("wake: partial=\"{}\" matched ({} ms)", text, t0.elapsed().as_millis()));
+                            }
+                        }
+                        Some(crate::stt::SttEvent::Final { text }) => {
+                            wake_log(format!("wake: transcript=\"{}\" ({} ms)", text, t0.elapsed().as_millis()));
+                            if !matched && samples >= min_samples && matches_wake(&text, &phrase) {
+                                *heard.lock() = Some(text);
+                                hit.store(true, std::sync::atomic::Ordering::SeqCst);
+                            }
+                            break;
+                        }
+                        Some(crate::stt::SttEvent::Error { message }) => { wake_log(format!("wake: stt error: {}", message)); break; }
+                        None => break,
+                    },
+                }
+            }
+        });
+        tx
+    }
+}
+
+impl WakeDetector for SttMatchDetector {
+    fn name(&self) -> &'static str { "stt" }
+
+    fn process(&mut self, frame: &[i16]) -> anyhow::Result<bool> {
+        let frame_ms = (frame.len() as u32 * 1000) / 16_000;
+        let energy: f32 = frame.iter().map(|s| s.abs() as f32 / 32768.0).sum::<f32>() / (frame.len().max(1) as f32);
+        if energy > self.threshold {
+            if self.utterance.is_none() { self.utterance = Some(self.begin_utterance()); }
+            self.in_speech = true; self.speech_ms += frame_ms; self.silence_ms = 0;
+        } else {
+            // accumulate silence, require at least 150ms to close speech
+            self.silence_ms += frame_ms;
+            if self.in_speech && self.silence_ms >= 150 {
+                wake_log(format!("wake: speech ended ({} ms)", self.speech_ms));
+                self.in_speech = false; self.speech_ms = 0; self.silence_ms = 0;
+                self.utterance = None;
+            }
+        }
+        // Trailing quiet frames still belong to the utterance
+        if let Some(tx) = self.utterance.as_ref() { let _ = tx.send(frame.to_vec()); }
+        Ok(self.hit.swap(false, std::sync::atomic::Ordering::SeqCst))
+    }
+
+    fn heard(&mut self) -> Option<String> { self.heard.lock().take() }
+}
+
+impl WakeOptions {
+    /// Engine in effect after applying the `None` fallback.
+    pub fn resolved_engine(&self) -> WakeEngine {
+        self.engine.unwrap_or(if self.kws_model.exists() { WakeEngine::Kws } else { WakeEngine::Stt })
+    }
+
+    /// Options for the realtime session a detection starts.
+    pub fn session_options(&self) -> crate::realtime::RealtimeOptions {
+        crate::realtime::RealtimeOptions {
+            model: self.realtime_model.clone(),
+            voice: self.realtime_voice.clone(),
+            audio: Some(crate::realtime::RealtimeAudioOpts { in_sr: Some(16000), out_format: Some("pcm16".into()), full_duplex: Some(self.full_duplex), aec: self.aec, ..Default::default() }),
+            instructions: None,
+            endpoint: self.realtime_endpoint.clone(),
+            transport: None,
+            record: Some(self.record_sessions),
+        }
+    }
+}
+
+/// Build the configured detector.
+pub fn build_detector(opts: &WakeOptions) -> anyhow::Result<Box<dyn WakeDetector>> {
+    match opts.resolved_engine() {
+        WakeEngine::Kws => {
+            let model = crate::wake_kws::KeywordModel::load(&opts.kws_model)
+                .map_err(|e| anyhow::anyhow!("no enrolled wake phrase ({}); POST /api/wake/enroll first", e))?;
+            let det = crate::wake_kws::KwsDetector::new(&model, opts.kws_sensitivity);
+            wake_log(format!("wake: keyword spotter ready (phrase=\"{}\", templates={}, threshold={:.2})", model.phrase, model.templates.len(), det.threshold()));
+            Ok(Box::new(det))
+        }
+        WakeEngine::Porcupine => {
+            #[cfg(feature = "wake-porcupine")]
+            {
+                let det = crate::wake_porcupine::PorcupineDetector::new(crate::wake_porcupine::PorcupineOpts::from_config(opts.porcupine.as_ref())?)?;
+                wake_log(format!("wake: Porcupine ready (sr={}Hz, frame_length={})", det.sample_rate, det.frame_length));
+                Ok(Box::new(det))
+            }
+            #[cfg(not(feature = "wake-porcupine"))]
+            anyhow::bail!("wake engine porcupine needs a build with the wake-porcupine feature")
+        }
+        WakeEngine::Stt => Ok(Box::new(SttMatchDetector::new(opts))),
+    }
+}
+
+/// Capture `count` enrollment takes of `seconds` each from the default microphone.
+#[cfg(feature = "realtime-audio")]
+pub async fn record_takes(count: usize, seconds: f32) -> anyhow::Result<Vec<Vec<i16>>> {
+    let want = (seconds.clamp(0.5, 10.0) * 16_000.0) as usize;
+    let mut takes = Vec::with_capacity(count);
+    for i in 0..count {
+        wake_log(format!("wake: enrollment take {}/{} — say the phrase", i + 1, count));
+        let (tx, mut rx) = mpsc::channel::<Vec<i16>>(64);
+        let (ready_tx, ready_rx) = tokio::sync::oneshot::channel::<anyhow::Result<()>>();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_for_thread = stop.clone();
+        std::thread::spawn(move || match crate::realtime_audio::start_capture(16000, 30, tx) {
+            Ok(_cap) => {
+                let _ = ready_tx.send(Ok(()));
+                while !stop_for_thread.load(Ordering::SeqCst) { std::thread::sleep(std::time::Duration::from_millis(20)); }
+            }
+            Err(e) => { let _ = ready_tx.send(Err(e)); }
+        });
+        ready_rx.await.map_err(|_| anyhow::anyhow!("capture thread exited"))??;
+        let mut pcm = Vec::with_capacity(want);
+        while pcm.len() < want {
+            match rx.recv().await { Some(f) => pcm.extend_from_slice(&f), None => break }
+        }
+        stop.store(true, Ordering::SeqCst);
+        pcm.truncate(want);
+        takes.push(pcm);
+    }
+    Ok(takes)
 }
 
 #[derive(Clone)]
@@ -37,18 +265,24 @@ pub struct WakeSentinel {
 
 struct Inner {
     opts: WakeOptions,
+    /// Voice-note commands after the wake word (`[voice] notes.wake_commands`).
+    notes: Option<crate::notes::Notes>,
     active: bool,
     stop: Option<tokio::sync::oneshot::Sender<()>>,
 }
 
 impl Default for WakeOptions {
     fn default() -> Self {
-        Self { phrase: "hey vim".into(), enabled: true, vad_sensitivity: 0.5, min_speech_ms: 400, refractory_ms: 3000 }
+        Self {
+            phrase: "hey vim".into(), enabled: true, vad_sensitivity: 0.5, min_speech_ms: 400, refractory_ms: 3000,
+            engine: None, kws_model: PathBuf::from("storage/wake/kws.json"), kws_sensitivity: 0.5, porcupine: None, stt: Default::default(),
+            realtime_model: None, realtime_voice: None, realtime_endpoint: None, full_duplex: false, aec: None, record_sessions: false,
+        }
     }
 }
 
 impl WakeSentinel {
-    pub fn new(opts: WakeOptions) -> Self { Self { inner: Arc::new(RwLock::new(Inner { opts, active: false, stop: None })) } }
+    pub fn new(opts: WakeOptions) -> Self { Self { inner: Arc::new(RwLock::new(Inner { opts, notes: None, active: false, stop: None })) } }
 
     pub fn status(&self) -> (bool, WakeOptions) {
         let g = self.inner.read();
@@ -57,6 +291,8 @@ impl WakeSentinel {
 
     pub fn set_enabled(&self, enabled: bool) { self.inner.write().opts.enabled = enabled; }
 
+    pub fn set_notes(&self, notes: crate::notes::Notes) { self.inner.write().notes = Some(notes); }
+
     #[cfg(feature = "realtime-audio")]
     pub async fn start_task(&self, realtime: crate::realtime::RealtimeManager) {
         let mut g = self.inner.write();
@@ -65,11 +301,16 @@ impl WakeSentinel {
         g.stop = Some(tx);
         g.active = true;
         let opts = g.opts.clone();
+        let notes = g.notes.clone();
         drop(g);
 
         tokio::spawn(async move {
             if !opts.enabled { wake_log("wake: disabled at start; not listening"); return; }
-            wake_log(format!("wake: starting (enabled=true, sr=16000, chunk=30ms, phrase=\"{}\")", opts.phrase));
+            let mut detector = match build_detector(&opts) {
+                Ok(d) => d,
+                Err(e) => { wake_log(format!("wake: detector init error: {}", e)); return; }
+            };
+            wake_log(format!("wake: starting (enabled=true, engine={}, sr=16000, chunk=30ms, phrase=\"{}\")", detector.name(), opts.phrase));
             // Start capture in a dedicated thread to avoid Send bounds; deliver frames via tokio mpsc
             let (tx_frames, mut rx_frames) = mpsc::channel::<Vec<i16>>(64);
             let stop_flag = Arc::new(AtomicBool::new(false));
@@ -84,112 +325,24 @@ impl WakeSentinel {
                     Err(e) => { eprintln!("[wake] capture error: {}", e); }
                 }
             });
-            let last_trigger = StdArc::new(StdMutex::new(std::time::Instant::now() - std::time::Duration::from_millis(opts.refractory_ms)));
-            let mut ring: VecDeque<i16> = VecDeque::with_capacity(16000 * 3);
-            #[cfg(feature = "wake-porcupine")]
-            let mut porcupine = {
-                // Read Porcupine paths from env for now; later wire config
-                let mut keyword_path = std::env::var("PORCUPINE_KEYWORD_PATH").unwrap_or_else(|_| String::new());
-                if keyword_path.is_empty() {
-                    if let Ok(dir) = std::env::var("PORCUPINE_KEYWORD_DIR") {
-                        if let Ok(rd) = std::fs::read_dir(dir) {
-                            for e in rd.flatten() {
-                                if let Some(ext) = e.path().extension().and_then(|s| s.to_str()) { if ext.eq_ignore_ascii_case("ppn") { if let Some(s) = e.path().to_str() { keyword_path = s.to_string(); break; } } }
-                            }
-                        }
-                    }
-                }
-                let model_path = std::env::var("PORCUPINE_MODEL_PATH").ok();
-                let access_key_env = std::env::var("PORCUPINE_ACCESS_KEY_ENV").unwrap_or_else(|_| "PICOVOICE_ACCESS_KEY".into());
-                let sensitivity: f32 = std::env::var("PORCUPINE_SENSITIVITY").ok().and_then(|s| s.parse().ok()).unwrap_or(0.5);
-                if keyword_path.is_empty() { wake_log("wake: PORCUPINE_KEYWORD_PATH not set; wake disabled"); }
-                match crate::wake_porcupine::PorcupineDetector::new(crate::wake_porcupine::PorcupineOpts { access_key_env, keyword_path, model_path, sensitivity }) {
-                    Ok(det) => { wake_log(format!("wake: Porcupine ready (sr={}Hz, frame_length={})", det.sample_rate, det.frame_length)); det }
-                    Err(e) => { wake_log(format!("wake: Porcupine init error: {}", e)); return; }
-                }
-            };
-            #[cfg(feature = "wake-porcupine")]
-            let mut porcupine_buf: Vec<i16> = Vec::with_capacity(1024);
-            let mut in_speech = false;
-            let mut speech_len_ms: u32 = 0;
-            let mut phrase_buf: Vec<i16> = vec![];
-            let mut silence_ms: u32 = 0;
-            // Dynamic energy threshold based on sensitivity (0..1)
-            let thr: f32 = 0.005 + (0.025 * opts.vad_sensitivity.clamp(0.0, 1.0));
-            wake_log(format!("wake: VAD threshold set to {:.3}", thr));
+            let mut last_trigger = std::time::Instant::now() - std::time::Duration::from_millis(opts.refractory_ms);
             loop {
                 tokio::select! {
                     _ = &mut rx_stop => { stop_flag.store(true, Ordering::SeqCst); break; }
                     Some(frame) = rx_frames.recv() => {
-                        // Maintain ring buffer (3s)
-                        for s in &frame { if ring.len()>=16000*3 { ring.pop_front(); } ring.push_back(*s); }
-                        // Porcupine path (if enabled)
-                        #[cfg(feature = "wake-porcupine")]
-                        {
-                            porcupine_buf.extend_from_slice(&frame);
-                            while porcupine_buf.len() >= porcupine.frame_length {
-                                let chunk: Vec<i16> = porcupine_buf.drain(0..porcupine.frame_length).collect();
-                                match porcupine.process(&chunk) {
-                                    Ok(true) => {
-                                        let mut permit = false;
-                                        let now = std::time::Instant::now();
-                                        if let Ok(mut g) = last_trigger.lock() { if now.duration_since(*g).as_millis() as u64 >= opts.refractory_ms { *g = now; permit = true; } }
-                                        if permit {
-                                            wake_log("wake: Porcupine detected — starting realtime session");
-                                            let _ = realtime.start(crate::realtime::RealtimeOptions { model: Some("gpt-realtime".into()), voice: Some("alloy".into()), audio: Some(crate::realtime::RealtimeAudioOpts { in_sr: Some(16000), out_format: Some("pcm16".into()) }), instructions: None, endpoint: None, transport: None }).await;
-                                        } else {
-                                            wake_log("wake: detection suppressed by refractory window");
-                                        }
-                                    }
-                                    Ok(false) => {}
-                                    Err(e) => { wake_log(format!("wake: Porcupine error: {}", e)); }
-                                }
-                            }
-                            continue;
-                        }
-                        // VAD energy
-                        let energy: f32 = frame.iter().map(|s| s.abs() as f32 / 32768.0).sum::<f32>() / (frame.len().max(1) as f32);
-                        if energy > thr {
-                            in_speech = true; speech_len_ms += 30; silence_ms = 0; phrase_buf.extend_from_slice(&frame);
-                        } else {
-                            // accumulate silence, require at least 150ms to close speech
-                            silence_ms += 30;
-                            if in_speech && silence_ms >= 150 {
-                                // Speech ended
-                                in_speech = false;
-                                if speech_len_ms >= opts.min_speech_ms {
-                                    // Offload STT to background; don't block capture loop
-                                    let audio = std::mem::take(&mut phrase_buf);
-                                    let p = opts.phrase.clone();
-                                    let rt = realtime.clone();
-                                    let last_tr = last_trigger.clone();
-                                    let ref_ms = opts.refractory_ms;
-                                    tokio::spawn(async move {
-                                        wake_log(format!("wake: speech ended ({} ms) — transcribing (bg)", speech_len_ms));
-                                        let t0 = std::time::Instant::now();
-                                        let text = transcribe(&audio, 16000).await;
-                                        let dt = t0.elapsed().as_millis();
-                                        if !text.is_empty() { wake_log(format!("wake: transcript=\"{}\" ({} ms)", text, dt)); } else { wake_log(format!("wake: empty transcript ({} ms)", dt)); }
-                                        // Gate by refractory and phrase match
-                                        let now = std::time::Instant::now();
-                                        if matches_wake(&text, &p) {
-                                            // Check refractory without holding the lock across await
-                                            let mut permit = false;
-                                            if let Ok(mut g) = last_tr.lock() {
-                                                if now.duration_since(*g).as_millis() as u64 >= ref_ms { *g = now; permit = true; }
-                                            }
-                                            if permit {
-                                                wake_log("wake: phrase matched — starting realtime session");
-                                                let _ = rt.start(crate::realtime::RealtimeOptions { model: Some("gpt-realtime".into()), voice: Some("alloy".into()), audio: Some(crate::realtime::RealtimeAudioOpts { in_sr: Some(16000), out_format: Some("pcm16".into()) }), instructions: None, endpoint: None, transport: None }).await;
-                                            } else {
-                                                wake_log("wake: match suppressed by refractory window");
-                                            }
-                                        }
-                                    });
+                        match detector.process(&frame) {
+                            Ok(true) => {
+                                let now = std::time::Instant::now();
+                                if now.duration_since(last_trigger).as_millis() as u64 >= opts.refractory_ms {
+                                    last_trigger = now;
+                                    wake_log(format!("wake: {} detected", detector.name()));
+                                    handle_wake(&opts, notes.as_ref(), detector.heard(), &mut rx_frames, &realtime).await;
+                                } else {
+                                    wake_log("wake: detection suppressed by refractory window");
                                 }
-                                // reset accumulators
-                                speech_len_ms = 0; phrase_buf.clear(); silence_ms = 0;
                             }
+                            Ok(false) => {}
+                            Err(e) => { wake_log(format!("wake: {} error: {}", detector.name(), e)); }
                         }
                     }
                 }
@@ -197,28 +350,85 @@ impl WakeSentinel {
         });
     }
 
+    /// Stop and start again so option or model changes take effect.
+    #[cfg(feature = "realtime-audio")]
+    pub async fn restart_task(&self, realtime: crate::realtime::RealtimeManager) {
+        self.stop_task().await;
+        self.start_task(realtime).await;
+    }
+
     pub async fn stop_task(&self) {
         if let Some(tx) = self.inner.write().stop.take() { let _ = tx.send(()); }
         self.inner.write().active = false;
     }
 }
 
-async fn transcribe(_pcm: &[i16], _sr: u32) -> String {
-    // Try local STT first (voice-daemon); fallback to OpenAI
-    match crate::stt::transcribe_local_pcm16(_pcm, _sr, None).await {
-        Ok(t) if !t.is_empty() => return t,
-        Ok(_) => {},
-        Err(e) => { wake_log(format!("wake: local STT error: {}", e)); }
-    }
-    if std::env::var("OPENAI_API_KEY").is_ok() {
-        match crate::stt::transcribe_openai_pcm16(_pcm, _sr).await {
-            Ok(t) => return t,
-            Err(e) => { wake_log(format!("wake: STT error: {}", e)); }
+/// After a detection: run a note command when one follows the phrase (and commands
+/// are enabled), otherwise start a realtime session. `frames` is the live 16 kHz mic.
+pub async fn handle_wake(
+    opts: &WakeOptions,
+    notes: Option<&crate::notes::Notes>,
+    heard: Option<String>,
+    frames: &mut tokio::sync::mpsc::Receiver<Vec<i16>>,
+    realtime: &crate::realtime::RealtimeManager,
+) {
+    use crate::notes::{parse_command, NewNote, VoiceCommand};
+    if let Some(notes) = notes.filter(|n| n.opts.wake_commands) {
+        // A transcribing engine may have heard the command already; a bare phrase means listen for it
+        let said_more = heard.as_ref().is_some_and(|h| normalize(h).split(' ').count() > normalize(&opts.phrase).split(' ').count());
+        let mut command = heard.as_deref().and_then(parse_command).map(|c| (c, Vec::new()));
+        if command.is_none() && !said_more {
+            let mut gate = notes.command_gate(16000);
+            crate::notes::capture(frames, &mut gate).await;
+            let pcm = gate.into_pcm();
+            if !pcm.is_empty() {
+                match notes.transcribe(&pcm, 16000).await {
+                    Ok(text) => {
+                        wake_log(format!("wake: command=\"{}\"", text));
+                        command = parse_command(&text).map(|c| (c, pcm));
+                    }
+                    Err(e) => wake_log(format!("wake: command stt error: {}", e)),
+                }
+            }
+        }
+        match command {
+            Some((VoiceCommand::TakeNote { task, text }, pcm)) => {
+                let note = match text {
+                    Some(text) => NewNote { text: Some(text), pcm, sample_rate: 16000, task_id: None, task },
+                    None => {
+                        wake_log("wake: dictating note");
+                        let mut gate = notes.dictation_gate(16000);
+                        crate::notes::capture(frames, &mut gate).await;
+                        NewNote { text: None, pcm: gate.into_pcm(), sample_rate: 16000, task_id: None, task }
+                    }
+                };
+                match notes.file(note).await {
+                    Ok(rec) => {
+                        wake_log(format!("wake: note {} filed under \"{}\"", rec.atom_id, rec.task));
+                        notes.say(&format!("Noted under {}.", rec.task));
+                    }
+                    Err(e) => {
+                        wake_log(format!("wake: note failed: {}", e));
+                        notes.say("Sorry, I didn't get that note.");
+                    }
+                }
+                return;
+            }
+            Some((VoiceCommand::Recall { query }, _)) => {
+                match notes.recall(&query, None, 5).await {
+                    Ok(r) => {
+                        wake_log(format!("wake: recall \"{}\" -> {} notes", query, r.hits.len()));
+                        notes.say(&r.spoken);
+                    }
+                    Err(e) => wake_log(format!("wake: recall failed: {}", e)),
+                }
+                return;
+            }
+            None => {}
         }
-    } else {
-        wake_log("wake: no STT available — ensure voice-daemon is running or set OPENAI_API_KEY");
     }
-    String::new()
+    wake_log("wake: starting realtime session");
+    let _ = realtime.start(opts.session_options()).await;
 }
 
 fn normalize(s: &str) -> String {
@@ -245,7 +455,7 @@ fn matches_wake(text: &str, phrase: &str) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use super::matches_wake;
+    use super::{build_detector, matches_wake, WakeEngine, WakeOptions};
     #[test]
     fn test_matches() {
         assert!(matches_wake("hey vim", "hey vim"));
@@ -254,4 +464,19 @@ mod tests {
         assert!(matches_wake("hay vim", "hey vim"));
         assert!(!matches_wake("hello there", "hey vim"));
     }
+
+    #[test]
+    fn engine_falls_back_to_stt_until_enrolled() {
+        let mut opts = WakeOptions { kws_model: std::env::temp_dir().join(format!("kws_missing_{}.json", uuid::Uuid::new_v4())), ..Default::default() };
+        assert_eq!(opts.resolved_engine(), WakeEngine::Stt);
+        assert_eq!(WakeEngine::parse("KWS"), Some(WakeEngine::Kws));
+        assert_eq!(WakeEngine::parse("porcupine"), Some(WakeEngine::Porcupine));
+        assert_eq!(WakeEngine::parse("snowboy"), None);
+        // Explicit kws without a model is an error rather than a silent fallback
+        opts.engine = Some(WakeEngine::Kws);
+        assert!(build_detector(&opts).is_err());
+        let s = opts.session_options();
+        assert_eq!(s.audio.and_then(|a| a.in_sr), Some(16000));
+        assert!(s.model.is_none(), "model comes from config, not a hardcoded name");
+    }
 }
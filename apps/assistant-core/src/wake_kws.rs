@@ -0,0 +1,374 @@
+//! Offline keyword spotter: MFCC features and DTW template matching.
+//!
+//! Enrollment turns a few recordings of the wake phrase into templates and
+//! calibrates a threshold from how far the takes are from each other. Detection
+//! runs a streaming subsequence DTW of every template against the live feature
+//! stream, so the phrase is found even when it runs straight into a request.
+//! No model files, network or proprietary keys are involved.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub const SAMPLE_RATE: u32 = 16_000;
+const FRAME_LEN: usize = 400; // 25 ms
+const HOP: usize = 160; // 10 ms
+const N_FFT: usize = 512;
+const N_MELS: usize = 26;
+/// Cepstral coefficients kept per frame. c0 (overall level) is dropped so matching
+/// does not depend on how loud or close the speaker is.
+const N_CEPS: usize = 8;
+/// Half-width of the triangular smoothing over FFT bins (31.25 Hz each).
+const SMOOTH_BINS: isize = 4;
+/// Range kept below each frame's loudest mel band, in natural-log units (~30 dB).
+const DYNAMIC_RANGE: f32 = 7.0;
+/// A take whose loudest frame is below this holds no speech.
+const MIN_SPEECH_RMS: f32 = 300.0;
+/// Shortest usable take after trimming silence.
+const MIN_TEMPLATE_FRAMES: usize = 20;
+
+/// One analysis frame: cepstra for matching and the frame level for gating.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub ceps: Vec<f32>,
+    pub rms: f32,
+}
+
+/// Streaming MFCC extractor for 16 kHz mono PCM.
+pub struct Mfcc {
+    window: Vec<f32>,
+    mel: Vec<Vec<(usize, f32)>>,
+    /// Pre-emphasised samples not yet consumed, with the raw samples alongside for levels.
+    pending: Vec<f32>,
+    raw: Vec<f32>,
+    last: f32,
+}
+
+impl Default for Mfcc {
+    fn default() -> Self { Self::new() }
+}
+
+impl Mfcc {
+    pub fn new() -> Self {
+        let window = (0..FRAME_LEN).map(|i| 0.54 - 0.46 * (std::f32::consts::TAU * i as f32 / (FRAME_LEN - 1) as f32).cos()).collect();
+        Self { window, mel: mel_filterbank(), pending: Vec::with_capacity(FRAME_LEN * 2), raw: Vec::with_capacity(FRAME_LEN * 2), last: 0.0 }
+    }
+
+    /// Append samples and return every complete frame they finish.
+    pub fn push(&mut self, pcm: &[i16]) -> Vec<Frame> {
+        for &s in pcm {
+            let x = s as f32 / 32768.0;
+            // Pre-emphasis lifts the formant region above the voiced low end
+            self.pending.push(x - 0.97 * self.last);
+            self.raw.push(x);
+            self.last = x;
+        }
+        let mut out = vec![];
+        while self.pending.len() >= FRAME_LEN {
+            out.push(self.frame(&self.pending[..FRAME_LEN], &self.raw[..FRAME_LEN]));
+            self.pending.drain(..HOP);
+            self.raw.drain(..HOP);
+        }
+        out
+    }
+
+    fn frame(&self, samples: &[f32], raw: &[f32]) -> Frame {
+        let rms = (raw.iter().map(|x| x * x).sum::<f32>() / raw.len() as f32).sqrt() * 32768.0;
+        let mut re = vec![0.0f32; N_FFT];
+        let mut im = vec![0.0f32; N_FFT];
+        for (i, (s, w)) in samples.iter().zip(&self.window).enumerate() { re[i] = s * w; }
+        fft(&mut re, &mut im);
+        let raw: Vec<f32> = (0..=N_FFT / 2).map(|k| re[k] * re[k] + im[k] * im[k]).collect();
+        // Smear harmonics over ~280 Hz so the bands follow the formant envelope, not the pitch
+        let power: Vec<f32> = (0..raw.len()).map(|k| {
+            let (mut acc, mut wsum) = (0.0, 0.0);
+            for o in -SMOOTH_BINS..=SMOOTH_BINS {
+                if let Some(v) = k.checked_add_signed(o).and_then(|j| raw.get(j)) {
+                    let w = (SMOOTH_BINS + 1 - o.abs()) as f32;
+                    acc += v * w;
+                    wsum += w;
+                }
+            }
+            acc / wsum
+        }).collect();
+        let mut log_mel: Vec<f32> = self.mel.iter().map(|f| f.iter().map(|&(k, w)| power[k] * w).sum::<f32>().max(1e-10).ln()).collect();
+        // Floor each band relative to the frame peak: near-empty bands would otherwise
+        // sit at the fixed noise level and make the cepstra depend on loudness
+        let peak = log_mel.iter().copied().fold(f32::MIN, f32::max);
+        log_mel.iter_mut().for_each(|v| *v = v.max(peak - DYNAMIC_RANGE));
+        // DCT-II, skipping c0
+        let ceps = (1..=N_CEPS).map(|c| {
+            log_mel.iter().enumerate().map(|(m, v)| v * (std::f32::consts::PI * c as f32 * (m as f32 + 0.5) / N_MELS as f32).cos()).sum::<f32>() * (2.0 / N_MELS as f32).sqrt()
+        }).collect();
+        Frame { ceps, rms }
+    }
+}
+
+/// All frames of a complete recording.
+pub fn features(pcm: &[i16]) -> Vec<Frame> {
+    Mfcc::new().push(pcm)
+}
+
+fn hz_to_mel(hz: f32) -> f32 { 2595.0 * (1.0 + hz / 700.0).log10() }
+
+fn mel_to_hz(mel: f32) -> f32 { 700.0 * (10f32.powf(mel / 2595.0) - 1.0) }
+
+/// Triangular filters between 60 Hz and 7.6 kHz as sparse (bin, weight) lists.
+fn mel_filterbank() -> Vec<Vec<(usize, f32)>> {
+    let (lo, hi) = (hz_to_mel(60.0), hz_to_mel(7_600.0));
+    let bin = |mel: f32| mel_to_hz(mel) * N_FFT as f32 / SAMPLE_RATE as f32;
+    let edges: Vec<f32> = (0..N_MELS + 2).map(|i| bin(lo + (hi - lo) * i as f32 / (N_MELS + 1) as f32)).collect();
+    (0..N_MELS).map(|m| {
+        let (l, c, r) = (edges[m], edges[m + 1], edges[m + 2]);
+        (l.floor() as usize..=(r.ceil() as usize).min(N_FFT / 2)).filter_map(|k| {
+            let f = k as f32;
+            let w = if f <= c { (f - l) / (c - l).max(1e-6) } else { (r - f) / (r - c).max(1e-6) };
+            (w > 0.0).then_some((k, w))
+        }).collect()
+    }).collect()
+}
+
+/// In-place iterative radix-2 FFT; `re.len()` must be a power of two.
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 { j ^= bit; bit >>= 1; }
+        j |= bit;
+        if i < j { re.swap(i, j); im.swap(i, j); }
+    }
+    let mut len = 2;
+    while len <= n {
+        let ang = -std::f32::consts::TAU / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (wr, wi) = ((ang * k as f32).cos(), (ang * k as f32).sin());
+                let (a, b) = (start + k, start + k + len / 2);
+                let (xr, xi) = (re[b] * wr - im[b] * wi, re[b] * wi + im[b] * wr);
+                re[b] = re[a] - xr; im[b] = im[a] - xi;
+                re[a] += xr; im[a] += xi;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum::<f32>().sqrt()
+}
+
+/// Keep the span between the first and last frame clearly above the take's noise floor.
+fn trim_silence(frames: &[Frame]) -> &[Frame] {
+    if frames.is_empty() { return frames; }
+    let mut levels: Vec<f32> = frames.iter().map(|f| f.rms).collect();
+    levels.sort_by(|a, b| a.total_cmp(b));
+    let floor = levels[levels.len() / 10];
+    let peak = levels[levels.len() - 1];
+    if peak < MIN_SPEECH_RMS || peak < 4.0 * floor { return &frames[..0]; }
+    let thr = floor + 0.1 * (peak - floor);
+    let first = frames.iter().position(|f| f.rms > thr).unwrap_or(0);
+    let last = frames.iter().rposition(|f| f.rms > thr).unwrap_or(frames.len() - 1);
+    &frames[first..=last.max(first)]
+}
+
+/// Streaming subsequence DTW of one template: the match may start at any stream
+/// frame, and each stream frame advances the template by 0, 1 or 2 frames
+/// (tolerates roughly half to double speed).
+struct Matcher {
+    template: Vec<Vec<f32>>,
+    cost: Vec<f32>,
+    len: Vec<u32>,
+    /// The path into this cell held the template still (no two holds in a row).
+    held: Vec<bool>,
+}
+
+impl Matcher {
+    fn new(template: Vec<Vec<f32>>) -> Self {
+        let n = template.len();
+        Self { template, cost: vec![f32::INFINITY; n], len: vec![0; n], held: vec![false; n] }
+    }
+
+    /// Feed one frame; returns the mean per-step distance of the best path ending
+    /// on the last template frame.
+    fn step(&mut self, x: &[f32]) -> f32 {
+        let n = self.template.len();
+        let mut cost = vec![f32::INFINITY; n];
+        let mut len = vec![0u32; n];
+        let mut held = vec![false; n];
+        for i in 0..n {
+            let d = distance(&self.template[i], x);
+            if i == 0 {
+                // Any stream frame may start a match
+                cost[0] = d;
+                len[0] = 1;
+                continue;
+            }
+            let mut best = f32::INFINITY;
+            let hold = (!self.held[i]).then_some(i);
+            // (predecessor, steps, hold): a skip is charged for the template frame it jumps
+            // over, so a shorter word cannot race through the template for free
+            for (p, steps, is_hold) in [(hold, 1, true), (Some(i - 1), 1, false), (i.checked_sub(2), 2, false)] {
+                let Some(p) = p else { continue };
+                if !self.cost[p].is_finite() { continue; }
+                let (c, l) = (self.cost[p] + d * steps as f32, self.len[p] + steps);
+                let avg = c / l as f32;
+                if avg < best { best = avg; cost[i] = c; len[i] = l; held[i] = is_hold; }
+            }
+        }
+        self.cost = cost;
+        self.len = len;
+        self.held = held;
+        if self.len[n - 1] == 0 { f32::INFINITY } else { self.cost[n - 1] / self.len[n - 1] as f32 }
+    }
+
+    fn reset(&mut self) {
+        self.cost.iter_mut().for_each(|c| *c = f32::INFINITY);
+        self.len.iter_mut().for_each(|l| *l = 0);
+        self.held.iter_mut().for_each(|h| *h = false);
+    }
+}
+
+/// Best (lowest) score of `template` anywhere in `query`.
+fn best_score(template: &[Vec<f32>], query: &[Frame]) -> f32 {
+    let mut m = Matcher::new(template.to_vec());
+    query.iter().map(|f| m.step(&f.ceps)).fold(f32::INFINITY, f32::min)
+}
+
+/// Enrolled wake phrase, stored as JSON (default `<home>/wake/kws.json`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeywordModel {
+    pub phrase: String,
+    pub sample_rate: u32,
+    /// Per take: trimmed cepstral frames.
+    pub templates: Vec<Vec<Vec<f32>>>,
+    /// Mean score between enrollment takes; the detection threshold scales this.
+    pub calibration: f32,
+    pub created_at: DateTime<Utc>,
+}
+
+impl KeywordModel {
+    /// Build templates from recordings of the phrase (16 kHz mono, at least two takes).
+    pub fn enroll(phrase: &str, takes: &[Vec<i16>]) -> Result<Self> {
+        if takes.len() < 2 { anyhow::bail!("need at least 2 recordings of the wake phrase, got {}", takes.len()); }
+        let mut all = vec![];
+        let mut templates = vec![];
+        for (i, pcm) in takes.iter().enumerate() {
+            let frames = features(pcm);
+            let speech = trim_silence(&frames);
+            if speech.len() < MIN_TEMPLATE_FRAMES { anyhow::bail!("recording {} has no usable speech ({} ms)", i + 1, speech.len() * 10); }
+            templates.push(speech.iter().map(|f| f.ceps.clone()).collect::<Vec<_>>());
+            all.push(frames);
+        }
+        // Calibrate on whole takes, the way live audio reaches the detector
+        let mut pairs = vec![];
+        for (i, t) in templates.iter().enumerate() {
+            for (j, q) in all.iter().enumerate() {
+                if i != j { pairs.push(best_score(t, q)); }
+            }
+        }
+        let calibration = pairs.iter().sum::<f32>() / pairs.len() as f32;
+        Ok(Self { phrase: phrase.to_string(), sample_rate: SAMPLE_RATE, templates, calibration, created_at: Utc::now() })
+    }
+
+    /// Best score of any template within a recording, on the calibration scale
+    /// (around 1.0 for a take like the enrolled ones).
+    pub fn score(&self, pcm: &[i16]) -> f32 {
+        let frames = features(pcm);
+        let best = self.templates.iter().map(|t| best_score(t, &frames)).fold(f32::INFINITY, f32::min);
+        best / self.calibration.max(1e-6)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path).with_context(|| format!("read {}", path.display()))?;
+        serde_json::from_slice(&bytes).with_context(|| format!("parse {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() { std::fs::create_dir_all(dir)?; }
+        std::fs::write(path, serde_json::to_vec(self)?).with_context(|| format!("write {}", path.display()))
+    }
+}
+
+/// Live detector over an enrolled model.
+pub struct KwsDetector {
+    mfcc: Mfcc,
+    matchers: Vec<Matcher>,
+    threshold: f32,
+    /// Recent frame levels, as long as the longest template.
+    levels: std::collections::VecDeque<f32>,
+    span: usize,
+    floor: f32,
+    last_score: f32,
+}
+
+impl KwsDetector {
+    /// `sensitivity` 0..1: higher accepts matches further from the enrolled takes.
+    pub fn new(model: &KeywordModel, sensitivity: f32) -> Self {
+        let threshold = model.calibration * (1.0 + sensitivity.clamp(0.0, 1.0));
+        let span = model.templates.iter().map(|t| t.len()).max().unwrap_or(1);
+        Self {
+            mfcc: Mfcc::new(),
+            matchers: model.templates.iter().cloned().map(Matcher::new).collect(),
+            threshold,
+            levels: std::collections::VecDeque::with_capacity(span),
+            span,
+            floor: 50.0,
+            last_score: f32::INFINITY,
+        }
+    }
+
+    pub fn threshold(&self) -> f32 { self.threshold }
+
+    /// Lowest score seen in the most recent `push` (for logging and tuning).
+    pub fn last_score(&self) -> f32 { self.last_score }
+
+    /// Feed 16 kHz mono samples; true when the phrase ended within them.
+    pub fn push(&mut self, pcm: &[i16]) -> bool {
+        let mut hit = false;
+        self.last_score = f32::INFINITY;
+        for frame in self.mfcc.push(pcm) {
+            if self.levels.len() == self.span { self.levels.pop_front(); }
+            self.levels.push_back(frame.rms);
+            // Noise floor: fast down, slow up
+            self.floor = if frame.rms < self.floor { 0.7 * self.floor + 0.3 * frame.rms } else { 0.995 * self.floor + 0.005 * frame.rms }.max(1.0);
+            let score = self.matchers.iter_mut().map(|m| m.step(&frame.ceps)).fold(f32::INFINITY, f32::min);
+            self.last_score = self.last_score.min(score);
+            // Only a window that actually holds speech may match
+            let voiced = self.levels.iter().filter(|l| **l > 3.0 * self.floor && **l > 100.0).count();
+            if !hit && score < self.threshold && voiced * 2 >= self.levels.len() {
+                hit = true;
+                self.matchers.iter_mut().for_each(Matcher::reset);
+            }
+        }
+        hit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fft_finds_a_pure_tone() {
+        let k = 32;
+        let mut re: Vec<f32> = (0..N_FFT).map(|i| (std::f32::consts::TAU * k as f32 * i as f32 / N_FFT as f32).cos()).collect();
+        let mut im = vec![0.0; N_FFT];
+        fft(&mut re, &mut im);
+        let mag: Vec<f32> = (0..N_FFT / 2).map(|i| (re[i] * re[i] + im[i] * im[i]).sqrt()).collect();
+        let peak = mag.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1)).unwrap().0;
+        assert_eq!(peak, k);
+        assert!((mag[k] - N_FFT as f32 / 2.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn cepstra_ignore_gain() {
+        let tone: Vec<i16> = (0..4_000).map(|i| ((i as f32 * 0.2).sin() * 3_000.0 + (i as f32 * 0.05).sin() * 2_000.0) as i16).collect();
+        let quiet: Vec<i16> = tone.iter().map(|s| s / 4).collect();
+        let (a, b) = (features(&tone), features(&quiet));
+        assert_eq!(a.len(), (4_000 - FRAME_LEN) / HOP + 1);
+        assert!(distance(&a[10].ceps, &b[10].ceps) < 0.05);
+        assert!(a[10].rms > 3.5 * b[10].rms);
+    }
+}
@@ -6,6 +6,7 @@ pub struct PorcupineDetector {
     inner: pv_porcupine::Porcupine,
     pub frame_length: usize,
     pub sample_rate: u32,
+    buf: Vec<i16>,
 }
 
 pub struct PorcupineOpts {
@@ -15,6 +16,43 @@ pub struct PorcupineOpts {
     pub sensitivity: f32,
 }
 
+impl PorcupineOpts {
+    /// Resolve from `[voice.porcupine]`; values may be `${VAR}` references and
+    /// unset fields fall back to the `PORCUPINE_*` env vars.
+    pub fn from_config(cfg: Option<&crate::config::PorcupineConfig>) -> anyhow::Result<Self> {
+        let field = |v: Option<&String>, var: &str| v.and_then(|s| expand(s)).or_else(|| std::env::var(var).ok().filter(|s| !s.is_empty()));
+        let mut keyword_path = field(cfg.and_then(|c| c.keyword_path.as_ref()), "PORCUPINE_KEYWORD_PATH");
+        if keyword_path.is_none() {
+            if let Some(dir) = field(cfg.and_then(|c| c.keyword_dir.as_ref()), "PORCUPINE_KEYWORD_DIR") {
+                keyword_path = std::fs::read_dir(&dir).ok().and_then(|rd| {
+                    rd.flatten().map(|e| e.path())
+                        .find(|p| p.extension().and_then(|s| s.to_str()).map(|e| e.eq_ignore_ascii_case("ppn")).unwrap_or(false))
+                        .and_then(|p| p.to_str().map(String::from))
+                });
+            }
+        }
+        let keyword_path = keyword_path.context("no Porcupine keyword (.ppn) configured")?;
+        Ok(Self {
+            access_key_env: field(cfg.and_then(|c| c.access_key_env.as_ref()), "PORCUPINE_ACCESS_KEY_ENV").unwrap_or_else(|| "PICOVOICE_ACCESS_KEY".into()),
+            keyword_path,
+            model_path: field(cfg.and_then(|c| c.model_path.as_ref()), "PORCUPINE_MODEL_PATH"),
+            sensitivity: cfg.and_then(|c| c.sensitivity)
+                .or_else(|| std::env::var("PORCUPINE_SENSITIVITY").ok().and_then(|s| s.parse().ok()))
+                .unwrap_or(0.5),
+        })
+    }
+}
+
+/// `${VAR}` expands to the variable's value; empty results count as unset.
+fn expand(v: &str) -> Option<String> {
+    let v = v.trim();
+    let out = match v.strip_prefix("${").and_then(|r| r.strip_suffix('}')) {
+        Some(var) => std::env::var(var).ok()?,
+        None => v.to_string(),
+    };
+    (!out.is_empty()).then_some(out)
+}
+
 impl PorcupineDetector {
     pub fn new(opts: PorcupineOpts) -> anyhow::Result<Self> {
         let access_key = std::env::var(&opts.access_key_env)
@@ -29,7 +67,7 @@ impl PorcupineDetector {
         let inner = builder.init().map_err(|e| anyhow::anyhow!(format!("porcupine init: {:?}", e)))?;
         let frame_length = inner.frame_length() as usize;
         let sample_rate = inner.sample_rate() as u32;
-        Ok(Self { inner, frame_length, sample_rate })
+        Ok(Self { inner, frame_length, sample_rate, buf: Vec::new() })
     }
 
     pub fn process(&mut self, frame: &[i16]) -> anyhow::Result<bool> {
@@ -38,3 +76,17 @@ impl PorcupineDetector {
     }
 }
 
+impl crate::wake::WakeDetector for PorcupineDetector {
+    fn name(&self) -> &'static str { "porcupine" }
+
+    /// Re-chunks arbitrary frames into Porcupine's fixed frame length.
+    fn process(&mut self, frame: &[i16]) -> anyhow::Result<bool> {
+        self.buf.extend_from_slice(frame);
+        let mut hit = false;
+        while self.buf.len() >= self.frame_length {
+            let chunk: Vec<i16> = self.buf.drain(..self.frame_length).collect();
+            hit |= PorcupineDetector::process(self, &chunk)?;
+        }
+        Ok(hit)
+    }
+}
@@ -0,0 +1,87 @@
+use assistant_core::{api, app, config};
+use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+mod common;
+use common::call;
+
+#[derive(Clone, Default)]
+struct Mock { pid: u32, seen: Arc<Mutex<Vec<Value>>> }
+
+/// Mock chat completions: asks to kill `pid` until a tool result is in the
+/// history, then answers.
+async fn completions(State(mock): State<Mock>, Json(body): Json<Value>) -> Json<Value> {
+    mock.seen.lock().push(body.clone());
+    let answered = body["messages"].as_array().unwrap().iter().any(|m| m["role"] == "tool");
+    let message = if answered { json!({"role": "assistant", "content": "Left it running."}) } else {
+        json!({"role": "assistant", "content": null, "tool_calls": [
+            {"id": "call_1", "type": "function", "function": {"name": "proc_kill", "arguments": json!({"pid": mock.pid}).to_string()}}
+        ]})
+    };
+    Json(json!({"choices": [{"message": message}]}))
+}
+
+#[tokio::test]
+async fn model_tool_calls_are_held_for_approval() {
+    // Not spawned through the tools, so killing it is a Warn and needs approval
+    let mut other = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+    let mock = Mock { pid: other.id(), ..Default::default() };
+    let provider = Router::new().route("/v1/chat/completions", post(completions)).with_state(mock.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, provider).await.unwrap(); });
+    std::env::set_var("OPENAI_BASE_URL", format!("http://{}/v1", addr));
+    std::env::set_var("OPENAI_API_KEY", "test");
+
+    let state = app::AppState::new(config::Config::default()).await;
+    let router = api::build_router(state.clone());
+    let ask = json!({"model": "gpt-test", "messages": [{"role": "user", "content": "Kill that sleep"}]});
+
+    // The reply comes back held instead of waiting on a prompt, and nothing ran
+    let (status, held) = call(&router, "POST", "/api/chat/complete", Some(ask.clone())).await;
+    assert_eq!(status, StatusCode::ACCEPTED, "{}", held);
+    let pending = &held["pending_approval"][0];
+    assert_eq!((pending["tool_call_id"].as_str(), pending["name"].as_str()), (Some("call_1"), Some("proc_kill")));
+    assert!(state.handles.approval_prompt.read().is_none());
+    assert!(other.try_wait().unwrap().is_none());
+    let last = held["messages"].as_array().unwrap().last().unwrap().clone();
+    assert_eq!(last["tool_calls"][0]["id"], "call_1");
+
+    // Denied: the model hears so and the process is left alone
+    let approval_id = pending["approval_id"].as_str().unwrap().to_string();
+    let (status, _) = call(&router, "POST", &format!("/api/approvals/{}/deny", approval_id), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let resume = json!({"model": "gpt-test", "messages": held["messages"], "approvals": [{"tool_call_id": "call_1", "approval_id": approval_id}]});
+    let (status, resp) = call(&router, "POST", "/api/chat/complete", Some(resume)).await;
+    assert_eq!(status, StatusCode::OK, "{}", resp);
+    assert_eq!(resp["reply"], "Left it running.");
+    let sent = mock.seen.lock().last().unwrap()["messages"].clone();
+    let result = sent.as_array().unwrap().iter().find(|m| m["role"] == "tool").expect("tool result sent back").clone();
+    assert!(result["content"].as_str().unwrap().contains("denied by user"), "{}", result);
+    assert!(other.try_wait().unwrap().is_none(), "process was killed without approval");
+
+    // A token for another action does not carry over; the approved one runs the call
+    let (_, held) = call(&router, "POST", "/api/chat/complete", Some(ask)).await;
+    let approval_id = held["pending_approval"][0]["approval_id"].as_str().unwrap().to_string();
+    let (_, approved) = call(&router, "POST", &format!("/api/approvals/{}/approve", approval_id), None).await;
+    let token = approved["token"].as_str().unwrap();
+    let (_, forged) = call(&router, "POST", "/api/approvals", Some(json!({"command": "proc.list"}))).await;
+    let forged_id = forged["id"].as_str().unwrap();
+    let _ = call(&router, "POST", &format!("/api/approvals/{}/approve", forged_id), None).await;
+    let resume = |id: &str, token: &str| json!({"model": "gpt-test", "messages": held["messages"], "approvals": [{"tool_call_id": "call_1", "approval_id": id, "approve_token": token}]});
+    let (status, again) = call(&router, "POST", "/api/chat/complete", Some(resume(forged_id, token))).await;
+    assert_eq!(status, StatusCode::ACCEPTED, "{}", again);
+    assert!(other.try_wait().unwrap().is_none());
+
+    let (status, resp) = call(&router, "POST", "/api/chat/complete", Some(resume(&approval_id, token))).await;
+    assert_eq!(status, StatusCode::OK, "{}", resp);
+    let status = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        loop {
+            if let Some(s) = other.try_wait().unwrap() { break s; }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+    }).await.expect("approved kill ran");
+    assert!(!status.success());
+}
@@ -0,0 +1,128 @@
+use assistant_core::{api, app, config};
+use axum::{extract::State, http::StatusCode, response::IntoResponse, routing::post, Json, Router};
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+mod common;
+use common::call;
+
+type Seen = Arc<Mutex<Vec<Value>>>;
+
+/// Mock chat completions: replies "Noted." to chat requests and numbered
+/// summaries ("SUMMARY-n") to summary requests, which carry no tools.
+/// Summaries for `gpt-stall` never come back in time.
+async fn completions(State(seen): State<Seen>, Json(body): Json<Value>) -> axum::response::Response {
+    let summaries = seen.lock().iter().filter(|b| is_summary(b)).count();
+    seen.lock().push(body.clone());
+    if is_summary(&body) && body["model"] == "gpt-stall" { tokio::time::sleep(std::time::Duration::from_secs(30)).await; }
+    if body["stream"] == true {
+        let sse = "data: {\"choices\":[{\"delta\":{\"content\":\"Noted.\"}}]}\n\ndata: [DONE]\n\n";
+        return ([("content-type", "text/event-stream")], sse).into_response();
+    }
+    let content = if is_summary(&body) { format!("SUMMARY-{}", summaries + 1) } else { "Noted.".into() };
+    Json(json!({"choices": [{"message": {"role": "assistant", "content": content}}]})).into_response()
+}
+
+fn is_summary(body: &Value) -> bool { body.get("tools").is_none() && body["stream"] != true }
+
+/// The `context` event of an SSE reply.
+fn context_event(sse: &str) -> Value {
+    let data = sse.split("event: context\n").nth(1).and_then(|r| r.lines().next()).expect("context event");
+    serde_json::from_str(data.trim_start_matches("data: ")).unwrap()
+}
+
+fn turn(topic: &str) -> String {
+    format!("{} with some more words to make this turn long enough to count against the budget", topic)
+}
+
+#[tokio::test]
+async fn long_sessions_send_summary_window_and_memory_pack() {
+    let seen: Seen = Arc::default();
+    let mock = Router::new().route("/v1/chat/completions", post(completions)).with_state(seen.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, mock).await.unwrap(); });
+    std::env::set_var("OPENAI_BASE_URL", format!("http://{}/v1", addr));
+    std::env::set_var("OPENAI_API_KEY", "test");
+
+    let home = std::env::temp_dir().join(format!("chat_context_{}", uuid::Uuid::new_v4()));
+    let cfg = config::Config {
+        foreman: Some(config::ForemanConfig { home: Some(home.to_string_lossy().to_string()), profile: None }),
+        chat: Some(config::ChatConfig { history_tokens: Some(60), summary_tokens: Some(200), pack_tokens: Some(400), summary_timeout_ms: None }),
+        ..Default::default()
+    };
+    let state = app::AppState::new(cfg).await;
+    let router = api::build_router(state.clone());
+
+    // A pinned note and a task digest for the memory pack
+    let store = state.handles.memory.as_ref().unwrap().store.clone();
+    let task = store.create_task("Shell setup", "open", None).await.unwrap();
+    let atom = store.put_atom(task.id, "fact", "The user's login shell is fish", None).await.unwrap();
+    store.pin_atom(atom, true).await.unwrap();
+    store.upsert_task_digest(task.id, Some("Moving dotfiles to fish"), None, None).await.unwrap();
+
+    let (_, s) = call(&router, "POST", "/api/chat/sessions", None).await;
+    let sid = s["id"].as_str().unwrap().to_string();
+    for (role, topic) in [("user", "first question"), ("assistant", "first answer"), ("user", "second question"), ("assistant", "second answer"), ("user", "how do I set my fish prompt")] {
+        call(&router, "POST", &format!("/api/chat/sessions/{}/append", sid), Some(json!({"role": role, "content": turn(topic)}))).await;
+    }
+
+    // The client's history is ignored: the session is read back and trimmed
+    let body = json!({"session_id": sid, "model": "gpt-test", "messages": [{"role": "user", "content": "stale copy"}]});
+    let (status, sse) = call(&router, "POST", "/api/chat/stream", Some(body.clone())).await;
+    assert_eq!(status, StatusCode::OK);
+    let sse = sse.as_str().unwrap().to_string();
+    let report = context_event(&sse);
+    assert_eq!((report["kept"].as_u64(), report["summarized"].as_u64(), report["summary_cached"].as_bool()), (Some(1), Some(4), Some(false)), "{}", report);
+    assert!(report["pack_cards"].as_u64().unwrap() >= 1, "{}", report);
+
+    let sent = seen.lock().iter().rev().find(|b| !is_summary(b)).unwrap()["messages"].to_string();
+    assert!(sent.contains("Memory context") && sent.contains("login shell is fish") && sent.contains("Moving dotfiles to fish"), "{}", sent);
+    assert!(sent.contains("Summary of the earlier conversation:\\nSUMMARY-1"), "{}", sent);
+    assert!(sent.contains("how do I set my fish prompt"), "{}", sent);
+    assert!(!sent.contains("first question") && !sent.contains("stale copy"), "{}", sent);
+
+    let (_, s) = call(&router, "GET", &format!("/api/chat/sessions/{}", sid), None).await;
+    let msgs = s["messages"].as_array().unwrap().clone();
+    assert_eq!(s["summary"], "SUMMARY-1");
+    assert_eq!(s["summary_upto"], msgs[3]["id"]);
+
+    // A new turn moves the window; only the newly dropped turns are folded in
+    call(&router, "POST", &format!("/api/chat/sessions/{}/append", sid), Some(json!({"role": "user", "content": turn("third question").repeat(2)}))).await;
+    call(&router, "POST", "/api/chat/stream", Some(body)).await;
+    let fold = seen.lock().iter().rfind(|b| is_summary(b)).unwrap()["messages"][1]["content"].as_str().unwrap().to_string();
+    assert!(fold.starts_with("Summary so far:\nSUMMARY-1"), "{}", fold);
+    assert!(fold.contains("fish prompt") && !fold.contains("first question"), "{}", fold);
+    let (_, s) = call(&router, "GET", &format!("/api/chat/sessions/{}", sid), None).await;
+    assert_eq!(s["summary"], "SUMMARY-2");
+
+    // Regenerating keeps the window, so the cached summary is reused
+    let calls = seen.lock().iter().filter(|b| is_summary(b)).count();
+    let (status, sse) = call(&router, "POST", &format!("/api/chat/sessions/{}/regenerate", sid), Some(json!({"model": "gpt-test"}))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(context_event(sse.as_str().unwrap())["summary_cached"], true);
+    assert_eq!(seen.lock().iter().filter(|b| is_summary(b)).count(), calls);
+    let _ = std::fs::remove_dir_all(&home);
+
+    // A summary slower than the timeout falls back to the digest; the reply is not held up
+    let home = std::env::temp_dir().join(format!("chat_context_{}", uuid::Uuid::new_v4()));
+    let cfg = config::Config {
+        foreman: Some(config::ForemanConfig { home: Some(home.to_string_lossy().to_string()), profile: None }),
+        chat: Some(config::ChatConfig { history_tokens: Some(60), summary_tokens: Some(200), pack_tokens: Some(0), summary_timeout_ms: Some(200) }),
+        ..Default::default()
+    };
+    let router = api::build_router(app::AppState::new(cfg).await);
+    let (_, s) = call(&router, "POST", "/api/chat/sessions", None).await;
+    let sid = s["id"].as_str().unwrap().to_string();
+    for (role, topic) in [("user", "first question"), ("assistant", "first answer"), ("user", "second question")] {
+        call(&router, "POST", &format!("/api/chat/sessions/{}/append", sid), Some(json!({"role": role, "content": turn(topic)}))).await;
+    }
+    let body = json!({"session_id": sid, "model": "gpt-stall", "messages": []});
+    let (status, sse) = tokio::time::timeout(std::time::Duration::from_secs(5), call(&router, "POST", "/api/chat/stream", Some(body))).await.expect("reply not held up by the summary");
+    assert_eq!(status, StatusCode::OK);
+    assert!(sse.as_str().unwrap().contains("Noted."));
+    let sent = seen.lock().iter().rev().find(|b| !is_summary(b)).unwrap()["messages"].to_string();
+    assert!(sent.contains("Summary of the earlier conversation:\\nuser: first question"), "{}", sent);
+    let _ = std::fs::remove_dir_all(&home);
+}
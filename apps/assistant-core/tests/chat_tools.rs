@@ -0,0 +1,104 @@
+use assistant_core::{api, app, chats, config};
+use axum::{extract::State, http::StatusCode, response::IntoResponse, routing::post, Json, Router};
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+mod common;
+use common::call;
+
+type Seen = Arc<Mutex<Vec<Value>>>;
+
+/// Mock chat completions: asks for one tool call until a tool result is in the
+/// history, then answers (streamed when asked to).
+async fn completions(State(seen): State<Seen>, Json(body): Json<Value>) -> axum::response::Response {
+    seen.lock().push(body.clone());
+    if body["stream"] == true {
+        let sse = "data: {\"choices\":[{\"delta\":{\"content\":\"Planned.\"}}]}\n\ndata: [DONE]\n\n";
+        return ([("content-type", "text/event-stream")], sse).into_response();
+    }
+    let answered = body["messages"].as_array().unwrap().iter().any(|m| m["role"] == "tool");
+    let message = if answered { json!({"role": "assistant", "content": "Planned."}) } else {
+        json!({"role": "assistant", "content": null, "tool_calls": [
+            {"id": "call_1", "type": "function", "function": {"name": "installer_plan_install", "arguments": "{\"manager\":\"apt\",\"pkg\":\"ripgrep\"}"}}
+        ]})
+    };
+    Json(json!({"choices": [{"message": message}]})).into_response()
+}
+
+#[tokio::test]
+async fn tool_turns_are_saved_and_replayed() {
+    let seen: Seen = Arc::default();
+    let mock = Router::new().route("/v1/chat/completions", post(completions)).with_state(seen.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, mock).await.unwrap(); });
+    std::env::set_var("OPENAI_BASE_URL", format!("http://{}/v1", addr));
+    std::env::set_var("OPENAI_API_KEY", "test");
+
+    let home = std::env::temp_dir().join(format!("chat_tools_{}", uuid::Uuid::new_v4()));
+    let cfg = config::Config { foreman: Some(config::ForemanConfig { home: Some(home.to_string_lossy().to_string()), profile: None }), ..Default::default() };
+    let state = app::AppState::new(cfg).await;
+    let router = api::build_router(state.clone());
+
+    // Approve whatever the chat asks for
+    let approver = {
+        let (state, router) = (state.clone(), router.clone());
+        tokio::spawn(async move {
+            loop {
+                let prompt = state.handles.approval_prompt.read().clone();
+                if let Some(p) = prompt {
+                    assert!(p.title.starts_with("Chat tool requires approval"), "{}", p.title);
+                    call(&router, "POST", "/api/approval/answer", Some(json!({"id": p.id, "answer": "approve"}))).await;
+                    return p.id;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            }
+        })
+    };
+
+    let (_, s) = call(&router, "POST", "/api/chat/sessions", None).await;
+    let sid = s["id"].as_str().unwrap().to_string();
+    call(&router, "POST", &format!("/api/chat/sessions/{}/append", sid), Some(json!({"role": "user", "content": "Plan installing ripgrep"}))).await;
+    let (status, sse) = call(&router, "POST", "/api/chat/stream", Some(json!({"session_id": sid, "model": "gpt-test", "messages": [{"role": "user", "content": "Plan installing ripgrep"}]}))).await;
+    assert_eq!(status, StatusCode::OK);
+    let sse = sse.as_str().unwrap().to_string();
+    assert!(sse.contains("event: tool_result") && sse.contains("event: done"), "{}", sse);
+    let approval_id = approver.await.unwrap();
+
+    // user → assistant (calls) → tool → assistant
+    let (_, s) = call(&router, "GET", &format!("/api/chat/sessions/{}", sid), None).await;
+    let msgs = s["messages"].as_array().unwrap().clone();
+    let roles: Vec<&str> = msgs.iter().map(|m| m["role"].as_str().unwrap()).collect();
+    assert_eq!(roles, vec!["user", "assistant", "tool", "assistant"], "{:#?}", msgs);
+    assert_eq!(msgs[1]["tool_calls"], json!([{"id": "call_1", "name": "installer_plan_install", "arguments": {"manager": "apt", "pkg": "ripgrep"}}]));
+    assert_eq!(msgs[2]["tool_call_id"], "call_1");
+    assert_eq!(msgs[2]["tool_name"], "installer_plan_install");
+    assert_eq!(msgs[2]["approval_id"], json!(approval_id));
+    assert!(msgs[2]["content"].as_str().unwrap().contains("plan_id"), "{}", msgs[2]);
+    assert_eq!(msgs[3]["content"], "Planned.");
+    assert!(sse.contains(&format!("\"message_id\":\"{}\"", msgs[2]["id"].as_str().unwrap())), "{}", sse);
+
+    // The next turn sends the saved history back with the calls in provider form
+    let mut history = msgs.clone();
+    history.push(json!({"role": "user", "content": "Thanks"}));
+    call(&router, "POST", "/api/chat/stream", Some(json!({"session_id": sid, "model": "gpt-test", "messages": history}))).await;
+    let sent = seen.lock().last().unwrap()["messages"].clone();
+    let sent = sent.as_array().unwrap();
+    let at = sent.iter().position(|m| m["role"] == "assistant" && m.get("tool_calls").is_some()).expect("assistant tool_calls replayed");
+    assert_eq!(sent[at]["tool_calls"][0], json!({"id": "call_1", "type": "function", "function": {"name": "installer_plan_install", "arguments": "{\"manager\":\"apt\",\"pkg\":\"ripgrep\"}"}}));
+    assert_eq!(sent[at + 1]["role"], "tool");
+    assert_eq!(sent[at + 1]["tool_call_id"], "call_1");
+
+    // Oversized results are cut in the session and kept whole as an artifact
+    let store = chats::ChatStore::new(state.handles.memory.as_ref().unwrap().store.clone()).with_results_dir(home.join("results"));
+    let big = json!({"stdout": "x".repeat(chats::TOOL_RESULT_CHARS * 2)});
+    let call_rec = chats::ToolCallRecord { id: "call_2".into(), name: "shell_exec".into(), arguments: json!({}) };
+    let m = store.append_tool_result(&sid, &call_rec, &big, None).await.unwrap();
+    let artifact = m.artifact_id.expect("artifact saved");
+    assert!(m.content.chars().count() < chats::TOOL_RESULT_CHARS + 100, "{}", m.content.len());
+    assert!(m.content.ends_with(&format!("full result in artifact {}]", artifact)), "{}", &m.content[m.content.len() - 80..]);
+    let saved = std::fs::read_to_string(home.join("results").join(format!("{}.json", m.id))).unwrap();
+    assert_eq!(serde_json::from_str::<Value>(&saved).unwrap(), big);
+    let _ = std::fs::remove_dir_all(&home);
+}
@@ -0,0 +1,154 @@
+use assistant_core::{api, app, config};
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+
+mod common;
+use common::call;
+
+async fn state_at(home: &std::path::Path) -> app::SharedState {
+    let cfg = config::Config { foreman: Some(config::ForemanConfig { home: Some(home.to_string_lossy().to_string()), profile: None }), ..Default::default() };
+    app::AppState::new(cfg).await
+}
+
+fn temp_home(tag: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("{}_{}", tag, uuid::Uuid::new_v4()))
+}
+
+fn ids(session: &Value) -> Vec<String> {
+    session["messages"].as_array().unwrap().iter().map(|m| m["id"].as_str().unwrap().to_string()).collect()
+}
+
+#[tokio::test]
+async fn edit_regenerate_and_fork_branch_the_session() {
+    let home = temp_home("chats_branch");
+    let router = api::build_router(state_at(&home).await);
+    let (_, s) = call(&router, "POST", "/api/chat/sessions", None).await;
+    let sid = s["id"].as_str().unwrap().to_string();
+    let (_, user) = call(&router, "POST", &format!("/api/chat/sessions/{}/append", sid), Some(json!({"role": "user", "content": "Which shell am I using?\nThanks"}))).await;
+    let (_, sse) = call(&router, "POST", "/api/chat/stream", Some(json!({"session_id": sid, "model": "mock", "messages": [{"role": "user", "content": "Which shell am I using?"}]}))).await;
+    assert!(sse.as_str().unwrap().contains("event: done"));
+
+    let (_, s) = call(&router, "GET", &format!("/api/chat/sessions/{}", sid), None).await;
+    assert_eq!(s["title"], "Which shell am I using?");
+    let first = ids(&s);
+    assert_eq!(s["messages"][1]["content"], "Hello world");
+    assert_eq!(s["messages"][1]["parent_id"], user["id"]);
+
+    // Regenerate: a second reply next to the first
+    let (status, sse) = call(&router, "POST", &format!("/api/chat/sessions/{}/regenerate", sid), Some(json!({"model": "mock"}))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(sse.as_str().unwrap().contains("event: assistant_started"));
+    let (_, s) = call(&router, "GET", &format!("/api/chat/sessions/{}", sid), None).await;
+    let second = ids(&s);
+    assert_eq!((second.len(), &second[0]), (2, &first[0]));
+    assert_ne!(second[1], first[1]);
+    assert_eq!(s["messages"][1]["siblings"], json!([first[1], second[1]]));
+
+    // Either reply can be brought back
+    let (_, s) = call(&router, "POST", &format!("/api/chat/sessions/{}/head", sid), Some(json!({"message_id": first[1]}))).await;
+    assert_eq!(ids(&s), first);
+
+    // Editing the question branches from its parent and answers again
+    let (status, _) = call(&router, "POST", &format!("/api/chat/sessions/{}/messages/{}/edit", sid, first[0]), Some(json!({"content": "Which terminal am I using?", "model": "mock"}))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, s) = call(&router, "GET", &format!("/api/chat/sessions/{}", sid), None).await;
+    assert_eq!(s["messages"][0]["content"], "Which terminal am I using?");
+    assert_eq!(s["messages"][0]["siblings"].as_array().unwrap().len(), 2);
+    assert_eq!(s["messages"][1]["content"], "Hello world");
+    assert_eq!(s["title"], "Which shell am I using?");
+    let edited = ids(&s);
+    // Only user messages can be edited; ids must belong to the session
+    assert_eq!(call(&router, "POST", &format!("/api/chat/sessions/{}/messages/{}/edit", sid, edited[1]), Some(json!({"content": "x", "regenerate": false}))).await.0, StatusCode::BAD_REQUEST);
+    assert_eq!(call(&router, "POST", &format!("/api/chat/sessions/{}/messages/nope/edit", sid), Some(json!({"content": "x", "regenerate": false}))).await.0, StatusCode::NOT_FOUND);
+
+    // Fork the edited question into a new session
+    let (status, fork) = call(&router, "POST", &format!("/api/chat/sessions/{}/fork", sid), Some(json!({"message_id": edited[0]}))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(fork["forked_from"], json!(sid));
+    assert_eq!(fork["messages"].as_array().unwrap().len(), 1);
+    assert_eq!(fork["messages"][0]["content"], "Which terminal am I using?");
+    assert_ne!(fork["messages"][0]["id"], json!(edited[0]));
+    let (_, list) = call(&router, "GET", "/api/chat/sessions", None).await;
+    assert_eq!(list[0]["id"], fork["id"]);
+    assert_eq!(list.as_array().unwrap().len(), 2);
+
+    assert_eq!(call(&router, "DELETE", &format!("/api/chat/sessions/{}", sid), None).await.0, StatusCode::OK);
+    assert_eq!(call(&router, "GET", &format!("/api/chat/sessions/{}", sid), None).await.0, StatusCode::NOT_FOUND);
+    let _ = std::fs::remove_dir_all(&home);
+}
+
+#[tokio::test]
+async fn search_spans_every_session() {
+    let home = temp_home("chats_search");
+    let router = api::build_router(state_at(&home).await);
+    for text in ["the quick brown fox", "walking the lazy dogs"] {
+        let (_, s) = call(&router, "POST", "/api/chat/sessions", None).await;
+        call(&router, "POST", &format!("/api/chat/sessions/{}/append", s["id"].as_str().unwrap()), Some(json!({"role": "user", "content": text}))).await;
+    }
+    let (status, hits) = call(&router, "GET", "/api/chat/search?q=dog", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let hits = hits.as_array().unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0]["role"], "user");
+    assert!(hits[0]["snippet"].as_str().unwrap().contains("[dogs]"), "{:?}", hits);
+    // Query syntax is not passed through; words must all match
+    let (status, hits) = call(&router, "GET", "/api/chat/search?q=%22brown%22%20fox*%20(", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(hits.as_array().unwrap().len(), 1);
+    let _ = std::fs::remove_dir_all(&home);
+}
+
+#[tokio::test]
+async fn legacy_json_chats_are_imported_once() {
+    let home = temp_home("chats_import");
+    let dir = home.join("chats");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("a.json"), json!({"id": "a", "messages": [
+        {"id": "a1", "role": "user", "content": "How do I install ripgrep?", "at": "2024-02-01T10:00:00Z"},
+        {"id": "a2", "role": "assistant", "content": "sudo apt install ripgrep", "at": "2024-02-01T10:00:05Z"}
+    ]}).to_string()).unwrap();
+    std::fs::write(dir.join("b.json"), json!({"id": "b", "messages": [
+        {"role": "user", "content": "Old question", "at": "2024-01-01T10:00:00Z"}
+    ]}).to_string()).unwrap();
+    std::fs::write(dir.join("broken.json"), "{not json").unwrap();
+
+    let router = api::build_router(state_at(&home).await);
+    let (_, list) = call(&router, "GET", "/api/chat/sessions", None).await;
+    let got: Vec<(&str, &str)> = list.as_array().unwrap().iter().map(|s| (s["id"].as_str().unwrap(), s["title"].as_str().unwrap())).collect();
+    assert_eq!(got, vec![("a", "How do I install ripgrep?"), ("b", "Old question")]);
+    let (_, a) = call(&router, "GET", "/api/chat/sessions/a", None).await;
+    assert_eq!(ids(&a), vec!["a1", "a2"]);
+    assert_eq!(a["messages"][1]["parent_id"], "a1");
+    assert!(dir.join("imported/a.json").exists() && !dir.join("a.json").exists());
+    assert!(dir.join("broken.json").exists());
+    let (_, hits) = call(&router, "GET", "/api/chat/search?q=ripgrep", None).await;
+    assert_eq!(hits.as_array().unwrap().len(), 2);
+
+    // A second start finds nothing new
+    let router = api::build_router(state_at(&home).await);
+    let (_, list) = call(&router, "GET", "/api/chat/sessions", None).await;
+    assert_eq!(list.as_array().unwrap().len(), 2);
+    let _ = std::fs::remove_dir_all(&home);
+}
+
+#[tokio::test]
+async fn concurrent_appends_chain_on_one_branch() {
+    let home = temp_home("chats_concurrent");
+    let state = state_at(&home).await;
+    let store = assistant_core::chats::ChatStore::new(state.handles.memory.as_ref().unwrap().store.clone());
+    // The first appends race to create the session as well
+    let sid = uuid::Uuid::new_v4().to_string();
+    let tasks: Vec<_> = (0..16).map(|i| {
+        let (store, sid) = (store.clone(), sid.clone());
+        tokio::spawn(async move { store.append(&sid, "user", &format!("message {}", i)).await.unwrap() })
+    }).collect();
+    for t in tasks { t.await.unwrap(); }
+
+    let session = store.get(&sid).await.unwrap().unwrap();
+    assert_eq!(session.messages.len(), 16, "every append is on the head's branch");
+    assert!(session.messages[0].parent_id.is_none());
+    for pair in session.messages.windows(2) {
+        assert_eq!(pair[1].parent_id.as_deref(), Some(pair[0].id.as_str()));
+    }
+    let _ = std::fs::remove_dir_all(&home);
+}
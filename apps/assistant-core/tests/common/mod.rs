@@ -0,0 +1,17 @@
+//! Helpers shared by the API integration tests.
+
+use axum::{body::Body, http::Request, http::StatusCode, Router};
+use http_body_util::BodyExt as _;
+use serde_json::Value;
+use tower::ServiceExt;
+
+/// Send one JSON request through the router; non-JSON bodies come back as a string.
+pub async fn call(app: &Router, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let req = Request::builder().method(method).uri(uri).header("content-type", "application/json")
+        .body(body.map(|b| Body::from(b.to_string())).unwrap_or_else(Body::empty)).unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+    let v = serde_json::from_slice(&bytes).unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).to_string()));
+    (status, v)
+}
@@ -0,0 +1,47 @@
+use assistant_core::duplex::{rms, DuplexConfig, DuplexPipeline};
+
+// Fixtures: s16le 16 kHz mono, regenerated by tests/fixtures/duplex/generate.py
+fn fixture(name: &str) -> Vec<i16> {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/duplex").join(name);
+    let bytes = std::fs::read(&path).unwrap_or_else(|e| panic!("read {}: {}", path.display(), e));
+    bytes.chunks_exact(2).map(|c| i16::from_le_bytes([c[0], c[1]])).collect()
+}
+
+/// Feed mic/reference through the pipeline in 20 ms frames, as the realtime loop does.
+fn run(mic: &[i16], far: &[i16]) -> (Vec<i16>, Vec<usize>) {
+    let mut p = DuplexPipeline::new(DuplexConfig::default());
+    let frame = 320;
+    let mut cleaned = Vec::with_capacity(mic.len());
+    let mut barge_at = vec![];
+    for (i, (m, r)) in mic.chunks(frame).zip(far.chunks(frame)).enumerate() {
+        let out = p.process(m, r, true);
+        if out.barge_in { barge_at.push(i * frame); }
+        cleaned.extend(out.cleaned);
+    }
+    (cleaned, barge_at)
+}
+
+#[test]
+fn echo_only_is_cancelled_without_barge_in() {
+    let far = fixture("far.pcm");
+    let mic = fixture("mic_echo.pcm");
+    let (cleaned, barge_at) = run(&mic, &far);
+    assert!(barge_at.is_empty(), "echo alone must not interrupt the assistant: {:?}", barge_at);
+    // Echo return loss enhancement over the second half, once the filter has converged
+    let half = mic.len() / 2;
+    let erle = 20.0 * (rms(&mic[half..]) / rms(&cleaned[half..]).max(1.0)).log10();
+    assert!(erle >= 10.0, "ERLE {:.1} dB", erle);
+}
+
+#[test]
+fn user_talking_over_echo_triggers_one_barge_in() {
+    let far = fixture("far.pcm");
+    let mic = fixture("mic_barge.pcm");
+    let (cleaned, barge_at) = run(&mic, &far);
+    assert_eq!(barge_at.len(), 1, "exactly one barge-in per response: {:?}", barge_at);
+    // The user starts at 1.0 s; detection within 500 ms of onset
+    let onset = 16_000;
+    assert!(barge_at[0] >= onset && barge_at[0] <= onset + 8_000, "barge-in at sample {}", barge_at[0]);
+    // Near-end speech survives cancellation: well above the residual echo before onset
+    assert!(rms(&cleaned[onset..]) > 2.0 * rms(&cleaned[onset / 2..onset]));
+}
@@ -0,0 +1,62 @@
+#!/usr/bin/env python3
+"""Regenerate the full-duplex fixtures (s16le, 16 kHz, mono, 2 s each).
+
+far.pcm       assistant audio as sent to the speaker
+mic_echo.pcm  mic while only the assistant talks: room echo of far.pcm + noise
+mic_barge.pcm same echo, with the user talking over it from 1.0 s
+
+Signals are synthetic speech-like bursts (voiced harmonics + breath noise under a
+syllable envelope) so the files are small and reproducible.
+"""
+import math, os, random, struct
+
+SR = 16000
+N = SR * 2
+HERE = os.path.dirname(os.path.abspath(__file__))
+
+
+def voice(seed, f0, syll_hz, amp):
+    rnd = random.Random(seed)
+    out, phase = [], 0.0
+    for i in range(N):
+        t = i / SR
+        env = max(0.0, math.sin(math.pi * syll_hz * t)) ** 0.5
+        f = f0 * (1.0 + 0.08 * math.sin(2 * math.pi * 1.3 * t))
+        phase += 2 * math.pi * f / SR
+        s = sum(math.sin(k * phase) / k for k in range(1, 8))
+        out.append(amp * env * (0.8 * s / 2.6 + 0.2 * rnd.uniform(-1, 1)))
+    return out
+
+
+def room(x, delay=80, length=480, gain=0.35, seed=7):
+    rnd = random.Random(seed)
+    ir = [0.0] * (delay + length)
+    for k in range(length):
+        ir[delay + k] = gain * rnd.gauss(0, 1) * math.exp(-k / 90.0) * 0.06
+    ir[delay] = gain
+    y = [0.0] * len(x)
+    taps = [(k, h) for k, h in enumerate(ir) if h != 0.0]
+    for i in range(len(x)):
+        acc = 0.0
+        for k, h in taps:
+            if i >= k:
+                acc += h * x[i - k]
+        y[i] = acc
+    return y
+
+
+def write(name, xs):
+    with open(os.path.join(HERE, name), "wb") as f:
+        for v in xs:
+            f.write(struct.pack("<h", max(-32768, min(32767, int(round(v))))))
+
+
+far = voice(1, 140.0, 3.0, 9000.0)
+noise = random.Random(3)
+echo = [e + noise.gauss(0, 40) for e in room(far)]
+user = voice(2, 210.0, 2.2, 7000.0)
+barge = [e + (u if i >= SR else 0.0) for i, (e, u) in enumerate(zip(echo, user))]
+
+write("far.pcm", far)
+write("mic_echo.pcm", echo)
+write("mic_barge.pcm", barge)
@@ -1,10 +1,21 @@
-use assistant_core::{api, app, config, gatekeeper::ProposedAction};
+use assistant_core::{api, app, config, gatekeeper::ProposedAction, installer};
 use axum::{http::Request, body::{Body, to_bytes}};
 use tower::ServiceExt;
 
+/// Pretends every command succeeds without touching the host.
+struct NoopRunner;
+
+#[async_trait::async_trait]
+impl installer::CommandRunner for NoopRunner {
+    async fn run(&self, _argv: &[String], _lines: Option<tokio::sync::mpsc::UnboundedSender<(installer::Stream, String)>>) -> anyhow::Result<installer::RunOutput> {
+        Ok(installer::RunOutput { code: Some(0), ..Default::default() })
+    }
+}
+
 #[tokio::test]
 async fn installer_apply_requires_approval() {
     let state = app::AppState::new(config::Config::default()).await;
+    state.handles.tools.installer().set_runner(std::sync::Arc::new(NoopRunner));
     let app_router = api::build_router(state.clone());
 
     // Create an approval for an installer action and approve it to get a token
@@ -50,4 +61,16 @@ async fn installer_apply_requires_approval() {
         Request::builder().method("POST").uri("/api/tools/installer/apply_install").header("content-type","application/json").body(Body::from(good_apply.to_string())).unwrap()
     ).await.unwrap();
     assert!(resp.status().is_success());
+    let b = to_bytes(resp.into_body(), 1024*1024).await.unwrap();
+    let applied: serde_json::Value = serde_json::from_slice(&b).unwrap();
+    assert_eq!(applied["applied"], true);
+
+    // Plans survive outside the request and unsafe names are rejected up front
+    let stored = state.handles.tools.installer().get(&plan_id).await.unwrap().expect("plan persisted");
+    assert_eq!(stored.status, "applied");
+    let bad = serde_json::json!({"params": {"pkg": "ripgrep; rm -rf /", "manager": "apt"}});
+    let resp = app_router.clone().oneshot(
+        Request::builder().method("POST").uri("/api/tools/installer/plan_install").header("content-type","application/json").body(Body::from(bad.to_string())).unwrap()
+    ).await.unwrap();
+    assert!(!resp.status().is_success());
 }
@@ -20,3 +20,19 @@ async fn create_and_list_tasks() {
     assert_eq!(resp.status(), StatusCode::OK);
 }
 
+
+#[tokio::test]
+async fn inserts_are_visible_to_the_rest_of_the_pool() {
+    let db = std::env::temp_dir().join(format!("memory_pool_{}.db", uuid::Uuid::new_v4()));
+    let migrations = std::path::PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/migrations"));
+    let mem = assistant_core::memory::Memory::init(db.clone(), migrations).await.unwrap();
+    // Holding a connection makes each insert run on another one
+    let mut held = mem.store.pool().acquire().await.unwrap();
+    for i in 0..200 {
+        let id = mem.store.append_event(None, "test:pool", Some(&serde_json::json!({"i": i}))).await.unwrap();
+        let seen: Option<i64> = sqlx::query_scalar("SELECT id FROM Event WHERE id = ?1").bind(id).fetch_optional(&mut *held).await.unwrap();
+        assert_eq!(seen, Some(id), "insert {} not visible yet", i);
+    }
+    drop(held);
+    let _ = std::fs::remove_file(&db);
+}
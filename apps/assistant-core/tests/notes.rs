@@ -0,0 +1,75 @@
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+
+mod common;
+use common::call;
+
+/// Fake local STT: reports what it was sent so the test can check the audio arrived.
+async fn transcribe(headers: HeaderMap, body: axum::body::Bytes) -> Json<Value> {
+    let sr = headers.get("x-sample-rate").and_then(|v| v.to_str().ok()).unwrap_or("?").to_string();
+    assert_eq!(sr, "16000");
+    Json(json!({"text": format!(" Buy oat milk and eggs ({} samples) ", body.len() / 2)}))
+}
+
+#[tokio::test]
+async fn dictated_note_is_filed_with_audio_and_recalled() {
+    let listener = tokio::net::TcpListener::bind((std::net::Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, Router::new().route("/v1/stt/transcribe", post(transcribe))).await.unwrap(); });
+
+    let home = std::env::temp_dir().join(format!("notes_{}", uuid::Uuid::new_v4()));
+    let cfg: assistant_core::config::Config = toml::from_str(&format!(
+        "[foreman]\nhome = \"{}\"\n[voice]\nwake_phrase = \"hey vim\"\nwake_enabled = false\nstt = {{ endpoint = \"http://{}/v1/stt/transcribe\", streaming = false }}\nnotes = {{ task = \"Inbox\" }}\n",
+        home.display(), addr
+    )).unwrap();
+    let state = assistant_core::app::AppState::new(cfg).await;
+    let app = assistant_core::api::build_router(state.clone());
+
+    // Audio only: transcribed locally, recording kept as an artifact on the default task
+    let samples: Vec<i16> = (0..8_000).map(|i| ((i as f32 * 0.05).sin() * 4_000.0) as i16).collect();
+    let (status, note) = call(&app, "POST", "/api/notes", Some(json!({"samples": samples, "sample_rate": 16000}))).await;
+    assert_eq!(status, StatusCode::OK, "{}", note);
+    assert_eq!(note["text"], "Buy oat milk and eggs (8000 samples)");
+    assert_eq!(note["task"], "Inbox");
+    assert_eq!(note["duration_ms"], 500);
+    let audio = std::path::PathBuf::from(note["audio"].as_str().unwrap());
+    assert!(audio.starts_with(home.join("notes")));
+    assert_eq!(std::fs::read(&audio).unwrap().len(), 44 + 16_000);
+
+    let store = &state.handles.memory.as_ref().expect("memory").store;
+    let atom = store.get_atom_full(note["atom_id"].as_i64().unwrap()).await.unwrap().unwrap();
+    assert_eq!(atom.kind, "note");
+    assert_eq!(atom.source, "voice");
+    assert_eq!(atom.source_ref, Some(format!("artifact:{}", note["artifact_id"])));
+    let artifact = store.get_artifact(note["artifact_id"].as_i64().unwrap()).await.unwrap().unwrap();
+    assert_eq!(artifact.mime.as_deref(), Some("audio/wav"));
+
+    // Text note on a named task, created on demand and reused after
+    let (_, typed) = call(&app, "POST", "/api/notes", Some(json!({"text": "Dentist moved to Friday", "task": "Errands"}))).await;
+    assert_eq!(typed["task"], "Errands");
+    assert!(typed["artifact_id"].is_null());
+    let (_, again) = call(&app, "POST", "/api/notes", Some(json!({"text": "Return library books", "task": "errands"}))).await;
+    assert_eq!(again["task_id"], typed["task_id"]);
+    // Other atom kinds mentioning the same words are not notes
+    store.put_atom(typed["task_id"].as_i64().unwrap(), "fact", "oat milk is in aisle 4", None).await.unwrap();
+
+    let (status, recall) = call(&app, "GET", "/api/notes/recall?q=the%20oat%20milk", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let hits = recall["hits"].as_array().unwrap();
+    assert_eq!(hits.len(), 1, "{}", recall);
+    assert_eq!(hits[0]["atom_id"], note["atom_id"]);
+    assert_eq!(hits[0]["audio"].as_str().map(std::path::PathBuf::from), Some(audio));
+    assert_eq!(recall["spoken"], "You noted one thing about the oat milk: Buy oat milk and eggs (8000 samples).");
+    assert_eq!(recall["spoken_aloud"], false, "no playback in this build");
+
+    let (_, none) = call(&app, "GET", "/api/notes/recall?q=passport", None).await;
+    assert_eq!(none["spoken"], "I couldn't find any notes about passport.");
+
+    assert_eq!(call(&app, "POST", "/api/notes", Some(json!({"text": "  "}))).await.0, StatusCode::BAD_REQUEST);
+    assert_eq!(call(&app, "POST", "/api/notes", Some(json!({"text": "x", "task_id": 999_999}))).await.0, StatusCode::INTERNAL_SERVER_ERROR);
+    #[cfg(not(feature = "realtime-audio"))]
+    assert_eq!(call(&app, "POST", "/api/notes/dictate", Some(json!({}))).await.0, StatusCode::NOT_IMPLEMENTED);
+    let _ = std::fs::remove_dir_all(&home);
+}
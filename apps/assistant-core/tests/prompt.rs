@@ -0,0 +1,53 @@
+use assistant_core::{api, app, config};
+use axum::{body::Body, http::Request, http::StatusCode};
+use http_body_util::BodyExt as _;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+async fn get(app: &axum::Router, uri: &str) -> (StatusCode, Value) {
+    let resp = app.clone().oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap()).await.unwrap();
+    let status = resp.status();
+    let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn prompt_sections_and_games_come_from_config() {
+    let dir = std::env::temp_dir().join(format!("prompt_api_{}", uuid::Uuid::new_v4()));
+    let (sections, roms) = (dir.join("prompts"), dir.join("roms"));
+    std::fs::create_dir_all(sections.clone()).unwrap();
+    std::fs::create_dir_all(roms.join("nds")).unwrap();
+    std::fs::write(roms.join("nds").join("Mario_Kart_DS.nds"), b"").unwrap();
+    std::fs::write(sections.join("10-games.md"), "---\nrequires: [games_by_console]\n---\nGames in {{roms_root}} for {{player}}:\n{{games_by_console}}\n{{emulators}}").unwrap();
+    std::fs::write(sections.join("20-voice.md"), "---\nmode: voice\n---\nBe brief.").unwrap();
+
+    let cfg = config::Config {
+        foreman: Some(config::ForemanConfig { home: Some(dir.join("home").to_string_lossy().to_string()), profile: None }),
+        prompts: Some(config::PromptsConfig {
+            dir: Some(sections.to_string_lossy().to_string()),
+            max_tokens: None,
+            vars: [("player".to_string(), "Sam".to_string())].into_iter().collect(),
+        }),
+        games: Some(config::GamesConfig {
+            roms_root: Some(roms.to_string_lossy().to_string()),
+            emulators: [("nds".to_string(), mcp_games::Emulator { name: Some("Nintendo DS".into()), bin: "melonDS".into(), args: vec![] })].into_iter().collect(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let router = api::build_router(app::AppState::new(cfg).await);
+
+    let (status, text) = get(&router, "/api/prompt").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(text["text"], format!("Games in {} for Sam:\n- nds: Mario Kart DS\n- Nintendo DS (nds): melonDS (no arguments)", roms.display()));
+    assert_eq!(text["sections"][1], json!({"name": "20-voice", "tokens": 0, "skipped": "voice mode only"}));
+    assert_eq!(text["tokens"], text["sections"][0]["tokens"]);
+
+    let (_, voice) = get(&router, "/api/prompt?mode=voice").await;
+    assert!(voice["text"].as_str().unwrap().ends_with("\n\nBe brief."), "{}", voice);
+    assert_eq!(get(&router, "/api/prompt?mode=shout").await.0, StatusCode::BAD_REQUEST);
+
+    let (_, games) = get(&router, "/api/games").await;
+    assert_eq!(games, json!([["nds", ["Mario Kart DS"]]]));
+    let _ = std::fs::remove_dir_all(&dir);
+}
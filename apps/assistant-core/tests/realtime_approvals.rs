@@ -0,0 +1,91 @@
+#![cfg(feature = "realtime")]
+
+use assistant_core::{api, app, config, realtime::RealtimeOptions};
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::{body::Body, extract::State, http::Request, response::IntoResponse, routing::get, Router};
+use tokio::sync::mpsc;
+use tower::ServiceExt;
+
+/// Mock Realtime server: sends one write-class tool call, then the end of a user
+/// turn, and forwards client events up to the tool.output.
+async fn ws_handler(ws: WebSocketUpgrade, State(tx): State<mpsc::UnboundedSender<serde_json::Value>>) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| handle_ws(socket, tx))
+}
+
+async fn handle_ws(mut socket: WebSocket, tx: mpsc::UnboundedSender<serde_json::Value>) {
+    if let Some(Ok(Message::Text(_session_update))) = socket.recv().await {
+        let call = serde_json::json!({"type": "tool.call", "id": "c1", "name": "installer.plan_install", "arguments": {"manager": "apt", "pkg": "ripgrep"}});
+        let _ = socket.send(Message::Text(call.to_string())).await;
+        let _ = socket.send(Message::Text(serde_json::json!({"type": "input_audio_buffer.speech_stopped"}).to_string())).await;
+        while let Some(Ok(msg)) = socket.recv().await {
+            if let Message::Text(txt) = msg {
+                let v: serde_json::Value = serde_json::from_str(&txt).unwrap_or_default();
+                let done = v["type"] == "tool.output";
+                let _ = tx.send(v);
+                if done { break; }
+            }
+        }
+    }
+}
+
+async fn run_with_answer(answer: &str) -> serde_json::Value {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mock = Router::new().route("/realtime", get(ws_handler)).with_state(tx);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, mock).await.unwrap(); });
+
+    let tmp = format!("./storage/test_rt_approval_{}", uuid::Uuid::new_v4());
+    let cfg = config::Config { foreman: Some(config::ForemanConfig { home: Some(tmp), profile: None }), ..Default::default() };
+    let state = app::AppState::new(cfg).await;
+    let router = api::build_router(state.clone());
+    let opts = RealtimeOptions { model: None, voice: None, audio: None, instructions: None, endpoint: Some(format!("ws://{}/realtime", addr)), transport: None, record: None };
+    state.handles.realtime.start(opts).await.unwrap();
+
+    // Wait for the prompt raised by the tool call, then answer it over HTTP
+    let mut prompt = None;
+    for _ in 0..100 {
+        if let Some(p) = state.handles.approval_prompt.read().clone() { prompt = Some(p); break; }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    let prompt = prompt.expect("approval prompt raised");
+    assert_eq!(prompt.details["tool"], "plan_install");
+
+    // The pending approval does not hold up the session: the turn end is answered meanwhile
+    let next = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv()).await.expect("loop keeps running").unwrap();
+    assert_eq!(next["type"], "response.create");
+    assert!(state.handles.approval_prompt.read().is_some());
+
+    let body = serde_json::json!({"id": prompt.id, "answer": answer, "reason": "not now"});
+    let resp = router
+        .oneshot(Request::builder().method("POST").uri("/api/approval/answer").header("content-type", "application/json").body(Body::from(body.to_string())).unwrap())
+        .await
+        .unwrap();
+    assert!(resp.status().is_success());
+
+    let out = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+        loop {
+            let v = rx.recv().await.unwrap();
+            if v["type"] == "tool.output" { return v; }
+        }
+    }).await.expect("tool.output in time");
+    state.handles.realtime.stop().await.unwrap();
+    assert!(state.handles.approval_prompt.read().is_none());
+    out
+}
+
+#[tokio::test]
+async fn deny_returns_structured_refusal() {
+    let out = run_with_answer("no").await;
+    assert_eq!(out["id"], "c1");
+    assert_eq!(out["output"]["decision"], "deny");
+    assert_eq!(out["output"]["approved"], false);
+    assert_eq!(out["output"]["reason"], "not now");
+}
+
+#[tokio::test]
+async fn approve_runs_the_tool() {
+    let out = run_with_answer("approve").await;
+    assert!(out["output"].get("decision").is_none(), "{}", out);
+    assert!(out["output"]["plan_id"].is_string(), "{}", out);
+}
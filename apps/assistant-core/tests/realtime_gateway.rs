@@ -0,0 +1,209 @@
+#![cfg(feature = "realtime-gateway")]
+
+use assistant_core::realtime_gateway::{router, GatewayConfig};
+use axum::{routing::post, Json, Router};
+use futures_util::{SinkExt, StreamExt};
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio_tungstenite::tungstenite::Message;
+
+type Ws = tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+async fn serve(app: Router) -> String {
+    let listener = tokio::net::TcpListener::bind((std::net::Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+    format!("{}:{}", addr.ip(), addr.port())
+}
+
+/// Fake local STT and OpenAI-compatible LLM. The LLM asks for `shell_which` once,
+/// then answers in text after seeing the tool output.
+async fn backends(llm_log: Arc<Mutex<Vec<Value>>>) -> (String, String) {
+    let stt = Router::new().route("/v1/stt/transcribe", post(|| async { Json(json!({"text": "where is echo"})) }));
+    let llm = Router::new().route("/v1/chat/completions", post(move |Json(body): Json<Value>| {
+        let log = llm_log.clone();
+        async move {
+            log.lock().push(body.clone());
+            let last = body["messages"].as_array().and_then(|m| m.last()).cloned().unwrap_or_default();
+            if last["role"] == "tool" || body.get("tools").is_none() {
+                Json(json!({"choices": [{"message": {"role": "assistant", "content": "Echo lives in /bin."}}]}))
+            } else {
+                Json(json!({"choices": [{"message": {"role": "assistant", "content": null, "tool_calls": [
+                    {"id": "call_1", "type": "function", "function": {"name": "shell_which", "arguments": "{\"cmd\":\"echo\"}"}}
+                ]}}]}))
+            }
+        }
+    }));
+    let stt_addr = serve(stt).await;
+    let llm_addr = serve(llm).await;
+    (format!("http://{}/v1/stt/transcribe", stt_addr), format!("http://{}/v1/chat/completions", llm_addr))
+}
+
+async fn connect(cfg: GatewayConfig) -> Ws {
+    let addr = serve(router(cfg)).await;
+    let (ws, _) = tokio_tungstenite::connect_async(format!("ws://{}/v1/realtime", addr)).await.unwrap();
+    ws
+}
+
+async fn send(ws: &mut Ws, v: Value) {
+    ws.send(Message::Text(v.to_string())).await.unwrap();
+}
+
+/// Read events until one of type `until`; returns everything seen (inclusive).
+async fn read_until(ws: &mut Ws, until: &str) -> Vec<Value> {
+    let mut seen = vec![];
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(5);
+    loop {
+        let msg = tokio::time::timeout_at(deadline, ws.next()).await
+            .unwrap_or_else(|_| panic!("timed out waiting for {}; saw {:?}", until, seen.iter().map(|v: &Value| v["type"].clone()).collect::<Vec<_>>()));
+        let Some(Ok(Message::Text(t))) = msg else { continue };
+        let v: Value = serde_json::from_str(&t).unwrap();
+        let done = v["type"] == until;
+        seen.push(v);
+        if done { return seen; }
+    }
+}
+
+fn has(events: &[Value], typ: &str) -> bool {
+    events.iter().any(|e| e["type"] == typ)
+}
+
+fn audio_b64(pcm: &[i16]) -> String {
+    use base64::Engine as _;
+    let bytes: Vec<u8> = pcm.iter().flat_map(|s| s.to_le_bytes()).collect();
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+#[tokio::test]
+async fn spoken_turn_runs_tool_call_and_speaks_the_answer() {
+    let llm_log = Arc::new(Mutex::new(vec![]));
+    let (stt, llm) = backends(llm_log.clone()).await;
+    // 0.1 s of silence at 22.05 kHz stands in for a TTS engine
+    let cfg = GatewayConfig { stt_endpoint: Some(stt), llm_url: llm, tts_cmd: Some("head -c 4410 /dev/zero".into()), ..Default::default() };
+    let mut ws = connect(cfg).await;
+    read_until(&mut ws, "session.created").await;
+
+    send(&mut ws, json!({"type": "session.update", "session": {
+        "instructions": "You are Foreman.", "output_audio_format": "pcm16", "turn_detection": {"type": "server_vad"},
+        "tools": [{"type": "function", "name": "shell_which", "description": "PATH lookup", "parameters": {"type": "object", "properties": {"cmd": {"type": "string"}}}}]
+    }})).await;
+    read_until(&mut ws, "session.updated").await;
+
+    // 400 ms of "speech" then 800 ms of silence, in 40 ms frames like the capture loop
+    let speech: Vec<i16> = (0..6_400).map(|i| ((i as f32 * 0.12).sin() * 8_000.0) as i16).collect();
+    let audio: Vec<i16> = speech.into_iter().chain(std::iter::repeat_n(0, 12_800)).collect();
+    for frame in audio.chunks(640) {
+        send(&mut ws, json!({"type": "input_audio_buffer.append", "audio": audio_b64(frame)})).await;
+    }
+    let events = read_until(&mut ws, "conversation.item.input_audio_transcription.completed").await;
+    assert!(has(&events, "input_audio_buffer.speech_started"));
+    assert!(has(&events, "input_audio_buffer.speech_stopped"));
+    assert_eq!(events.last().unwrap()["transcript"], "where is echo");
+
+    // The bridge asks for a response on speech_stopped; the model calls a tool
+    send(&mut ws, json!({"type": "response.create", "response": {"modalities": ["audio", "text"]}})).await;
+    let events = read_until(&mut ws, "response.done").await;
+    assert!(has(&events, "response.created"));
+    let out = &events.last().unwrap()["response"]["output"][0];
+    assert_eq!(out["type"], "function_call");
+    assert_eq!(out["name"], "shell_which");
+    assert_eq!(out["call_id"], "call_1");
+    assert_eq!(out["arguments"], "{\"cmd\":\"echo\"}");
+
+    send(&mut ws, json!({"type": "conversation.item.create", "item": {"type": "function_call_output", "call_id": "call_1", "output": "{\"path\":\"/bin/echo\"}"}})).await;
+    send(&mut ws, json!({"type": "response.create", "response": {"modalities": ["audio", "text"]}})).await;
+    let events = read_until(&mut ws, "response.done").await;
+    assert!(has(&events, "response.audio.delta"), "TTS audio is streamed");
+    assert!(has(&events, "response.audio.done"));
+    let transcript = events.iter().find(|e| e["type"] == "response.audio_transcript.done").unwrap();
+    assert_eq!(transcript["transcript"], "Echo lives in /bin.");
+    let out = &events.last().unwrap()["response"]["output"][0];
+    assert_eq!(out["type"], "message");
+    assert_eq!(out["role"], "assistant");
+
+    let log = llm_log.lock();
+    assert_eq!(log.len(), 2);
+    assert_eq!(log[0]["messages"][0], json!({"role": "system", "content": "You are Foreman."}));
+    assert_eq!(log[0]["messages"][1], json!({"role": "user", "content": "where is echo"}));
+    assert_eq!(log[0]["tools"][0]["function"]["name"], "shell_which");
+    let msgs = log[1]["messages"].as_array().unwrap();
+    assert_eq!(msgs[2]["tool_calls"][0]["id"], "call_1");
+    assert_eq!(msgs[3], json!({"role": "tool", "tool_call_id": "call_1", "content": "{\"path\":\"/bin/echo\"}"}));
+}
+
+#[tokio::test]
+async fn response_cancel_stops_an_in_flight_answer() {
+    let (stt, llm) = backends(Arc::new(Mutex::new(vec![]))).await;
+    let cfg = GatewayConfig { stt_endpoint: Some(stt), llm_url: llm, tts_cmd: Some("sleep 5; head -c 100 /dev/zero".into()), ..Default::default() };
+    let mut ws = connect(cfg).await;
+    read_until(&mut ws, "session.created").await;
+
+    send(&mut ws, json!({"type": "conversation.item.create", "item": {"type": "message", "role": "user", "content": [{"type": "input_text", "text": "hello"}]}})).await;
+    read_until(&mut ws, "conversation.item.created").await;
+    send(&mut ws, json!({"type": "response.create"})).await;
+    read_until(&mut ws, "response.audio_transcript.delta").await;
+
+    send(&mut ws, json!({"type": "response.create", "event_id": "dup"})).await;
+    let err = read_until(&mut ws, "error").await.pop().unwrap();
+    assert_eq!(err["error"]["code"], "conversation_already_has_active_response");
+    assert_eq!(err["error"]["event_id"], "dup");
+
+    send(&mut ws, json!({"type": "response.cancel"})).await;
+    let done = read_until(&mut ws, "response.done").await.pop().unwrap();
+    assert_eq!(done["response"]["status"], "cancelled");
+}
+
+#[test]
+fn settings_come_from_the_voice_config() {
+    std::env::set_var("GATEWAY_TEST_KEY", "sk-local");
+    let cfg: assistant_core::config::Config = toml::from_str(r#"
+        [voice]
+        stt = { endpoint = "http://127.0.0.1:9000/v1/stt/transcribe" }
+        tts = { engine = "piper", voice = "en_US-amy", sample_rate = 16000 }
+        [voice.realtime_gateway]
+        llm_url = "http://127.0.0.1:8080/v1/chat/completions"
+        llm_model = "qwen2.5"
+        llm_api_key_env = "GATEWAY_TEST_KEY"
+        silence_ms = 900
+    "#).unwrap();
+    let g = GatewayConfig::from_config(&cfg);
+    assert_eq!(g.llm_url, "http://127.0.0.1:8080/v1/chat/completions");
+    assert_eq!((g.llm_model.as_str(), g.llm_api_key.as_deref()), ("qwen2.5", Some("sk-local")));
+    assert_eq!(g.stt_endpoint.as_deref(), Some("http://127.0.0.1:9000/v1/stt/transcribe"));
+    assert_eq!((g.tts_cmd.as_deref(), g.tts_sr), (Some("piper --model en_US-amy --output-raw"), 16000));
+    assert_eq!((g.silence_ms, g.in_sr), (900, 16_000));
+
+    let d = GatewayConfig::from_config(&assistant_core::config::Config::default());
+    assert_eq!((d.llm_url, d.tts_cmd), (GatewayConfig::default().llm_url, None));
+
+    // Without an llm_url the gateway follows the core chat provider
+    std::env::set_var("OPENAI_BASE_URL", "http://127.0.0.1:8081/v1/");
+    std::env::set_var("OPENAI_MODEL", "gpt-test");
+    std::env::set_var("OPENAI_API_KEY", "sk-core");
+    let c = GatewayConfig::from_config(&assistant_core::config::Config::default());
+    assert_eq!(c.llm_url, "http://127.0.0.1:8081/v1/chat/completions");
+    assert_eq!((c.llm_model.as_str(), c.llm_api_key.as_deref()), ("gpt-test", Some("sk-core")));
+    let g = GatewayConfig::from_config(&cfg);
+    assert_eq!((g.llm_url.as_str(), g.llm_api_key.as_deref()), ("http://127.0.0.1:8080/v1/chat/completions", Some("sk-local")));
+}
+
+#[tokio::test]
+async fn events_are_handled_while_a_turn_is_transcribed() {
+    let stt = Router::new().route("/v1/stt/transcribe", post(|| async {
+        tokio::time::sleep(std::time::Duration::from_millis(800)).await;
+        Json(json!({"text": "slow words"}))
+    }));
+    let stt = format!("http://{}/v1/stt/transcribe", serve(stt).await);
+    let mut ws = connect(GatewayConfig { stt_endpoint: Some(stt), ..Default::default() }).await;
+    read_until(&mut ws, "session.created").await;
+
+    send(&mut ws, json!({"type": "input_audio_buffer.append", "audio": audio_b64(&[1_000; 3_200])})).await;
+    send(&mut ws, json!({"type": "input_audio_buffer.commit"})).await;
+    send(&mut ws, json!({"type": "session.update", "session": {"instructions": "Be brief."}})).await;
+    let events = read_until(&mut ws, "session.updated").await;
+    assert!(has(&events, "input_audio_buffer.committed"));
+    assert!(!has(&events, "conversation.item.input_audio_transcription.completed"), "the update waited on STT");
+    let done = read_until(&mut ws, "conversation.item.input_audio_transcription.completed").await.pop().unwrap();
+    assert_eq!(done["transcript"], "slow words");
+}
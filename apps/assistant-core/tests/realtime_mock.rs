@@ -33,7 +33,8 @@ async fn handle_ws(mut socket: WebSocket) {
             assert_eq!(v.get("id").and_then(|s| s.as_str()), Some("1"));
         }
     }
-    let _ = socket.send(Message::Close(None)).await;
+    // Stay connected until the client hangs up
+    while let Some(Ok(_)) = socket.recv().await {}
 }
 
 #[tokio::test]
@@ -45,12 +46,15 @@ async fn realtime_connects_to_mock() {
     tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
 
     // Start the realtime manager pointing to the mock endpoint
-    let rt = RealtimeManager::new();
+    let rt = RealtimeManager::default();
     let endpoint = format!("ws://{}:{}/realtime", addr.ip(), addr.port());
-    rt.start(RealtimeOptions { model: Some("gpt-realtime".into()), voice: None, audio: None, instructions: None, endpoint: Some(endpoint) }).await.unwrap();
+    rt.start(RealtimeOptions { model: Some("gpt-realtime".into()), voice: None, audio: None, instructions: None, endpoint: Some(endpoint), transport: None, record: None }).await.unwrap();
 
-    // Wait briefly for state to flip to active and to process one tool call
-    tokio::time::sleep(std::time::Duration::from_millis(80)).await;
+    // Wait for state to flip to active and to process one tool call
+    for _ in 0..100 {
+        if rt.status().active { break; }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
     let st = rt.status();
     assert!(st.active, "status should be active after connect");
 
@@ -0,0 +1,159 @@
+#![cfg(feature = "realtime")]
+
+use assistant_core::realtime::{RealtimeManager, RealtimeOptions};
+use assistant_core::realtime_record::{Direction, RecordedEvent, RecordedFrame, RecordingMeta, SessionBundle, SessionRecorder};
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::response::IntoResponse;
+use axum::{routing::get, Router};
+use parking_lot::RwLock;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+fn manager(home: &Path) -> RealtimeManager {
+    RealtimeManager::new(
+        assistant_core::tools::ToolsManager::default(),
+        Arc::new(assistant_core::gatekeeper::PolicyEngine::default()),
+        Arc::new(RwLock::new(None)),
+        assistant_core::gatekeeper::PromptDecisions::default(),
+        Some(home.join("chats")),
+    )
+}
+
+fn tmp_home() -> PathBuf {
+    let p = std::env::temp_dir().join(format!("rt_replay_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(p.join("chats")).unwrap();
+    p
+}
+
+/// Messages in the (single) chat file under `<home>/chats`.
+fn chat_messages(home: &Path) -> Vec<Value> {
+    let Some(entry) = std::fs::read_dir(home.join("chats")).unwrap().flatten().next() else { return vec![] };
+    let v: Value = serde_json::from_slice(&std::fs::read(entry.path()).unwrap()).unwrap();
+    v["messages"].as_array().cloned().unwrap_or_default()
+}
+
+/// Event ids are random per run; compare everything else.
+fn strip_ids(events: Vec<Value>) -> Vec<Value> {
+    events.into_iter().map(|mut e| { e.as_object_mut().unwrap().remove("event_id"); e }).collect()
+}
+
+async fn ws_handler(ws: WebSocketUpgrade) -> impl IntoResponse { ws.on_upgrade(scripted) }
+
+/// One scripted turn: a spoken answer, then a tool call on response.done.
+async fn scripted(mut socket: WebSocket) {
+    let Some(Ok(Message::Text(_update))) = socket.recv().await else { return };
+    let script = [
+        json!({"type": "session.updated"}),
+        json!({"type": "response.created", "response": {"id": "resp_1"}}),
+        json!({"type": "response.audio_transcript.delta", "delta": "Let me "}),
+        json!({"type": "response.audio_transcript.delta", "delta": "check."}),
+        json!({"type": "response.audio_transcript.done", "transcript": "Let me check."}),
+        json!({"type": "response.audio.delta", "item_id": "item_1", "delta": "AAAAAA=="}),
+        json!({"type": "response.audio.done"}),
+        json!({"type": "response.done", "response": {"id": "resp_1", "output": [
+            {"type": "function_call", "name": "shell.which", "call_id": "call_1", "arguments": "{\"cmd\":\"echo\"}"}
+        ]}}),
+    ];
+    for ev in script {
+        let _ = socket.send(Message::Text(ev.to_string())).await;
+    }
+    // function_call_output, then response.create
+    for _ in 0..2 { let _ = socket.recv().await; }
+    let _ = socket.send(Message::Close(None)).await;
+    while let Some(Ok(_)) = socket.recv().await {}
+}
+
+#[tokio::test]
+async fn recorded_session_replays_to_the_same_client_events() {
+    let app = Router::new().route("/realtime", get(ws_handler));
+    let listener = tokio::net::TcpListener::bind((std::net::Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+
+    let live_home = tmp_home();
+    let rt = manager(&live_home);
+    let opts = RealtimeOptions { model: None, voice: None, audio: None, instructions: None, endpoint: Some(format!("ws://{}/realtime", addr)), transport: None, record: Some(true) };
+    rt.start(opts).await.unwrap();
+    let mut id = None;
+    for _ in 0..200 {
+        let st = rt.status();
+        if id.is_none() { id = st.recording.clone(); }
+        if id.is_some() && !st.active { break; }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    rt.stop().await.unwrap();
+    let id = id.expect("session was recorded");
+
+    let bundle = SessionBundle::load(&live_home.join("realtime_sessions").join(&id)).unwrap();
+    assert!(bundle.meta.ended_at.is_some());
+    assert_eq!(bundle.inbound().count(), 9, "eight scripted events and the close");
+    // Assistant audio is in playback.pcm, not repeated as base64 in the log
+    let delta = bundle.inbound().find_map(|e| match &e.frame {
+        RecordedFrame::Json { event } if event["type"] == "response.audio.delta" => Some(event),
+        _ => None,
+    }).unwrap();
+    assert!(delta.get("delta").is_none(), "{}", delta);
+    assert_eq!(delta["audio_bytes"], 4);
+    let recorded = bundle.outbound_json();
+    assert_eq!(recorded[0]["type"], "session.update");
+
+    let replay_home = tmp_home();
+    let out = manager(&replay_home).replay(&bundle).await;
+    // Everything the live client sent after session.update is reproduced
+    let live: Vec<Value> = recorded[1..].iter().map(|v| (*v).clone()).collect();
+    assert_eq!(strip_ids(out.outbound.clone()), strip_ids(live));
+    assert_eq!(out.outbound[0]["type"], "conversation.item.create");
+    assert_eq!(out.outbound[0]["item"]["call_id"], "call_1");
+    assert_eq!(out.outbound[1]["type"], "response.create");
+    assert_eq!(out.processed_calls, vec!["call_1".to_string()]);
+    assert!(out.response_active, "response.create after the tool output is in flight");
+    assert!(out.assistant_flushed);
+    assert!(!out.playing_audio);
+    assert!(!out.active, "server close ends the session");
+
+    // Transcript flushed to chat exactly once, in both runs
+    for home in [&live_home, &replay_home] {
+        let msgs = chat_messages(home);
+        assert_eq!(msgs.len(), 1, "{:?}", msgs);
+        assert_eq!(msgs[0]["role"], "assistant");
+        assert_eq!(msgs[0]["content"], "Let me check.");
+    }
+    let _ = std::fs::remove_dir_all(&live_home);
+    let _ = std::fs::remove_dir_all(&replay_home);
+}
+
+#[tokio::test]
+async fn handwritten_bundle_exercises_flush_and_response_guards() {
+    let home = tmp_home();
+    let rec = SessionRecorder::create(&home.join("bundles"), RecordingMeta::new(None, None, 16_000, 24_000, "pcm16")).unwrap();
+    for ev in [
+        json!({"type": "input_audio_buffer.speech_stopped"}),
+        // Second speech_stopped while the first response is pending must not create another
+        json!({"type": "input_audio_buffer.speech_stopped"}),
+        json!({"type": "response.created"}),
+        json!({"type": "response.text.delta", "delta": "Hi"}),
+        json!({"type": "response.text.done"}),
+        // A late transcript.done after the flush must not append twice
+        json!({"type": "response.audio_transcript.done"}),
+        json!({"type": "response.done", "response": {"output": []}}),
+        json!({"type": "error", "error": {"message": "boom"}}),
+    ] {
+        rec.text(Direction::In, &ev.to_string());
+    }
+    rec.finish();
+    let bundle = SessionBundle::load(&rec.dir()).unwrap();
+    assert!(matches!(bundle.events[0], RecordedEvent { dir: Direction::In, frame: RecordedFrame::Json { .. }, .. }));
+
+    let out = manager(&home).replay(&bundle).await;
+    let types: Vec<&str> = out.outbound.iter().map(|e| e["type"].as_str().unwrap()).collect();
+    assert_eq!(types, vec!["response.create"]);
+    assert!(out.assistant_flushed);
+    assert!(!out.response_active);
+    assert!(out.active && !out.closed);
+    assert_eq!(out.last_error.as_deref(), Some("realtime error: boom"));
+    let msgs = chat_messages(&home);
+    assert_eq!(msgs.len(), 1);
+    assert_eq!(msgs[0]["content"], "Hi");
+    let _ = std::fs::remove_dir_all(&home);
+}
@@ -0,0 +1,120 @@
+#![cfg(feature = "realtime")]
+
+use assistant_core::{api, app, config, realtime::RealtimeOptions};
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::{extract::State, http::StatusCode, response::IntoResponse, routing::get, Router};
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+
+mod common;
+use common::call;
+
+async fn ws_handler(ws: WebSocketUpgrade, State(tx): State<mpsc::UnboundedSender<String>>) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| handle_ws(socket, tx))
+}
+
+/// Mock Realtime server. Reports the instructions it was configured with; on a
+/// first call (no call history yet) plays one user turn, one assistant turn, a
+/// held tool call and an error, then hangs up.
+async fn handle_ws(mut socket: WebSocket, tx: mpsc::UnboundedSender<String>) {
+    let Some(Ok(Message::Text(update))) = socket.recv().await else { return };
+    let v: Value = serde_json::from_str(&update).unwrap_or_default();
+    let instructions = v["session"]["instructions"].as_str().unwrap_or("").to_string();
+    let _ = tx.send(instructions.clone());
+    if !instructions.contains("Recent calls:") {
+        for ev in [
+            json!({"type": "input_audio_buffer.transcription.delta", "delta": "Install ripgrep "}),
+            json!({"type": "input_audio_buffer.transcription.delta", "delta": "please"}),
+            json!({"type": "input_audio_buffer.transcription.completed"}),
+            json!({"type": "response.created", "response": {"id": "resp_1"}}),
+            json!({"type": "response.audio_transcript.delta", "delta": "Planning that now."}),
+            json!({"type": "response.audio_transcript.done"}),
+            json!({"type": "tool.call", "id": "c1", "name": "installer.plan_install", "arguments": {"manager": "apt", "pkg": "ripgrep"}}),
+        ] {
+            let _ = socket.send(Message::Text(ev.to_string())).await;
+        }
+        while let Some(Ok(Message::Text(txt))) = socket.recv().await {
+            if serde_json::from_str::<Value>(&txt).unwrap_or_default()["type"] == "tool.output" { break; }
+        }
+        let _ = socket.send(Message::Text(json!({"type": "error", "error": {"message": "rate limited"}}).to_string())).await;
+    }
+    let _ = socket.send(Message::Close(None)).await;
+    while let Some(Ok(_)) = socket.recv().await {}
+}
+
+/// Poll the listing until the newest session is closed.
+async fn wait_ended(router: &Router, count: usize) -> Value {
+    for _ in 0..200 {
+        let (_, list) = call(router, "GET", "/api/realtime/sessions", None).await;
+        let sessions = list["sessions"].as_array().cloned().unwrap_or_default();
+        if sessions.len() == count && sessions[0]["status"] != "active" { return sessions[0].clone(); }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    panic!("session {} did not end", count);
+}
+
+#[tokio::test]
+async fn calls_are_journaled_and_seed_the_next_call() {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mock = Router::new().route("/realtime", get(ws_handler)).with_state(tx);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, mock).await.unwrap(); });
+
+    let home = std::env::temp_dir().join(format!("rt_sessions_{}", uuid::Uuid::new_v4()));
+    let cfg = config::Config { foreman: Some(config::ForemanConfig { home: Some(home.to_string_lossy().to_string()), profile: None }), ..Default::default() };
+    let state = app::AppState::new(cfg).await;
+    let router = api::build_router(state.clone());
+    let opts = || RealtimeOptions { model: None, voice: None, audio: None, instructions: None, endpoint: Some(format!("ws://{}/realtime", addr)), transport: None, record: None };
+
+    state.handles.realtime.start(opts()).await.unwrap();
+    let first = rx.recv().await.unwrap();
+    assert!(!first.contains("Recent calls:"));
+    // The tool call is held for approval; decline it
+    let mut prompt = None;
+    for _ in 0..100 {
+        if let Some(p) = state.handles.approval_prompt.read().clone() { prompt = Some(p); break; }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    let prompt = prompt.expect("approval prompt raised");
+    let (status, _) = call(&router, "POST", "/api/approval/answer", Some(json!({"id": prompt.id, "answer": "no"}))).await;
+    assert!(status.is_success());
+
+    let summary = wait_ended(&router, 1).await;
+    assert_eq!(summary["status"], "ended");
+    assert_eq!(summary["model"], "gpt-realtime");
+    assert_eq!((summary["turns"].as_u64(), summary["tool_calls"].as_u64(), summary["errors"].as_u64()), (Some(2), Some(1), Some(1)));
+
+    let (status, detail) = call(&router, "GET", &format!("/api/realtime/sessions/{}", summary["id"].as_str().unwrap()), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(detail["ended_at"].is_string());
+    let turns: Vec<(&str, &str)> = detail["turns"].as_array().unwrap().iter().map(|t| (t["role"].as_str().unwrap(), t["text"].as_str().unwrap())).collect();
+    assert_eq!(turns, vec![("user", "Install ripgrep please"), ("assistant", "Planning that now.")]);
+    let tool = &detail["tool_calls"][0];
+    assert_eq!(tool["call_id"], "c1");
+    assert_eq!(tool["name"], "installer.plan_install");
+    assert_eq!(tool["arguments"], json!({"manager": "apt", "pkg": "ripgrep"}));
+    assert_eq!(tool["ok"], false);
+    assert_eq!(tool["result"]["decision"], "deny");
+    assert_eq!(tool["approval"], "deny");
+    assert_eq!(tool["approval_id"], json!(prompt.id));
+    assert_eq!(detail["errors"][0]["message"], "rate limited");
+    // The call is a task in memory like any other
+    let store = &state.handles.memory.as_ref().expect("memory").store;
+    let task = store.list_tasks().await.unwrap().into_iter().find(|t| t.id == detail["task_id"].as_i64().unwrap()).unwrap();
+    assert_eq!((task.status.as_str(), task.tags.as_deref()), ("done", Some("realtime")));
+
+    // The next call is seeded from the journal, not the chat file
+    state.handles.realtime.start(opts()).await.unwrap();
+    let second = rx.recv().await.unwrap();
+    let history = second.split("Recent calls:\n").nth(1).expect("call history in instructions");
+    assert!(history.ends_with("user: Install ripgrep please\nassistant: Planning that now.\ntool installer.plan_install {\"manager\":\"apt\",\"pkg\":\"ripgrep\"} (denied by user)"), "{}", history);
+    wait_ended(&router, 2).await;
+
+    // Same history in the memory pack
+    let (_, pack) = call(&router, "POST", "/api/context/pack", Some(json!({"token_budget": 2048}))).await;
+    assert!(pack["recent_calls"].as_str().unwrap_or("").contains("assistant: Planning that now."), "{}", pack);
+
+    assert_eq!(call(&router, "GET", "/api/realtime/sessions/nope", None).await.0, StatusCode::NOT_FOUND);
+    let _ = std::fs::remove_dir_all(&home);
+}
@@ -0,0 +1,116 @@
+#![cfg(feature = "realtime")]
+
+use assistant_core::stt::{open_stream, LocalStreamingStt, SttEngine, SttEvent, SttOptions};
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::Query;
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+async fn serve(app: Router) -> String {
+    let listener = tokio::net::TcpListener::bind((std::net::Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+    addr.to_string()
+}
+
+/// Fake voice daemon: a partial per 100 ms of audio, a final on end that reports
+/// what it received.
+async fn daemon_ws(ws: WebSocketUpgrade, Query(q): Query<HashMap<String, String>>) -> impl IntoResponse {
+    let sr = q.get("sample_rate").cloned().unwrap_or_default();
+    ws.on_upgrade(move |socket| daemon(socket, sr))
+}
+
+async fn daemon(mut socket: WebSocket, sr: String) {
+    let words = ["hey", "vim", "open", "notes"];
+    let mut samples = 0usize;
+    let mut sent = 0usize;
+    while let Some(Ok(msg)) = socket.recv().await {
+        match msg {
+            Message::Binary(b) => {
+                samples += b.len() / 2;
+                while sent < samples / 1_600 && sent < words.len() {
+                    sent += 1;
+                    let _ = socket.send(Message::Text(json!({"type": "partial", "text": words[..sent].join(" ")}).to_string())).await;
+                }
+            }
+            Message::Text(t) if t.contains("\"end\"") => {
+                let text = format!("{} ({} samples @ {})", words[..sent].join(" "), samples, sr);
+                let _ = socket.send(Message::Text(json!({"type": "final", "text": text}).to_string())).await;
+            }
+            _ => {}
+        }
+    }
+}
+
+fn daemon_router() -> Router {
+    Router::new()
+        .route("/v1/stt/stream", get(daemon_ws))
+        .route("/v1/stt/transcribe", post(|| async { Json(json!({"text": "batch transcript"})) }))
+}
+
+#[tokio::test]
+async fn streaming_client_delivers_partials_then_final() {
+    let addr = serve(daemon_router()).await;
+    let engine = LocalStreamingStt::new(format!("ws://{}/v1/stt/stream", addr));
+    let mut stream = engine.open(16_000).await.unwrap();
+    let mut partials = vec![];
+    for _ in 0..10 {
+        assert!(stream.push(&[100i16; 480]));
+        // Let the daemon answer between frames, as live capture would
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        while let Some(ev) = stream.try_event() {
+            if let SttEvent::Partial { text } = ev { partials.push(text); }
+        }
+    }
+    let final_text = stream.finish().await.unwrap();
+    assert_eq!(final_text, "hey vim open (4800 samples @ 16000)");
+    assert!(!partials.is_empty());
+    assert!(partials.windows(2).all(|w| w[1].starts_with(&w[0])), "partials grow: {:?}", partials);
+}
+
+#[tokio::test]
+async fn falls_back_to_batch_when_streaming_is_down() {
+    let addr = serve(daemon_router()).await;
+    let opts = SttOptions {
+        stream_endpoint: Some("ws://127.0.0.1:9/v1/stt/stream".into()),
+        endpoint: Some(format!("http://{}/v1/stt/transcribe", addr)),
+        batch_only: false,
+    };
+    let mut stream = open_stream(&opts, 16_000).await.unwrap();
+    stream.push(&[0i16; 1_600]);
+    stream.end();
+    assert_eq!(stream.next_event().await, Some(SttEvent::Final { text: "batch transcript".into() }));
+}
+
+#[tokio::test]
+async fn core_endpoint_relays_the_daemon_stream() {
+    let daemon = serve(daemon_router()).await;
+    let cfg: assistant_core::config::Config = toml::from_str(&format!(
+        "[voice]\nwake_phrase = \"hey vim\"\nwake_enabled = false\nstt = {{ stream_endpoint = \"ws://{}/v1/stt/stream\" }}\n",
+        daemon
+    )).unwrap();
+    let state = assistant_core::app::AppState::new(cfg).await;
+    let core = serve(assistant_core::api::build_router(state)).await;
+
+    let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{}/api/stt/stream?sample_rate=16000", core)).await.unwrap();
+    use tokio_tungstenite::tungstenite::Message as M;
+    for _ in 0..4 {
+        ws.send(M::Binary(vec![0u8; 960])).await.unwrap();
+    }
+    ws.send(M::Text(json!({"type": "end"}).to_string())).await.unwrap();
+    let mut events = vec![];
+    while let Some(Ok(M::Text(t))) = ws.next().await {
+        let v: Value = serde_json::from_str(&t).unwrap();
+        let done = v["type"] != "partial";
+        events.push(v);
+        if done { break; }
+    }
+    let last = events.last().unwrap();
+    assert_eq!(last["type"], "final");
+    assert_eq!(last["text"], "hey (1920 samples @ 16000)");
+    assert!(events.iter().any(|e| e["type"] == "partial" && e["text"] == "hey"));
+}
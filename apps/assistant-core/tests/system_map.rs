@@ -7,13 +7,14 @@ async fn digest_is_deterministic_and_bounded() {
     use assistant_core::system_map::{digest::compute_digest, model::*};
     let map = SystemMap {
         scanned_at: chrono::Utc::now(),
-        hardware: HardwareInfo { cpu_model: Some("Test CPU".into()), gpu_model: None, ram_gb: Some(16.0) },
+        hardware: HardwareInfo { cpu_model: Some("Test CPU".into()), gpu_model: None, ram_gb: Some(16.0), gpus: vec![] },
         os: OsInfo { name: "TestOS".into(), version: Some("1.0".into()), kernel: Some("k1".into()), arch: Some("x86_64".into()) },
         runtimes: RuntimesInfo { python: Some("Python 3.10".into()), node: Some("v18".into()), rustc: Some("rustc 1.78".into()), cargo: Some("cargo 1.78".into()), java: None, cuda: None },
         package_managers: vec!["apt".into(), "pip".into(), "cargo".into()],
         apps: vec!["docker".into(), "vscode".into(), "git".into()],
         dev_env: DevEnvInfo { editors: vec!["vscode".into()], vcs: vec!["git".into()] },
-        network: NetworkInfo { hostname: Some("host".into()), interfaces: vec![] },
+        network: NetworkInfo { hostname: Some("host".into()), interfaces: vec![], links: vec![] },
+        ..Default::default()
     };
     let d1 = compute_digest(&map);
     let d2 = compute_digest(&map);
@@ -26,7 +27,7 @@ async fn digest_is_deterministic_and_bounded() {
 async fn map_persisted_and_event_emitted_on_change() {
     // Use a temp directory for home
     let tmp = std::path::PathBuf::from(format!("./storage/test_map_{}", uuid::Uuid::new_v4()));
-    let cfg = config::Config { foreman: Some(config::ForemanConfig { home: Some(tmp.to_string_lossy().to_string()), profile: None }), voice: None, schedules: None, mcp: None };
+    let cfg = config::Config { foreman: Some(config::ForemanConfig { home: Some(tmp.to_string_lossy().to_string()), profile: None }), voice: None, schedules: None, mcp: None, system_map: None, chat: None, prompts: None, games: None, fs: None };
     let state = app::AppState::new(cfg).await;
     let app_router = api::build_router(state.clone());
 
@@ -34,13 +35,14 @@ async fn map_persisted_and_event_emitted_on_change() {
     use assistant_core::system_map::model::*;
     let fixed = SystemMap {
         scanned_at: chrono::Utc::now(),
-        hardware: HardwareInfo { cpu_model: Some("Test CPU".into()), gpu_model: None, ram_gb: Some(8.0) },
+        hardware: HardwareInfo { cpu_model: Some("Test CPU".into()), gpu_model: None, ram_gb: Some(8.0), gpus: vec![] },
         os: OsInfo { name: "TestOS".into(), version: Some("1.0".into()), kernel: Some("k".into()), arch: Some("x86_64".into()) },
         runtimes: RuntimesInfo::default(),
         package_managers: vec!["apt".into()],
         apps: vec!["git".into()],
         dev_env: DevEnvInfo::default(),
         network: NetworkInfo::default(),
+        ..Default::default()
     };
     state.handles.system_map.update_with(fixed).await.expect("update_with ok");
     let map_path = state.handles.system_map.map_path().to_path_buf();
@@ -56,3 +58,130 @@ async fn map_persisted_and_event_emitted_on_change() {
         assert!(has_event, "expected system_map:updated event");
     }
 }
+
+#[tokio::test]
+async fn map_uris_expand_scanner_results() {
+    let tmp = std::path::PathBuf::from(format!("./storage/test_map_{}", uuid::Uuid::new_v4()));
+    let cfg = config::Config { foreman: Some(config::ForemanConfig { home: Some(tmp.to_string_lossy().to_string()), profile: None }), ..Default::default() };
+    let state = app::AppState::new(cfg).await;
+    let app_router = api::build_router(state.clone());
+
+    use assistant_core::system_map::model::*;
+    let fixed = SystemMap {
+        scanned_at: chrono::Utc::now(),
+        packages: vec![
+            PackageEntry { manager: "dpkg".into(), name: "retroarch".into(), version: Some("1.16".into()) },
+            PackageEntry { manager: "dpkg".into(), name: "qemu-utils".into(), version: None },
+            PackageEntry { manager: "pip".into(), name: "requests".into(), version: Some("2.31.0".into()) },
+        ],
+        containers: vec![ContainerEntry { runtime: "docker".into(), id: "abc".into(), name: "db".into(), image: "postgres".into(), state: "running".into(), status: None }],
+        repos: vec![RepoEntry { path: "/src/foreman".into(), branch: Some("main".into()), remote: None, worktrees: vec!["/src/foreman-wt".into()] }],
+        ..Default::default()
+    };
+    state.handles.system_map.update_with(fixed).await.expect("update_with ok");
+
+    let sm = &state.handles.system_map;
+    assert_eq!(sm.resolve_uri("map://packages").unwrap()["counts"]["dpkg"], 2);
+    assert_eq!(sm.resolve_uri("map://packages/pip").unwrap()["packages"][0]["name"], "requests");
+    assert_eq!(sm.resolve_uri("map://emulators").unwrap()["emulators"], serde_json::json!(["retroarch"]));
+    assert_eq!(sm.resolve_uri("map://worktrees").unwrap()["repos"][0]["worktrees"][0], "/src/foreman-wt");
+    assert!(sm.resolve_uri("map://nope").is_none());
+    let digest = sm.get_digest();
+    assert!(digest.contains("Containers: 1 (1 running)"), "{}", digest);
+
+    // Expandable through the context API
+    let body = serde_json::json!({"handle": "map://containers"});
+    let resp = app_router
+        .oneshot(Request::builder().method("POST").uri("/api/context/expand").header("content-type", "application/json").body(Body::from(body.to_string())).unwrap())
+        .await
+        .unwrap();
+    assert!(resp.status().is_success());
+    let b = axum::body::to_bytes(resp.into_body(), 1024 * 1024).await.unwrap();
+    let v: serde_json::Value = serde_json::from_slice(&b).unwrap();
+    assert!(v["chunk"].as_str().unwrap().contains("postgres"));
+}
+
+#[tokio::test]
+async fn diff_history_is_recorded_and_served() {
+    let tmp = std::path::PathBuf::from(format!("./storage/test_map_{}", uuid::Uuid::new_v4()));
+    let cfg = config::Config { foreman: Some(config::ForemanConfig { home: Some(tmp.to_string_lossy().to_string()), profile: None }), ..Default::default() };
+    let state = app::AppState::new(cfg).await;
+    let app_router = api::build_router(state.clone());
+
+    use assistant_core::system_map::model::*;
+    let mut map = SystemMap {
+        scanned_at: chrono::Utc::now(),
+        os: OsInfo { name: "TestOS".into(), ..Default::default() },
+        runtimes: RuntimesInfo { rustc: Some("rustc 1.81.0 (eeb90cda1 2024-09-04)".into()), ..Default::default() },
+        apps: vec!["git".into()],
+        ..Default::default()
+    };
+    let sm = &state.handles.system_map;
+    sm.update_with(map.clone()).await.unwrap();
+    map.runtimes.rustc = Some("rustc 1.82.0 (f6e511eec 2024-10-15)".into());
+    map.apps.push("steam".into());
+    sm.update_with(map.clone()).await.unwrap();
+    // Unchanged map records nothing new
+    sm.update_with(map).await.unwrap();
+
+    let digest = sm.get_digest();
+    assert!(digest.contains("rustc upgraded to 1.82.0 today"), "{}", digest);
+
+    let resp = app_router
+        .clone()
+        .oneshot(Request::builder().uri("/api/system_map/diff?since=1h").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert!(resp.status().is_success());
+    let b = axum::body::to_bytes(resp.into_body(), 1024 * 1024).await.unwrap();
+    let v: serde_json::Value = serde_json::from_slice(&b).unwrap();
+    let last = v["diffs"].as_array().unwrap().last().unwrap().clone();
+    let paths: Vec<&str> = last["changes"].as_array().unwrap().iter().map(|c| c["path"].as_str().unwrap()).collect();
+    assert_eq!(paths, vec!["runtimes.rustc", "apps.steam"]);
+    assert!(std::fs::read_dir(sm.history_dir()).unwrap().count() >= 1);
+
+    let resp = app_router
+        .oneshot(Request::builder().uri("/api/system_map/diff?since=yesterday-ish").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), axum::http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn stale_map_is_flagged_and_refreshed_in_background() {
+    use assistant_core::system_map::{model::*, scanners::ScanOptions, RefreshPolicy, SystemMapManager};
+    let tmp = std::path::PathBuf::from(format!("./storage/test_map_{}", uuid::Uuid::new_v4()));
+    let all = ["dpkg", "rpm", "pacman", "pip", "cargo", "systemd-user", "docker", "podman", "net", "gpu", "git-repos"];
+    let opts = ScanOptions { disabled: all.iter().map(|s| s.to_string()).collect(), repo_roots: vec![], ..Default::default() };
+    let policy = RefreshPolicy { interval: None, max_age: chrono::Duration::hours(24), debounce: std::time::Duration::from_millis(10) };
+    let sm = SystemMapManager::with_options(&tmp, None, opts, policy);
+
+    let old = SystemMap { scanned_at: chrono::Utc::now() - chrono::Duration::days(3), os: OsInfo { name: "TestOS".into(), ..Default::default() }, ..Default::default() };
+    sm.update_with(old.clone()).await.unwrap();
+    assert!(sm.is_stale());
+    assert!(sm.get_digest().starts_with("Note: System Map is stale (last scanned 3 days ago)"), "{}", sm.get_digest());
+
+    // The refresher rescans a stale map right away
+    sm.spawn_refresher();
+    let wait_for = |after: chrono::DateTime<chrono::Utc>| {
+        let sm = sm.clone();
+        async move {
+            for _ in 0..200 {
+                if sm.get_map().map(|m| m.scanned_at > after).unwrap_or(false) { return true; }
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            }
+            false
+        }
+    };
+    assert!(wait_for(old.scanned_at).await, "stale map refreshed");
+    assert!(!sm.is_stale());
+    assert!(!sm.get_digest().starts_with("Note:"));
+
+    // Triggers (installer, project.init) rescan incrementally; $PATH probes are reused
+    let first = sm.get_map().unwrap();
+    sm.request_refresh("test");
+    assert!(wait_for(first.scanned_at).await, "triggered refresh ran");
+    let second = sm.get_map().unwrap();
+    assert_eq!(second.probes_fingerprint, first.probes_fingerprint);
+    assert_eq!(second.apps, first.apps);
+}
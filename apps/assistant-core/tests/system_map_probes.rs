@@ -0,0 +1,32 @@
+// Changes $PATH, so it runs in its own test binary
+use assistant_core::system_map::{scan::scan_system, scanners::ScanOptions};
+
+#[tokio::test]
+async fn rescans_pick_up_runtime_updates_without_path_changes() {
+    let bin = std::env::temp_dir().join(format!("fake_toolchain_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&bin).unwrap();
+    let rustc = bin.join("rustc");
+    let install = |version: &str| {
+        std::fs::write(&rustc, format!("#!/bin/sh\necho 'rustc {}'\n", version)).unwrap();
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&rustc, std::fs::Permissions::from_mode(0o755)).unwrap();
+    };
+    install("1.80.0");
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    std::env::set_var("PATH", std::env::join_paths(std::iter::once(bin.clone()).chain(std::env::split_paths(&path))).unwrap());
+    let all = ["dpkg", "rpm", "pacman", "pip", "cargo", "systemd-user", "docker", "podman", "net", "gpu", "git-repos"];
+    let opts = ScanOptions { disabled: all.iter().map(|s| s.to_string()).collect(), repo_roots: vec![], ..Default::default() };
+
+    let first = scan_system(&opts, None).await;
+    assert_eq!(first.runtimes.rustc.as_deref(), Some("rustc 1.80.0"));
+    // Like `rustup update`: the binary changes in place, the directory does not
+    let dir_mtime = std::fs::metadata(&bin).unwrap().modified().unwrap();
+    install("1.81.0");
+    assert_eq!(std::fs::metadata(&bin).unwrap().modified().unwrap(), dir_mtime);
+
+    let second = scan_system(&opts, Some(&first)).await;
+    assert_eq!(second.probes_fingerprint, first.probes_fingerprint);
+    assert_eq!(second.runtimes.rustc.as_deref(), Some("rustc 1.81.0"));
+    assert_eq!(second.apps, first.apps);
+    let _ = std::fs::remove_dir_all(&bin);
+}
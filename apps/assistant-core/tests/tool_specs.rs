@@ -0,0 +1,183 @@
+use assistant_core::tools::{chat_tool_defs, realtime_tool_schemas, ToolsManager};
+use serde_json::{json, Value};
+
+fn manifests(files: &[(&str, Value)]) -> (std::path::PathBuf, ToolsManager) {
+    let dir = std::env::temp_dir().join(format!("tool_specs_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for (name, v) in files {
+        std::fs::write(dir.join(name), v.to_string()).unwrap();
+    }
+    let tm = ToolsManager::load_from_dir(&dir);
+    (dir, tm)
+}
+
+fn fs_manifest() -> Value {
+    json!({
+        "server": "fs",
+        "tools": [
+            "stat",
+            {
+                "name": "read",
+                "description": "Read a UTF-8 text file.",
+                "input_schema": {"type": "object", "properties": {"path": {"type": "string"}}, "required": ["path"], "additionalProperties": false},
+                "risk": {"paths_param": "path"}
+            },
+            {"name": "write_text", "risk": {"writes": true, "paths_param": "path"}}
+        ]
+    })
+}
+
+#[tokio::test]
+async fn schemas_come_from_manifests() {
+    let (dir, tm) = manifests(&[("fs.json", fs_manifest())]);
+    assert_eq!(tm.list(), vec![("fs".to_string(), vec!["stat".to_string(), "read".to_string(), "write_text".to_string()])]);
+
+    let rt = realtime_tool_schemas(&tm);
+    let read = rt.iter().find(|t| t["name"] == "fs_read").unwrap();
+    assert_eq!(read["description"], "Read a UTF-8 text file.");
+    assert_eq!(read["parameters"]["required"], json!(["path"]));
+    let stat = rt.iter().find(|t| t["name"] == "fs_stat").unwrap();
+    assert_eq!(stat["description"], "Call fs.stat via Foreman MCP");
+    assert_eq!(stat["parameters"]["additionalProperties"], true);
+    assert_eq!(rt.last().unwrap()["name"], "end_call");
+
+    let chat = chat_tool_defs(&tm);
+    assert_eq!(chat.len(), 3);
+    assert_eq!(chat[1]["function"]["name"], "fs_read");
+    assert_eq!(chat[1]["function"]["parameters"], read["parameters"]);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn calls_are_validated_before_dispatch() {
+    let (dir, tm) = manifests(&[("fs.json", fs_manifest())]);
+    let err = tm.invoke("fs", "read", json!({"path": 7, "mode": "r"})).await.unwrap_err().to_string();
+    assert!(err.starts_with("invalid params for fs.read:"), "{}", err);
+    assert!(err.contains("params.path: expected string, got number") && err.contains("params.mode: unknown property"), "{}", err);
+    // Tools without a schema go straight through
+    assert!(tm.validate("fs", "stat", &json!({"anything": true})).is_ok());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn policy_action_follows_risk_flags() {
+    let shell = json!({"server": "shell", "tools": [{"name": "exec", "risk": {"exec": true}}]});
+    let (dir, tm) = manifests(&[("fs.json", fs_manifest()), ("shell.json", shell)]);
+
+    let read = tm.proposed_action("fs", "read", &json!({"path": "~/notes.md"}));
+    assert_eq!((read.command.as_str(), read.writes, read.paths.clone()), ("fs.read", false, vec!["~/notes.md".to_string()]));
+    let write = tm.proposed_action("fs", "write_text", &json!({"path": "/etc/hosts", "content": ""}));
+    assert!(write.writes);
+    assert_eq!(write.paths, vec!["/etc/hosts".to_string()]);
+    // The program line is visible to require_approval keywords
+    let exec = tm.proposed_action("shell", "exec", &json!({"cmd": "sudo", "args": ["apt", "install", "x"]}));
+    assert_eq!(exec.command, "shell.exec sudo apt install x");
+    assert!(!exec.writes);
+    // Tools without a spec are judged by name
+    assert!(tm.proposed_action("mystery", "write_all", &json!({})).writes);
+    assert!(!tm.proposed_action("mystery", "peek", &json!({})).writes);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn describe_handshake_fills_in_specs() {
+    let answer = json!({"ok": true, "result": {"tools": [
+        {"name": "search", "description": "Search the web.", "input_schema": {"type": "object", "properties": {"query": {"type": "string"}}, "required": ["query"]}, "risk": {"network": true}},
+        {"name": "fetch", "description": "Fetch a page.", "risk": {"network": true}}
+    ]}});
+    let server = |tools: Value| json!({
+        "server": "web", "tools": tools, "transport": "stdio", "describe": true,
+        "bin": format!("read line; echo '{}'", answer),
+    });
+    let (dir, tm) = manifests(&[("web.json", server(json!([])))]);
+    assert!(tm.list()[0].1.is_empty());
+    assert_eq!(tm.describe("web").await.unwrap(), 2);
+    assert_eq!(tm.list()[0].1, vec!["search".to_string(), "fetch".to_string()]);
+    assert!(tm.spec("web", "fetch").unwrap().risk.network);
+    assert!(tm.validate("web", "search", &json!({})).unwrap_err().to_string().contains("missing required property 'query'"));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    // Manifest entries pick which tools are exposed and win over the server's answer
+    let (dir, tm) = manifests(&[("web.json", server(json!([{"name": "fetch", "description": "Fetch (local wording)."}])))]);
+    tm.describe("web").await.unwrap();
+    let fetch = tm.spec("web", "fetch").unwrap();
+    assert_eq!(fetch.description.as_deref(), Some("Fetch (local wording)."));
+    assert!(fetch.risk.network);
+    assert!(tm.spec("web", "search").is_none());
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let (dir, tm) = manifests(&[("fs.json", fs_manifest())]);
+    assert!(tm.describe("fs").await.unwrap_err().to_string().contains("no stdio server"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn fs_changes_take_their_action_from_the_fs_server() {
+    let (dir, tm) = manifests(&[("fs.json", fs_manifest())]);
+    let mv = tm.proposed_action("fs", "move", &json!({"from": "/etc/hosts", "to": "/etc/hosts.bak"}));
+    assert_eq!((mv.command.as_str(), mv.writes, mv.paths.clone()), ("fs.move /etc/hosts /etc/hosts.bak", true, vec!["/etc/hosts".to_string(), "/etc/hosts.bak".to_string()]));
+    // Recursive deletes read like the shell command, so `rm -rf` approval rules catch them
+    assert_eq!(tm.proposed_action("fs", "delete", &json!({"path": "/etc/ssl", "recursive": true})).command, "rm -rf /etc/ssl");
+    assert!(!tm.proposed_action("fs", "search", &json!({"pattern": "x"})).writes);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn git_calls_write_only_when_they_change_the_repository() {
+    let git = json!({"server": "git", "tools": [{"name": "branch", "risk": {"writes": true, "paths_param": "path"}}, {"name": "stash", "risk": {"writes": true, "paths_param": "path"}}]});
+    let (dir, tm) = manifests(&[("git.json", git)]);
+    // The manifest marks branch and stash as writes; listing either is still a read
+    assert!(!tm.proposed_action("git", "branch", &json!({"path": "/tmp", "action": "list"})).writes);
+    assert!(!tm.proposed_action("git", "stash", &json!({"path": "/tmp"})).writes);
+    let delete = tm.proposed_action("git", "branch", &json!({"path": "/nonexistent/repo", "action": "delete", "name": "old", "force": true}));
+    assert_eq!((delete.command.as_str(), delete.writes, delete.paths.clone()), ("git branch -D old", true, vec!["/nonexistent/repo".to_string()]));
+    assert_eq!(tm.proposed_action("git", "stash", &json!({"action": "drop"})).command, "git stash drop stash@{0}");
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn only_tools_that_honour_dry_run_skip_the_gate() {
+    let dry_run = json!({"type": "object", "properties": {"pid": {"type": "integer"}, "path": {"type": "string"}, "dry_run": {"type": "boolean"}}});
+    let fs = json!({"server": "fs", "tools": [{"name": "write", "input_schema": dry_run}, "delete"]});
+    let proc = json!({"server": "proc", "tools": [{"name": "kill", "input_schema": dry_run}]});
+    let (dir, tm) = manifests(&[("fs.json", fs), ("proc.json", proc)]);
+    assert!(tm.is_dry_run("fs", "write", &json!({"path": "/etc/hosts", "dry_run": true})));
+    assert!(!tm.is_dry_run("fs", "write", &json!({"path": "/etc/hosts", "dry_run": false})));
+    // fs.delete honours the flag, but this spec does not declare it
+    assert!(!tm.is_dry_run("fs", "delete", &json!({"path": "/etc/hosts", "dry_run": true})));
+    // proc.kill ignores the flag whatever its schema claims
+    assert!(!tm.is_dry_run("proc", "kill", &json!({"pid": 1, "dry_run": true})));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn described_servers_refuse_calls_until_they_have_a_schema() {
+    let proc = json!({"server": "proc", "tools": ["kill"], "transport": "stdio", "describe": true, "bin": "exit 1"});
+    let (dir, tm) = manifests(&[("proc.json", proc)]);
+    let err = tm.validate("proc", "kill", &json!({"pid": 1})).unwrap_err().to_string();
+    assert!(err.contains("no schema for proc.kill yet"), "{}", err);
+    // invoke retries the handshake, then refuses instead of falling back
+    let mut child = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+    let err = tm.invoke("proc", "kill", json!({"pid": child.id(), "dry_run": true})).await.unwrap_err().to_string();
+    assert!(err.contains("no schema for proc.kill yet"), "{}", err);
+    assert!(child.try_wait().unwrap().is_none());
+    let _ = child.kill();
+    let _ = child.wait();
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn fs_calls_use_the_roots_from_core_config() {
+    let root = std::env::temp_dir().join(format!("tool_specs_root_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(root.join("inside.txt"), "hi").unwrap();
+    let cfg = assistant_core::config::Config { fs: Some(assistant_core::config::FsConfig { roots: vec![root.to_string_lossy().to_string()], max_read_bytes: None }), ..Default::default() };
+    let (dir, tm) = manifests(&[]);
+    let tm = tm.with_config(&cfg);
+    let read = tm.invoke("fs", "read", json!({"path": root.join("inside.txt")})).await.unwrap();
+    assert_eq!(read["content"], "hi");
+    let err = tm.invoke("fs", "read", json!({"path": "/etc/hostname"})).await.unwrap_err().to_string();
+    assert!(err.contains("outside"), "{}", err);
+    let _ = std::fs::remove_dir_all(&dir);
+    let _ = std::fs::remove_dir_all(&root);
+}
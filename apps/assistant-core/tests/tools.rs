@@ -63,3 +63,180 @@ async fn git_commit_requires_approval() {
     ).await.unwrap();
     assert_eq!(resp.status(), StatusCode::CONFLICT);
 }
+
+#[tokio::test]
+async fn malformed_fs_write_is_rejected_before_approval() {
+    // Tools come from a manifest declaring the write schema
+    let dir = std::env::temp_dir().join(format!("foreman_tools_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let schema = serde_json::json!({"type": "object", "properties": {"path": {"type": "string"}, "content": {"type": "string"}}, "required": ["path", "content"], "additionalProperties": false});
+    let manifest = serde_json::json!({"server": "fs", "tools": [{"name": "write", "input_schema": schema, "risk": {"writes": true, "paths_param": "path"}}]});
+    std::fs::write(dir.join("fs.json"), manifest.to_string()).unwrap();
+    let mut state = (*app::AppState::new(config::Config::default()).await).clone();
+    state.handles.tools = assistant_core::tools::ToolsManager::load_from_dir(&dir);
+    let state = std::sync::Arc::new(state);
+    let app_router = api::build_router(state.clone());
+
+    let body = serde_json::json!({"params": {"content": "no path"}}).to_string();
+    let resp = app_router.clone().oneshot(
+        Request::builder().method("POST").uri("/api/tools/fs/write").header("content-type","application/json").body(Body::from(body)).unwrap()
+    ).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert!(state.handles.approval_prompt.read().is_none());
+
+    // The approval fields are not the tool's, so they do not fail the schema
+    let body = serde_json::json!({"params": {"path": "./tmp.txt", "content": "hello", "approval_id": "a", "approve_token": "t"}}).to_string();
+    let resp = app_router.clone().oneshot(
+        Request::builder().method("POST").uri("/api/tools/fs/write").header("content-type","application/json").body(Body::from(body)).unwrap()
+    ).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn risk_flags_and_keywords_gate_api_tool_calls() {
+    let dir = std::env::temp_dir().join(format!("foreman_gate_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(dir.join("tools.d")).unwrap();
+    std::fs::create_dir_all(dir.join("policy.d")).unwrap();
+    let scratch = serde_json::json!({"server": "scratch", "tools": [
+        "peek",
+        {"name": "save", "risk": {"writes": true}},
+        {"name": "run", "risk": {"exec": true}}
+    ]});
+    let installer = serde_json::json!({"server": "installer", "tools": [{"name": "plan_install", "risk": {"writes": true}}]});
+    std::fs::write(dir.join("tools.d/scratch.json"), scratch.to_string()).unwrap();
+    std::fs::write(dir.join("tools.d/installer.json"), installer.to_string()).unwrap();
+    std::fs::write(dir.join("policy.d/00-test.yaml"), "require_approval:\n  - sudo\n").unwrap();
+    let mut state = (*app::AppState::new(config::Config::default()).await).clone();
+    state.handles.tools = assistant_core::tools::ToolsManager::load_from_dir(&dir.join("tools.d"))
+        .with_installer(assistant_core::installer::Installer::new(state.handles.memory.clone(), None));
+    state.handles.policy = std::sync::Arc::new(assistant_core::gatekeeper::PolicyEngine::load_from_dir(dir.join("policy.d")).unwrap());
+    let state = std::sync::Arc::new(state);
+    let app_router = api::build_router(state.clone());
+    let post = |uri: &str, params: serde_json::Value| Request::builder().method("POST").uri(uri).header("content-type","application/json")
+        .body(Body::from(serde_json::json!({"params": params}).to_string())).unwrap();
+
+    // Reads and harmless commands pass the gate (and fail later: there is no scratch server)
+    for (uri, params) in [("/api/tools/scratch/peek", serde_json::json!({})), ("/api/tools/scratch/run", serde_json::json!({"cmd": "ls"}))] {
+        let resp = app_router.clone().oneshot(post(uri, params)).await.unwrap();
+        assert_ne!(resp.status(), StatusCode::CONFLICT, "{}", uri);
+        assert!(state.handles.approval_prompt.read().is_none(), "{}", uri);
+    }
+    // Declared writes are held
+    let resp = app_router.clone().oneshot(post("/api/tools/scratch/save", serde_json::json!({"text": "x"}))).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+    let prompt = state.handles.approval_prompt.write().take().unwrap();
+    assert_eq!(prompt.title, "scratch.save requires approval");
+    assert_eq!(prompt.details["arguments"], serde_json::json!({"text": "x"}));
+    // Exec tools are held when their command line hits a require_approval keyword
+    let resp = app_router.clone().oneshot(post("/api/tools/scratch/run", serde_json::json!({"cmd": "sudo", "args": ["reboot"]}))).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+    let prompt = state.handles.approval_prompt.write().take().unwrap();
+    assert_eq!(prompt.action.command, "scratch.run sudo reboot");
+    assert_eq!(prompt.details["reasons"], serde_json::json!(["requires approval: sudo"]));
+    // Planning an install only records a plan; the installer gates its own apply
+    let resp = app_router.clone().oneshot(post("/api/tools/installer/plan_install", serde_json::json!({"pkg": "ripgrep", "manager": "apt"}))).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(state.handles.approval_prompt.read().is_none());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn proc_kill_dry_run_still_requires_approval() {
+    // proc.kill has no dry run; the flag must not bypass the gate for a process we did not start
+    let mut child = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+    let state = app::AppState::new(config::Config::default()).await;
+    let params = serde_json::json!({"pid": child.id(), "dry_run": true});
+    assert!(!state.handles.tools.is_dry_run("proc", "kill", &params));
+    let decision = state.handles.policy.evaluate(&state.handles.tools.proposed_action("proc", "kill", &params));
+    assert!(!matches!(decision.kind, assistant_core::gatekeeper::PolicyDecisionKind::Allow));
+
+    let app_router = api::build_router(state.clone());
+    let body = serde_json::json!({"params": params}).to_string();
+    let resp = app_router.clone().oneshot(
+        Request::builder().method("POST").uri("/api/tools/proc/kill").header("content-type","application/json").body(Body::from(body)).unwrap()
+    ).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+    assert!(state.handles.approval_prompt.read().is_some());
+    assert!(child.try_wait().unwrap().is_none());
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+#[tokio::test]
+async fn steam_installed_reads_libraries_under_root() {
+    let tmp_dir = std::env::temp_dir().join(format!("foreman_steam_{}", uuid::Uuid::new_v4()));
+    let (root, library) = (tmp_dir.join("Steam"), tmp_dir.join("Library"));
+    std::fs::create_dir_all(root.join("steamapps")).unwrap();
+    std::fs::create_dir_all(library.join("steamapps")).unwrap();
+    std::fs::write(root.join("steamapps/libraryfolders.vdf"), format!("\"libraryfolders\"\n{{\n\t\"0\" {{ \"path\" \"{}\" }}\n\t\"1\" {{ \"path\" \"{}\" }}\n}}\n", root.display(), library.display())).unwrap();
+    std::fs::write(library.join("steamapps/appmanifest_620.acf"), "\"AppState\"\n{\n\t\"appid\" \"620\"\n\t\"name\" \"Portal 2\"\n\t\"StateFlags\" \"4\"\n\t\"SizeOnDisk\" \"1024\"\n\t\"LastPlayed\" \"1729350000\"\n}\n").unwrap();
+
+    let state = app::AppState::new(config::Config::default()).await;
+    let app_router = api::build_router(state);
+    let body = serde_json::json!({"params": {"root": root.to_string_lossy()}}).to_string();
+    let resp = app_router.clone().oneshot(
+        Request::builder().method("POST").uri("/api/tools/steam/installed").header("content-type","application/json").body(Body::from(body)).unwrap()
+    ).await.unwrap();
+    assert!(resp.status().is_success());
+    let bytes = to_bytes(resp.into_body(), 1024 * 1024).await.unwrap();
+    let v: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let game = &v["games"][0];
+    assert_eq!((game["name"].as_str(), game["state"].as_str(), game["size_on_disk"].as_u64(), game["last_played"].as_i64()), (Some("Portal 2"), Some("installed"), Some(1024), Some(1729350000)));
+    assert_eq!(game["library"].as_str(), Some(library.to_string_lossy().as_ref()));
+    let _ = std::fs::remove_dir_all(&tmp_dir);
+}
+
+#[tokio::test]
+async fn proc_kill_is_free_for_spawned_processes_only() {
+    // The games registry feeds the shell allowlist; register `sleep 30` as an emulator
+    let tmp_dir = std::env::temp_dir().join(format!("foreman_proc_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&tmp_dir).unwrap();
+    std::fs::write(tmp_dir.join("foreman.toml"), "[games.emulators.test]\nbin = \"sleep\"\nargs = [\"30\"]\n").unwrap();
+    std::env::set_var("FOREMAN_CONFIG", tmp_dir.join("foreman.toml"));
+
+    let state = app::AppState::new(config::Config::default()).await;
+    let app_router = api::build_router(state);
+    let call = |uri: &str, params: serde_json::Value| {
+        let req = Request::builder().method("POST").uri(uri).header("content-type","application/json").body(Body::from(serde_json::json!({"params": params}).to_string())).unwrap();
+        let app_router = app_router.clone();
+        async move {
+            let resp = app_router.oneshot(req).await.unwrap();
+            let status = resp.status();
+            let bytes = to_bytes(resp.into_body(), 1024 * 1024).await.unwrap();
+            (status, serde_json::from_slice::<serde_json::Value>(&bytes).unwrap_or_default())
+        }
+    };
+
+    let (status, started) = call("/api/tools/shell/exec", serde_json::json!({"cmd": "sleep", "args": ["30"], "wait": false})).await;
+    assert!(status.is_success(), "{}", started);
+    let pid = started["pid"].as_u64().unwrap();
+    let (_, listed) = call("/api/tools/proc/list", serde_json::json!({"spawned": true, "sample_ms": 0})).await;
+    let pids: Vec<u64> = listed["processes"].as_array().unwrap().iter().map(|p| p["pid"].as_u64().unwrap()).collect();
+    assert_eq!(pids, vec![pid]);
+
+    let mut other = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+    let (status, _) = call("/api/tools/proc/kill", serde_json::json!({"pid": other.id()})).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, killed) = call("/api/tools/proc/kill", serde_json::json!({"pid": pid})).await;
+    assert!(status.is_success(), "{}", killed);
+    let (_, watched) = call("/api/tools/proc/watch", serde_json::json!({"pid": pid, "timeout_ms": 5000})).await;
+    assert_eq!(watched["exited"], true);
+
+    let _ = other.kill();
+    let _ = other.wait();
+    let _ = std::fs::remove_dir_all(&tmp_dir);
+}
+
+#[tokio::test]
+async fn fs_changes_require_approval() {
+    let state = app::AppState::new(config::Config::default()).await;
+    let app_router = api::build_router(state);
+    let body = serde_json::json!({"params": {"path": "./tmp.txt", "content": "hello"}}).to_string();
+    let resp = app_router.clone().oneshot(
+        Request::builder().method("POST").uri("/api/tools/fs/write").header("content-type","application/json").body(Body::from(body)).unwrap()
+    ).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+    assert!(!std::path::Path::new("./tmp.txt").exists());
+}
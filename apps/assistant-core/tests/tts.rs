@@ -0,0 +1,129 @@
+use assistant_core::tts::{read_wav, CommandTts, PcmSink, Priority, Speech, SpeechQueue, TtsEngine, TtsService, WavFileTts};
+use axum::http::{Request, StatusCode};
+use http_body_util::BodyExt as _;
+use parking_lot::Mutex;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tower::util::ServiceExt;
+
+/// Records the first sample of each clip (clips are constant tones) and flushes.
+#[derive(Clone, Default)]
+struct Recorder {
+    log: Arc<Mutex<Vec<String>>>,
+}
+
+impl PcmSink for Recorder {
+    fn push_pcm(&self, pcm: &[i16], _sample_rate: u32) { self.log.lock().push(pcm[0].to_string()); }
+    fn flush(&self) { self.log.lock().push("flush".into()); }
+}
+
+fn clip(tag: i16, ms: usize) -> Speech { Speech { pcm: vec![tag; ms * 16], sample_rate: 16_000 } }
+
+async fn wait_idle(q: &SpeechQueue) {
+    for _ in 0..300 {
+        let st = q.status();
+        if st.queued == 0 && st.playing.is_none() { return; }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("queue did not drain");
+}
+
+fn tmp_dir() -> PathBuf {
+    let p = std::env::temp_dir().join(format!("tts_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&p).unwrap();
+    p
+}
+
+#[tokio::test]
+async fn queue_plays_by_priority_and_alerts_preempt() {
+    let rec = Recorder::default();
+    let sink = rec.clone();
+    let q = SpeechQueue::spawn(move || Ok(sink));
+    q.enqueue(clip(1, 300), Priority::Low);
+    while q.status().playing.is_none() { tokio::time::sleep(Duration::from_millis(5)).await; }
+    q.enqueue(clip(2, 20), Priority::Low);
+    q.enqueue(clip(3, 20), Priority::Normal);
+    q.enqueue(clip(4, 20), Priority::Alert);
+    wait_idle(&q).await;
+    assert_eq!(*rec.log.lock(), vec!["1", "flush", "4", "3", "2"]);
+
+    // clear drops the backlog and cuts the current clip
+    rec.log.lock().clear();
+    q.enqueue(clip(5, 300), Priority::Normal);
+    q.enqueue(clip(6, 20), Priority::Normal);
+    while q.status().playing.is_none() { tokio::time::sleep(Duration::from_millis(5)).await; }
+    q.clear();
+    wait_idle(&q).await;
+    assert_eq!(*rec.log.lock(), vec!["5", "flush"]);
+    q.close();
+}
+
+#[tokio::test]
+async fn service_speaks_sentences_in_order_from_wav_clips() {
+    let dir = tmp_dir();
+    std::fs::write(dir.join("hello_there.wav"), assistant_core::stt::encode_wav_pcm16(&[7; 160], 16_000)).unwrap();
+    std::fs::write(dir.join("default.wav"), assistant_core::stt::encode_wav_pcm16(&[9; 160], 16_000)).unwrap();
+    assert_eq!(read_wav(&dir.join("default.wav")).unwrap().pcm.len(), 160);
+
+    let rec = Recorder::default();
+    let sink = rec.clone();
+    let tts = TtsService::new(Some(Arc::new(WavFileTts { dir: dir.clone() })), Some(SpeechQueue::spawn(move || Ok(sink))), false);
+    let sentences = tts.speak("Hello there. Anything else?", Priority::Normal).unwrap();
+    assert_eq!(sentences, vec!["Hello there.", "Anything else?"]);
+    for _ in 0..100 {
+        if rec.log.lock().len() == 2 { break; }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(*rec.log.lock(), vec!["7", "9"]);
+
+    // Streamed tokens are spoken sentence by sentence
+    rec.log.lock().clear();
+    let mut stream = tts.stream(Priority::Normal).unwrap();
+    for piece in ["Hel", "lo there", ". And", " more"] { stream.push(piece); }
+    stream.finish();
+    for _ in 0..100 {
+        if rec.log.lock().len() == 2 { break; }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(*rec.log.lock(), vec!["7", "9"]);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn command_engine_reads_raw_pcm_from_stdout() {
+    let tts = CommandTts { command: "cat >/dev/null; printf 'AAAA'".into(), sample_rate: 22_050 };
+    let speech = tts.synthesize("ignored").await.unwrap();
+    assert_eq!(speech.pcm, vec![0x4141, 0x4141]);
+    assert_eq!(speech.sample_rate, 22_050);
+    assert!(CommandTts { command: "exit 3".into(), sample_rate: 22_050 }.synthesize("x").await.is_err());
+}
+
+#[tokio::test]
+async fn speak_endpoint_returns_wav_without_playback() {
+    let dir = tmp_dir();
+    std::fs::write(dir.join("default.wav"), assistant_core::stt::encode_wav_pcm16(&[3; 320], 16_000)).unwrap();
+    let cfg: assistant_core::config::Config = toml::from_str(&format!(
+        "[voice]\nwake_phrase = \"hey vim\"\nwake_enabled = false\ntts = {{ engine = \"wav\", dir = \"{}\" }}\n",
+        dir.display()
+    )).unwrap();
+    let app = assistant_core::api::build_router(assistant_core::app::AppState::new(cfg).await);
+    let speak = |body: serde_json::Value| Request::builder().method("POST").uri("/api/tts/speak")
+        .header("content-type", "application/json").body(axum::body::Body::from(body.to_string())).unwrap();
+
+    let resp = app.clone().oneshot(speak(serde_json::json!({"text": "Hi.", "play": false}))).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()["content-type"], "audio/wav");
+    let wav = resp.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(&wav[..4], b"RIFF");
+    assert_eq!(wav.len(), 44 + 640);
+
+    // Default build has no output device: asking to play explicitly is an error
+    let resp = app.clone().oneshot(speak(serde_json::json!({"text": "Hi.", "play": true, "priority": "alert"}))).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    let resp = app.oneshot(Request::builder().uri("/api/tts/status").body(axum::body::Body::empty()).unwrap()).await.unwrap();
+    let v: serde_json::Value = serde_json::from_slice(&resp.into_body().collect().await.unwrap().to_bytes()).unwrap();
+    assert_eq!(v["engine"], "wav");
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,117 @@
+use assistant_core::wake_kws::{KeywordModel, KwsDetector};
+
+/// Vowel targets as (F1, F2) formant pairs.
+const AH: (f32, f32) = (730.0, 1_090.0);
+const EE: (f32, f32) = (270.0, 2_290.0);
+const OO: (f32, f32) = (300.0, 870.0);
+const EH: (f32, f32) = (530.0, 1_840.0);
+
+struct Lcg(u64);
+
+impl Lcg {
+    /// Uniform in -1..1.
+    fn next(&mut self) -> f32 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        ((self.0 >> 33) as f32 / (1u64 << 31) as f32) * 2.0 - 1.0
+    }
+}
+
+/// Crude voiced speech: harmonics of f0 shaped by two formant resonances,
+/// gliding between vowel targets. `speed` > 1 speaks slower.
+fn say(vowels: &[(f32, f32)], f0: f32, speed: f32, gain: f32, shift: f32) -> Vec<i16> {
+    let seg = (0.16 * speed * 16_000.0) as usize;
+    let mut out = Vec::with_capacity(seg * vowels.len());
+    let mut phase = 0.0f32;
+    for n in 0..seg * vowels.len() {
+        let pos = n as f32 / seg as f32;
+        let (a, b) = (vowels[(pos as usize).min(vowels.len() - 1)], vowels[((pos as usize) + 1).min(vowels.len() - 1)]);
+        // Glide over the last 30% of each vowel
+        let t = ((pos.fract() - 0.7) / 0.3).clamp(0.0, 1.0);
+        let (f1, f2) = ((a.0 + (b.0 - a.0) * t) * shift, (a.1 + (b.1 - a.1) * t) * shift);
+        phase += std::f32::consts::TAU * f0 / 16_000.0;
+        let mut s = 0.0;
+        for h in 1..40 {
+            let f = f0 * h as f32;
+            if f > 7_000.0 { break; }
+            let amp = (-((f - f1) / 110.0).powi(2)).exp() + 0.6 * (-((f - f2) / 140.0).powi(2)).exp() + 0.02;
+            s += amp * (phase * h as f32).sin();
+        }
+        // Onset/offset ramps
+        let env = (n as f32 / 400.0).min(1.0).min((seg * vowels.len() - n) as f32 / 400.0);
+        out.push((s * env * gain * 2_500.0).clamp(-32_000.0, 32_000.0) as i16);
+    }
+    out
+}
+
+fn with_noise(pcm: Vec<i16>, level: f32, rng: &mut Lcg) -> Vec<i16> {
+    pcm.into_iter().map(|s| (s as f32 + rng.next() * level) as i16).collect()
+}
+
+fn silence(ms: usize, rng: &mut Lcg) -> Vec<i16> {
+    with_noise(vec![0; ms * 16], 60.0, rng)
+}
+
+fn phrase_only(rng: &mut Lcg) -> Vec<i16> {
+    with_noise([vec![0; 4_800], say(&[AH, EE, OO], 122.0, 1.05, 0.5, 1.0), vec![0; 4_800]].concat(), 60.0, rng)
+}
+
+fn enrolled(rng: &mut Lcg) -> KeywordModel {
+    let takes: Vec<Vec<i16>> = [(125.0, 1.0, 1.0, 1.0), (118.0, 1.1, 0.7, 0.98), (131.0, 0.92, 1.2, 1.02)].iter()
+        .map(|&(f0, speed, gain, shift)| with_noise([vec![0; 4_800], say(&[AH, EE, OO], f0, speed, gain, shift), vec![0; 4_800]].concat(), 60.0, rng))
+        .collect();
+    KeywordModel::enroll("hey vim", &takes).unwrap()
+}
+
+/// Run a stream through the detector in 30 ms frames; sample offsets of detections.
+fn detect(det: &mut KwsDetector, stream: &[i16]) -> Vec<usize> {
+    stream.chunks(480).enumerate().filter_map(|(i, f)| det.push(f).then_some(i * 480)).collect()
+}
+
+#[test]
+fn enrollment_trims_and_calibrates() {
+    let mut rng = Lcg(7);
+    let model = enrolled(&mut rng);
+    assert_eq!(model.templates.len(), 3);
+    for t in &model.templates {
+        // ~480 ms of speech at 10 ms hops, silence trimmed
+        assert!((35..=70).contains(&t.len()), "template frames {}", t.len());
+    }
+    assert!(model.calibration.is_finite() && model.calibration > 0.0);
+    assert!(KeywordModel::enroll("x", &[say(&[AH], 120.0, 1.0, 1.0, 1.0)]).is_err(), "one take is not enough");
+    assert!(KeywordModel::enroll("x", &[vec![0; 16_000], vec![0; 16_000]]).is_err(), "silence is not a template");
+
+    let path = std::env::temp_dir().join(format!("kws_{}.json", uuid::Uuid::new_v4()));
+    model.save(&path).unwrap();
+    let back = KeywordModel::load(&path).unwrap();
+    assert_eq!(back.phrase, "hey vim");
+    assert_eq!(back.templates.len(), 3);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn detects_the_phrase_once_and_ignores_other_speech() {
+    let mut rng = Lcg(42);
+    let model = enrolled(&mut rng);
+    let mut det = KwsDetector::new(&model, 0.5);
+
+    // Other words, then the phrase in a new voice, slower and quieter, running into more speech
+    let other = [say(&[OO, AH, EE], 124.0, 1.0, 1.0, 1.0), silence(400, &mut rng), say(&[EH, OO], 124.0, 1.0, 1.0, 1.0), say(&[EE, EH, AH], 124.0, 1.0, 1.0, 1.0)].concat();
+    let stream = [silence(800, &mut rng), other.clone(), silence(600, &mut rng)].concat();
+    let onset = stream.len();
+    let phrase = with_noise(say(&[AH, EE, OO], 112.0, 1.15, 0.5, 0.97), 60.0, &mut rng);
+    let end = onset + phrase.len();
+    let stream = [stream, phrase, say(&[EH, AH], 112.0, 1.0, 0.5, 0.97), silence(800, &mut rng)].concat();
+
+    // Offline scores are on the calibration scale: the phrase near 1, other words well above
+    assert!(model.score(&phrase_only(&mut rng)) < 1.2);
+    assert!(model.score(&other) > 2.0, "other speech scored {:.2}", model.score(&other));
+
+    let hits = detect(&mut det, &stream);
+    assert_eq!(hits.len(), 1, "hits at {:?} (threshold {:.2})", hits, det.threshold());
+    // Fires near the end of the phrase
+    assert!(hits[0] + 480 >= onset + (end - onset) * 2 / 3 && hits[0] <= end + 4_800, "hit at {} for phrase {}..{}", hits[0], onset, end);
+
+    // Silence and noise alone never trigger
+    let mut det = KwsDetector::new(&model, 1.0);
+    assert!(detect(&mut det, &with_noise(vec![0; 48_000], 400.0, &mut rng)).is_empty());
+}
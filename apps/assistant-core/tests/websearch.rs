@@ -0,0 +1,26 @@
+use assistant_core::{api, app, config};
+use axum::http::{Request, StatusCode};
+use tower::util::ServiceExt; // for `oneshot`
+
+#[tokio::test]
+async fn search_requires_query() {
+    let state = app::AppState::new(config::Config::default()).await;
+    let app = api::build_router(state);
+    let resp = app
+        .oneshot(Request::builder().uri("/api/search?q=%20").body(axum::body::Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn search_without_server_is_bad_gateway() {
+    // No tools.d manifests are loaded from the crate dir, so the websearch server is unknown
+    let state = app::AppState::new(config::Config::default()).await;
+    let app = api::build_router(state);
+    let resp = app
+        .oneshot(Request::builder().uri("/api/search?q=rust").body(axum::body::Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_GATEWAY);
+}
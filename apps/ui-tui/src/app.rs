@@ -37,6 +37,8 @@ pub struct App {
     pub chat_session_id: Option<String>,
     pub chat_modal: Option<ChatModal>,
     pub chat_stream: bool,
+    /// Show tool calls and results in full rather than one line each.
+    pub chat_tools_open: bool,
     pub show_help: bool,
     pub reports: Vec<String>,
     pub report_sel: usize,
@@ -46,6 +48,8 @@ pub struct App {
     #[cfg(feature = "voice")]
     pub voice_rec: Option<crate::audio::VoiceRecorder>,
     pub voice_last_seen: Option<Instant>,
+    /// Input text before the current utterance; partial transcripts are shown after it.
+    pub voice_base: Option<String>,
     // Realtime status
     pub rt_active: bool,
     pub last_rt_poll: Option<Instant>,
@@ -119,6 +123,7 @@ impl Default for App {
             chat_session_id: None,
             chat_modal: None,
             chat_stream: false,
+            chat_tools_open: false,
             show_help: false,
             reports: vec![],
             report_sel: 0,
@@ -128,6 +133,7 @@ impl Default for App {
             #[cfg(feature = "voice")]
             voice_rec: None,
             voice_last_seen: None,
+            voice_base: None,
             rt_active: false,
             last_rt_poll: None,
             health_ok: false,
@@ -260,23 +266,33 @@ pub async fn run() -> anyhow::Result<()> {
         while let Ok(ev) = evt_rx.try_recv() {
             match ev {
                 ChatEvent::AssistantReply(reply) => {
-                    app.chat_messages.push(ChatMsg { role: "assistant".into(), content: reply });
+                    app.chat_messages.push(ChatMsg::new("assistant", reply));
                     // Do not force-scroll if user scrolled up; staying at bottom is implicit when chat_scroll==0
                 }
                 ChatEvent::StreamDelta(delta) => {
-                    if let Some(last) = app.chat_messages.last_mut() {
-                        if last.role == "assistant" { last.content.push_str(&delta); }
+                    // After tool results the reply continues in a new message
+                    if app.chat_messages.last().map(|m| m.role != "assistant").unwrap_or(true) {
+                        app.chat_messages.push(ChatMsg::new("assistant", String::new()));
                     }
+                    if let Some(last) = app.chat_messages.last_mut() { last.content.push_str(&delta); }
                 }
-                ChatEvent::ToolNote(line) => {
-                    app.chat_messages.push(ChatMsg { role: "assistant".into(), content: line });
+                ChatEvent::ToolCalls(calls) => {
+                    match app.chat_messages.last_mut() {
+                        Some(last) if last.role == "assistant" && last.tool_calls.is_empty() => last.tool_calls = calls,
+                        _ => app.chat_messages.push(ChatMsg { tool_calls: calls, ..ChatMsg::new("assistant", String::new()) }),
+                    }
                 }
+                ChatEvent::ToolResult(msg) => { app.chat_messages.push(msg); }
+                ChatEvent::SessionLoaded(messages) => { app.chat_messages = messages; }
                 ChatEvent::StreamDone => { push_toast(&mut app, "Chat complete", ToastKind::Success); }
                 ChatEvent::Error(msg) => { push_toast(&mut app, msg, ToastKind::Error); }
+                ChatEvent::VoicePartial(t) => {
+                    if let Some(base) = app.voice_base.as_ref() { app.input = join_transcript(base, &t); }
+                }
                 ChatEvent::VoiceTranscript(t) => {
+                    let base = app.voice_base.take().unwrap_or_else(|| app.input.clone());
+                    app.input = join_transcript(&base, &t);
                     if !t.is_empty() {
-                        if !app.input.is_empty() { app.input.push(' '); }
-                        app.input.push_str(&t);
                         push_toast(&mut app, "Voice: transcribed", ToastKind::Success);
                         app.status = "Voice: transcribed".into();
                     }
@@ -419,7 +435,20 @@ pub async fn run() -> anyhow::Result<()> {
                                 #[cfg(feature = "voice")]
                                 {
                                     match crate::audio::VoiceRecorder::start() {
-                                        Ok(r) => { app.voice_rec = Some(r); app.voice_ptt = true; app.voice_last_seen = Some(Instant::now()); app.status = "Listening… (hold Ctrl-Space)".into(); push_toast(&mut app, "Listening…", ToastKind::Info); }
+                                        Ok((r, frames)) => {
+                                            app.voice_rec = Some(r); app.voice_ptt = true; app.voice_last_seen = Some(Instant::now());
+                                            app.voice_base = Some(app.input.clone());
+                                            // Stream while recording; partials update the input line live
+                                            let tx = evt_tx.clone();
+                                            tokio::spawn(async move {
+                                                let ptx = tx.clone();
+                                                match net::stt_stream(frames, crate::audio::SAMPLE_RATE, move |p| { let _ = ptx.send(ChatEvent::VoicePartial(p)); }).await {
+                                                    Ok(text) => { let _ = tx.send(ChatEvent::VoiceTranscript(text)); },
+                                                    Err(e) => { let _ = tx.send(ChatEvent::Error(format!("stt error: {}", e))); }
+                                                }
+                                            });
+                                            app.status = "Listening… (hold Ctrl-Space)".into(); push_toast(&mut app, "Listening…", ToastKind::Info);
+                                        }
                                         Err(e) => { push_toast(&mut app, format!("Mic error: {}", e), ToastKind::Error); }
                                     }
                                 }
@@ -741,7 +770,7 @@ pub async fn run() -> anyhow::Result<()> {
                             } else if app.active == Screen::Chat && !app.input.trim().is_empty() && !app.input.trim().starts_with('/') {
                                 // Send chat message to OpenAI
                                 let user = app.input.trim().to_string();
-                                app.chat_messages.push(ChatMsg { role: "user".into(), content: user.clone() });
+                                app.chat_messages.push(ChatMsg::new("user", user.clone()));
                                 app.chat_scroll = 0;
                                 app.input.clear();
                                 #[cfg(feature = "http")]
@@ -756,24 +785,46 @@ pub async fn run() -> anyhow::Result<()> {
                                     let tx = evt_tx.clone();
                                     if app.chat_stream {
                                         // Insert placeholder assistant message for streaming
-                                        app.chat_messages.push(ChatMsg { role: "assistant".into(), content: String::new() });
+                                        app.chat_messages.push(ChatMsg::new("assistant", String::new()));
                                         let sid = app.chat_session_id.clone();
                                         let model = app.chat_model.clone();
                                         tokio::spawn(async move {
                                             let tx2 = tx.clone();
-                                            let r = net::core_chat_stream(&history, &model, move |event, data| {
+                                            // The core saves the reply, tool turns included, into the session
+                                            let r = net::core_chat_stream(&history, &model, sid.as_deref(), move |event, data| {
                                                 match event.as_str() {
-                                                    "token" => { let _ = tx.send(ChatEvent::StreamDelta(data.clone())); }
-                                                    "tool_call" => { let _ = tx.send(ChatEvent::ToolNote(format!("Tool call: {}", data))); }
-                                                    "tool_result" => { let _ = tx.send(ChatEvent::ToolNote(format!("Tool result: {}", data))); }
+                                                    "token" => {
+                                                        let text = serde_json::from_str::<serde_json::Value>(&data).ok()
+                                                            .and_then(|v| v.get("text").and_then(|t| t.as_str()).map(String::from))
+                                                            .unwrap_or(data);
+                                                        let _ = tx.send(ChatEvent::StreamDelta(text));
+                                                    }
+                                                    "tool_calls" => {
+                                                        let calls = serde_json::from_str::<serde_json::Value>(&data).ok()
+                                                            .and_then(|v| serde_json::from_value(v["calls"].clone()).ok())
+                                                            .unwrap_or_default();
+                                                        let _ = tx.send(ChatEvent::ToolCalls(calls));
+                                                    }
+                                                    "tool_result" => {
+                                                        let v = serde_json::from_str::<serde_json::Value>(&data).unwrap_or_default();
+                                                        let text = |k: &str| v.get(k).and_then(|x| x.as_str()).map(String::from);
+                                                        let _ = tx.send(ChatEvent::ToolResult(ChatMsg {
+                                                            tool_call_id: text("id"), tool_name: text("name"), approval_id: text("approval_id"),
+                                                            artifact_id: v.get("artifact_id").and_then(|x| x.as_i64()),
+                                                            ..ChatMsg::new("tool", text("result").unwrap_or_default())
+                                                        }));
+                                                    }
                                                     "error" => { let _ = tx.send(ChatEvent::Error(format!("{}", data))); }
                                                     "done" => { let _ = tx.send(ChatEvent::StreamDone); }
                                                     _ => {}
                                                 }
                                             }).await;
                                             match r {
-                                                Ok(final_text) => {
-                                                    if let Some(id) = sid { let _ = net::chat_append(&id, "assistant", &final_text).await; }
+                                                Ok(_) => {
+                                                    // Take the saved branch, with ids, as the source of truth
+                                                    if let Some(id) = sid {
+                                                        if let Ok(s) = net::chat_get(&id).await { let _ = tx2.send(ChatEvent::SessionLoaded(s.messages)); }
+                                                    }
                                                 }
                                                 Err(e) => { let _ = tx2.send(ChatEvent::Error(format!("Chat error: {}", e))); }
                                             }
@@ -860,18 +911,10 @@ pub async fn run() -> anyhow::Result<()> {
                             #[cfg(feature = "voice")]
                             {
                                 if let Some(rec) = app.voice_rec.take() {
-                                    let tx = evt_tx.clone();
+                                    // Keep the tail of the utterance; the stream finishes once the recorder closes
                                     tokio::spawn(async move {
                                         tokio::time::sleep(std::time::Duration::from_millis(300)).await;
-                                        match rec.stop_and_into_wav() {
-                                            Ok(wav) => {
-                                                match net::stt_transcribe_whisper(wav).await {
-                                                    Ok(text) => { let _ = tx.send(ChatEvent::VoiceTranscript(text)); },
-                                                    Err(e) => { let _ = tx.send(ChatEvent::Error(format!("stt error: {}", e))); }
-                                                }
-                                            }
-                                            Err(e) => { let _ = tx.send(ChatEvent::Error(format!("mic stop error: {}", e))); }
-                                        }
+                                        rec.stop();
                                     });
                                     push_toast(&mut app, "Processing…", ToastKind::Info);
                                     app.status = "Processing voice…".into();
@@ -913,18 +956,9 @@ pub async fn run() -> anyhow::Result<()> {
                     #[cfg(feature = "voice")]
                     {
                         if let Some(rec) = app.voice_rec.take() {
-                            let tx = evt_tx.clone();
                             tokio::spawn(async move {
                                 tokio::time::sleep(std::time::Duration::from_millis(300)).await;
-                                match rec.stop_and_into_wav() {
-                                    Ok(wav) => {
-                                        match net::stt_transcribe_whisper(wav).await {
-                                            Ok(text) => { let _ = tx.send(ChatEvent::VoiceTranscript(text)); },
-                                            Err(e) => { let _ = tx.send(ChatEvent::Error(format!("stt error: {}", e))); }
-                                        }
-                                    }
-                                    Err(e) => { let _ = tx.send(ChatEvent::Error(format!("mic stop error: {}", e))); }
-                                }
+                                rec.stop();
                             });
                             push_toast(&mut app, "Processing…", ToastKind::Info);
                             app.status = "Processing voice…".into();
@@ -953,7 +987,7 @@ pub async fn run() -> anyhow::Result<()> {
 
 async fn handle_command(mut app: &mut App, cmd: String) {
     if cmd == "/help" {
-        app.status = "Commands: /help, /new, /load, /delete, /stream on|off, /models [gpt-5|gpt-5-mini], /select on|off, /voice start [ws://…]|status|end|enable_wake|disable_wake, /audio test|devices|beep".into();
+        app.status = "Commands: /help, /new, /load, /delete, /stream on|off, /toolblocks open|closed, /models [gpt-5|gpt-5-mini], /select on|off, /voice start [ws://…]|status|end|enable_wake|disable_wake, /note [task], /recall TOPIC, /audio test|devices|beep".into();
         return;
     }
     if let Some(rest) = cmd.strip_prefix("/select") {
@@ -1084,6 +1118,29 @@ async fn handle_command(mut app: &mut App, cmd: String) {
             return;
         }
     }
+    if let Some(rest) = cmd.strip_prefix("/note") {
+        #[cfg(feature = "http")]
+        {
+            let task = rest.trim();
+            match net::note_dictate((!task.is_empty()).then_some(task)).await {
+                Ok(s) => { app.status = s.clone(); push_toast(&mut app, s, ToastKind::Success); }
+                Err(e) => { app.status = format!("note err: {}", e); push_toast(&mut app, format!("Note error: {}", e), ToastKind::Error); }
+            }
+            return;
+        }
+    }
+    if let Some(rest) = cmd.strip_prefix("/recall") {
+        #[cfg(feature = "http")]
+        {
+            let q = rest.trim();
+            if q.is_empty() { app.status = "usage: /recall TOPIC".into(); return; }
+            match net::notes_recall(q).await {
+                Ok((spoken, lines)) => { app.status = spoken; app.tool_output_text = lines; push_toast(&mut app, "Notes recalled", ToastKind::Info); }
+                Err(e) => { app.status = format!("recall err: {}", e); push_toast(&mut app, format!("Recall error: {}", e), ToastKind::Error); }
+            }
+            return;
+        }
+    }
     if let Some(rest) = cmd.strip_prefix("/logs") {
         let n: usize = rest.trim().split_whitespace().next().and_then(|s| if s.is_empty() { None } else { s.parse().ok() }).unwrap_or(200);
         let path = std::path::Path::new("storage/logs/ui-tui.log");
@@ -1267,6 +1324,16 @@ async fn handle_command(mut app: &mut App, cmd: String) {
             return;
         }
     }
+    if let Some(rest) = cmd.strip_prefix("/toolblocks") {
+        match rest.trim() {
+            "open" => app.chat_tools_open = true,
+            "closed" => app.chat_tools_open = false,
+            "" => app.chat_tools_open = !app.chat_tools_open,
+            _ => { app.status = "usage: /toolblocks [open|closed]".into(); return; }
+        }
+        app.status = format!("tool blocks: {}", if app.chat_tools_open { "open" } else { "closed" });
+        return;
+    }
     if let Some(rest) = cmd.strip_prefix("/stream ") {
         let on = rest.trim().eq_ignore_ascii_case("on");
         let off = rest.trim().eq_ignore_ascii_case("off");
@@ -1497,6 +1564,8 @@ fn ui(f: &mut Frame, app: &App) {
             "Tools: ←/→ servers, ↑/↓ tools, Ctrl-E edit params, Ctrl-L pick Steam game, Enter run",
             "Voice: Hold Ctrl-Space to dictate; Hang up: Ctrl-\\",
             "Realtime: /voice status | /voice end | /voice enable_wake | /voice disable_wake",
+            "Notes: /note [task] dictates until silence • /recall TOPIC reads notes back",
+            "Chat: /toolblocks open|closed shows tool calls and results in full or as one line",
             "Codex: Ctrl-N new session, Ctrl-Y continue",
             "Projects: Ctrl-G picker, /proj scan | /proj pick PATH | /proj clear",
             "Logs: /logs [N] to view last N lines",
@@ -1869,6 +1938,76 @@ pub mod net {
         Ok(())
     }
 
+    /// Dictate a voice note on core's mic; returns a one-line summary.
+    pub async fn note_dictate(task: Option<&str>) -> anyhow::Result<String> {
+        let resp = reqwest::Client::new().post("http://127.0.0.1:6061/api/notes/dictate")
+            .json(&serde_json::json!({"task": task})).send().await?;
+        let status = resp.status();
+        let v: serde_json::Value = resp.json().await.unwrap_or_default();
+        if !status.is_success() {
+            anyhow::bail!(v.get("message").and_then(|m| m.as_str()).unwrap_or(status.as_str()).to_string());
+        }
+        let note = &v["note"];
+        Ok(format!("Noted in {}: {}", note["task"].as_str().unwrap_or("?"), note["text"].as_str().unwrap_or("")))
+    }
+
+    /// Search voice notes; returns the read-back and one line per hit.
+    pub async fn notes_recall(q: &str) -> anyhow::Result<(String, String)> {
+        let resp = reqwest::Client::new().get("http://127.0.0.1:6061/api/notes/recall").query(&[("q", q)]).send().await?;
+        let status = resp.status();
+        let v: serde_json::Value = resp.json().await.unwrap_or_default();
+        if !status.is_success() {
+            anyhow::bail!(v.get("message").and_then(|m| m.as_str()).unwrap_or(status.as_str()).to_string());
+        }
+        let lines = v["hits"].as_array().map(|hits| hits.iter()
+            .map(|h| format!("[{}] {}", h["created_at"].as_str().unwrap_or(""), h["text"].as_str().unwrap_or("")))
+            .collect::<Vec<_>>().join("\n")).unwrap_or_default();
+        Ok((v["spoken"].as_str().unwrap_or("").to_string(), lines))
+    }
+
+    /// Stream push-to-talk audio through core's `/api/stt/stream`, reporting partial
+    /// transcripts; returns the final one. Falls back to Whisper upload when core is unreachable.
+    #[cfg(all(feature = "http", feature = "voice"))]
+    pub async fn stt_stream(mut frames: tokio::sync::mpsc::UnboundedReceiver<Vec<i16>>, sample_rate: u32, on_partial: impl Fn(String)) -> anyhow::Result<String> {
+        use futures_util::SinkExt;
+        use tokio_tungstenite::tungstenite::Message;
+        let url = format!("ws://127.0.0.1:6061/api/stt/stream?sample_rate={}", sample_rate);
+        let mut ws = match tokio_tungstenite::connect_async(url.as_str()).await {
+            Ok((ws, _)) => ws,
+            Err(_) => {
+                let mut pcm = Vec::new();
+                while let Some(f) = frames.recv().await { pcm.extend_from_slice(&f); }
+                return stt_transcribe_whisper(foreman_types::audio::encode_wav_pcm16(&pcm, sample_rate)).await;
+            }
+        };
+        let mut sending = true;
+        loop {
+            tokio::select! {
+                f = frames.recv(), if sending => match f {
+                    Some(pcm) => {
+                        let bytes: Vec<u8> = pcm.iter().flat_map(|s| s.to_le_bytes()).collect();
+                        ws.send(Message::Binary(bytes)).await?;
+                    }
+                    None => {
+                        sending = false;
+                        ws.send(Message::Text(serde_json::json!({"type": "end"}).to_string())).await?;
+                    }
+                },
+                msg = ws.next() => {
+                    let Some(msg) = msg else { anyhow::bail!("stt stream closed") };
+                    let Message::Text(t) = msg? else { continue };
+                    let v: serde_json::Value = serde_json::from_str(&t)?;
+                    let text = v.get("text").and_then(|x| x.as_str()).unwrap_or("").to_string();
+                    match v.get("type").and_then(|x| x.as_str()) {
+                        Some("partial") => on_partial(text),
+                        Some("final") => { let _ = ws.close(None).await; return Ok(text); }
+                        _ => anyhow::bail!(v.get("message").and_then(|x| x.as_str()).unwrap_or("stt error").to_string()),
+                    }
+                }
+            }
+        }
+    }
+
     #[cfg(all(feature = "http", feature = "voice"))]
     pub async fn stt_transcribe_whisper(wav_bytes: Vec<u8>) -> anyhow::Result<String> {
         let key = std::env::var("OPENAI_API_KEY").map_err(|_| anyhow::anyhow!("OPENAI_API_KEY not set"))?;
@@ -2046,7 +2185,7 @@ pub mod net {
 
     pub async fn core_chat_complete(history: &Vec<super::ChatMsg>, model: &str) -> anyhow::Result<String> {
         let body = serde_json::json!({
-            "messages": history.iter().map(|m| serde_json::json!({"role": m.role, "content": m.content})).collect::<Vec<_>>(),
+            "messages": history.iter().map(|m| serde_json::json!({"role": m.role, "content": m.content, "tool_calls": m.tool_calls, "tool_call_id": m.tool_call_id})).collect::<Vec<_>>(),
             "model": model,
         });
         let resp = reqwest::Client::new().post("http://127.0.0.1:6061/api/chat/complete").json(&body).send().await?;
@@ -2080,6 +2219,12 @@ pub mod net {
         Ok(Some(resp.json::<ChatSession>().await?))
     }
 
+    pub async fn chat_get(id: &str) -> anyhow::Result<ChatSession> {
+        let resp = reqwest::get(&format!("http://127.0.0.1:6061/api/chat/sessions/{}", id)).await?;
+        if !resp.status().is_success() { anyhow::bail!("chat get http {}", resp.status()); }
+        Ok(resp.json::<ChatSession>().await?)
+    }
+
     pub async fn chat_new() -> anyhow::Result<String> {
         let resp = reqwest::Client::new().post("http://127.0.0.1:6061/api/chat/sessions").send().await?;
         if !resp.status().is_success() { anyhow::bail!("chat new http {}", resp.status()); }
@@ -2207,11 +2352,14 @@ pub mod net {
     }
 
     // Core streaming with tools (SSE)
-    pub async fn core_chat_stream<F>(history: &Vec<super::ChatMsg>, model: &str, mut on_event: F) -> anyhow::Result<String>
+    // With a session the core saves the reply itself; tool turns in `history`
+    // are sent with their calls and call ids so the model sees them again.
+    pub async fn core_chat_stream<F>(history: &Vec<super::ChatMsg>, model: &str, session_id: Option<&str>, mut on_event: F) -> anyhow::Result<String>
     where F: FnMut(String, String) + Send + 'static {
         let body = serde_json::json!({
-            "messages": history.iter().map(|m| serde_json::json!({"role": m.role, "content": m.content})).collect::<Vec<_>>(),
+            "messages": history.iter().map(|m| serde_json::json!({"role": m.role, "content": m.content, "tool_calls": m.tool_calls, "tool_call_id": m.tool_call_id})).collect::<Vec<_>>(),
             "model": model,
+            "session_id": session_id,
         });
         let client = reqwest::Client::new();
         let resp = client.post("http://127.0.0.1:6061/api/chat/stream").json(&body).send().await?;
@@ -2309,11 +2457,40 @@ pub mod net {
 #[derive(Clone, Debug)]
 pub enum ChatModal { Delete { list: Vec<net::SessionInfo>, sel: usize } }
 
-#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-pub struct ChatMsg { pub role: String, pub content: String }
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+pub struct ChatMsg {
+    pub role: String,
+    pub content: String,
+    /// Tools an assistant turn called.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ChatToolCall>,
+    /// For `tool` messages: the call answered and how it was approved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact_id: Option<i64>,
+}
+
+impl ChatMsg {
+    pub fn new(role: &str, content: impl Into<String>) -> Self { Self { role: role.into(), content: content.into(), ..Default::default() } }
+}
+
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+pub struct ChatToolCall { pub id: String, pub name: String, #[serde(default)] pub arguments: serde_json::Value }
 
 #[derive(Clone, Debug)]
-enum ChatEvent { AssistantReply(String), StreamDelta(String), StreamDone, Error(String), ToolNote(String), VoiceTranscript(String) }
+enum ChatEvent { AssistantReply(String), StreamDelta(String), StreamDone, Error(String), ToolCalls(Vec<ChatToolCall>), ToolResult(ChatMsg), SessionLoaded(Vec<ChatMsg>), VoicePartial(String), VoiceTranscript(String) }
+fn join_transcript(base: &str, text: &str) -> String {
+    match (base.is_empty(), text.is_empty()) {
+        (_, true) => base.to_string(),
+        (true, false) => text.to_string(),
+        (false, false) => format!("{} {}", base, text),
+    }
+}
 // Extend background event channel with Agents log lines via SSE
 #[derive(Clone, Debug)]
 enum AgentsEvent { Log(String) }
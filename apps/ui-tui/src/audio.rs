@@ -1,47 +1,65 @@
 #![cfg(feature = "voice")]
-use std::io::Write;
+use std::io::{Read, Write};
+
+/// Capture rate for push-to-talk: 16 kHz mono PCM16.
+pub const SAMPLE_RATE: u32 = 16_000;
+/// 30 ms at 16 kHz.
+const FRAME_BYTES: usize = 960;
 
 pub struct VoiceRecorder {
     child: std::process::Child,
-    path: std::path::PathBuf,
 }
 
 impl VoiceRecorder {
-    pub fn start() -> anyhow::Result<Self> {
-        // Ensure storage/tmp exists
-        let tmp_dir = std::path::Path::new("storage/tmp");
-        std::fs::create_dir_all(tmp_dir)?;
-        let ts = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_millis();
-        let path = tmp_dir.join(format!("voice-{}.wav", ts));
-
+    /// Start the recorder; PCM frames arrive on the returned channel until `stop`.
+    pub fn start() -> anyhow::Result<(Self, tokio::sync::mpsc::UnboundedReceiver<Vec<i16>>)> {
         // Prefer ffmpeg for graceful stop by sending 'q' to stdin
         let mut use_ffmpeg = false;
         if std::process::Command::new("ffmpeg").arg("-version").stdout(std::process::Stdio::null()).stderr(std::process::Stdio::null()).status().is_ok() {
             use_ffmpeg = true;
         }
 
-        let child = if use_ffmpeg {
-            // Linux ALSA default device; mono, 16 kHz
+        let mut child = if use_ffmpeg {
+            // Linux ALSA default device; mono, 16 kHz, raw s16le on stdout
             std::process::Command::new("ffmpeg")
-                .args(["-hide_banner", "-loglevel", "error", "-f", "alsa", "-i", "default", "-ac", "1", "-ar", "16000", "-y"])
-                .arg(&path)
+                .args(["-hide_banner", "-loglevel", "error", "-f", "alsa", "-i", "default", "-ac", "1", "-ar", "16000", "-f", "s16le", "-"])
                 .stdin(std::process::Stdio::piped())
+                .stdout(std::process::Stdio::piped())
                 .spawn()
                 .map_err(|e| anyhow::anyhow!("failed to start ffmpeg: {}", e))?
         } else {
-            // Fallback to arecord; mono, 16 kHz, 16-bit LE, WAV
+            // Fallback to arecord; mono, 16 kHz, 16-bit LE, raw on stdout
             std::process::Command::new("arecord")
-                .args(["-q", "-f", "S16_LE", "-r", "16000", "-c", "1", "-t", "wav"])
-                .arg(&path)
+                .args(["-q", "-f", "S16_LE", "-r", "16000", "-c", "1", "-t", "raw"])
                 .stdin(std::process::Stdio::null())
+                .stdout(std::process::Stdio::piped())
                 .spawn()
                 .map_err(|e| anyhow::anyhow!("failed to start arecord: {}", e))?
         };
 
-        Ok(Self { child, path })
+        let mut stdout = child.stdout.take().ok_or_else(|| anyhow::anyhow!("recorder has no stdout"))?;
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        std::thread::spawn(move || {
+            let mut buf = vec![0u8; FRAME_BYTES];
+            let mut filled = 0;
+            loop {
+                match stdout.read(&mut buf[filled..]) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => filled += n,
+                }
+                if filled == FRAME_BYTES {
+                    if tx.send(pcm_from_le(&buf)).is_err() { return; }
+                    filled = 0;
+                }
+            }
+            if filled >= 2 { let _ = tx.send(pcm_from_le(&buf[..filled])); }
+        });
+
+        Ok((Self { child }, rx))
     }
 
-    pub fn stop_and_into_wav(mut self) -> anyhow::Result<Vec<u8>> {
+    /// Stop recording; the frame channel closes once the recorder has flushed.
+    pub fn stop(mut self) {
         // Try graceful stop if ffmpeg (send 'q') else kill
         if let Some(mut stdin) = self.child.stdin.take() {
             let _ = stdin.write_all(b"q\n");
@@ -49,10 +67,9 @@ impl VoiceRecorder {
             let _ = self.child.kill();
         }
         let _ = self.child.wait();
-
-        // Read file and remove it
-        let bytes = std::fs::read(&self.path)?;
-        let _ = std::fs::remove_file(&self.path);
-        Ok(bytes)
     }
 }
+
+fn pcm_from_le(bytes: &[u8]) -> Vec<i16> {
+    bytes.chunks_exact(2).map(|c| i16::from_le_bytes([c[0], c[1]])).collect()
+}
@@ -11,10 +11,16 @@ pub fn draw(f: &mut Frame, area: Rect, app: &App) {
         lines.push(Line::from("(Type a message and press Enter)"));
     } else {
         for m in &app.chat_messages {
+            if m.role == "tool" { tool_block(&mut lines, m, app.chat_tools_open); continue; }
             let who = if m.role == "assistant" { "AI" } else { "You" };
             let style = if m.role == "assistant" { Style::default().fg(Color::Cyan) } else { Style::default().fg(Color::Green) };
             lines.push(Line::from(format!("{}:", who)).style(style));
             for l in m.content.lines() { lines.push(Line::from(format!("  {}", l))); }
+            for c in &m.tool_calls {
+                let args = c.arguments.to_string();
+                let args = if app.chat_tools_open { args } else { clip(&args, 60) };
+                lines.push(Line::from(format!("  ⚙ {}({})", c.name, args)).style(Style::default().fg(Color::Yellow)));
+            }
             lines.push(Line::from(""));
         }
     }
@@ -30,3 +36,26 @@ pub fn draw(f: &mut Frame, area: Rect, app: &App) {
         .wrap(Wrap { trim: true });
     f.render_widget(p, area);
 }
+
+/// A tool result: one summary line, or the full output when tool blocks are open
+/// (`/toolblocks`).
+fn tool_block(lines: &mut Vec<Line<'_>>, m: &crate::app::ChatMsg, open: bool) {
+    let name = m.tool_name.as_deref().unwrap_or("tool");
+    let mut head = format!("{} {} → {} chars", if open { "▾" } else { "▸" }, name, m.content.chars().count());
+    if m.approval_id.is_some() {
+        // Refusals carry the decision in the result; a run tool was approved
+        let decision = serde_json::from_str::<serde_json::Value>(&m.content).ok()
+            .and_then(|v| v.get("decision").and_then(|d| d.as_str()).map(String::from));
+        head.push_str(match decision.as_deref() { Some("deny") => " • denied", Some("timeout") => " • approval timed out", _ => " • approved" });
+    }
+    if let Some(a) = m.artifact_id { head.push_str(&format!(" • full result: artifact {}", a)); }
+    lines.push(Line::from(format!("  {}", head)).style(Style::default().fg(Color::Yellow)));
+    if open {
+        for l in m.content.lines() { lines.push(Line::from(format!("    {}", l)).style(Style::default().fg(Color::DarkGray))); }
+    }
+    lines.push(Line::from(""));
+}
+
+fn clip(s: &str, n: usize) -> String {
+    if s.chars().count() <= n { s.to_string() } else { format!("{}…", s.chars().take(n).collect::<String>()) }
+}
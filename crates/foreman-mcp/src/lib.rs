@@ -22,3 +22,82 @@ impl ToolResponse {
     pub fn err(msg: impl Into<String>) -> Self { Self { ok: false, result: JsonValue::Null, error: Some(msg.into()) } }
 }
 
+
+pub mod schema;
+
+/// Tool name a server answers with its `Describe` when it supports the handshake.
+pub const DESCRIBE_TOOL: &str = "describe";
+
+/// What a tool can do to the machine; drives the core's policy check.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolRisk {
+    /// Changes files or system state.
+    #[serde(default)]
+    pub writes: bool,
+    /// Talks to the network.
+    #[serde(default)]
+    pub network: bool,
+    /// Runs a program named by the caller (`cmd` + `args`).
+    #[serde(default)]
+    pub exec: bool,
+    /// Parameter holding the path (or list of paths) the tool touches.
+    #[serde(default)]
+    pub paths_param: Option<String>,
+}
+
+/// One tool as declared by a manifest entry or a `describe` answer.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolSpec {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// JSON Schema for `params`.
+    #[serde(default)]
+    pub input_schema: Option<JsonValue>,
+    /// JSON Schema for `result`; informational.
+    #[serde(default)]
+    pub output_schema: Option<JsonValue>,
+    #[serde(default)]
+    pub risk: ToolRisk,
+}
+
+impl ToolSpec {
+    pub fn named(name: impl Into<String>) -> Self { Self { name: name.into(), ..Default::default() } }
+
+    /// Fill what this spec leaves unset from `other` (risk flags are OR-ed).
+    pub fn or(mut self, other: &ToolSpec) -> Self {
+        if self.description.is_none() { self.description = other.description.clone(); }
+        if self.input_schema.is_none() { self.input_schema = other.input_schema.clone(); }
+        if self.output_schema.is_none() { self.output_schema = other.output_schema.clone(); }
+        self.risk.writes |= other.risk.writes;
+        self.risk.network |= other.risk.network;
+        self.risk.exec |= other.risk.exec;
+        if self.risk.paths_param.is_none() { self.risk.paths_param = other.risk.paths_param.clone(); }
+        self
+    }
+}
+
+/// What a call does, in the shape of the core gatekeeper's `ProposedAction`.
+/// Servers whose risk depends on the arguments (a recursive delete, a branch
+/// listing vs. a branch delete, the emulator behind a game title) build one.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ProposedAction {
+    pub command: String,
+    pub writes: bool,
+    pub paths: Vec<String>,
+    pub intent: Option<String>,
+}
+
+/// A leading `~/` (or a bare `~`) is the user's home directory.
+pub fn expand_home(p: &str) -> std::path::PathBuf {
+    match (p.strip_prefix("~/").or(if p == "~" { Some("") } else { None }), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => std::path::Path::new(&home).join(rest),
+        _ => std::path::PathBuf::from(p),
+    }
+}
+
+/// Result of the `describe` handshake.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Describe {
+    pub tools: Vec<ToolSpec>,
+}
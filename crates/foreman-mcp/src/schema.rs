@@ -0,0 +1,143 @@
+//! Minimal JSON Schema check for tool params.
+//!
+//! Covers the keywords tool manifests use: `type`, `enum`, `properties`,
+//! `required`, `additionalProperties`, `items`, `minimum`/`maximum`,
+//! `minLength`/`maxLength` and `minItems`/`maxItems`. Other keywords are ignored.
+
+use serde_json::Value as JsonValue;
+
+/// Check `value` against `schema`. Returns every violation, each prefixed
+/// with the path of the offending value (`params.args[0]`).
+pub fn validate(schema: &JsonValue, value: &JsonValue) -> Result<(), Vec<String>> {
+    let mut errors = vec![];
+    check(schema, value, "params", &mut errors);
+    if errors.is_empty() { Ok(()) } else { Err(errors) }
+}
+
+fn check(schema: &JsonValue, value: &JsonValue, at: &str, errors: &mut Vec<String>) {
+    let Some(schema) = schema.as_object() else { return };
+    if let Some(ty) = schema.get("type") {
+        let types: Vec<&str> = match ty {
+            JsonValue::String(s) => vec![s.as_str()],
+            JsonValue::Array(a) => a.iter().filter_map(|t| t.as_str()).collect(),
+            _ => vec![],
+        };
+        if !types.is_empty() && !types.iter().any(|t| is_type(value, t)) {
+            errors.push(format!("{}: expected {}, got {}", at, types.join(" or "), type_name(value)));
+            return;
+        }
+    }
+    if let Some(options) = schema.get("enum").and_then(|e| e.as_array()) {
+        if !options.contains(value) {
+            let list: Vec<String> = options.iter().map(|o| o.to_string()).collect();
+            errors.push(format!("{}: must be one of {}", at, list.join(", ")));
+        }
+    }
+    match value {
+        JsonValue::Object(map) => {
+            let props = schema.get("properties").and_then(|p| p.as_object());
+            for key in schema.get("required").and_then(|r| r.as_array()).into_iter().flatten().filter_map(|k| k.as_str()) {
+                if !map.contains_key(key) { errors.push(format!("{}: missing required property '{}'", at, key)); }
+            }
+            for (key, v) in map {
+                let path = format!("{}.{}", at, key);
+                match props.and_then(|p| p.get(key)) {
+                    Some(sub) => check(sub, v, &path, errors),
+                    None => match schema.get("additionalProperties") {
+                        Some(JsonValue::Bool(false)) => errors.push(format!("{}: unknown property", path)),
+                        Some(sub @ JsonValue::Object(_)) => check(sub, v, &path, errors),
+                        _ => {}
+                    },
+                }
+            }
+        }
+        JsonValue::Array(items) => {
+            bounds(schema, "minItems", "maxItems", items.len() as f64, at, "items", errors);
+            if let Some(sub) = schema.get("items") {
+                for (i, v) in items.iter().enumerate() { check(sub, v, &format!("{}[{}]", at, i), errors); }
+            }
+        }
+        JsonValue::String(s) => bounds(schema, "minLength", "maxLength", s.chars().count() as f64, at, "characters", errors),
+        JsonValue::Number(n) => bounds(schema, "minimum", "maximum", n.as_f64().unwrap_or(0.0), at, "", errors),
+        _ => {}
+    }
+}
+
+fn bounds(schema: &serde_json::Map<String, JsonValue>, min: &str, max: &str, n: f64, at: &str, unit: &str, errors: &mut Vec<String>) {
+    let unit = if unit.is_empty() { String::new() } else { format!(" {}", unit) };
+    if let Some(lo) = schema.get(min).and_then(|v| v.as_f64()) {
+        if n < lo { errors.push(format!("{}: must be at least {}{}", at, lo, unit)); }
+    }
+    if let Some(hi) = schema.get(max).and_then(|v| v.as_f64()) {
+        if n > hi { errors.push(format!("{}: must be at most {}{}", at, hi, unit)); }
+    }
+}
+
+fn is_type(value: &JsonValue, ty: &str) -> bool {
+    match ty {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => value.as_i64().is_some() || value.as_u64().is_some() || value.as_f64().map(|f| f.fract() == 0.0).unwrap_or(false),
+        _ => true,
+    }
+}
+
+fn type_name(value: &JsonValue) -> &'static str {
+    match value {
+        JsonValue::Null => "null",
+        JsonValue::Bool(_) => "boolean",
+        JsonValue::Number(_) => "number",
+        JsonValue::String(_) => "string",
+        JsonValue::Array(_) => "array",
+        JsonValue::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn exec_schema() -> JsonValue {
+        json!({
+            "type": "object",
+            "properties": {
+                "cmd": {"type": "string", "minLength": 1},
+                "args": {"type": "array", "items": {"type": "string"}, "maxItems": 2},
+                "n": {"type": "integer", "minimum": 1, "maximum": 50},
+                "mode": {"enum": ["a", "b"]}
+            },
+            "required": ["cmd"],
+            "additionalProperties": false
+        })
+    }
+
+    #[test]
+    fn accepts_matching_params() {
+        assert!(validate(&exec_schema(), &json!({"cmd": "ls", "args": ["-l"], "n": 5, "mode": "a"})).is_ok());
+        assert!(validate(&json!({"type": "object", "properties": {}, "additionalProperties": true}), &json!({"anything": [1]})).is_ok());
+    }
+
+    #[test]
+    fn reports_each_violation_with_its_path() {
+        let errs = validate(&exec_schema(), &json!({"args": ["a", 2, "c"], "n": 0, "mode": "z", "extra": true})).unwrap_err();
+        assert!(errs.contains(&"params: missing required property 'cmd'".to_string()), "{:?}", errs);
+        assert!(errs.contains(&"params.args: must be at most 2 items".to_string()), "{:?}", errs);
+        assert!(errs.contains(&"params.args[1]: expected string, got number".to_string()), "{:?}", errs);
+        assert!(errs.contains(&"params.n: must be at least 1".to_string()), "{:?}", errs);
+        assert!(errs.contains(&"params.mode: must be one of \"a\", \"b\"".to_string()), "{:?}", errs);
+        assert!(errs.contains(&"params.extra: unknown property".to_string()), "{:?}", errs);
+        assert_eq!(validate(&exec_schema(), &json!("ls")).unwrap_err(), vec!["params: expected object, got string".to_string()]);
+    }
+
+    #[test]
+    fn nullable_types_and_integers() {
+        let s = json!({"type": "object", "properties": {"repo": {"type": ["string", "null"]}, "n": {"type": "integer"}}});
+        assert!(validate(&s, &json!({"repo": null, "n": 3})).is_ok());
+        assert_eq!(validate(&s, &json!({"n": 2.5})).unwrap_err(), vec!["params.n: expected integer, got number".to_string()]);
+    }
+}
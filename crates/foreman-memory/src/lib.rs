@@ -2,8 +2,17 @@ use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
 use sqlx::{Pool, Row, Sqlite, SqlitePool};
 use std::path::Path;
+use std::str::FromStr;
+
+/// The row of an `INSERT … RETURNING` read with `fetch_all`. `fetch_one` stops
+/// stepping after the row, which leaves the insert uncommitted until the
+/// connection reuses the statement, so the rest of the pool could miss it.
+fn inserted(rows: Vec<sqlx::sqlite::SqliteRow>) -> Result<sqlx::sqlite::SqliteRow> {
+    rows.into_iter().next().ok_or_else(|| anyhow::anyhow!("insert returned no row"))
+}
 
 #[derive(Clone)]
 pub struct MemoryStore {
@@ -108,6 +117,75 @@ pub struct AgentIssue {
     pub ts: DateTime<Utc>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallPlanRow {
+    pub id: String,
+    pub manager: String,
+    pub pkg: String,
+    pub commands_json: String,
+    pub rollback_json: Option<String>,
+    pub status: String,
+    pub dry_run_json: Option<String>,
+    pub result_json: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RealtimeSessionRow {
+    pub id: String,
+    pub task_id: i64,
+    pub model: Option<String>,
+    pub endpoint: Option<String>,
+    pub transport: Option<String>,
+    pub status: String,
+    pub error: Option<String>,
+    pub recording: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatSessionRow {
+    pub id: String,
+    pub title: Option<String>,
+    pub head_id: Option<String>,
+    pub forked_from: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Rolling summary of older turns, and the last message it covers.
+    pub summary: Option<String>,
+    pub summary_upto: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessageRow {
+    pub id: String,
+    pub session_id: String,
+    pub parent_id: Option<String>,
+    pub role: String,
+    pub content: String,
+    pub at: DateTime<Utc>,
+    /// Assistant turns: the tool calls made, as JSON `[{id, name, arguments}]`.
+    pub tool_calls: Option<String>,
+    /// Tool turns: the call answered, the tool, the approval asked and the
+    /// artifact holding the full result when content was capped.
+    pub tool_call_id: Option<String>,
+    pub tool_name: Option<String>,
+    pub approval_id: Option<String>,
+    pub artifact_id: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatSearchHit {
+    pub session_id: String,
+    pub message_id: String,
+    pub role: String,
+    pub snippet: String,
+    pub score: f64,
+    pub at: DateTime<Utc>,
+}
+
 impl MemoryStore {
     pub async fn new(db_path: &Path, migrations_dir: &Path) -> Result<Self> {
         if let Some(parent) = db_path.parent() { std::fs::create_dir_all(parent)?; }
@@ -116,7 +194,8 @@ impl MemoryStore {
         } else {
             format!("sqlite://{}", db_path.display())
         };
-        let pool = SqlitePool::connect(&url).await?;
+        let opts = SqliteConnectOptions::from_str(&url)?.create_if_missing(true);
+        let pool = SqlitePoolOptions::new().connect_with(opts).await?;
         // Run migrations from a filesystem path
         let migrator = sqlx::migrate::Migrator::new(migrations_dir).await?;
         migrator.run(&pool).await?;
@@ -124,7 +203,8 @@ impl MemoryStore {
     }
 
     pub async fn new_in_memory(migrations_dir: &Path) -> Result<Self> {
-        let pool = SqlitePool::connect("sqlite::memory:").await?;
+        // Every connection to :memory: is its own database; keep to one
+        let pool = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await?;
         let migrator = sqlx::migrate::Migrator::new(migrations_dir).await?;
         migrator.run(&pool).await?;
         Ok(Self { pool })
@@ -134,14 +214,15 @@ impl MemoryStore {
 
     pub async fn append_event(&self, task_id: Option<i64>, kind: &str, payload_json: Option<&JsonValue>) -> Result<i64> {
         let payload = payload_json.map(|v| v.to_string());
-        let row = sqlx::query(
+        let rows = sqlx::query(
             r#"INSERT INTO Event(task_id, kind, payload_json) VALUES (?1, ?2, ?3) RETURNING id"#,
         )
         .bind(task_id)
         .bind(kind)
         .bind(payload)
-        .fetch_one(&self.pool)
+        .fetch_all(&self.pool)
         .await?;
+        let row = inserted(rows)?;
         Ok(row.get::<i64, _>("id"))
     }
 
@@ -153,28 +234,30 @@ impl MemoryStore {
         payload_json: Option<&JsonValue>,
     ) -> Result<i64> {
         let payload = payload_json.map(|v| v.to_string());
-        let row = sqlx::query(
+        let rows = sqlx::query(
             r#"INSERT INTO Event(task_id, agent_id, kind, payload_json) VALUES (?1, ?2, ?3, ?4) RETURNING id"#,
         )
         .bind(task_id)
         .bind(agent_id)
         .bind(kind)
         .bind(payload)
-        .fetch_one(&self.pool)
+        .fetch_all(&self.pool)
         .await?;
+        let row = inserted(rows)?;
         Ok(row.get::<i64, _>("id"))
     }
 
     pub async fn create_task(&self, title: &str, status: &str, tags: Option<&str>) -> Result<Task> {
-        let row = sqlx::query(
+        let rows = sqlx::query(
             r#"INSERT INTO Task(title, status, tags) VALUES (?1, ?2, ?3)
                RETURNING id, title, status, created_at, updated_at, tags"#,
         )
         .bind(title)
         .bind(status)
         .bind(tags)
-        .fetch_one(&self.pool)
+        .fetch_all(&self.pool)
         .await?;
+        let row = inserted(rows)?;
         Ok(Task {
             id: row.get("id"),
             title: row.get("title"),
@@ -202,6 +285,15 @@ impl MemoryStore {
             .collect())
     }
 
+    pub async fn update_task_status(&self, id: i64, status: &str) -> Result<()> {
+        sqlx::query(r#"UPDATE Task SET status = ?1 WHERE id = ?2"#)
+            .bind(status)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
     pub async fn upsert_task_digest(&self, task_id: i64, short: Option<&str>, paragraph: Option<&str>, tokens: Option<i64>) -> Result<()> {
         sqlx::query(
             r#"INSERT INTO TaskDigest(task_id, short, paragraph, tokens) VALUES (?1, ?2, ?3, ?4)
@@ -216,16 +308,43 @@ impl MemoryStore {
         Ok(())
     }
 
+    /// The task's digest: the paragraph when there is one, else the short form.
+    pub async fn get_task_digest(&self, task_id: i64) -> Result<Option<String>> {
+        let row = sqlx::query(r#"SELECT COALESCE(paragraph, short) AS digest FROM TaskDigest WHERE task_id = ?1"#)
+            .bind(task_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.and_then(|r| r.get::<Option<String>, _>("digest")))
+    }
+
     pub async fn put_atom(&self, task_id: i64, kind: &str, text: &str, tags: Option<&str>) -> Result<i64> {
-        let row = sqlx::query(
+        let rows = sqlx::query(
             r#"INSERT INTO Atom(task_id, kind, text, tags) VALUES (?1, ?2, ?3, ?4) RETURNING id"#,
         )
         .bind(task_id)
         .bind(kind)
         .bind(text)
         .bind(tags)
-        .fetch_one(&self.pool)
+        .fetch_all(&self.pool)
         .await?;
+        let row = inserted(rows)?;
+        Ok(row.get::<i64, _>("id"))
+    }
+
+    /// `put_atom` with provenance, e.g. source `voice` and source_ref `artifact:12`.
+    pub async fn put_atom_sourced(&self, task_id: i64, kind: &str, text: &str, tags: Option<&str>, source: &str, source_ref: Option<&str>) -> Result<i64> {
+        let rows = sqlx::query(
+            r#"INSERT INTO Atom(task_id, kind, text, tags, source, source_ref) VALUES (?1, ?2, ?3, ?4, ?5, ?6) RETURNING id"#,
+        )
+        .bind(task_id)
+        .bind(kind)
+        .bind(text)
+        .bind(tags)
+        .bind(source)
+        .bind(source_ref)
+        .fetch_all(&self.pool)
+        .await?;
+        let row = inserted(rows)?;
         Ok(row.get::<i64, _>("id"))
     }
 
@@ -289,16 +408,37 @@ impl MemoryStore {
             .collect())
     }
 
+    /// All events of a task, oldest first.
+    pub async fn get_events_by_task(&self, task_id: i64) -> Result<Vec<Event>> {
+        let rows = sqlx::query(
+            r#"SELECT id, task_id, kind, payload_json, created_at FROM Event WHERE task_id = ?1 ORDER BY id ASC"#,
+        )
+        .bind(task_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|r| Event {
+                id: r.get("id"),
+                task_id: r.get("task_id"),
+                kind: r.get("kind"),
+                payload_json: r.get("payload_json"),
+                created_at: r.get("created_at"),
+            })
+            .collect())
+    }
+
     pub async fn create_artifact(&self, task_id: i64, path: &Path, mime: Option<&str>, sha256: Option<&str>) -> Result<i64> {
-        let row = sqlx::query(
+        let rows = sqlx::query(
             r#"INSERT INTO Artifact(task_id, path, mime, sha256) VALUES (?1, ?2, ?3, ?4) RETURNING id"#,
         )
         .bind(task_id)
         .bind(path.to_string_lossy().to_string())
         .bind(mime)
         .bind(sha256)
-        .fetch_one(&self.pool)
+        .fetch_all(&self.pool)
         .await?;
+        let row = inserted(rows)?;
         Ok(row.get::<i64, _>("id"))
     }
 
@@ -323,7 +463,7 @@ impl MemoryStore {
         auto_approval_level: i64,
         plan_artifact_id: Option<i64>,
     ) -> Result<Agent> {
-        let row = sqlx::query(
+        let rows = sqlx::query(
             r#"INSERT INTO Agent(id, task_id, title, status, plan_artifact_id, root_dir, model, servers_json, auto_approval_level)
                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
                RETURNING id, task_id, title, status, plan_artifact_id, root_dir, model, servers_json, auto_approval_level, created_at, updated_at"#,
@@ -337,8 +477,9 @@ impl MemoryStore {
         .bind(model)
         .bind(servers_json)
         .bind(auto_approval_level)
-        .fetch_one(&self.pool)
+        .fetch_all(&self.pool)
         .await?;
+        let row = inserted(rows)?;
         Ok(Agent {
             id: row.get("id"),
             task_id: row.get("task_id"),
@@ -420,7 +561,7 @@ impl MemoryStore {
         details_md: Option<&str>,
         action_required: bool,
     ) -> Result<String> {
-        let row = sqlx::query(
+        let rows = sqlx::query(
             r#"INSERT INTO AgentIssue(agent_id, severity, title, details_md, action_required)
                VALUES (?1, ?2, ?3, ?4, ?5)
                RETURNING id"#,
@@ -430,8 +571,9 @@ impl MemoryStore {
         .bind(title)
         .bind(details_md)
         .bind(if action_required { 1 } else { 0 })
-        .fetch_one(&self.pool)
+        .fetch_all(&self.pool)
         .await?;
+        let row = inserted(rows)?;
         Ok(row.get::<String, _>("id"))
     }
 
@@ -573,4 +715,400 @@ impl MemoryStore {
             origin_url: r.get("origin_url"),
         }))
     }
+
+    pub async fn create_install_plan(&self, id: &str, manager: &str, pkg: &str, commands_json: &str, rollback_json: Option<&str>) -> Result<()> {
+        sqlx::query(r#"INSERT INTO InstallPlan(id, manager, pkg, commands_json, rollback_json) VALUES (?1, ?2, ?3, ?4, ?5)"#)
+            .bind(id)
+            .bind(manager)
+            .bind(pkg)
+            .bind(commands_json)
+            .bind(rollback_json)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_install_plan(&self, id: &str) -> Result<Option<InstallPlanRow>> {
+        let row = sqlx::query(
+            r#"SELECT id, manager, pkg, commands_json, rollback_json, status, dry_run_json, result_json, created_at, updated_at
+               FROM InstallPlan WHERE id = ?1"#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(|r| InstallPlanRow {
+            id: r.get("id"),
+            manager: r.get("manager"),
+            pkg: r.get("pkg"),
+            commands_json: r.get("commands_json"),
+            rollback_json: r.get("rollback_json"),
+            status: r.get("status"),
+            dry_run_json: r.get("dry_run_json"),
+            result_json: r.get("result_json"),
+            created_at: r.get("created_at"),
+            updated_at: r.get("updated_at"),
+        }))
+    }
+
+    /// Move a plan from `from` to `to` in one statement; false when it was not in `from`.
+    pub async fn transition_install_plan(&self, id: &str, from: &str, to: &str) -> Result<bool> {
+        let res = sqlx::query(r#"UPDATE InstallPlan SET status = ?1 WHERE id = ?2 AND status = ?3"#)
+            .bind(to)
+            .bind(id)
+            .bind(from)
+            .execute(&self.pool)
+            .await?;
+        Ok(res.rows_affected() > 0)
+    }
+
+    pub async fn set_install_plan_dry_run(&self, id: &str, dry_run_json: &str) -> Result<()> {
+        sqlx::query(r#"UPDATE InstallPlan SET dry_run_json = ?1 WHERE id = ?2"#)
+            .bind(dry_run_json)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn finish_install_plan(&self, id: &str, status: &str, result_json: &str) -> Result<()> {
+        sqlx::query(r#"UPDATE InstallPlan SET status = ?1, result_json = ?2 WHERE id = ?3"#)
+            .bind(status)
+            .bind(result_json)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Fail every plan still marked `running`; returns how many were reset.
+    pub async fn fail_running_install_plans(&self, result_json: &str) -> Result<u64> {
+        let res = sqlx::query(r#"UPDATE InstallPlan SET status = 'failed', result_json = ?1 WHERE status = 'running'"#)
+            .bind(result_json)
+            .execute(&self.pool)
+            .await?;
+        Ok(res.rows_affected())
+    }
+
+    pub async fn create_realtime_session(&self, id: &str, task_id: i64, model: Option<&str>, endpoint: Option<&str>, transport: Option<&str>) -> Result<()> {
+        sqlx::query(r#"INSERT INTO RealtimeSession(id, task_id, model, endpoint, transport) VALUES (?1, ?2, ?3, ?4, ?5)"#)
+            .bind(id)
+            .bind(task_id)
+            .bind(model)
+            .bind(endpoint)
+            .bind(transport)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn set_realtime_session_recording(&self, id: &str, recording: &str) -> Result<()> {
+        sqlx::query(r#"UPDATE RealtimeSession SET recording = ?1 WHERE id = ?2"#)
+            .bind(recording)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn finish_realtime_session(&self, id: &str, status: &str, error: Option<&str>) -> Result<()> {
+        sqlx::query(
+            r#"UPDATE RealtimeSession SET status = ?1, error = COALESCE(?2, error), ended_at = strftime('%Y-%m-%dT%H:%M:%fZ','now') WHERE id = ?3"#,
+        )
+        .bind(status)
+        .bind(error)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_realtime_session(&self, id: &str) -> Result<Option<RealtimeSessionRow>> {
+        let row = sqlx::query(
+            r#"SELECT id, task_id, model, endpoint, transport, status, error, recording, started_at, ended_at
+               FROM RealtimeSession WHERE id = ?1"#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(|r| realtime_session_row(&r)))
+    }
+
+    /// Most recent sessions first.
+    pub async fn list_realtime_sessions(&self, limit: i64) -> Result<Vec<RealtimeSessionRow>> {
+        let rows = sqlx::query(
+            r#"SELECT id, task_id, model, endpoint, transport, status, error, recording, started_at, ended_at
+               FROM RealtimeSession ORDER BY started_at DESC, rowid DESC LIMIT ?1"#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.iter().map(realtime_session_row).collect())
+    }
+
+    pub async fn create_chat_session(&self, id: &str, title: Option<&str>, forked_from: Option<&str>) -> Result<()> {
+        sqlx::query(r#"INSERT OR IGNORE INTO ChatSession(id, title, forked_from) VALUES (?1, ?2, ?3)"#)
+            .bind(id)
+            .bind(title)
+            .bind(forked_from)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_chat_session(&self, id: &str) -> Result<Option<ChatSessionRow>> {
+        let row = sqlx::query(
+            r#"SELECT id, title, head_id, forked_from, created_at, updated_at, summary, summary_upto FROM ChatSession WHERE id = ?1"#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(|r| chat_session_row(&r)))
+    }
+
+    /// Most recently updated first.
+    pub async fn list_chat_sessions(&self, limit: i64) -> Result<Vec<ChatSessionRow>> {
+        let rows = sqlx::query(
+            r#"SELECT id, title, head_id, forked_from, created_at, updated_at, summary, summary_upto
+               FROM ChatSession ORDER BY updated_at DESC, rowid DESC LIMIT ?1"#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.iter().map(chat_session_row).collect())
+    }
+
+    /// Returns false when there was no such session.
+    pub async fn delete_chat_session(&self, id: &str) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(r#"DELETE FROM ChatMessage WHERE session_id = ?1"#).bind(id).execute(&mut *tx).await?;
+        let res = sqlx::query(r#"DELETE FROM ChatSession WHERE id = ?1"#).bind(id).execute(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(res.rows_affected() > 0)
+    }
+
+    pub async fn set_chat_title(&self, id: &str, title: &str) -> Result<()> {
+        sqlx::query(r#"UPDATE ChatSession SET title = ?1 WHERE id = ?2"#)
+            .bind(title)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Point the session at another branch leaf (or at nothing).
+    pub async fn set_chat_head(&self, id: &str, head_id: Option<&str>) -> Result<()> {
+        sqlx::query(r#"UPDATE ChatSession SET head_id = ?1, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ','now') WHERE id = ?2"#)
+            .bind(head_id)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Cache the rolling summary; leaves `updated_at` alone so listing order
+    /// only follows new messages.
+    pub async fn set_chat_summary(&self, id: &str, summary: &str, upto: &str) -> Result<()> {
+        sqlx::query(r#"UPDATE ChatSession SET summary = ?1, summary_upto = ?2 WHERE id = ?3"#)
+            .bind(summary)
+            .bind(upto)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Insert a message and make it the session head. `at` (default now) also
+    /// becomes the session's `updated_at`, so imported history keeps its order.
+    pub async fn append_chat_message(&self, session_id: &str, id: &str, parent_id: Option<&str>, role: &str, content: &str, at: Option<DateTime<Utc>>) -> Result<ChatMessageRow> {
+        let at = at.map(|t| t.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string());
+        let mut tx = self.pool.begin().await?;
+        let rows = sqlx::query(
+            r#"INSERT INTO ChatMessage(id, session_id, parent_id, role, content, at)
+               VALUES (?1, ?2, ?3, ?4, ?5, COALESCE(?6, strftime('%Y-%m-%dT%H:%M:%fZ','now')))
+               RETURNING id, session_id, parent_id, role, content, at, tool_calls, tool_call_id, tool_name, approval_id, artifact_id"#,
+        )
+        .bind(id)
+        .bind(session_id)
+        .bind(parent_id)
+        .bind(role)
+        .bind(content)
+        .bind(&at)
+        .fetch_all(&mut *tx)
+        .await?;
+        let row = inserted(rows)?;
+        sqlx::query(r#"UPDATE ChatSession SET head_id = ?1, updated_at = COALESCE(?3, strftime('%Y-%m-%dT%H:%M:%fZ','now')) WHERE id = ?2"#)
+            .bind(id)
+            .bind(session_id)
+            .bind(&at)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(chat_message_row(&row))
+    }
+
+    /// Append under the session's current head. The session row is written
+    /// first, so the transaction holds the write lock before it reads the head
+    /// and concurrent appends chain instead of forking.
+    pub async fn append_chat_message_at_head(&self, session_id: &str, id: &str, role: &str, content: &str) -> Result<ChatMessageRow> {
+        let mut tx = self.pool.begin().await?;
+        let touched = sqlx::query(r#"UPDATE ChatSession SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ','now') WHERE id = ?1"#)
+            .bind(session_id)
+            .execute(&mut *tx)
+            .await?;
+        if touched.rows_affected() == 0 { anyhow::bail!("no such session: {}", session_id); }
+        let rows = sqlx::query(
+            r#"INSERT INTO ChatMessage(id, session_id, parent_id, role, content, at)
+               SELECT ?1, id, head_id, ?3, ?4, strftime('%Y-%m-%dT%H:%M:%fZ','now') FROM ChatSession WHERE id = ?2
+               RETURNING id, session_id, parent_id, role, content, at, tool_calls, tool_call_id, tool_name, approval_id, artifact_id"#,
+        )
+        .bind(id)
+        .bind(session_id)
+        .bind(role)
+        .bind(content)
+        .fetch_all(&mut *tx)
+        .await?;
+        let row = inserted(rows)?;
+        sqlx::query(r#"UPDATE ChatSession SET head_id = ?1 WHERE id = ?2"#)
+            .bind(id)
+            .bind(session_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(chat_message_row(&row))
+    }
+
+    pub async fn set_chat_message_content(&self, id: &str, content: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(r#"UPDATE ChatMessage SET content = ?1 WHERE id = ?2"#)
+            .bind(content)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(r#"UPDATE ChatSession SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ','now') WHERE id = (SELECT session_id FROM ChatMessage WHERE id = ?1)"#)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Record the tool side of a message: the calls an assistant turn made, or
+    /// what a tool turn answered.
+    pub async fn set_chat_message_tools(&self, id: &str, tool_calls: Option<&str>, tool_call_id: Option<&str>, tool_name: Option<&str>, approval_id: Option<&str>, artifact_id: Option<i64>) -> Result<()> {
+        sqlx::query(r#"UPDATE ChatMessage SET tool_calls = ?1, tool_call_id = ?2, tool_name = ?3, approval_id = ?4, artifact_id = ?5 WHERE id = ?6"#)
+            .bind(tool_calls)
+            .bind(tool_call_id)
+            .bind(tool_name)
+            .bind(approval_id)
+            .bind(artifact_id)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Append `delta` to the session head when it has `role`. Returns false
+    /// when the head is another role (or there is none).
+    pub async fn extend_chat_head(&self, session_id: &str, role: &str, delta: &str) -> Result<bool> {
+        let res = sqlx::query(
+            r#"UPDATE ChatMessage SET content = content || ?1, at = strftime('%Y-%m-%dT%H:%M:%fZ','now')
+               WHERE id = (SELECT head_id FROM ChatSession WHERE id = ?2) AND role = ?3"#,
+        )
+        .bind(delta)
+        .bind(session_id)
+        .bind(role)
+        .execute(&self.pool)
+        .await?;
+        if res.rows_affected() == 0 { return Ok(false); }
+        sqlx::query(r#"UPDATE ChatSession SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ','now') WHERE id = ?1"#)
+            .bind(session_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(true)
+    }
+
+    /// Every message of a session, all branches, in insertion order.
+    pub async fn get_chat_messages(&self, session_id: &str) -> Result<Vec<ChatMessageRow>> {
+        let rows = sqlx::query(
+            r#"SELECT id, session_id, parent_id, role, content, at, tool_calls, tool_call_id, tool_name, approval_id, artifact_id
+               FROM ChatMessage WHERE session_id = ?1 ORDER BY seq ASC"#,
+        )
+        .bind(session_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.iter().map(chat_message_row).collect())
+    }
+
+    /// Full-text search over message content; `q` is an FTS5 query.
+    pub async fn search_chat_messages(&self, q: &str, limit: i64) -> Result<Vec<ChatSearchHit>> {
+        let rows = sqlx::query(
+            r#"SELECT m.session_id, m.id as message_id, m.role, m.at,
+                      snippet(chat_fts, 0, '[', ']', ' … ', 10) as snippet,
+                      bm25(chat_fts) as score
+               FROM chat_fts
+               JOIN ChatMessage m ON m.seq = chat_fts.rowid
+               WHERE chat_fts MATCH ?1
+               ORDER BY score ASC, m.seq DESC
+               LIMIT ?2"#,
+        )
+        .bind(q)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|r| ChatSearchHit {
+                session_id: r.get("session_id"),
+                message_id: r.get("message_id"),
+                role: r.get("role"),
+                snippet: r.get("snippet"),
+                score: r.get::<f64, _>("score"),
+                at: r.get("at"),
+            })
+            .collect())
+    }
+}
+
+fn realtime_session_row(r: &sqlx::sqlite::SqliteRow) -> RealtimeSessionRow {
+    RealtimeSessionRow {
+        id: r.get("id"),
+        task_id: r.get("task_id"),
+        model: r.get("model"),
+        endpoint: r.get("endpoint"),
+        transport: r.get("transport"),
+        status: r.get("status"),
+        error: r.get("error"),
+        recording: r.get("recording"),
+        started_at: r.get("started_at"),
+        ended_at: r.get("ended_at"),
+    }
+}
+
+fn chat_session_row(r: &sqlx::sqlite::SqliteRow) -> ChatSessionRow {
+    ChatSessionRow {
+        id: r.get("id"),
+        title: r.get("title"),
+        head_id: r.get("head_id"),
+        forked_from: r.get("forked_from"),
+        created_at: r.get("created_at"),
+        updated_at: r.get("updated_at"),
+        summary: r.get("summary"),
+        summary_upto: r.get("summary_upto"),
+    }
+}
+
+fn chat_message_row(r: &sqlx::sqlite::SqliteRow) -> ChatMessageRow {
+    ChatMessageRow {
+        id: r.get("id"),
+        session_id: r.get("session_id"),
+        parent_id: r.get("parent_id"),
+        role: r.get("role"),
+        content: r.get("content"),
+        at: r.get("at"),
+        tool_calls: r.get("tool_calls"),
+        tool_call_id: r.get("tool_call_id"),
+        tool_name: r.get("tool_name"),
+        approval_id: r.get("approval_id"),
+        artifact_id: r.get("artifact_id"),
+    }
 }
@@ -0,0 +1,26 @@
+//! PCM helpers shared by core and the TUI.
+
+/// Wrap PCM16 mono samples in a WAV container (for upload-style STT and
+/// saved recordings).
+pub fn encode_wav_pcm16(pcm: &[i16], sr: u32) -> Vec<u8> {
+    let mut out = Vec::with_capacity(44 + pcm.len() * 2);
+    let byte_rate = sr * 2; // mono, 16-bit
+    let block_align = 2u16;
+    let subchunk2_size = (pcm.len() * 2) as u32;
+    let chunk_size = 36 + subchunk2_size;
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&chunk_size.to_le_bytes());
+    out.extend_from_slice(b"WAVE");
+    out.extend_from_slice(b"fmt ");
+    out.extend_from_slice(&16u32.to_le_bytes()); // PCM subchunk size
+    out.extend_from_slice(&1u16.to_le_bytes()); // PCM format
+    out.extend_from_slice(&1u16.to_le_bytes()); // channels
+    out.extend_from_slice(&sr.to_le_bytes());
+    out.extend_from_slice(&byte_rate.to_le_bytes());
+    out.extend_from_slice(&block_align.to_le_bytes());
+    out.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&subchunk2_size.to_le_bytes());
+    for s in pcm { out.extend_from_slice(&s.to_le_bytes()); }
+    out
+}
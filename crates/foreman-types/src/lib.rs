@@ -1,5 +1,7 @@
 //! Shared basic types (scaffold)
 
+pub mod audio;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct VersionInfo {
     pub name: &'static str,
@@ -79,6 +79,32 @@ def get_stt_model():
     return _stt_model
 
 
+# Streaming STT re-transcribes the utterance so far after this much new audio
+STT_PARTIAL_MS = int(os.environ.get("STT_PARTIAL_MS", "800"))
+WHISPER_SR = 16000
+
+
+def stt_available():
+    return HAVE_WHISPER and np is not None
+
+
+def transcribe_pcm16(data: bytes, sample_rate: int = WHISPER_SR, language=None) -> str:
+    """Transcribe PCM16 mono little-endian audio; blocking (run it in an executor)."""
+    model = get_stt_model()
+    if model is None:
+        raise RuntimeError("STT model failed to load")
+    arr = np.frombuffer(data, dtype=np.int16).astype(np.float32) / 32768.0
+    if sample_rate != WHISPER_SR and len(arr) > 0:
+        n = int(len(arr) * WHISPER_SR / sample_rate)
+        arr = np.interp(np.linspace(0, len(arr) - 1, n), np.arange(len(arr)), arr).astype(np.float32)
+    opts = {"language": language} if language else {}
+    # Use fp16 on CUDA only for speed; CPU/MPS prefer fp32
+    if _stt_device in (None, 'cpu', 'mps'):
+        opts["fp16"] = False
+    res = model.transcribe(arr, **opts)
+    return res.get("text", "").strip()
+
+
 async def run_server(host: str, port: int):
     engine = select_engine()
     # Setup wake thread (Porcupine) if enabled
@@ -148,25 +174,78 @@ async def run_server(host: str, port: int):
             if not data:
                 return web.json_response({"error": "empty body"}, status=400)
             try:
-                # Decode PCM16 mono little-endian to float32 -1..1
-                arr = np.frombuffer(data, dtype=np.int16).astype(np.float32) / 32768.0
-                # Transcribe
+                sr = int(request.headers.get("x-sample-rate") or WHISPER_SR)
                 lang = request.query.get("language") or os.environ.get("WHISPER_LANG")
-                opts = {"language": lang} if lang else {}
-                # Use fp16 on CUDA only for speed; CPU/MPS prefer fp32
-                if _stt_device in (None, 'cpu', 'mps'):
-                    opts["fp16"] = False
-                res = model.transcribe(arr, **opts)
-                text = res.get("text", "")
+                loop = asyncio.get_running_loop()
+                text = await loop.run_in_executor(None, transcribe_pcm16, data, sr, lang)
                 return web.json_response({"text": text})
             except Exception as e:
                 return web.json_response({"error": str(e)}, status=500)
 
+        async def stt_stream_handler(request: web.Request):
+            # Binary PCM16LE mono frames in, then {"type":"end"}; {"type":"partial"|"final"|"error"} out.
+            # Refuse before the upgrade so clients fall back to /v1/stt/transcribe.
+            if not stt_available():
+                return web.json_response({"error": "STT not available"}, status=503)
+            try:
+                sr = int(request.query.get("sample_rate") or WHISPER_SR)
+            except ValueError:
+                return web.json_response({"error": "bad sample_rate"}, status=400)
+            lang = request.query.get("language") or os.environ.get("WHISPER_LANG")
+            ws = web.WebSocketResponse()
+            await ws.prepare(request)
+            loop = asyncio.get_running_loop()
+            pcm = bytearray()
+            step = max(1, sr * 2 * STT_PARTIAL_MS // 1000)
+            state = {"at": 0, "text": "", "task": None}
+
+            async def partial(upto: int):
+                try:
+                    text = await loop.run_in_executor(None, transcribe_pcm16, bytes(pcm[:upto]), sr, lang)
+                    if text and text != state["text"] and not ws.closed:
+                        state["text"] = text
+                        await ws.send_json({"type": "partial", "text": text})
+                except Exception as e:
+                    print(f"voice-daemon: partial transcript failed: {e}")
+
+            try:
+                async for msg in ws:
+                    if msg.type == aiohttp.WSMsgType.BINARY:
+                        pcm.extend(msg.data)
+                        # One partial at a time; audio keeps buffering meanwhile
+                        busy = state["task"] is not None and not state["task"].done()
+                        if len(pcm) - state["at"] >= step and not busy:
+                            state["at"] = len(pcm)
+                            state["task"] = asyncio.create_task(partial(len(pcm)))
+                    elif msg.type == aiohttp.WSMsgType.TEXT:
+                        try:
+                            kind = json.loads(msg.data).get("type")
+                        except Exception:
+                            kind = None
+                        if kind != "end":
+                            continue
+                        if state["task"] is not None:
+                            await state["task"]
+                        text = await loop.run_in_executor(None, transcribe_pcm16, bytes(pcm), sr, lang)
+                        await ws.send_json({"type": "final", "text": text})
+                        break
+                    elif msg.type == aiohttp.WSMsgType.ERROR:
+                        break
+            except Exception as e:
+                if not ws.closed:
+                    await ws.send_json({"type": "error", "message": str(e)})
+            finally:
+                if state["task"] is not None and not state["task"].done():
+                    state["task"].cancel()
+                await ws.close()
+            return ws
+
         app.router.add_get("/v1/tts/health", health_handler)
         app.router.add_get("/v1/tts/stream", ws_handler)
         app.router.add_get("/v1/stt/health", stt_health_handler)
         app.router.add_get("/v1/wake/health", wake_health_handler)
         app.router.add_post("/v1/stt/transcribe", stt_transcribe_handler)
+        app.router.add_get("/v1/stt/stream", stt_stream_handler)
         # Optionally preload model in background
         if HAVE_WHISPER and os.environ.get("WHISPER_PRELOAD", "1") not in ("0","false","False"):
             async def _preload():
@@ -260,13 +339,8 @@ async def run_server(host: str, port: int):
                     try:
                         length = int(self.headers.get('Content-Length') or '0')
                         data = self.rfile.read(length)
-                        arr = np.frombuffer(data, dtype=np.int16).astype(np.float32) / 32768.0
-                        lang = None
-                        opts = {"language": lang} if lang else {}
-                        if _stt_device in (None, 'cpu', 'mps'):
-                            opts["fp16"] = False
-                        res = model.transcribe(arr, **opts)
-                        text = res.get("text", "")
+                        sr = int(self.headers.get("x-sample-rate") or WHISPER_SR)
+                        text = transcribe_pcm16(data, sr, os.environ.get("WHISPER_LANG"))
                         body = json.dumps({"text": text}).encode("utf-8")
                         self.send_response(200)
                         self.send_header("Content-Type", "application/json")
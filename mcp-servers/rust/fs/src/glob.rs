@@ -0,0 +1,91 @@
+//! Shell-style globs, compiled to regexes over `/`-separated relative paths.
+//!
+//! `*` and `?` stay within one path component, `**` spans any number of
+//! them, `[abc]`/`[!a-z]` are character classes and `{a,b}` alternatives.
+
+use anyhow::{bail, Result};
+use regex::Regex;
+
+#[derive(Debug, Clone)]
+pub struct Glob {
+    re: Regex,
+    /// No `/` in the pattern: match the file name alone, like ripgrep's `-g`.
+    name_only: bool,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Result<Self> {
+        let pattern = pattern.trim_start_matches("./");
+        Ok(Self { re: Regex::new(&to_regex(pattern)?)?, name_only: !pattern.contains('/') })
+    }
+
+    /// `rel` is relative to the search base, with `/` separators.
+    pub fn matches(&self, rel: &str) -> bool {
+        let subject = if self.name_only { rel.rsplit('/').next().unwrap_or(rel) } else { rel };
+        self.re.is_match(subject)
+    }
+
+    /// Whole-path matching even for patterns without `/` (for `glob`).
+    pub fn matches_path(&self, rel: &str) -> bool { self.re.is_match(rel) }
+}
+
+fn to_regex(pattern: &str) -> Result<String> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut out = String::from("^");
+    let mut braces = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                let at_start = i == 0 || chars[i - 1] == '/';
+                if at_start && chars.get(i + 2) == Some(&'/') {
+                    out.push_str("(?:[^/]*/)*");
+                    i += 3;
+                    continue;
+                }
+                out.push_str(".*");
+                i += 2;
+                continue;
+            }
+            '*' => out.push_str("[^/]*"),
+            '?' => out.push_str("[^/]"),
+            '[' => {
+                let Some(end) = chars[i + 1..].iter().position(|c| *c == ']').map(|p| p + i + 1) else { bail!("unclosed [ in glob {}", pattern) };
+                let mut class: String = chars[i + 1..end].iter().collect();
+                if let Some(rest) = class.strip_prefix('!') { class = format!("^{}", rest); }
+                out.push('[');
+                out.push_str(&class.replace('\\', "\\\\").replace('[', "\\["));
+                out.push(']');
+                i = end;
+            }
+            '{' => { braces += 1; out.push_str("(?:"); }
+            '}' if braces > 0 => { braces -= 1; out.push(')'); }
+            ',' if braces > 0 => out.push('|'),
+            '\\' if i + 1 < chars.len() => { i += 1; out.push_str(&regex::escape(&chars[i].to_string())); }
+            _ => out.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    if braces > 0 { bail!("unclosed {{ in glob {}", pattern); }
+    out.push('$');
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs_match_like_a_shell() {
+        let g = Glob::new("src/**/*.rs").unwrap();
+        assert!(g.matches("src/lib.rs") && g.matches("src/a/b/mod.rs"));
+        assert!(!g.matches("tests/x.rs") && !g.matches("src/lib.rsx"));
+        let g = Glob::new("*.{md,txt}").unwrap();
+        assert!(g.matches("docs/README.md") && g.matches("notes.txt") && !g.matches("a.rs"));
+        assert!(!g.matches_path("docs/README.md"));
+        let g = Glob::new("data/file[0-9]?.[!b]in").unwrap();
+        assert!(g.matches("data/file1a.cin") && !g.matches("data/file1a.bin") && !g.matches("data/x/file1a.cin"));
+        assert!(Glob::new("a{b").is_err() && Glob::new("a[b").is_err());
+    }
+}
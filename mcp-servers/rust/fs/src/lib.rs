@@ -1,27 +1,488 @@
-use anyhow::Result;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value as JsonValue};
-use tokio::fs;
-
-pub async fn stat(path: &str) -> Result<JsonValue> {
-    let meta = fs::metadata(path).await?;
-    let is_dir = meta.is_dir();
-    let is_file = meta.is_file();
-    let size = if is_file { Some(meta.len()) } else { None };
-    Ok(json!({ "is_dir": is_dir, "is_file": is_file, "size": size }))
+use std::io::{BufRead, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+pub mod glob;
+pub mod sandbox;
+pub mod walk;
+
+use foreman_mcp::{Describe, ProposedAction, ToolRisk, ToolSpec};
+use glob::Glob;
+use sandbox::Sandbox;
+
+/// Default cap on the bytes one `read` returns.
+pub const MAX_READ_BYTES: u64 = 1024 * 1024;
+/// `search` skips files larger than this.
+const MAX_SEARCH_BYTES: u64 = 4 * 1024 * 1024;
+/// Bytes inspected for a NUL to call a file binary.
+const SNIFF_BYTES: usize = 8192;
+/// Binary byte ranges are returned as hex, at most this many bytes.
+const MAX_HEX_BYTES: u64 = 64 * 1024;
+/// Search lines longer than this are cut in the results.
+const MAX_LINE_CHARS: usize = 400;
+
+/// `[fs]` in foreman.toml.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct FsConfig {
+    /// Folders the tools may touch (`~/` expanded; default: the working directory).
+    #[serde(default)]
+    pub roots: Vec<String>,
+    /// Cap on what one `read` returns (default 1 MiB).
+    #[serde(default)]
+    pub max_read_bytes: Option<u64>,
 }
 
-pub async fn list(path: &str) -> Result<JsonValue> {
-    let mut entries: Vec<String> = vec![];
-    let mut dir = fs::read_dir(path).await?;
-    while let Ok(Some(ent)) = dir.next_entry().await {
-        if let Ok(name) = ent.file_name().into_string() { entries.push(name); }
+impl FsConfig {
+    /// `[fs]` of `FOREMAN_CONFIG` (default `config/foreman.toml`); defaults
+    /// when the file or table is missing or malformed.
+    pub fn load() -> Self {
+        let path = std::env::var("FOREMAN_CONFIG").unwrap_or_else(|_| "config/foreman.toml".into());
+        let Ok(text) = std::fs::read_to_string(path) else { return Self::default() };
+        toml::from_str::<toml::Value>(&text).ok()
+            .and_then(|v| v.get("fs").cloned())
+            .and_then(|g| g.try_into().ok())
+            .unwrap_or_default()
     }
-    entries.sort();
-    Ok(json!({ "entries": entries }))
 }
 
-pub async fn read(path: &str) -> Result<JsonValue> {
-    let content = fs::read_to_string(path).await?;
-    Ok(json!({ "content": content }))
+fn str_param<'a>(params: &'a JsonValue, key: &str) -> Option<&'a str> { params.get(key).and_then(|v| v.as_str()) }
+fn bool_param(params: &JsonValue, key: &str, default: bool) -> bool { params.get(key).and_then(|v| v.as_bool()).unwrap_or(default) }
+fn u64_param(params: &JsonValue, key: &str) -> Option<u64> { params.get(key).and_then(|v| v.as_u64()) }
+fn shown(p: &Path) -> String { p.to_string_lossy().to_string() }
+
+fn is_binary(head: &[u8]) -> bool { head.contains(&0) }
+
+fn hex(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+    bytes.iter().fold(String::with_capacity(bytes.len() * 2), |mut out, b| { let _ = write!(out, "{:02x}", b); out })
 }
 
+pub struct Fs {
+    sandbox: Sandbox,
+    max_read_bytes: u64,
+}
+
+impl Default for Fs {
+    fn default() -> Self { Self::new(FsConfig::default()) }
+}
+
+impl Fs {
+    pub fn new(cfg: FsConfig) -> Self {
+        let roots = if cfg.roots.is_empty() { vec![".".to_string()] } else { cfg.roots };
+        Self { sandbox: Sandbox::new(&roots), max_read_bytes: cfg.max_read_bytes.unwrap_or(MAX_READ_BYTES) }
+    }
+
+    pub fn describe() -> Describe {
+        let tool = |name: &str, description: &str, risk: ToolRisk, input_schema: JsonValue| ToolSpec {
+            name: name.into(),
+            description: Some(description.into()),
+            input_schema: Some(input_schema),
+            output_schema: None,
+            risk,
+        };
+        let reads = ToolRisk { paths_param: Some("path".into()), ..Default::default() };
+        let writes = ToolRisk { writes: true, paths_param: Some("path".into()), ..Default::default() };
+        let path = json!({"type": "string", "minLength": 1, "description": "File or folder (`~/` expanded, relative to the working directory)."});
+        let base = json!({"type": "string", "description": "Folder to start from (default: the working directory)."});
+        let hidden = json!({"type": "boolean", "description": "Include dot files and folders."});
+        let dry_run = json!({"type": "boolean", "description": "Check the call and return its action without changing anything."});
+        let content = json!({"type": "string"});
+        let create_dirs = json!({"type": "boolean", "description": "Create missing parent folders."});
+        Describe { tools: vec![
+            tool("stat", "File metadata: kind, size, modification time and permissions.", reads.clone(), json!({
+                "type": "object",
+                "properties": {"path": path},
+                "required": ["path"],
+                "additionalProperties": false
+            })),
+            tool("list", "List entries in a directory.", reads.clone(), json!({
+                "type": "object",
+                "properties": {"path": {"type": "string", "description": "Directory (defaults to .)"}},
+                "additionalProperties": false
+            })),
+            tool("read", "Read a file, whole or by byte range (offset/length) or line range (start_line/end_line, 1-based, inclusive). Binary files are reported as such; a byte range of one comes back as hex. Large reads are cut at the size cap with next_offset set.", reads.clone(), json!({
+                "type": "object",
+                "properties": {
+                    "path": path,
+                    "offset": {"type": "integer", "minimum": 0},
+                    "length": {"type": "integer", "minimum": 0},
+                    "start_line": {"type": "integer", "minimum": 1},
+                    "end_line": {"type": "integer", "minimum": 1}
+                },
+                "required": ["path"],
+                "additionalProperties": false
+            })),
+            tool("glob", "Find paths under a folder matching a glob (*, ?, **, [a-z], {a,b}).", reads.clone(), json!({
+                "type": "object",
+                "properties": {
+                    "pattern": {"type": "string", "minLength": 1, "description": "Relative to path, e.g. src/**/*.rs."},
+                    "path": base,
+                    "include_hidden": hidden,
+                    "limit": {"type": "integer", "minimum": 1, "description": "Max paths (default 500)."}
+                },
+                "required": ["pattern"],
+                "additionalProperties": false
+            })),
+            tool("search", "Search file contents for a regex (or fixed string), ripgrep style: line, column and text of each match, optionally with context lines. Binary and oversized files are skipped.", reads.clone(), json!({
+                "type": "object",
+                "properties": {
+                    "pattern": {"type": "string", "minLength": 1},
+                    "path": {"type": "string", "description": "Folder or file to search (default: the working directory)."},
+                    "glob": {"type": "string", "description": "Only files matching this glob (a pattern without / matches file names)."},
+                    "fixed_strings": {"type": "boolean"},
+                    "case_insensitive": {"type": "boolean"},
+                    "context": {"type": "integer", "minimum": 0, "maximum": 10, "description": "Lines of context around each match."},
+                    "include_hidden": hidden,
+                    "max_results": {"type": "integer", "minimum": 1, "description": "Default 200."}
+                },
+                "required": ["pattern"],
+                "additionalProperties": false
+            })),
+            tool("tree", "A folder as a nested tree down to a depth.", reads, json!({
+                "type": "object",
+                "properties": {
+                    "path": base,
+                    "depth": {"type": "integer", "minimum": 1, "maximum": 10, "description": "Default 3."},
+                    "include_hidden": hidden,
+                    "limit": {"type": "integer", "minimum": 1, "description": "Max entries (default 1000)."}
+                },
+                "additionalProperties": false
+            })),
+            tool("write", "Write a text file, replacing it unless overwrite is false.", writes.clone(), json!({
+                "type": "object",
+                "properties": {"path": path, "content": content, "create_dirs": create_dirs, "overwrite": {"type": "boolean"}, "dry_run": dry_run},
+                "required": ["path", "content"],
+                "additionalProperties": false
+            })),
+            tool("append", "Append text to a file, creating it if missing.", writes.clone(), json!({
+                "type": "object",
+                "properties": {"path": path, "content": content, "create_dirs": create_dirs, "dry_run": dry_run},
+                "required": ["path", "content"],
+                "additionalProperties": false
+            })),
+            tool("move", "Move or rename a file or folder.", ToolRisk { writes: true, ..Default::default() }, json!({
+                "type": "object",
+                "properties": {"from": path, "to": path, "overwrite": {"type": "boolean"}, "create_dirs": create_dirs, "dry_run": dry_run},
+                "required": ["from", "to"],
+                "additionalProperties": false
+            })),
+            tool("delete", "Delete a file, symlink or empty folder; recursive deletes a folder with its contents.", writes, json!({
+                "type": "object",
+                "properties": {"path": path, "recursive": {"type": "boolean"}, "dry_run": dry_run},
+                "required": ["path"],
+                "additionalProperties": false
+            })),
+        ]}
+    }
+
+    pub fn call(&self, tool: &str, params: &JsonValue) -> Result<JsonValue> {
+        match tool {
+            "stat" => self.stat(params),
+            "list" => self.list(params),
+            "read" => self.read(params),
+            "glob" => self.glob(params),
+            "search" => self.search(params),
+            "tree" => self.tree(params),
+            "write" => self.write(params),
+            // Older name of `write`, which created parent folders by default
+            "write_text" => {
+                let mut params = params.clone();
+                if params.get("create_dirs").is_none() { params["create_dirs"] = json!(true); }
+                self.write(&params)
+            }
+            "append" => self.append(params),
+            "move" => self.rename(params),
+            "delete" => self.delete(params),
+            foreman_mcp::DESCRIBE_TOOL => Ok(serde_json::to_value(Self::describe())?),
+            _ => Err(anyhow!("unknown tool")),
+        }
+    }
+
+    /// The action a mutating call would take, for the gatekeeper; None for
+    /// read-only tools. Paths that do not resolve are shown as given.
+    pub fn proposed_action(&self, tool: &str, params: &JsonValue) -> Option<ProposedAction> {
+        let resolved = |key: &str| {
+            let raw = str_param(params, key).unwrap_or_default();
+            self.sandbox.target(raw).map(|p| shown(&p)).unwrap_or_else(|_| raw.to_string())
+        };
+        let (command, paths) = match tool {
+            "write" | "write_text" | "append" => {
+                let path = resolved("path");
+                (format!("fs.{} {}", if tool == "append" { "append" } else { "write" }, path), vec![path])
+            }
+            "move" => {
+                let (from, to) = (resolved("from"), resolved("to"));
+                (format!("fs.move {} {}", from, to), vec![from, to])
+            }
+            // Spelled like the shell command so `rm -rf` approval rules apply
+            "delete" if bool_param(params, "recursive", false) => { let path = resolved("path"); (format!("rm -rf {}", path), vec![path]) }
+            "delete" => { let path = resolved("path"); (format!("fs.delete {}", path), vec![path]) }
+            _ => return None,
+        };
+        Some(ProposedAction { command, writes: true, paths, intent: None })
+    }
+
+    fn action(&self, tool: &str, params: &JsonValue) -> JsonValue {
+        serde_json::to_value(self.proposed_action(tool, params)).unwrap_or_default()
+    }
+
+    fn base(&self, params: &JsonValue) -> Result<PathBuf> {
+        self.sandbox.resolve(str_param(params, "path").unwrap_or("."))
+    }
+
+    pub fn stat(&self, params: &JsonValue) -> Result<JsonValue> {
+        let raw = str_param(params, "path").unwrap_or(".");
+        let real = self.sandbox.resolve(raw)?;
+        let is_symlink = self.sandbox.target(raw).ok().and_then(|p| p.symlink_metadata().ok()).map(|m| m.file_type().is_symlink()).unwrap_or(false);
+        let meta = std::fs::metadata(&real)?;
+        let modified = meta.modified().ok().and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok()).map(|d| d.as_secs());
+        use std::os::unix::fs::PermissionsExt;
+        Ok(json!({
+            "path": shown(&real),
+            "is_dir": meta.is_dir(),
+            "is_file": meta.is_file(),
+            "is_symlink": is_symlink,
+            "size": if meta.is_file() { Some(meta.len()) } else { None },
+            "modified": modified,
+            "mode": format!("{:o}", meta.permissions().mode() & 0o7777),
+            "readonly": meta.permissions().readonly(),
+        }))
+    }
+
+    pub fn list(&self, params: &JsonValue) -> Result<JsonValue> {
+        let dir = self.base(params)?;
+        let mut entries: Vec<String> = std::fs::read_dir(&dir)?.flatten().filter_map(|e| e.file_name().into_string().ok()).collect();
+        entries.sort();
+        Ok(json!({ "entries": entries }))
+    }
+
+    pub fn read(&self, params: &JsonValue) -> Result<JsonValue> {
+        let path = self.sandbox.resolve(str_param(params, "path").unwrap_or_default())?;
+        if path.is_dir() { bail!("{} is a folder", path.display()); }
+        let mut file = std::fs::File::open(&path)?;
+        let size = file.metadata()?.len();
+        let mut head = vec![0u8; SNIFF_BYTES.min(size as usize)];
+        file.read_exact(&mut head)?;
+        let binary = is_binary(&head);
+        file.rewind()?;
+
+        let start_line = u64_param(params, "start_line");
+        let end_line = u64_param(params, "end_line");
+        if start_line.is_some() || end_line.is_some() {
+            if binary { bail!("{} is binary; read it by byte range", path.display()); }
+            let start = start_line.unwrap_or(1).max(1);
+            let end = end_line.unwrap_or(u64::MAX);
+            if end < start { bail!("end_line is before start_line"); }
+            let mut content = String::new();
+            let mut last = None;
+            let mut truncated = false;
+            let mut lines = std::io::BufReader::new(file);
+            let mut line = String::new();
+            let mut n = 0u64;
+            loop {
+                line.clear();
+                if lines.read_line(&mut line)? == 0 { break; }
+                n += 1;
+                if n < start { continue; }
+                if n > end { break; }
+                if (content.len() + line.len()) as u64 > self.max_read_bytes { truncated = true; break; }
+                content.push_str(&line);
+                last = Some(n);
+            }
+            return Ok(json!({ "path": shown(&path), "size": size, "binary": false, "start_line": start, "end_line": last, "content": content, "truncated": truncated }));
+        }
+
+        let ranged = params.get("offset").is_some() || params.get("length").is_some();
+        if binary && !ranged {
+            return Ok(json!({ "path": shown(&path), "size": size, "binary": true, "content": null }));
+        }
+        let offset = u64_param(params, "offset").unwrap_or(0).min(size);
+        let cap = if binary { MAX_HEX_BYTES.min(self.max_read_bytes) } else { self.max_read_bytes };
+        let wanted = u64_param(params, "length").unwrap_or(size - offset).min(size - offset);
+        let length = wanted.min(cap);
+        let mut buf = vec![0u8; length as usize];
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut buf)?;
+        let truncated = length < wanted;
+        let mut out = json!({ "path": shown(&path), "size": size, "binary": binary, "offset": offset, "length": length, "truncated": truncated });
+        if binary { out["hex"] = json!(hex(&buf)); } else { out["content"] = json!(String::from_utf8_lossy(&buf)); }
+        if truncated { out["next_offset"] = json!(offset + length); }
+        Ok(out)
+    }
+
+    pub fn glob(&self, params: &JsonValue) -> Result<JsonValue> {
+        let pattern = str_param(params, "pattern").ok_or_else(|| anyhow!("pattern required"))?;
+        let glob = Glob::new(pattern)?;
+        let base = self.base(params)?;
+        let limit = u64_param(params, "limit").unwrap_or(500) as usize;
+        let opts = walk::Options { max_depth: if pattern.contains("**") { usize::MAX } else { pattern.matches('/').count() + 1 }, include_hidden: bool_param(params, "include_hidden", false) };
+        let mut matches: Vec<String> = vec![];
+        let mut truncated = walk::walk(&base, opts, |e| {
+            if glob.matches_path(&e.rel) { matches.push(e.rel.clone()); }
+            matches.len() <= limit
+        });
+        if matches.len() > limit { matches.truncate(limit); truncated = true; }
+        Ok(json!({ "base": shown(&base), "matches": matches, "truncated": truncated }))
+    }
+
+    pub fn search(&self, params: &JsonValue) -> Result<JsonValue> {
+        let pattern = str_param(params, "pattern").ok_or_else(|| anyhow!("pattern required"))?;
+        let pattern = if bool_param(params, "fixed_strings", false) { regex::escape(pattern) } else { pattern.to_string() };
+        let re = regex::RegexBuilder::new(&pattern)
+            .case_insensitive(bool_param(params, "case_insensitive", false))
+            .build()
+            .context("bad pattern")?;
+        let only = str_param(params, "glob").map(Glob::new).transpose()?;
+        let context = u64_param(params, "context").unwrap_or(0).min(10) as usize;
+        let max_results = u64_param(params, "max_results").unwrap_or(200) as usize;
+        let base = self.base(params)?;
+
+        let mut files: Vec<(PathBuf, String)> = vec![];
+        let mut truncated = false;
+        if base.is_file() {
+            files.push((base.clone(), base.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()));
+        } else {
+            let opts = walk::Options { max_depth: usize::MAX, include_hidden: bool_param(params, "include_hidden", false) };
+            truncated = walk::walk(&base, opts, |e| {
+                if !e.is_dir && !e.is_symlink && only.as_ref().map(|g| g.matches(&e.rel)).unwrap_or(true) {
+                    files.push((e.path.clone(), e.rel.clone()));
+                }
+                true
+            });
+        }
+
+        let cut = |s: &str| -> String {
+            let s = s.trim_end_matches(['\n', '\r']);
+            if s.chars().count() > MAX_LINE_CHARS { format!("{}…", s.chars().take(MAX_LINE_CHARS).collect::<String>()) } else { s.to_string() }
+        };
+        let mut matches: Vec<JsonValue> = vec![];
+        let mut searched = 0usize;
+        'files: for (path, rel) in files {
+            let Ok(meta) = path.metadata() else { continue };
+            if meta.len() > MAX_SEARCH_BYTES { continue; }
+            let Ok(bytes) = std::fs::read(&path) else { continue };
+            if is_binary(&bytes[..bytes.len().min(SNIFF_BYTES)]) { continue; }
+            searched += 1;
+            let text = String::from_utf8_lossy(&bytes);
+            let lines: Vec<&str> = text.lines().collect();
+            for (i, line) in lines.iter().enumerate() {
+                let Some(m) = re.find(line) else { continue };
+                if matches.len() >= max_results { truncated = true; break 'files; }
+                let mut hit = json!({ "path": rel, "line": i + 1, "column": m.start() + 1, "text": cut(line) });
+                if context > 0 {
+                    hit["before"] = json!(lines[i.saturating_sub(context)..i].iter().map(|l| cut(l)).collect::<Vec<_>>());
+                    hit["after"] = json!(lines[i + 1..(i + 1 + context).min(lines.len())].iter().map(|l| cut(l)).collect::<Vec<_>>());
+                }
+                matches.push(hit);
+            }
+        }
+        Ok(json!({ "base": shown(&base), "matches": matches, "files_searched": searched, "truncated": truncated }))
+    }
+
+    pub fn tree(&self, params: &JsonValue) -> Result<JsonValue> {
+        let base = self.base(params)?;
+        if !base.is_dir() { bail!("{} is not a folder", base.display()); }
+        let depth = u64_param(params, "depth").unwrap_or(3).clamp(1, 10) as usize;
+        let limit = u64_param(params, "limit").unwrap_or(1000) as usize;
+        let opts = walk::Options { max_depth: depth, include_hidden: bool_param(params, "include_hidden", false) };
+        let mut entries: Vec<walk::Entry> = vec![];
+        let truncated = walk::walk(&base, opts, |e| {
+            entries.push(e.clone());
+            entries.len() < limit
+        });
+
+        // Folders not entered because of the depth limit are marked truncated
+        let node = |e: &walk::Entry| -> JsonValue {
+            let name = e.rel.rsplit('/').next().unwrap_or(&e.rel);
+            match (e.is_dir, e.is_symlink) {
+                (true, _) if e.depth == depth => json!({ "name": name, "kind": "dir", "truncated": true }),
+                (true, _) => json!({ "name": name, "kind": "dir", "children": [] }),
+                (_, true) => json!({ "name": name, "kind": "symlink" }),
+                _ => json!({ "name": name, "kind": "file", "size": e.path.metadata().map(|m| m.len()).unwrap_or(0) }),
+            }
+        };
+        // The walk yields each folder before its contents
+        let mut root = json!({ "name": base.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| shown(&base)), "kind": "dir", "children": [] });
+        for e in entries.iter() {
+            let mut at = &mut root;
+            for part in e.rel.split('/').take(e.depth - 1) {
+                let children = at["children"].as_array_mut().context("tree parent is not a folder")?;
+                let ix = children.iter().position(|c| c["name"] == part).context("tree parent missing")?;
+                at = &mut children[ix];
+            }
+            at["children"].as_array_mut().context("tree parent is not a folder")?.push(node(e));
+        }
+        Ok(json!({ "base": shown(&base), "tree": root, "entries": entries.len(), "truncated": truncated }))
+    }
+
+    fn prepare_parent(path: &Path, create_dirs: bool) -> Result<()> {
+        let Some(parent) = path.parent() else { return Ok(()) };
+        if parent.is_dir() { return Ok(()); }
+        if !create_dirs { bail!("{}: folder does not exist (pass create_dirs)", parent.display()); }
+        std::fs::create_dir_all(parent)?;
+        Ok(())
+    }
+
+    pub fn write(&self, params: &JsonValue) -> Result<JsonValue> {
+        let path = self.sandbox.write_target(str_param(params, "path").unwrap_or_default())?;
+        let content = str_param(params, "content").ok_or_else(|| anyhow!("content required"))?;
+        let existed = path.exists();
+        if existed && path.is_dir() { bail!("{} is a folder", path.display()); }
+        if existed && !bool_param(params, "overwrite", true) { bail!("{} exists (pass overwrite)", path.display()); }
+        let action = self.action("write", params);
+        if bool_param(params, "dry_run", false) { return Ok(json!({ "dry_run": true, "action": action })); }
+        Self::prepare_parent(&path, bool_param(params, "create_dirs", false))?;
+        std::fs::write(&path, content.as_bytes())?;
+        Ok(json!({ "path": shown(&path), "bytes": content.len(), "created": !existed, "action": action }))
+    }
+
+    pub fn append(&self, params: &JsonValue) -> Result<JsonValue> {
+        let path = self.sandbox.write_target(str_param(params, "path").unwrap_or_default())?;
+        let content = str_param(params, "content").ok_or_else(|| anyhow!("content required"))?;
+        if path.is_dir() { bail!("{} is a folder", path.display()); }
+        let action = self.action("append", params);
+        if bool_param(params, "dry_run", false) { return Ok(json!({ "dry_run": true, "action": action })); }
+        Self::prepare_parent(&path, bool_param(params, "create_dirs", false))?;
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&path)?;
+        file.write_all(content.as_bytes())?;
+        Ok(json!({ "path": shown(&path), "bytes": content.len(), "size": file.metadata()?.len(), "action": action }))
+    }
+
+    pub fn rename(&self, params: &JsonValue) -> Result<JsonValue> {
+        let from = self.sandbox.target(str_param(params, "from").unwrap_or_default())?;
+        let to = self.sandbox.target(str_param(params, "to").unwrap_or_default())?;
+        if from.symlink_metadata().is_err() { bail!("{}: not found", from.display()); }
+        if to.symlink_metadata().is_ok() && !bool_param(params, "overwrite", false) { bail!("{} exists (pass overwrite)", to.display()); }
+        if to.starts_with(&from) { bail!("cannot move {} into itself", from.display()); }
+        let action = self.action("move", params);
+        if bool_param(params, "dry_run", false) { return Ok(json!({ "dry_run": true, "action": action })); }
+        Self::prepare_parent(&to, bool_param(params, "create_dirs", false))?;
+        if let Err(e) = std::fs::rename(&from, &to) {
+            // Across file systems: copy and remove, for plain files only
+            if !from.is_file() { return Err(e.into()); }
+            std::fs::copy(&from, &to)?;
+            std::fs::remove_file(&from)?;
+        }
+        Ok(json!({ "from": shown(&from), "to": shown(&to), "action": action }))
+    }
+
+    pub fn delete(&self, params: &JsonValue) -> Result<JsonValue> {
+        let path = self.sandbox.target(str_param(params, "path").unwrap_or_default())?;
+        let meta = path.symlink_metadata().with_context(|| format!("{}: not found", path.display()))?;
+        let kind = if meta.file_type().is_symlink() { "symlink" } else if meta.is_dir() { "dir" } else { "file" };
+        let recursive = bool_param(params, "recursive", false);
+        if kind == "dir" && !recursive && std::fs::read_dir(&path)?.next().is_some() {
+            bail!("{} is not empty (pass recursive)", path.display());
+        }
+        let action = self.action("delete", params);
+        if bool_param(params, "dry_run", false) { return Ok(json!({ "dry_run": true, "action": action })); }
+        match kind {
+            "dir" if recursive => std::fs::remove_dir_all(&path)?,
+            "dir" => std::fs::remove_dir(&path)?,
+            _ => std::fs::remove_file(&path)?,
+        }
+        Ok(json!({ "path": shown(&path), "kind": kind, "action": action }))
+    }
+}
@@ -1,41 +1,24 @@
 use foreman_mcp::{ToolRequest, ToolResponse};
-use mcp_fs as fs_svr;
+use mcp_fs::{Fs, FsConfig};
 use std::io::{self, BufRead, Write};
 
 #[tokio::main]
 async fn main() {
+    let fs = Fs::new(FsConfig::load());
     let stdin = io::stdin();
     let mut stdout = io::stdout();
     let mut lines = stdin.lock().lines();
-    if let Some(Ok(line)) = lines.next() {
+    while let Some(Ok(line)) = lines.next() {
+        if line.trim().is_empty() { continue; }
         let req: Result<ToolRequest, _> = serde_json::from_str(&line);
         let resp = match req {
-            Ok(r) => handle(r).await,
+            Ok(r) => match fs.call(&r.tool, &r.params) {
+                Ok(v) => ToolResponse::ok(v),
+                Err(e) => ToolResponse::err(e.to_string()),
+            },
             Err(e) => ToolResponse::err(format!("bad request: {}", e)),
         };
         let _ = writeln!(stdout, "{}", serde_json::to_string(&resp).unwrap());
+        let _ = stdout.flush();
     }
 }
-
-async fn handle(req: ToolRequest) -> ToolResponse {
-    let res = match req.tool.as_str() {
-        "stat" => {
-            let path = req.params.get("path").and_then(|v| v.as_str()).unwrap_or(".");
-            fs_svr::stat(path).await
-        }
-        "list" => {
-            let path = req.params.get("path").and_then(|v| v.as_str()).unwrap_or(".");
-            fs_svr::list(path).await
-        }
-        "read" => {
-            let path = req.params.get("path").and_then(|v| v.as_str()).unwrap_or("");
-            fs_svr::read(path).await
-        }
-        _ => Err(anyhow::anyhow!("unknown tool")),
-    };
-    match res {
-        Ok(v) => ToolResponse::ok(v),
-        Err(e) => ToolResponse::err(e.to_string()),
-    }
-}
-
@@ -0,0 +1,84 @@
+//! Sandbox roots: every path a tool touches must resolve, symlinks followed,
+//! to somewhere under one of them.
+
+use anyhow::{bail, Context, Result};
+use foreman_mcp::expand_home;
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    /// Canonical roots; configured roots that do not exist are left out.
+    roots: Vec<PathBuf>,
+}
+
+impl Sandbox {
+    pub fn new(roots: &[String]) -> Self {
+        let mut out: Vec<PathBuf> = vec![];
+        for r in roots {
+            let Ok(real) = expand_home(r).canonicalize() else { continue };
+            if !out.contains(&real) { out.push(real); }
+        }
+        Self { roots: out }
+    }
+
+    pub fn roots(&self) -> &[PathBuf] { &self.roots }
+
+    /// `path` made absolute (`~/` expanded, relative to the working directory).
+    fn absolute(path: &str) -> Result<PathBuf> {
+        if path.is_empty() { bail!("path required"); }
+        let p = expand_home(path);
+        Ok(if p.is_absolute() { p } else { std::env::current_dir()?.join(p) })
+    }
+
+    fn check(&self, real: &Path, shown: &str) -> Result<()> {
+        if self.roots.iter().any(|r| real.starts_with(r)) { return Ok(()); }
+        let roots: Vec<String> = self.roots.iter().map(|r| r.display().to_string()).collect();
+        bail!("{} is outside the sandbox roots ({})", shown, if roots.is_empty() { "none configured".into() } else { roots.join(", ") })
+    }
+
+    /// An existing path, symlinks followed, for reading.
+    pub fn resolve(&self, path: &str) -> Result<PathBuf> {
+        let abs = Self::absolute(path)?;
+        let real = abs.canonicalize().with_context(|| format!("{}: not found", path))?;
+        self.check(&real, path)?;
+        Ok(real)
+    }
+
+    /// A path to create, change or remove. The parent is resolved like
+    /// `resolve` (missing folders allowed, `..` not); the last component is
+    /// kept as is, so a symlink there is the object itself, not its target.
+    pub fn target(&self, path: &str) -> Result<PathBuf> {
+        let abs = Self::absolute(path)?;
+        let name = match abs.components().next_back() {
+            Some(Component::Normal(n)) => n.to_os_string(),
+            _ => bail!("{}: not a file or folder name", path),
+        };
+        let parent = abs.parent().context("no parent folder")?;
+        let mut existing = parent;
+        let mut missing: Vec<&std::ffi::OsStr> = vec![];
+        while !existing.exists() {
+            match existing.components().next_back() {
+                Some(Component::Normal(n)) => missing.push(n),
+                _ => bail!("{}: '..' through a missing folder", path),
+            }
+            existing = existing.parent().context("no existing parent folder")?;
+        }
+        let mut real = existing.canonicalize()?;
+        real.extend(missing.iter().rev());
+        real.push(name);
+        self.check(&real, path)?;
+        if self.roots.contains(&real) { bail!("{} is a sandbox root", path); }
+        Ok(real)
+    }
+
+    /// `target`, and when it is a symlink, its destination must be inside too
+    /// (writing through a link writes the destination).
+    pub fn write_target(&self, path: &str) -> Result<PathBuf> {
+        let real = self.target(path)?;
+        if real.symlink_metadata().map(|m| m.file_type().is_symlink()).unwrap_or(false) {
+            let dest = real.canonicalize().with_context(|| format!("{}: dangling symlink", path))?;
+            self.check(&dest, &format!("{} (symlink to {})", path, dest.display()))?;
+        }
+        Ok(real)
+    }
+}
@@ -0,0 +1,62 @@
+//! Depth-first walk shared by `glob`, `search` and `tree`.
+
+use std::path::{Path, PathBuf};
+
+/// Entries visited before a walk gives up, whatever the caller's limits.
+pub const MAX_VISITED: usize = 200_000;
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub path: PathBuf,
+    /// Relative to the base, `/`-separated.
+    pub rel: String,
+    pub depth: usize,
+    pub is_dir: bool,
+    pub is_symlink: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    /// Levels below the base (1 = its direct entries).
+    pub max_depth: usize,
+    pub include_hidden: bool,
+}
+
+/// Dot entries (`.git`, `.cache`, ...) are skipped unless asked for.
+fn hidden(name: &str) -> bool { name.starts_with('.') }
+
+/// Entries under `base`: a folder's entries in name order, then those of its
+/// subfolders. Symlinked folders are listed but not entered. Stops when
+/// `visit` returns false or after `MAX_VISITED` entries; returns whether it
+/// stopped early.
+pub fn walk(base: &Path, opts: Options, mut visit: impl FnMut(&Entry) -> bool) -> bool {
+    let mut stack: Vec<(PathBuf, String, usize)> = vec![(base.to_path_buf(), String::new(), 0)];
+    let mut visited = 0usize;
+    while let Some((dir, rel, depth)) = stack.pop() {
+        if depth >= opts.max_depth { continue; }
+        let Ok(rd) = std::fs::read_dir(&dir) else { continue };
+        let mut ents: Vec<(String, PathBuf)> = rd.flatten()
+            .map(|e| (e.file_name().to_string_lossy().to_string(), e.path()))
+            .filter(|(n, _)| opts.include_hidden || !hidden(n))
+            .collect();
+        ents.sort();
+        let mut subdirs: Vec<(PathBuf, String, usize)> = vec![];
+        for (name, path) in ents {
+            visited += 1;
+            if visited > MAX_VISITED { return true; }
+            let Ok(meta) = path.symlink_metadata() else { continue };
+            let is_symlink = meta.file_type().is_symlink();
+            let entry = Entry {
+                rel: if rel.is_empty() { name.clone() } else { format!("{}/{}", rel, name) },
+                depth: depth + 1,
+                is_dir: meta.is_dir(),
+                is_symlink,
+                path,
+            };
+            if !visit(&entry) { return true; }
+            if entry.is_dir { subdirs.push((entry.path, entry.rel, depth + 1)); }
+        }
+        stack.extend(subdirs.into_iter().rev());
+    }
+    false
+}
@@ -0,0 +1,130 @@
+use mcp_fs::{Fs, FsConfig};
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// A sandbox rooted at `<dir>/root` with a small project in it.
+fn sandbox(dir: &Path) -> (Fs, String) {
+    let root = dir.join("root");
+    for (file, text) in [
+        ("src/lib.rs", "pub mod walk;\n\npub fn answer() -> u32 {\n    42\n}\n"),
+        ("src/walk/mod.rs", "// TODO: depth limits\nfn walk() {}\n"),
+        ("docs/README.md", "# Notes\nTODO: write docs\n"),
+        (".hidden/secret.txt", "todo: hidden\n"),
+        ("notes.txt", "one\ntwo\nthree\nfour\nfive\n"),
+    ] {
+        std::fs::create_dir_all(root.join(file).parent().unwrap()).unwrap();
+        std::fs::write(root.join(file), text).unwrap();
+    }
+    std::fs::write(root.join("blob.bin"), [0x7f, b'E', b'L', b'F', 0, 1, 2, 3]).unwrap();
+    let fs = Fs::new(FsConfig { roots: vec![root.to_string_lossy().to_string()], max_read_bytes: Some(16) });
+    (fs, root.canonicalize().unwrap().to_string_lossy().to_string())
+}
+
+fn at(root: &str, rel: &str) -> String { format!("{}/{}", root, rel) }
+
+#[test]
+fn reads_by_range_and_detects_binary() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (fs, root) = sandbox(tmp.path());
+
+    let whole = fs.call("read", &json!({"path": at(&root, "notes.txt")})).unwrap();
+    assert_eq!((whole["content"].as_str(), whole["truncated"].as_bool(), whole["next_offset"].as_u64()), (Some("one\ntwo\nthree\nfo"), Some(true), Some(16)));
+    let rest = fs.call("read", &json!({"path": at(&root, "notes.txt"), "offset": 16})).unwrap();
+    assert_eq!((rest["content"].as_str(), rest["truncated"].as_bool()), (Some("ur\nfive\n"), Some(false)));
+    let lines = fs.call("read", &json!({"path": at(&root, "notes.txt"), "start_line": 2, "end_line": 3})).unwrap();
+    assert_eq!((lines["content"].as_str(), lines["end_line"].as_u64()), (Some("two\nthree\n"), Some(3)));
+
+    let blob = fs.call("read", &json!({"path": at(&root, "blob.bin")})).unwrap();
+    assert_eq!((blob["binary"].as_bool(), blob["size"].as_u64(), blob["content"].clone()), (Some(true), Some(8), Value::Null));
+    let bytes = fs.call("read", &json!({"path": at(&root, "blob.bin"), "offset": 1, "length": 3})).unwrap();
+    assert_eq!(bytes["hex"], "454c46");
+    assert!(fs.call("read", &json!({"path": at(&root, "blob.bin"), "start_line": 1})).is_err());
+
+    let stat = fs.call("stat", &json!({"path": at(&root, "notes.txt")})).unwrap();
+    assert_eq!((stat["is_file"].as_bool(), stat["size"].as_u64(), stat["is_symlink"].as_bool()), (Some(true), Some(24), Some(false)));
+}
+
+#[test]
+fn glob_search_and_tree() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (fs, root) = sandbox(tmp.path());
+
+    let rs = fs.call("glob", &json!({"pattern": "**/*.rs", "path": root})).unwrap();
+    assert_eq!(rs["matches"], json!(["src/lib.rs", "src/walk/mod.rs"]));
+    let top = fs.call("glob", &json!({"pattern": "*.{txt,bin}", "path": root})).unwrap();
+    assert_eq!(top["matches"], json!(["blob.bin", "notes.txt"]));
+    let hidden = fs.call("glob", &json!({"pattern": "**/*.txt", "path": root, "include_hidden": true})).unwrap();
+    assert_eq!((hidden["matches"].clone(), hidden["truncated"].as_bool()), (json!(["notes.txt", ".hidden/secret.txt"]), Some(false)));
+    let capped = fs.call("glob", &json!({"pattern": "**/*.txt", "path": root, "include_hidden": true, "limit": 1})).unwrap();
+    assert_eq!((capped["matches"].clone(), capped["truncated"].as_bool()), (json!(["notes.txt"]), Some(true)));
+
+    let todo = fs.call("search", &json!({"pattern": "todo", "path": root, "case_insensitive": true})).unwrap();
+    let hits: Vec<(&str, u64, u64)> = todo["matches"].as_array().unwrap().iter().map(|m| (m["path"].as_str().unwrap(), m["line"].as_u64().unwrap(), m["column"].as_u64().unwrap())).collect();
+    assert_eq!(hits, vec![("docs/README.md", 2, 1), ("src/walk/mod.rs", 1, 4)]);
+    let ctx = fs.call("search", &json!({"pattern": "42", "path": root, "glob": "*.rs", "fixed_strings": true, "context": 1})).unwrap();
+    assert_eq!(ctx["matches"], json!([{"path": "src/lib.rs", "line": 4, "column": 5, "text": "    42", "before": ["pub fn answer() -> u32 {"], "after": ["}"]}]));
+    assert!(fs.call("search", &json!({"pattern": "(", "path": root})).is_err());
+
+    let tree = fs.call("tree", &json!({"path": root, "depth": 1})).unwrap();
+    let names: Vec<(&str, bool)> = tree["tree"]["children"].as_array().unwrap().iter().map(|c| (c["name"].as_str().unwrap(), c["truncated"].as_bool().unwrap_or(false))).collect();
+    assert_eq!(names, vec![("blob.bin", false), ("docs", true), ("notes.txt", false), ("src", true)]);
+    let tree = fs.call("tree", &json!({"path": at(&root, "src")})).unwrap();
+    assert_eq!(tree["tree"]["children"][1], json!({"name": "walk", "kind": "dir", "children": [{"name": "mod.rs", "kind": "file", "size": 35}]}));
+}
+
+#[test]
+fn writes_return_their_action() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (fs, root) = sandbox(tmp.path());
+
+    let dry = fs.call("write", &json!({"path": at(&root, "out/a.txt"), "content": "hi", "dry_run": true})).unwrap();
+    assert_eq!(dry["action"], json!({"command": format!("fs.write {}/out/a.txt", root), "writes": true, "paths": [at(&root, "out/a.txt")], "intent": null}));
+    assert!(!Path::new(&at(&root, "out")).exists());
+    assert!(fs.call("write", &json!({"path": at(&root, "out/a.txt"), "content": "hi"})).unwrap_err().to_string().contains("create_dirs"));
+    let wrote = fs.call("write", &json!({"path": at(&root, "out/a.txt"), "content": "hi", "create_dirs": true})).unwrap();
+    assert_eq!((wrote["created"].as_bool(), wrote["bytes"].as_u64()), (Some(true), Some(2)));
+    assert!(fs.call("write", &json!({"path": at(&root, "out/a.txt"), "content": "x", "overwrite": false})).is_err());
+    let appended = fs.call("append", &json!({"path": at(&root, "out/a.txt"), "content": " there"})).unwrap();
+    assert_eq!(appended["size"], 8);
+
+    let moved = fs.call("move", &json!({"from": at(&root, "out/a.txt"), "to": at(&root, "b.txt")})).unwrap();
+    assert_eq!(moved["action"]["paths"], json!([at(&root, "out/a.txt"), at(&root, "b.txt")]));
+    assert_eq!(std::fs::read_to_string(at(&root, "b.txt")).unwrap(), "hi there");
+    assert!(fs.call("move", &json!({"from": at(&root, "notes.txt"), "to": at(&root, "b.txt")})).unwrap_err().to_string().contains("overwrite"));
+
+    assert!(fs.call("delete", &json!({"path": at(&root, "src")})).unwrap_err().to_string().contains("not empty"));
+    let rm = fs.call("delete", &json!({"path": at(&root, "src"), "recursive": true})).unwrap();
+    assert_eq!(rm["action"]["command"], format!("rm -rf {}/src", root));
+    assert!(!Path::new(&at(&root, "src")).exists());
+    assert_eq!(fs.proposed_action("read", &json!({"path": at(&root, "b.txt")})), None);
+}
+
+#[test]
+fn paths_stay_inside_the_sandbox() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (fs, root) = sandbox(tmp.path());
+    let outside = tmp.path().join("outside");
+    std::fs::create_dir_all(&outside).unwrap();
+    std::fs::write(outside.join("secret"), "s").unwrap();
+    std::os::unix::fs::symlink(&outside, at(&root, "escape")).unwrap();
+    std::os::unix::fs::symlink(outside.join("secret"), at(&root, "secret-link")).unwrap();
+
+    for err in [
+        fs.call("read", &json!({"path": outside.join("secret")})),
+        fs.call("read", &json!({"path": at(&root, "../outside/secret")})),
+        fs.call("read", &json!({"path": at(&root, "escape/secret")})),
+        fs.call("read", &json!({"path": at(&root, "secret-link")})),
+        fs.call("write", &json!({"path": at(&root, "escape/new"), "content": "x"})),
+        fs.call("write", &json!({"path": at(&root, "secret-link"), "content": "x"})),
+        fs.call("list", &json!({"path": at(&root, "escape")})),
+        fs.call("move", &json!({"from": at(&root, "notes.txt"), "to": outside.join("notes.txt")})),
+    ] {
+        assert!(err.unwrap_err().to_string().contains("outside the sandbox roots"));
+    }
+    assert!(fs.call("write", &json!({"path": at(&root, "missing/../x"), "content": "x", "create_dirs": true})).is_err());
+    assert!(fs.call("delete", &json!({"path": root, "recursive": true})).unwrap_err().to_string().contains("sandbox root"));
+
+    // Removing a link removes the link, not what it points at
+    fs.call("delete", &json!({"path": at(&root, "escape")})).unwrap();
+    assert!(outside.join("secret").exists());
+}
@@ -0,0 +1,44 @@
+//! ROM catalog: one folder per console under the ROM root.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Rom {
+    /// File stem with underscores as spaces.
+    pub title: String,
+    /// Console folder name.
+    pub console: String,
+    pub path: PathBuf,
+}
+
+/// Files in the console folders under `root` (hidden files skipped), sorted
+/// by console then title.
+pub fn scan_roms(root: &Path) -> Vec<Rom> {
+    let mut out: Vec<Rom> = vec![];
+    let Ok(entries) = std::fs::read_dir(root) else { return out };
+    for entry in entries.flatten() {
+        if !entry.file_type().map(|t| t.is_dir()).unwrap_or(false) { continue; }
+        let console = entry.file_name().to_string_lossy().to_string();
+        for game in std::fs::read_dir(entry.path()).into_iter().flatten().flatten() {
+            let path = game.path();
+            if !path.is_file() || game.file_name().to_string_lossy().starts_with('.') { continue; }
+            let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else { continue };
+            out.push(Rom { title: stem.replace('_', " "), console: console.clone(), path });
+        }
+    }
+    out.sort_by(|a, b| (&a.console, &a.title).cmp(&(&b.console, &b.title)));
+    out
+}
+
+/// Titles grouped by console; consoles without games do not appear.
+pub fn by_console(roms: &[Rom]) -> Vec<(String, Vec<String>)> {
+    let mut out: Vec<(String, Vec<String>)> = vec![];
+    for rom in roms {
+        match out.last_mut() {
+            Some((console, titles)) if *console == rom.console => titles.push(rom.title.clone()),
+            _ => out.push((rom.console.clone(), vec![rom.title.clone()])),
+        }
+    }
+    out
+}
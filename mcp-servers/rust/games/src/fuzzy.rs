@@ -0,0 +1,63 @@
+//! Title matching for `launch`: tolerant of case, punctuation, file-name
+//! underscores, typos and partial titles.
+
+/// Lowercase words of `s`; anything that is not a letter or digit separates words.
+pub fn normalize(s: &str) -> String {
+    s.chars()
+        .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// How well `query` names `title`, from 0 (unrelated) to 1 (same words).
+/// A query that is a run of the title's words ("minish cap") scores 0.9;
+/// otherwise the best of character-bigram similarity (typos) and the share of
+/// query words that start a title word ("zelda mini").
+pub fn score(query: &str, title: &str) -> f64 {
+    let (q, t) = (normalize(query), normalize(title));
+    if q.is_empty() || t.is_empty() { return 0.0; }
+    if q == t { return 1.0; }
+    if format!(" {} ", t).contains(&format!(" {} ", q)) { return 0.9; }
+    let words: Vec<&str> = t.split(' ').collect();
+    let query_words: Vec<&str> = q.split(' ').collect();
+    let covered = query_words.iter().filter(|w| words.iter().any(|tw| tw.starts_with(**w))).count();
+    let cover = covered as f64 / query_words.len() as f64;
+    dice(&q, &t).max(0.8 * cover)
+}
+
+/// Sørensen–Dice coefficient over character bigrams.
+fn dice(a: &str, b: &str) -> f64 {
+    let bigrams = |s: &str| -> Vec<(char, char)> {
+        let chars: Vec<char> = s.chars().collect();
+        chars.windows(2).map(|w| (w[0], w[1])).collect()
+    };
+    let (a, mut b) = (bigrams(a), bigrams(b));
+    if a.is_empty() || b.is_empty() { return 0.0; }
+    let total = a.len() + b.len();
+    let mut shared = 0usize;
+    for g in a.iter() {
+        if let Some(i) = b.iter().position(|x| x == g) {
+            b.swap_remove(i);
+            shared += 1;
+        }
+    }
+    2.0 * shared as f64 / total as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_titles_and_typos_match() {
+        assert_eq!(normalize("Pokémon_Emerald (U)"), "pokémon emerald u");
+        assert_eq!(score("mario kart ds", "Mario_Kart_DS"), 1.0);
+        assert_eq!(score("minish cap", "The Legend of Zelda - The Minish Cap"), 0.9);
+        assert!(score("pokemon emrald", "Pokemon Emerald") > 0.7);
+        assert!(score("zelda mini", "The Legend of Zelda - The Minish Cap") >= 0.8);
+        assert!(score("mario kart", "Golden Sun") < 0.3);
+        assert_eq!(score("", "Golden Sun"), 0.0);
+    }
+}
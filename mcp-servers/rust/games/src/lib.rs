@@ -0,0 +1,312 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+
+pub mod catalog;
+pub mod fuzzy;
+pub mod steam;
+pub mod vdf;
+
+use catalog::Rom;
+use foreman_mcp::{expand_home, Describe, ProposedAction, ToolRisk, ToolSpec};
+
+/// Console name of Steam games in the catalog.
+pub const STEAM: &str = "steam";
+/// Lowest fuzzy score `launch` and `catalog` accept for a title.
+pub const MIN_SCORE: f64 = 0.5;
+/// Matches scoring within this of the best one make `launch` ambiguous.
+const AMBIGUITY: f64 = 0.05;
+
+/// `[games]` in foreman.toml.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct GamesConfig {
+    /// ROM folder with one subfolder per console (`~/` expanded; default `~/games/roms`).
+    #[serde(default)]
+    pub roms_root: Option<String>,
+    /// Steam client command (default `steam`).
+    #[serde(default)]
+    pub steam_bin: Option<String>,
+    /// Steam install whose `libraryfolders.vdf` lists the libraries (default:
+    /// the usual install locations).
+    #[serde(default)]
+    pub steam_root: Option<String>,
+    /// Quick-launch list of Steam games (default `config/steamgames.toml`).
+    #[serde(default)]
+    pub steam_list: Option<String>,
+    /// Emulator registry keyed by console folder name.
+    #[serde(default)]
+    pub emulators: BTreeMap<String, Emulator>,
+}
+
+/// How to launch the games of one console.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Emulator {
+    /// Display name of the console, e.g. "Game Boy Advance".
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Program to run (`~/` expanded).
+    pub bin: String,
+    /// Argument template; `{rom}` is replaced by the ROM path. Empty starts
+    /// the emulator alone.
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+impl GamesConfig {
+    /// `[games]` of `FOREMAN_CONFIG` (default `config/foreman.toml`); defaults
+    /// when the file or table is missing or malformed.
+    pub fn load() -> Self {
+        let path = std::env::var("FOREMAN_CONFIG").unwrap_or_else(|_| "config/foreman.toml".into());
+        let Ok(text) = std::fs::read_to_string(path) else { return Self::default() };
+        toml::from_str::<toml::Value>(&text).ok()
+            .and_then(|v| v.get("games").cloned())
+            .and_then(|g| g.try_into().ok())
+            .unwrap_or_default()
+    }
+
+    pub fn roms_root(&self) -> PathBuf {
+        expand_home(self.roms_root.as_deref().unwrap_or("~/games/roms"))
+    }
+
+    pub fn steam_bin(&self) -> String {
+        self.steam_bin.clone().unwrap_or_else(|| "steam".into())
+    }
+
+    pub fn steam_list(&self) -> PathBuf {
+        expand_home(self.steam_list.as_deref().unwrap_or("config/steamgames.toml"))
+    }
+
+    /// Every Steam library folder of the configured or default installs.
+    pub fn steam_libraries(&self) -> Vec<PathBuf> {
+        match self.steam_root.as_deref() {
+            Some(root) => steam::libraries(&[expand_home(root)]),
+            None => steam::libraries(&steam::default_roots()),
+        }
+    }
+
+    /// Shell allowlist rules (the `shell_allowlist` schema of config/policy.d)
+    /// for what the registry launches: each emulator with exactly its template's
+    /// argument count, the leading literal arguments pinned and `{rom}`-only
+    /// templates held to the console's ROM folder; and `steam -applaunch <APPID>`.
+    pub fn shell_allowlist(&self) -> Vec<JsonValue> {
+        let mut rules: Vec<JsonValue> = vec![];
+        for (console, emu) in self.emulators.iter() {
+            let mut args = json!({"count": emu.args.len()});
+            let literal: Vec<&String> = emu.args.iter().take_while(|a| !a.contains("{rom}")).collect();
+            if !literal.is_empty() { args["starts_with_tokens"] = json!(literal); }
+            if !emu.args.is_empty() && emu.args.iter().all(|a| a == "{rom}") {
+                args["path_prefixes"] = json!([self.roms_root().join(console).to_string_lossy()]);
+            }
+            let rule = json!({"match": expand_home(&emu.bin).to_string_lossy(), "args": args});
+            if !rules.contains(&rule) { rules.push(rule); }
+        }
+        rules.push(json!({"match": self.steam_bin(), "args": {"min_count": 2, "starts_with_tokens": ["-applaunch"], "digits_at": 1}}));
+        rules
+    }
+}
+
+/// One catalog entry: a ROM, or a Steam game (console `steam`).
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Game {
+    pub title: String,
+    pub console: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub appid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub installed: Option<bool>,
+}
+
+impl From<Rom> for Game {
+    fn from(r: Rom) -> Self {
+        Self { title: r.title, console: r.console, path: Some(r.path), appid: None, installed: None }
+    }
+}
+
+#[derive(Default)]
+pub struct Games {
+    cfg: GamesConfig,
+}
+
+impl Games {
+    pub fn new(cfg: GamesConfig) -> Self { Self { cfg } }
+
+    pub fn config(&self) -> &GamesConfig { &self.cfg }
+
+    /// Answer to the `describe` handshake: the tools below with their schemas.
+    pub fn describe() -> Describe {
+        let tool = |name: &str, description: &str, risk: ToolRisk, input_schema: JsonValue| ToolSpec {
+            name: name.into(),
+            description: Some(description.into()),
+            input_schema: Some(input_schema),
+            output_schema: None,
+            risk,
+        };
+        let console = json!({"type": "string", "description": "Console folder name (e.g. gba, nds) or steam."});
+        Describe { tools: vec![
+            tool("catalog", "List games: ROMs by console folder and Steam games (quick-launch list and installed).", ToolRisk::default(), json!({
+                "type": "object",
+                "properties": {
+                    "query": {"type": "string", "description": "Fuzzy title filter; best matches first."},
+                    "console": console,
+                    "limit": {"type": "integer", "minimum": 1, "description": "Max games (default 200)."}
+                },
+                "additionalProperties": false
+            })),
+            tool("emulators", "The emulator registry: console, program, argument template and whether the program is installed.", ToolRisk::default(), json!({
+                "type": "object",
+                "properties": {},
+                "additionalProperties": false
+            })),
+            tool("launch", "Launch a game by title (fuzzy). ROMs start with the console's registered emulator, Steam games with steam -applaunch. Fails with the candidates when the title is ambiguous.", ToolRisk { exec: true, ..Default::default() }, json!({
+                "type": "object",
+                "properties": {
+                    "title": {"type": "string", "minLength": 1, "description": "Game title; partial titles and typos are fine."},
+                    "console": console,
+                    "dry_run": {"type": "boolean", "description": "Return the command without starting it."}
+                },
+                "required": ["title"],
+                "additionalProperties": false
+            })),
+        ]}
+    }
+
+    /// ROMs, then Steam games: the quick-launch list, then installed games not on it.
+    pub fn games(&self) -> Vec<Game> {
+        let mut out: Vec<Game> = catalog::scan_roms(&self.cfg.roms_root()).into_iter().map(Game::from).collect();
+        let installed = steam::installed(&self.cfg.steam_libraries());
+        let installed_ids: HashSet<&str> = installed.iter().filter(|a| a.installed).map(|a| a.appid.as_str()).collect();
+        let listed = steam::user_list(&self.cfg.steam_list());
+        let listed_ids: HashSet<String> = listed.iter().map(|(_, id)| id.clone()).collect();
+        let steam_game = |title: &str, appid: &str| Game {
+            title: title.to_string(),
+            console: STEAM.into(),
+            path: None,
+            appid: Some(appid.to_string()),
+            installed: Some(installed_ids.contains(appid)),
+        };
+        out.extend(listed.iter().map(|(title, id)| steam_game(title, id)));
+        out.extend(installed.iter().filter(|a| !listed_ids.contains(&a.appid)).map(|a| steam_game(&a.name, &a.appid)));
+        out
+    }
+
+    // --- Public tool handlers ---
+
+    pub fn catalog(&self, params: &JsonValue) -> Result<JsonValue> {
+        let console = params.get("console").and_then(|v| v.as_str());
+        let query = params.get("query").and_then(|v| v.as_str()).map(str::trim).filter(|q| !q.is_empty());
+        let limit = params.get("limit").and_then(|v| v.as_u64()).unwrap_or(200).max(1) as usize;
+        let games: Vec<Game> = self.games().into_iter().filter(|g| console.map(|c| g.console == c).unwrap_or(true)).collect();
+        let games: Vec<Game> = match query {
+            Some(q) => ranked(q, games).into_iter().map(|(_, g)| g).collect(),
+            None => games,
+        };
+        let total = games.len();
+        Ok(json!({
+            "roms_root": self.cfg.roms_root().to_string_lossy(),
+            "total": total,
+            "games": games.into_iter().take(limit).collect::<Vec<_>>(),
+        }))
+    }
+
+    pub fn emulators(&self, _params: &JsonValue) -> Result<JsonValue> {
+        let roms = catalog::scan_roms(&self.cfg.roms_root());
+        let list: Vec<JsonValue> = self.cfg.emulators.iter().map(|(console, emu)| json!({
+            "console": console,
+            "name": emu.name,
+            "bin": expand_home(&emu.bin).to_string_lossy(),
+            "args": emu.args,
+            "found": which(&emu.bin).is_some(),
+            "games": roms.iter().filter(|r| r.console == *console).count(),
+        })).collect();
+        Ok(json!({"emulators": list, "steam": {"bin": self.cfg.steam_bin(), "found": which(&self.cfg.steam_bin()).is_some()}}))
+    }
+
+    pub fn launch(&self, params: &JsonValue) -> Result<JsonValue> {
+        let title = params.get("title").and_then(|v| v.as_str()).unwrap_or("").trim();
+        if title.is_empty() { bail!("title required"); }
+        let console = params.get("console").and_then(|v| v.as_str());
+        let dry_run = params.get("dry_run").and_then(|v| v.as_bool()).unwrap_or(false);
+        let game = self.find(title, console)?;
+        let (cmd, args) = self.command(&game)?;
+        let mut out = json!({"title": game.title, "console": game.console, "cmd": cmd, "args": args});
+        if dry_run {
+            out["dry_run"] = json!(true);
+            return Ok(out);
+        }
+        let Some(program) = which(&cmd) else { bail!("{} not found; check the emulator registry ([games.emulators] in foreman.toml)", cmd) };
+        let mut child = std::process::Command::new(program)
+            .args(&args)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()?;
+        out["pid"] = json!(child.id());
+        // Reap the game when it exits so it does not linger as a zombie
+        std::thread::spawn(move || { let _ = child.wait(); });
+        Ok(out)
+    }
+
+    /// What a launch runs: the program line and the ROM it opens. The title
+    /// names no path, so the gatekeeper gets both from here. `None` when the
+    /// title does not resolve to one game (the launch fails the same way).
+    pub fn proposed_action(&self, tool: &str, params: &JsonValue) -> Option<ProposedAction> {
+        if tool != "launch" { return None; }
+        let title = params.get("title").and_then(|v| v.as_str())?.trim();
+        let game = self.find(title, params.get("console").and_then(|v| v.as_str())).ok()?;
+        let (cmd, args) = self.command(&game).ok()?;
+        Some(ProposedAction {
+            command: std::iter::once(format!("games.launch {}", cmd)).chain(args).collect::<Vec<_>>().join(" "),
+            writes: false,
+            paths: game.path.iter().map(|p| p.to_string_lossy().to_string()).collect(),
+            intent: Some(format!("launch {} ({})", game.title, game.console)),
+        })
+    }
+
+    /// The one game `title` names, or an error listing the closest candidates.
+    pub fn find(&self, title: &str, console: Option<&str>) -> Result<Game> {
+        let games: Vec<Game> = self.games().into_iter().filter(|g| console.map(|c| g.console == c).unwrap_or(true)).collect();
+        let mut ranked = ranked(title, games).into_iter();
+        let Some((best, game)) = ranked.next() else { bail!("no game matches '{}'", title) };
+        let close: Vec<Game> = ranked.take_while(|(s, _)| best - s <= AMBIGUITY).map(|(_, g)| g).collect();
+        if !close.is_empty() {
+            let names: Vec<String> = std::iter::once(&game).chain(close.iter()).map(|g| format!("{} ({})", g.title, g.console)).collect();
+            bail!("'{}' matches several games: {}; name one or pass console", title, names.join(", "));
+        }
+        Ok(game)
+    }
+
+    /// Program and arguments that start `game`.
+    pub fn command(&self, game: &Game) -> Result<(String, Vec<String>)> {
+        if let Some(appid) = &game.appid {
+            return Ok((self.cfg.steam_bin(), vec!["-applaunch".into(), appid.clone()]));
+        }
+        let Some(emu) = self.cfg.emulators.get(&game.console) else {
+            bail!("no emulator registered for {}; add [games.emulators.{}] to foreman.toml", game.console, game.console)
+        };
+        let rom = game.path.as_deref().map(|p| p.to_string_lossy().to_string()).unwrap_or_default();
+        let args = emu.args.iter().map(|a| a.replace("{rom}", &rom)).collect();
+        Ok((expand_home(&emu.bin).to_string_lossy().to_string(), args))
+    }
+}
+
+/// Games scoring at least `MIN_SCORE` for `query`, best first.
+fn ranked(query: &str, games: Vec<Game>) -> Vec<(f64, Game)> {
+    let mut scored: Vec<(f64, Game)> = games.into_iter()
+        .map(|g| (fuzzy::score(query, &g.title), g))
+        .filter(|(s, _)| *s >= MIN_SCORE)
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored
+}
+
+/// `bin` itself when it is a path, else its first match on `PATH`.
+fn which(bin: &str) -> Option<PathBuf> {
+    let bin = expand_home(bin);
+    if bin.components().count() > 1 { return bin.is_file().then_some(bin); }
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path).map(|d| d.join(&bin)).find(|p| p.is_file())
+}
@@ -0,0 +1,32 @@
+use foreman_mcp::{ToolRequest, ToolResponse};
+use mcp_games::{Games, GamesConfig};
+use std::io::{self, BufRead, Write};
+
+#[tokio::main]
+async fn main() {
+    let games = Games::new(GamesConfig::load());
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    let mut lines = stdin.lock().lines();
+    while let Some(Ok(line)) = lines.next() {
+        if line.trim().is_empty() { continue; }
+        let req: Result<ToolRequest, _> = serde_json::from_str(&line);
+        let resp = match req {
+            Ok(r) => handle(&games, r),
+            Err(e) => ToolResponse::err(format!("bad request: {}", e)),
+        };
+        let _ = writeln!(stdout, "{}", serde_json::to_string(&resp).unwrap());
+        let _ = stdout.flush();
+    }
+}
+
+fn handle(games: &Games, req: ToolRequest) -> ToolResponse {
+    let res = match req.tool.as_str() {
+        "catalog" => games.catalog(&req.params),
+        "emulators" => games.emulators(&req.params),
+        "launch" => games.launch(&req.params),
+        foreman_mcp::DESCRIBE_TOOL => Ok(serde_json::to_value(Games::describe()).unwrap_or_default()),
+        _ => Err(anyhow::anyhow!("unknown tool")),
+    };
+    match res { Ok(v) => ToolResponse::ok(v), Err(e) => ToolResponse::err(e.to_string()) }
+}
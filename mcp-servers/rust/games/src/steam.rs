@@ -0,0 +1,149 @@
+//! Steam games: the user's quick-launch list and the app manifests of every
+//! library folder.
+
+use crate::vdf;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// `StateFlags` bits of an app manifest.
+const FULLY_INSTALLED: u32 = 4;
+const UPDATE_REQUIRED: u32 = 2;
+const UNINSTALLING: u32 = 2048;
+const UPDATE_PAUSED: u32 = 512;
+/// Update running or started, preallocating, adding files, downloading, staging, committing.
+const UPDATING: u32 = 256 | 1024 | 262_144 | 524_288 | 1_048_576 | 2_097_152 | 4_194_304;
+
+/// `[games]` of a steamgames.toml: name → AppID (string or integer), sorted by name.
+pub fn user_list(path: &Path) -> Vec<(String, String)> {
+    let Ok(text) = std::fs::read_to_string(path) else { return vec![] };
+    let Ok(val) = toml::from_str::<toml::Value>(&text) else { return vec![] };
+    let mut out: Vec<(String, String)> = vec![];
+    if let Some(tbl) = val.get("games").and_then(|v| v.as_table()) {
+        for (name, v) in tbl.iter() {
+            let appid = if let Some(s) = v.as_str() { s.to_string() } else if let Some(n) = v.as_integer() { n.to_string() } else { continue };
+            out.push((name.to_string(), appid));
+        }
+    }
+    out.sort_by_key(|a| a.0.to_lowercase());
+    out
+}
+
+/// One `appmanifest_<appid>.acf`.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct AppManifest {
+    pub appid: String,
+    pub name: String,
+    /// Folder under `steamapps/common`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub install_dir: Option<String>,
+    /// Fully installed, possibly with an update pending.
+    pub installed: bool,
+    /// installed, update required, updating, update paused, uninstalling or not installed.
+    pub state: String,
+    pub state_flags: u32,
+    pub size_on_disk: u64,
+    /// Unix seconds; absent when never played.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_played: Option<i64>,
+    /// Library folder holding the manifest.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub library: Option<PathBuf>,
+}
+
+pub fn parse_appmanifest(text: &str) -> Result<AppManifest> {
+    let doc = vdf::parse(text)?;
+    let app = doc.block("AppState").context("no AppState block")?;
+    let field = |key: &str| app.str(key).map(str::to_string);
+    let number = |key: &str| app.str(key).and_then(|v| v.trim().parse::<u64>().ok());
+    let appid = field("appid").context("no appid")?;
+    let state_flags = number("StateFlags").unwrap_or(0) as u32;
+    Ok(AppManifest {
+        name: field("name").unwrap_or_else(|| format!("App {}", appid)),
+        appid,
+        install_dir: field("installdir"),
+        installed: state_flags & FULLY_INSTALLED != 0,
+        state: state(state_flags).into(),
+        state_flags,
+        size_on_disk: number("SizeOnDisk").unwrap_or(0),
+        last_played: number("LastPlayed").filter(|t| *t > 0).map(|t| t as i64),
+        library: None,
+    })
+}
+
+fn state(flags: u32) -> &'static str {
+    if flags & UNINSTALLING != 0 { return "uninstalling"; }
+    if flags & UPDATE_PAUSED != 0 { return "update paused"; }
+    if flags & UPDATING != 0 { return "updating"; }
+    match (flags & FULLY_INSTALLED != 0, flags & UPDATE_REQUIRED != 0) {
+        (true, true) => "update required",
+        (true, false) => "installed",
+        _ => "not installed",
+    }
+}
+
+/// The usual native, legacy and Flatpak Steam installs.
+pub fn default_roots() -> Vec<PathBuf> {
+    let home = PathBuf::from(std::env::var("HOME").unwrap_or_default());
+    vec![
+        home.join(".local/share/Steam"),
+        home.join(".steam/steam"),
+        home.join(".var/app/com.valvesoftware.Steam/data/Steam"),
+    ]
+}
+
+/// Library folders of the Steam install at `root`: the install itself plus
+/// every folder in its `libraryfolders.vdf` (current `"path"` blocks or the
+/// older numbered entries). `root` may also be a `steamapps` folder.
+pub fn library_folders(root: &Path) -> Vec<PathBuf> {
+    let root = if root.file_name().map(|n| n == "steamapps").unwrap_or(false) { root.parent().unwrap_or(root) } else { root };
+    let mut out = vec![root.to_path_buf()];
+    let vdf_text = ["steamapps/libraryfolders.vdf", "config/libraryfolders.vdf"].iter()
+        .find_map(|f| std::fs::read_to_string(root.join(f)).ok());
+    let Some(doc) = vdf_text.and_then(|t| vdf::parse(&t).ok()) else { return out };
+    let Some(folders) = doc.block("libraryfolders") else { return out };
+    for (key, value) in folders.iter() {
+        if !key.chars().all(|c| c.is_ascii_digit()) { continue; }
+        let path = match value {
+            vdf::Value::Block(b) => b.str("path"),
+            vdf::Value::Str(s) => Some(s.as_str()),
+        };
+        if let Some(p) = path.map(PathBuf::from) {
+            if !out.contains(&p) { out.push(p); }
+        }
+    }
+    out
+}
+
+/// Library folders of all `roots` that exist, each once (installs are often
+/// reachable through symlinks, e.g. `~/.steam/steam`).
+pub fn libraries(roots: &[PathBuf]) -> Vec<PathBuf> {
+    let mut seen: Vec<PathBuf> = vec![];
+    let mut out: Vec<PathBuf> = vec![];
+    for lib in roots.iter().flat_map(|r| library_folders(r)) {
+        let Ok(real) = lib.join("steamapps").canonicalize() else { continue };
+        if seen.contains(&real) { continue; }
+        seen.push(real);
+        out.push(lib);
+    }
+    out
+}
+
+/// Manifests in the `steamapps` of each library, sorted by name; unreadable
+/// or malformed manifests are skipped.
+pub fn installed(libraries: &[PathBuf]) -> Vec<AppManifest> {
+    let mut out: Vec<AppManifest> = vec![];
+    for lib in libraries {
+        let Ok(rd) = std::fs::read_dir(lib.join("steamapps")) else { continue };
+        for ent in rd.flatten() {
+            let name = ent.file_name().to_string_lossy().to_string();
+            if !(name.starts_with("appmanifest_") && name.ends_with(".acf")) { continue; }
+            let Some(mut app) = std::fs::read_to_string(ent.path()).ok().and_then(|t| parse_appmanifest(&t).ok()) else { continue };
+            if out.iter().any(|a| a.appid == app.appid) { continue; }
+            app.library = Some(lib.clone());
+            out.push(app);
+        }
+    }
+    out.sort_by(|a, b| a.name.cmp(&b.name));
+    out
+}
@@ -0,0 +1,159 @@
+//! Valve KeyValues text (`.vdf`, `.acf`): `"key" "value"` pairs and
+//! `"key" { ... }` blocks, with `\"`, `\\`, `\n` and `\t` escapes, unquoted
+//! tokens, `//` comments and `[$PLATFORM]` conditionals (ignored).
+
+use anyhow::{bail, Result};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Str(String),
+    Block(Block),
+}
+
+/// Pairs in file order. Keys may repeat; lookups are case-insensitive and
+/// return the first match, as Steam does.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Block(pub Vec<(String, Value)>);
+
+impl Block {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.0.iter().find(|(k, _)| k.eq_ignore_ascii_case(key)).map(|(_, v)| v)
+    }
+
+    pub fn str(&self, key: &str) -> Option<&str> {
+        match self.get(key)? { Value::Str(s) => Some(s), Value::Block(_) => None }
+    }
+
+    pub fn block(&self, key: &str) -> Option<&Block> {
+        match self.get(key)? { Value::Block(b) => Some(b), Value::Str(_) => None }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token { Open, Close, Str(String) }
+
+/// The top-level pairs of `text` (usually a single named block).
+pub fn parse(text: &str) -> Result<Block> {
+    let mut lexer = Lexer { chars: text.chars().peekable(), line: 1 };
+    parse_block(&mut lexer, false)
+}
+
+fn parse_block(lexer: &mut Lexer, nested: bool) -> Result<Block> {
+    let mut pairs: Vec<(String, Value)> = vec![];
+    loop {
+        let key = match lexer.token()? {
+            Some(Token::Str(k)) => k,
+            Some(Token::Close) if nested => return Ok(Block(pairs)),
+            None if !nested => return Ok(Block(pairs)),
+            None => bail!("line {}: unexpected end of input, missing '}}'", lexer.line),
+            Some(t) => bail!("line {}: expected a key, found {:?}", lexer.line, t),
+        };
+        let value = match lexer.token()? {
+            Some(Token::Str(v)) => Value::Str(v),
+            Some(Token::Open) => Value::Block(parse_block(lexer, true)?),
+            Some(Token::Close) | None => bail!("line {}: key \"{}\" has no value", lexer.line, key),
+        };
+        pairs.push((key, value));
+    }
+}
+
+struct Lexer<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+}
+
+impl Lexer<'_> {
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') { self.line += 1; }
+        c
+    }
+
+    fn token(&mut self) -> Result<Option<Token>> {
+        loop {
+            let Some(&c) = self.chars.peek() else { return Ok(None) };
+            match c {
+                c if c.is_whitespace() => { self.bump(); }
+                '/' => {
+                    self.bump();
+                    if self.chars.peek() != Some(&'/') { bail!("line {}: stray '/'", self.line); }
+                    while !matches!(self.chars.peek(), None | Some('\n')) { self.bump(); }
+                }
+                '[' => {
+                    let line = self.line;
+                    while self.bump().map(|c| c != ']').unwrap_or(false) {}
+                    if self.line != line { bail!("line {}: unterminated conditional", line); }
+                }
+                '{' => { self.bump(); return Ok(Some(Token::Open)); }
+                '}' => { self.bump(); return Ok(Some(Token::Close)); }
+                '"' => {
+                    self.bump();
+                    return self.quoted().map(|s| Some(Token::Str(s)));
+                }
+                _ => {
+                    let mut s = String::new();
+                    while let Some(&c) = self.chars.peek() {
+                        if c.is_whitespace() || matches!(c, '{' | '}' | '"') { break; }
+                        s.push(c);
+                        self.bump();
+                    }
+                    return Ok(Some(Token::Str(s)));
+                }
+            }
+        }
+    }
+
+    fn quoted(&mut self) -> Result<String> {
+        let line = self.line;
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                None => bail!("line {}: unterminated string", line),
+                Some('"') => return Ok(s),
+                Some('\\') => match self.bump() {
+                    Some('n') => s.push('\n'),
+                    Some('t') => s.push('\t'),
+                    Some(c @ ('\\' | '"')) => s.push(c),
+                    Some(c) => { s.push('\\'); s.push(c); }
+                    None => bail!("line {}: unterminated string", line),
+                },
+                Some(c) => s.push(c),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_blocks_escapes_and_comments() {
+        let doc = parse(r#"
+            // leading comment
+            "Root"
+            {
+                "Name"      "Say \"hi\"\tnow" // trailing
+                "Path"      "C:\\Games"
+                Unquoted    42
+                "os"        "linux" [$LINUX]
+                "Inner" { "a" "1" "A" "2" }
+            }
+        "#).unwrap();
+        let root = doc.block("root").unwrap();
+        assert_eq!(root.str("name"), Some("Say \"hi\"\tnow"));
+        assert_eq!(root.str("PATH"), Some("C:\\Games"));
+        assert_eq!(root.str("unquoted"), Some("42"));
+        assert_eq!(root.str("os"), Some("linux"));
+        assert_eq!(root.block("inner").unwrap().str("a"), Some("1"));
+        assert_eq!(root.block("inner").unwrap().iter().count(), 2);
+
+        assert!(parse("\"a\" { \"b\" \"c\"").unwrap_err().to_string().contains("missing '}'"));
+        assert!(parse("\"a\" \"unterminated").unwrap_err().to_string().contains("line 1: unterminated string"));
+        assert!(parse("\"a\"\n}").unwrap_err().to_string().contains("line 2: key \"a\" has no value"));
+    }
+}
@@ -0,0 +1,107 @@
+use mcp_games::{Emulator, Games, GamesConfig};
+use serde_json::json;
+use std::path::Path;
+
+fn library(dir: &Path) -> Games {
+    for (console, file) in [("gba", "Pokemon_Emerald.gba"), ("gba", "Golden_Sun.gba"), ("gb", "Pokemon_Red.gb"), ("nds", "Mario_Kart_DS.nds"), ("snes", "Chrono_Trigger.sfc")] {
+        std::fs::create_dir_all(dir.join("roms").join(console)).unwrap();
+        std::fs::write(dir.join("roms").join(console).join(file), b"").unwrap();
+    }
+    std::fs::create_dir_all(dir.join("steam").join("steamapps")).unwrap();
+    std::fs::write(dir.join("steam/steamapps/appmanifest_403640.acf"), "\"AppState\"\n{\n\t\"appid\"\t\t\"403640\"\n\t\"name\"\t\t\"Dishonored 2\"\n\t\"StateFlags\"\t\t\"4\"\n}\n").unwrap();
+    std::fs::write(dir.join("steam/steamapps/appmanifest_620.acf"), "\"AppState\"\n{\n\t\"appid\"\t\t\"620\"\n\t\"name\"\t\t\"Portal 2\"\n\t\"StateFlags\"\t\t\"4\"\n}\n").unwrap();
+    std::fs::write(dir.join("steamgames.toml"), "[games]\n\"Dishonored 2\" = 403640\n\"Cyberpunk\" = \"1091500\"\n").unwrap();
+    let mgba = Emulator { name: Some("Game Boy Advance".into()), bin: "mgba-qt".into(), args: vec!["{rom}".into()] };
+    let cfg = GamesConfig {
+        roms_root: Some(dir.join("roms").to_string_lossy().to_string()),
+        steam_root: Some(dir.join("steam").to_string_lossy().to_string()),
+        steam_list: Some(dir.join("steamgames.toml").to_string_lossy().to_string()),
+        emulators: [
+            ("gba".to_string(), mgba.clone()),
+            ("gb".to_string(), Emulator { name: Some("Game Boy".into()), ..mgba }),
+            ("nds".to_string(), Emulator { name: None, bin: "/opt/melonDS.AppImage".into(), args: vec![] }),
+        ].into_iter().collect(),
+        ..Default::default()
+    };
+    Games::new(cfg)
+}
+
+#[test]
+fn catalog_lists_roms_by_console_and_steam_games() {
+    let tmp = tempfile::tempdir().unwrap();
+    let games = library(tmp.path());
+
+    let all = games.catalog(&json!({})).unwrap();
+    assert_eq!(all["total"], 8);
+    let steam = games.catalog(&json!({"console": "steam"})).unwrap();
+    assert_eq!(steam["games"], json!([
+        {"title": "Cyberpunk", "console": "steam", "appid": "1091500", "installed": false},
+        {"title": "Dishonored 2", "console": "steam", "appid": "403640", "installed": true},
+        {"title": "Portal 2", "console": "steam", "appid": "620", "installed": true},
+    ]));
+
+    let found = games.catalog(&json!({"query": "pokemon"})).unwrap();
+    let titles: Vec<&str> = found["games"].as_array().unwrap().iter().map(|g| g["title"].as_str().unwrap()).collect();
+    assert_eq!(titles.len(), 2);
+    assert!(titles.contains(&"Pokemon Emerald") && titles.contains(&"Pokemon Red"));
+}
+
+#[test]
+fn launch_matches_titles_fuzzily() {
+    let tmp = tempfile::tempdir().unwrap();
+    let games = library(tmp.path());
+    let rom = tmp.path().join("roms/gba/Pokemon_Emerald.gba");
+
+    let out = games.launch(&json!({"title": "pokemon emrald", "dry_run": true})).unwrap();
+    assert_eq!(out, json!({"title": "Pokemon Emerald", "console": "gba", "cmd": "mgba-qt", "args": [rom.to_string_lossy()], "dry_run": true}));
+    let out = games.launch(&json!({"title": "mario kart", "dry_run": true})).unwrap();
+    assert_eq!((out["cmd"].as_str(), out["args"].clone()), (Some("/opt/melonDS.AppImage"), json!([])));
+    let out = games.launch(&json!({"title": "dishonored", "dry_run": true})).unwrap();
+    assert_eq!(out["args"], json!(["-applaunch", "403640"]));
+
+    let err = games.launch(&json!({"title": "pokemon", "dry_run": true})).unwrap_err().to_string();
+    assert!(err.contains("Pokemon Emerald (gba)") && err.contains("Pokemon Red (gb)"), "{}", err);
+    assert_eq!(games.launch(&json!({"title": "pokemon", "console": "gb", "dry_run": true})).unwrap()["title"], "Pokemon Red");
+    assert!(games.launch(&json!({"title": "halo infinite"})).unwrap_err().to_string().contains("no game matches"));
+    assert!(games.launch(&json!({"title": "chrono trigger"})).unwrap_err().to_string().contains("no emulator registered for snes"));
+    assert!(games.launch(&json!({"title": "mario kart"})).unwrap_err().to_string().contains("/opt/melonDS.AppImage not found"));
+}
+
+#[test]
+fn launches_are_exec_calls_that_name_the_rom() {
+    let tmp = tempfile::tempdir().unwrap();
+    let games = library(tmp.path());
+    let rom = tmp.path().join("roms/gba/Pokemon_Emerald.gba").to_string_lossy().to_string();
+
+    let launch = Games::describe().tools.into_iter().find(|t| t.name == "launch").unwrap();
+    assert!(launch.risk.exec && !launch.risk.writes);
+    let action = games.proposed_action("launch", &json!({"title": "pokemon emrald"})).unwrap();
+    assert_eq!(action.command, format!("games.launch mgba-qt {}", rom));
+    assert_eq!((action.writes, action.paths), (false, vec![rom]));
+    // Steam games have no ROM; ambiguous titles launch nothing
+    let action = games.proposed_action("launch", &json!({"title": "dishonored"})).unwrap();
+    assert_eq!((action.command.as_str(), action.paths.len()), ("games.launch steam -applaunch 403640", 0));
+    assert!(games.proposed_action("launch", &json!({"title": "pokemon"})).is_none());
+    assert!(games.proposed_action("catalog", &json!({})).is_none());
+}
+
+#[test]
+fn shell_allowlist_follows_the_registry() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mut cfg = GamesConfig { roms_root: Some("/roms".into()), ..Default::default() };
+    cfg.emulators.insert("gba".into(), Emulator { name: None, bin: "mgba-qt".into(), args: vec!["{rom}".into()] });
+    cfg.emulators.insert("psx".into(), Emulator { name: None, bin: "duckstation".into(), args: vec!["-fullscreen".into(), "--".into(), "{rom}".into()] });
+    cfg.emulators.insert("nds".into(), Emulator { name: None, bin: "melonDS".into(), args: vec![] });
+    assert_eq!(cfg.shell_allowlist(), vec![
+        json!({"match": "mgba-qt", "args": {"count": 1, "path_prefixes": ["/roms/gba"]}}),
+        json!({"match": "melonDS", "args": {"count": 0}}),
+        json!({"match": "duckstation", "args": {"count": 3, "starts_with_tokens": ["-fullscreen", "--"]}}),
+        json!({"match": "steam", "args": {"min_count": 2, "starts_with_tokens": ["-applaunch"], "digits_at": 1}}),
+    ]);
+
+    let path = tmp.path().join("foreman.toml");
+    std::fs::write(&path, "[foreman]\nprofile = \"x\"\n\n[games]\nroms_root = \"/roms\"\nsteam_bin = \"/usr/bin/steam\"\n\n[games.emulators.gba]\nbin = \"mgba-qt\"\nargs = [\"{rom}\"]\n").unwrap();
+    std::env::set_var("FOREMAN_CONFIG", &path);
+    let loaded = GamesConfig::load();
+    assert_eq!((loaded.steam_bin(), loaded.emulators["gba"].args.clone()), ("/usr/bin/steam".to_string(), vec!["{rom}".to_string()]));
+}
@@ -0,0 +1,87 @@
+use mcp_games::steam::{self, AppManifest};
+use mcp_games::{vdf, Games, GamesConfig};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+
+fn fixture(name: &str) -> String {
+    std::fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/steam").join(name)).unwrap()
+}
+
+/// A Steam install at `<dir>/Steam` with a second library at `<dir>/Library`.
+fn install(dir: &Path) -> (PathBuf, PathBuf) {
+    let (root, library) = (dir.join("Steam"), dir.join("Library"));
+    std::fs::create_dir_all(root.join("steamapps")).unwrap();
+    std::fs::create_dir_all(library.join("steamapps")).unwrap();
+    let folders = fixture("libraryfolders.vdf")
+        .replace("{steam}", &root.to_string_lossy())
+        .replace("{library}", &library.to_string_lossy());
+    std::fs::write(root.join("steamapps/libraryfolders.vdf"), folders).unwrap();
+    for (lib, acf) in [(&root, "appmanifest_403640.acf"), (&root, "appmanifest_620.acf"), (&root, "appmanifest_999.acf"), (&library, "appmanifest_1091500.acf")] {
+        std::fs::write(lib.join("steamapps").join(acf), fixture(acf)).unwrap();
+    }
+    (root, library)
+}
+
+#[test]
+fn app_manifests_parse_state_size_and_last_played() {
+    let app = steam::parse_appmanifest(&fixture("appmanifest_403640.acf")).unwrap();
+    assert_eq!(app, AppManifest {
+        appid: "403640".into(),
+        name: "Dishonored 2".into(),
+        install_dir: Some("Dishonored2".into()),
+        installed: true,
+        state: "installed".into(),
+        state_flags: 4,
+        size_on_disk: 41_259_385_112,
+        last_played: Some(1_729_350_000),
+        library: None,
+    });
+    let doc = vdf::parse(&fixture("appmanifest_403640.acf")).unwrap();
+    assert_eq!(doc.block("AppState").unwrap().str("LauncherPath"), Some("C:\\Program Files (x86)\\Steam\\steam.exe"));
+
+    let portal = steam::parse_appmanifest(&fixture("appmanifest_620.acf")).unwrap();
+    assert_eq!((portal.installed, portal.state.as_str(), portal.last_played), (true, "update required", None));
+    let cyberpunk = steam::parse_appmanifest(&fixture("appmanifest_1091500.acf")).unwrap();
+    assert_eq!((cyberpunk.installed, cyberpunk.state.as_str()), (false, "updating"));
+    assert!(steam::parse_appmanifest(&fixture("appmanifest_999.acf")).is_err());
+}
+
+#[test]
+fn libraries_come_from_libraryfolders_vdf() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (root, library) = install(tmp.path());
+    assert_eq!(steam::library_folders(&root), vec![root.clone(), library.clone()]);
+    assert_eq!(steam::library_folders(&root.join("steamapps")), vec![root.clone(), library.clone()]);
+
+    // The legacy format lists paths directly
+    let legacy = tmp.path().join("Legacy");
+    std::fs::create_dir_all(legacy.join("steamapps")).unwrap();
+    std::fs::write(legacy.join("steamapps/libraryfolders.vdf"), fixture("libraryfolders_legacy.vdf")).unwrap();
+    assert_eq!(steam::library_folders(&legacy), vec![legacy.clone(), PathBuf::from("/mnt/old/SteamLibrary"), PathBuf::from("/mnt/Games \"Backup\"")]);
+
+    // Symlinked installs and missing folders are left out
+    let link = tmp.path().join("steam-link");
+    std::os::unix::fs::symlink(&root, &link).unwrap();
+    assert_eq!(steam::libraries(&[root.clone(), link, tmp.path().join("missing")]), vec![root.clone(), library.clone()]);
+
+    let apps = steam::installed(&steam::libraries(std::slice::from_ref(&root)));
+    let names: Vec<(&str, &str)> = apps.iter().map(|a| (a.name.as_str(), a.state.as_str())).collect();
+    assert_eq!(names, vec![("Cyberpunk 2077", "updating"), ("Dishonored 2", "installed"), ("Portal 2", "update required")]);
+    assert_eq!(apps[0].library.as_deref(), Some(library.as_path()));
+    assert_eq!(serde_json::to_value(&apps[1]).unwrap()["last_played"], 1_729_350_000);
+}
+
+#[test]
+fn catalog_marks_only_fully_installed_games() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (root, _) = install(tmp.path());
+    let games = Games::new(GamesConfig {
+        roms_root: Some(tmp.path().join("roms").to_string_lossy().to_string()),
+        steam_root: Some(root.to_string_lossy().to_string()),
+        steam_list: Some(tmp.path().join("none.toml").to_string_lossy().to_string()),
+        ..Default::default()
+    });
+    let steam = games.catalog(&json!({"console": "steam"})).unwrap();
+    let installed: Vec<(&str, bool)> = steam["games"].as_array().unwrap().iter().map(|g| (g["title"].as_str().unwrap(), g["installed"].as_bool().unwrap())).collect();
+    assert_eq!(installed, vec![("Cyberpunk 2077", false), ("Dishonored 2", true), ("Portal 2", true)]);
+}
@@ -0,0 +1,170 @@
+//! Unified diffs (`git diff`/`show` with `a/` and `b/` prefixes) parsed into
+//! files, hunks and numbered lines.
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct FileDiff {
+    pub path: String,
+    /// Set when the file was renamed or copied, or its old name differs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<String>,
+    /// added, deleted, modified, renamed or copied.
+    pub status: String,
+    pub binary: bool,
+    pub additions: usize,
+    pub deletions: usize,
+    pub hunks: Vec<Hunk>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    /// Text after the second `@@` (usually the enclosing function).
+    pub header: String,
+    pub lines: Vec<Line>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Line {
+    /// context, add or del.
+    pub kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new: Option<usize>,
+    pub text: String,
+}
+
+/// Parsed files, and whether `max_lines` hunk lines cut the diff short.
+pub fn parse(text: &str, max_lines: usize) -> (Vec<FileDiff>, bool) {
+    let mut files: Vec<FileDiff> = vec![];
+    let (mut old_no, mut new_no, mut kept) = (0, 0, 0);
+    let mut truncated = false;
+    for raw in text.split('\n') {
+        if let Some(rest) = raw.strip_prefix("diff --git ") {
+            let path = header_path(rest).unwrap_or_default();
+            files.push(FileDiff { path: path.clone(), status: "modified".into(), ..Default::default() });
+            continue;
+        }
+        let Some(file) = files.last_mut() else { continue };
+        if let Some(hunk) = file.hunks.last_mut().filter(|_| !raw.starts_with("@@")) {
+            let (kind, text) = match raw.chars().next() {
+                Some(' ') => ("context", &raw[1..]),
+                Some('+') => ("add", &raw[1..]),
+                Some('-') => ("del", &raw[1..]),
+                Some('\\') => continue, // "\ No newline at end of file"
+                _ => continue,
+            };
+            match kind {
+                "add" => file.additions += 1,
+                "del" => file.deletions += 1,
+                _ => {}
+            }
+            let (old, new) = match kind {
+                "add" => { new_no += 1; (None, Some(new_no - 1)) }
+                "del" => { old_no += 1; (Some(old_no - 1), None) }
+                _ => { old_no += 1; new_no += 1; (Some(old_no - 1), Some(new_no - 1)) }
+            };
+            if kept >= max_lines { truncated = true; continue; }
+            kept += 1;
+            hunk.lines.push(Line { kind, old, new, text: text.to_string() });
+            continue;
+        }
+        if let Some(h) = parse_hunk_header(raw) {
+            (old_no, new_no) = (h.old_start, h.new_start);
+            file.hunks.push(h);
+        } else if let Some(p) = raw.strip_prefix("--- ") {
+            if let Some(p) = side_path(p, "a/") { file.old_path = Some(p); }
+        } else if let Some(p) = raw.strip_prefix("+++ ") {
+            if let Some(p) = side_path(p, "b/") { file.path = p; }
+        } else if raw.starts_with("new file mode") {
+            file.status = "added".into();
+        } else if raw.starts_with("deleted file mode") {
+            file.status = "deleted".into();
+        } else if let Some(p) = raw.strip_prefix("rename from ").or_else(|| raw.strip_prefix("copy from ")) {
+            file.old_path = Some(unquote(p));
+        } else if let Some(p) = raw.strip_prefix("rename to ").or_else(|| raw.strip_prefix("copy to ")) {
+            file.status = if raw.starts_with("rename") { "renamed" } else { "copied" }.into();
+            file.path = unquote(p);
+        } else if raw.starts_with("Binary files ") || raw == "GIT binary patch" {
+            file.binary = true;
+        }
+    }
+    for f in &mut files {
+        // `--- a/x` on a plain change (or a deletion) only repeats the name
+        if f.status == "deleted" {
+            if let Some(old) = f.old_path.take() { f.path = old; }
+        } else if f.old_path.as_deref() == Some(f.path.as_str()) {
+            f.old_path = None;
+        }
+    }
+    (files, truncated)
+}
+
+/// `@@ -a,b +c,d @@ header`; a missing count means 1.
+fn parse_hunk_header(line: &str) -> Option<Hunk> {
+    let rest = line.strip_prefix("@@ -")?;
+    let (ranges, header) = rest.split_once(" @@")?;
+    let (old, new) = ranges.split_once(" +")?;
+    let range = |r: &str| -> Option<(usize, usize)> {
+        match r.split_once(',') {
+            Some((s, n)) => Some((s.parse().ok()?, n.parse().ok()?)),
+            None => Some((r.parse().ok()?, 1)),
+        }
+    };
+    let ((old_start, old_lines), (new_start, new_lines)) = (range(old)?, range(new)?);
+    Some(Hunk { old_start, old_lines, new_start, new_lines, header: header.trim().to_string(), lines: vec![] })
+}
+
+/// `a/<path>` of a `---`/`+++` line; `/dev/null` is no path.
+fn side_path(p: &str, prefix: &str) -> Option<String> {
+    let p = unquote(p.trim_end_matches('\t'));
+    p.strip_prefix(prefix).map(str::to_string)
+}
+
+/// The path of `diff --git a/<p> b/<p>` when both sides agree (so spaces in
+/// names need no quoting rules); renames are named by later lines.
+fn header_path(rest: &str) -> Option<String> {
+    let rest = unquote(rest);
+    let inner = rest.strip_prefix("a/")?;
+    let half = inner.len().checked_sub(3)? / 2;
+    let (a, b) = (inner.get(..half)?, inner.get(half..)?);
+    (b.strip_prefix(" b/") == Some(a)).then(|| a.to_string())
+}
+
+/// Git quotes names with control characters or quotes in them.
+fn unquote(p: &str) -> String {
+    match p.strip_prefix('"').and_then(|p| p.strip_suffix('"')) {
+        Some(inner) => inner.replace("\\\"", "\"").replace("\\t", "\t").replace("\\n", "\n").replace("\\\\", "\\"),
+        None => p.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_changes_renames_and_binaries() {
+        let text = "diff --git a/src/a b.rs b/src/a b.rs\nindex 1..2 100644\n--- a/src/a b.rs\n+++ b/src/a b.rs\n@@ -1,3 +1,3 @@ fn main() {\n one\n-two\n+deux\n three\n\\ No newline at end of file\n@@ -10 +10,2 @@\n ten\n+eleven\ndiff --git a/old.txt b/new.txt\nsimilarity index 100%\nrename from old.txt\nrename to new.txt\ndiff --git a/logo.png b/logo.png\nnew file mode 100644\nBinary files /dev/null and b/logo.png differ\n";
+        let (files, truncated) = parse(text, 100);
+        assert!(!truncated);
+        assert_eq!(files.len(), 3);
+        let a = &files[0];
+        assert_eq!((a.path.as_str(), a.old_path.clone(), a.status.as_str(), a.additions, a.deletions), ("src/a b.rs", None, "modified", 2, 1));
+        assert_eq!((a.hunks[0].header.as_str(), a.hunks[1].old_lines, a.hunks[1].new_lines), ("fn main() {", 1, 2));
+        assert_eq!(a.hunks[0].lines[2], Line { kind: "add", old: None, new: Some(2), text: "deux".into() });
+        assert_eq!(a.hunks[0].lines[3], Line { kind: "context", old: Some(3), new: Some(3), text: "three".into() });
+        assert_eq!(a.hunks[1].lines[1], Line { kind: "add", old: None, new: Some(11), text: "eleven".into() });
+        assert_eq!((files[1].path.as_str(), files[1].old_path.as_deref(), files[1].status.as_str()), ("new.txt", Some("old.txt"), "renamed"));
+        assert_eq!((files[2].path.as_str(), files[2].status.as_str(), files[2].binary), ("logo.png", "added", true));
+
+        let (files, truncated) = parse(text, 2);
+        assert!(truncated);
+        assert_eq!((files[0].hunks[0].lines.len(), files[0].hunks[1].lines.len(), files[0].additions), (2, 0, 2));
+    }
+}
@@ -1,18 +1,667 @@
-use anyhow::Result;
-use serde_json::{json, Value as JsonValue};
-use tokio::fs;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Serialize;
+use serde_json::{json, Map, Value as JsonValue};
+use std::process::Output;
 use tokio::process::Command;
 
-pub async fn status(path: &str) -> Result<JsonValue> {
-    let git_dir = format!("{}/.git", path.trim_end_matches('/'));
-    let is_repo = fs::metadata(&git_dir).await.is_ok();
-    if !is_repo {
+pub mod diff;
+
+use foreman_mcp::{Describe, ProposedAction, ToolRisk, ToolSpec};
+
+/// Default `limit` of `log`.
+const LOG_LIMIT: usize = 50;
+const MAX_LOG: usize = 1000;
+/// Default `max_lines` of `diff`, `show` and `stash show` (hunk lines, all files).
+const DIFF_LINES: usize = 5000;
+/// Cap on a file read with `show`.
+const MAX_SHOW_BYTES: usize = 1024 * 1024;
+/// Commit identity for repositories without `user.name`/`user.email`.
+const FALLBACK_NAME: &str = "Codex";
+const FALLBACK_EMAIL: &str = "codex@example.com";
+/// `log` fields are split by 0x1f and commits by 0x1e.
+const LOG_FORMAT: &str = "--format=%H%x1f%h%x1f%an%x1f%ae%x1f%aI%x1f%P%x1f%s%x1f%b%x1e";
+
+pub fn describe() -> Describe {
+    let tool = |name: &str, description: &str, writes: bool, input_schema: JsonValue| ToolSpec {
+        name: name.into(),
+        description: Some(description.into()),
+        input_schema: Some(input_schema),
+        output_schema: None,
+        risk: ToolRisk { writes, paths_param: Some("path".into()), ..Default::default() },
+    };
+    let path = json!({"type": "string", "description": "Repository (or a folder in it); defaults to ."});
+    let paths = json!({"type": "array", "items": {"type": "string"}, "description": "Limit to these files or folders."});
+    let context = json!({"type": "integer", "minimum": 0, "maximum": 100, "description": "Context lines around changes (default 3)."});
+    let max_lines = json!({"type": "integer", "minimum": 1, "description": "Max hunk lines over all files (default 5000)."});
+    Describe { tools: vec![
+        tool("status", "Branch, upstream, ahead/behind and one entry per changed, untracked or conflicted file.", false, json!({
+            "type": "object",
+            "properties": {
+                "path": path,
+                "untracked": {"type": "boolean", "description": "List untracked files (default true)."}
+            },
+            "additionalProperties": false
+        })),
+        tool("log", "Commits reachable from a ref or range, newest first, optionally only those touching paths.", false, json!({
+            "type": "object",
+            "properties": {
+                "path": path,
+                "range": {"type": "string", "description": "A ref (main, v1.2) or range (main..feature); defaults to HEAD."},
+                "paths": paths,
+                "limit": {"type": "integer", "minimum": 1, "maximum": 1000, "description": "Max commits (default 50)."},
+                "skip": {"type": "integer", "minimum": 0},
+                "author": {"type": "string"},
+                "since": {"type": "string", "description": "A date git understands (2024-01-31, \"2 weeks ago\")."},
+                "until": {"type": "string"},
+                "grep": {"type": "string", "description": "Regex over commit messages."}
+            },
+            "additionalProperties": false
+        })),
+        tool("diff", "Changes as files with hunks and numbered lines: unstaged (default), staged, against a ref (from) or between two refs (from, to).", false, json!({
+            "type": "object",
+            "properties": {
+                "path": path,
+                "staged": {"type": "boolean", "description": "Index against HEAD (or from)."},
+                "from": {"type": "string"},
+                "to": {"type": "string"},
+                "paths": paths,
+                "context": context,
+                "max_lines": max_lines
+            },
+            "additionalProperties": false
+        })),
+        tool("show", "A commit with its changes against its first parent, or a file's content at a revision.", false, json!({
+            "type": "object",
+            "properties": {
+                "path": path,
+                "rev": {"type": "string", "description": "Defaults to HEAD."},
+                "file": {"type": "string", "description": "Return this file (relative to the repository root) as of rev instead."},
+                "paths": paths,
+                "context": context,
+                "max_lines": max_lines
+            },
+            "additionalProperties": false
+        })),
+        tool("blame", "Last commit to touch each line of a file.", false, json!({
+            "type": "object",
+            "properties": {
+                "path": path,
+                "file": {"type": "string"},
+                "rev": {"type": "string", "description": "Blame as of this revision (default: the working tree)."},
+                "start_line": {"type": "integer", "minimum": 1},
+                "end_line": {"type": "integer", "minimum": 1}
+            },
+            "required": ["file"],
+            "additionalProperties": false
+        })),
+        tool("branch", "List, create, switch to or delete local branches. Without an action, name switches to that branch and creates it when missing.", true, json!({
+            "type": "object",
+            "properties": {
+                "path": path,
+                "action": {"type": "string", "enum": ["list", "create", "switch", "delete"]},
+                "name": {"type": "string"},
+                "start": {"type": "string", "description": "Start point of a new branch (default HEAD)."},
+                "create": {"type": "boolean", "description": "switch: create the branch when missing."},
+                "force": {"type": "boolean", "description": "delete: also when not merged."},
+                "remotes": {"type": "boolean", "description": "list: include remote-tracking branches."}
+            },
+            "additionalProperties": false
+        })),
+        tool("stash", "List, push, pop, apply, drop or show stashes.", true, json!({
+            "type": "object",
+            "properties": {
+                "path": path,
+                "action": {"type": "string", "enum": ["list", "push", "pop", "apply", "drop", "show"]},
+                "stash": {"description": "stash@{n} or n (default the latest).", "type": ["string", "integer"]},
+                "message": {"type": "string"},
+                "include_untracked": {"type": "boolean"},
+                "paths": paths,
+                "max_lines": max_lines
+            },
+            "additionalProperties": false
+        })),
+        tool("add", "Stage files (all changes when no patterns are given).", true, json!({
+            "type": "object",
+            "properties": {
+                "path": path,
+                "patterns": {"type": "array", "items": {"type": "string"}}
+            },
+            "additionalProperties": false
+        })),
+        tool("commit", "Commit the staged changes.", true, json!({
+            "type": "object",
+            "properties": {
+                "path": path,
+                "message": {"type": "string"},
+                "all": {"type": "boolean", "description": "Stage tracked changes first (commit -a)."}
+            },
+            "required": ["message"],
+            "additionalProperties": false
+        })),
+    ]}
+}
+
+pub async fn call(tool: &str, params: &JsonValue) -> Result<JsonValue> {
+    match tool {
+        "status" => status(params).await,
+        "log" => log(params).await,
+        "diff" => diff(params).await,
+        "show" => show(params).await,
+        "blame" => blame(params).await,
+        "branch" => branch(params).await,
+        "stash" => stash(params).await,
+        "add" => add(params).await,
+        "commit" => commit(params).await,
+        foreman_mcp::DESCRIBE_TOOL => Ok(serde_json::to_value(describe())?),
+        _ => Err(anyhow!("unknown tool")),
+    }
+}
+
+/// Policy view of the calls that change the repository: `add`, `commit`, and
+/// `branch`/`stash` unless they only list or show. Spelled like the git
+/// commands so `require_approval` keywords (`branch -D`, `stash drop`) match.
+pub fn proposed_action(tool: &str, params: &JsonValue) -> Option<ProposedAction> {
+    let name = str_param(params, "name").unwrap_or_default();
+    let (command, intent) = match tool {
+        "add" => {
+            let patterns = patterns(params);
+            (format!("git add {}", if patterns.is_empty() { "-A".into() } else { patterns.join(" ") }), None)
+        }
+        "commit" => ("git commit".to_string(), str_param(params, "message").map(str::to_string)),
+        "branch" => (match branch_action(params) {
+            "create" => format!("git branch {}", name),
+            "switch" => format!("git switch {}", name),
+            "delete" if bool_param(params, "force", false) => format!("git branch -D {}", name),
+            "delete" => format!("git branch -d {}", name),
+            _ => return None,
+        }, None),
+        "stash" => match str_param(params, "action").unwrap_or("list") {
+            "push" => ("git stash push".to_string(), str_param(params, "message").map(str::to_string)),
+            a @ ("pop" | "apply" | "drop") => (format!("git stash {} {}", a, stash_ref(params).unwrap_or_default()), None),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let raw = repo(params);
+    let path = std::fs::canonicalize(raw).map(|p| p.to_string_lossy().to_string()).unwrap_or_else(|_| raw.to_string());
+    Some(ProposedAction { command, writes: true, paths: vec![path], intent })
+}
+
+fn str_param<'a>(params: &'a JsonValue, key: &str) -> Option<&'a str> { params.get(key).and_then(|v| v.as_str()) }
+fn bool_param(params: &JsonValue, key: &str, default: bool) -> bool { params.get(key).and_then(|v| v.as_bool()).unwrap_or(default) }
+fn usize_param(params: &JsonValue, key: &str) -> Option<usize> { params.get(key).and_then(|v| v.as_u64()).map(|n| n as usize) }
+fn repo(params: &JsonValue) -> &str { str_param(params, "path").unwrap_or(".") }
+
+/// A ref, range or branch name from `params`. A leading `-` would be read as
+/// an option, so it is refused.
+fn rev<'a>(params: &'a JsonValue, key: &str) -> Result<Option<&'a str>> {
+    match str_param(params, key).filter(|s| !s.is_empty()) {
+        Some(r) if r.starts_with('-') => bail!("{} must not start with '-': {}", key, r),
+        r => Ok(r),
+    }
+}
+
+fn strings(params: &JsonValue, key: &str) -> Vec<String> {
+    match params.get(key) {
+        Some(JsonValue::String(s)) => vec![s.clone()],
+        Some(JsonValue::Array(a)) => a.iter().filter_map(|p| p.as_str().map(str::to_string)).collect(),
+        _ => vec![],
+    }
+}
+
+fn patterns(params: &JsonValue) -> Vec<String> { strings(params, "patterns") }
+
+/// `git -C <repo> <args>`, with output in the C locale, file names unquoted
+/// and no prompts or optional index locks.
+async fn run(repo: &str, args: &[&str]) -> Result<Output> {
+    Command::new("git")
+        .arg("-C").arg(repo)
+        .arg("-c").arg("core.quotepath=false")
+        .args(args)
+        .env("LC_ALL", "C")
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("GIT_OPTIONAL_LOCKS", "0")
+        .output().await
+        .context("running git")
+}
+
+/// `run`, with a failed command turned into an error carrying git's message.
+async fn git(repo: &str, args: &[&str]) -> Result<String> {
+    let out = run(repo, args).await?;
+    if !out.status.success() {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        let msg = stderr.lines().map(|l| l.trim_start_matches("fatal: ").trim_start_matches("error: ")).filter(|l| !l.is_empty()).collect::<Vec<_>>().join("; ");
+        let sub = args.iter().find(|a| !a.starts_with('-') && !a.contains('=')).unwrap_or(&"");
+        bail!("git {}: {}", sub, if msg.is_empty() { format!("exit {:?}", out.status.code()) } else { msg });
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).to_string())
+}
+
+async fn is_repo(repo: &str) -> bool {
+    run(repo, &["rev-parse", "--is-inside-work-tree"]).await
+        .map(|o| o.status.success() && o.stdout.starts_with(b"true"))
+        .unwrap_or(false)
+}
+
+async fn require_repo(repo: &str) -> Result<()> {
+    if !is_repo(repo).await { bail!("{} is not in a git work tree", repo); }
+    Ok(())
+}
+
+/// Full hash of a revision, if it resolves.
+async fn resolve(repo: &str, rev: &str) -> Option<String> {
+    let out = run(repo, &["rev-parse", "--verify", "-q", &format!("{}^{{commit}}", rev)]).await.ok()?;
+    out.status.success().then(|| String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
+
+// ---- status ----
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct StatusEntry {
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub orig_path: Option<String>,
+    /// Index and worktree columns of `git status --porcelain=v2` (`.` = unchanged).
+    pub index: String,
+    pub worktree: String,
+    /// modified, added, deleted, renamed, copied, type changed, conflicted, untracked or ignored.
+    pub kind: String,
+    pub staged: bool,
+    pub unstaged: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct Status {
+    /// None when HEAD is detached.
+    pub branch: Option<String>,
+    /// None before the first commit.
+    pub head: Option<String>,
+    pub upstream: Option<String>,
+    pub ahead: u64,
+    pub behind: u64,
+    pub files: Vec<StatusEntry>,
+}
+
+/// `git status --porcelain=v2 --branch -z` output.
+pub fn parse_status(text: &str) -> Status {
+    let mut st = Status::default();
+    let mut records = text.split('\0').filter(|r| !r.is_empty());
+    while let Some(rec) = records.next() {
+        if let Some(h) = rec.strip_prefix("# ") {
+            let (key, value) = h.split_once(' ').unwrap_or((h, ""));
+            match key {
+                "branch.oid" if value != "(initial)" => st.head = Some(value.to_string()),
+                "branch.head" if value != "(detached)" => st.branch = Some(value.to_string()),
+                "branch.upstream" => st.upstream = Some(value.to_string()),
+                "branch.ab" => {
+                    for n in value.split(' ') {
+                        if let Some(a) = n.strip_prefix('+') { st.ahead = a.parse().unwrap_or(0); }
+                        if let Some(b) = n.strip_prefix('-') { st.behind = b.parse().unwrap_or(0); }
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+        let (kind, rest) = rec.split_at(1);
+        let rest = rest.trim_start();
+        let (xy, path, orig_path) = match kind {
+            "1" => (rest.get(..2).unwrap_or(".."), rest.splitn(8, ' ').nth(7).unwrap_or_default(), None),
+            "2" => (rest.get(..2).unwrap_or(".."), rest.splitn(9, ' ').nth(8).unwrap_or_default(), records.next()),
+            "u" => (rest.get(..2).unwrap_or(".."), rest.splitn(10, ' ').nth(9).unwrap_or_default(), None),
+            "?" | "!" => ("..", rest, None),
+            _ => continue,
+        };
+        let (x, y) = xy.split_at(1);
+        let has = |c: &str| x == c || y == c;
+        let kind = match kind {
+            "?" => "untracked",
+            "!" => "ignored",
+            "u" => "conflicted",
+            _ if has("R") => "renamed",
+            _ if has("C") => "copied",
+            _ if has("A") => "added",
+            _ if has("D") => "deleted",
+            _ if has("T") => "type changed",
+            _ => "modified",
+        };
+        st.files.push(StatusEntry {
+            path: path.to_string(),
+            orig_path: orig_path.map(str::to_string),
+            index: x.to_string(),
+            worktree: y.to_string(),
+            kind: kind.into(),
+            staged: x != "." && kind != "conflicted",
+            unstaged: y != "." || matches!(kind, "untracked" | "conflicted"),
+        });
+    }
+    st
+}
+
+/// `changed` stays the number of entries, as before per-file entries existed.
+pub async fn status(params: &JsonValue) -> Result<JsonValue> {
+    let path = repo(params);
+    if !is_repo(path).await {
         return Ok(json!({ "repo": false }));
     }
-    // Run a simple git status --porcelain summary
-    let out = Command::new("git").arg("-C").arg(path).arg("status").arg("--porcelain").output().await?;
-    let ok = out.status.success();
-    let changed = String::from_utf8_lossy(&out.stdout).lines().count();
-    Ok(json!({ "repo": true, "ok": ok, "changed": changed }))
+    let untracked = if bool_param(params, "untracked", true) { "--untracked-files=all" } else { "--untracked-files=no" };
+    let out = run(path, &["status", "--porcelain=v2", "--branch", "-z", untracked]).await?;
+    let st = parse_status(&String::from_utf8_lossy(&out.stdout));
+    let mut v = serde_json::to_value(&st)?;
+    v["repo"] = json!(true);
+    v["ok"] = json!(out.status.success());
+    v["changed"] = json!(st.files.len());
+    Ok(v)
+}
+
+// ---- history ----
+
+fn parse_commits(text: &str) -> Vec<JsonValue> {
+    text.split('\x1e').filter_map(|rec| {
+        let f: Vec<&str> = rec.trim_start_matches('\n').split('\x1f').collect();
+        if f.len() < 8 { return None; }
+        Some(json!({
+            "commit": f[0],
+            "short": f[1],
+            "author": f[2],
+            "email": f[3],
+            "date": f[4],
+            "parents": f[5].split_whitespace().collect::<Vec<_>>(),
+            "subject": f[6],
+            "body": f[7].trim_end(),
+        }))
+    }).collect()
 }
 
+pub async fn log(params: &JsonValue) -> Result<JsonValue> {
+    let path = repo(params);
+    require_repo(path).await?;
+    let range = rev(params, "range")?;
+    let limit = usize_param(params, "limit").unwrap_or(LOG_LIMIT).clamp(1, MAX_LOG);
+    if range.is_none() && resolve(path, "HEAD").await.is_none() {
+        return Ok(json!({ "commits": [], "truncated": false }));
+    }
+    let mut args: Vec<String> = vec!["log".into(), LOG_FORMAT.into(), format!("-n{}", limit + 1)];
+    if let Some(skip) = usize_param(params, "skip") { args.push(format!("--skip={}", skip)); }
+    for key in ["author", "since", "until", "grep"] {
+        if let Some(v) = str_param(params, key) { args.push(format!("--{}={}", key, v)); }
+    }
+    args.push("--end-of-options".into());
+    if let Some(r) = range { args.push(r.into()); }
+    args.push("--".into());
+    args.extend(strings(params, "paths"));
+    let mut commits = parse_commits(&git(path, &args.iter().map(String::as_str).collect::<Vec<_>>()).await?);
+    let truncated = commits.len() > limit;
+    commits.truncate(limit);
+    Ok(json!({ "commits": commits, "truncated": truncated }))
+}
+
+fn context_arg(params: &JsonValue) -> String { format!("-U{}", usize_param(params, "context").unwrap_or(3).min(100)) }
+
+/// Parsed diff output with per-file and total line counts.
+fn diff_result(text: &str, params: &JsonValue) -> JsonValue {
+    let (files, truncated) = diff::parse(text, usize_param(params, "max_lines").unwrap_or(DIFF_LINES));
+    let (additions, deletions) = files.iter().fold((0, 0), |(a, d), f| (a + f.additions, d + f.deletions));
+    json!({ "files": files, "additions": additions, "deletions": deletions, "truncated": truncated })
+}
+
+const DIFF_ARGS: [&str; 5] = ["--no-color", "--no-ext-diff", "-M", "--src-prefix=a/", "--dst-prefix=b/"];
+
+pub async fn diff(params: &JsonValue) -> Result<JsonValue> {
+    let path = repo(params);
+    require_repo(path).await?;
+    let staged = bool_param(params, "staged", false);
+    let (from, to) = (rev(params, "from")?, rev(params, "to")?);
+    if to.is_some() && from.is_none() { bail!("to needs from"); }
+    if staged && to.is_some() { bail!("staged compares the index with from (default HEAD); it takes no to"); }
+    let context = context_arg(params);
+    let mut args: Vec<&str> = vec!["diff"];
+    args.extend(DIFF_ARGS);
+    args.push(&context);
+    if staged { args.push("--cached"); }
+    args.push("--end-of-options");
+    args.extend(from);
+    args.extend(to);
+    args.push("--");
+    let paths = strings(params, "paths");
+    args.extend(paths.iter().map(String::as_str));
+    let mut v = diff_result(&git(path, &args).await?, params);
+    v["mode"] = json!(match (staged, from, to) {
+        (_, Some(_), Some(_)) => "refs",
+        (true, _, _) => "staged",
+        (false, Some(_), None) => "worktree",
+        _ => "unstaged",
+    });
+    Ok(v)
+}
+
+pub async fn show(params: &JsonValue) -> Result<JsonValue> {
+    let path = repo(params);
+    require_repo(path).await?;
+    let rev = rev(params, "rev")?.unwrap_or("HEAD");
+    if let Some(file) = str_param(params, "file") {
+        let out = run(path, &["show", &format!("{}:{}", rev, file.trim_start_matches('/'))]).await?;
+        if !out.status.success() { bail!("{} is not in {}", file, rev); }
+        let size = out.stdout.len();
+        if out.stdout.iter().take(8192).any(|b| *b == 0) {
+            return Ok(json!({ "rev": rev, "file": file, "size": size, "binary": true }));
+        }
+        let end = size.min(MAX_SHOW_BYTES);
+        let content = String::from_utf8_lossy(&out.stdout[..end]);
+        return Ok(json!({ "rev": rev, "file": file, "size": size, "binary": false, "content": content, "truncated": end < size }));
+    }
+    let commit = parse_commits(&git(path, &["log", "-1", LOG_FORMAT, "--end-of-options", rev]).await?).into_iter().next()
+        .ok_or_else(|| anyhow!("{} is not a commit", rev))?;
+    let context = context_arg(params);
+    let mut args: Vec<&str> = vec!["show", "--format=", "--diff-merges=first-parent"];
+    args.extend(DIFF_ARGS);
+    args.extend([context.as_str(), "--end-of-options", rev, "--"]);
+    let paths = strings(params, "paths");
+    args.extend(paths.iter().map(String::as_str));
+    let mut v = diff_result(&git(path, &args).await?, params);
+    v["commit"] = commit;
+    Ok(v)
+}
+
+/// `git blame --porcelain`: each line is preceded by `<hash> <orig> <final> [<n>]`,
+/// and the first line from a commit by its `author`, `summary`, ... headers.
+fn parse_blame(text: &str) -> (Vec<JsonValue>, Map<String, JsonValue>) {
+    let (mut lines, mut commits) = (vec![], Map::new());
+    let (mut hash, mut line_no) = (String::new(), 0u64);
+    for raw in text.lines() {
+        if let Some(content) = raw.strip_prefix('\t') {
+            lines.push(json!({ "line": line_no, "commit": hash, "text": content }));
+            continue;
+        }
+        let words: Vec<&str> = raw.split(' ').collect();
+        let is_header = words[0].len() >= 40 && words[0].chars().all(|c| c.is_ascii_hexdigit())
+            && (3..=4).contains(&words.len()) && words[1..].iter().all(|w| w.parse::<u64>().is_ok());
+        if is_header {
+            hash = words[0].to_string();
+            line_no = words[2].parse().unwrap_or(0);
+            commits.entry(hash.clone()).or_insert_with(|| json!({}));
+            continue;
+        }
+        let Some((key, value)) = raw.split_once(' ') else { continue };
+        let field = match key {
+            "author" => ("author", json!(value)),
+            "author-mail" => ("email", json!(value.trim_start_matches('<').trim_end_matches('>'))),
+            "author-time" => ("time", json!(value.parse::<i64>().unwrap_or(0))),
+            "summary" => ("summary", json!(value)),
+            _ => continue,
+        };
+        if let Some(c) = commits.get_mut(&hash) { c[field.0] = field.1; }
+    }
+    (lines, commits)
+}
+
+pub async fn blame(params: &JsonValue) -> Result<JsonValue> {
+    let path = repo(params);
+    require_repo(path).await?;
+    let file = str_param(params, "file").filter(|f| !f.is_empty()).ok_or_else(|| anyhow!("file required"))?;
+    let rev = rev(params, "rev")?;
+    let mut args: Vec<String> = vec!["blame".into(), "--porcelain".into()];
+    match (usize_param(params, "start_line"), usize_param(params, "end_line")) {
+        (Some(s), Some(e)) if e < s => bail!("end_line is before start_line"),
+        (Some(s), Some(e)) => args.push(format!("-L{},{}", s, e)),
+        (Some(s), None) => args.push(format!("-L{},", s)),
+        (None, Some(e)) => args.push(format!("-L1,{}", e)),
+        (None, None) => {}
+    }
+    args.extend(rev.map(str::to_string));
+    args.extend(["--".to_string(), file.to_string()]);
+    let (lines, commits) = parse_blame(&git(path, &args.iter().map(String::as_str).collect::<Vec<_>>()).await?);
+    Ok(json!({ "file": file, "rev": rev, "lines": lines, "commits": commits }))
+}
+
+// ---- branches and stashes ----
+
+fn branch_action(params: &JsonValue) -> &str {
+    str_param(params, "action").unwrap_or(if str_param(params, "name").is_some() { "switch" } else { "list" })
+}
+
+async fn branch_name<'a>(path: &str, params: &'a JsonValue) -> Result<&'a str> {
+    let name = rev(params, "name")?.ok_or_else(|| anyhow!("branch name required"))?;
+    if !run(path, &["check-ref-format", "--branch", name]).await?.status.success() {
+        bail!("invalid branch name {}", name);
+    }
+    Ok(name)
+}
+
+pub async fn branch(params: &JsonValue) -> Result<JsonValue> {
+    let path = repo(params);
+    require_repo(path).await?;
+    let action = branch_action(params);
+    if action == "list" {
+        let mut args = vec!["for-each-ref", "--format=%(refname)%1f%(refname:short)%1f%(objectname:short)%1f%(upstream:short)%1f%(HEAD)%1f%(committerdate:iso-strict)%1f%(subject)", "refs/heads"];
+        if bool_param(params, "remotes", false) { args.push("refs/remotes"); }
+        let branches: Vec<JsonValue> = git(path, &args).await?.lines().filter_map(|l| {
+            let f: Vec<&str> = l.split('\x1f').collect();
+            if f.len() < 7 || f[0].ends_with("/HEAD") { return None; }
+            Some(json!({
+                "name": f[1],
+                "commit": f[2],
+                "upstream": (!f[3].is_empty()).then_some(f[3]),
+                "current": f[4] == "*",
+                "remote": f[0].starts_with("refs/remotes/"),
+                "date": f[5],
+                "subject": f[6],
+            }))
+        }).collect();
+        let current = git(path, &["branch", "--show-current"]).await?.trim().to_string();
+        return Ok(json!({ "current": (!current.is_empty()).then_some(current), "branches": branches }));
+    }
+    let name = branch_name(path, params).await?;
+    let existing = resolve(path, &format!("refs/heads/{}", name)).await;
+    let start = rev(params, "start")?;
+    match action {
+        "create" => {
+            if existing.is_some() { bail!("branch {} already exists", name); }
+            git(path, &[&["branch", name][..], start.as_slice()].concat()).await?;
+            Ok(json!({ "ok": true, "action": "create", "name": name, "commit": resolve(path, name).await }))
+        }
+        "switch" => {
+            // Without an action the call reads "work on <name>", so a missing branch is created
+            let create = bool_param(params, "create", params.get("action").is_none());
+            if existing.is_none() && !create { bail!("no branch {} (pass create: true to create it)", name); }
+            if existing.is_some() {
+                git(path, &["switch", name]).await?;
+            } else {
+                git(path, &[&["switch", "-c", name][..], start.as_slice()].concat()).await?;
+            }
+            Ok(json!({ "ok": true, "action": "switch", "name": name, "created": existing.is_none(), "commit": resolve(path, "HEAD").await }))
+        }
+        "delete" => {
+            let Some(commit) = existing else { bail!("no branch {}", name) };
+            git(path, &["branch", if bool_param(params, "force", false) { "-D" } else { "-d" }, name]).await?;
+            Ok(json!({ "ok": true, "action": "delete", "name": name, "commit": commit }))
+        }
+        other => bail!("unknown branch action {} (list, create, switch or delete)", other),
+    }
+}
+
+/// `stash@{n}` from `stash` given as that or as `n`; the latest by default.
+fn stash_ref(params: &JsonValue) -> Result<String> {
+    let n = match params.get("stash") {
+        None | Some(JsonValue::Null) => 0,
+        Some(JsonValue::Number(n)) => n.as_u64().ok_or_else(|| anyhow!("stash must be stash@{{n}} or n"))?,
+        Some(JsonValue::String(s)) => s.strip_prefix("stash@{").and_then(|s| s.strip_suffix('}')).unwrap_or(s).parse()
+            .map_err(|_| anyhow!("stash must be stash@{{n}} or n, not {}", s))?,
+        Some(_) => bail!("stash must be stash@{{n}} or n"),
+    };
+    Ok(format!("stash@{{{}}}", n))
+}
+
+pub async fn stash(params: &JsonValue) -> Result<JsonValue> {
+    let path = repo(params);
+    require_repo(path).await?;
+    let action = str_param(params, "action").unwrap_or("list");
+    match action {
+        "list" => {
+            let stashes: Vec<JsonValue> = git(path, &["stash", "list", "--format=%gd%x1f%H%x1f%cI%x1f%gs"]).await?.lines().filter_map(|l| {
+                let f: Vec<&str> = l.split('\x1f').collect();
+                (f.len() == 4).then(|| json!({ "stash": f[0], "commit": f[1], "date": f[2], "message": f[3] }))
+            }).collect();
+            Ok(json!({ "stashes": stashes }))
+        }
+        "push" => {
+            let mut args: Vec<String> = vec!["stash".into(), "push".into()];
+            if bool_param(params, "include_untracked", false) { args.push("--include-untracked".into()); }
+            if let Some(m) = str_param(params, "message") { args.push(format!("--message={}", m)); }
+            args.push("--".into());
+            args.extend(strings(params, "paths"));
+            let out = git(path, &args.iter().map(String::as_str).collect::<Vec<_>>()).await?;
+            let created = !out.contains("No local changes to save");
+            Ok(json!({ "ok": true, "action": "push", "created": created, "stash": created.then_some("stash@{0}") }))
+        }
+        "pop" | "apply" | "drop" => {
+            let stash = stash_ref(params)?;
+            let commit = resolve(path, &stash).await.ok_or_else(|| anyhow!("no {}", stash))?;
+            git(path, &["stash", action, &stash]).await?;
+            Ok(json!({ "ok": true, "action": action, "stash": stash, "commit": commit }))
+        }
+        "show" => {
+            let stash = stash_ref(params)?;
+            let mut args: Vec<&str> = vec!["stash", "show", "--patch"];
+            args.extend(DIFF_ARGS);
+            args.push(&stash);
+            let mut v = diff_result(&git(path, &args).await?, params);
+            v["stash"] = json!(stash);
+            Ok(v)
+        }
+        other => bail!("unknown stash action {} (list, push, pop, apply, drop or show)", other),
+    }
+}
+
+// ---- staging and commits ----
+
+pub async fn add(params: &JsonValue) -> Result<JsonValue> {
+    let path = repo(params);
+    let patterns = patterns(params);
+    let mut args: Vec<&str> = vec!["add"];
+    if patterns.is_empty() { args.push("-A"); } else {
+        args.push("--");
+        args.extend(patterns.iter().map(String::as_str));
+    }
+    let out = run(path, &args).await?;
+    Ok(json!({ "ok": out.status.success() }))
+}
+
+/// Returns `ok: false` with git's exit code when there is nothing to commit.
+pub async fn commit(params: &JsonValue) -> Result<JsonValue> {
+    let path = repo(params);
+    let msg = str_param(params, "message").unwrap_or("");
+    if msg.is_empty() { bail!("message required"); }
+    let mut args: Vec<String> = vec![];
+    for (key, fallback) in [("user.name", FALLBACK_NAME), ("user.email", FALLBACK_EMAIL)] {
+        if !run(path, &["config", key]).await?.status.success() {
+            args.extend(["-c".to_string(), format!("{}={}", key, fallback)]);
+        }
+    }
+    args.extend(["commit".into(), format!("--message={}", msg)]);
+    if bool_param(params, "all", false) { args.push("--all".into()); }
+    let out = run(path, &args.iter().map(String::as_str).collect::<Vec<_>>()).await?;
+    let ok = out.status.success();
+    let commit = if ok { resolve(path, "HEAD").await } else { None };
+    Ok(json!({ "ok": ok, "code": out.status.code(), "commit": commit }))
+}
@@ -0,0 +1,195 @@
+use serde_json::{json, Value};
+use std::path::Path;
+use std::process::Command;
+
+fn sh(dir: &Path, args: &[&str]) {
+    let out = Command::new("git").arg("-C").arg(dir).args(args).output().unwrap();
+    assert!(out.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&out.stderr));
+}
+
+/// A throwaway repository on `main` with its own identity.
+fn repo(dir: &Path) -> String {
+    sh(dir, &["init", "-q", "-b", "main"]);
+    sh(dir, &["config", "user.name", "Test"]);
+    sh(dir, &["config", "user.email", "test@example.com"]);
+    dir.to_string_lossy().to_string()
+}
+
+fn write(dir: &Path, file: &str, text: &str) {
+    std::fs::create_dir_all(dir.join(file).parent().unwrap()).unwrap();
+    std::fs::write(dir.join(file), text).unwrap();
+}
+
+async fn call(tool: &str, params: Value) -> Value {
+    mcp_git::call(tool, &params).await.unwrap_or_else(|e| panic!("{} {}: {}", tool, params, e))
+}
+
+async fn commit(path: &str, message: &str) -> String {
+    assert_eq!(call("add", json!({"path": path})).await["ok"], true);
+    let done = call("commit", json!({"path": path, "message": message})).await;
+    assert_eq!(done["ok"], true, "{}", done);
+    done["commit"].as_str().unwrap().to_string()
+}
+
+/// first: a.txt, b.txt; second: a.txt; third: src/c.rs
+async fn history(dir: &Path) -> String {
+    let path = repo(dir);
+    write(dir, "a.txt", "one\n");
+    write(dir, "b.txt", "bee\n");
+    commit(&path, "first").await;
+    write(dir, "a.txt", "one\ntwo\n");
+    commit(&path, "second: a").await;
+    write(dir, "src/c.rs", "fn c() {}\n");
+    commit(&path, "third").await;
+    path
+}
+
+fn subjects(log: &Value) -> Vec<&str> {
+    log["commits"].as_array().unwrap().iter().map(|c| c["subject"].as_str().unwrap()).collect()
+}
+
+#[tokio::test]
+async fn status_entries_and_log_filters() {
+    let tmp = tempfile::tempdir().unwrap();
+    let path = repo(tmp.path());
+    let empty = call("status", json!({"path": path})).await;
+    assert_eq!((empty["repo"].as_bool(), empty["branch"].as_str(), empty["head"].clone(), empty["changed"].as_u64()), (Some(true), Some("main"), Value::Null, Some(0)));
+    assert_eq!(call("log", json!({"path": path})).await["commits"], json!([]));
+    std::fs::remove_dir_all(tmp.path().join(".git")).unwrap();
+
+    let path = history(tmp.path()).await;
+    write(tmp.path(), "a.txt", "one\ntwo\nthree\n");
+    write(tmp.path(), "d.txt", "new\n");
+    sh(tmp.path(), &["add", "d.txt"]);
+    sh(tmp.path(), &["mv", "b.txt", "bee.txt"]);
+    write(tmp.path(), "e.txt", "untracked\n");
+
+    let st = call("status", json!({"path": path})).await;
+    assert_eq!((st["branch"].as_str(), st["head"].as_str().map(str::len), st["changed"].as_u64()), (Some("main"), Some(40), Some(4)));
+    let entry = |p: &str| st["files"].as_array().unwrap().iter().find(|f| f["path"] == p).cloned().unwrap_or_else(|| panic!("no {} in {}", p, st));
+    assert_eq!(entry("a.txt"), json!({"path": "a.txt", "index": ".", "worktree": "M", "kind": "modified", "staged": false, "unstaged": true}));
+    assert_eq!(entry("d.txt")["kind"], "added");
+    assert_eq!((entry("bee.txt")["kind"].as_str(), entry("bee.txt")["orig_path"].as_str(), entry("bee.txt")["staged"].as_bool()), (Some("renamed"), Some("b.txt"), Some(true)));
+    assert_eq!((entry("e.txt")["kind"].as_str(), entry("e.txt")["unstaged"].as_bool()), (Some("untracked"), Some(true)));
+    assert_eq!(call("status", json!({"path": path, "untracked": false})).await["changed"], 3);
+
+    let log = call("log", json!({"path": path})).await;
+    assert_eq!(subjects(&log), vec!["third", "second: a", "first"]);
+    let first = &log["commits"][2];
+    assert_eq!((first["author"].as_str(), first["email"].as_str(), first["parents"].as_array().map(Vec::len)), (Some("Test"), Some("test@example.com"), Some(0)));
+    assert_eq!(log["commits"][0]["parents"][0], log["commits"][1]["commit"]);
+    let capped = call("log", json!({"path": path, "limit": 2})).await;
+    assert_eq!((subjects(&capped), capped["truncated"].as_bool()), (vec!["third", "second: a"], Some(true)));
+    assert_eq!(subjects(&call("log", json!({"path": path, "paths": ["a.txt"]})).await), vec!["second: a", "first"]);
+    assert_eq!(subjects(&call("log", json!({"path": path, "range": "HEAD~2..HEAD", "skip": 1})).await), vec!["second: a"]);
+    assert_eq!(subjects(&call("log", json!({"path": path, "grep": "^sec"})).await), vec!["second: a"]);
+    assert!(mcp_git::call("log", &json!({"path": path, "range": "--all"})).await.unwrap_err().to_string().contains("must not start with '-'"));
+
+    let plain = tempfile::tempdir().unwrap();
+    assert_eq!(call("status", json!({"path": plain.path()})).await, json!({"repo": false}));
+    assert!(mcp_git::call("log", &json!({"path": plain.path()})).await.unwrap_err().to_string().contains("not in a git work tree"));
+}
+
+#[tokio::test]
+async fn diffs_show_and_blame() {
+    let tmp = tempfile::tempdir().unwrap();
+    let path = history(tmp.path()).await;
+    write(tmp.path(), "a.txt", "one\nTWO\n");
+    write(tmp.path(), "d.txt", "new\n");
+    sh(tmp.path(), &["add", "d.txt"]);
+
+    let unstaged = call("diff", json!({"path": path})).await;
+    assert_eq!((unstaged["mode"].as_str(), unstaged["files"].as_array().map(Vec::len), unstaged["additions"].as_u64(), unstaged["deletions"].as_u64()), (Some("unstaged"), Some(1), Some(1), Some(1)));
+    assert_eq!(unstaged["files"][0]["hunks"][0]["lines"], json!([
+        {"kind": "context", "old": 1, "new": 1, "text": "one"},
+        {"kind": "del", "old": 2, "text": "two"},
+        {"kind": "add", "new": 2, "text": "TWO"},
+    ]));
+    let staged = call("diff", json!({"path": path, "staged": true})).await;
+    assert_eq!((staged["files"][0]["path"].as_str(), staged["files"][0]["status"].as_str()), (Some("d.txt"), Some("added")));
+    let refs = call("diff", json!({"path": path, "from": "HEAD~2", "to": "HEAD", "context": 0})).await;
+    let files: Vec<(&str, &str)> = refs["files"].as_array().unwrap().iter().map(|f| (f["path"].as_str().unwrap(), f["status"].as_str().unwrap())).collect();
+    assert_eq!((refs["mode"].as_str(), files), (Some("refs"), vec![("a.txt", "modified"), ("src/c.rs", "added")]));
+    assert_eq!(refs["files"][0]["hunks"][0]["lines"], json!([{"kind": "add", "new": 2, "text": "two"}]));
+    let only = call("diff", json!({"path": path, "from": "HEAD~2", "paths": ["src"]})).await;
+    assert_eq!((only["mode"].as_str(), only["files"].as_array().map(Vec::len)), (Some("worktree"), Some(1)));
+    assert!(mcp_git::call("diff", &json!({"path": path, "to": "HEAD"})).await.is_err());
+    assert!(mcp_git::call("diff", &json!({"path": path, "from": "-p"})).await.is_err());
+
+    let second = call("show", json!({"path": path, "rev": "HEAD~1"})).await;
+    assert_eq!((second["commit"]["subject"].as_str(), second["files"][0]["path"].as_str(), second["additions"].as_u64()), (Some("second: a"), Some("a.txt"), Some(1)));
+    let root = call("show", json!({"path": path, "rev": "HEAD~2"})).await;
+    assert_eq!(root["files"].as_array().unwrap().iter().map(|f| f["status"].as_str().unwrap()).collect::<Vec<_>>(), vec!["added", "added"]);
+    let old = call("show", json!({"path": path, "rev": "HEAD~2", "file": "a.txt"})).await;
+    assert_eq!((old["content"].as_str(), old["binary"].as_bool()), (Some("one\n"), Some(false)));
+    assert!(mcp_git::call("show", &json!({"path": path, "rev": "HEAD~2", "file": "src/c.rs"})).await.is_err());
+
+    let blame = call("blame", json!({"path": path, "file": "a.txt", "rev": "HEAD"})).await;
+    let lines: Vec<(u64, &str, &str)> = blame["lines"].as_array().unwrap().iter()
+        .map(|l| (l["line"].as_u64().unwrap(), l["text"].as_str().unwrap(), blame["commits"][l["commit"].as_str().unwrap()]["summary"].as_str().unwrap()))
+        .collect();
+    assert_eq!(lines, vec![(1, "one", "first"), (2, "two", "second: a")]);
+    let second_line = call("blame", json!({"path": path, "file": "a.txt", "start_line": 2})).await;
+    assert_eq!((second_line["lines"].as_array().map(Vec::len), second_line["lines"][0]["text"].as_str()), (Some(1), Some("TWO")));
+    assert_eq!(second_line["commits"][second_line["lines"][0]["commit"].as_str().unwrap()]["author"], "Not Committed Yet");
+}
+
+#[tokio::test]
+async fn branches_and_stashes() {
+    let tmp = tempfile::tempdir().unwrap();
+    let path = history(tmp.path()).await;
+
+    let list = call("branch", json!({"path": path})).await;
+    assert_eq!((list["current"].as_str(), list["branches"][0]["name"].as_str(), list["branches"][0]["current"].as_bool()), (Some("main"), Some("main"), Some(true)));
+    let created = call("branch", json!({"path": path, "action": "create", "name": "feature", "start": "HEAD~1"})).await;
+    assert_eq!(created["ok"], true);
+    assert!(mcp_git::call("branch", &json!({"path": path, "action": "create", "name": "feature"})).await.unwrap_err().to_string().contains("already exists"));
+    assert!(mcp_git::call("branch", &json!({"path": path, "action": "create", "name": "a..b"})).await.unwrap_err().to_string().contains("invalid branch name"));
+    assert!(mcp_git::call("branch", &json!({"path": path, "action": "switch", "name": "nope"})).await.is_err());
+    let topic = call("branch", json!({"path": path, "name": "topic"})).await;
+    assert_eq!((topic["action"].as_str(), topic["created"].as_bool()), (Some("switch"), Some(true)));
+    assert_eq!(call("branch", json!({"path": path, "action": "list"})).await["current"], "topic");
+    call("branch", json!({"path": path, "action": "switch", "name": "feature"})).await;
+    assert_eq!(call("status", json!({"path": path})).await["head"], created["commit"]);
+    call("branch", json!({"path": path, "name": "main"})).await;
+    assert_eq!(call("branch", json!({"path": path, "action": "delete", "name": "topic"})).await["ok"], true);
+    let names: Vec<String> = call("branch", json!({"path": path})).await["branches"].as_array().unwrap().iter().map(|b| b["name"].as_str().unwrap().to_string()).collect();
+    assert_eq!(names, vec!["feature", "main"]);
+
+    assert_eq!(call("stash", json!({"path": path, "action": "push"})).await["created"], false);
+    write(tmp.path(), "a.txt", "one\nstashed\n");
+    let pushed = call("stash", json!({"path": path, "action": "push", "message": "wip"})).await;
+    assert_eq!((pushed["created"].as_bool(), pushed["stash"].as_str()), (Some(true), Some("stash@{0}")));
+    assert_eq!(call("status", json!({"path": path})).await["changed"], 0);
+    let list = call("stash", json!({"path": path})).await;
+    assert_eq!((list["stashes"][0]["stash"].as_str(), list["stashes"][0]["message"].as_str()), (Some("stash@{0}"), Some("On main: wip")));
+    let shown = call("stash", json!({"path": path, "action": "show", "stash": 0})).await;
+    assert_eq!((shown["files"][0]["path"].as_str(), shown["additions"].as_u64()), (Some("a.txt"), Some(1)));
+    assert_eq!(call("stash", json!({"path": path, "action": "pop", "stash": "stash@{0}"})).await["ok"], true);
+    assert_eq!(std::fs::read_to_string(tmp.path().join("a.txt")).unwrap(), "one\nstashed\n");
+    assert!(mcp_git::call("stash", &json!({"path": path, "action": "drop"})).await.unwrap_err().to_string().contains("no stash@{0}"));
+}
+
+#[test]
+fn only_changes_are_proposed_to_the_gatekeeper() {
+    let tmp = tempfile::tempdir().unwrap();
+    let real = tmp.path().canonicalize().unwrap().to_string_lossy().to_string();
+    let action = |tool: &str, params: Value| mcp_git::proposed_action(tool, &params);
+    for (tool, params) in [
+        ("status", json!({})),
+        ("log", json!({})),
+        ("diff", json!({"staged": true})),
+        ("branch", json!({})),
+        ("branch", json!({"action": "list"})),
+        ("stash", json!({"action": "show"})),
+    ] {
+        assert_eq!(action(tool, params), None, "{}", tool);
+    }
+    let delete = action("branch", json!({"path": tmp.path(), "action": "delete", "name": "old", "force": true})).unwrap();
+    assert_eq!((delete.command.as_str(), delete.writes, delete.paths.clone()), ("git branch -D old", true, vec![real.clone()]));
+    assert_eq!(action("branch", json!({"name": "topic"})).unwrap().command, "git switch topic");
+    assert_eq!(action("stash", json!({"action": "drop", "stash": 2})).unwrap().command, "git stash drop stash@{2}");
+    assert_eq!(action("add", json!({"patterns": ["src", "README.md"]})).unwrap().command, "git add src README.md");
+    let commit = action("commit", json!({"path": tmp.path(), "message": "fix"})).unwrap();
+    assert_eq!((commit.command.as_str(), commit.intent.as_deref(), commit.paths.clone()), ("git commit", Some("fix"), vec![real]));
+}
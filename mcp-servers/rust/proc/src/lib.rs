@@ -1,17 +1,203 @@
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use serde_json::{json, Value as JsonValue};
-use tokio::fs;
-
-pub async fn list() -> Result<JsonValue> {
-    // List processes by scanning /proc for numeric dirs
-    let mut pids: Vec<i32> = vec![];
-    let mut dir = fs::read_dir("/proc").await?;
-    while let Ok(Some(ent)) = dir.next_entry().await {
-        if let Ok(name) = ent.file_name().into_string() {
-            if let Ok(pid) = name.parse::<i32>() { pids.push(pid); }
-        }
+use std::time::{Duration, Instant};
+
+pub mod procfs;
+
+use foreman_mcp::{Describe, ToolRisk, ToolSpec};
+use procfs::{Clock, Process};
+
+/// Default `limit` of `list`.
+const LIST_LIMIT: usize = 50;
+/// Default CPU sampling window of `list`.
+const SAMPLE_MS: u64 = 200;
+/// Default `timeout_ms` of `watch`.
+const WATCH_TIMEOUT_MS: u64 = 30_000;
+const POLL: Duration = Duration::from_millis(100);
+
+pub fn describe() -> Describe {
+    let tool = |name: &str, description: &str, writes: bool, input_schema: JsonValue| ToolSpec {
+        name: name.into(),
+        description: Some(description.into()),
+        input_schema: Some(input_schema),
+        output_schema: None,
+        risk: ToolRisk { writes, ..Default::default() },
+    };
+    let pid = json!({"type": "integer", "minimum": 1});
+    let expect_start = json!({"type": "integer", "description": "start_time from list; refuses a pid that has since been reused."});
+    Describe { tools: vec![
+        tool("list", "List processes with name, cmdline, user, state, CPU%, RSS and start time. Filter by name, user, state or pids; sort by pid, cpu, rss, start or name.", false, json!({
+            "type": "object",
+            "properties": {
+                "query": {"type": "string", "description": "Substring of the name or cmdline (case-insensitive)."},
+                "user": {"type": "string"},
+                "state": {"type": "string", "description": "running, sleeping, disk sleep, zombie, stopped, idle, ..."},
+                "pids": {"type": "array", "items": {"type": "integer"}},
+                "spawned": {"type": "boolean", "description": "Only processes started by shell.exec (wait=false) or a game launch; resolved by the core."},
+                "sort_by": {"type": "string", "enum": ["pid", "cpu", "rss", "start", "name"]},
+                "desc": {"type": "boolean", "description": "Descending (default for cpu and rss)."},
+                "limit": {"type": "integer", "minimum": 1, "description": "Max processes (default 50)."},
+                "sample_ms": {"type": "integer", "minimum": 0, "maximum": 5000, "description": "CPU% window (default 200); 0 averages since each process started."}
+            },
+            "additionalProperties": false
+        })),
+        tool("kill", "Send a signal to a process (default TERM). A pid not pinned by expect_start needs force.", true, json!({
+            "type": "object",
+            "properties": {
+                "pid": pid,
+                "signal": {"type": "string", "enum": ["TERM", "KILL", "INT", "HUP", "STOP", "CONT", "USR1", "USR2"]},
+                "expect_start": expect_start,
+                "force": {"type": "boolean", "description": "Signal a pid given without expect_start; the caller has approved it."}
+            },
+            "required": ["pid"],
+            "additionalProperties": false
+        })),
+        tool("watch", "Wait until a process exits or the timeout passes; reports which.", false, json!({
+            "type": "object",
+            "properties": {
+                "pid": pid,
+                "timeout_ms": {"type": "integer", "minimum": 0, "maximum": 600000, "description": "Default 30000."},
+                "expect_start": expect_start
+            },
+            "required": ["pid"],
+            "additionalProperties": false
+        })),
+    ]}
+}
+
+pub async fn call(tool: &str, params: &JsonValue) -> Result<JsonValue> {
+    match tool {
+        "list" => list(params).await,
+        "kill" => kill(params),
+        "watch" => watch(params).await,
+        foreman_mcp::DESCRIBE_TOOL => Ok(serde_json::to_value(describe())?),
+        _ => Err(anyhow!("unknown tool")),
     }
-    pids.sort();
-    Ok(json!({ "pids": pids }))
 }
 
+/// `(pid, start_time)` of a running process, for telling it apart from a
+/// later process reusing the pid.
+pub fn identity(pid: u32) -> Option<(u32, i64)> {
+    let clock = Clock::read();
+    let stat = procfs::parse_stat(&std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?)?;
+    (stat.state != 'Z').then(|| (pid, clock.start_time(stat.start_ticks)))
+}
+
+pub async fn list(params: &JsonValue) -> Result<JsonValue> {
+    let query = params.get("query").and_then(|v| v.as_str()).map(str::to_lowercase);
+    let user = params.get("user").and_then(|v| v.as_str());
+    let state = params.get("state").and_then(|v| v.as_str());
+    let pids: Option<Vec<u32>> = params.get("pids").and_then(|v| v.as_array())
+        .map(|a| a.iter().filter_map(|p| p.as_u64()).map(|p| p as u32).collect());
+    let sort_by = params.get("sort_by").and_then(|v| v.as_str()).unwrap_or("pid");
+    let desc = params.get("desc").and_then(|v| v.as_bool()).unwrap_or(matches!(sort_by, "cpu" | "rss"));
+    let limit = params.get("limit").and_then(|v| v.as_u64()).map(|n| n as usize).unwrap_or(LIST_LIMIT);
+    let sample_ms = params.get("sample_ms").and_then(|v| v.as_u64()).unwrap_or(SAMPLE_MS).min(5000);
+    if !matches!(sort_by, "pid" | "cpu" | "rss" | "start" | "name") { bail!("sort_by must be pid, cpu, rss, start or name"); }
+
+    let clock = Clock::read();
+    let users = procfs::users();
+    let candidates = pids.unwrap_or_else(procfs::pids);
+    let read_all = |pids: &[u32]| -> Vec<Process> { pids.iter().filter_map(|p| procfs::read(*p, &clock, &users)).collect() };
+    let mut procs: Vec<Process> = read_all(&candidates).into_iter()
+        .filter(|p| query.as_ref().map(|q| p.name.to_lowercase().contains(q) || p.cmdline.to_lowercase().contains(q)).unwrap_or(true))
+        .filter(|p| user.map(|u| p.user == u || p.uid.to_string() == u).unwrap_or(true))
+        .filter(|p| state.map(|s| p.state.eq_ignore_ascii_case(s)).unwrap_or(true))
+        .collect();
+
+    if sample_ms > 0 && !procs.is_empty() {
+        let before: Vec<(u32, i64, u64)> = procs.iter().map(|p| (p.pid, p.start_time, p.cpu_ticks)).collect();
+        let started = Instant::now();
+        tokio::time::sleep(Duration::from_millis(sample_ms)).await;
+        let secs = started.elapsed().as_secs_f64();
+        let after = read_all(&before.iter().map(|b| b.0).collect::<Vec<_>>());
+        procs.retain_mut(|p| {
+            let Some(now) = after.iter().find(|a| a.pid == p.pid && a.start_time == p.start_time) else { return false };
+            let ticks = now.cpu_ticks.saturating_sub(p.cpu_ticks) as f64;
+            *p = Process { cpu_percent: procfs::round1(ticks / clock.ticks_per_sec as f64 / secs * 100.0), ..now.clone() };
+            true
+        });
+    }
+
+    match sort_by {
+        "cpu" => procs.sort_by(|a, b| a.cpu_percent.total_cmp(&b.cpu_percent)),
+        "rss" => procs.sort_by_key(|p| p.rss_bytes),
+        "start" => procs.sort_by_key(|p| p.start_time),
+        "name" => procs.sort_by_key(|p| p.name.to_lowercase()),
+        _ => procs.sort_by_key(|p| p.pid),
+    }
+    if desc { procs.reverse(); }
+    let total = procs.len();
+    procs.truncate(limit);
+    Ok(json!({ "total": total, "processes": procs }))
+}
+
+fn signal(name: &str) -> Option<libc::c_int> {
+    Some(match name.trim_start_matches("SIG").to_uppercase().as_str() {
+        "TERM" => libc::SIGTERM,
+        "KILL" => libc::SIGKILL,
+        "INT" => libc::SIGINT,
+        "HUP" => libc::SIGHUP,
+        "STOP" => libc::SIGSTOP,
+        "CONT" => libc::SIGCONT,
+        "USR1" => libc::SIGUSR1,
+        "USR2" => libc::SIGUSR2,
+        _ => return None,
+    })
+}
+
+/// The process behind `params.pid`, checked against `expect_start`.
+fn target(params: &JsonValue) -> Result<(u32, Option<Process>)> {
+    let pid = params.get("pid").and_then(|v| v.as_u64()).filter(|p| *p > 0 && *p <= i32::MAX as u64)
+        .ok_or_else(|| anyhow!("missing pid"))? as u32;
+    let found = procfs::read(pid, &Clock::read(), &procfs::users());
+    if let (Some(expect), Some(p)) = (params.get("expect_start").and_then(|v| v.as_i64()), found.as_ref()) {
+        if p.start_time != expect { bail!("pid {} now belongs to another process ({})", pid, p.name); }
+    }
+    Ok((pid, found))
+}
+
+/// The server trusts no caller to know a bare pid: it signals only a process
+/// pinned by `expect_start`, or any other with `force: true`. The core pins the
+/// processes its tools started and passes `force` once the gatekeeper has
+/// approved the call.
+pub fn kill(params: &JsonValue) -> Result<JsonValue> {
+    let (pid, found) = target(params)?;
+    let pinned = params.get("expect_start").and_then(|v| v.as_i64()).is_some();
+    if !pinned && !params.get("force").and_then(|v| v.as_bool()).unwrap_or(false) {
+        bail!("pid {} is not pinned by expect_start; pass force to signal it", pid);
+    }
+    let sig_name = params.get("signal").and_then(|v| v.as_str()).unwrap_or("TERM");
+    let sig = signal(sig_name).ok_or_else(|| anyhow!("unsupported signal {}", sig_name))?;
+    let me = std::process::id();
+    if pid == 1 || pid == me || Some(pid) == procfs::read(me, &Clock::read(), &procfs::users()).map(|p| p.ppid) {
+        bail!("refusing to signal pid {}", pid);
+    }
+    let Some(process) = found else { bail!("no process {}", pid) };
+    // SAFETY: kill has no memory preconditions; pid is a positive i32
+    if unsafe { libc::kill(pid as libc::pid_t, sig) } != 0 {
+        bail!("kill {}: {}", pid, std::io::Error::last_os_error());
+    }
+    Ok(json!({ "pid": pid, "name": process.name, "signal": sig_name.trim_start_matches("SIG").to_uppercase() }))
+}
+
+/// A pid counts as exited once it is gone, a zombie, or reused by a process
+/// with another start time.
+pub async fn watch(params: &JsonValue) -> Result<JsonValue> {
+    let (pid, found) = target(params)?;
+    let timeout = Duration::from_millis(params.get("timeout_ms").and_then(|v| v.as_u64()).unwrap_or(WATCH_TIMEOUT_MS).min(600_000));
+    let start = params.get("expect_start").and_then(|v| v.as_i64()).or(found.as_ref().map(|p| p.start_time));
+    let name = found.as_ref().map(|p| p.name.clone());
+    let started = Instant::now();
+    loop {
+        let exited = match identity(pid) {
+            None => true,
+            Some((_, s)) => start.map(|st| st != s).unwrap_or(false),
+        };
+        let after_ms = started.elapsed().as_millis() as u64;
+        if exited || started.elapsed() >= timeout {
+            return Ok(json!({ "pid": pid, "name": name, "exited": exited, "after_ms": after_ms }));
+        }
+        tokio::time::sleep(POLL.min(timeout.saturating_sub(started.elapsed()))).await;
+    }
+}
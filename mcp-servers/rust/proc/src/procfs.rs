@@ -0,0 +1,201 @@
+//! Process details from `/proc/<pid>/{stat,status,cmdline}`.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Fields of `/proc/<pid>/stat` used here.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stat {
+    pub pid: u32,
+    /// Executable name, at most 15 bytes; may contain spaces and parentheses.
+    pub comm: String,
+    pub state: char,
+    pub ppid: u32,
+    /// utime + stime, in clock ticks.
+    pub cpu_ticks: u64,
+    pub threads: u32,
+    /// Clock ticks after boot.
+    pub start_ticks: u64,
+}
+
+/// `comm` is wrapped in parentheses and can itself contain ") ", so the
+/// fields after it are found from the last ')'.
+pub fn parse_stat(text: &str) -> Option<Stat> {
+    let open = text.find('(')?;
+    let close = text.rfind(')')?;
+    let pid = text[..open].trim().parse().ok()?;
+    let comm = text.get(open + 1..close)?.to_string();
+    // Field 3 (state) is rest[0]; field n is rest[n - 3]
+    let rest: Vec<&str> = text.get(close + 1..)?.split_whitespace().collect();
+    let num = |field: usize| rest.get(field - 3).and_then(|s| s.parse::<u64>().ok());
+    Some(Stat {
+        pid,
+        comm,
+        state: rest.first()?.chars().next()?,
+        ppid: num(4)? as u32,
+        cpu_ticks: num(14)? + num(15)?,
+        threads: num(20)? as u32,
+        start_ticks: num(22)?,
+    })
+}
+
+/// Fields of `/proc/<pid>/status` used here.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Status {
+    /// Real user id.
+    pub uid: u32,
+    /// Resident set in kB; kernel threads have none.
+    pub rss_kb: u64,
+}
+
+pub fn parse_status(text: &str) -> Status {
+    let mut status = Status::default();
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once(':') else { continue };
+        let first = value.split_whitespace().next().and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
+        match key {
+            "Uid" => status.uid = first as u32,
+            "VmRSS" => status.rss_kb = first,
+            _ => {}
+        }
+    }
+    status
+}
+
+pub fn state_name(state: char) -> &'static str {
+    match state {
+        'R' => "running",
+        'S' => "sleeping",
+        'D' => "disk sleep",
+        'Z' => "zombie",
+        'T' => "stopped",
+        't' => "tracing stop",
+        'X' | 'x' => "dead",
+        'I' => "idle",
+        'P' => "parked",
+        'W' => "waking",
+        'K' => "wakekill",
+        _ => "unknown",
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Process {
+    pub pid: u32,
+    pub ppid: u32,
+    pub name: String,
+    /// Arguments joined by spaces; empty for kernel threads.
+    pub cmdline: String,
+    pub user: String,
+    pub uid: u32,
+    pub state: String,
+    /// Share of one CPU, over the sample interval or since start.
+    pub cpu_percent: f64,
+    pub rss_bytes: u64,
+    pub threads: u32,
+    /// Unix seconds; with the pid it identifies the process across pid reuse.
+    pub start_time: i64,
+    #[serde(skip)]
+    pub cpu_ticks: u64,
+}
+
+/// Machine constants needed to turn ticks into times.
+#[derive(Debug, Clone)]
+pub struct Clock {
+    pub ticks_per_sec: u64,
+    /// Unix seconds at boot (`btime` in `/proc/stat`).
+    pub boot_time: i64,
+}
+
+impl Clock {
+    pub fn read() -> Self {
+        // SAFETY: sysconf has no preconditions
+        let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        let boot_time = std::fs::read_to_string("/proc/stat").ok()
+            .and_then(|t| t.lines().find_map(|l| l.strip_prefix("btime ").and_then(|v| v.trim().parse().ok())))
+            .unwrap_or(0);
+        Self { ticks_per_sec: if ticks > 0 { ticks as u64 } else { 100 }, boot_time }
+    }
+
+    pub fn start_time(&self, start_ticks: u64) -> i64 {
+        self.boot_time + (start_ticks / self.ticks_per_sec) as i64
+    }
+}
+
+/// Uid → user name from `/etc/passwd`.
+pub fn users() -> HashMap<u32, String> {
+    std::fs::read_to_string("/etc/passwd").unwrap_or_default().lines()
+        .filter_map(|l| {
+            let mut parts = l.split(':');
+            let name = parts.next()?;
+            let uid = parts.nth(1)?.parse().ok()?;
+            Some((uid, name.to_string()))
+        })
+        .collect()
+}
+
+/// One process, with `cpu_percent` averaged since it started; None when it
+/// is gone (or `/proc` is unreadable).
+pub fn read(pid: u32, clock: &Clock, users: &HashMap<u32, String>) -> Option<Process> {
+    read_in(Path::new("/proc"), pid, clock, users)
+}
+
+pub fn read_in(proc_root: &Path, pid: u32, clock: &Clock, users: &HashMap<u32, String>) -> Option<Process> {
+    let dir = proc_root.join(pid.to_string());
+    let stat = parse_stat(&std::fs::read_to_string(dir.join("stat")).ok()?)?;
+    let status = parse_status(&std::fs::read_to_string(dir.join("status")).unwrap_or_default());
+    let cmdline = std::fs::read(dir.join("cmdline")).unwrap_or_default()
+        .split(|b| *b == 0)
+        .filter(|a| !a.is_empty())
+        .map(|a| String::from_utf8_lossy(a).to_string())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let uptime = std::fs::read_to_string(proc_root.join("uptime")).ok()
+        .and_then(|t| t.split_whitespace().next().and_then(|v| v.parse::<f64>().ok()))
+        .unwrap_or(0.0);
+    let tps = clock.ticks_per_sec as f64;
+    let alive = uptime - stat.start_ticks as f64 / tps;
+    let cpu_percent = if alive > 0.0 { stat.cpu_ticks as f64 / tps / alive * 100.0 } else { 0.0 };
+    Some(Process {
+        pid: stat.pid,
+        ppid: stat.ppid,
+        name: stat.comm,
+        cmdline,
+        user: users.get(&status.uid).cloned().unwrap_or_else(|| status.uid.to_string()),
+        uid: status.uid,
+        state: state_name(stat.state).into(),
+        cpu_percent: round1(cpu_percent),
+        rss_bytes: status.rss_kb * 1024,
+        threads: stat.threads,
+        start_time: clock.start_time(stat.start_ticks),
+        cpu_ticks: stat.cpu_ticks,
+    })
+}
+
+/// Pids under `/proc`, ascending.
+pub fn pids() -> Vec<u32> {
+    let mut pids: Vec<u32> = std::fs::read_dir("/proc").into_iter().flatten().flatten()
+        .filter_map(|e| e.file_name().to_str().and_then(|n| n.parse().ok()))
+        .collect();
+    pids.sort_unstable();
+    pids
+}
+
+pub fn round1(x: f64) -> f64 { (x * 10.0).round() / 10.0 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stat_and_status_parse() {
+        let stat = "4242 (Web (Content)) S 4200 4242 4242 0 -1 4194560 1234 0 0 0 250 50 0 0 20 0 12 0 987654 1048576 2048 18446744073709551615 1 1 0 0 0 0 0 4096 0 0 0 0 17 3 0 0 0 0 0";
+        assert_eq!(parse_stat(stat), Some(Stat { pid: 4242, comm: "Web (Content)".into(), state: 'S', ppid: 4200, cpu_ticks: 300, threads: 12, start_ticks: 987654 }));
+        assert_eq!(parse_stat("12 (truncated) S 1"), None);
+
+        let status = parse_status("Name:\tfirefox\nState:\tS (sleeping)\nUid:\t1000\t1000\t1000\t1000\nVmRSS:\t  204800 kB\nThreads:\t12\n");
+        assert_eq!(status, Status { uid: 1000, rss_kb: 204800 });
+        assert_eq!(parse_status("Name:\tkthreadd\nUid:\t0\t0\t0\t0\n").rss_kb, 0);
+    }
+}
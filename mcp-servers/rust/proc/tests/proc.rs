@@ -0,0 +1,44 @@
+use serde_json::json;
+
+#[tokio::test]
+async fn list_reports_details_filters_and_sorts() {
+    let me = std::process::id();
+    let out = mcp_proc::list(&json!({"pids": [me], "sample_ms": 0})).await.unwrap();
+    assert_eq!(out["total"], 1);
+    let p = &out["processes"][0];
+    assert_eq!(p["pid"], me);
+    assert!(p["rss_bytes"].as_u64().unwrap() > 0);
+    assert!(p["start_time"].as_i64().unwrap() > 1_600_000_000);
+    assert!(!p["user"].as_str().unwrap().is_empty() && !p["cmdline"].as_str().unwrap().is_empty());
+    assert_eq!(mcp_proc::identity(me), Some((me, p["start_time"].as_i64().unwrap())));
+
+    let by_rss = mcp_proc::list(&json!({"sort_by": "rss", "limit": 5, "sample_ms": 0})).await.unwrap();
+    let rss: Vec<u64> = by_rss["processes"].as_array().unwrap().iter().map(|p| p["rss_bytes"].as_u64().unwrap()).collect();
+    assert!(rss.len() <= 5 && rss.windows(2).all(|w| w[0] >= w[1]), "{:?}", rss);
+    assert!(mcp_proc::list(&json!({"sort_by": "mood"})).await.is_err());
+}
+
+#[tokio::test]
+async fn kill_and_watch_a_child() {
+    let mut child = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+    let pid = child.id();
+    let (_, start) = mcp_proc::identity(pid).unwrap();
+    let out = mcp_proc::list(&json!({"query": "sleep", "pids": [pid]})).await.unwrap();
+    assert_eq!(out["processes"][0]["name"], "sleep");
+
+    let out = mcp_proc::watch(&json!({"pid": pid, "timeout_ms": 150})).await.unwrap();
+    assert_eq!((out["exited"].as_bool(), out["name"].as_str()), (Some(false), Some("sleep")));
+    let err = mcp_proc::kill(&json!({"pid": pid, "expect_start": start - 60})).unwrap_err().to_string();
+    assert!(err.contains("another process"), "{}", err);
+    assert!(mcp_proc::kill(&json!({"pid": pid, "signal": "WINCH"})).is_err());
+    assert!(mcp_proc::kill(&json!({"pid": std::process::id(), "force": true})).is_err());
+    let err = mcp_proc::kill(&json!({"pid": pid})).unwrap_err().to_string();
+    assert!(err.contains("force"), "{}", err);
+    assert_eq!(mcp_proc::watch(&json!({"pid": pid, "timeout_ms": 0})).await.unwrap()["exited"], false);
+
+    let out = mcp_proc::kill(&json!({"pid": pid, "signal": "KILL", "expect_start": start})).unwrap();
+    assert_eq!(out["signal"], "KILL");
+    let out = mcp_proc::watch(&json!({"pid": pid, "timeout_ms": 5000})).await.unwrap();
+    assert_eq!(out["exited"], true);
+    child.wait().unwrap();
+}
@@ -2,6 +2,7 @@ use anyhow::{Result, bail};
 use serde::{Deserialize};
 use serde_json::{json, Value as JsonValue};
 use once_cell::sync::OnceCell;
+use foreman_mcp::expand_home;
 use std::path::PathBuf;
 
 pub async fn list_dir(path: &str) -> Result<JsonValue> {
@@ -100,10 +101,14 @@ fn load_shell_rules() -> Vec<ShellRule> {
             }
         }
     }
-    if rules.is_empty() {
-        rules.push(ShellRule { r#match: "mgba-qt".into(), args: Some(ShellArgsRule { count: Some(1), min_count: None, max_count: None, path_prefixes: Some(vec!["/home/kil/games/roms".into()]), starts_with_tokens: None, digits_at: None }) });
-        rules.push(ShellRule { r#match: "/home/kil/games/emulators/melonDS-x86_64.AppImage".into(), args: Some(ShellArgsRule { count: Some(0), min_count: None, max_count: None, path_prefixes: None, starts_with_tokens: None, digits_at: None }) });
-        rules.push(ShellRule { r#match: "melonDS-x86_64.AppImage".into(), args: Some(ShellArgsRule { count: Some(0), min_count: None, max_count: None, path_prefixes: None, starts_with_tokens: None, digits_at: None }) });
+    // Emulators and Steam: whatever the games registry launches
+    rules.extend(mcp_games::GamesConfig::load().shell_allowlist().into_iter().filter_map(|r| serde_json::from_value(r).ok()));
+    // `~/` in commands and path prefixes is the user's home
+    for r in rules.iter_mut() {
+        r.r#match = expand_home(&r.r#match).to_string_lossy().to_string();
+        if let Some(prefixes) = r.args.as_mut().and_then(|a| a.path_prefixes.as_mut()) {
+            for p in prefixes.iter_mut() { *p = expand_home(p).to_string_lossy().to_string(); }
+        }
     }
     let _ = SHELL_RULES.set(rules.clone());
     rules
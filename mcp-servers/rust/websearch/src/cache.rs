@@ -0,0 +1,79 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Metadata stored next to each cached page (`meta.json`).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PageMeta {
+    pub url: String,
+    pub final_url: String,
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub fetched_at: DateTime<Utc>,
+    pub bytes: usize,
+    pub text_chars: usize,
+}
+
+impl PageMeta {
+    pub fn is_fresh(&self, ttl: Duration) -> bool {
+        let age = Utc::now().signed_duration_since(self.fetched_at);
+        age.to_std().map(|a| a <= ttl).unwrap_or(true)
+    }
+}
+
+/// On-disk page cache: `<root>/<sha256(url)[..32]>/{meta.json,page.html,text.txt}`.
+#[derive(Debug, Clone)]
+pub struct WebCache {
+    root: PathBuf,
+}
+
+impl WebCache {
+    pub fn new(root: &Path) -> Self { Self { root: root.to_path_buf() } }
+
+    pub fn root(&self) -> &Path { &self.root }
+
+    pub fn key(url: &str) -> String {
+        let digest = Sha256::digest(url.as_bytes());
+        hex::encode(digest)[..32].to_string()
+    }
+
+    pub fn dir_for(&self, url: &str) -> PathBuf { self.root.join(Self::key(url)) }
+
+    pub async fn get(&self, url: &str) -> Option<PageMeta> {
+        let text = tokio::fs::read_to_string(self.dir_for(url).join("meta.json")).await.ok()?;
+        serde_json::from_str(&text).ok()
+    }
+
+    pub async fn read_text(&self, url: &str) -> Option<String> {
+        tokio::fs::read_to_string(self.dir_for(url).join("text.txt")).await.ok()
+    }
+
+    pub async fn put(&self, meta: &PageMeta, html: &str, text: &str) -> Result<PathBuf> {
+        let dir = self.dir_for(&meta.url);
+        tokio::fs::create_dir_all(&dir).await?;
+        if !html.is_empty() { tokio::fs::write(dir.join("page.html"), html).await?; }
+        tokio::fs::write(dir.join("text.txt"), text).await?;
+        // meta.json last so a partially written entry is never considered cached
+        tokio::fs::write(dir.join("meta.json"), serde_json::to_vec_pretty(meta)?).await?;
+        Ok(dir)
+    }
+
+    /// All cached pages with their readable text (used by the local index engine).
+    pub async fn entries(&self) -> Vec<(PageMeta, String)> {
+        let mut out = vec![];
+        let Ok(mut rd) = tokio::fs::read_dir(&self.root).await else { return out; };
+        while let Ok(Some(ent)) = rd.next_entry().await {
+            let dir = ent.path();
+            let Ok(meta_text) = tokio::fs::read_to_string(dir.join("meta.json")).await else { continue; };
+            let Ok(meta) = serde_json::from_str::<PageMeta>(&meta_text) else { continue; };
+            let text = tokio::fs::read_to_string(dir.join("text.txt")).await.unwrap_or_default();
+            out.push((meta, text));
+        }
+        out
+    }
+}
@@ -0,0 +1,174 @@
+use crate::cache::WebCache;
+use crate::WebsearchConfig;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SearchHit {
+    pub title: String,
+    pub url: String,
+    pub snippet: String,
+    pub engine: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published: Option<String>,
+}
+
+#[async_trait]
+pub trait SearchEngine: Send + Sync {
+    fn name(&self) -> &'static str;
+    async fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>>;
+}
+
+/// Instantiate engines in configured order; unknown names are skipped.
+pub fn build_engines(cfg: &WebsearchConfig, cache: &WebCache) -> Vec<Box<dyn SearchEngine>> {
+    let mut out: Vec<Box<dyn SearchEngine>> = vec![];
+    for name in cfg.engines.iter() {
+        match name.as_str() {
+            "searxng" => out.push(Box::new(SearxngEngine::new(&cfg.searxng_url, cfg.timeout, cfg.retries, cfg.backoff))),
+            "local" => out.push(Box::new(LocalIndexEngine::new(cache.clone()))),
+            other => eprintln!("[mcp-websearch] unknown engine '{}' ignored", other),
+        }
+    }
+    out
+}
+
+// --- SearXNG-compatible JSON endpoint (`/search?q=..&format=json`) ---
+
+pub struct SearxngEngine {
+    http: Client,
+    base: String,
+    retries: u32,
+    backoff: Duration,
+}
+
+#[derive(Deserialize)]
+struct SearxResp { #[serde(default)] results: Vec<SearxResult> }
+
+#[derive(Deserialize)]
+struct SearxResult {
+    #[serde(default)] url: String,
+    #[serde(default)] title: String,
+    #[serde(default)] content: Option<String>,
+    #[serde(default, rename = "publishedDate")] published_date: Option<String>,
+}
+
+impl SearxngEngine {
+    pub fn new(base: &str, timeout: Duration, retries: u32, backoff: Duration) -> Self {
+        let http = Client::builder()
+            .user_agent(crate::USER_AGENT)
+            .timeout(timeout)
+            .build()
+            .expect("reqwest client");
+        Self { http, base: base.trim_end_matches('/').to_string(), retries, backoff }
+    }
+}
+
+#[async_trait]
+impl SearchEngine for SearxngEngine {
+    fn name(&self) -> &'static str { "searxng" }
+
+    async fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>> {
+        let endpoint = format!("{}/search", self.base);
+        let mut attempt = 0u32;
+        let resp = loop {
+            let resp = self.http
+                .get(&endpoint)
+                .query(&[("q", query), ("format", "json"), ("pageno", "1")])
+                .send()
+                .await?;
+            let status = resp.status();
+            let retryable = status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error();
+            if retryable && attempt < self.retries {
+                tokio::time::sleep(self.backoff * 2u32.pow(attempt)).await;
+                attempt += 1;
+                continue;
+            }
+            if !status.is_success() { return Err(anyhow!("searxng HTTP {}", status)); }
+            break resp;
+        };
+        let body: SearxResp = resp.json().await.map_err(|e| anyhow!("searxng bad JSON: {}", e))?;
+        let hits = body
+            .results
+            .into_iter()
+            .filter_map(|r| {
+                let url = crate::fetch::sanitize_url(&r.url).ok()?;
+                Some(SearchHit {
+                    title: r.title.trim().to_string(),
+                    url: url.to_string(),
+                    snippet: r.content.unwrap_or_default().trim().to_string(),
+                    engine: "searxng".into(),
+                    published: r.published_date,
+                })
+            })
+            .take(limit)
+            .collect();
+        Ok(hits)
+    }
+}
+
+// --- Local index over pages previously fetched into the cache ---
+
+pub struct LocalIndexEngine {
+    cache: WebCache,
+}
+
+impl LocalIndexEngine {
+    pub fn new(cache: WebCache) -> Self { Self { cache } }
+}
+
+fn terms(query: &str) -> Vec<String> {
+    query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| t.len() > 1)
+        .map(|t| t.to_lowercase())
+        .collect()
+}
+
+fn snippet_around(text: &str, terms: &[String], width: usize) -> String {
+    let lower = text.to_lowercase();
+    let hit = terms.iter().filter_map(|t| lower.find(t.as_str())).min().unwrap_or(0);
+    // Lowercasing can shift byte offsets for non-ASCII text; clamp to char boundaries
+    let mut start = hit.saturating_sub(width / 2).min(text.len());
+    while !text.is_char_boundary(start) { start -= 1; }
+    let mut end = (start + width).min(text.len());
+    while !text.is_char_boundary(end) { end -= 1; }
+    let mut s = text[start..end].split_whitespace().collect::<Vec<_>>().join(" ");
+    if start > 0 { s.insert(0, '…'); }
+    if end < text.len() { s.push('…'); }
+    s
+}
+
+#[async_trait]
+impl SearchEngine for LocalIndexEngine {
+    fn name(&self) -> &'static str { "local" }
+
+    async fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>> {
+        let qterms = terms(query);
+        if qterms.is_empty() { return Ok(vec![]); }
+        let mut scored: Vec<(usize, crate::cache::PageMeta, String)> = vec![];
+        for (meta, text) in self.cache.entries().await {
+            let title = meta.title.clone().unwrap_or_default().to_lowercase();
+            let body = text.to_lowercase();
+            let score: usize = qterms
+                .iter()
+                .map(|t| title.matches(t.as_str()).count() * 3 + body.matches(t.as_str()).count())
+                .sum();
+            if score > 0 { scored.push((score, meta, text)); }
+        }
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.fetched_at.cmp(&a.1.fetched_at)));
+        Ok(scored
+            .into_iter()
+            .take(limit)
+            .map(|(_, meta, text)| SearchHit {
+                title: meta.title.clone().unwrap_or_else(|| meta.url.clone()),
+                url: meta.url.clone(),
+                snippet: snippet_around(&text, &qterms, 200),
+                engine: "local".into(),
+                published: Some(meta.fetched_at.to_rfc3339()),
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,171 @@
+use crate::robots::RobotsRules;
+use crate::WebsearchConfig;
+use anyhow::{anyhow, bail, Result};
+use reqwest::header::{CONTENT_TYPE, LOCATION};
+use reqwest::Client;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use url::Url;
+
+/// Redirects followed before a fetch gives up.
+const MAX_REDIRECTS: usize = 5;
+
+/// Accept only absolute http(s) URLs without credentials; drop the fragment.
+pub fn sanitize_url(raw: &str) -> Result<Url> {
+    let raw = raw.trim();
+    if raw.is_empty() { bail!("url required"); }
+    let mut url = Url::parse(raw).map_err(|e| anyhow!("invalid url '{}': {}", raw, e))?;
+    if !matches!(url.scheme(), "http" | "https") { bail!("unsupported scheme: {}", url.scheme()); }
+    if url.host_str().map(|h| h.is_empty()).unwrap_or(true) { bail!("url has no host"); }
+    if !url.username().is_empty() || url.password().is_some() { bail!("credentials in url are not allowed"); }
+    url.set_fragment(None);
+    Ok(url)
+}
+
+#[derive(Debug, Clone)]
+pub struct FetchedPage {
+    pub final_url: String,
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: String,
+}
+
+impl FetchedPage {
+    pub fn is_html(&self) -> bool {
+        self.content_type.as_deref().map(|c| c.contains("html")).unwrap_or(true)
+    }
+}
+
+pub struct Fetcher {
+    http: Client,
+    /// robots.txt may redirect (http to https, another host); RFC 9309 says to follow.
+    robots_http: Client,
+    min_interval: Duration,
+    max_bytes: usize,
+    robots: Mutex<HashMap<String, RobotsRules>>,
+    last_hit: Mutex<HashMap<String, Instant>>,
+}
+
+fn origin_key(url: &Url) -> String {
+    format!("{}://{}", url.scheme(), url.host_str().unwrap_or("")) + &url.port().map(|p| format!(":{}", p)).unwrap_or_default()
+}
+
+impl Fetcher {
+    pub fn new(cfg: &WebsearchConfig) -> Self {
+        let client = |redirect| Client::builder()
+            .user_agent(crate::USER_AGENT)
+            .timeout(cfg.timeout)
+            .redirect(redirect)
+            .build()
+            .expect("reqwest client");
+        Self {
+            // Page redirects are followed by hand so every hop passes the same checks
+            http: client(reqwest::redirect::Policy::none()),
+            robots_http: client(reqwest::redirect::Policy::limited(MAX_REDIRECTS)),
+            min_interval: cfg.min_interval,
+            max_bytes: cfg.max_bytes,
+            robots: Mutex::new(HashMap::new()),
+            last_hit: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Check robots.txt for the URL's origin (fetched once per origin and
+    /// memoized). While robots.txt is unreachable the origin is disallowed, but
+    /// the failure isn't memoized, so the next call asks again.
+    pub async fn allowed(&self, url: &Url) -> Result<bool> {
+        let origin = origin_key(url);
+        let cached = self.robots.lock().await.get(&origin).cloned();
+        let rules = match cached {
+            Some(r) => r,
+            None => match self.load_robots(url).await {
+                Ok(rules) => {
+                    self.robots.lock().await.insert(origin, rules.clone());
+                    rules
+                }
+                Err(e) => {
+                    eprintln!("[mcp-websearch] robots.txt for {} unavailable ({}); disallowing for now", origin, e);
+                    RobotsRules::disallow_all()
+                }
+            },
+        };
+        let mut path = url.path().to_string();
+        if let Some(q) = url.query() { path.push('?'); path.push_str(q); }
+        Ok(rules.allows(&path))
+    }
+
+    /// Rules from the origin's robots.txt; an error when it is unreachable or
+    /// answers 5xx.
+    async fn load_robots(&self, url: &Url) -> Result<RobotsRules> {
+        let robots_url = format!("{}/robots.txt", origin_key(url));
+        self.throttle(url).await;
+        let resp = self.robots_http.get(&robots_url).send().await?;
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(RobotsRules::parse(&resp.text().await?, crate::USER_AGENT));
+        }
+        // No robots.txt (4xx) means no restrictions
+        if status.is_client_error() { return Ok(RobotsRules::allow_all()); }
+        bail!("{} answered HTTP {}", robots_url, status)
+    }
+
+    /// Per-origin politeness delay.
+    async fn throttle(&self, url: &Url) {
+        if self.min_interval.is_zero() { return; }
+        let origin = origin_key(url);
+        let wait = {
+            let mut g = self.last_hit.lock().await;
+            let now = Instant::now();
+            let wait = g.get(&origin).map(|t| self.min_interval.saturating_sub(now.duration_since(*t))).unwrap_or_default();
+            g.insert(origin, now + wait);
+            wait
+        };
+        if !wait.is_zero() { tokio::time::sleep(wait).await; }
+    }
+
+    /// Fetch `url`, following redirects. Each hop must be a URL `sanitize_url`
+    /// accepts and allowed by its own origin's robots.txt.
+    pub async fn get(&self, url: &Url) -> Result<FetchedPage> {
+        let mut url = url.clone();
+        let mut hops = 0;
+        let mut resp = loop {
+            if !self.allowed(&url).await? { bail!("blocked by robots.txt: {}", url); }
+            self.throttle(&url).await;
+            let resp = self.http.get(url.as_str()).send().await?;
+            if !resp.status().is_redirection() { break resp; }
+            let Some(location) = resp.headers().get(LOCATION).and_then(|v| v.to_str().ok()) else { break resp };
+            hops += 1;
+            if hops > MAX_REDIRECTS { bail!("fetch {} failed: more than {} redirects", url, MAX_REDIRECTS); }
+            let next = url.join(location).map_err(|e| anyhow!("bad redirect from {}: {}", url, e))?;
+            url = sanitize_url(next.as_str()).map_err(|e| anyhow!("redirect from {} refused: {}", url, e))?;
+        };
+        let status = resp.status();
+        if !status.is_success() { bail!("fetch {} failed: HTTP {}", url, status); }
+        let content_type = resp.headers().get(CONTENT_TYPE).and_then(|v| v.to_str().ok()).map(|s| s.to_lowercase());
+        if let Some(ct) = content_type.as_deref() {
+            if !(ct.contains("html") || ct.starts_with("text/")) { bail!("unsupported content-type: {}", ct); }
+        }
+        let final_url = resp.url().to_string();
+        let mut buf: Vec<u8> = vec![];
+        while let Some(chunk) = resp.chunk().await? {
+            buf.extend_from_slice(&chunk);
+            if buf.len() >= self.max_bytes { buf.truncate(self.max_bytes); break; }
+        }
+        Ok(FetchedPage { final_url, status: status.as_u16(), content_type, body: String::from_utf8_lossy(&buf).to_string() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_rejects_unsafe_urls() {
+        assert!(sanitize_url("javascript:alert(1)").is_err());
+        assert!(sanitize_url("file:///etc/passwd").is_err());
+        assert!(sanitize_url("https://user:pw@example.com/").is_err());
+        assert!(sanitize_url("not a url").is_err());
+        let u = sanitize_url(" https://Example.com/a#frag ").unwrap();
+        assert_eq!(u.as_str(), "https://example.com/a");
+    }
+}
@@ -0,0 +1,206 @@
+use anyhow::{bail, Result};
+use serde_json::{json, Value as JsonValue};
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub mod cache;
+pub mod engine;
+pub mod fetch;
+pub mod readable;
+pub mod robots;
+
+use cache::{PageMeta, WebCache};
+use foreman_mcp::{Describe, ToolRisk, ToolSpec};
+use engine::SearchEngine;
+use fetch::Fetcher;
+
+pub const USER_AGENT: &str = "foreman-websearch/0.1";
+
+#[derive(Debug, Clone)]
+pub struct WebsearchConfig {
+    /// Engines tried in order; the first one returning hits wins.
+    pub engines: Vec<String>,
+    /// Base URL of a SearXNG-compatible instance (JSON output must be enabled).
+    pub searxng_url: String,
+    /// Where fetched pages are cached (sanitized HTML, readable text, metadata).
+    pub cache_dir: PathBuf,
+    pub cache_ttl: Duration,
+    /// Minimum delay between two requests to the same host.
+    pub min_interval: Duration,
+    pub max_bytes: usize,
+    pub timeout: Duration,
+    /// Retries for engines on 429/5xx, with exponential backoff starting at `backoff`.
+    pub retries: u32,
+    pub backoff: Duration,
+}
+
+impl Default for WebsearchConfig {
+    fn default() -> Self {
+        Self {
+            engines: vec!["searxng".into(), "local".into()],
+            searxng_url: "http://127.0.0.1:8888".into(),
+            cache_dir: PathBuf::from("storage/artifacts/web"),
+            cache_ttl: Duration::from_secs(24 * 3600),
+            min_interval: Duration::from_millis(1000),
+            max_bytes: 2 * 1024 * 1024,
+            timeout: Duration::from_secs(15),
+            retries: 2,
+            backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+impl WebsearchConfig {
+    /// Defaults overridden by `WEBSEARCH_ENGINES` (comma list), `SEARXNG_URL`, `WEBSEARCH_CACHE_DIR`,
+    /// `WEBSEARCH_CACHE_TTL_SECS` and `WEBSEARCH_MIN_INTERVAL_MS`.
+    pub fn from_env() -> Self {
+        let mut cfg = Self::default();
+        if let Ok(v) = std::env::var("WEBSEARCH_ENGINES") {
+            let engines: Vec<String> = v.split(',').map(|s| s.trim().to_lowercase()).filter(|s| !s.is_empty()).collect();
+            if !engines.is_empty() { cfg.engines = engines; }
+        }
+        if let Ok(v) = std::env::var("SEARXNG_URL") { cfg.searxng_url = v; }
+        if let Ok(v) = std::env::var("WEBSEARCH_CACHE_DIR") { cfg.cache_dir = PathBuf::from(v); }
+        if let Some(n) = std::env::var("WEBSEARCH_CACHE_TTL_SECS").ok().and_then(|s| s.parse::<u64>().ok()) { cfg.cache_ttl = Duration::from_secs(n); }
+        if let Some(n) = std::env::var("WEBSEARCH_MIN_INTERVAL_MS").ok().and_then(|s| s.parse::<u64>().ok()) { cfg.min_interval = Duration::from_millis(n); }
+        cfg
+    }
+}
+
+pub struct Websearch {
+    engines: Vec<Box<dyn SearchEngine>>,
+    fetcher: Fetcher,
+    cache: WebCache,
+    cache_ttl: Duration,
+}
+
+impl Websearch {
+    pub fn new(cfg: WebsearchConfig) -> Self {
+        let cache = WebCache::new(&cfg.cache_dir);
+        let engines = engine::build_engines(&cfg, &cache);
+        let fetcher = Fetcher::new(&cfg);
+        Self { engines, fetcher, cache, cache_ttl: cfg.cache_ttl }
+    }
+
+    pub fn cache(&self) -> &WebCache { &self.cache }
+
+    /// Answer to the `describe` handshake: the tools below with their schemas.
+    pub fn describe() -> Describe {
+        let url = json!({"type": "string", "description": "http(s) URL"});
+        let tool = |name: &str, description: &str, input_schema: JsonValue| ToolSpec {
+            name: name.into(),
+            description: Some(description.into()),
+            input_schema: Some(input_schema),
+            output_schema: None,
+            risk: ToolRisk { network: true, ..Default::default() },
+        };
+        Describe { tools: vec![
+            tool("search", "Search the web. Returns URL, title and snippet per hit from the first engine that answers.", json!({
+                "type": "object",
+                "properties": {
+                    "query": {"type": "string", "minLength": 1, "description": "Search query."},
+                    "limit": {"type": "integer", "minimum": 1, "maximum": 50, "description": "Max hits (default 10)."},
+                    "engine": {"type": "string", "description": "Only use this engine (searxng or local)."}
+                },
+                "required": ["query"],
+                "additionalProperties": false
+            })),
+            tool("fetch", "Fetch a page (robots-aware, cached) and return its readable text.", json!({
+                "type": "object",
+                "properties": {
+                    "url": url,
+                    "refresh": {"type": "boolean", "description": "Bypass the cache."},
+                    "max_chars": {"type": "integer", "minimum": 1, "description": "Truncate text (default 20000)."}
+                },
+                "required": ["url"],
+                "additionalProperties": false
+            })),
+            tool("metadata", "Title, description and fetch info of a page, from cache when fresh.", json!({
+                "type": "object",
+                "properties": {"url": url},
+                "required": ["url"],
+                "additionalProperties": false
+            })),
+        ]}
+    }
+
+    // --- Public tool handlers ---
+
+    pub async fn search(&self, params: &JsonValue) -> Result<JsonValue> {
+        let query = params.get("query").and_then(|v| v.as_str()).unwrap_or("").trim();
+        if query.is_empty() { bail!("query required"); }
+        let limit = params.get("limit").and_then(|v| v.as_u64()).unwrap_or(10).clamp(1, 50) as usize;
+        let only = params.get("engine").and_then(|v| v.as_str()).map(|s| s.to_lowercase());
+        let mut errors: Vec<String> = vec![];
+        let mut tried = 0usize;
+        for e in self.engines.iter() {
+            if only.as_deref().map(|o| o != e.name()).unwrap_or(false) { continue; }
+            tried += 1;
+            match e.search(query, limit).await {
+                Ok(hits) if !hits.is_empty() => {
+                    return Ok(json!({"query": query, "engine": e.name(), "results": hits}));
+                }
+                Ok(_) => {}
+                Err(err) => errors.push(format!("{}: {}", e.name(), err)),
+            }
+        }
+        if tried == 0 { bail!("no engine configured{}", only.map(|o| format!(" named '{}'", o)).unwrap_or_default()); }
+        if !errors.is_empty() && errors.len() == tried { bail!("all engines failed: {}", errors.join("; ")); }
+        Ok(json!({"query": query, "engine": null, "results": [], "errors": errors}))
+    }
+
+    pub async fn fetch(&self, params: &JsonValue) -> Result<JsonValue> {
+        let raw = params.get("url").and_then(|v| v.as_str()).unwrap_or("");
+        let refresh = params.get("refresh").and_then(|v| v.as_bool()).unwrap_or(false);
+        let max_chars = params.get("max_chars").and_then(|v| v.as_u64()).unwrap_or(20_000) as usize;
+        let (meta, cached) = self.fetch_page(raw, refresh).await?;
+        let text = self.cache.read_text(&meta.url).await.unwrap_or_default();
+        let truncated = text.chars().count() > max_chars;
+        let text: String = if truncated { text.chars().take(max_chars).collect() } else { text };
+        Ok(json!({
+            "url": meta.url,
+            "final_url": meta.final_url,
+            "title": meta.title,
+            "description": meta.description,
+            "fetched_at": meta.fetched_at,
+            "cached": cached,
+            "path": self.cache.dir_for(&meta.url).to_string_lossy(),
+            "text": text,
+            "truncated": truncated,
+        }))
+    }
+
+    pub async fn metadata(&self, params: &JsonValue) -> Result<JsonValue> {
+        let raw = params.get("url").and_then(|v| v.as_str()).unwrap_or("");
+        let (meta, cached) = self.fetch_page(raw, false).await?;
+        let mut v = serde_json::to_value(&meta)?;
+        if let Some(obj) = v.as_object_mut() { obj.insert("cached".into(), json!(cached)); }
+        Ok(v)
+    }
+
+    /// Returns cached metadata when fresh; otherwise fetches (robots permitting) and caches.
+    async fn fetch_page(&self, raw: &str, refresh: bool) -> Result<(PageMeta, bool)> {
+        let url = fetch::sanitize_url(raw)?;
+        if !refresh {
+            if let Some(meta) = self.cache.get(url.as_str()).await {
+                if meta.is_fresh(self.cache_ttl) { return Ok((meta, true)); }
+            }
+        }
+        let page = self.fetcher.get(&url).await?;
+        let doc = if page.is_html() { readable::extract(&page.body) } else { readable::Readable { title: None, description: None, text: page.body.clone() } };
+        let html = if page.is_html() { readable::sanitize_html(&page.body) } else { String::new() };
+        let meta = PageMeta {
+            url: url.to_string(),
+            final_url: page.final_url.clone(),
+            status: page.status,
+            content_type: page.content_type.clone(),
+            title: doc.title.clone(),
+            description: doc.description.clone(),
+            fetched_at: chrono::Utc::now(),
+            bytes: page.body.len(),
+            text_chars: doc.text.chars().count(),
+        };
+        self.cache.put(&meta, &html, &doc.text).await?;
+        Ok((meta, false))
+    }
+}
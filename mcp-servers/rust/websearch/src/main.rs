@@ -0,0 +1,32 @@
+use foreman_mcp::{ToolRequest, ToolResponse};
+use mcp_websearch::{Websearch, WebsearchConfig};
+use std::io::{self, BufRead, Write};
+
+#[tokio::main]
+async fn main() {
+    let ws = Websearch::new(WebsearchConfig::from_env());
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    let mut lines = stdin.lock().lines();
+    while let Some(Ok(line)) = lines.next() {
+        if line.trim().is_empty() { continue; }
+        let req: Result<ToolRequest, _> = serde_json::from_str(&line);
+        let resp = match req {
+            Ok(r) => handle(&ws, r).await,
+            Err(e) => ToolResponse::err(format!("bad request: {}", e)),
+        };
+        let _ = writeln!(stdout, "{}", serde_json::to_string(&resp).unwrap());
+        let _ = stdout.flush();
+    }
+}
+
+async fn handle(ws: &Websearch, req: ToolRequest) -> ToolResponse {
+    let res = match req.tool.as_str() {
+        "search" => ws.search(&req.params).await,
+        "fetch" => ws.fetch(&req.params).await,
+        "metadata" => ws.metadata(&req.params).await,
+        foreman_mcp::DESCRIBE_TOOL => Ok(serde_json::to_value(Websearch::describe()).unwrap_or_default()),
+        _ => Err(anyhow::anyhow!("unknown tool")),
+    };
+    match res { Ok(v) => ToolResponse::ok(v), Err(e) => ToolResponse::err(e.to_string()) }
+}
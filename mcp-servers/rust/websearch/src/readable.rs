@@ -0,0 +1,296 @@
+//! Lightweight HTML handling: readable-text extraction and sanitization.
+//! Not a full HTML parser; good enough for article-like pages (no JS rendering).
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Readable {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub text: String,
+}
+
+/// Elements whose content never contributes readable text.
+const SKIP_TEXT: &[&str] = &[
+    "script", "style", "noscript", "template", "svg", "iframe", "object", "embed", "nav", "header", "footer", "aside", "form", "button", "select",
+];
+/// Elements removed from the cached HTML entirely.
+const SKIP_HTML: &[&str] = &["script", "noscript", "template", "iframe", "object", "embed", "frame", "frameset"];
+const BLOCK: &[&str] = &[
+    "p", "div", "br", "li", "ul", "ol", "h1", "h2", "h3", "h4", "h5", "h6", "tr", "table",
+    "section", "article", "main", "pre", "blockquote", "dd", "dt", "hr", "figcaption",
+];
+
+enum Token<'a> {
+    Text(&'a str),
+    Tag { name: String, closing: bool, raw: &'a str, attrs: Vec<(String, String)> },
+    Comment,
+}
+
+struct Tokenizer<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn new(src: &'a str) -> Self { Self { src, pos: 0 } }
+
+    /// Skip past the closing tag of a raw-text element such as `<script>`.
+    fn skip_until_close(&mut self, name: &str) {
+        let rest = &self.src[self.pos..];
+        let lower = rest.to_ascii_lowercase();
+        let needle = format!("</{}", name);
+        match lower.find(&needle) {
+            Some(i) => {
+                let after = self.pos + i;
+                self.pos = self.src[after..].find('>').map(|j| after + j + 1).unwrap_or(self.src.len());
+            }
+            None => self.pos = self.src.len(),
+        }
+    }
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        if self.pos >= self.src.len() { return None; }
+        let rest = &self.src[self.pos..];
+        if !rest.starts_with('<') {
+            let end = rest.find('<').unwrap_or(rest.len());
+            self.pos += end;
+            return Some(Token::Text(&rest[..end]));
+        }
+        if rest.starts_with("<!--") {
+            self.pos += rest.find("-->").map(|i| i + 3).unwrap_or(rest.len());
+            return Some(Token::Comment);
+        }
+        // Find the end of the tag, honouring quoted attribute values
+        let bytes = rest.as_bytes();
+        let mut quote: Option<u8> = None;
+        let mut end = None;
+        for (i, &b) in bytes.iter().enumerate().skip(1) {
+            match quote {
+                Some(q) if b == q => quote = None,
+                Some(_) => {}
+                None if b == b'"' || b == b'\'' => quote = Some(b),
+                None if b == b'>' => { end = Some(i); break; }
+                None => {}
+            }
+        }
+        let Some(end) = end else {
+            // Unterminated '<': treat the remainder as text
+            self.pos = self.src.len();
+            return Some(Token::Text(rest));
+        };
+        self.pos += end + 1;
+        let raw = &rest[..=end];
+        let inner = rest[1..end].trim();
+        if inner.starts_with('!') || inner.starts_with('?') { return Some(Token::Comment); }
+        let closing = inner.starts_with('/');
+        let inner = inner.trim_start_matches('/');
+        let name_end = inner.find(|c: char| c.is_whitespace() || c == '/').unwrap_or(inner.len());
+        let name = inner[..name_end].to_ascii_lowercase();
+        let attrs = if closing { vec![] } else { parse_attrs(&inner[name_end..]) };
+        Some(Token::Tag { name, closing, raw, attrs })
+    }
+}
+
+fn parse_attrs(s: &str) -> Vec<(String, String)> {
+    let mut out = vec![];
+    let chars: Vec<char> = s.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        while i < chars.len() && (chars[i].is_whitespace() || chars[i] == '/') { i += 1; }
+        let start = i;
+        while i < chars.len() && !chars[i].is_whitespace() && chars[i] != '=' && chars[i] != '/' { i += 1; }
+        if start == i { break; }
+        let key: String = chars[start..i].iter().collect::<String>().to_ascii_lowercase();
+        while i < chars.len() && chars[i].is_whitespace() { i += 1; }
+        let mut value = String::new();
+        if i < chars.len() && chars[i] == '=' {
+            i += 1;
+            while i < chars.len() && chars[i].is_whitespace() { i += 1; }
+            if i < chars.len() && (chars[i] == '"' || chars[i] == '\'') {
+                let q = chars[i];
+                i += 1;
+                let vs = i;
+                while i < chars.len() && chars[i] != q { i += 1; }
+                value = chars[vs..i].iter().collect();
+                i += 1;
+            } else {
+                let vs = i;
+                while i < chars.len() && !chars[i].is_whitespace() { i += 1; }
+                value = chars[vs..i].iter().collect();
+            }
+        }
+        out.push((key, decode_entities(&value)));
+    }
+    out
+}
+
+pub fn decode_entities(s: &str) -> String {
+    if !s.contains('&') { return s.to_string(); }
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let semi = rest.find(';').filter(|&j| j <= 10);
+        let decoded = semi.and_then(|j| {
+            let ent = &rest[1..j];
+            let c = match ent {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                "mdash" => Some('—'),
+                "ndash" => Some('–'),
+                "hellip" => Some('…'),
+                _ if ent.starts_with("#x") || ent.starts_with("#X") => u32::from_str_radix(&ent[2..], 16).ok().and_then(char::from_u32),
+                _ if ent.starts_with('#') => ent[1..].parse::<u32>().ok().and_then(char::from_u32),
+                _ => None,
+            };
+            c.map(|c| (c, j))
+        });
+        match decoded {
+            Some((c, j)) => { out.push(c); rest = &rest[j + 1..]; }
+            None => { out.push('&'); rest = &rest[1..]; }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Collapse runs of spaces within lines and limit blank lines to one.
+fn normalize_text(s: &str) -> String {
+    let mut lines: Vec<String> = vec![];
+    let mut blank = false;
+    for line in s.lines() {
+        let l = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if l.is_empty() {
+            if !blank && !lines.is_empty() { lines.push(String::new()); }
+            blank = true;
+        } else {
+            lines.push(l);
+            blank = false;
+        }
+    }
+    while lines.last().map(|l| l.is_empty()).unwrap_or(false) { lines.pop(); }
+    lines.join("\n")
+}
+
+pub fn extract(html: &str) -> Readable {
+    let mut out = Readable::default();
+    let mut text = String::new();
+    let mut tok = Tokenizer::new(html);
+    let mut in_title = false;
+    let mut title = String::new();
+    while let Some(t) = tok.next() {
+        match t {
+            Token::Text(s) => {
+                if in_title { title.push_str(s); } else { text.push_str(&decode_entities(s)); }
+            }
+            Token::Tag { name, closing, attrs, .. } => {
+                if name == "title" {
+                    in_title = !closing;
+                    if closing && out.title.is_none() {
+                        let t = decode_entities(title.trim());
+                        let t = t.split_whitespace().collect::<Vec<_>>().join(" ");
+                        if !t.is_empty() { out.title = Some(t); }
+                    }
+                    continue;
+                }
+                if name == "meta" && out.description.is_none() {
+                    let get = |k: &str| attrs.iter().find(|(a, _)| a == k).map(|(_, v)| v.clone());
+                    let key = get("name").or_else(|| get("property")).unwrap_or_default().to_ascii_lowercase();
+                    if key == "description" || key == "og:description" {
+                        out.description = get("content").map(|c| c.trim().to_string()).filter(|c| !c.is_empty());
+                    }
+                    continue;
+                }
+                if !closing && SKIP_TEXT.contains(&name.as_str()) {
+                    tok.skip_until_close(&name);
+                    continue;
+                }
+                if BLOCK.contains(&name.as_str()) { text.push('\n'); }
+            }
+            Token::Comment => {}
+        }
+    }
+    out.text = normalize_text(&text);
+    out
+}
+
+/// Remove active content (scripts, frames, comments, `on*` handlers, `javascript:` URLs).
+pub fn sanitize_html(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut tok = Tokenizer::new(html);
+    while let Some(t) = tok.next() {
+        match t {
+            Token::Text(s) => out.push_str(s),
+            Token::Comment => {}
+            Token::Tag { name, closing, raw, attrs } => {
+                if SKIP_HTML.contains(&name.as_str()) {
+                    if !closing { tok.skip_until_close(&name); }
+                    continue;
+                }
+                let unsafe_attr = |(k, v): &(String, String)| {
+                    k.starts_with("on") || ((k == "href" || k == "src" || k == "action") && v.trim_start().to_ascii_lowercase().starts_with("javascript:"))
+                };
+                if closing || !attrs.iter().any(unsafe_attr) {
+                    out.push_str(raw);
+                    continue;
+                }
+                out.push('<');
+                out.push_str(&name);
+                for (k, v) in attrs.iter().filter(|a| !unsafe_attr(a)) {
+                    out.push_str(&format!(" {}=\"{}\"", k, v.replace('&', "&amp;").replace('"', "&quot;")));
+                }
+                if raw.ends_with("/>") { out.push_str(" /"); }
+                out.push('>');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"<!doctype html>
+<html><head>
+  <title>Rust &amp; Friends</title>
+  <meta name="description" content="A page about &quot;Rust&quot;.">
+  <script>var x = "<p>not text</p>";</script>
+  <style>p { color: red }</style>
+</head>
+<body>
+  <nav><a href="/">Home</a> | <a href="/about">About</a></nav>
+  <article>
+    <h1>Ownership</h1>
+    <p>Each value has   an <b>owner</b>.</p>
+    <!-- hidden comment -->
+    <p>Borrowing&nbsp;is &#x2713; checked&#46;</p>
+  </article>
+  <footer>© 2025</footer>
+</body></html>"#;
+
+    #[test]
+    fn extracts_title_description_and_text() {
+        let r = extract(PAGE);
+        assert_eq!(r.title.as_deref(), Some("Rust & Friends"));
+        assert_eq!(r.description.as_deref(), Some("A page about \"Rust\"."));
+        assert_eq!(r.text, "Ownership\n\nEach value has an owner.\n\nBorrowing is ✓ checked.");
+        assert!(!r.text.contains("not text"));
+        assert!(!r.text.contains("Home"));
+    }
+
+    #[test]
+    fn sanitize_strips_active_content() {
+        let html = r#"<div onclick="evil()" class="x"><a href="javascript:alert(1)">a</a><script>bad()</script><!-- c --><img src="/i.png"/></div>"#;
+        let s = sanitize_html(html);
+        assert_eq!(s, r#"<div class="x"><a>a</a><img src="/i.png"/></div>"#);
+    }
+}
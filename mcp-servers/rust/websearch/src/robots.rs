@@ -0,0 +1,119 @@
+//! Minimal robots.txt support: user-agent groups, Allow/Disallow with `*` and `$`,
+//! longest match wins and Allow wins ties.
+
+/// (allow, pattern)
+type Rule = (bool, String);
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RobotsRules {
+    rules: Vec<Rule>,
+}
+
+impl RobotsRules {
+    pub fn allow_all() -> Self { Self::default() }
+
+    pub fn disallow_all() -> Self { Self { rules: vec![(false, "/".into())] } }
+
+    /// Parse the group matching `user_agent` (product token, case-insensitive), falling back to `*`.
+    pub fn parse(text: &str, user_agent: &str) -> Self {
+        let ua = user_agent.split('/').next().unwrap_or(user_agent).to_lowercase();
+        let mut groups: Vec<(Vec<String>, Vec<Rule>)> = vec![];
+        let mut in_agents = false;
+        for raw in text.lines() {
+            let line = raw.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else { continue; };
+            let key = key.trim().to_lowercase();
+            let value = value.trim().to_string();
+            match key.as_str() {
+                "user-agent" => {
+                    if !in_agents { groups.push((vec![], vec![])); in_agents = true; }
+                    if let Some(g) = groups.last_mut() { g.0.push(value.to_lowercase()); }
+                }
+                "allow" | "disallow" => {
+                    in_agents = false;
+                    if let Some(g) = groups.last_mut() {
+                        // An empty Disallow means "allow everything" and contributes no rule
+                        if !value.is_empty() { g.1.push((key == "allow", value)); }
+                    }
+                }
+                _ => { in_agents = false; }
+            }
+        }
+        let specific = groups.iter().find(|(agents, _)| agents.iter().any(|a| a != "*" && ua.contains(a.as_str())));
+        let chosen = specific.or_else(|| groups.iter().find(|(agents, _)| agents.iter().any(|a| a == "*")));
+        Self { rules: chosen.map(|(_, r)| r.clone()).unwrap_or_default() }
+    }
+
+    pub fn allows(&self, path: &str) -> bool {
+        let path = if path.is_empty() { "/" } else { path };
+        let mut best: Option<(usize, bool)> = None;
+        for (allow, pat) in self.rules.iter() {
+            if pattern_matches(pat, path) {
+                let len = pat.len();
+                best = match best {
+                    Some((l, a)) if l > len || (l == len && a) => Some((l, a)),
+                    _ => Some((len, *allow)),
+                };
+            }
+        }
+        best.map(|(_, a)| a).unwrap_or(true)
+    }
+}
+
+fn pattern_matches(pat: &str, path: &str) -> bool {
+    let (pat, anchored) = match pat.strip_suffix('$') { Some(p) => (p, true), None => (pat, false) };
+    let parts: Vec<&str> = pat.split('*').collect();
+    if !path.starts_with(parts[0]) { return false; }
+    let mut pos = parts[0].len();
+    let last = parts.len() - 1;
+    for (i, part) in parts.iter().enumerate().skip(1) {
+        if anchored && i == last {
+            return path.len() >= pos + part.len() && path.ends_with(part);
+        }
+        match path[pos..].find(part) {
+            Some(found) => pos += found + part.len(),
+            None => return false,
+        }
+    }
+    !anchored || pos == path.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "\
+# comment
+User-agent: *
+Disallow: /private/
+Allow: /private/public-note.html
+Disallow: /*.pdf$
+
+User-agent: foreman-websearch
+Disallow: /no-bots/
+";
+
+    #[test]
+    fn specific_group_wins_over_wildcard() {
+        let r = RobotsRules::parse(SAMPLE, "foreman-websearch/0.1");
+        assert!(!r.allows("/no-bots/page"));
+        assert!(r.allows("/private/secret.html"));
+    }
+
+    #[test]
+    fn wildcard_group_longest_match() {
+        let r = RobotsRules::parse(SAMPLE, "otherbot/1.0");
+        assert!(!r.allows("/private/secret.html"));
+        assert!(r.allows("/private/public-note.html"));
+        assert!(!r.allows("/papers/a.pdf"));
+        assert!(r.allows("/papers/a.pdf?x=1"));
+        assert!(r.allows("/"));
+    }
+
+    #[test]
+    fn empty_disallow_allows_everything() {
+        let r = RobotsRules::parse("User-agent: *\nDisallow:\n", "x");
+        assert!(r.allows("/anything"));
+        assert!(!RobotsRules::disallow_all().allows("/anything"));
+    }
+}
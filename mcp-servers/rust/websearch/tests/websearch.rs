@@ -0,0 +1,198 @@
+use axum::{extract::State, http::StatusCode, response::{IntoResponse, Redirect}, routing::get, Json, Router};
+use mcp_websearch::{Websearch, WebsearchConfig};
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Default)]
+struct Hits {
+    article: AtomicUsize,
+    search: AtomicUsize,
+}
+
+const ARTICLE: &str = r#"<html><head><title>Borrow Checker Notes</title>
+<meta name="description" content="Notes on lifetimes."></head>
+<body><script>tracking()</script><article><h1>Lifetimes</h1><p>The borrow checker validates references.</p></article></body></html>"#;
+
+/// Local stand-in for both a SearXNG instance and a small website.
+async fn spawn_site(flaky_search: bool) -> (String, Arc<Hits>) {
+    let hits = Arc::new(Hits::default());
+    let app = Router::new()
+        .route("/robots.txt", get(|| async { "User-agent: *\nDisallow: /private/\n" }))
+        .route("/article", get(|State(h): State<Arc<Hits>>| async move {
+            h.article.fetch_add(1, Ordering::SeqCst);
+            ([("content-type", "text/html; charset=utf-8")], ARTICLE)
+        }))
+        .route("/private/secret", get(|| async { ([("content-type", "text/html")], "<p>secret</p>") }))
+        .route("/moved", get(|| async { Redirect::permanent("/article") }))
+        .route("/sneaky", get(|| async { Redirect::temporary("/private/secret") }))
+        .route("/local-file", get(|| async { Redirect::temporary("file:///etc/passwd") }))
+        .route("/search", get(move |State(h): State<Arc<Hits>>| async move {
+            let n = h.search.fetch_add(1, Ordering::SeqCst);
+            if flaky_search && n == 0 {
+                return StatusCode::TOO_MANY_REQUESTS.into_response();
+            }
+            Json(json!({"results": [
+                {"url": "https://example.org/rust", "title": " Rust ", "content": "Systems language", "engine": "ddg"},
+                {"url": "javascript:alert(1)", "title": "bad", "content": ""}
+            ]})).into_response()
+        }))
+        .with_state(hits.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+    (format!("http://{}", addr), hits)
+}
+
+fn config(base: &str, cache: &std::path::Path, engines: &[&str]) -> WebsearchConfig {
+    WebsearchConfig {
+        engines: engines.iter().map(|s| s.to_string()).collect(),
+        searxng_url: base.to_string(),
+        cache_dir: cache.to_path_buf(),
+        min_interval: Duration::ZERO,
+        backoff: Duration::from_millis(1),
+        ..WebsearchConfig::default()
+    }
+}
+
+#[tokio::test]
+async fn searxng_results_are_sanitized() {
+    let (base, _) = spawn_site(false).await;
+    let tmp = tempfile::tempdir().unwrap();
+    let ws = Websearch::new(config(&base, tmp.path(), &["searxng"]));
+    let v = ws.search(&json!({"query": "rust"})).await.expect("search");
+    assert_eq!(v["engine"], "searxng");
+    let results = v["results"].as_array().unwrap();
+    assert_eq!(results.len(), 1, "javascript: url dropped");
+    assert_eq!(results[0]["title"], "Rust");
+    assert_eq!(results[0]["url"], "https://example.org/rust");
+}
+
+#[tokio::test]
+async fn searxng_retries_after_rate_limit() {
+    let (base, hits) = spawn_site(true).await;
+    let tmp = tempfile::tempdir().unwrap();
+    let ws = Websearch::new(config(&base, tmp.path(), &["searxng"]));
+    let v = ws.search(&json!({"query": "rust", "limit": 5})).await.expect("search after retry");
+    assert_eq!(v["results"].as_array().unwrap().len(), 1);
+    assert_eq!(hits.search.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn fetch_extracts_text_and_hits_cache() {
+    let (base, hits) = spawn_site(false).await;
+    let tmp = tempfile::tempdir().unwrap();
+    let ws = Websearch::new(config(&base, tmp.path(), &["local"]));
+    let url = format!("{}/article#section", base);
+
+    let first = ws.fetch(&json!({"url": url})).await.expect("fetch");
+    assert_eq!(first["cached"], false);
+    assert_eq!(first["title"], "Borrow Checker Notes");
+    assert_eq!(first["text"], "Lifetimes\n\nThe borrow checker validates references.");
+    let dir = std::path::PathBuf::from(first["path"].as_str().unwrap());
+    let html = std::fs::read_to_string(dir.join("page.html")).unwrap();
+    assert!(!html.contains("tracking()"), "scripts stripped from cached html");
+
+    let second = ws.fetch(&json!({"url": url})).await.expect("fetch cached");
+    assert_eq!(second["cached"], true);
+    assert_eq!(hits.article.load(Ordering::SeqCst), 1, "second fetch served from cache");
+
+    let meta = ws.metadata(&json!({"url": url})).await.expect("metadata");
+    assert_eq!(meta["description"], "Notes on lifetimes.");
+    assert_eq!(meta["cached"], true);
+}
+
+#[tokio::test]
+async fn fetch_respects_robots() {
+    let (base, _) = spawn_site(false).await;
+    let tmp = tempfile::tempdir().unwrap();
+    let ws = Websearch::new(config(&base, tmp.path(), &["local"]));
+    let err = ws.fetch(&json!({"url": format!("{}/private/secret", base)})).await.unwrap_err();
+    assert!(err.to_string().contains("robots"), "{}", err);
+    assert!(ws.fetch(&json!({"url": "file:///etc/passwd"})).await.is_err());
+}
+
+#[tokio::test]
+async fn falls_back_to_local_index_when_engine_is_down() {
+    let (base, _) = spawn_site(false).await;
+    let tmp = tempfile::tempdir().unwrap();
+    // Nothing listens on port 9 (discard); searxng fails and the local index answers
+    let mut cfg = config(&base, tmp.path(), &["searxng", "local"]);
+    cfg.searxng_url = "http://127.0.0.1:9".into();
+    cfg.retries = 0;
+    let ws = Websearch::new(cfg);
+    ws.fetch(&json!({"url": format!("{}/article", base)})).await.expect("fetch");
+
+    let v = ws.search(&json!({"query": "borrow checker"})).await.expect("search");
+    assert_eq!(v["engine"], "local");
+    let results = v["results"].as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert!(results[0]["snippet"].as_str().unwrap().contains("borrow checker"));
+
+    let none = ws.search(&json!({"query": "kubernetes"})).await.expect("empty search");
+    assert!(none["results"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn robots_outage_is_not_remembered() {
+    // robots.txt answers 503 once, then allows everything
+    let robots_hits = Arc::new(AtomicUsize::new(0));
+    let app = Router::new()
+        .route("/robots.txt", get(|State(n): State<Arc<AtomicUsize>>| async move {
+            if n.fetch_add(1, Ordering::SeqCst) == 0 { StatusCode::SERVICE_UNAVAILABLE.into_response() } else { "User-agent: *\nDisallow:\n".into_response() }
+        }))
+        .route("/article", get(|| async { ([("content-type", "text/html")], ARTICLE) }))
+        .with_state(robots_hits.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/article", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+    let tmp = tempfile::tempdir().unwrap();
+    let ws = Websearch::new(config("http://127.0.0.1:9", tmp.path(), &["local"]));
+
+    let err = ws.fetch(&json!({"url": url})).await.unwrap_err();
+    assert!(err.to_string().contains("blocked by robots.txt"), "{}", err);
+    ws.fetch(&json!({"url": url})).await.expect("allowed once robots.txt is back");
+    assert_eq!(robots_hits.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn describe_covers_every_tool() {
+    let d = Websearch::describe();
+    let names: Vec<&str> = d.tools.iter().map(|t| t.name.as_str()).collect();
+    assert_eq!(names, vec!["search", "fetch", "metadata"]);
+    assert!(d.tools.iter().all(|t| t.risk.network && !t.risk.writes));
+    let search = d.tools[0].input_schema.as_ref().unwrap();
+    assert!(foreman_mcp::schema::validate(search, &json!({"query": "rust", "limit": 5})).is_ok());
+    assert!(foreman_mcp::schema::validate(search, &json!({"limit": 500})).is_err());
+}
+
+#[tokio::test]
+async fn redirects_are_checked_on_every_hop() {
+    let (base, hits) = spawn_site(false).await;
+    let tmp = tempfile::tempdir().unwrap();
+    let ws = Websearch::new(config(&base, tmp.path(), &["local"]));
+
+    let moved = ws.fetch(&json!({"url": format!("{}/moved", base)})).await.expect("same-site redirect");
+    assert_eq!(moved["final_url"], format!("{}/article", base));
+    assert_eq!(hits.article.load(Ordering::SeqCst), 1);
+
+    let err = ws.fetch(&json!({"url": format!("{}/sneaky", base)})).await.unwrap_err();
+    assert!(err.to_string().contains("blocked by robots.txt") && err.to_string().contains("/private/secret"), "{}", err);
+    let err = ws.fetch(&json!({"url": format!("{}/local-file", base)})).await.unwrap_err();
+    assert!(err.to_string().contains("unsupported scheme"), "{}", err);
+
+    // Another origin is held to its own robots.txt
+    let closed = Router::new()
+        .route("/robots.txt", get(|| async { "User-agent: *\nDisallow: /\n" }))
+        .route("/page", get(|| async { ([("content-type", "text/html")], "<p>closed</p>") }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let target = format!("http://{}/page", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, closed).await.unwrap(); });
+    let hop = Router::new().route("/hop", get(move || async move { Redirect::temporary(&target) }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let hop_url = format!("http://{}/hop", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, hop).await.unwrap(); });
+    let err = ws.fetch(&json!({"url": hop_url})).await.unwrap_err();
+    assert!(err.to_string().contains("blocked by robots.txt"), "{}", err);
+}
@@ -38,7 +38,7 @@ export function ChatView({ sessionId }: { sessionId: string }) {
         if (pm) {
           // Prefer whichever content is longer (avoid overwriting streamed content with empty/partial server copies)
           const content = (sm.content?.length ?? 0) >= (pm.content?.length ?? 0) ? sm.content : pm.content;
-          map.set(sm.id, { ...pm, role: sm.role, content, at: sm.at ?? pm.at });
+          map.set(sm.id, { ...pm, ...sm, content, at: sm.at ?? pm.at });
         } else {
           map.set(sm.id, sm as any);
         }
@@ -75,7 +75,7 @@ export function ChatView({ sessionId }: { sessionId: string }) {
     setLocalMessages((prev) => [...prev, userSaved as any]);
 
     // Build the message history for the next turn from local state (single source of truth)
-    const toSend = localMessages.map((m) => ({ role: m.role, content: m.content }));
+    const toSend = localMessages.map((m) => ({ role: m.role, content: m.content, tool_calls: m.tool_calls, tool_call_id: m.tool_call_id }));
 
     setStreaming(true);
     try {
@@ -115,6 +115,17 @@ export function ChatView({ sessionId }: { sessionId: string }) {
         setTokenBuffer((buf) => buf + t);
       }
     } else if (evt.event === 'tool_call' || evt.event === 'tool_calls' || evt.event === 'tool_result') {
+      // Tool turns are messages of their own, shown as collapsible blocks
+      if (evt.event === 'tool_calls' && evt.data?.id) {
+        const { id, calls } = evt.data;
+        setLocalMessages((prev) => prev.map((m) => (m.id === id ? { ...m, tool_calls: calls } : m)));
+      } else if (evt.event === 'tool_result') {
+        const d = evt.data;
+        setLocalMessages((prev) => [...prev, {
+          id: d.message_id ?? `tool-${d.id}`, role: 'tool', content: d.result ?? '', at: new Date().toISOString(),
+          tool_call_id: d.id, tool_name: d.name, approval_id: d.approval_id, artifact_id: d.artifact_id,
+        }]);
+      }
       pushActivity({ ts: Date.now(), kind: evt.event === 'tool_result' ? 'tool_result' : 'tool_call', payload: evt.data });
       // Inline logs if enabled
       if (settings.inlineToolLogs) {
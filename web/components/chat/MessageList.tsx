@@ -1,6 +1,6 @@
 "use client";
 import { useEffect, useRef } from 'react';
-import type { ChatMessage } from '@/lib/types';
+import type { ChatMessage, ToolCall } from '@/lib/types';
 import { parseFences } from '@/lib/markdown';
 import { CodeBlock } from '@/components/primitives/CodeBlock';
 
@@ -10,8 +10,10 @@ export function MessageList({ messages, toolInlines, streamingId }: { messages:
   return (
     <div className="flex-1 overflow-y-auto p-4">
       <div className="mx-auto flex max-w-3xl flex-col gap-3">
-        {messages.map((m) => (
-          <Bubble key={m.id} role={m.role} content={m.content} streaming={streamingId === m.id} />
+        {messages.map((m) => m.role === 'tool' ? (
+          <ToolResult key={m.id} message={m} />
+        ) : (
+          <Bubble key={m.id} role={m.role} content={m.content} toolCalls={m.tool_calls} streaming={streamingId === m.id} />
         ))}
         {toolInlines?.map((t, i) => (<Bubble key={`tool-${i}`} role="tool" content={t} />))}
         <div ref={endRef} />
@@ -20,9 +22,11 @@ export function MessageList({ messages, toolInlines, streamingId }: { messages:
   );
 }
 
-function Bubble({ role, content, streaming }: { role: string; content: string; streaming?: boolean }) {
+function Bubble({ role, content, toolCalls, streaming }: { role: string; content: string; toolCalls?: ToolCall[]; streaming?: boolean }) {
   const isUser = role === 'user';
   const segs = parseFences(content);
+  // A turn that only called tools has nothing else to show
+  if (!content && toolCalls?.length && !streaming) return <>{toolCalls.map((c) => <ToolCallBlock key={c.id} call={c} />)}</>;
   return (
     <div className={`flex ${isUser ? 'justify-end' : 'justify-start'}`}>
       <div className={`max-w-[720px] rounded-lg p-3 shadow-card ${isUser ? 'bg-bg-1' : 'bg-bg-1'}`}>
@@ -31,7 +35,33 @@ function Bubble({ role, content, streaming }: { role: string; content: string; s
         ) : (
           <p key={i} className="whitespace-pre-wrap">{s.content}{streaming && i === segs.length - 1 ? '▍' : ''}</p>
         ))}
+        {toolCalls?.map((c) => <ToolCallBlock key={c.id} call={c} />)}
       </div>
     </div>
   );
 }
+
+function ToolCallBlock({ call }: { call: ToolCall }) {
+  return (
+    <details className="my-1 max-w-[720px] rounded-lg bg-bg-1 p-2 text-sm">
+      <summary className="cursor-pointer text-text-dim">⚙ {call.name}</summary>
+      <div className="mt-2"><CodeBlock value={JSON.stringify(call.arguments ?? {}, null, 2)} language="json" /></div>
+    </details>
+  );
+}
+
+function ToolResult({ message }: { message: ChatMessage }) {
+  let decision: string | undefined;
+  try { decision = JSON.parse(message.content)?.decision; } catch { /* cut or not JSON */ }
+  const approval = message.approval_id ? (decision === 'deny' ? 'denied' : decision === 'timeout' ? 'approval timed out' : 'approved') : null;
+  return (
+    <details className="max-w-[720px] rounded-lg bg-bg-1 p-2 text-sm">
+      <summary className="cursor-pointer text-text-dim">
+        ↳ {message.tool_name ?? 'tool'} result · {message.content.length} chars
+        {approval ? ` · ${approval}` : ''}
+        {message.artifact_id != null ? ` · full result in artifact ${message.artifact_id}` : ''}
+      </summary>
+      <div className="mt-2"><CodeBlock value={message.content} language="json" /></div>
+    </details>
+  );
+}
@@ -60,11 +60,11 @@ export const api = {
     json(`/api/agents`, { method: 'POST', body: JSON.stringify(args) }),
   listSessions: () => json<Array<{ id: string; updated_at: string; title?: string }>>(`/api/chat/sessions`),
   createSession: () => json<{ id: string; messages: Array<{ id: string; role: string; content: string; at?: string }> }>(`/api/chat/sessions`, { method: 'POST' }),
-  getSession: (id: string) => json<{ id: string; messages: Array<{ id: string; role: string; content: string; at?: string }> }>(`/api/chat/sessions/${id}`),
+  getSession: (id: string) => json<import('./types').ChatSession>(`/api/chat/sessions/${id}`),
   deleteSession: (id: string) => fetch(`${API_BASE}/api/chat/sessions/${id}`, { method: 'DELETE' }).then(r => { if (!r.ok) throw new Error(`${r.status}`); }),
   // Append returns JSON { id, role, content, at }
   appendMessage: (id: string, msg: { role: string; content: string }) =>
     json<{ id: string; role: string; content: string; at?: string }>(`/api/chat/sessions/${id}/append`, { method: 'POST', body: JSON.stringify(msg) }),
-  streamChat: (body: { session_id?: string; messages: Array<{ role: string; content: string }>; model?: string; max_steps?: number }, signal?: AbortSignal) =>
+  streamChat: (body: { session_id?: string; messages: Array<{ role: string; content: string; tool_calls?: import('./types').ToolCall[]; tool_call_id?: string }>; model?: string; max_steps?: number }, signal?: AbortSignal) =>
     fetch(`${API_BASE}/api/chat/stream`, { method: 'POST', body: JSON.stringify(body), signal, headers: { 'content-type': 'application/json' } }),
 };
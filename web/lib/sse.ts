@@ -1,9 +1,10 @@
 export type ChatEvent =
+  | { event: 'context'; data: { kept: number; summarized: number; summary_cached: boolean; history_tokens: number; summary_tokens: number; pack_cards: number; pack_tokens: number } }
   | { event: 'token'; data: { text?: string } }
   | { event: 'assistant_started'; data: { id: string } }
-  | { event: 'tool_calls'; data: any }
+  | { event: 'tool_calls'; data: { id?: string; calls: Array<{ id: string; name: string; arguments?: any }> } }
   | { event: 'tool_call'; data: any }
-  | { event: 'tool_result'; data: any }
+  | { event: 'tool_result'; data: { id: string; name: string; result: string; message_id?: string; approval_id?: string; artifact_id?: number } }
   | { event: 'error'; data: { message?: string } }
   | { event: 'done'; data: {} }
   | { event: 'message'; data: any };
@@ -1,5 +1,18 @@
-export type ChatMessage = { id: string; role: 'system' | 'user' | 'assistant' | 'tool'; content: string; at?: string };
-export type ChatSession = { id: string; messages: ChatMessage[] };
+export type ToolCall = { id: string; name: string; arguments?: any };
+export type ChatMessage = {
+  id: string;
+  role: 'system' | 'user' | 'assistant' | 'tool';
+  content: string;
+  at?: string;
+  // Assistant turns that called tools
+  tool_calls?: ToolCall[];
+  // Tool results: the call answered, approval asked, artifact with the full result if cut
+  tool_call_id?: string;
+  tool_name?: string;
+  approval_id?: string;
+  artifact_id?: number;
+};
+export type ChatSession = { id: string; messages: ChatMessage[]; summary?: string; summary_upto?: string };
 
 export type ProposedAction = {
   command: string;